@@ -0,0 +1,37 @@
+//! Opens a live preview of a customized folder icon.
+//!
+//! ```text
+//! cargo run --example preview --features preview-window -- <icon.png>... [--profile profile.json]
+//! ```
+//!
+//! Each PNG becomes one size in the base icon set (at @1x). Edit the
+//! profile JSON while the window is open to see changes immediately.
+
+use folco_renderer::preview::PreviewWindow;
+use folco_renderer::{FolderIconBase, FolderIconCustomizer, IconImage, IconSet, SurfaceColor};
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let mut args = std::env::args().skip(1);
+    let mut icons = IconSet::new();
+    let mut window = PreviewWindow::new();
+
+    while let Some(arg) = args.next() {
+        if arg == "--profile" {
+            let path = args.next().ok_or("--profile requires a path")?;
+            window = window.watch_profile(path);
+        } else {
+            let data = image::open(&arg)?.to_rgba8();
+            icons.add_image(IconImage::new_full_content(data, 1.0));
+        }
+    }
+
+    if icons.is_empty() {
+        return Err("usage: preview <icon.png>... [--profile profile.json]".into());
+    }
+
+    // Golden-yellow Windows folder surface
+    let surface = SurfaceColor::new(255, 217, 112);
+    let mut customizer = FolderIconCustomizer::new(FolderIconBase::new(icons, surface));
+    window.run(&mut customizer)?;
+    Ok(())
+}
@@ -0,0 +1,32 @@
+//! Parses arbitrary JSON as a profile, applies it, and renders a small icon.
+
+#![no_main]
+
+use folco_renderer::{
+    Configurable, CustomizationProfile, FolderIconBase, FolderIconCustomizer, IconImage, IconSet,
+    SurfaceColor,
+};
+use image::{Rgba, RgbaImage};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(json) = std::str::from_utf8(data) else {
+        return;
+    };
+    let Ok(profile) = CustomizationProfile::from_json(json) else {
+        return;
+    };
+
+    let mut icons = IconSet::new();
+    icons.add_image(IconImage::new_full_content(
+        RgbaImage::from_pixel(16, 16, Rgba([255, 217, 112, 255])),
+        1.0,
+    ));
+    let mut customizer =
+        FolderIconCustomizer::new(FolderIconBase::new(icons, SurfaceColor::new(255, 217, 112)));
+    customizer.apply_profile(&profile);
+
+    // Errors are fine; panics and runaway allocations are not
+    let _ = customizer.render(16);
+    let _ = customizer.export_profile().to_json();
+});
@@ -0,0 +1,16 @@
+//! Recolors and renders arbitrary text as a monochrome SVG.
+
+#![no_main]
+
+use folco_renderer::fuzzing::render_svg_with_color;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let Some((&[r, g, b], rest)) = data.split_first_chunk::<3>() else {
+        return;
+    };
+    let Ok(svg) = std::str::from_utf8(rest) else {
+        return;
+    };
+    let _ = render_svg_with_color(svg, 16, Some((r, g, b, 255)));
+});
@@ -0,0 +1,13 @@
+//! Rasterizes arbitrary SVG markup.
+
+#![no_main]
+
+use folco_renderer::fuzzing::render_svg;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(svg) = std::str::from_utf8(data) else {
+        return;
+    };
+    let _ = render_svg(svg, 32);
+});
@@ -0,0 +1,830 @@
+//! Image analysis: legibility scoring and palette extraction.
+//!
+//! Small icon sizes (16px, 32px) are where customizations most often stop
+//! reading well: a decal that looks fine at 256px can blend into the folder
+//! at 16px. This module scores each rendered size and produces actionable
+//! [`LegibilityWarning`]s that an editor can surface next to the preview.
+//!
+//! [`extract_palette`] summarizes an arbitrary image (e.g. a wallpaper) as a
+//! handful of representative colors, and [`sample_dominant_color`] finds
+//! the body color of a folder artwork.
+//!
+//! # Example
+//!
+//! ```
+//! use folco_renderer::{FolderIconCustomizer, FolderIconBase, IconSet, SurfaceColor};
+//!
+//! let surface = SurfaceColor::new(255, 217, 112);
+//! let mut customizer = FolderIconCustomizer::new(FolderIconBase::new(IconSet::new(), surface));
+//!
+//! let report = customizer.analyze_legibility().unwrap();
+//! for warning in &report.warnings {
+//!     println!("{warning}");
+//! }
+//! ```
+
+use std::collections::HashMap;
+
+use image::RgbaImage;
+use palette::{LinSrgb, Srgb};
+use serde::{Deserialize, Serialize};
+
+use crate::icon::{IconImage, RectPx};
+use crate::layer::finite_or;
+use crate::render_id::RenderId;
+
+/// Logical sizes at or below this value are considered "small" and checked
+/// against the legibility thresholds.
+pub const SMALL_SIZE_MAX: u32 = 32;
+
+/// Minimum acceptable silhouette edge contrast (0.0–1.0).
+const MIN_EDGE_CONTRAST: f32 = 0.5;
+
+/// Minimum acceptable contrast ratio between a decal and the surface behind it.
+const MIN_DECAL_CONTRAST: f32 = 1.5;
+
+/// Maximum acceptable fraction of "busy" pixels in the icon content.
+const MAX_DETAIL_DENSITY: f32 = 0.35;
+
+/// Luminance step between neighboring pixels that counts as visible detail.
+const DETAIL_LUMINANCE_STEP: f32 = 0.1;
+
+/// Alpha value at or above which a pixel is considered part of the icon.
+const OPAQUE_ALPHA: u8 = 128;
+
+// ============================================================================
+// Report Types
+// ============================================================================
+
+/// Legibility scores for a single rendered icon size.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "tsify", derive(tsify_next::Tsify))]
+#[cfg_attr(feature = "tsify", tsify(into_wasm_abi, from_wasm_abi))]
+#[serde(rename_all = "camelCase")]
+pub struct SizeLegibility {
+    /// Logical size of the rendered image (pixel width / scale, rounded).
+    pub logical_size: u32,
+    /// Display scale factor of the rendered image.
+    pub scale: f32,
+    /// How sharply the silhouette separates from the background (0.0–1.0).
+    ///
+    /// Computed as the mean alpha step across silhouette boundary pixels.
+    /// Soft, halo-heavy edges score low.
+    pub edge_contrast: f32,
+    /// WCAG contrast ratio between the decal and the surface behind it.
+    ///
+    /// `None` if the decal layer is inactive or covers no pixels.
+    pub decal_contrast: Option<f32>,
+    /// Fraction of opaque pixels that differ visibly from a neighbor (0.0–1.0).
+    pub detail_density: f32,
+    /// Fingerprint of the render these scores were measured on.
+    #[cfg_attr(feature = "tsify", tsify(type = "string"))]
+    pub render_id: RenderId,
+}
+
+/// An actionable legibility problem found at a specific size.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "tsify", derive(tsify_next::Tsify))]
+#[cfg_attr(feature = "tsify", tsify(into_wasm_abi, from_wasm_abi))]
+#[serde(tag = "kind", rename_all = "kebab-case", rename_all_fields = "camelCase")]
+pub enum LegibilityWarning {
+    /// The silhouette edge is too soft to read against a desktop background.
+    LowEdgeContrast {
+        /// Logical size the warning applies to.
+        logical_size: u32,
+        /// The measured edge contrast.
+        edge_contrast: f32,
+    },
+    /// The decal does not stand out enough from the folder surface.
+    LowDecalContrast {
+        /// Logical size the warning applies to.
+        logical_size: u32,
+        /// The measured contrast ratio.
+        contrast_ratio: f32,
+    },
+    /// The icon has too much fine detail to read at this size.
+    HighDetailDensity {
+        /// Logical size the warning applies to.
+        logical_size: u32,
+        /// The measured detail density.
+        detail_density: f32,
+    },
+}
+
+impl std::fmt::Display for LegibilityWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::LowEdgeContrast { logical_size, edge_contrast } => write!(
+                f,
+                "edge contrast too low at {logical_size}px ({edge_contrast:.2})"
+            ),
+            Self::LowDecalContrast { logical_size, contrast_ratio } => write!(
+                f,
+                "decal contrast too low at {logical_size}px ({contrast_ratio:.2}:1)"
+            ),
+            Self::HighDetailDensity { logical_size, detail_density } => write!(
+                f,
+                "too much detail at {logical_size}px ({:.0}% busy pixels)",
+                detail_density * 100.0
+            ),
+        }
+    }
+}
+
+/// Legibility scores for every rendered size, plus warnings for small sizes.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "tsify", derive(tsify_next::Tsify))]
+#[cfg_attr(feature = "tsify", tsify(into_wasm_abi, from_wasm_abi))]
+#[serde(rename_all = "camelCase")]
+pub struct LegibilityReport {
+    /// Per-size scores, in base icon set order.
+    pub sizes: Vec<SizeLegibility>,
+    /// Warnings for sizes at or below [`SMALL_SIZE_MAX`].
+    pub warnings: Vec<LegibilityWarning>,
+}
+
+impl LegibilityReport {
+    /// Adds a size's scores and records any threshold violations.
+    pub(crate) fn push(&mut self, scores: SizeLegibility) {
+        if scores.logical_size <= SMALL_SIZE_MAX {
+            let logical_size = scores.logical_size;
+            if scores.edge_contrast < MIN_EDGE_CONTRAST {
+                self.warnings.push(LegibilityWarning::LowEdgeContrast {
+                    logical_size,
+                    edge_contrast: scores.edge_contrast,
+                });
+            }
+            if let Some(ratio) = scores.decal_contrast
+                && ratio < MIN_DECAL_CONTRAST
+            {
+                self.warnings.push(LegibilityWarning::LowDecalContrast {
+                    logical_size,
+                    contrast_ratio: ratio,
+                });
+            }
+            if scores.detail_density > MAX_DETAIL_DENSITY {
+                self.warnings.push(LegibilityWarning::HighDetailDensity {
+                    logical_size,
+                    detail_density: scores.detail_density,
+                });
+            }
+        }
+        self.sizes.push(scores);
+    }
+
+    /// Returns true if no warnings were produced.
+    pub fn is_legible(&self) -> bool {
+        self.warnings.is_empty()
+    }
+}
+
+// ============================================================================
+// Metrics
+// ============================================================================
+
+/// Computes the WCAG relative luminance of an sRGB color (0.0–1.0).
+pub fn relative_luminance(r: u8, g: u8, b: u8) -> f32 {
+    let linear = |c: u8| {
+        let c = c as f32 / 255.0;
+        if c <= 0.04045 {
+            c / 12.92
+        } else {
+            ((c + 0.055) / 1.055).powf(2.4)
+        }
+    };
+    0.2126 * linear(r) + 0.7152 * linear(g) + 0.0722 * linear(b)
+}
+
+/// Computes the WCAG contrast ratio between two luminances (1.0–21.0).
+pub fn contrast_ratio(l1: f32, l2: f32) -> f32 {
+    let (hi, lo) = if l1 >= l2 { (l1, l2) } else { (l2, l1) };
+    (hi + 0.05) / (lo + 0.05)
+}
+
+/// Measures how sharply the silhouette separates from the background.
+///
+/// For every pair of 4-neighbors where one side is part of the icon and the
+/// other is not, the alpha step between them is averaged. Images with no
+/// silhouette boundary (fully opaque or fully transparent) score 1.0.
+pub(crate) fn edge_contrast(image: &RgbaImage) -> f32 {
+    let mut total = 0.0;
+    let mut count = 0u32;
+
+    for_each_neighbor_pair(image, |a, b| {
+        let (ia, ib) = (a[3] >= OPAQUE_ALPHA, b[3] >= OPAQUE_ALPHA);
+        if ia != ib {
+            total += (a[3] as f32 - b[3] as f32).abs() / 255.0;
+            count += 1;
+        }
+    });
+
+    if count == 0 { 1.0 } else { total / count as f32 }
+}
+
+/// Measures the fraction of opaque pixels with a visible luminance step to
+/// their right or bottom neighbor.
+pub(crate) fn detail_density(image: &RgbaImage) -> f32 {
+    let opaque = image.pixels().filter(|p| p[3] >= OPAQUE_ALPHA).count();
+    if opaque == 0 {
+        return 0.0;
+    }
+
+    let mut busy = 0usize;
+    for_each_neighbor_pair(image, |a, b| {
+        if a[3] >= OPAQUE_ALPHA && b[3] >= OPAQUE_ALPHA {
+            let la = relative_luminance(a[0], a[1], a[2]);
+            let lb = relative_luminance(b[0], b[1], b[2]);
+            if (la - lb).abs() > DETAIL_LUMINANCE_STEP {
+                busy += 1;
+            }
+        }
+    });
+
+    // Each pixel has up to two forward neighbors
+    (busy as f32 / (opaque * 2) as f32).min(1.0)
+}
+
+/// Computes the contrast ratio between a decal tile and the image behind it.
+///
+/// Only pixels where the tile is at least half opaque are considered.
+/// Returns `None` if the tile covers no such pixels.
+pub(crate) fn decal_contrast(background: &RgbaImage, tile: &RgbaImage) -> Option<f32> {
+    let mut decal_lum = 0.0;
+    let mut back_lum = 0.0;
+    let mut count = 0u32;
+
+    for (x, y, t) in tile.enumerate_pixels() {
+        if t[3] < OPAQUE_ALPHA || x >= background.width() || y >= background.height() {
+            continue;
+        }
+        let b = background.get_pixel(x, y);
+        decal_lum += relative_luminance(t[0], t[1], t[2]);
+        back_lum += relative_luminance(b[0], b[1], b[2]);
+        count += 1;
+    }
+
+    if count == 0 {
+        return None;
+    }
+    Some(contrast_ratio(decal_lum / count as f32, back_lum / count as f32))
+}
+
+/// Extracts up to `n` representative colors from an image using median cut.
+///
+/// Boxes are split along their widest channel at the midpoint of its range.
+///
+/// Pixels below half opacity are ignored. Colors are returned in order of
+/// how many pixels they represent, most common first. Returns fewer than
+/// `n` colors if the image has fewer distinct colors.
+pub fn extract_palette(image: &RgbaImage, n: usize) -> Vec<(u8, u8, u8)> {
+    let pixels: Vec<[u8; 3]> = image
+        .pixels()
+        .filter(|p| p[3] >= OPAQUE_ALPHA)
+        .map(|p| [p[0], p[1], p[2]])
+        .collect();
+    if pixels.is_empty() || n == 0 {
+        return Vec::new();
+    }
+
+    let mut boxes = vec![pixels];
+    while boxes.len() < n {
+        // Split the box with the widest channel range
+        let Some((index, channel)) = boxes
+            .iter()
+            .enumerate()
+            .filter(|(_, b)| b.len() > 1)
+            .map(|(i, b)| {
+                let (channel, range) = widest_channel(b);
+                (i, channel, range)
+            })
+            .filter(|&(_, _, range)| range > 0)
+            .max_by_key(|&(_, _, range)| range)
+            .map(|(i, channel, _)| (i, channel))
+        else {
+            break;
+        };
+
+        // Split at the midpoint of the range so a single color never
+        // straddles two boxes
+        let mut bucket = boxes.swap_remove(index);
+        bucket.sort_unstable_by_key(|p| p[channel]);
+        let (min, max) = (bucket[0][channel], bucket[bucket.len() - 1][channel]);
+        let mid = min + (max - min) / 2;
+        let split = bucket.partition_point(|p| p[channel] <= mid);
+        let upper = bucket.split_off(split);
+        boxes.push(bucket);
+        boxes.push(upper);
+    }
+
+    boxes.sort_by_key(|b| std::cmp::Reverse(b.len()));
+    boxes
+        .iter()
+        .map(|b| {
+            let sum = b.iter().fold([0u64; 3], |acc, p| {
+                [acc[0] + p[0] as u64, acc[1] + p[1] as u64, acc[2] + p[2] as u64]
+            });
+            let len = b.len() as u64;
+            ((sum[0] / len) as u8, (sum[1] / len) as u8, (sum[2] / len) as u8)
+        })
+        .collect()
+}
+
+/// Options for [`sample_dominant_color`].
+///
+/// The default samples every visible pixel in the content bounds uniformly.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct DominantColorSampling {
+    /// Region to sample, in image pixels, within the content bounds (e.g.
+    /// the area behind a glyph). `None` samples all of the content bounds.
+    pub region: Option<RectPx>,
+    /// Region to ignore, in image pixels (e.g. the folder tab, whose
+    /// shading skews the average away from the folder body).
+    pub exclude: Option<RectPx>,
+    /// Extra weight given to pixels near the center of the content bounds.
+    ///
+    /// A pixel at the center counts `1.0 + center_weight` times as much as
+    /// one at the edge, falling off linearly. `0.0` samples uniformly.
+    pub center_weight: f32,
+    /// Pixels with alpha below this are ignored, e.g. to leave out the
+    /// anti-aliased rim and drop shadow of soft-edged artwork. Fully
+    /// transparent pixels are always ignored.
+    pub min_alpha: u8,
+    /// How much each pixel's alpha counts toward its weight.
+    pub alpha_weighting: AlphaWeighting,
+    /// Averages in linear light rather than on sRGB values, so a mix of
+    /// light and dark pixels doesn't come out too dark.
+    pub linear_light: bool,
+}
+
+/// How [`sample_dominant_color`] weighs pixels by their alpha.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AlphaWeighting {
+    /// Each pixel counts in proportion to its alpha.
+    #[default]
+    Alpha,
+    /// Every sampled pixel counts the same, however transparent.
+    Uniform,
+}
+
+impl DominantColorSampling {
+    /// Creates options that sample uniformly with nothing excluded.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Samples only pixels inside `region`.
+    pub fn with_region(mut self, region: RectPx) -> Self {
+        self.region = Some(region);
+        self
+    }
+
+    /// Ignores pixels inside `region`.
+    pub fn with_excluded_region(mut self, region: RectPx) -> Self {
+        self.exclude = Some(region);
+        self
+    }
+
+    /// Weights center pixels more heavily. Negative values are treated as 0.
+    pub fn with_center_weight(mut self, center_weight: f32) -> Self {
+        self.center_weight = center_weight.max(0.0);
+        self
+    }
+
+    /// Ignores pixels with alpha below `min_alpha`.
+    pub fn with_min_alpha(mut self, min_alpha: u8) -> Self {
+        self.min_alpha = min_alpha;
+        self
+    }
+
+    /// Sets how pixels are weighed by their alpha.
+    pub fn with_alpha_weighting(mut self, alpha_weighting: AlphaWeighting) -> Self {
+        self.alpha_weighting = alpha_weighting;
+        self
+    }
+
+    /// Averages in linear light when `linear_light` is true.
+    pub fn with_linear_light(mut self, linear_light: bool) -> Self {
+        self.linear_light = linear_light;
+        self
+    }
+}
+
+/// Samples the dominant color of an icon's content.
+///
+/// Returns the alpha- and position-weighted mean color of the pixels in
+/// the icon's content bounds, honoring the sampled and excluded regions, center
+/// weighting, alpha threshold and averaging space in `options`. Hosts can use this to derive an accurate
+/// [`SurfaceColor`](crate::SurfaceColor) from OS folder artwork whose tab
+/// is shaded differently from the body. Returns `None` if no visible
+/// pixels remain.
+pub fn sample_dominant_color(icon: &IconImage, options: &DominantColorSampling) -> Option<(u8, u8, u8)> {
+    let bounds = icon.content_bounds;
+    // A uniform sample of bounds holding every visible pixel is the
+    // memoized mean
+    let stats = icon.stats();
+    let holds = |v: &RectPx| v.x >= bounds.x && v.y >= bounds.y && v.right() <= bounds.right() && v.bottom() <= bounds.bottom();
+    let plain = options.min_alpha <= 1 && options.alpha_weighting == AlphaWeighting::Alpha && !options.linear_light;
+    if plain && options.region.is_none() && options.exclude.is_none() && options.center_weight <= 0.0 && stats.visible_bounds.as_ref().is_none_or(holds) {
+        return stats.mean_color;
+    }
+
+    let (cx, cy) = (
+        bounds.x as f32 + bounds.width as f32 / 2.0,
+        bounds.y as f32 + bounds.height as f32 / 2.0,
+    );
+    let (half_w, half_h) = ((bounds.width as f32 / 2.0).max(1.0), (bounds.height as f32 / 2.0).max(1.0));
+    let center_weight = options.center_weight.max(0.0);
+
+    let mut sum = [0.0f64; 3];
+    let mut total = 0.0f64;
+    for (x, y, pixel) in icon.data().enumerate_pixels() {
+        let inside = |r: &RectPx| x >= r.x && x < r.right() && y >= r.y && y < r.bottom();
+        if pixel[3] == 0
+            || pixel[3] < options.min_alpha
+            || !inside(&bounds)
+            || !options.region.as_ref().is_none_or(inside)
+            || options.exclude.as_ref().is_some_and(inside)
+        {
+            continue;
+        }
+
+        // Normalized elliptical distance from the center: 0 at center, 1 at edges
+        let (dx, dy) = ((x as f32 + 0.5 - cx) / half_w, (y as f32 + 0.5 - cy) / half_h);
+        let falloff = 1.0 - (dx * dx + dy * dy).sqrt().min(1.0);
+        let coverage = match options.alpha_weighting {
+            AlphaWeighting::Alpha => pixel[3] as f32 / 255.0,
+            AlphaWeighting::Uniform => 1.0,
+        };
+        let weight = (coverage * (1.0 + center_weight * falloff)) as f64;
+
+        let color = if options.linear_light {
+            let linear = Srgb::new(pixel[0], pixel[1], pixel[2]).into_format::<f32>().into_linear();
+            [linear.red as f64, linear.green as f64, linear.blue as f64]
+        } else {
+            [pixel[0] as f64, pixel[1] as f64, pixel[2] as f64]
+        };
+        for (acc, channel) in sum.iter_mut().zip(color) {
+            *acc += channel * weight;
+        }
+        total += weight;
+    }
+
+    (total > 0.0).then(|| {
+        let [r, g, b] = sum.map(|c| c / total);
+        if options.linear_light {
+            let color: Srgb<u8> = Srgb::from_linear(LinSrgb::new(r as f32, g as f32, b as f32));
+            (color.red, color.green, color.blue)
+        } else {
+            (r.round() as u8, g.round() as u8, b.round() as u8)
+        }
+    })
+}
+
+/// How a [`DominantColorSampler`] reduces an icon's pixels to one color.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "tsify", derive(tsify_next::Tsify))]
+#[cfg_attr(feature = "tsify", tsify(into_wasm_abi, from_wasm_abi))]
+#[serde(tag = "kind", rename_all = "kebab-case", rename_all_fields = "camelCase")]
+pub enum SamplingStrategy {
+    /// The alpha-weighted mean, as [`sample_dominant_color`] computes it.
+    Average,
+    /// The median of each channel. Unlike the mean, it lands on a color
+    /// the icon actually has when one color covers most of it.
+    Median,
+    /// The mean of the most common color, counting colors whose channels
+    /// fall in the same `tolerance`-wide buckets as one (treated as 1 if 0).
+    MostFrequent {
+        /// Width of each channel's buckets.
+        tolerance: u8,
+    },
+    /// The alpha-weighted mean, favoring the center of the content bounds
+    /// by `feather` (see [`DominantColorSampling::center_weight`]).
+    CenterWeighted {
+        /// Extra weight of the center pixel over the edges.
+        feather: f32,
+    },
+}
+
+/// Samples the dominant color of each base image a
+/// [`LayerPipeline`](crate::LayerPipeline) renders, emitting it as a
+/// [`DominantColor`](crate::DominantColor) for layers that derive their
+/// colors from it.
+///
+/// Set with [`LayerPipeline::set_dominant_color_sampler`](crate::LayerPipeline::set_dominant_color_sampler).
+/// A color target replaces the sampled color with its target.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "tsify", derive(tsify_next::Tsify))]
+#[cfg_attr(feature = "tsify", tsify(into_wasm_abi, from_wasm_abi))]
+#[serde(rename_all = "camelCase")]
+pub struct DominantColorSampler {
+    /// How the sampled pixels are reduced to one color.
+    pub strategy: SamplingStrategy,
+    /// Pixels with alpha below this are ignored.
+    #[serde(default)]
+    pub min_alpha: u8,
+}
+
+impl DominantColorSampler {
+    /// Creates a sampler using `strategy` on every visible pixel.
+    pub fn new(strategy: SamplingStrategy) -> Self {
+        Self { strategy, min_alpha: 0 }
+    }
+
+    /// Ignores pixels with alpha below `min_alpha`.
+    pub fn with_min_alpha(mut self, min_alpha: u8) -> Self {
+        self.min_alpha = min_alpha;
+        self
+    }
+
+    /// Returns the dominant color of the pixels in `icon`'s content
+    /// bounds, or `None` if none are sampled.
+    ///
+    /// ```
+    /// use folco_renderer::analysis::{DominantColorSampler, SamplingStrategy};
+    /// use folco_renderer::IconImage;
+    /// use image::{Rgba, RgbaImage};
+    ///
+    /// // Mostly red, with a blue stripe
+    /// let data = RgbaImage::from_fn(10, 10, |x, _| if x < 3 { Rgba([0, 0, 255, 255]) } else { Rgba([255, 0, 0, 255]) });
+    /// let icon = IconImage::new_full_content(data, 1.0);
+    /// assert_eq!(DominantColorSampler::new(SamplingStrategy::Median).sample(&icon), Some((255, 0, 0)));
+    /// ```
+    pub fn sample(&self, icon: &IconImage) -> Option<(u8, u8, u8)> {
+        let options = DominantColorSampling::new().with_min_alpha(self.min_alpha);
+        let tolerance = match self.strategy {
+            SamplingStrategy::Average => return sample_dominant_color(icon, &options),
+            SamplingStrategy::CenterWeighted { feather } => {
+                return sample_dominant_color(icon, &options.with_center_weight(finite_or(feather, 0.0)));
+            }
+            SamplingStrategy::Median => None,
+            SamplingStrategy::MostFrequent { tolerance } => Some(tolerance.max(1)),
+        };
+
+        let bounds = icon.content_bounds;
+        let pixels: Vec<[u8; 3]> = icon
+            .data()
+            .enumerate_pixels()
+            .filter(|&(x, y, p)| {
+                p[3] > 0 && p[3] >= self.min_alpha && x >= bounds.x && x < bounds.right() && y >= bounds.y && y < bounds.bottom()
+            })
+            .map(|(_, _, p)| [p[0], p[1], p[2]])
+            .collect();
+        if pixels.is_empty() {
+            return None;
+        }
+
+        let Some(tolerance) = tolerance else {
+            let median = |c: usize| {
+                let mut channel: Vec<u8> = pixels.iter().map(|p| p[c]).collect();
+                let middle = channel.len() / 2;
+                *channel.select_nth_unstable(middle).1
+            };
+            return Some((median(0), median(1), median(2)));
+        };
+
+        let mut buckets: HashMap<[u8; 3], (u32, [u64; 3])> = HashMap::new();
+        for pixel in &pixels {
+            let (count, sum) = buckets.entry(pixel.map(|c| c / tolerance)).or_default();
+            *count += 1;
+            for (acc, &channel) in sum.iter_mut().zip(pixel) {
+                *acc += channel as u64;
+            }
+        }
+        // Ties go to the lowest bucket, so the result doesn't depend on
+        // hash order
+        let (_, (count, sum)) = buckets.into_iter().max_by_key(|&(bucket, (count, _))| (count, std::cmp::Reverse(bucket)))?;
+        let mean = |c: u64| (c as f64 / count as f64).round() as u8;
+        Some((mean(sum[0]), mean(sum[1]), mean(sum[2])))
+    }
+}
+
+/// Returns the channel index with the largest value range, and that range.
+fn widest_channel(pixels: &[[u8; 3]]) -> (usize, u8) {
+    (0..3)
+        .map(|c| {
+            let (min, max) = pixels
+                .iter()
+                .fold((u8::MAX, u8::MIN), |(lo, hi), p| (lo.min(p[c]), hi.max(p[c])));
+            (c, max - min)
+        })
+        .max_by_key(|&(_, range)| range)
+        .unwrap()
+}
+
+/// Calls `f` for each horizontally and vertically adjacent pixel pair.
+fn for_each_neighbor_pair(image: &RgbaImage, mut f: impl FnMut(&image::Rgba<u8>, &image::Rgba<u8>)) {
+    let (w, h) = image.dimensions();
+    for y in 0..h {
+        for x in 0..w {
+            let p = image.get_pixel(x, y);
+            if x + 1 < w {
+                f(p, image.get_pixel(x + 1, y));
+            }
+            if y + 1 < h {
+                f(p, image.get_pixel(x, y + 1));
+            }
+        }
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgba;
+
+    #[test]
+    fn contrast_ratio_extremes() {
+        let white = relative_luminance(255, 255, 255);
+        let black = relative_luminance(0, 0, 0);
+        assert!((contrast_ratio(white, black) - 21.0).abs() < 0.01);
+        assert_eq!(contrast_ratio(white, white), 1.0);
+    }
+
+    #[test]
+    fn edge_contrast_crisp_vs_soft() {
+        // Opaque square in the middle of a transparent canvas
+        let mut crisp = RgbaImage::new(8, 8);
+        for y in 2..6 {
+            for x in 2..6 {
+                crisp.put_pixel(x, y, Rgba([255, 0, 0, 255]));
+            }
+        }
+        assert_eq!(edge_contrast(&crisp), 1.0);
+
+        // Same square surrounded by a faint halo
+        let mut soft = crisp.clone();
+        for y in 1..7 {
+            for x in 1..7 {
+                if soft.get_pixel(x, y)[3] == 0 {
+                    soft.put_pixel(x, y, Rgba([255, 0, 0, 100]));
+                }
+            }
+        }
+        assert!(edge_contrast(&soft) < edge_contrast(&crisp));
+    }
+
+    #[test]
+    fn detail_density_flat_vs_checkerboard() {
+        let flat = RgbaImage::from_pixel(8, 8, Rgba([128, 128, 128, 255]));
+        assert_eq!(detail_density(&flat), 0.0);
+
+        let checker = RgbaImage::from_fn(8, 8, |x, y| {
+            if (x + y) % 2 == 0 {
+                Rgba([0, 0, 0, 255])
+            } else {
+                Rgba([255, 255, 255, 255])
+            }
+        });
+        assert!(detail_density(&checker) > MAX_DETAIL_DENSITY);
+    }
+
+    #[test]
+    fn decal_contrast_ignores_transparent_tile() {
+        let background = RgbaImage::from_pixel(4, 4, Rgba([255, 217, 112, 255]));
+        let empty = RgbaImage::new(4, 4);
+        assert!(decal_contrast(&background, &empty).is_none());
+
+        let same = RgbaImage::from_pixel(4, 4, Rgba([255, 217, 112, 255]));
+        assert_eq!(decal_contrast(&background, &same), Some(1.0));
+    }
+
+    #[test]
+    fn dominant_color_excludes_tab_and_weights_center() {
+        // 10x10 folder: a dark tab across the top three rows, body below,
+        // with a light border column on the right edge of the body
+        let mut data = RgbaImage::from_pixel(10, 10, image::Rgba([200, 160, 60, 255]));
+        for x in 0..10 {
+            for y in 0..3 {
+                data.put_pixel(x, y, image::Rgba([120, 90, 30, 255]));
+            }
+        }
+        for y in 3..10 {
+            data.put_pixel(9, y, image::Rgba([250, 250, 250, 255]));
+        }
+        let icon = IconImage::new_full_content(data, 1.0);
+
+        let uniform = sample_dominant_color(&icon, &DominantColorSampling::new()).unwrap();
+        let no_tab = DominantColorSampling::new().with_excluded_region(RectPx::new(0, 0, 10, 3));
+        let body = sample_dominant_color(&icon, &no_tab).unwrap();
+        assert!(body.0 > uniform.0 + 20, "{body:?} vs {uniform:?}");
+
+        // Center weighting pulls the result away from the edge column
+        let centered = sample_dominant_color(&icon, &no_tab.with_center_weight(8.0)).unwrap();
+        assert!(centered.2 < body.2, "{centered:?} vs {body:?}");
+
+        let empty = IconImage::new_full_content(RgbaImage::new(4, 4), 1.0);
+        assert_eq!(sample_dominant_color(&empty, &DominantColorSampling::new()), None);
+    }
+
+    #[test]
+    fn dominant_color_strategies_on_soft_edged_artwork() {
+        // A body surrounded by a darker anti-aliased rim and a faint drop
+        // shadow below, as OS folder artwork is drawn
+        let body = (230, 180, 60);
+        let data = RgbaImage::from_fn(24, 24, |x, y| {
+            if y >= 22 {
+                return Rgba([0, 0, 0, 30]);
+            }
+            match [x, y, 23 - x, 21 - y].into_iter().min().unwrap() {
+                0 | 1 => Rgba([120, 90, 30, 100]),
+                _ => Rgba([body.0, body.1, body.2, 255]),
+            }
+        });
+        let icon = IconImage::new_full_content(data, 1.0);
+        let distance = |options: DominantColorSampling| {
+            let (r, g, b) = sample_dominant_color(&icon, &options).unwrap();
+            r.abs_diff(body.0) as u32 + g.abs_diff(body.1) as u32 + b.abs_diff(body.2) as u32
+        };
+
+        let weighted = distance(DominantColorSampling::new());
+        let uniform = distance(DominantColorSampling::new().with_alpha_weighting(AlphaWeighting::Uniform));
+        assert!(0 < weighted && weighted < uniform, "{weighted} vs {uniform}");
+        assert_eq!(distance(DominantColorSampling::new().with_min_alpha(128)), 0);
+
+        // Black and white average to mid-gray in sRGB, lighter in linear light
+        let checker = RgbaImage::from_fn(4, 4, |x, y| if (x + y) % 2 == 0 { Rgba([0, 0, 0, 255]) } else { Rgba([255; 4]) });
+        let checker = IconImage::new_full_content(checker, 1.0);
+        assert_eq!(sample_dominant_color(&checker, &DominantColorSampling::new()), Some((128, 128, 128)));
+        assert_eq!(sample_dominant_color(&checker, &DominantColorSampling::new().with_linear_light(true)), Some((188, 188, 188)));
+    }
+
+    #[test]
+    fn sampler_strategies_pick_the_main_color() {
+        use crate::icon::SurfaceColor;
+        use crate::layer::{DecalConfig, LayerPipeline};
+
+        // Mostly orange in two close shades, with a blue stripe
+        let data = RgbaImage::from_fn(10, 10, |x, y| match x {
+            0..4 => Rgba([30, 60, 220, 255]),
+            _ if y < 5 => Rgba([240, 150, 40, 255]),
+            _ => Rgba([236, 146, 36, 255]),
+        });
+        let icon = IconImage::new_full_content(data, 1.0);
+        let sample = |strategy| DominantColorSampler::new(strategy).sample(&icon).unwrap();
+
+        assert_eq!(sample(SamplingStrategy::Average), (155, 113, 111));
+        assert_eq!(sample(SamplingStrategy::Median), (236, 146, 40));
+        // The stripe outnumbers each shade, but not both together
+        assert_eq!(sample(SamplingStrategy::MostFrequent { tolerance: 1 }), (30, 60, 220));
+        assert_eq!(sample(SamplingStrategy::MostFrequent { tolerance: 32 }), (238, 148, 38));
+        let centered = sample(SamplingStrategy::CenterWeighted { feather: 8.0 });
+        assert!(centered.0 > 155 && centered.2 < 111, "{centered:?}");
+
+        // The pipeline emits the sample for the decal to darken
+        let square = r##"<svg xmlns="http://www.w3.org/2000/svg" width="10" height="10"><rect width="10" height="10"/></svg>"##;
+        let mut pipeline = LayerPipeline::default();
+        pipeline.decal.set_config(Some(DecalConfig::new(square, 0.5)));
+        let surface = SurfaceColor::new(255, 217, 112);
+        let unsampled = pipeline.render(&icon, &surface).unwrap();
+        assert!(pipeline.set_dominant_color_sampler(Some(DominantColorSampler::new(SamplingStrategy::Median))));
+        assert!(!pipeline.set_dominant_color_sampler(pipeline.dominant_color_sampler()));
+        let sampled = pipeline.render(&icon, &surface).unwrap();
+        assert_ne!(sampled.data().get_pixel(5, 5), unsampled.data().get_pixel(5, 5));
+    }
+
+    #[test]
+    fn extract_palette_orders_by_population() {
+        // Three quarters blue, one quarter red
+        let image = RgbaImage::from_fn(8, 8, |x, _| {
+            if x < 2 {
+                Rgba([255, 0, 0, 255])
+            } else {
+                Rgba([0, 0, 255, 255])
+            }
+        });
+
+        let palette = extract_palette(&image, 4);
+        assert_eq!(palette.len(), 2, "Only two distinct colors exist");
+        assert_eq!(palette[0], (0, 0, 255));
+        assert_eq!(palette[1], (255, 0, 0));
+
+        assert!(extract_palette(&RgbaImage::new(4, 4), 3).is_empty());
+    }
+
+    #[test]
+    fn report_warns_only_for_small_sizes() {
+        let low = |logical_size| SizeLegibility {
+            logical_size,
+            scale: 1.0,
+            edge_contrast: 1.0,
+            decal_contrast: Some(1.1),
+            detail_density: 0.0,
+            render_id: "0".parse().unwrap(),
+        };
+
+        let mut report = LegibilityReport::default();
+        report.push(low(16));
+        report.push(low(256));
+
+        assert_eq!(report.sizes.len(), 2);
+        assert_eq!(report.warnings.len(), 1);
+        assert_eq!(
+            report.warnings[0].to_string(),
+            "decal contrast too low at 16px (1.10:1)"
+        );
+    }
+}
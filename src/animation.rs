@@ -0,0 +1,250 @@
+//! Frame scheduling for animated customizations.
+//!
+//! An [`AnimatedProfile`] describes how a [`CustomizationProfile`] changes
+//! over time. A [`FrameScheduler`] steps through it at a fixed frame rate,
+//! applying each frame's profile to a [`FolderIconCustomizer`] and
+//! reporting, per frame, which layer caches survive and what re-renders.
+//! Frames that change nothing keep every cache, and a frame that changes
+//! one layer only invalidates that layer and the ones depending on it.
+//!
+//! # Example
+//!
+//! ```
+//! use folco_renderer::animation::{FrameScheduler, Keyframes};
+//! use folco_renderer::{CustomizationProfile, FolderColorTargetConfig, FolderIconBase, FolderIconCustomizer, IconSet, SurfaceColor};
+//!
+//! let blue = CustomizationProfile::new().with_folder_color_target(FolderColorTargetConfig::new(33, 150, 243));
+//! let red = CustomizationProfile::new().with_folder_color_target(FolderColorTargetConfig::new(229, 57, 53));
+//! let blink = Keyframes::new(1.0, blue).with_keyframe(0.5, red);
+//!
+//! let mut customizer = FolderIconCustomizer::new(FolderIconBase::new(IconSet::new(), SurfaceColor::new(255, 217, 112)));
+//! let mut scheduler = FrameScheduler::new(blink, 4.0);
+//! let changed: Vec<bool> = std::iter::from_fn(|| scheduler.advance(&mut customizer))
+//!     .map(|plan| !plan.report.is_noop())
+//!     .collect();
+//! assert_eq!(changed, [true, false, true, false]);
+//! ```
+
+use crate::customizer::{ApplyReport, Configurable, FolderIconCustomizer};
+use crate::layer::LayerKind;
+use crate::profile::CustomizationProfile;
+
+/// Slack for comparing frame times with keyframe times, so a keyframe at
+/// 0.7s lands on frame 21 at 30 fps despite `21.0 / 30.0` rounding above.
+const TIME_EPSILON: f64 = 1e-9;
+
+/// Every layer of the fixed pipeline, in pipeline order.
+const ALL_LAYERS: [LayerKind; 6] = [
+    LayerKind::FolderColorTarget,
+    LayerKind::GradientTint,
+    LayerKind::Shadow,
+    LayerKind::Decal,
+    LayerKind::Overlay,
+    LayerKind::Text,
+];
+
+// ============================================================================
+// AnimatedProfile
+// ============================================================================
+
+/// A customization profile that changes over time.
+pub trait AnimatedProfile {
+    /// Returns the profile shown `time` seconds into the animation.
+    fn profile_at(&self, time: f64) -> CustomizationProfile;
+
+    /// Returns the length of the animation in seconds.
+    fn duration(&self) -> f64;
+}
+
+/// An [`AnimatedProfile`] that holds each keyframe's profile until the
+/// next keyframe.
+#[derive(Debug, Clone)]
+pub struct Keyframes {
+    /// Keyframes sorted by time, the first at 0.0.
+    keyframes: Vec<(f64, CustomizationProfile)>,
+    duration: f64,
+}
+
+impl Keyframes {
+    /// Creates an animation `duration` seconds long showing `initial`
+    /// from the start.
+    pub fn new(duration: f64, initial: CustomizationProfile) -> Self {
+        Self {
+            keyframes: vec![(0.0, initial)],
+            duration: duration.max(0.0),
+        }
+    }
+
+    /// Shows `profile` from `time` seconds on. A keyframe at an existing
+    /// time replaces it; negative and non-finite times count as 0.0.
+    pub fn with_keyframe(mut self, time: f64, profile: CustomizationProfile) -> Self {
+        let time = if time.is_finite() { time.max(0.0) } else { 0.0 };
+        let index = self.keyframes.partition_point(|(at, _)| *at < time);
+        match self.keyframes.get_mut(index) {
+            Some((at, existing)) if *at == time => *existing = profile,
+            _ => self.keyframes.insert(index, (time, profile)),
+        }
+        self
+    }
+}
+
+impl AnimatedProfile for Keyframes {
+    fn profile_at(&self, time: f64) -> CustomizationProfile {
+        let index = self.keyframes.partition_point(|(at, _)| *at <= time + TIME_EPSILON);
+        self.keyframes[index.saturating_sub(1)].1.clone()
+    }
+
+    fn duration(&self) -> f64 {
+        self.duration
+    }
+}
+
+// ============================================================================
+// FrameScheduler
+// ============================================================================
+
+/// What one frame changes, as returned by [`FrameScheduler::plan`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct FramePlan {
+    /// Frame index, from 0.
+    pub frame: u64,
+    /// Time of the frame in seconds.
+    pub time: f64,
+    /// What applying the frame's profile changes, compared with the
+    /// customizer's state before the frame.
+    pub report: ApplyReport,
+    /// Layers whose caches survive the frame, in pipeline order.
+    pub valid_caches: Vec<LayerKind>,
+}
+
+/// Steps a [`FolderIconCustomizer`] through an [`AnimatedProfile`] at a
+/// fixed frame rate.
+///
+/// Frame times are computed from the frame index rather than accumulated,
+/// so they don't drift over long animations.
+#[derive(Debug, Clone)]
+pub struct FrameScheduler<A> {
+    animation: A,
+    fps: f64,
+    next_frame: u64,
+}
+
+impl<A: AnimatedProfile> FrameScheduler<A> {
+    /// Creates a scheduler running `animation` at `fps` frames per second.
+    /// Rates that aren't positive and finite fall back to 30 fps.
+    pub fn new(animation: A, fps: f64) -> Self {
+        let fps = if fps.is_finite() && fps > 0.0 { fps } else { 30.0 };
+        Self {
+            animation,
+            fps,
+            next_frame: 0,
+        }
+    }
+
+    /// Returns the animation.
+    pub fn animation(&self) -> &A {
+        &self.animation
+    }
+
+    /// Returns the number of frames, at least 1.
+    pub fn frame_count(&self) -> u64 {
+        let frames = self.animation.duration() * self.fps;
+        // 0.1s at 30 fps is 3.0000000000000004 frames, not 4
+        let whole = if (frames - frames.round()).abs() < TIME_EPSILON { frames.round() } else { frames.ceil() };
+        (whole as u64).max(1)
+    }
+
+    /// Returns the time of `frame` in seconds.
+    pub fn time_of(&self, frame: u64) -> f64 {
+        frame as f64 / self.fps
+    }
+
+    /// Reports what showing `frame` would change in `customizer`, without
+    /// changing anything.
+    pub fn plan(&self, customizer: &FolderIconCustomizer, frame: u64) -> FramePlan {
+        let time = self.time_of(frame);
+        let report = customizer.preview_apply(&self.animation.profile_at(time));
+        let valid_caches = ALL_LAYERS
+            .into_iter()
+            .filter(|kind| !report.invalidated_caches.contains(kind))
+            .collect();
+        FramePlan {
+            frame,
+            time,
+            report,
+            valid_caches,
+        }
+    }
+
+    /// Applies the next frame's profile to `customizer`, returning its
+    /// plan, or `None` once every frame has been shown.
+    ///
+    /// Frames whose profile matches the previous one leave the customizer
+    /// untouched, so its renders are served from the composite cache.
+    pub fn advance(&mut self, customizer: &mut FolderIconCustomizer) -> Option<FramePlan> {
+        if self.next_frame >= self.frame_count() {
+            return None;
+        }
+        let plan = self.plan(customizer, self.next_frame);
+        if !plan.report.is_noop() {
+            customizer.apply_profile(&self.animation.profile_at(plan.time));
+        }
+        self.next_frame += 1;
+        Some(plan)
+    }
+
+    /// Starts over from the first frame.
+    pub fn rewind(&mut self) {
+        self.next_frame = 0;
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::icon::{FolderIconBase, IconImage, IconSet, SurfaceColor};
+    use crate::layer::{DecalConfig, FolderColorTargetConfig};
+    use image::RgbaImage;
+
+    const SQUARE: &str = r##"<svg xmlns="http://www.w3.org/2000/svg" width="10" height="10"><rect width="10" height="10" fill="#000"/></svg>"##;
+
+    #[test]
+    fn frame_times_are_exact() {
+        let keyframes = Keyframes::new(1.0, CustomizationProfile::new())
+            .with_keyframe(0.7, CustomizationProfile::new().with_decal(DecalConfig::new(SQUARE, 0.5)));
+        let scheduler = FrameScheduler::new(keyframes, 30.0);
+        assert_eq!(scheduler.frame_count(), 30);
+        assert!(scheduler.animation().profile_at(scheduler.time_of(20)).decal.is_none());
+        assert!(scheduler.animation().profile_at(scheduler.time_of(21)).decal.is_some());
+        assert_eq!(FrameScheduler::new(Keyframes::new(0.1, CustomizationProfile::new()), 30.0).frame_count(), 3);
+    }
+
+    #[test]
+    fn only_the_changed_layer_and_its_dependents_rerender() {
+        let mut icons = IconSet::new();
+        icons.add_image(IconImage::new_full_content(RgbaImage::new(16, 16), 1.0));
+        let mut customizer = FolderIconCustomizer::new(FolderIconBase::new(icons, SurfaceColor::new(255, 217, 112)));
+
+        let blue = CustomizationProfile::new()
+            .with_folder_color_target(FolderColorTargetConfig::new(33, 150, 243))
+            .with_decal(DecalConfig::new(SQUARE, 0.5));
+        let bigger = blue.clone().with_decal(DecalConfig::new(SQUARE, 0.6));
+        let mut scheduler = FrameScheduler::new(Keyframes::new(0.75, blue).with_keyframe(0.5, bigger), 4.0);
+
+        let plans: Vec<FramePlan> = std::iter::from_fn(|| scheduler.advance(&mut customizer)).collect();
+        assert_eq!(plans.len(), 3);
+        assert!(plans[1].report.is_noop());
+        assert_eq!(plans[1].valid_caches, ALL_LAYERS);
+        assert!(plans[1].report.rerendered_sizes.is_empty());
+
+        // Resizing the decal keeps the recolor cached
+        assert_eq!(plans[2].report.changed_layers, [LayerKind::Decal]);
+        assert!(plans[2].valid_caches.contains(&LayerKind::FolderColorTarget));
+        assert!(!plans[2].valid_caches.contains(&LayerKind::Decal));
+        assert_eq!(customizer.pipeline.decal.config().unwrap().scale, 0.6);
+    }
+}
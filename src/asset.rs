@@ -0,0 +1,304 @@
+//! Process-wide registry of shared assets.
+//!
+//! Apps that customize many folders with the same glyph would otherwise
+//! hold one copy of its markup per pipeline. Registering it once in the
+//! [`AssetRegistry`] and referencing it with [`SvgSource::AssetId`] keeps a
+//! single copy that every customizer and thread resolves against.
+//!
+//! # Example
+//!
+//! ```
+//! use folco_renderer::{AssetRegistry, DecalConfig, SvgSource};
+//!
+//! let handle = AssetRegistry::global()
+//!     .register_svg("glyph.star", r#"<svg xmlns="http://www.w3.org/2000/svg" width="8" height="8"/>"#)
+//!     .unwrap();
+//! let decal = DecalConfig {
+//!     source: handle.source(),
+//!     ..DecalConfig::new("", 0.5)
+//! };
+//! assert_eq!(decal.source, SvgSource::asset("glyph.star"));
+//!
+//! // The asset stays registered while any handle is alive
+//! drop(handle);
+//! assert!(!AssetRegistry::global().contains("glyph.star"));
+//! ```
+
+use std::collections::HashMap;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::sync::{Arc, Mutex, MutexGuard, OnceLock, PoisonError};
+
+use thiserror::Error;
+
+use crate::layer::{ResolvedSvg, SvgSource};
+
+/// Errors that can occur while registering an asset.
+#[derive(Debug, Error)]
+pub enum AssetError {
+    /// The id is already registered with different content.
+    ///
+    /// Replacing live content would silently change what other holders
+    /// render, so the existing registration must be released first.
+    #[error("asset {id:?} is already registered with different content")]
+    Conflict {
+        /// The conflicting asset id.
+        id: String,
+    },
+}
+
+/// Content of a registered asset.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum AssetData {
+    /// SVG markup.
+    Svg(ResolvedSvg),
+    /// Encoded PNG bytes.
+    Png(Arc<[u8]>),
+}
+
+#[derive(Debug)]
+struct Entry {
+    data: AssetData,
+    /// Hash of `data`, so caches can tell re-registered content apart.
+    hash: u64,
+    refs: usize,
+}
+
+/// Thread-safe, reference-counted store of SVG and PNG assets by id.
+///
+/// There is one registry per process, reached through
+/// [`global`](Self::global). Each registration returns an [`AssetHandle`];
+/// registering identical content under the same id again shares the entry,
+/// and the asset is removed when its last handle is dropped.
+///
+/// Once removed, an id can be registered again with other content. Layer
+/// caches and [`RenderId`](crate::RenderId)s follow the content, not just
+/// the id, so renders pick up the new asset.
+#[derive(Debug)]
+pub struct AssetRegistry {
+    entries: Mutex<HashMap<String, Entry>>,
+}
+
+impl AssetRegistry {
+    /// Returns the process-wide registry.
+    pub fn global() -> &'static AssetRegistry {
+        static REGISTRY: OnceLock<AssetRegistry> = OnceLock::new();
+        REGISTRY.get_or_init(|| AssetRegistry { entries: Mutex::default() })
+    }
+
+    /// Registers SVG markup under `id`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`AssetError::Conflict`] if `id` is registered with
+    /// different content.
+    pub fn register_svg(&'static self, id: impl Into<String>, svg: &str) -> Result<AssetHandle, AssetError> {
+        self.register(id.into(), AssetData::Svg(ResolvedSvg::from(svg)))
+    }
+
+    /// Registers encoded PNG bytes under `id`.
+    ///
+    /// [`SvgSource::AssetId`] resolves a PNG asset as it does a
+    /// [`SvgSource::Raster`] source, wrapping the image in an SVG of its
+    /// pixel size.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`AssetError::Conflict`] if `id` is registered with
+    /// different content.
+    pub fn register_png(&'static self, id: impl Into<String>, png: &[u8]) -> Result<AssetHandle, AssetError> {
+        self.register(id.into(), AssetData::Png(Arc::from(png)))
+    }
+
+    fn register(&'static self, id: String, data: AssetData) -> Result<AssetHandle, AssetError> {
+        let mut entries = self.lock();
+        match entries.get_mut(&id) {
+            Some(entry) if entry.data != data => return Err(AssetError::Conflict { id }),
+            Some(entry) => entry.refs += 1,
+            None => {
+                let mut hasher = DefaultHasher::new();
+                data.hash(&mut hasher);
+                entries.insert(id.clone(), Entry { data, hash: hasher.finish(), refs: 1 });
+            }
+        }
+        Ok(AssetHandle { registry: self, id })
+    }
+
+    /// Returns the content registered under `id`.
+    pub fn get(&self, id: &str) -> Option<AssetData> {
+        self.lock().get(id).map(|entry| entry.data.clone())
+    }
+
+    /// Returns a hash of the content registered under `id`.
+    pub(crate) fn content_hash(&self, id: &str) -> Option<u64> {
+        self.lock().get(id).map(|entry| entry.hash)
+    }
+
+    /// Returns the SVG markup registered under `id`, if it is an SVG asset.
+    pub fn svg(&self, id: &str) -> Option<ResolvedSvg> {
+        match self.get(id)? {
+            AssetData::Svg(svg) => Some(svg),
+            AssetData::Png(_) => None,
+        }
+    }
+
+    /// Returns the PNG bytes registered under `id`, if it is a PNG asset.
+    pub fn png(&self, id: &str) -> Option<Arc<[u8]>> {
+        match self.get(id)? {
+            AssetData::Png(png) => Some(png),
+            AssetData::Svg(_) => None,
+        }
+    }
+
+    /// Returns true if `id` is registered.
+    pub fn contains(&self, id: &str) -> bool {
+        self.lock().contains_key(id)
+    }
+
+    /// Returns the number of registered assets.
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    /// Returns true if no assets are registered.
+    pub fn is_empty(&self) -> bool {
+        self.lock().is_empty()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HashMap<String, Entry>> {
+        // Entries are updated atomically under the lock, so a panic
+        // elsewhere cannot leave the map inconsistent
+        self.entries.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// Keeps a registered asset alive.
+///
+/// Cloning takes another reference; the asset is removed from the
+/// registry when the last handle is dropped.
+#[derive(Debug)]
+pub struct AssetHandle {
+    registry: &'static AssetRegistry,
+    id: String,
+}
+
+impl AssetHandle {
+    /// Returns the asset id.
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// Returns an [`SvgSource`] referencing this asset.
+    pub fn source(&self) -> SvgSource {
+        SvgSource::asset(self.id.clone())
+    }
+}
+
+impl Clone for AssetHandle {
+    fn clone(&self) -> Self {
+        if let Some(entry) = self.registry.lock().get_mut(&self.id) {
+            entry.refs += 1;
+        }
+        Self {
+            registry: self.registry,
+            id: self.id.clone(),
+        }
+    }
+}
+
+impl Drop for AssetHandle {
+    fn drop(&mut self) {
+        let mut entries = self.registry.lock();
+        if let Some(entry) = entries.get_mut(&self.id) {
+            entry.refs -= 1;
+            if entry.refs == 0 {
+                entries.remove(&self.id);
+            }
+        }
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SVG: &str = r#"<svg xmlns="http://www.w3.org/2000/svg" width="4" height="4"/>"#;
+
+    #[test]
+    fn handles_refcount_registrations() {
+        let registry = AssetRegistry::global();
+        let first = registry.register_svg("test.refcount", SVG).unwrap();
+        let second = registry.register_svg("test.refcount", SVG).unwrap();
+        let third = second.clone();
+        assert!(matches!(
+            registry.register_svg("test.refcount", "<svg/>"),
+            Err(AssetError::Conflict { .. })
+        ));
+
+        drop(first);
+        drop(second);
+        assert_eq!(registry.svg("test.refcount").as_deref(), Some(SVG));
+        assert!(registry.png("test.refcount").is_none());
+        drop(third);
+        assert!(!registry.contains("test.refcount"));
+    }
+
+    #[test]
+    fn asset_sources_resolve_against_registry() {
+        let source = SvgSource::asset("test.resolve");
+        assert!(matches!(source.resolve_shared(), Err(crate::RenderError::UnknownAsset { .. })));
+
+        let handle = AssetRegistry::global().register_svg("test.resolve", SVG).unwrap();
+        assert_eq!(&*handle.source().resolve_shared().unwrap(), SVG);
+        assert_eq!(source.resolve().unwrap(), SVG);
+        drop(handle);
+
+        // Not memoized: unregistering makes the source unresolvable again
+        assert!(source.resolve_shared().is_err());
+
+        // PNG assets resolve like raster sources
+        let mut png = Vec::new();
+        image::RgbaImage::from_pixel(4, 2, image::Rgba([200, 30, 40, 255]))
+            .write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+            .unwrap();
+        let handle = AssetRegistry::global().register_png("test.png", &png).unwrap();
+        let rendered = crate::layer::svg::render_source(&handle.source(), 16).unwrap();
+        assert_eq!(rendered.dimensions(), (16, 8));
+        assert_eq!(rendered.get_pixel(8, 4).0, [200, 30, 40, 255]);
+        drop(handle);
+        let bad = AssetRegistry::global().register_png("test.bad-png", b"\x89PNG").unwrap();
+        assert!(matches!(bad.source().resolve_shared(), Err(crate::RenderError::InvalidRaster { .. })));
+        drop(bad);
+    }
+
+    #[test]
+    fn re_registered_content_reaches_caches_and_ids() {
+        use crate::{DecalConfig, FolderIconBase, FolderIconCustomizer, IconImage, IconSet, SurfaceColor};
+        use image::{Rgba, RgbaImage};
+
+        let square = |fill: &str| format!(r#"<svg xmlns="http://www.w3.org/2000/svg" width="4" height="4"><rect width="4" height="4" fill="{fill}"/></svg>"#);
+        let surface = SurfaceColor::new(255, 217, 112);
+        let mut icons = IconSet::new();
+        icons.add_image(IconImage::new_full_content(RgbaImage::from_pixel(16, 16, Rgba([255, 217, 112, 255])), 1.0));
+        let mut customizer = FolderIconCustomizer::new(FolderIconBase::new(icons, surface));
+        let decal = DecalConfig {
+            source: SvgSource::asset("test.reregister"),
+            ..DecalConfig::new("", 1.0)
+        };
+        customizer.pipeline.decal.set_config(Some(decal));
+
+        let handle = AssetRegistry::global().register_svg("test.reregister", &square("#000")).unwrap();
+        let first = customizer.render_identified(16).unwrap();
+        drop(handle);
+        // Fully transparent content, so the decal disappears
+        let _handle = AssetRegistry::global().register_svg("test.reregister", &square("none")).unwrap();
+        let second = customizer.render_identified(16).unwrap();
+
+        assert_ne!(first.id, second.id);
+        assert_ne!(first.image, second.image);
+        assert_eq!(second.image.data().get_pixel(8, 8).0, [255, 217, 112, 255]);
+    }
+}
@@ -0,0 +1,155 @@
+//! Rendering many base icons with one customization.
+//!
+//! A [`FolderIconCustomizer`](crate::FolderIconCustomizer) caches per image
+//! size, for one base icon. Applying the same profile to hundreds of
+//! folders through it would either mix up their caches or, with a fresh
+//! customizer each, parse and rasterize the same decal and badge SVGs for
+//! every one. An [`IconBatchRenderer`] renders each base with its own
+//! caches but shares the stamps: each SVG is parsed once per size and
+//! color, and rasterized once per size, color and subpixel offset, however
+//! many icons it lands on.
+//!
+//! ```
+//! use folco_renderer::{CustomizationProfile, FolderIconBase, IconBatchRenderer, IconImage, IconSet, SurfaceColor};
+//! use image::{Rgba, RgbaImage};
+//!
+//! let folder = |r, g, b| {
+//!     let icon = IconImage::new_full_content(RgbaImage::from_pixel(32, 32, Rgba([r, g, b, 255])), 1.0);
+//!     FolderIconBase::new(IconSet::from_images(vec![icon]), SurfaceColor::new(r, g, b))
+//! };
+//! let folders = vec![folder(255, 217, 112), folder(120, 144, 156), folder(66, 165, 245)];
+//!
+//! let renderer = IconBatchRenderer::new(&CustomizationProfile::preset("Work").unwrap());
+//! let rendered = renderer.render_all(&folders);
+//! assert!(rendered.iter().all(|icons| icons.as_ref().is_ok_and(|icons| icons.len() == 1)));
+//! ```
+
+use std::sync::Arc;
+
+use crate::customizer::apply_profile_to;
+use crate::error::RenderError;
+use crate::icon::{FolderIconBase, IconSet};
+use crate::layer::stamps::{SharedStamps, StampCache};
+use crate::layer::LayerPipeline;
+use crate::profile::CustomizationProfile;
+
+/// Renders many base icons with the same layers, sharing decal, overlay
+/// and badge stamps between them.
+///
+/// Cloning the renderer shares its stamp cache.
+#[derive(Clone)]
+pub struct IconBatchRenderer {
+    /// The configured layers, never rendered, so their caches stay empty.
+    pipeline: LayerPipeline,
+    stamps: Arc<StampCache>,
+}
+
+impl IconBatchRenderer {
+    /// Creates a renderer applying `profile`, as
+    /// [`Configurable::apply_profile`](crate::Configurable::apply_profile)
+    /// would, so a profile failing
+    /// [`validate`](CustomizationProfile::validate) leaves it unconfigured.
+    pub fn new(profile: &CustomizationProfile) -> Self {
+        let mut pipeline = LayerPipeline::default();
+        let _ = apply_profile_to(&mut pipeline, profile, &mut Vec::new());
+        Self::from_pipeline(pipeline)
+    }
+
+    /// Creates a renderer with the layers and settings of `pipeline`, such
+    /// as its accent color and time budget. Its caches are not used.
+    pub fn from_pipeline(mut pipeline: LayerPipeline) -> Self {
+        pipeline.invalidate_all();
+        let stamps = Arc::new(StampCache::default());
+        pipeline.shared_stamps = Some(SharedStamps(Arc::clone(&stamps)));
+        Self { pipeline, stamps }
+    }
+
+    /// Returns the configured layers.
+    pub fn pipeline(&self) -> &LayerPipeline {
+        &self.pipeline
+    }
+
+    /// Renders every size of `base`, in canonical order (see
+    /// [`IconSet::sort_canonical`]).
+    ///
+    /// # Errors
+    ///
+    /// Returns [`RenderError::EmptyIconSet`] if the base has no images, or
+    /// a render error if any layer fails.
+    pub fn render(&self, base: &FolderIconBase) -> Result<IconSet, RenderError> {
+        if base.icons.is_empty() {
+            return Err(RenderError::EmptyIconSet);
+        }
+        // Layer caches hold one image per size, so each base gets its own
+        let mut pipeline = self.pipeline.clone();
+        let rendered = base
+            .icons
+            .iter()
+            .map(|image| pipeline.render(image, &base.surface_color))
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut set = IconSet::from_images(rendered);
+        set.sort_canonical();
+        Ok(set)
+    }
+
+    /// Renders every base, in order. A base that fails doesn't stop the
+    /// others.
+    ///
+    /// With the `rayon` feature, bases render in parallel.
+    pub fn render_all(&self, bases: &[FolderIconBase]) -> Vec<Result<IconSet, RenderError>> {
+        #[cfg(feature = "rayon")]
+        {
+            use rayon::prelude::*;
+            bases.par_iter().map(|base| self.render(base)).collect()
+        }
+        #[cfg(not(feature = "rayon"))]
+        bases.iter().map(|base| self.render(base)).collect()
+    }
+
+    /// Returns the number of distinct stamp rasterizations made so far.
+    pub fn cached_stamps(&self) -> usize {
+        self.stamps.len()
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::customizer::{Configurable, FolderIconCustomizer};
+    use crate::icon::{IconImage, SurfaceColor};
+    use crate::layer::{DecalConfig, FolderColorTargetConfig, OverlayPosition, SvgOverlayConfig};
+    use image::{Rgba, RgbaImage};
+
+    const SQUARE: &str = r##"<svg xmlns="http://www.w3.org/2000/svg" width="10" height="10"><rect width="10" height="10" fill="#000"/></svg>"##;
+    const BADGE: &str = r##"<svg xmlns="http://www.w3.org/2000/svg" width="10" height="10"><circle cx="5" cy="5" r="5" fill="#e53935"/></svg>"##;
+
+    #[test]
+    fn batch_matches_customizer_and_shares_stamps() {
+        let folder = |r, g, b| {
+            let icon = |size| IconImage::new_full_content(RgbaImage::from_pixel(size, size, Rgba([r, g, b, 255])), 1.0);
+            FolderIconBase::new(IconSet::from_images(vec![icon(32), icon(16)]), SurfaceColor::new(r, g, b))
+        };
+        let folders = vec![folder(255, 217, 112), folder(120, 144, 156), folder(255, 217, 112)];
+        let profile = CustomizationProfile::new()
+            .with_folder_color_target(FolderColorTargetConfig::new(33, 150, 243))
+            .with_decal(DecalConfig::new(SQUARE, 0.4))
+            .with_overlay(SvgOverlayConfig::new(BADGE, OverlayPosition::BottomRight, 0.3));
+
+        let renderer = IconBatchRenderer::new(&profile);
+        let rendered = renderer.render_all(&folders);
+        for (base, batch) in folders.iter().zip(rendered) {
+            let mut customizer = FolderIconCustomizer::new(base.clone());
+            customizer.apply_profile(&profile);
+            assert_eq!(batch.unwrap(), customizer.render_all().unwrap());
+        }
+
+        // Recolored alike, every folder gets the same decal color, so the
+        // badge and the decal are each rasterized once per size
+        assert_eq!(renderer.cached_stamps(), 2 * 2);
+        assert!(matches!(renderer.render(&FolderIconBase::new(IconSet::new(), SurfaceColor::new(0, 0, 0))), Err(RenderError::EmptyIconSet)));
+    }
+}
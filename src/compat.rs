@@ -0,0 +1,210 @@
+//! Profile import from other folder customization tools.
+//!
+//! Only available when the `import-compat` feature is enabled.
+//!
+//! It provides:
+//!
+//! - The [`ProfileImporter`] trait, so format-specific importers can be
+//!   added (here or in an application) as formats become documented.
+//! - [`ColorImporter`], which covers what these tools fundamentally store
+//!   for a folder — a single color — from a one-line hex value
+//!   (`#2196f3`, `0x2196F3`), `r,g,b` triple, or [`FolderColor`] name.
+//!
+//! # Scope
+//!
+//! Native importers for Folder Colorizer, Foldery and LiteIcon are out of
+//! scope. None of them documents its on-disk format, and none of those
+//! files holds more than the folder color [`ColorImporter`] already reads:
+//! the rest is icon artwork, not customization settings. Guessing at
+//! undocumented binary layouts would misread files from other versions,
+//! so those formats are left to applications that can test against them.
+//!
+//! # Example
+//!
+//! ```
+//! use folco_renderer::compat::{import_profile, ColorImporter, ProfileImporter};
+//!
+//! let importers: [&dyn ProfileImporter; 1] = [&ColorImporter];
+//! let profile = import_profile(b"color=#2196F3", &importers).unwrap();
+//! assert_eq!(profile.folder_color_target.unwrap().target_b, 243);
+//! ```
+
+use thiserror::Error;
+
+use crate::folder_color::FolderColor;
+use crate::layer::FolderColorTargetConfig;
+use crate::profile::CustomizationProfile;
+
+/// Errors that can occur while importing a foreign profile.
+#[derive(Debug, Error)]
+pub enum ImportError {
+    /// No importer recognized the input.
+    #[error("unrecognized profile format")]
+    UnrecognizedFormat,
+
+    /// The input was recognized but could not be converted.
+    #[error("invalid {format} profile: {message}")]
+    Invalid {
+        /// Name of the importer that rejected the input.
+        format: &'static str,
+        /// Human-readable reason.
+        message: String,
+    },
+}
+
+/// Converts another tool's configuration into a [`CustomizationProfile`].
+pub trait ProfileImporter {
+    /// Short human-readable name of the format (e.g. "color").
+    fn name(&self) -> &'static str;
+
+    /// Returns true if `data` looks like this importer's format.
+    fn detect(&self, data: &[u8]) -> bool;
+
+    /// Converts `data` into a profile.
+    fn import(&self, data: &[u8]) -> Result<CustomizationProfile, ImportError>;
+}
+
+/// Imports `data` with the first importer whose [`detect`](ProfileImporter::detect)
+/// accepts it.
+///
+/// # Errors
+///
+/// Returns [`ImportError::UnrecognizedFormat`] if no importer accepts the
+/// input, or the selected importer's error.
+pub fn import_profile(
+    data: &[u8],
+    importers: &[&dyn ProfileImporter],
+) -> Result<CustomizationProfile, ImportError> {
+    importers
+        .iter()
+        .find(|importer| importer.detect(data))
+        .ok_or(ImportError::UnrecognizedFormat)?
+        .import(data)
+}
+
+// ============================================================================
+// ColorImporter
+// ============================================================================
+
+/// Imports a single folder color as a color target profile.
+///
+/// Accepts a single line of text whose value (after an optional `key=` or
+/// `key:` prefix, where the key is a plain identifier, and surrounding
+/// quotes) is one of:
+/// - `#RRGGBB`, `RRGGBB`, or `0xRRGGBB`
+/// - `r,g,b` with decimal channels
+/// - a [`FolderColor`] name such as `deep-purple`
+///
+/// Anything else, such as a multi-line file that merely mentions a color,
+/// is not detected, so the importer can sit before stricter ones.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ColorImporter;
+
+/// Longest input [`ColorImporter`] accepts, in bytes.
+const MAX_COLOR_LINE: usize = 256;
+
+impl ColorImporter {
+    /// Parses a color line into RGB channels.
+    fn parse(text: &str) -> Option<(u8, u8, u8)> {
+        let line = text.trim();
+        if line.is_empty() || line.len() > MAX_COLOR_LINE || line.contains(['\n', '\r', '\0']) {
+            return None;
+        }
+        let value = match line.split_once(['=', ':']) {
+            Some((key, value)) if is_key(key) => value,
+            Some(_) => return None,
+            None => line,
+        };
+        let value = value.trim().trim_matches(['"', '\'']);
+
+        let hex = value
+            .strip_prefix('#')
+            .or_else(|| value.strip_prefix("0x"))
+            .or_else(|| value.strip_prefix("0X"))
+            .unwrap_or(value);
+        if hex.len() == 6 && hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            let rgb = u32::from_str_radix(hex, 16).ok()?;
+            return Some(((rgb >> 16) as u8, (rgb >> 8) as u8, rgb as u8));
+        }
+
+        let channels: Vec<_> = value.split(',').map(|c| c.trim().parse::<u8>()).collect();
+        if let [Ok(r), Ok(g), Ok(b)] = channels[..] {
+            return Some((r, g, b));
+        }
+
+        value.parse::<FolderColor>().ok().map(|c| c.rgb())
+    }
+}
+
+/// Returns true for a plain, optionally quoted identifier like `color` or
+/// `"folder.color"`.
+fn is_key(key: &str) -> bool {
+    let key = key.trim().trim_matches(['"', '\'']);
+    !key.is_empty() && key.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'))
+}
+
+impl ProfileImporter for ColorImporter {
+    fn name(&self) -> &'static str {
+        "color"
+    }
+
+    fn detect(&self, data: &[u8]) -> bool {
+        std::str::from_utf8(data).is_ok_and(|text| Self::parse(text).is_some())
+    }
+
+    fn import(&self, data: &[u8]) -> Result<CustomizationProfile, ImportError> {
+        let invalid = |message: &str| ImportError::Invalid {
+            format: self.name(),
+            message: message.to_string(),
+        };
+        let text = std::str::from_utf8(data).map_err(|_| invalid("not UTF-8 text"))?;
+        let (r, g, b) = Self::parse(text).ok_or_else(|| invalid("no recognizable color value"))?;
+        Ok(CustomizationProfile::new().with_folder_color_target(FolderColorTargetConfig::new(r, g, b)))
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn import(text: &str) -> Option<(u8, u8, u8)> {
+        let ct = ColorImporter.import(text.as_bytes()).ok()?.folder_color_target?;
+        Some((ct.target_r, ct.target_g, ct.target_b))
+    }
+
+    #[test]
+    fn color_importer_formats() {
+        assert_eq!(import("#2196f3"), Some((33, 150, 243)));
+        assert_eq!(import("Color=0x2196F3\n"), Some((33, 150, 243)));
+        assert_eq!(import("\"color\": \"33, 150, 243\""), Some((33, 150, 243)));
+        assert_eq!(import("deep-purple"), Some(FolderColor::DeepPurple.rgb()));
+        assert_eq!(import("not a color"), None);
+    }
+
+    #[test]
+    fn color_importer_rejects_loose_matches() {
+        for text in [
+            "[folder]\ncolor=#2196f3",
+            "see the docs at http://example.com: red",
+            "url=http://example.com",
+            "",
+        ] {
+            assert!(!ColorImporter.detect(text.as_bytes()), "{text:?}");
+        }
+        assert!(!ColorImporter.detect(&[0x89, b'P', b'N', b'G', b'\r', b'\n']));
+        assert!(!ColorImporter.detect(format!("color={}#2196f3", " ".repeat(MAX_COLOR_LINE)).as_bytes()));
+    }
+
+    #[test]
+    fn import_profile_reports_unrecognized() {
+        let importers: [&dyn ProfileImporter; 1] = [&ColorImporter];
+        assert!(matches!(
+            import_profile(b"<plist/>", &importers),
+            Err(ImportError::UnrecognizedFormat)
+        ));
+    }
+}
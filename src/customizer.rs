@@ -1,9 +1,17 @@
 //! Icon customization engine with layered transformations.
 
-use crate::icon::{FolderIconBase, IconImage, IconSet, SurfaceColor};
-use crate::layer::LayerPipeline;
+use crate::analysis::{self, LegibilityReport, SizeLegibility};
+use crate::icon::{FolderIconBase, IconImage, IconSet, IconVariant, SurfaceColor};
+use serde::{Deserialize, Serialize};
+
+use crate::layer::{CacheKey, CostEstimate, Layer, LayerConfig, LayerKind, LayerPipeline, LayerVersions, SvgSource};
 use crate::error::RenderError;
-use crate::profile::CustomizationProfile;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::layer::cancel::CancellationToken;
+use crate::profile::{CustomizationProfile, ProfilePresetError, ProfileValueError};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::queue::RenderFuture;
+use crate::render_id::RenderId;
 
 // ============================================================================
 // Configurable Trait
@@ -18,6 +26,142 @@ pub trait Configurable {
     fn export_profile(&self) -> CustomizationProfile;
 }
 
+// ============================================================================
+// RenderedIcon
+// ============================================================================
+
+/// A rendered icon paired with the [`RenderId`] of its inputs.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RenderedIcon {
+    /// The rendered image.
+    pub image: IconImage,
+    /// Fingerprint of everything that determined `image`'s pixels.
+    pub id: RenderId,
+}
+
+// ============================================================================
+// ApplyReport
+// ============================================================================
+
+/// What [`apply_profile`](Configurable::apply_profile) would do, as
+/// computed by [`FolderIconCustomizer::preview_apply`].
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "tsify", derive(tsify_next::Tsify))]
+#[cfg_attr(feature = "tsify", tsify(into_wasm_abi, from_wasm_abi))]
+#[serde(rename_all = "camelCase")]
+pub struct ApplyReport {
+    /// Layers whose configuration would change.
+    pub changed_layers: Vec<LayerKind>,
+    /// Layers whose caches would be invalidated, either because their
+    /// configuration changes or because an upstream layer they depend on does.
+    pub invalidated_caches: Vec<LayerKind>,
+    /// Logical sizes that would re-render, ascending and deduplicated.
+    pub rerendered_sizes: Vec<u32>,
+    /// Problems found in the profile.
+    pub warnings: Vec<ApplyWarning>,
+}
+
+impl ApplyReport {
+    /// Returns true if applying the profile would change nothing.
+    pub fn is_noop(&self) -> bool {
+        self.changed_layers.is_empty()
+    }
+}
+
+/// A problem found in a profile by [`FolderIconCustomizer::preview_apply`].
+///
+/// Only [`NonFinite`](Self::NonFinite) and
+/// [`Unserializable`](Self::Unserializable) stop the profile from applying;
+/// the others usually mean the result won't be what the author intended.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "tsify", derive(tsify_next::Tsify))]
+#[cfg_attr(feature = "tsify", tsify(into_wasm_abi, from_wasm_abi))]
+#[serde(tag = "kind", rename_all = "kebab-case", rename_all_fields = "camelCase")]
+pub enum ApplyWarning {
+    /// A preset reference can't be resolved, so the layer would be left
+    /// unconfigured.
+    UnresolvedPreset {
+        /// The layer holding the reference.
+        layer: LayerKind,
+        /// Why resolution failed.
+        message: String,
+    },
+    /// A layer's SVG source can't be resolved, so rendering would fail.
+    UnresolvedSource {
+        /// The layer holding the source.
+        layer: LayerKind,
+        /// Why resolution failed.
+        message: String,
+    },
+    /// A scale outside 0.0–1.0 would be clamped at render time.
+    ScaleOutOfRange {
+        /// The layer holding the scale.
+        layer: LayerKind,
+        /// The configured scale.
+        scale: f32,
+    },
+    /// A number is NaN or infinite, so the profile won't be applied; see
+    /// [`CustomizationProfile::validate`].
+    NonFinite {
+        /// JSON Pointer to the value, e.g. `/decal/scale`.
+        path: String,
+    },
+    /// The profile can't be serialized, so it won't be applied; see
+    /// [`CustomizationProfile::validate`].
+    Unserializable {
+        /// Why serialization failed.
+        message: String,
+    },
+}
+
+impl std::fmt::Display for ApplyWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UnresolvedPreset { layer, message } => write!(f, "{layer} preset not applied: {message}"),
+            Self::UnresolvedSource { layer, message } => write!(f, "{layer} source can't be resolved: {message}"),
+            Self::ScaleOutOfRange { layer, scale } => write!(f, "{layer} scale {scale} will be clamped to 0.0–1.0"),
+            Self::NonFinite { path } => write!(f, "{path}: not a finite number, profile not applied"),
+            Self::Unserializable { message } => write!(f, "profile cannot be serialized ({message}), not applied"),
+        }
+    }
+}
+
+/// Resolves a layer's config from a profile, recording a warning and
+/// falling back to no config if its preset reference is broken.
+fn resolved_or_warn<'a, C>(
+    resolved: Result<Option<&'a C>, ProfilePresetError>,
+    layer: LayerKind,
+    warnings: &mut Vec<ApplyWarning>,
+) -> Option<&'a C> {
+    resolved.unwrap_or_else(|error| {
+        warnings.push(ApplyWarning::UnresolvedPreset {
+            layer,
+            message: error.to_string(),
+        });
+        None
+    })
+}
+
+/// Checks an SVG layer's source and scale.
+fn check_svg_layer(source: &SvgSource, scale: f32, layer: LayerKind, warnings: &mut Vec<ApplyWarning>) {
+    if let Err(error) = source.resolve_shared() {
+        warnings.push(ApplyWarning::UnresolvedSource {
+            layer,
+            message: error.to_string(),
+        });
+    }
+    if !(0.0..=1.0).contains(&scale) {
+        warnings.push(ApplyWarning::ScaleOutOfRange { layer, scale });
+    }
+}
+
+/// Returns a config-only layer holding `config`, for dependency queries.
+fn staged<C: LayerConfig>(config: Option<&C>) -> Layer<C> {
+    let mut layer = Layer::default();
+    layer.set_config(config.cloned());
+    layer
+}
+
 // ============================================================================
 // FolderIconCustomizer
 // ============================================================================
@@ -31,8 +175,11 @@ pub trait Configurable {
 /// # Layer Pipeline
 ///
 /// 1. **Color Target** (`pipeline.folder_color_target`) - Recolors to a target RGB color
-/// 2. **Decal Imprint** (`pipeline.decal`) - Renders an SVG at the center
-/// 3. **SVG Overlay** (`pipeline.overlay`) - Renders an SVG at a corner position
+/// 2. **Gradient Tint** (`pipeline.gradient_tint`) - Blends a gradient over the content
+/// 3. **Shadow** (`pipeline.shadow`) - Casts a drop shadow beneath the icon
+/// 4. **Decal Imprint** (`pipeline.decal`) - Renders an SVG at the center
+/// 5. **SVG Overlay** (`pipeline.overlay`) - Renders an SVG at a corner position
+/// 6. **Text Label** (`pipeline.text`) - Draws a short caption
 ///
 /// Each layer's rendering logic lives on its concrete `Layer<Config>` type,
 /// keeping config structs as pure data.
@@ -56,6 +203,7 @@ pub trait Configurable {
 /// // Render
 /// let output = customizer.render_all();
 /// ```
+#[derive(Clone)]
 pub struct FolderIconCustomizer {
     /// The original system folder icon set (never modified).
     base_icons: IconSet,
@@ -65,10 +213,18 @@ pub struct FolderIconCustomizer {
     /// Used to compute deltas when applying a profile with target RGB colors.
     surface_color: SurfaceColor,
 
+    /// The base icon as SVG, if a vector source is available.
+    base_svg: Option<String>,
+
     /// The layer pipeline. Access layers directly to configure them.
     ///
     /// See [`LayerPipeline`] for the dependency graph and available layers.
     pub pipeline: LayerPipeline,
+
+    /// The last applied profile with its presets resolved, before its
+    /// intensity was baked in. Exported while the layers still hold what
+    /// it applied.
+    applied: Option<CustomizationProfile>,
 }
 
 impl FolderIconCustomizer {
@@ -77,7 +233,9 @@ impl FolderIconCustomizer {
         Self {
             base_icons: base.icons,
             surface_color: base.surface_color,
+            base_svg: base.svg,
             pipeline: LayerPipeline::default(),
+            applied: None,
         }
     }
 
@@ -91,6 +249,11 @@ impl FolderIconCustomizer {
         &self.surface_color
     }
 
+    /// Returns the base icon's SVG source, if any.
+    pub fn base_svg(&self) -> Option<&str> {
+        self.base_svg.as_deref()
+    }
+
     /// Renders a single icon at the specified logical size.
     ///
     /// Returns the closest matching size from the base icon set,
@@ -98,31 +261,402 @@ impl FolderIconCustomizer {
     ///
     /// # Errors
     ///
-    /// Returns [`RenderError::NoBaseIcon`] if no base icon matches the size,
-    /// or a render error if a layer fails (e.g., invalid SVG or emoji).
+    /// Returns [`RenderError::EmptyIconSet`] if the base has no images,
+    /// [`RenderError::NoBaseIcon`] if none matches the size, or a render
+    /// error if a layer fails (e.g., invalid SVG or emoji).
     pub fn render(&mut self, logical_size: u32) -> Result<IconImage, RenderError> {
-        let base = self
-            .base_icons
-            .find_by_logical_size(logical_size)
-            .ok_or(RenderError::NoBaseIcon { logical_size })?
-            .clone();
+        let base = self.base_for(logical_size)?.clone();
         self.pipeline.render(&base, &self.surface_color)
     }
 
+    /// Renders like [`render`](Self::render) on a shared worker (rayon's
+    /// pool with the `rayon` feature), returning a future of the result for
+    /// async hosts.
+    ///
+    /// The render uses a copy of the pipeline holding only this size's
+    /// cache entries, so they serve it but don't keep its output. A render
+    /// that panics completes with [`RenderError::Panicked`]. It stops with
+    /// [`RenderError::Cancelled`] once `token` is cancelled or the future
+    /// is dropped, so a host can abandon stale renders as a slider moves.
+    ///
+    /// ```
+    /// use folco_renderer::layer::cancel::CancellationToken;
+    /// use folco_renderer::{FolderIconBase, FolderIconCustomizer, IconImage, IconSet, SurfaceColor};
+    /// use image::RgbaImage;
+    ///
+    /// let base = FolderIconBase::new(IconSet::from_images(vec![IconImage::new_full_content(RgbaImage::new(32, 32), 1.0)]), SurfaceColor::new(255, 217, 112));
+    /// let customizer = FolderIconCustomizer::new(base);
+    ///
+    /// let future = customizer.render_async(32, CancellationToken::new());
+    /// # let poll = |mut future: folco_renderer::queue::RenderFuture| {
+    /// #     use std::future::Future;
+    /// #     let waker = std::task::Waker::noop();
+    /// #     loop {
+    /// #         if let std::task::Poll::Ready(result) = std::pin::Pin::new(&mut future).poll(&mut std::task::Context::from_waker(waker)) {
+    /// #             return result;
+    /// #         }
+    /// #         std::thread::yield_now();
+    /// #     }
+    /// # };
+    /// // With an async runtime: `future.await`
+    /// let image = poll(future).unwrap();
+    /// assert_eq!(image.data().dimensions(), (32, 32));
+    /// ```
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn render_async(&self, logical_size: u32, token: CancellationToken) -> RenderFuture {
+        let job = self
+            .base_for(logical_size)
+            .map(|base| (base.clone(), self.pipeline.copy_shard(CacheKey::from_icon(base))));
+        let surface_color = self.surface_color;
+        RenderFuture::spawn(token.clone(), move || {
+            let (base, mut pipeline) = job?;
+            pipeline.set_cancellation_token(Some(token));
+            pipeline.render(&base, &surface_color)
+        })
+    }
+
+    /// Returns the base image [`render`](Self::render) uses for
+    /// `logical_size`.
+    fn base_for(&self, logical_size: u32) -> Result<&IconImage, RenderError> {
+        if self.base_icons.is_empty() {
+            return Err(RenderError::EmptyIconSet);
+        }
+        self.base_icons
+            .find_by_logical_size(logical_size)
+            .ok_or(RenderError::NoBaseIcon { logical_size })
+    }
+
+    /// Renders the customized icon as a single resolution-independent SVG.
+    ///
+    /// Requires a base with an SVG source (see [`FolderIconBase::with_svg`]).
+    /// See [`LayerPipeline::render_svg_composite`] for how each layer is
+    /// expressed.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`RenderError::NotVectorRepresentable`] if the base has no
+    /// SVG source or the active layers can't be expressed as vectors.
+    pub fn render_svg_composite(&self) -> Result<String, RenderError> {
+        let base_svg = self.base_svg.as_deref().ok_or(RenderError::NotVectorRepresentable {
+            reason: "the base icon has no SVG source",
+        })?;
+        self.pipeline.render_svg_composite(base_svg, &self.surface_color)
+    }
+
+    /// Estimates the cost of [`render`](Self::render) without rendering.
+    ///
+    /// Returns `None` if no base icon matches the size. See
+    /// [`LayerPipeline::estimate_cost`].
+    pub fn estimate_cost(&self, logical_size: u32) -> Option<CostEstimate> {
+        let base = self.base_icons.find_by_logical_size(logical_size)?;
+        Some(self.pipeline.estimate_cost(base))
+    }
+
+    /// Renders a single icon and tags it with its [`RenderId`].
+    ///
+    /// # Errors
+    ///
+    /// Same as [`render`](Self::render).
+    pub fn render_identified(&mut self, logical_size: u32) -> Result<RenderedIcon, RenderError> {
+        let id = self.render_id_for(self.base_for(logical_size)?)?;
+        let image = self.render(logical_size)?;
+        Ok(RenderedIcon { image, id })
+    }
+
+    /// Returns the [`RenderId`] that [`render`](Self::render) would produce
+    /// for `logical_size`, without rendering.
+    ///
+    /// Compare against a previously stored ID to decide whether a re-render
+    /// is needed. Returns `None` if no base icon matches the size, or if
+    /// the active configs can't be serialized (see
+    /// [`RenderError::Unidentifiable`]).
+    pub fn render_id(&self, logical_size: u32) -> Option<RenderId> {
+        let base = self.base_icons.find_by_logical_size(logical_size)?;
+        self.render_id_for(base).ok()
+    }
+
+    /// Computes the [`RenderId`] for rendering `base` with the current state.
+    fn render_id_for(&self, base: &IconImage) -> Result<RenderId, RenderError> {
+        // Disabled and muted layers don't affect pixels, so leave them out of the hash
+        let pipeline = &self.pipeline;
+        let profile = CustomizationProfile {
+            folder_color_target: pipeline
+                .renders(LayerKind::FolderColorTarget)
+                .then(|| pipeline.folder_color_target.config().cloned())
+                .flatten(),
+            gradient_tint: pipeline
+                .renders(LayerKind::GradientTint)
+                .then(|| pipeline.gradient_tint.config().cloned())
+                .flatten(),
+            shadow: pipeline.renders(LayerKind::Shadow).then(|| pipeline.shadow.config().cloned()).flatten(),
+            decal: pipeline.renders(LayerKind::Decal).then(|| pipeline.decal.config().cloned()).flatten(),
+            overlay: pipeline.renders(LayerKind::Overlay).then(|| pipeline.overlay.config().cloned()).flatten(),
+            text: pipeline.renders(LayerKind::Text).then(|| pipeline.text.config().cloned()).flatten(),
+            ..CustomizationProfile::default()
+        };
+        RenderId::compute(
+            base,
+            &self.surface_color,
+            &profile,
+            pipeline.accent_color(),
+            pipeline.dominant_color_sampler(),
+            pipeline.placement_rounding(),
+        )
+        .map_err(|source| RenderError::Unidentifiable { source })
+    }
+
+    /// Applies a profile as [`apply_profile`](Configurable::apply_profile)
+    /// does, returning the broken preset references it found.
+    ///
+    /// A layer whose reference can't be resolved is left unconfigured and
+    /// reported as [`ApplyWarning::UnresolvedPreset`].
+    ///
+    /// ```
+    /// use folco_renderer::{ApplyWarning, CustomizationProfile, FolderIconBase, FolderIconCustomizer, IconSet, LayerKind, SurfaceColor};
+    ///
+    /// let mut customizer = FolderIconCustomizer::new(FolderIconBase::new(IconSet::new(), SurfaceColor::new(255, 217, 112)));
+    /// let profile = CustomizationProfile::new().with_decal_preset("missing");
+    ///
+    /// let warnings = customizer.try_apply_profile(&profile).unwrap();
+    /// assert!(matches!(warnings[..], [ApplyWarning::UnresolvedPreset { layer: LayerKind::Decal, .. }]));
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error, changing nothing, if the profile fails
+    /// [`CustomizationProfile::validate`].
+    pub fn try_apply_profile(&mut self, profile: &CustomizationProfile) -> Result<Vec<ApplyWarning>, ProfileValueError> {
+        let mut warnings = Vec::new();
+        self.applied = Some(apply_profile_to(&mut self.pipeline, profile, &mut warnings)?);
+        Ok(warnings)
+    }
+
+    /// Reports what [`apply_profile`](Configurable::apply_profile) would
+    /// change, without changing anything.
+    ///
+    /// Lists the layers whose config differs, the layer caches that would
+    /// be invalidated (following the pipeline's dependency graph), the
+    /// sizes that would re-render, and any problems found in the profile.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use folco_renderer::{Configurable, CustomizationProfile, FolderColorTargetConfig, FolderIconBase, FolderIconCustomizer, IconSet, LayerKind, SurfaceColor};
+    ///
+    /// let customizer = FolderIconCustomizer::new(FolderIconBase::new(IconSet::new(), SurfaceColor::new(255, 217, 112)));
+    /// let profile = CustomizationProfile::new().with_folder_color_target(FolderColorTargetConfig::new(33, 150, 243));
+    ///
+    /// let report = customizer.preview_apply(&profile);
+    /// assert_eq!(report.changed_layers, [LayerKind::FolderColorTarget]);
+    /// // The decal depends on the color target, so its cache goes too
+    /// assert!(report.invalidated_caches.contains(&LayerKind::Decal));
+    /// ```
+    pub fn preview_apply(&self, profile: &CustomizationProfile) -> ApplyReport {
+        let pipeline = &self.pipeline;
+        let mut report = ApplyReport::default();
+        if let Err(error) = profile.validate() {
+            report.warnings.push(match error {
+                ProfileValueError::NonFinite { path } => ApplyWarning::NonFinite { path },
+                ProfileValueError::Unserializable { message } => ApplyWarning::Unserializable { message },
+            });
+            return report;
+        }
+        let profile = profile.with_intensity_applied();
+
+        let color_target = resolved_or_warn(
+            profile.resolved_folder_color_target(),
+            LayerKind::FolderColorTarget,
+            &mut report.warnings,
+        );
+        let gradient_tint = resolved_or_warn(
+            profile.resolved_gradient_tint(),
+            LayerKind::GradientTint,
+            &mut report.warnings,
+        );
+        let shadow = resolved_or_warn(profile.resolved_shadow(), LayerKind::Shadow, &mut report.warnings);
+        let decal = resolved_or_warn(profile.resolved_decal(), LayerKind::Decal, &mut report.warnings);
+        let overlay = resolved_or_warn(profile.resolved_overlay(), LayerKind::Overlay, &mut report.warnings);
+        let text = resolved_or_warn(profile.resolved_text(), LayerKind::Text, &mut report.warnings);
+        if let Some(decal) = decal {
+            check_svg_layer(&decal.source, decal.scale, LayerKind::Decal, &mut report.warnings);
+        }
+        if let Some(overlay) = overlay {
+            check_svg_layer(&overlay.source, overlay.scale, LayerKind::Overlay, &mut report.warnings);
+        }
+        if let Some(text) = text
+            && !(0.0..=1.0).contains(&text.size)
+        {
+            report.warnings.push(ApplyWarning::ScaleOutOfRange {
+                layer: LayerKind::Text,
+                scale: text.size,
+            });
+        }
+
+        let changes = [
+            (LayerKind::FolderColorTarget, pipeline.folder_color_target.would_change(color_target)),
+            (LayerKind::GradientTint, pipeline.gradient_tint.would_change(gradient_tint)),
+            (LayerKind::Shadow, pipeline.shadow.would_change(shadow)),
+            (LayerKind::Decal, pipeline.decal.would_change(decal)),
+            (LayerKind::Overlay, pipeline.overlay.would_change(overlay)),
+            (LayerKind::Text, pipeline.text.would_change(text)),
+        ];
+        report.changed_layers = changes.iter().filter(|(_, changed)| *changed).map(|(kind, _)| *kind).collect();
+        if report.is_noop() {
+            return report;
+        }
+
+        // A cache is stale if its layer changes or its dependency version
+        // moves because an upstream layer's version is bumped
+        let before = pipeline.layer_versions();
+        let bump = |version: u64, changed: bool| if changed { version.wrapping_add(1) } else { version };
+        let after = LayerVersions {
+            folder_color_target: bump(before.folder_color_target, changes[0].1),
+            gradient_tint: bump(before.gradient_tint, changes[1].1),
+            shadow: bump(before.shadow, changes[2].1),
+            decal: bump(before.decal, changes[3].1),
+            overlay: bump(before.overlay, changes[4].1),
+            text: bump(before.text, changes[5].1),
+            accent: before.accent,
+        };
+        let color_target = staged(color_target);
+        let gradient_tint = staged(gradient_tint);
+        let shadow = staged(shadow);
+        let decal = staged(decal);
+        let overlay = staged(overlay);
+        let text = staged(text);
+        let dependencies_move = [
+            color_target.dependencies(&before) != color_target.dependencies(&after),
+            gradient_tint.dependencies(&before) != gradient_tint.dependencies(&after),
+            shadow.dependencies(&before) != shadow.dependencies(&after),
+            decal.dependencies(&before) != decal.dependencies(&after),
+            overlay.dependencies(&before) != overlay.dependencies(&after),
+            text.dependencies(&before) != text.dependencies(&after),
+        ];
+        report.invalidated_caches = changes
+            .iter()
+            .zip(dependencies_move)
+            .filter(|((_, changed), moved)| *changed || *moved)
+            .map(|((kind, _), _)| *kind)
+            .collect();
+
+        // Every layer feeds the composite, so any change re-renders every size
+        let mut sizes: Vec<u32> = self
+            .base_icons
+            .iter()
+            .map(|icon| icon.logical_size().0.round() as u32)
+            .collect();
+        sizes.sort_unstable();
+        sizes.dedup();
+        report.rerendered_sizes = sizes;
+
+        report
+    }
+
     /// Renders all sizes in the base icon set with customizations applied.
     ///
-    /// Returns a new `IconSet` containing the rendered images.
+    /// Returns a new `IconSet` containing the rendered images in canonical
+    /// order (see [`IconSet::sort_canonical`]), regardless of the base
+    /// set's insertion order. Every [`IconVariant`] gets the same
+    /// customization and keeps its tag.
+    ///
+    /// With the `rayon` feature, sizes render in parallel. Each size gets
+    /// its own shard of the pipeline holding just that size's cache
+    /// entries, which are merged back afterwards, so the caches end up as
+    /// after a sequential render.
     ///
     /// # Errors
     ///
-    /// Returns a render error if any layer fails.
+    /// Returns [`RenderError::EmptyIconSet`] if the base has no images, or
+    /// a render error if any layer fails.
     pub fn render_all(&mut self) -> Result<IconSet, RenderError> {
+        let base_images = self.base_icons.clone();
+        self.render_images(&base_images)
+    }
+
+    /// Like [`render_all`](Self::render_all), for the images of `variant`
+    /// only, e.g. to export the open-state folder icon separately.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`RenderError::EmptyIconSet`] if the base has no images of
+    /// `variant`, or a render error if any layer fails.
+    pub fn render_variant(&mut self, variant: IconVariant) -> Result<IconSet, RenderError> {
+        let base_images = self.base_icons.variant(variant);
+        self.render_images(&base_images)
+    }
+
+    /// Renders every image of `base_images`, in canonical order.
+    fn render_images(&mut self, base_images: &IconSet) -> Result<IconSet, RenderError> {
+        if base_images.is_empty() {
+            return Err(RenderError::EmptyIconSet);
+        }
+        #[cfg(not(feature = "rayon"))]
+        let rendered = {
+            let mut rendered = Vec::with_capacity(base_images.len());
+            for base in base_images.iter() {
+                rendered.push(self.pipeline.render(base, &self.surface_color)?);
+            }
+            rendered
+        };
+        #[cfg(feature = "rayon")]
+        let rendered = {
+            use rayon::prelude::*;
+
+            let mut shards: Vec<_> = base_images
+                .iter()
+                .map(|base| (self.pipeline.take_shard(CacheKey::from_icon(base)), base))
+                .collect();
+            let surface_color = self.surface_color;
+            let results: Vec<_> = shards
+                .par_iter_mut()
+                .map(|(shard, base)| shard.render(base, &surface_color))
+                .collect();
+            for (shard, _) in shards {
+                self.pipeline.merge_shard(shard);
+            }
+            results.into_iter().collect::<Result<Vec<_>, _>>()?
+        };
+        let mut set = IconSet::from_images(rendered);
+        set.sort_canonical();
+        Ok(set)
+    }
+
+    /// Scores the legibility of every rendered size.
+    ///
+    /// Each base icon is rendered with the current customizations and
+    /// measured for silhouette edge contrast, decal-vs-surface contrast,
+    /// and detail density. Sizes at or below
+    /// [`SMALL_SIZE_MAX`](crate::analysis::SMALL_SIZE_MAX) that fall outside
+    /// the recommended thresholds produce a
+    /// [`LegibilityWarning`](crate::LegibilityWarning).
+    ///
+    /// # Errors
+    ///
+    /// Returns a render error if any layer fails.
+    pub fn analyze_legibility(&mut self) -> Result<LegibilityReport, RenderError> {
         let base_images: Vec<_> = self.base_icons.iter().cloned().collect();
-        let mut rendered = Vec::with_capacity(base_images.len());
+        let mut report = LegibilityReport::default();
+
         for base in &base_images {
-            rendered.push(self.pipeline.render(base, &self.surface_color)?);
+            let rendered = self.pipeline.render(base, &self.surface_color)?;
+
+            // Compare the decal tile against the surface it sits on
+            let decal_contrast = self
+                .pipeline
+                .decal_backdrop(base, &self.surface_color)?
+                .and_then(|(backdrop, tile)| analysis::decal_contrast(&backdrop, &tile));
+
+            report.push(SizeLegibility {
+                logical_size: rendered.logical_size().0.round() as u32,
+                scale: rendered.scale,
+                edge_contrast: analysis::edge_contrast(rendered.data()),
+                decal_contrast,
+                detail_density: analysis::detail_density(rendered.data()),
+                render_id: self.render_id_for(base)?,
+            });
         }
-        Ok(IconSet::from_images(rendered))
+
+        Ok(report)
     }
 
     /// Clears all layer caches. Useful for freeing memory.
@@ -137,6 +671,16 @@ impl Configurable for FolderIconCustomizer {
     /// Color target settings are expressed as target RGB colors; the customizer
     /// computes the necessary HSL deltas from the stored surface color internally.
     ///
+    /// Preset references are resolved here. A layer whose reference cannot
+    /// be resolved is left unconfigured; use
+    /// [`try_apply_profile`](FolderIconCustomizer::try_apply_profile) to
+    /// get these as warnings, or
+    /// [`preview_apply`](FolderIconCustomizer::preview_apply) to check
+    /// beforehand.
+    ///
+    /// A profile failing [`CustomizationProfile::validate`] is not applied
+    /// at all; `try_apply_profile` returns the error.
+    ///
     /// # Example
     ///
     /// ```
@@ -150,15 +694,16 @@ impl Configurable for FolderIconCustomizer {
     /// customizer.apply_profile(&profile);
     /// ```
     fn apply_profile(&mut self, profile: &CustomizationProfile) {
-        self.pipeline.folder_color_target.set_config(profile.folder_color_target.clone());
-        self.pipeline.decal.set_config(profile.decal.clone());
-        self.pipeline.overlay.set_config(profile.overlay.clone());
+        let _ = self.try_apply_profile(profile);
     }
 
     /// Exports the current customization settings as a profile.
     ///
     /// Color target settings are exported as target RGB colors directly
-    /// from the stored config.
+    /// from the stored config. While the layers hold what the last
+    /// [`apply_profile`](Configurable::apply_profile) set, that profile is
+    /// exported with its own intensity and unscaled strengths, so a
+    /// round trip doesn't compound the intensity.
     ///
     /// # Example
     ///
@@ -173,14 +718,73 @@ impl Configurable for FolderIconCustomizer {
     /// let json = profile.to_json().unwrap();
     /// ```
     fn export_profile(&self) -> CustomizationProfile {
+        let pipeline = &self.pipeline;
+        if let Some(applied) = &self.applied {
+            let scaled = applied.with_intensity_applied();
+            let unchanged = !(pipeline.folder_color_target.would_change(scaled.folder_color_target.as_ref())
+                || pipeline.gradient_tint.would_change(scaled.gradient_tint.as_ref())
+                || pipeline.shadow.would_change(scaled.shadow.as_ref())
+                || pipeline.decal.would_change(scaled.decal.as_ref())
+                || pipeline.overlay.would_change(scaled.overlay.as_ref())
+                || pipeline.text.would_change(scaled.text.as_ref())
+                || pipeline.overlay_arrangement() != applied.overlay_arrangement);
+            if unchanged {
+                return applied.clone();
+            }
+        }
         CustomizationProfile {
             folder_color_target: self.pipeline.folder_color_target.config().cloned(),
+            gradient_tint: self.pipeline.gradient_tint.config().cloned(),
+            shadow: self.pipeline.shadow.config().cloned(),
             decal: self.pipeline.decal.config().cloned(),
             overlay: self.pipeline.overlay.config().cloned(),
+            text: self.pipeline.text.config().cloned(),
+            overlay_arrangement: self.pipeline.overlay_arrangement(),
+            ..CustomizationProfile::default()
         }
     }
 }
 
+/// Configures the layers of `pipeline` from `profile` at its intensity,
+/// leaving layers whose presets don't resolve unconfigured and recording
+/// them in `warnings`.
+///
+/// Returns the profile as applied: presets resolved, unresolvable layers
+/// dropped, and the intensity not yet baked in.
+///
+/// # Errors
+///
+/// Returns an error, leaving `pipeline` unchanged, if the profile fails
+/// [`CustomizationProfile::validate`].
+pub(crate) fn apply_profile_to(
+    pipeline: &mut LayerPipeline,
+    profile: &CustomizationProfile,
+    warnings: &mut Vec<ApplyWarning>,
+) -> Result<CustomizationProfile, ProfileValueError> {
+    profile.validate()?;
+    let applied = CustomizationProfile {
+        folder_color_target: resolved_or_warn(profile.resolved_folder_color_target(), LayerKind::FolderColorTarget, warnings)
+            .cloned(),
+        gradient_tint: resolved_or_warn(profile.resolved_gradient_tint(), LayerKind::GradientTint, warnings).cloned(),
+        shadow: resolved_or_warn(profile.resolved_shadow(), LayerKind::Shadow, warnings).cloned(),
+        decal: resolved_or_warn(profile.resolved_decal(), LayerKind::Decal, warnings).cloned(),
+        overlay: resolved_or_warn(profile.resolved_overlay(), LayerKind::Overlay, warnings).cloned(),
+        text: resolved_or_warn(profile.resolved_text(), LayerKind::Text, warnings).cloned(),
+        intensity: profile.intensity,
+        overlay_arrangement: profile.overlay_arrangement,
+        ..CustomizationProfile::default()
+    };
+    let scaled = applied.with_intensity_applied();
+    pipeline.folder_color_target.set_config(scaled.folder_color_target.clone());
+    pipeline.gradient_tint.set_config(scaled.gradient_tint.clone());
+    pipeline.shadow.set_config(scaled.shadow);
+    pipeline.decal.set_config(scaled.decal.clone());
+    pipeline.overlay.set_config(scaled.overlay.clone());
+    pipeline.text.set_config(scaled.text.clone());
+    pipeline.set_overlay_arrangement(applied.overlay_arrangement);
+    Ok(applied)
+}
+
 // ============================================================================
 // Tests
 // ============================================================================
@@ -217,6 +821,45 @@ mod tests {
         FolderIconBase::new(set, TEST_SURFACE)
     }
 
+    #[test]
+    fn render_id_tracks_active_inputs() {
+        let mut customizer = FolderIconCustomizer::new(create_test_icon_base());
+        let plain = customizer.render_identified(16).unwrap();
+        assert_eq!(customizer.render_id(16), Some(plain.id));
+        assert_ne!(customizer.render_id(32), Some(plain.id));
+
+        customizer
+            .pipeline
+            .folder_color_target
+            .set_config(Some(FolderColorTargetConfig::new(33, 150, 243)));
+        let recolored = customizer.render_id(16).unwrap();
+        assert_ne!(recolored, plain.id);
+
+        // A disabled layer renders like an absent one
+        customizer.pipeline.folder_color_target.set_enabled(false);
+        assert_eq!(customizer.render_id(16), Some(plain.id));
+    }
+
+    #[test]
+    fn export_round_trips_intensity_without_compounding() {
+        let mut customizer = FolderIconCustomizer::new(create_test_icon_base());
+        let profile = CustomizationProfile::new()
+            .with_decal(DecalConfig::new(TEST_SVG, 0.5).with_opacity(0.8))
+            .with_intensity(0.5);
+        customizer.apply_profile(&profile);
+        assert_eq!(customizer.pipeline.decal.config().unwrap().opacity, 0.4);
+
+        let exported = customizer.export_profile();
+        assert_eq!((exported.intensity, exported.decal.as_ref().unwrap().opacity), (0.5, 0.8));
+        customizer.apply_profile(&exported);
+        assert_eq!(customizer.pipeline.decal.config().unwrap().opacity, 0.4);
+
+        // Once the layers are edited directly, what they hold is exported
+        customizer.pipeline.decal.set_config(Some(DecalConfig::new(TEST_SVG, 0.5).with_opacity(0.6)));
+        let edited = customizer.export_profile();
+        assert_eq!((edited.intensity, edited.decal.unwrap().opacity), (1.0, 0.6));
+    }
+
     #[test]
     fn customizer_creation() {
         let base = create_test_icon_base();
@@ -253,8 +896,45 @@ mod tests {
         assert_eq!(rendered.dimensions().width, 16);
 
         // Verify the image is unchanged
-        let pixel = rendered.data.get_pixel(0, 0);
+        let pixel = rendered.data().get_pixel(0, 0);
         assert_eq!(pixel.0, [255, 0, 0, 255]);
+
+        // An identity pipeline borrows the base and caches nothing
+        assert!(customizer.pipeline.is_identity());
+        let base16 = customizer.base_icons().find_by_logical_size(16).unwrap().clone();
+        let surface = *customizer.surface_color();
+        let passed = customizer.pipeline.render_cow(&base16, &surface).unwrap();
+        assert!(matches!(passed, std::borrow::Cow::Borrowed(image) if std::ptr::eq(image, &base16)));
+        let (_, report) = customizer.pipeline.render_with_report(&base16, &surface).unwrap();
+        assert!(!report.cached);
+    }
+
+    #[test]
+    fn variants_render_alike_and_keep_their_tags() {
+        let mut base = create_test_icon_base();
+        // An open-state 32px icon, the same green as the normal one
+        let open = base.icons.find_by_logical_size(32).unwrap().clone().with_variant(IconVariant::Open);
+        base.icons.add_image(open);
+        let mut customizer = FolderIconCustomizer::new(base);
+        customizer
+            .pipeline
+            .decal
+            .set_config(Some(DecalConfig::new(TEST_SVG, 0.5)));
+
+        let rendered = customizer.render_all().unwrap();
+        assert_eq!(rendered.variants(), [IconVariant::Normal, IconVariant::Open]);
+        let normal = rendered.find_by_logical_size(32).unwrap();
+        let open = rendered.find_variant_by_logical_size(IconVariant::Open, 32).unwrap();
+        assert_eq!((normal.variant(), open.variant()), (IconVariant::Normal, IconVariant::Open));
+        assert_eq!(normal.data(), open.data());
+
+        let open_only = customizer.render_variant(IconVariant::Open).unwrap();
+        assert_eq!(open_only.len(), 1);
+        assert!(matches!(customizer.render_variant(IconVariant::Dragged), Err(RenderError::EmptyIconSet)));
+
+        let json = serde_json::to_string(&open_only).unwrap();
+        assert!(json.contains(r#""variant":"open""#));
+        assert_eq!(serde_json::from_str::<IconSet>(&json).unwrap()[0].variant(), IconVariant::Open);
     }
 
     #[test]
@@ -264,6 +944,19 @@ mod tests {
 
         let result = customizer.render_all().unwrap();
         assert_eq!(result.len(), 2);
+
+        // Every size stays cached, however the sizes were rendered
+        customizer
+            .pipeline
+            .folder_color_target
+            .set_config(Some(FolderColorTargetConfig::new(76, 175, 80)));
+        let colored = customizer.render_all().unwrap();
+        let surface = *customizer.surface_color();
+        for base in customizer.base_icons().clone().iter() {
+            let (image, report) = customizer.pipeline.render_with_report(base, &surface).unwrap();
+            assert!(report.cached);
+            assert!(colored.iter().any(|rendered| *rendered == image));
+        }
     }
 
     #[test]
@@ -278,7 +971,7 @@ mod tests {
             .set_config(Some(FolderColorTargetConfig::new(0, 188, 212)));
 
         let rendered = customizer.render(16).unwrap();
-        let pixel = rendered.data.get_pixel(0, 0);
+        let pixel = rendered.data().get_pixel(0, 0);
 
         // Green channel should be dominant after rotation
         assert!(
@@ -311,8 +1004,8 @@ mod tests {
         let second = customizer.render(16).unwrap();
 
         // Results should be different
-        let p1 = first.data.get_pixel(0, 0);
-        let p2 = second.data.get_pixel(0, 0);
+        let p1 = first.data().get_pixel(0, 0);
+        let p2 = second.data().get_pixel(0, 0);
         assert_ne!(
             p1.0, p2.0,
             "Different target colors should produce different results"
@@ -334,7 +1027,7 @@ mod tests {
         let second = customizer.render(16).unwrap();
 
         // Results should be identical (from cache)
-        assert_eq!(first.data.get_pixel(0, 0), second.data.get_pixel(0, 0));
+        assert_eq!(first.data().get_pixel(0, 0), second.data().get_pixel(0, 0));
     }
 
     #[test]
@@ -394,6 +1087,52 @@ mod tests {
         assert_eq!(layer.version(), 3);
     }
 
+    #[test]
+    fn solo_renders_one_layer_and_unsolo_restores() {
+        let mut customizer = FolderIconCustomizer::new(create_test_icon_base());
+        customizer
+            .pipeline
+            .folder_color_target
+            .set_config(Some(FolderColorTargetConfig::new(33, 150, 243)));
+        customizer.pipeline.decal.set_config(Some(DecalConfig::new(TEST_SVG, 0.25)));
+        let full = customizer.render(16).unwrap();
+        let full_id = customizer.render_id(16);
+
+        customizer.pipeline.solo(LayerKind::Decal);
+        assert!(!customizer.pipeline.renders(LayerKind::FolderColorTarget));
+        assert!(customizer.pipeline.folder_color_target.is_enabled());
+        let soloed = customizer.render(16).unwrap();
+        // The corner shows the uncolored base; the decal still draws
+        assert_eq!(soloed.data().get_pixel(0, 0).0, [255, 0, 0, 255]);
+        assert_ne!(soloed.data().get_pixel(8, 8), full.data().get_pixel(8, 8));
+        assert_ne!(customizer.render_id(16), full_id);
+
+        customizer.pipeline.unsolo();
+        assert_eq!(customizer.render(16).unwrap(), full);
+        assert_eq!(customizer.render_id(16), full_id);
+    }
+
+    #[test]
+    fn soloed_renders_track_muted_and_soloed_edits() {
+        const BLUE_SVG: &str = r##"<svg xmlns="http://www.w3.org/2000/svg" width="10" height="10"><rect width="10" height="10" fill="blue"/></svg>"##;
+
+        let mut customizer = FolderIconCustomizer::new(create_test_icon_base());
+        customizer.pipeline.decal.set_config(Some(DecalConfig::new(TEST_SVG, 0.5)));
+        let overlay = SvgOverlayConfig::new(TEST_SVG, OverlayPosition::BottomRight, 0.25);
+        customizer.pipeline.overlay.set_config(Some(overlay.clone()));
+        customizer.pipeline.solo(LayerKind::Overlay);
+        customizer.render(32).unwrap();
+
+        // Muted and soloed versions both rise by one
+        customizer.pipeline.decal.set_config(Some(DecalConfig::new(TEST_SVG, 0.4)));
+        customizer
+            .pipeline
+            .overlay
+            .set_config(Some(SvgOverlayConfig { source: BLUE_SVG.into(), ..overlay }));
+        let img = customizer.render(32).unwrap();
+        assert_eq!(img.data().get_pixel(28, 28).0, [0, 0, 255, 255]);
+    }
+
     #[test]
     fn layer_config_set_clear_cycle() {
         let mut layer: Layer<DecalConfig> = Layer::default();
@@ -430,19 +1169,19 @@ mod tests {
             .set_config(Some(FolderColorTargetConfig::new(0, 188, 212)));
         assert!(customizer.pipeline.folder_color_target.is_active());
         let rotated = customizer.render(16).unwrap();
-        let rotated_pixel = rotated.data.get_pixel(0, 0).0;
+        let rotated_pixel = rotated.data().get_pixel(0, 0).0;
 
         // Disable via toggle (config and cache preserved)
         customizer.pipeline.folder_color_target.set_enabled(false);
         assert!(!customizer.pipeline.folder_color_target.is_active());
         assert!(customizer.pipeline.folder_color_target.has_config()); // Config still present
         let disabled = customizer.render(16).unwrap();
-        assert_eq!(disabled.data.get_pixel(0, 0).0, [255, 0, 0, 255]); // Original red
+        assert_eq!(disabled.data().get_pixel(0, 0).0, [255, 0, 0, 255]); // Original red
 
         // Re-enable
         customizer.pipeline.folder_color_target.set_enabled(true);
         let re_enabled = customizer.render(16).unwrap();
-        assert_eq!(re_enabled.data.get_pixel(0, 0).0, rotated_pixel);
+        assert_eq!(re_enabled.data().get_pixel(0, 0).0, rotated_pixel);
     }
 
     #[test]
@@ -473,6 +1212,26 @@ mod tests {
         assert_eq!(clamped.scale, 1.0);
     }
 
+    #[test]
+    fn decal_scale_curve_interpolates() {
+        use crate::layer::ScaleBreakpoint;
+
+        let config = DecalConfig::new("svg", 0.5).with_scale_curve(vec![
+            ScaleBreakpoint::new(512, 0.3),
+            ScaleBreakpoint::new(16, 0.7),
+        ]);
+        assert_eq!(config.scale_curve[0].size, 16, "Breakpoints are sorted");
+
+        assert_eq!(config.scale_for_size(8.0), 0.7);
+        assert_eq!(config.scale_for_size(16.0), 0.7);
+        assert_eq!(config.scale_for_size(1024.0), 0.3);
+        // 90.5px is the log2 midpoint of 16 and 512
+        assert!((config.scale_for_size(90.5) - 0.5).abs() < 0.001);
+
+        // Without a curve the constant scale applies
+        assert_eq!(DecalConfig::new("svg", 0.4).scale_for_size(16.0), 0.4);
+    }
+
     #[test]
     fn overlay_config_creation() {
         let config = SvgOverlayConfig::new("<svg></svg>", OverlayPosition::BottomRight, 0.25);
@@ -480,6 +1239,144 @@ mod tests {
         assert_eq!(config.scale, 0.25);
     }
 
+    #[test]
+    fn overlay_anchors_to_decal_placement() {
+        use crate::layer::OverlayAnchor;
+
+        let mut customizer = FolderIconCustomizer::new(create_test_icon_base());
+        customizer.pipeline.decal.set_config(Some(DecalConfig::new(TEST_SVG, 0.5)));
+        let overlay = SvgOverlayConfig::new(TEST_SVG, OverlayPosition::BottomRight, 0.25);
+        customizer.pipeline.overlay.set_config(Some(overlay.clone()));
+
+        // Content anchor: overlay in the icon's bottom-right corner
+        let img = customizer.render(32).unwrap();
+        assert_eq!(img.data().get_pixel(28, 28).0, [255, 0, 0, 255]);
+
+        // Decal anchor: overlay in the bottom-right of the 16px decal (8..24)
+        customizer
+            .pipeline
+            .overlay
+            .set_config(Some(overlay.with_anchor(OverlayAnchor::Decal)));
+        let img = customizer.render(32).unwrap();
+        assert_eq!(img.data().get_pixel(20, 20).0, [255, 0, 0, 255]);
+        assert_ne!(img.data().get_pixel(28, 28).0, [255, 0, 0, 255]);
+
+        // Resizing the decal moves the anchored overlay
+        customizer.pipeline.decal.set_config(Some(DecalConfig::new(TEST_SVG, 1.0)));
+        let img = customizer.render(32).unwrap();
+        assert_eq!(img.data().get_pixel(28, 28).0, [255, 0, 0, 255]);
+    }
+
+    #[test]
+    fn color_target_emits_derived_palette() {
+        use crate::layer::{DerivedPalette, RenderContext};
+
+        let mut layer = Layer::<FolderColorTargetConfig>::default();
+        layer.set_config(Some(FolderColorTargetConfig::new(200, 40, 40)));
+        let base = create_test_icon_base().icons.iter().next().unwrap().clone();
+        let versions = LayerPipeline::default().layer_versions();
+
+        // Both the fresh and the cached path emit the palette
+        for _ in 0..2 {
+            let mut ctx = RenderContext::new(base.clone());
+            ctx.set(TEST_SURFACE);
+            layer.apply(&mut ctx, CacheKey::from_icon(&base), &versions).unwrap();
+
+            let palette = ctx.get::<DerivedPalette>().expect("palette should be emitted");
+            assert_eq!(*palette, DerivedPalette::from_rgb(200, 40, 40));
+            // Red's complement is cyan; darker/lighter keep the hue
+            assert!(palette.complementary.0 < palette.complementary.1);
+            assert!(palette.darker.0 < 200 && palette.lighter.0 > 200);
+        }
+    }
+
+    #[test]
+    fn color_target_css_filter() {
+        let surface = TEST_SURFACE;
+        let identity = FolderColorTargetConfig::new(surface.r, surface.g, surface.b);
+        assert_eq!(identity.to_css_filter(&surface), "none");
+
+        // Pure hue change between equally saturated, equally light colors
+        let red = SurfaceColor::new(255, 0, 0);
+        assert_eq!(FolderColorTargetConfig::new(0, 0, 255).to_css_filter(&red), "hue-rotate(240.0deg)");
+
+        let filter = FolderColorTargetConfig::new(33, 150, 243).to_css_filter(&surface);
+        assert!(filter.starts_with("hue-rotate(") && filter.contains("brightness("), "{filter}");
+    }
+
+    #[test]
+    fn matrix_hue_approximation_matches_css_filter() {
+        use crate::layer::folder_color_target::apply_folder_color_target;
+        use crate::layer::HueApproximation;
+
+        let red = SurfaceColor::new(255, 0, 0);
+        let icon = IconImage::new_full_content(RgbaImage::from_pixel(2, 2, image::Rgba([255, 0, 0, 255])), 1.0);
+        let exact = FolderColorTargetConfig::new(0, 255, 255);
+        assert_eq!(exact.to_css_filter(&red), "hue-rotate(180.0deg)");
+
+        let shifted = apply_folder_color_target(&icon, &red, &exact);
+        assert_eq!(shifted.data().get_pixel(0, 0).0, [0, 255, 255, 255]);
+
+        // Browsers render `hue-rotate(180deg)` on pure red as a dark teal
+        let matrix = exact.clone().with_hue_approximation(HueApproximation::Matrix);
+        assert!(matrix.differs_from(&exact));
+        let shifted = apply_folder_color_target(&icon, &red, &matrix);
+        assert_eq!(shifted.data().get_pixel(0, 0).0, [0, 109, 109, 255]);
+    }
+
+    #[test]
+    fn preserve_luminance_maps_any_surface_to_the_target() {
+        use crate::analysis::relative_luminance;
+        use crate::layer::folder_color_target::apply_folder_color_target;
+        use crate::layer::HueApproximation;
+
+        let config = FolderColorTargetConfig::new(255, 107, 0).with_hue_approximation(HueApproximation::PreserveLuminance);
+        let luminance = |[r, g, b, _]: [u8; 4]| relative_luminance(r, g, b);
+        // A yellow and a blue folder, each with a shaded and a highlight pixel
+        for (surface, shade, highlight) in [((255, 217, 112), [178, 150, 70], [255, 240, 200]), ((84, 160, 230), [40, 90, 150], [190, 225, 250])] {
+            let mut image = RgbaImage::from_pixel(3, 1, image::Rgba([surface.0, surface.1, surface.2, 255]));
+            image.put_pixel(1, 0, image::Rgba([shade[0], shade[1], shade[2], 255]));
+            image.put_pixel(2, 0, image::Rgba([highlight[0], highlight[1], highlight[2], 255]));
+            let surface = SurfaceColor::new(surface.0, surface.1, surface.2);
+            let mapped = apply_folder_color_target(&IconImage::new_full_content(image.clone(), 1.0), &surface, &config);
+
+            assert_eq!(mapped.data().get_pixel(0, 0).0, [255, 107, 0, 255]);
+            // Shading keeps its luminance ratio to the surface
+            let ratio = |image: &RgbaImage, x| luminance(image.get_pixel(x, 0).0) / luminance(image.get_pixel(0, 0).0);
+            assert!((ratio(mapped.data(), 1) - ratio(&image, 1)).abs() < 0.02);
+            let [r, g, b, _] = mapped.data().get_pixel(1, 0).0;
+            assert!(r > g && b == 0, "{:?}", (r, g, b));
+            // Too bright for orange: lighter, toward white
+            assert!((ratio(mapped.data(), 2) - ratio(&image, 2)).abs() < 0.02);
+            assert!(mapped.data().get_pixel(2, 0)[2] > 0);
+        }
+    }
+
+    #[test]
+    fn linear_hue_rotation_keeps_luminance() {
+        use crate::analysis::relative_luminance;
+        use crate::layer::folder_color_target::apply_folder_color_target;
+        use crate::layer::HueApproximation;
+
+        // A gold folder retargeted to teal, on a half-transparent highlight
+        let surface = SurfaceColor::new(200, 160, 60);
+        let target = (60, 170, 140);
+        let luminance = |[r, g, b, _]: [u8; 4]| relative_luminance(r, g, b);
+        let highlight = [250, 230, 170, 128];
+        let icon = IconImage::new_full_content(RgbaImage::from_pixel(1, 1, image::Rgba(highlight)), 1.0);
+        let error = |approximation| {
+            let config = FolderColorTargetConfig::new(target.0, target.1, target.2).with_hue_approximation(approximation);
+            let shifted = apply_folder_color_target(&icon, &surface, &config).data().get_pixel(0, 0).0;
+            assert_eq!(shifted[3], 128);
+            let scale = relative_luminance(target.0, target.1, target.2) / relative_luminance(surface.r, surface.g, surface.b);
+            (luminance(shifted) - scale * luminance(highlight)).abs()
+        };
+
+        let linear = error(HueApproximation::Linear);
+        assert!(linear < 0.01, "{linear}");
+        assert!(linear < error(HueApproximation::Exact) / 2.0, "{linear} vs {}", error(HueApproximation::Exact));
+    }
+
     #[test]
     fn decal_uses_hsl_mutated_dominant_color() {
         use crate::layer::folder_color_target::apply_folder_color_target;
@@ -512,7 +1409,7 @@ mod tests {
 
         // Now apply decal - it should use the emitted color, not re-sample
         let decal_config = DecalConfig::new(TEST_SVG, 0.5);
-        let _tile = render_decal(&decal_config, &ctx).unwrap();
+        let _tile = render_decal(&decal_config, &mut ctx).unwrap();
 
         // The DominantColor property should still be the target color
         let color_after_decal = ctx.get::<DominantColor>().unwrap().as_tuple();
@@ -548,6 +1445,10 @@ mod tests {
             folder_color_target: ct_layer.version(),
             decal: decal_layer.version(),
             overlay: 0,
+            gradient_tint: 0,
+            shadow: 0,
+            text: 0,
+            accent: 0,
         };
 
         // Apply color target layer (should skip because no config)
@@ -561,7 +1462,7 @@ mod tests {
 
         // Image should be unchanged (still blue)
         assert_eq!(
-            ctx.image.data.get_pixel(0, 0).0,
+            ctx.image.data().get_pixel(0, 0).0,
             [0, 0, 255, 255],
             "Image should be unchanged when color target has no config"
         );
@@ -572,7 +1473,7 @@ mod tests {
 
         // Image should still be unchanged (decal produces a tile, doesn't composite)
         assert_eq!(
-            ctx.image.data.get_pixel(0, 0).0,
+            ctx.image.data().get_pixel(0, 0).0,
             [0, 0, 255, 255],
             "Image should still be blue"
         );
@@ -601,6 +1502,10 @@ mod tests {
             folder_color_target: ct_layer.version(),
             decal: decal_layer.version(),
             overlay: 0,
+            gradient_tint: 0,
+            shadow: 0,
+            text: 0,
+            accent: 0,
         };
         let mut ctx1 = RenderContext::new(red_icon.clone());
         ctx1.set(TEST_SURFACE);
@@ -628,6 +1533,10 @@ mod tests {
             folder_color_target: ct_layer.version(), // New version!
             decal: decal_layer.version(),
             overlay: 0,
+            gradient_tint: 0,
+            shadow: 0,
+            text: 0,
+            accent: 0,
         };
         let mut ctx2 = RenderContext::new(red_icon.clone());
         ctx2.set(TEST_SURFACE);
@@ -642,12 +1551,181 @@ mod tests {
 
         // Image should be original red (not shifted)
         assert_eq!(
-            ctx2.image.data.get_pixel(0, 0).0,
+            ctx2.image.data().get_pixel(0, 0).0,
             [255, 0, 0, 255],
             "With color target cleared, image should be original red"
         );
     }
 
+    #[test]
+    fn accent_color_retargets_following_layers() {
+        use crate::layer::AccentColor;
+
+        let base = create_test_icon_base();
+        let mut customizer = FolderIconCustomizer::new(base);
+        customizer.pipeline.folder_color_target.set_config(Some(
+            FolderColorTargetConfig::new(244, 67, 54).with_follow_accent(true),
+        ));
+
+        // No accent yet — falls back to the configured red target
+        let fallback = customizer.render(16).unwrap().data().get_pixel(0, 0).0;
+
+        assert!(customizer.pipeline.set_accent_color(Some(AccentColor::new(0, 188, 212))));
+        assert!(!customizer.pipeline.set_accent_color(Some(AccentColor::new(0, 188, 212))));
+        let accented = customizer.render(16).unwrap().data().get_pixel(0, 0).0;
+        assert_ne!(fallback, accented, "Accent change should invalidate cached output");
+        assert!(accented[1] > accented[0], "Cyan accent should make green dominate");
+
+        // Layers that don't follow the accent ignore it
+        customizer
+            .pipeline
+            .folder_color_target
+            .set_config(Some(FolderColorTargetConfig::new(244, 67, 54)));
+        let ignored = customizer.render(16).unwrap().data().get_pixel(0, 0).0;
+        assert_eq!(ignored, fallback);
+    }
+
+    #[test]
+    fn overlays_opt_into_the_accent_color() {
+        use crate::layer::AccentColor;
+
+        let mut customizer = FolderIconCustomizer::new(create_test_icon_base());
+        let overlay = SvgOverlayConfig::new(TEST_SVG, OverlayPosition::BottomRight, 0.25);
+        customizer.pipeline.overlay.set_config(Some(overlay.clone()));
+        customizer.pipeline.set_accent_color(Some(AccentColor::new(0, 255, 0)));
+        assert_eq!(customizer.render(32).unwrap().data().get_pixel(28, 28).0, [255, 0, 0, 255]);
+
+        customizer.pipeline.overlay.set_config(Some(overlay.with_follow_accent(true)));
+        assert_eq!(customizer.render(32).unwrap().data().get_pixel(28, 28).0, [0, 255, 0, 255]);
+
+        // Accent changes reach the cached overlay
+        customizer.pipeline.set_accent_color(Some(AccentColor::new(0, 0, 255)));
+        assert_eq!(customizer.render(32).unwrap().data().get_pixel(28, 28).0, [0, 0, 255, 255]);
+    }
+
+    #[test]
+    fn protected_colors_survive_color_target() {
+        use crate::layer::ProtectedColor;
+
+        // Red folder with a white stripe and a brand-blue stripe
+        let img = RgbaImage::from_fn(16, 16, |x, _| match x {
+            0 => image::Rgba([255, 255, 255, 255]),
+            1 => image::Rgba([30, 60, 200, 255]),
+            _ => image::Rgba([255, 0, 0, 255]),
+        });
+        let mut icons = IconSet::new();
+        icons.add_image(IconImage::new_full_content(img, 1.0));
+        let mut customizer = FolderIconCustomizer::new(FolderIconBase::new(icons, TEST_SURFACE));
+
+        customizer.pipeline.folder_color_target.set_config(Some(
+            FolderColorTargetConfig::new(0, 188, 212)
+                .with_protected_color(ProtectedColor::neutral(0.05))
+                .with_protected_color(ProtectedColor::rgb(32, 64, 200, 0.05)),
+        ));
+
+        let rendered = customizer.render(16).unwrap();
+        assert_eq!(rendered.data().get_pixel(0, 0).0, [255, 255, 255, 255]);
+        assert_eq!(rendered.data().get_pixel(1, 0).0, [30, 60, 200, 255]);
+        assert_ne!(rendered.data().get_pixel(2, 0).0, [255, 0, 0, 255]);
+    }
+
+    #[test]
+    fn cloned_pipeline_is_independent() {
+        let base = create_test_icon_base();
+        let mut customizer = FolderIconCustomizer::new(base);
+        customizer
+            .pipeline
+            .folder_color_target
+            .set_config(Some(FolderColorTargetConfig::new(76, 175, 80)));
+        let live = customizer.render(16).unwrap();
+        let live_versions = customizer.pipeline.layer_versions();
+        customizer.pipeline.set_cancellation_token(Some(CancellationToken::new()));
+
+        // Speculatively preview a different color on a clone
+        let mut speculative = customizer.pipeline.clone();
+        // It shares neither the stamp cache nor the cancellation token
+        assert!(!std::sync::Arc::ptr_eq(&speculative.composite_stamps.0, &customizer.pipeline.composite_stamps.0));
+        assert!(speculative.cancellation_token().is_none());
+        speculative
+            .folder_color_target
+            .set_config(Some(FolderColorTargetConfig::new(33, 150, 243)));
+        let base16 = customizer.base_icons().find_by_logical_size(16).unwrap().clone();
+        let preview = speculative.render(&base16, customizer.surface_color()).unwrap();
+        assert_ne!(preview.data().get_pixel(0, 0), live.data().get_pixel(0, 0));
+        drop(speculative);
+
+        // The live pipeline is untouched
+        let after = customizer.pipeline.layer_versions();
+        assert_eq!(after.folder_color_target, live_versions.folder_color_target);
+        assert_eq!(customizer.pipeline.folder_color_target.config().unwrap().target_r, 76);
+        assert_eq!(customizer.render(16).unwrap(), live);
+    }
+
+    #[test]
+    fn render_rejects_images_over_limits() {
+        use crate::layer::RenderLimits;
+
+        let base = create_test_icon_base();
+        let mut customizer = FolderIconCustomizer::new(base);
+        customizer.pipeline.set_limits(RenderLimits::new(16, 256));
+
+        assert!(customizer.render(16).is_ok());
+        assert!(matches!(
+            customizer.render(32),
+            Err(RenderError::ImageTooLarge { width: 32, height: 32, .. })
+        ));
+        assert!(customizer.render_all().is_err());
+    }
+
+    #[test]
+    fn empty_base_set_is_an_error() {
+        let mut customizer = FolderIconCustomizer::new(FolderIconBase::new(IconSet::new(), SurfaceColor::new(255, 217, 112)));
+        assert!(matches!(customizer.render(16), Err(RenderError::EmptyIconSet)));
+        assert!(matches!(customizer.render_identified(16), Err(RenderError::EmptyIconSet)));
+        assert!(matches!(customizer.render_all(), Err(RenderError::EmptyIconSet)));
+    }
+
+    #[test]
+    fn legibility_report_covers_all_sizes() {
+        let base = create_test_icon_base();
+        let mut customizer = FolderIconCustomizer::new(base);
+        customizer
+            .pipeline
+            .decal
+            .set_config(Some(DecalConfig::new(TEST_SVG, 0.5)));
+
+        let report = customizer.analyze_legibility().unwrap();
+        assert_eq!(report.sizes.len(), 2);
+        assert_eq!(report.sizes[0].logical_size, 16);
+        assert!(report.sizes.iter().all(|s| s.decal_contrast.is_some()));
+
+        customizer.pipeline.decal.set_enabled(false);
+        let report = customizer.analyze_legibility().unwrap();
+        assert!(report.sizes.iter().all(|s| s.decal_contrast.is_none()));
+    }
+
+    #[test]
+    fn legibility_analysis_leaves_caches_consistent() {
+        use crate::layer::AccentColor;
+
+        let configure = |customizer: &mut FolderIconCustomizer| {
+            customizer.pipeline.folder_color_target.set_config(Some(
+                FolderColorTargetConfig::new(244, 67, 54).with_follow_accent(true),
+            ));
+            customizer.pipeline.set_accent_color(Some(AccentColor::new(76, 175, 80)));
+            customizer.pipeline.decal.set_config(Some(DecalConfig::new(TEST_SVG, 0.5)));
+        };
+        let mut fresh = FolderIconCustomizer::new(create_test_icon_base());
+        configure(&mut fresh);
+        let expected = fresh.render(32).unwrap();
+
+        let mut customizer = FolderIconCustomizer::new(create_test_icon_base());
+        configure(&mut customizer);
+        customizer.pipeline.set_cache_limit(0);
+        customizer.analyze_legibility().unwrap();
+        assert_eq!(customizer.render(32).unwrap(), expected);
+    }
+
     #[test]
     fn pipeline_property_flow_with_hsl_toggle() {
         // Integration test: verify the full pipeline handles color target toggle correctly
@@ -673,12 +1751,12 @@ mod tests {
 
         // Render with color target enabled
         let with_ct = customizer.render(16).unwrap();
-        let ct_pixel = with_ct.data.get_pixel(0, 0).0;
+        let ct_pixel = with_ct.data().get_pixel(0, 0).0;
 
         // Disable color target via toggle, keep decal
         customizer.pipeline.folder_color_target.set_enabled(false);
         let without_ct = customizer.render(16).unwrap();
-        let no_ct_pixel = without_ct.data.get_pixel(0, 0).0;
+        let no_ct_pixel = without_ct.data().get_pixel(0, 0).0;
 
         // With color target: image should be shifted (more green than red)
         assert!(
@@ -697,9 +1775,56 @@ mod tests {
         customizer.pipeline.folder_color_target.set_enabled(true);
         let re_enabled = customizer.render(16).unwrap();
         assert_eq!(
-            re_enabled.data.get_pixel(0, 0).0,
+            re_enabled.data().get_pixel(0, 0).0,
             ct_pixel,
             "Re-enabling color target should restore shifted result"
         );
     }
+
+    #[test]
+    fn preview_apply_reports_without_mutating() {
+        let mut customizer = FolderIconCustomizer::new(create_test_icon_base());
+        customizer
+            .pipeline
+            .overlay
+            .set_config(Some(SvgOverlayConfig::new(TEST_SVG, OverlayPosition::TopLeft, 0.25)));
+        let version = customizer.pipeline.decal.version();
+
+        let profile = CustomizationProfile::new()
+            .with_decal(DecalConfig {
+                scale: 1.5,
+                ..DecalConfig::new(TEST_SVG, 0.5)
+            })
+            .with_overlay(SvgOverlayConfig::new(TEST_SVG, OverlayPosition::TopLeft, 0.25))
+            .with_folder_color_target_preset("missing");
+        let report = customizer.preview_apply(&profile);
+
+        assert_eq!(report.changed_layers, [LayerKind::Decal]);
+        // A corner overlay doesn't depend on the decal
+        assert_eq!(report.invalidated_caches, [LayerKind::Decal]);
+        assert_eq!(report.rerendered_sizes, [16, 32]);
+        assert!(matches!(
+            report.warnings.as_slice(),
+            [
+                ApplyWarning::UnresolvedPreset { layer: LayerKind::FolderColorTarget, .. },
+                ApplyWarning::ScaleOutOfRange { layer: LayerKind::Decal, .. },
+            ]
+        ));
+        assert!(!customizer.pipeline.decal.has_config());
+        assert_eq!(customizer.pipeline.decal.version(), version);
+
+        let unchanged = customizer.export_profile();
+        let report = customizer.preview_apply(&unchanged);
+        assert!(report.is_noop() && report.rerendered_sizes.is_empty());
+
+        // An auto-placed overlay follows the decal, so its cache goes too
+        let auto = SvgOverlayConfig::new(TEST_SVG, OverlayPosition::Auto, 0.25);
+        customizer.pipeline.overlay.set_config(Some(auto.clone()));
+        let anchored = CustomizationProfile::new()
+            .with_decal(DecalConfig::new(TEST_SVG, 0.5))
+            .with_overlay(auto);
+        let report = customizer.preview_apply(&anchored);
+        assert_eq!(report.changed_layers, [LayerKind::Decal]);
+        assert_eq!(report.invalidated_caches, [LayerKind::Decal, LayerKind::Overlay]);
+    }
 }
@@ -27,10 +27,29 @@ pub enum RenderError {
         name: String,
     },
 
+    /// An asset id is not registered.
+    #[error("no asset registered as {id:?}")]
+    UnknownAsset {
+        /// The asset id that failed to resolve.
+        id: String,
+    },
+
     /// An emoji source was used but the `twemoji` feature is not enabled.
     #[error("emoji support requires the \"twemoji\" feature")]
     TwemojiNotAvailable,
 
+    /// The bundled font was requested but the `bundled-font` feature is
+    /// not enabled.
+    #[error("the bundled font requires the \"bundled-font\" feature")]
+    BundledFontNotAvailable,
+
+    /// A text font is not installed or its data could not be loaded.
+    #[error("font not available: {font}")]
+    FontNotAvailable {
+        /// The family name, or a description of the font data.
+        font: String,
+    },
+
     /// Failed to create a pixel buffer for rendering.
     #[error("failed to create render target ({width}x{height})")]
     PixmapCreation {
@@ -40,10 +59,77 @@ pub enum RenderError {
         height: u32,
     },
 
+    /// An image exceeds the configured render limits.
+    #[error("image of {width}x{height} exceeds render limits (max {max_dimension}px per side, {max_pixels} pixels)")]
+    ImageTooLarge {
+        /// Image width.
+        width: u32,
+        /// Image height.
+        height: u32,
+        /// Maximum allowed width or height.
+        max_dimension: u32,
+        /// Maximum allowed pixel count.
+        max_pixels: u64,
+    },
+
+    /// The base icon set has no images to render.
+    #[error("the base icon set is empty")]
+    EmptyIconSet,
+
     /// No base icon was found at the requested logical size.
     #[error("no base icon available for logical size {logical_size}")]
     NoBaseIcon {
         /// The logical size that was requested.
         logical_size: u32,
     },
+
+    /// An SVG file could not be read.
+    #[error("failed to read SVG file {path:?}: {source}")]
+    SvgFile {
+        /// The file that failed to read.
+        path: std::path::PathBuf,
+        /// The underlying I/O error.
+        source: std::io::Error,
+    },
+
+    /// Encoded image data could not be decoded.
+    #[error("failed to decode image: {source}")]
+    ImageDecode {
+        /// The underlying image error.
+        #[from]
+        source: image::ImageError,
+    },
+
+    /// Raster image data is not a PNG or JPEG image.
+    #[error("invalid raster image: {reason}")]
+    InvalidRaster {
+        /// What is wrong with the data.
+        reason: String,
+    },
+
+    /// The active layers cannot be expressed as SVG output.
+    #[error("cannot render as SVG: {reason}")]
+    NotVectorRepresentable {
+        /// Why vector output is not possible.
+        reason: &'static str,
+    },
+
+    /// The render's inputs can't be serialized to compute its
+    /// [`RenderId`](crate::RenderId), e.g. because a file source's path is
+    /// not valid UTF-8.
+    #[error("cannot compute render ID: {source}")]
+    Unidentifiable {
+        /// The underlying serialization error.
+        source: serde_json::Error,
+    },
+
+    /// The render's [`CancellationToken`](crate::layer::cancel::CancellationToken)
+    /// was cancelled before it finished.
+    #[error("the render was cancelled")]
+    Cancelled,
+
+    /// The render panicked on the shared render worker; see
+    /// [`FolderIconCustomizer::render_async`](crate::FolderIconCustomizer::render_async).
+    #[error("the render panicked")]
+    Panicked,
 }
@@ -0,0 +1,816 @@
+//! Packaging rendered icons as a single ZIP bundle.
+//!
+//! [`zip_bundle`] writes every size of an [`IconSet`] as a PNG, optionally
+//! an `.icns` and an `.ico` alongside, and a `manifest.json` describing the
+//! contents, so an icon theme can be stored or synced as one file.
+//!
+//! The archive is deterministic: entries are written in a fixed order with
+//! a fixed timestamp, so the same icons always produce the same bytes.
+//!
+//! Containers are written by [`Encoder`]s looked up by format name in an
+//! [`EncoderRegistry`], which holds PNG, ICNS and ICO encoders by default.
+//! Downstream crates can register their own to add platform formats, and
+//! use them on their own or in bundles via [`zip_bundle_with`].
+//!
+//! ```
+//! use folco_renderer::export::{zip_bundle, BundleLayout};
+//! use folco_renderer::{IconImage, IconSet};
+//! use image::RgbaImage;
+//!
+//! let icon = |size, scale| IconImage::new_full_content(RgbaImage::new(size, size), scale);
+//! let set = IconSet::from_images(vec![icon(16, 1.0), icon(32, 1.0), icon(64, 2.0)]);
+//!
+//! let zip = zip_bundle(&set, BundleLayout::new().with_file_stem("folder").with_ico(true)).unwrap();
+//! assert_eq!(&zip[..4], b"PK\x03\x04");
+//! ```
+
+use std::collections::BTreeSet;
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::icon::{IconImage, IconSet, TransparencyPolicy};
+use crate::render_id::{RenderId, PIXEL_PIPELINE_VERSION};
+
+/// Name of the manifest entry at the root of every bundle.
+pub const MANIFEST_FILE: &str = "manifest.json";
+
+/// Version of the manifest format, bumped on incompatible changes.
+pub const MANIFEST_VERSION: u32 = 1;
+
+/// DOS date of every entry: 1980-01-01, the earliest representable.
+const DOS_DATE: u16 = 0x0021;
+
+/// General purpose flag marking entry names as UTF-8.
+const UTF8_NAMES: u16 = 0x0800;
+
+/// Compression level of the manifest. PNGs are already compressed and are
+/// stored as-is.
+const MANIFEST_COMPRESSION_LEVEL: u8 = 9;
+
+/// Errors that can occur while building a bundle.
+#[derive(Debug, Error)]
+pub enum ExportError {
+    /// An image could not be encoded, or the set has no image an
+    /// requested container can hold.
+    #[error("failed to encode icon: {0}")]
+    Image(#[from] image::ImageError),
+
+    /// The manifest could not be serialized.
+    #[error("failed to serialize bundle manifest: {0}")]
+    Manifest(#[from] serde_json::Error),
+
+    /// No encoder is registered for a requested format.
+    #[error("no encoder registered for format {format:?}")]
+    UnknownFormat {
+        /// The format name that failed to resolve.
+        format: String,
+    },
+
+    /// A custom encoder failed.
+    #[error("encoder failed: {0}")]
+    Encoder(#[source] Box<dyn std::error::Error + Send + Sync>),
+
+    /// Two entries of a bundle would have the same name.
+    #[error("bundle would contain {name:?} twice")]
+    DuplicateEntry {
+        /// The entry name.
+        name: String,
+    },
+
+    /// The bundle exceeds the 4 GiB, 65535-entry or 65535-byte file name
+    /// limits of a ZIP without ZIP64 extensions.
+    #[error("bundle is too large for a ZIP archive")]
+    TooLarge,
+}
+
+// ============================================================================
+// Encoders
+// ============================================================================
+
+/// A file produced by an [`Encoder`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EncodedFile {
+    /// Suggested file name, such as `folder.icns`.
+    pub file_name: String,
+    /// MIME type of the contents.
+    pub mime_type: String,
+    /// The encoded bytes.
+    pub data: Vec<u8>,
+}
+
+/// Encodes an icon set into one or more files of some format.
+///
+/// ```
+/// use folco_renderer::export::{EncodedFile, Encoder, EncoderRegistry, ExportError};
+/// use folco_renderer::{IconImage, IconSet};
+/// use image::RgbaImage;
+///
+/// /// Writes the largest image as raw RGBA.
+/// struct Raw;
+///
+/// impl Encoder for Raw {
+///     fn format(&self) -> &str {
+///         "raw"
+///     }
+///
+///     fn encode(&self, icons: &IconSet, file_stem: &str) -> Result<Vec<EncodedFile>, ExportError> {
+///         let largest = icons.iter().max_by_key(|image| image.data().width());
+///         Ok(largest
+///             .map(|image| EncodedFile {
+///                 file_name: format!("{file_stem}.rgba"),
+///                 mime_type: "application/octet-stream".to_owned(),
+///                 data: image.data().as_raw().clone(),
+///             })
+///             .into_iter()
+///             .collect())
+///     }
+/// }
+///
+/// let mut encoders = EncoderRegistry::new();
+/// encoders.register(Raw);
+/// let set = IconSet::from_images(vec![IconImage::new_full_content(RgbaImage::new(16, 16), 1.0)]);
+/// let files = encoders.encode("raw", &set, "folder").unwrap();
+/// assert_eq!((files[0].file_name.as_str(), files[0].data.len()), ("folder.rgba", 1024));
+/// ```
+pub trait Encoder: Send + Sync {
+    /// Returns the name the encoder is registered under, such as `"icns"`.
+    fn format(&self) -> &str;
+
+    /// Encodes `icons`, naming the files after `file_stem`.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the set can't be encoded in this format. Custom encoders
+    /// can wrap their own errors in [`ExportError::Encoder`].
+    fn encode(&self, icons: &IconSet, file_stem: &str) -> Result<Vec<EncodedFile>, ExportError>;
+}
+
+/// Writes one PNG per slot of the set (see [`IconSet::to_pngs_by_size`]),
+/// named like `folder_16.png` and `folder_16@2x.png`. Registered as `"png"`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PngEncoder {
+    transparency: TransparencyPolicy,
+}
+
+impl PngEncoder {
+    /// Creates an encoder that keeps the alpha channel as-is.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reduces each image's partial transparency with `policy` before
+    /// encoding (see [`IconImage::apply_transparency_policy`]).
+    pub fn with_transparency(mut self, policy: TransparencyPolicy) -> Self {
+        self.transparency = policy;
+        self
+    }
+}
+
+impl Encoder for PngEncoder {
+    fn format(&self) -> &str {
+        "png"
+    }
+
+    fn encode(&self, icons: &IconSet, file_stem: &str) -> Result<Vec<EncodedFile>, ExportError> {
+        slot_images(icons)
+            .into_iter()
+            .map(|image| {
+                let (size, scale) = image.slot();
+                let data = match self.transparency {
+                    TransparencyPolicy::Preserve => image.to_png()?,
+                    policy => {
+                        let mut image = image.clone();
+                        image.apply_transparency_policy(policy);
+                        image.to_png()?
+                    }
+                };
+                Ok(EncodedFile {
+                    file_name: png_file_name(file_stem, size, scale),
+                    mime_type: "image/png".to_owned(),
+                    data,
+                })
+            })
+            .collect()
+    }
+}
+
+/// Writes a macOS `.icns` (see [`IconSet::to_icns`]). Registered as
+/// `"icns"`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct IcnsEncoder {
+    transparency: TransparencyPolicy,
+}
+
+impl IcnsEncoder {
+    /// Creates an encoder that keeps the alpha channel as-is.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reduces each image's partial transparency with `policy` before
+    /// encoding (see [`IconSet::to_icns_with_transparency`]).
+    pub fn with_transparency(mut self, policy: TransparencyPolicy) -> Self {
+        self.transparency = policy;
+        self
+    }
+}
+
+impl Encoder for IcnsEncoder {
+    fn format(&self) -> &str {
+        "icns"
+    }
+
+    fn encode(&self, icons: &IconSet, file_stem: &str) -> Result<Vec<EncodedFile>, ExportError> {
+        Ok(vec![EncodedFile {
+            file_name: format!("{file_stem}.icns"),
+            mime_type: "image/icns".to_owned(),
+            data: icons.to_icns_with_transparency(self.transparency)?,
+        }])
+    }
+}
+
+/// Writes a Windows `.ico` (see [`IconSet::to_ico`]). Registered as
+/// `"ico"`.
+///
+/// ```
+/// use folco_renderer::export::{EncoderRegistry, IcoEncoder};
+/// use folco_renderer::{IconImage, IconSet, TransparencyPolicy};
+/// use image::RgbaImage;
+///
+/// // Legacy targets only see the BMP entries' 1-bit masks
+/// let mut encoders = EncoderRegistry::new();
+/// encoders.register(IcoEncoder::new().with_transparency(TransparencyPolicy::Threshold { cutoff: 128 }));
+///
+/// let set = IconSet::from_images(vec![IconImage::new_full_content(RgbaImage::new(16, 16), 1.0)]);
+/// let files = encoders.encode("ico", &set, "folder").unwrap();
+/// assert_eq!(files[0].file_name, "folder.ico");
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct IcoEncoder {
+    transparency: TransparencyPolicy,
+}
+
+impl IcoEncoder {
+    /// Creates an encoder that keeps the alpha channel as-is.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reduces each entry's partial transparency with `policy` before
+    /// encoding (see [`IconSet::to_ico_with_transparency`]).
+    pub fn with_transparency(mut self, policy: TransparencyPolicy) -> Self {
+        self.transparency = policy;
+        self
+    }
+}
+
+impl Encoder for IcoEncoder {
+    fn format(&self) -> &str {
+        "ico"
+    }
+
+    fn encode(&self, icons: &IconSet, file_stem: &str) -> Result<Vec<EncodedFile>, ExportError> {
+        Ok(vec![EncodedFile {
+            file_name: format!("{file_stem}.ico"),
+            mime_type: "image/vnd.microsoft.icon".to_owned(),
+            data: icons.to_ico_with_transparency(self.transparency)?,
+        }])
+    }
+}
+
+/// Encoders by format name.
+///
+/// [`new`](Self::new) starts with [`PngEncoder`], [`IcnsEncoder`] and
+/// [`IcoEncoder`]; registering an encoder under a taken name replaces the
+/// existing one.
+#[derive(Clone)]
+pub struct EncoderRegistry {
+    encoders: Vec<Arc<dyn Encoder>>,
+}
+
+impl Default for EncoderRegistry {
+    fn default() -> Self {
+        let mut registry = Self::empty();
+        registry.register(PngEncoder::new());
+        registry.register(IcnsEncoder::new());
+        registry.register(IcoEncoder::new());
+        registry
+    }
+}
+
+impl std::fmt::Debug for EncoderRegistry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list().entries(self.formats()).finish()
+    }
+}
+
+impl EncoderRegistry {
+    /// Creates a registry holding the built-in encoders.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a registry with no encoders.
+    pub fn empty() -> Self {
+        Self { encoders: Vec::new() }
+    }
+
+    /// Registers `encoder` under its [`format`](Encoder::format), returning
+    /// the encoder it replaces, if any.
+    pub fn register(&mut self, encoder: impl Encoder + 'static) -> Option<Arc<dyn Encoder>> {
+        let encoder: Arc<dyn Encoder> = Arc::new(encoder);
+        match self.encoders.iter_mut().find(|e| e.format() == encoder.format()) {
+            Some(existing) => Some(std::mem::replace(existing, encoder)),
+            None => {
+                self.encoders.push(encoder);
+                None
+            }
+        }
+    }
+
+    /// Returns the encoder registered for `format`.
+    pub fn get(&self, format: &str) -> Option<&dyn Encoder> {
+        self.encoders.iter().find(|e| e.format() == format).map(|e| &**e)
+    }
+
+    /// Returns the registered format names, in registration order.
+    pub fn formats(&self) -> impl Iterator<Item = &str> {
+        self.encoders.iter().map(|e| e.format())
+    }
+
+    /// Encodes `icons` with the encoder registered for `format`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ExportError::UnknownFormat`] if no encoder is registered
+    /// for `format`, or the encoder's error.
+    pub fn encode(&self, format: &str, icons: &IconSet, file_stem: &str) -> Result<Vec<EncodedFile>, ExportError> {
+        self.get(format)
+            .ok_or_else(|| ExportError::UnknownFormat { format: format.to_owned() })?
+            .encode(icons, file_stem)
+    }
+}
+
+/// Returns the first image of each slot, by ascending slot.
+fn slot_images(icons: &IconSet) -> Vec<&IconImage> {
+    let mut images: Vec<_> = icons.iter().collect();
+    // Stable, so the first image of a slot wins as in `to_pngs_by_size`
+    images.sort_by_key(|image| image.slot());
+    images.dedup_by_key(|image| image.slot());
+    images
+}
+
+/// Returns the PNG file name for a logical size and scale.
+fn png_file_name(file_stem: &str, size: u32, scale: u32) -> String {
+    match scale {
+        1 => format!("{file_stem}_{size}.png"),
+        _ => format!("{file_stem}_{size}@{scale}x.png"),
+    }
+}
+
+// ============================================================================
+// BundleLayout
+// ============================================================================
+
+/// What goes into a bundle and where, for [`zip_bundle`].
+///
+/// By default, the bundle holds the PNGs under `png/` as `icon_16.png`,
+/// `icon_32@2x.png` and so on, and the manifest. Other formats are written
+/// at the root, in the order they were added.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BundleLayout {
+    png_dir: String,
+    file_stem: String,
+    formats: Vec<String>,
+    fingerprint: Option<RenderId>,
+}
+
+impl Default for BundleLayout {
+    fn default() -> Self {
+        Self {
+            png_dir: "png".to_owned(),
+            file_stem: "icon".to_owned(),
+            formats: Vec::new(),
+            fingerprint: None,
+        }
+    }
+}
+
+impl BundleLayout {
+    /// Creates the default layout.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the directory the PNGs are written under. An empty name puts
+    /// them at the root.
+    pub fn with_png_dir(mut self, dir: impl Into<String>) -> Self {
+        self.png_dir = dir.into().trim_matches('/').to_owned();
+        self
+    }
+
+    /// Sets the name every file starts with, before the size and extension.
+    pub fn with_file_stem(mut self, stem: impl Into<String>) -> Self {
+        self.file_stem = stem.into();
+        self
+    }
+
+    /// Sets whether to include a macOS `.icns` (see [`IconSet::to_icns`]).
+    pub fn with_icns(self, icns: bool) -> Self {
+        self.with_format_enabled("icns", icns)
+    }
+
+    /// Sets whether to include a Windows `.ico` (see [`IconSet::to_ico`]).
+    pub fn with_ico(self, ico: bool) -> Self {
+        self.with_format_enabled("ico", ico)
+    }
+
+    /// Adds the output of the encoder registered for `format`, as passed
+    /// to [`zip_bundle_with`].
+    pub fn with_format(self, format: impl Into<String>) -> Self {
+        self.with_format_enabled(format, true)
+    }
+
+    fn with_format_enabled(mut self, format: impl Into<String>, enabled: bool) -> Self {
+        let format = format.into();
+        match (self.formats.contains(&format), enabled) {
+            (false, true) => self.formats.push(format),
+            (true, false) => self.formats.retain(|f| *f != format),
+            _ => {}
+        }
+        self
+    }
+
+    /// Records the fingerprint of the render that produced the icons in
+    /// the manifest, typically [`FolderIconCustomizer::render_id`] at the
+    /// largest size.
+    ///
+    /// [`FolderIconCustomizer::render_id`]: crate::FolderIconCustomizer::render_id
+    pub fn with_fingerprint(mut self, fingerprint: RenderId) -> Self {
+        self.fingerprint = Some(fingerprint);
+        self
+    }
+
+    /// Returns the entry name of the PNG for a logical size and scale.
+    fn png_name(&self, size: u32, scale: u32) -> String {
+        let file = png_file_name(&self.file_stem, size, scale);
+        match self.png_dir.as_str() {
+            "" => file,
+            dir => format!("{dir}/{file}"),
+        }
+    }
+}
+
+// ============================================================================
+// Manifest
+// ============================================================================
+
+/// The `manifest.json` of a bundle.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "jsonschema", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "tsify", derive(tsify_next::Tsify))]
+#[cfg_attr(feature = "tsify", tsify(into_wasm_abi, from_wasm_abi))]
+#[serde(rename_all = "camelCase")]
+pub struct BundleManifest {
+    /// Manifest format version, [`MANIFEST_VERSION`] when written.
+    pub version: u32,
+    /// The [`PIXEL_PIPELINE_VERSION`] of the renderer that wrote the bundle.
+    pub pixel_pipeline_version: u32,
+    /// Fingerprint of the render the icons came from, if recorded.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "jsonschema", schemars(with = "Option<String>"))]
+    #[cfg_attr(feature = "tsify", tsify(type = "string", optional))]
+    pub fingerprint: Option<RenderId>,
+    /// The PNG entries, by ascending logical size and scale.
+    pub images: Vec<BundleImage>,
+    /// The entries written by other encoders, such as the `.icns`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub files: Vec<BundleFile>,
+}
+
+/// One PNG entry of a [`BundleManifest`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "jsonschema", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "tsify", derive(tsify_next::Tsify))]
+#[cfg_attr(feature = "tsify", tsify(into_wasm_abi, from_wasm_abi))]
+#[serde(rename_all = "camelCase")]
+pub struct BundleImage {
+    /// Entry name within the archive.
+    pub file: String,
+    /// Width in pixels.
+    pub width: u32,
+    /// Height in pixels.
+    pub height: u32,
+    /// Display scale factor.
+    pub scale: f32,
+    /// Logical size, rounded to whole points.
+    pub logical_size: u32,
+}
+
+/// One entry of a [`BundleManifest`] written by an [`Encoder`] other than
+/// the PNGs.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "jsonschema", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "tsify", derive(tsify_next::Tsify))]
+#[cfg_attr(feature = "tsify", tsify(into_wasm_abi, from_wasm_abi))]
+#[serde(rename_all = "camelCase")]
+pub struct BundleFile {
+    /// Entry name within the archive.
+    pub file: String,
+    /// Format name of the encoder that wrote it.
+    pub format: String,
+    /// MIME type of the contents.
+    pub mime_type: String,
+}
+
+// ============================================================================
+// Bundling
+// ============================================================================
+
+/// Packages `icons` as a ZIP archive laid out by `layout`.
+///
+/// Every slot of the set (see [`IconSet::to_pngs_by_size`]) is written as
+/// a PNG carrying its scale, followed by the requested containers and the
+/// manifest.
+///
+/// # Errors
+///
+/// Fails if an image can't be encoded, if a requested container can't hold
+/// any image of the set, or if the archive would be too large.
+pub fn zip_bundle(icons: &IconSet, layout: BundleLayout) -> Result<Vec<u8>, ExportError> {
+    zip_bundle_with(icons, layout, &EncoderRegistry::new())
+}
+
+/// Like [`zip_bundle`], looking up the layout's formats in `encoders`.
+///
+/// # Errors
+///
+/// Also fails with [`ExportError::UnknownFormat`] if a format isn't
+/// registered, or if two entries would share a name.
+pub fn zip_bundle_with(icons: &IconSet, layout: BundleLayout, encoders: &EncoderRegistry) -> Result<Vec<u8>, ExportError> {
+    let mut zip = ZipWriter::default();
+    let mut manifest = BundleManifest {
+        version: MANIFEST_VERSION,
+        pixel_pipeline_version: PIXEL_PIPELINE_VERSION,
+        fingerprint: layout.fingerprint,
+        images: Vec::new(),
+        files: Vec::new(),
+    };
+
+    for image in slot_images(icons) {
+        let (size, scale) = image.slot();
+        let file = layout.png_name(size, scale);
+        zip.add(&file, &image.to_png()?, false)?;
+        manifest.images.push(BundleImage {
+            file,
+            width: image.data().width(),
+            height: image.data().height(),
+            scale: image.scale,
+            logical_size: size,
+        });
+    }
+
+    for format in &layout.formats {
+        for encoded in encoders.encode(format, icons, &layout.file_stem)? {
+            zip.add(&encoded.file_name, &encoded.data, false)?;
+            manifest.files.push(BundleFile {
+                file: encoded.file_name,
+                format: format.clone(),
+                mime_type: encoded.mime_type,
+            });
+        }
+    }
+
+    zip.add(MANIFEST_FILE, &serde_json::to_vec_pretty(&manifest)?, true)?;
+    zip.finish()
+}
+
+// ============================================================================
+// ZIP writing
+// ============================================================================
+
+/// A central directory record, kept until the archive is finished.
+struct ZipEntry {
+    name: String,
+    name_len: u16,
+    method: u16,
+    crc: u32,
+    compressed_len: u32,
+    len: u32,
+    offset: u32,
+}
+
+/// Minimal writer for single-disk ZIP archives without ZIP64, storing or
+/// deflating each entry.
+#[derive(Default)]
+struct ZipWriter {
+    out: Vec<u8>,
+    entries: Vec<ZipEntry>,
+    names: BTreeSet<String>,
+}
+
+impl ZipWriter {
+    fn add(&mut self, name: &str, data: &[u8], deflate: bool) -> Result<(), ExportError> {
+        if !self.names.insert(name.to_owned()) {
+            return Err(ExportError::DuplicateEntry { name: name.to_owned() });
+        }
+        let (method, body) = if deflate {
+            (8, miniz_oxide::deflate::compress_to_vec(data, MANIFEST_COMPRESSION_LEVEL))
+        } else {
+            (0, data.to_vec())
+        };
+        let entry = ZipEntry {
+            name: name.to_owned(),
+            name_len: u16::try_from(name.len()).map_err(|_| ExportError::TooLarge)?,
+            method,
+            crc: crc32fast::hash(data),
+            compressed_len: u32::try_from(body.len()).map_err(|_| ExportError::TooLarge)?,
+            len: u32::try_from(data.len()).map_err(|_| ExportError::TooLarge)?,
+            offset: u32::try_from(self.out.len()).map_err(|_| ExportError::TooLarge)?,
+        };
+
+        self.out.extend_from_slice(&0x0403_4b50u32.to_le_bytes());
+        self.put_u16(20); // version needed: 2.0, for deflate
+        self.put_common(&entry);
+        self.out.extend_from_slice(name.as_bytes());
+        self.out.extend_from_slice(&body);
+        self.entries.push(entry);
+        Ok(())
+    }
+
+    fn finish(mut self) -> Result<Vec<u8>, ExportError> {
+        let count = u16::try_from(self.entries.len()).map_err(|_| ExportError::TooLarge)?;
+        let directory_start = self.out.len();
+        for entry in std::mem::take(&mut self.entries) {
+            self.out.extend_from_slice(&0x0201_4b50u32.to_le_bytes());
+            self.put_u16(20); // made by: MS-DOS, 2.0
+            self.put_u16(20);
+            self.put_common(&entry);
+            for _ in 0..3 {
+                self.put_u16(0); // comment length, start disk, internal attributes
+            }
+            self.out.extend_from_slice(&0u32.to_le_bytes()); // external attributes
+            self.out.extend_from_slice(&entry.offset.to_le_bytes());
+            self.out.extend_from_slice(entry.name.as_bytes());
+        }
+        let directory_len = u32::try_from(self.out.len() - directory_start).map_err(|_| ExportError::TooLarge)?;
+        let directory_start = u32::try_from(directory_start).map_err(|_| ExportError::TooLarge)?;
+
+        self.out.extend_from_slice(&0x0605_4b50u32.to_le_bytes());
+        for value in [0, 0, count, count] {
+            self.put_u16(value);
+        }
+        self.out.extend_from_slice(&directory_len.to_le_bytes());
+        self.out.extend_from_slice(&directory_start.to_le_bytes());
+        self.put_u16(0); // comment length
+        Ok(self.out)
+    }
+
+    /// Writes the fields local headers and central directory records share,
+    /// from the flags to the extra field length.
+    fn put_common(&mut self, entry: &ZipEntry) {
+        self.put_u16(UTF8_NAMES);
+        self.put_u16(entry.method);
+        self.put_u16(0); // time: midnight
+        self.put_u16(DOS_DATE);
+        for value in [entry.crc, entry.compressed_len, entry.len] {
+            self.out.extend_from_slice(&value.to_le_bytes());
+        }
+        self.put_u16(entry.name_len);
+        self.put_u16(0); // extra field length
+    }
+
+    fn put_u16(&mut self, value: u16) {
+        self.out.extend_from_slice(&value.to_le_bytes());
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::icon::IconImage;
+    use image::{Rgba, RgbaImage};
+
+    /// Reads `(name, data)` pairs back through the central directory.
+    fn read_entries(zip: &[u8]) -> Vec<(String, Vec<u8>)> {
+        let u16_at = |at: usize| u16::from_le_bytes([zip[at], zip[at + 1]]) as usize;
+        let u32_at = |at: usize| u32::from_le_bytes(zip[at..at + 4].try_into().unwrap()) as usize;
+        let eocd = zip.len() - 22;
+        assert_eq!(u32_at(eocd), 0x0605_4b50);
+        let mut record = u32_at(eocd + 16);
+        (0..u16_at(eocd + 10))
+            .map(|_| {
+                assert_eq!(u32_at(record), 0x0201_4b50);
+                let (method, crc) = (u16_at(record + 10), u32_at(record + 16) as u32);
+                let (compressed_len, name_len) = (u32_at(record + 20), u16_at(record + 28));
+                let name = String::from_utf8(zip[record + 46..record + 46 + name_len].to_vec()).unwrap();
+                let local = u32_at(record + 42);
+                let start = local + 30 + u16_at(local + 26) + u16_at(local + 28);
+                let body = &zip[start..start + compressed_len];
+                let data = match method {
+                    0 => body.to_vec(),
+                    _ => miniz_oxide::inflate::decompress_to_vec(body).unwrap(),
+                };
+                assert_eq!(crc32fast::hash(&data), crc, "{name}");
+                record += 46 + name_len;
+                (name, data)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn bundle_round_trips() {
+        let icon = |size, scale| IconImage::new_full_content(RgbaImage::from_pixel(size, size, Rgba([33, 150, 243, 255])), scale);
+        let set = IconSet::from_images(vec![icon(64, 2.0), icon(16, 1.0), icon(32, 1.0), icon(32, 2.0)]);
+        let fingerprint: RenderId = "00000000000000000000000000c0ffee".parse().unwrap();
+        let layout = BundleLayout::new().with_file_stem("folder").with_icns(true).with_ico(true).with_fingerprint(fingerprint);
+
+        let zip = zip_bundle(&set, layout.clone()).unwrap();
+        assert_eq!(zip, zip_bundle(&set, layout).unwrap());
+        let entries = read_entries(&zip);
+        let names: Vec<&str> = entries.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(
+            names,
+            ["png/folder_16.png", "png/folder_16@2x.png", "png/folder_32.png", "png/folder_32@2x.png", "folder.icns", "folder.ico", MANIFEST_FILE]
+        );
+
+        let reloaded = IconSet::from_image_bytes(&entries[3].1).unwrap();
+        assert_eq!((reloaded[0].data().width(), reloaded[0].scale), (64, 2.0));
+        assert!(IconSet::from_ico(&entries[5].1).is_ok());
+
+        let manifest: BundleManifest = serde_json::from_slice(&entries[6].1).unwrap();
+        assert_eq!(manifest.fingerprint, Some(fingerprint));
+        assert_eq!(manifest.pixel_pipeline_version, PIXEL_PIPELINE_VERSION);
+        let files: Vec<(&str, &str)> = manifest.files.iter().map(|f| (f.file.as_str(), f.mime_type.as_str())).collect();
+        assert_eq!(files, [("folder.icns", "image/icns"), ("folder.ico", "image/vnd.microsoft.icon")]);
+        let sizes: Vec<(u32, u32, f32)> = manifest.images.iter().map(|i| (i.logical_size, i.width, i.scale)).collect();
+        assert_eq!(sizes, [(16, 16, 1.0), (16, 32, 2.0), (32, 32, 1.0), (32, 64, 2.0)]);
+    }
+
+    /// Writes a one-line text file listing the set's pixel sizes.
+    struct Sizes;
+
+    impl Encoder for Sizes {
+        fn format(&self) -> &str {
+            "ico"
+        }
+
+        fn encode(&self, icons: &IconSet, file_stem: &str) -> Result<Vec<EncodedFile>, ExportError> {
+            let sizes: Vec<String> = icons.iter().map(|image| image.data().width().to_string()).collect();
+            Ok(vec![EncodedFile {
+                file_name: format!("{file_stem}.txt"),
+                mime_type: "text/plain".to_owned(),
+                data: sizes.join(",").into_bytes(),
+            }])
+        }
+    }
+
+    #[test]
+    fn custom_encoders_replace_and_extend() {
+        let set = IconSet::from_images(vec![IconImage::new_full_content(RgbaImage::new(16, 16), 1.0)]);
+        let mut encoders = EncoderRegistry::new();
+        assert_eq!(encoders.register(Sizes).map(|e| e.format().to_owned()).as_deref(), Some("ico"));
+        assert_eq!(encoders.formats().collect::<Vec<_>>(), ["png", "icns", "ico"]);
+
+        let zip = zip_bundle_with(&set, BundleLayout::new().with_ico(true), &encoders).unwrap();
+        let entries = read_entries(&zip);
+        assert_eq!(entries[1], ("icon.txt".to_owned(), b"16".to_vec()));
+
+        let unknown = zip_bundle_with(&set, BundleLayout::new().with_format("webp"), &encoders);
+        assert!(matches!(unknown, Err(ExportError::UnknownFormat { format }) if format == "webp"));
+        let clash = zip_bundle_with(&set, BundleLayout::new().with_png_dir("").with_format("png"), &encoders);
+        assert!(matches!(clash, Err(ExportError::DuplicateEntry { .. })));
+    }
+
+    #[test]
+    fn overlong_entry_names_are_rejected() {
+        let mut zip = ZipWriter::default();
+        assert!(matches!(zip.add(&"a".repeat(70_000), b"", false), Err(ExportError::TooLarge)));
+        assert!(zip.add(&"a".repeat(u16::MAX as usize), b"", false).is_ok());
+    }
+
+    #[test]
+    fn encoders_apply_the_transparency_policy() {
+        let mut image = RgbaImage::from_pixel(16, 16, Rgba([200, 100, 50, 255]));
+        image.put_pixel(0, 0, Rgba([10, 20, 30, 100]));
+        let set = IconSet::from_images(vec![IconImage::new_full_content(image, 1.0)]);
+        let policy = TransparencyPolicy::Matte { r: 0, g: 0, b: 0 };
+        let corner = |bytes: &[u8]| *IconSet::from_image_bytes(bytes).unwrap().get(0).unwrap().data().get_pixel(0, 0);
+
+        let png = PngEncoder::new().with_transparency(policy).encode(&set, "folder").unwrap();
+        assert_eq!(corner(&png[0].data)[3], 255);
+        let icns = IcnsEncoder::new().with_transparency(policy).encode(&set, "folder").unwrap();
+        assert_eq!(corner(&icns[0].data)[3], 255);
+        let ico = IcoEncoder::new().with_transparency(policy).encode(&set, "folder").unwrap();
+        assert_eq!(corner(&ico[0].data)[3], 255);
+
+        let preserved = PngEncoder::new().encode(&set, "folder").unwrap();
+        assert_eq!(corner(&preserved[0].data)[3], 100);
+    }
+}
@@ -0,0 +1,211 @@
+//! Icon extraction from application bundles and executables.
+//!
+//! Only available when the `extract` feature is enabled.
+//!
+//! - [`from_app_bundle`] reads the ICNS file a macOS `.app` bundle names
+//!   in its `Info.plist`.
+//! - [`from_pe`] reads the icon groups (`RT_GROUP_ICON`) embedded in a
+//!   Windows `.exe` or `.dll`, and [`from_pe_group`] a single one.
+//!
+//! Both parse files directly, so they work on any host OS — a Linux build
+//! server can extract icons from Windows binaries and macOS bundles alike.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use folco_renderer::extract;
+//!
+//! let icons = extract::from_app_bundle("/Applications/Safari.app").unwrap();
+//! let notepad = extract::from_pe(r"C:\Windows\notepad.exe").unwrap();
+//! ```
+
+use std::path::{Path, PathBuf};
+
+use image::ImageError;
+use pelite::resources::Name;
+use serde::Deserialize;
+use thiserror::Error;
+
+use crate::icns::read_icns;
+use crate::ico::decode_entry;
+use crate::icon::{IconImage, IconSet};
+use crate::layer::RenderLimits;
+
+/// Errors that can occur while extracting icons.
+#[derive(Debug, Error)]
+pub enum ExtractError {
+    /// The file or bundle could not be read.
+    #[error("failed to read {path}: {source}")]
+    Io {
+        /// The path that failed to read.
+        path: PathBuf,
+        /// The underlying I/O error.
+        source: std::io::Error,
+    },
+
+    /// The bundle's `Info.plist` could not be parsed.
+    #[error("invalid Info.plist: {0}")]
+    Plist(#[from] plist::Error),
+
+    /// The bundle does not declare an icon file.
+    ///
+    /// Bundles that only ship an asset catalog (`Assets.car`) fall here;
+    /// the catalog format is undocumented.
+    #[error("bundle declares no CFBundleIconFile")]
+    NoBundleIcon,
+
+    /// The file is not a valid PE image.
+    #[error("invalid PE file: {0}")]
+    Pe(#[from] pelite::Error),
+
+    /// A PE resource could not be located or read.
+    #[error("invalid PE resource: {0}")]
+    Resource(#[from] pelite::resources::FindError),
+
+    /// The file contains no icons.
+    #[error("no icons found")]
+    NoIcons,
+
+    /// An embedded icon image could not be decoded.
+    #[error("failed to decode icon: {0}")]
+    Image(#[from] ImageError),
+}
+
+/// The subset of `Info.plist` needed to locate the bundle icon.
+#[derive(Deserialize)]
+struct InfoPlist {
+    #[serde(rename = "CFBundleIconFile")]
+    icon_file: Option<String>,
+}
+
+/// Extracts the icon of a macOS `.app` bundle.
+///
+/// Reads `CFBundleIconFile` from `Contents/Info.plist` (XML or binary)
+/// and decodes the named file in `Contents/Resources`, appending `.icns`
+/// when the name has no extension, as Finder does.
+///
+/// # Errors
+///
+/// Returns [`ExtractError::NoBundleIcon`] if the bundle declares no icon
+/// file, or an error if the plist or ICNS file can't be read.
+pub fn from_app_bundle(path: impl AsRef<Path>) -> Result<IconSet, ExtractError> {
+    let contents = path.as_ref().join("Contents");
+    let plist_path = contents.join("Info.plist");
+    let info: InfoPlist = plist::from_bytes(&read(&plist_path)?)?;
+
+    let mut icon_path = contents.join("Resources").join(info.icon_file.ok_or(ExtractError::NoBundleIcon)?);
+    if icon_path.extension().is_none() {
+        icon_path.set_extension("icns");
+    }
+    Ok(read_icns(&read(&icon_path)?, RenderLimits::default())?)
+}
+
+/// Extracts the icons embedded in a Windows `.exe` or `.dll`.
+///
+/// Every image of every icon group is decoded, in resource order, at
+/// scale 1.0. Duplicate sizes across groups are kept; use
+/// [`IconSet::sort_canonical`] and filter as needed.
+///
+/// # Errors
+///
+/// Returns [`ExtractError::NoIcons`] if the file has no icon resources,
+/// or an error if the file is not a valid PE image.
+pub fn from_pe(path: impl AsRef<Path>) -> Result<IconSet, ExtractError> {
+    from_pe_bytes(&read(path.as_ref())?)
+}
+
+/// Like [`from_pe`], for a PE image already in memory.
+pub fn from_pe_bytes(bytes: &[u8]) -> Result<IconSet, ExtractError> {
+    read_pe_groups(bytes, |_| true)
+}
+
+/// Extracts the icon group with resource id `id` from a Windows `.exe` or
+/// `.dll`, the icon a shell location like `imageres.dll,-3` names.
+///
+/// # Errors
+///
+/// Returns [`ExtractError::NoIcons`] if the file has no such group, or an
+/// error if the file is not a valid PE image.
+pub fn from_pe_group(path: impl AsRef<Path>, id: u32) -> Result<IconSet, ExtractError> {
+    from_pe_group_bytes(&read(path.as_ref())?, id)
+}
+
+/// Like [`from_pe_group`], for a PE image already in memory.
+pub fn from_pe_group_bytes(bytes: &[u8], id: u32) -> Result<IconSet, ExtractError> {
+    read_pe_groups(bytes, |name| *name == Name::Id(id))
+}
+
+/// Decodes every image of the icon groups whose name passes `wanted`.
+fn read_pe_groups(bytes: &[u8], wanted: impl Fn(&Name) -> bool) -> Result<IconSet, ExtractError> {
+    let resources = pelite::PeFile::from_bytes(bytes)?.resources()?;
+    let mut icons = IconSet::new();
+    for group in resources.icons() {
+        let (name, group) = group?;
+        if !wanted(&name) {
+            continue;
+        }
+        for entry in group.entries() {
+            let data = group.image(entry.nId)?;
+            icons.add_image(IconImage::new_full_content(decode_entry(data, entry.bWidth, entry.bHeight)?, 1.0));
+        }
+    }
+
+    if icons.is_empty() {
+        return Err(ExtractError::NoIcons);
+    }
+    Ok(icons)
+}
+
+fn read(path: &Path) -> Result<Vec<u8>, ExtractError> {
+    std::fs::read(path).map_err(|source| ExtractError::Io {
+        path: path.to_path_buf(),
+        source,
+    })
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use image::{ImageFormat, RgbaImage};
+
+    use super::*;
+
+    #[test]
+    fn bundle_reads_declared_icns() {
+        let bundle = std::env::temp_dir().join(format!("folco-extract-{}.app", std::process::id()));
+        let resources = bundle.join("Contents/Resources");
+        std::fs::create_dir_all(&resources).unwrap();
+        std::fs::write(
+            bundle.join("Contents/Info.plist"),
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<plist version="1.0"><dict><key>CFBundleIconFile</key><string>AppIcon</string></dict></plist>"#,
+        )
+        .unwrap();
+
+        let mut png = Vec::new();
+        RgbaImage::new(16, 16)
+            .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+            .unwrap();
+        let mut icns = b"icns".to_vec();
+        icns.extend_from_slice(&((png.len() + 16) as u32).to_be_bytes());
+        icns.extend_from_slice(b"icp4");
+        icns.extend_from_slice(&((png.len() + 8) as u32).to_be_bytes());
+        icns.extend(png);
+        std::fs::write(resources.join("AppIcon.icns"), icns).unwrap();
+
+        let icons = from_app_bundle(&bundle);
+        std::fs::remove_dir_all(&bundle).unwrap();
+        assert_eq!(icons.unwrap().len(), 1);
+    }
+
+    #[test]
+    fn pe_rejects_non_pe_input() {
+        assert!(matches!(from_pe_bytes(b"not a PE file"), Err(ExtractError::Pe(_))));
+        assert!(matches!(from_pe_group_bytes(b"not a PE file", 3), Err(ExtractError::Pe(_))));
+    }
+}
@@ -257,6 +257,7 @@ impl std::fmt::Display for FolderColor {
 impl std::str::FromStr for FolderColor {
     type Err = String;
 
+    #[allow(clippy::collapsible_str_replace)]
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s.to_lowercase().replace(' ', "").replace('-', "").replace('_', "").as_str() {
             "red" => Ok(FolderColor::Red),
@@ -0,0 +1,342 @@
+//! Apple Icon Image (`.icns`) reading and writing.
+//!
+//! An ICNS file is a big-endian container of typed entries. Modern icons
+//! store each size as an embedded PNG; older ones use run-length encoded
+//! RGB planes with a separate 8-bit mask entry, or RLE-encoded ARGB. This
+//! reader decodes all three and skips JPEG 2000 and 1-bit entries. The
+//! writer always emits PNG entries.
+
+use std::io::Cursor;
+
+use image::error::{DecodingError, EncodingError, ImageFormatHint};
+use image::{ImageError, ImageFormat, RgbaImage};
+
+use crate::icon::{decode_with_limits, limit_error, IconImage, IconSet, TransparencyPolicy};
+use crate::layer::RenderLimits;
+
+/// File magic at the start of every ICNS container.
+pub(crate) const MAGIC: &[u8; 4] = b"icns";
+
+/// Size of the type + length header preceding each entry and the file.
+const HEADER_LEN: usize = 8;
+
+/// PNG file signature, used to tell PNG entries from JPEG 2000 ones.
+const PNG_SIGNATURE: &[u8; 8] = b"\x89PNG\r\n\x1a\n";
+
+/// Prefix of RLE-encoded ARGB entry data.
+const ARGB_SIGNATURE: &[u8; 4] = b"ARGB";
+
+/// Entry types that hold @2x representations; all others are 1x.
+const RETINA_TYPES: [&[u8; 4]; 5] = [b"ic10", b"ic11", b"ic12", b"ic13", b"ic14"];
+
+/// Legacy RLE RGB entry types with their edge length and mask entry type.
+const RGB_TYPES: [(&[u8; 4], u32, &[u8; 4]); 4] = [
+    (b"is32", 16, b"s8mk"),
+    (b"il32", 32, b"l8mk"),
+    (b"ih32", 48, b"h8mk"),
+    (b"it32", 128, b"t8mk"),
+];
+
+/// PNG entry types written for each (pixel size, scale) slot.
+const PNG_TYPES: [(u32, f32, &[u8; 4]); 11] = [
+    (16, 1.0, b"icp4"),
+    (32, 1.0, b"icp5"),
+    (64, 1.0, b"icp6"),
+    (128, 1.0, b"ic07"),
+    (256, 1.0, b"ic08"),
+    (512, 1.0, b"ic09"),
+    (32, 2.0, b"ic11"),
+    (64, 2.0, b"ic12"),
+    (256, 2.0, b"ic13"),
+    (512, 2.0, b"ic14"),
+    (1024, 2.0, b"ic10"),
+];
+
+/// Entry types that may hold RLE ARGB data, with their edge length.
+const ARGB_TYPES: [(&[u8; 4], u32); 3] = [(b"ic04", 16), (b"ic05", 32), (b"icsb", 18)];
+
+/// Decodes every PNG, RLE RGB and ARGB representation in an ICNS file.
+///
+/// When a legacy entry has the same size as a PNG one, the PNG wins.
+/// Images are returned in canonical order. Entries larger than `limits`
+/// are rejected. Fails if the container is malformed or holds no
+/// supported representations.
+pub(crate) fn read_icns(bytes: &[u8], limits: RenderLimits) -> Result<IconSet, ImageError> {
+    let (magic, total) = header(bytes, 0).ok_or_else(|| malformed("truncated header"))?;
+    if magic != MAGIC || total < HEADER_LEN || total > bytes.len() {
+        return Err(malformed("not an ICNS file"));
+    }
+
+    let mut entries = Vec::new();
+    let mut offset = HEADER_LEN;
+    while offset < total {
+        let (kind, len) = header(bytes, offset).ok_or_else(|| malformed("truncated entry header"))?;
+        if len < HEADER_LEN || offset + len > total {
+            return Err(malformed("entry length out of bounds"));
+        }
+        entries.push((kind, &bytes[offset + HEADER_LEN..offset + len]));
+        offset += len;
+    }
+
+    let mut icons = IconSet::new();
+    for &(kind, data) in &entries {
+        if data.starts_with(PNG_SIGNATURE) {
+            let scale = if RETINA_TYPES.contains(&kind) { 2.0 } else { 1.0 };
+            icons.add_image(IconImage::new_full_content(decode_with_limits(data, limits)?, scale));
+        }
+    }
+
+    // Legacy entries only fill sizes no PNG covers
+    let mut legacy = Vec::new();
+    for &(kind, data) in &entries {
+        let image = if let Some(&(_, size, mask_kind)) = RGB_TYPES.iter().find(|(t, ..)| *t == kind) {
+            let mask = entries.iter().find(|(t, _)| *t == mask_kind).map(|(_, mask)| *mask);
+            decode_rgb(kind, data, mask, size)?
+        } else if let Some(&(_, size)) = ARGB_TYPES.iter().find(|(t, _)| *t == kind)
+            && let Some(data) = data.strip_prefix(ARGB_SIGNATURE)
+        {
+            decode_argb(data, size)?
+        } else {
+            continue;
+        };
+        limits.check(image.width(), image.height()).map_err(|_| limit_error())?;
+        legacy.push(image);
+    }
+    for image in legacy {
+        if !icons.iter().any(|icon| icon.scale == 1.0 && icon.data().width() == image.width()) {
+            icons.add_image(IconImage::new_full_content(image, 1.0));
+        }
+    }
+
+    if icons.is_empty() {
+        return Err(malformed("no supported representations"));
+    }
+    icons.sort_canonical();
+    Ok(icons)
+}
+
+/// Decodes a legacy RGB entry, taking alpha from its mask entry if present.
+fn decode_rgb(kind: &[u8; 4], data: &[u8], mask: Option<&[u8]>, size: u32) -> Result<RgbaImage, ImageError> {
+    let pixels = (size * size) as usize;
+    // `it32` data starts with four zero bytes
+    let data = if kind == b"it32" { data.get(4..).unwrap_or_default() } else { data };
+    let planes = if data.len() == pixels * 3 {
+        // Small entries are sometimes stored uncompressed, interleaved
+        let mut planes = vec![0; pixels * 3];
+        for (i, rgb) in data.chunks_exact(3).enumerate() {
+            for (channel, value) in rgb.iter().enumerate() {
+                planes[channel * pixels + i] = *value;
+            }
+        }
+        planes
+    } else {
+        unpack_rle(data, pixels * 3)?
+    };
+    let mask = mask.filter(|mask| mask.len() == pixels);
+
+    Ok(RgbaImage::from_fn(size, size, |x, y| {
+        let i = (y * size + x) as usize;
+        let alpha = mask.map_or(255, |mask| mask[i]);
+        image::Rgba([planes[i], planes[pixels + i], planes[2 * pixels + i], alpha])
+    }))
+}
+
+/// Decodes an RLE ARGB entry (data after the `ARGB` prefix).
+fn decode_argb(data: &[u8], size: u32) -> Result<RgbaImage, ImageError> {
+    let pixels = (size * size) as usize;
+    let planes = unpack_rle(data, pixels * 4)?;
+    Ok(RgbaImage::from_fn(size, size, |x, y| {
+        let i = (y * size + x) as usize;
+        image::Rgba([planes[pixels + i], planes[2 * pixels + i], planes[3 * pixels + i], planes[i]])
+    }))
+}
+
+/// Expands ICNS run-length encoding until `len` bytes are produced.
+///
+/// A control byte below 0x80 is followed by `n + 1` literal bytes; one at
+/// or above 0x80 repeats the next byte `n - 0x80 + 3` times.
+fn unpack_rle(data: &[u8], len: usize) -> Result<Vec<u8>, ImageError> {
+    let mut out = Vec::with_capacity(len);
+    let mut bytes = data.iter();
+    while out.len() < len {
+        let control = *bytes.next().ok_or_else(|| malformed("truncated RLE data"))? as usize;
+        if control < 0x80 {
+            for _ in 0..=control {
+                out.push(*bytes.next().ok_or_else(|| malformed("truncated RLE data"))?);
+            }
+        } else {
+            let value = *bytes.next().ok_or_else(|| malformed("truncated RLE data"))?;
+            out.extend(std::iter::repeat_n(value, control - 0x80 + 3));
+        }
+    }
+    out.truncate(len);
+    Ok(out)
+}
+
+/// Encodes every image with an ICNS slot as a PNG entry.
+///
+/// Images are written in canonical order. Images that aren't square or
+/// whose size and scale have no ICNS entry type (e.g. 48px) are skipped,
+/// as are later images for an already-filled slot. `transparency` is
+/// applied to every image first. Fails if no image can be written.
+pub(crate) fn write_icns(icons: &IconSet, transparency: TransparencyPolicy) -> Result<Vec<u8>, ImageError> {
+    let mut icons = icons.clone();
+    icons.sort_canonical();
+    for icon in &mut icons.images {
+        icon.apply_transparency_policy(transparency);
+    }
+
+    let mut written: Vec<&[u8; 4]> = Vec::new();
+    let mut body = Vec::new();
+    for icon in icons.iter() {
+        let (width, height) = icon.data().dimensions();
+        let Some(&(.., kind)) = PNG_TYPES
+            .iter()
+            .find(|(size, scale, _)| width == height && *size == width && *scale == icon.scale)
+        else {
+            continue;
+        };
+        if written.contains(&kind) {
+            continue;
+        }
+
+        let mut png = Vec::new();
+        icon.data().write_to(&mut Cursor::new(&mut png), ImageFormat::Png)?;
+        body.extend_from_slice(kind);
+        body.extend_from_slice(&entry_len(png.len())?.to_be_bytes());
+        body.extend(png);
+        written.push(kind);
+    }
+
+    if written.is_empty() {
+        return Err(ImageError::Encoding(EncodingError::new(
+            ImageFormatHint::Name("ICNS".into()),
+            "no image matches an ICNS size",
+        )));
+    }
+    let mut file = MAGIC.to_vec();
+    file.extend_from_slice(&entry_len(body.len())?.to_be_bytes());
+    file.extend(body);
+    Ok(file)
+}
+
+/// Returns the length field for `len` bytes of entry data.
+fn entry_len(len: usize) -> Result<u32, ImageError> {
+    u32::try_from(len + HEADER_LEN).map_err(|_| {
+        ImageError::Encoding(EncodingError::new(ImageFormatHint::Name("ICNS".into()), "entry exceeds 4 GiB"))
+    })
+}
+
+/// Reads the 4-byte type and big-endian length at `offset`.
+fn header(bytes: &[u8], offset: usize) -> Option<(&[u8; 4], usize)> {
+    let header = bytes.get(offset..offset.checked_add(HEADER_LEN)?)?;
+    let kind = header[..4].try_into().ok()?;
+    let len = u32::from_be_bytes(header[4..].try_into().ok()?);
+    Some((kind, len as usize))
+}
+
+fn malformed(message: &str) -> ImageError {
+    ImageError::Decoding(DecodingError::new(ImageFormatHint::Name("ICNS".into()), message.to_string()))
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn png(size: u32) -> Vec<u8> {
+        let mut png = Vec::new();
+        RgbaImage::new(size, size)
+            .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+            .unwrap();
+        png
+    }
+
+    fn icns(entries: &[(&[u8; 4], Vec<u8>)]) -> Vec<u8> {
+        let mut body = Vec::new();
+        for (kind, data) in entries {
+            body.extend_from_slice(*kind);
+            body.extend_from_slice(&((data.len() + HEADER_LEN) as u32).to_be_bytes());
+            body.extend_from_slice(data);
+        }
+        let mut file = MAGIC.to_vec();
+        file.extend_from_slice(&((body.len() + HEADER_LEN) as u32).to_be_bytes());
+        file.extend(body);
+        file
+    }
+
+    #[test]
+    fn reads_png_entries_with_scale() {
+        let file = icns(&[(b"icp4", png(16)), (b"s8mk", vec![0; 256]), (b"ic11", png(32))]);
+        let icons = read_icns(&file, RenderLimits::default()).unwrap();
+
+        let sizes: Vec<_> = icons.iter().map(|img| (img.dimensions().width, img.scale)).collect();
+        assert_eq!(sizes, [(16, 1.0), (32, 2.0)]);
+    }
+
+    /// RLE-encodes one solid plane per value.
+    fn solid_planes(values: &[u8], pixels: usize) -> Vec<u8> {
+        let mut data = Vec::new();
+        for &value in values {
+            let mut remaining = pixels;
+            while remaining > 0 {
+                // Runs cover 3..=130 bytes; avoid leaving a remainder below 3
+                let count = if remaining > 130 && remaining - 130 < 3 { 127 } else { remaining.min(130) };
+                data.extend_from_slice(&[(count - 3 + 0x80) as u8, value]);
+                remaining -= count;
+            }
+        }
+        data
+    }
+
+    #[test]
+    fn reads_legacy_rle_entries() {
+        let pixels = 16 * 16;
+        let file = icns(&[
+            (b"is32", solid_planes(&[200, 0, 0], pixels)),
+            (b"s8mk", vec![128; pixels]),
+            (b"ic05", [b"ARGB".to_vec(), solid_planes(&[255, 0, 0, 90], 32 * 32)].concat()),
+        ]);
+
+        let icons = read_icns(&file, RenderLimits::default()).unwrap();
+        assert_eq!(icons.len(), 2);
+        assert_eq!(icons[0].data().get_pixel(3, 7).0, [200, 0, 0, 128]);
+        assert_eq!(icons[1].data().get_pixel(31, 0).0, [0, 0, 90, 255]);
+
+        // A PNG entry of the same size replaces the legacy one
+        let file = icns(&[(b"is32", solid_planes(&[1, 1, 1], pixels)), (b"icp4", png(16))]);
+        let icons = read_icns(&file, RenderLimits::default()).unwrap();
+        assert_eq!(icons.len(), 1);
+        assert_eq!(icons[0].data().get_pixel(0, 0).0, [0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn rejects_malformed_input() {
+        let mut file = icns(&[(b"ic07", png(16))]);
+        file.truncate(file.len() - 1);
+        assert!(read_icns(&file, RenderLimits::default()).is_err());
+        assert!(read_icns(&icns(&[(b"s8mk", vec![0; 4])]), RenderLimits::default()).is_err());
+        assert!(read_icns(b"PK\x03\x04", RenderLimits::default()).is_err());
+    }
+
+    #[test]
+    fn writes_png_entries_that_read_back() {
+        let icon = |size, scale| IconImage::new_full_content(RgbaImage::from_pixel(size, size, image::Rgba([9, 8, 7, 255])), scale);
+        let set = IconSet::from_images(vec![icon(64, 2.0), icon(48, 1.0), icon(16, 1.0), icon(32, 2.0), icon(16, 1.0)]);
+
+        let file = write_icns(&set, TransparencyPolicy::Preserve).unwrap();
+        assert_eq!(&file[..4], MAGIC);
+        assert_eq!(u32::from_be_bytes(file[4..8].try_into().unwrap()) as usize, file.len());
+        assert_eq!(&file[8..12], b"icp4");
+
+        let icons = read_icns(&file, RenderLimits::default()).unwrap();
+        let sizes: Vec<_> = icons.iter().map(|img| (img.dimensions().width, img.scale)).collect();
+        assert_eq!(sizes, [(16, 1.0), (32, 2.0), (64, 2.0)]);
+        assert_eq!(icons[1].data().get_pixel(0, 0).0, [9, 8, 7, 255]);
+
+        assert!(write_icns(&IconSet::from_images(vec![icon(48, 1.0)]), TransparencyPolicy::Preserve).is_err());
+    }
+}
@@ -0,0 +1,283 @@
+//! Windows icon (`.ico`) reading and writing.
+//!
+//! An ICO file is a little-endian directory of entries, each holding either
+//! a PNG or a headerless BMP (DIB) followed by a 1-bit AND mask. The
+//! `image` crate only decodes the largest entry, so the directory is walked
+//! here and each entry decoded on its own.
+
+use std::io::Cursor;
+
+use image::error::{DecodingError, EncodingError, ImageFormatHint};
+use image::{ImageError, ImageFormat, RgbaImage};
+
+use crate::icon::{limit_error, IconImage, IconSet, TransparencyPolicy};
+use crate::layer::RenderLimits;
+
+/// Size of the ICONDIR header.
+const HEADER_LEN: usize = 6;
+
+/// Size of each ICONDIRENTRY.
+const ENTRY_LEN: usize = 16;
+
+/// Size of the BITMAPINFOHEADER starting each BMP entry.
+const BITMAP_HEADER_LEN: u32 = 40;
+
+/// Largest edge length an ICO entry can describe.
+const MAX_SIZE: u32 = 256;
+
+/// Decodes every entry of an ICO file at scale 1.0, in canonical order.
+///
+/// Entries larger than `limits` are rejected from their directory and
+/// payload headers, before decoding. Fails if the container is malformed or
+/// empty.
+pub(crate) fn read_ico(bytes: &[u8], limits: RenderLimits) -> Result<IconSet, ImageError> {
+    let header = bytes.get(..HEADER_LEN).ok_or_else(|| malformed("truncated header"))?;
+    if header[..4] != [0, 0, 1, 0] {
+        return Err(malformed("not an ICO file"));
+    }
+    let count = u16::from_le_bytes([header[4], header[5]]) as usize;
+    if count == 0 {
+        return Err(malformed("no entries"));
+    }
+
+    let mut icons = IconSet::new();
+    for index in 0..count {
+        let offset = HEADER_LEN + index * ENTRY_LEN;
+        let entry = bytes
+            .get(offset..offset + ENTRY_LEN)
+            .ok_or_else(|| malformed("truncated directory"))?;
+        let (width, height) = (entry[0], entry[1]);
+        let len = u32::from_le_bytes(entry[8..12].try_into().unwrap()) as usize;
+        let start = u32::from_le_bytes(entry[12..16].try_into().unwrap()) as usize;
+        let data = start
+            .checked_add(len)
+            .and_then(|end| bytes.get(start..end))
+            .ok_or_else(|| malformed("entry out of bounds"))?;
+
+        let directory_size = |size: u8| if size == 0 { MAX_SIZE } else { size as u32 };
+        limits.check(directory_size(width), directory_size(height)).map_err(|_| limit_error())?;
+        if let Some((payload_width, payload_height)) = payload_dimensions(data) {
+            limits.check(payload_width, payload_height).map_err(|_| limit_error())?;
+        }
+
+        let image = decode_entry(data, width, height)?;
+        limits.check(image.width(), image.height()).map_err(|_| limit_error())?;
+        icons.add_image(IconImage::new_full_content(image, 1.0));
+    }
+
+    icons.sort_canonical();
+    Ok(icons)
+}
+
+/// Reads the dimensions an entry's payload declares: the PNG `IHDR`, or
+/// the BMP header (whose height covers the color and mask planes).
+///
+/// The directory's own sizes can't be trusted here, since the decoder goes
+/// by the payload.
+fn payload_dimensions(data: &[u8]) -> Option<(u32, u32)> {
+    let be = |at: usize| Some(u32::from_be_bytes(data.get(at..at + 4)?.try_into().unwrap()));
+    let le = |at: usize| Some(i32::from_le_bytes(data.get(at..at + 4)?.try_into().unwrap()));
+    if data.starts_with(b"\x89PNG") {
+        Some((be(16)?, be(20)?))
+    } else {
+        Some((le(4)?.unsigned_abs(), le(8)?.unsigned_abs() / 2))
+    }
+}
+
+/// Decodes a single ICO entry (also the payload of a Windows `RT_ICON`
+/// resource).
+///
+/// The entry is wrapped in a one-entry ICO container and handed to the
+/// `image` crate's decoder. `width` and `height` come from the directory,
+/// where 0 means 256.
+pub(crate) fn decode_entry(data: &[u8], width: u8, height: u8) -> Result<RgbaImage, ImageError> {
+    const SINGLE_HEADER_LEN: u32 = (HEADER_LEN + ENTRY_LEN) as u32;
+
+    let mut ico = Vec::with_capacity(SINGLE_HEADER_LEN as usize + data.len());
+    ico.extend_from_slice(&[0, 0, 1, 0, 1, 0]); // reserved, type = icon, count = 1
+    ico.extend_from_slice(&[width, height, 0, 0, 1, 0, 32, 0]);
+    ico.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    ico.extend_from_slice(&SINGLE_HEADER_LEN.to_le_bytes());
+    ico.extend_from_slice(data);
+
+    Ok(image::load_from_memory_with_format(&ico, ImageFormat::Ico)?.to_rgba8())
+}
+
+/// Encodes one entry per square pixel size up to 256px.
+///
+/// 256px entries are PNG-compressed, smaller ones 32-bit BMPs, which every
+/// Windows version reads. When several images share a pixel size, an @1x
+/// image is preferred. `transparency` is applied to every entry, since the
+/// BMP entries' AND masks are all legacy readers see of their alpha. Fails
+/// if no image fits.
+pub(crate) fn write_ico(icons: &IconSet, transparency: TransparencyPolicy) -> Result<Vec<u8>, ImageError> {
+    let mut images: Vec<&IconImage> = icons
+        .iter()
+        .filter(|icon| icon.data().width() == icon.data().height() && icon.data().width() <= MAX_SIZE)
+        .collect();
+    images.sort_by_key(|icon| (icon.data().width(), icon.scale != 1.0));
+    images.dedup_by_key(|icon| icon.data().width());
+    if images.is_empty() {
+        return Err(ImageError::Encoding(EncodingError::new(
+            ImageFormatHint::Exact(ImageFormat::Ico),
+            "no square image of 256px or less",
+        )));
+    }
+
+    let mut payloads = Vec::with_capacity(images.len());
+    for &icon in &images {
+        let mut icon = icon.clone();
+        icon.apply_transparency_policy(transparency);
+        payloads.push(if icon.data().width() == MAX_SIZE { encode_png(icon.data())? } else { encode_bmp(icon.data()) });
+    }
+
+    let mut file = vec![0, 0, 1, 0];
+    file.extend_from_slice(&(images.len() as u16).to_le_bytes());
+    let mut offset = HEADER_LEN + images.len() * ENTRY_LEN;
+    for (icon, payload) in images.iter().zip(&payloads) {
+        // 256 is stored as 0
+        let size = icon.data().width() as u8;
+        file.extend_from_slice(&[size, size, 0, 0, 1, 0, 32, 0]);
+        file.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+        file.extend_from_slice(&(offset as u32).to_le_bytes());
+        offset += payload.len();
+    }
+    for payload in payloads {
+        file.extend(payload);
+    }
+    Ok(file)
+}
+
+fn encode_png(image: &RgbaImage) -> Result<Vec<u8>, ImageError> {
+    let mut png = Vec::new();
+    image.write_to(&mut Cursor::new(&mut png), ImageFormat::Png)?;
+    Ok(png)
+}
+
+/// Encodes a 32-bit bottom-up BMP entry with its AND mask.
+fn encode_bmp(image: &RgbaImage) -> Vec<u8> {
+    let (width, height) = image.dimensions();
+    let mask_stride = width.div_ceil(32) * 4;
+    let pixel_len = width * height * 4;
+    let mask_len = mask_stride * height;
+
+    let mut bmp = Vec::with_capacity((BITMAP_HEADER_LEN + pixel_len + mask_len) as usize);
+    bmp.extend_from_slice(&BITMAP_HEADER_LEN.to_le_bytes());
+    bmp.extend_from_slice(&(width as i32).to_le_bytes());
+    // Height covers the color and mask planes
+    bmp.extend_from_slice(&(height as i32 * 2).to_le_bytes());
+    bmp.extend_from_slice(&1u16.to_le_bytes()); // planes
+    bmp.extend_from_slice(&32u16.to_le_bytes()); // bits per pixel
+    bmp.extend_from_slice(&0u32.to_le_bytes()); // BI_RGB
+    bmp.extend_from_slice(&(pixel_len + mask_len).to_le_bytes());
+    bmp.extend_from_slice(&[0; 16]); // resolution and palette counts
+
+    for y in (0..height).rev() {
+        for x in 0..width {
+            let [r, g, b, a] = image.get_pixel(x, y).0;
+            bmp.extend_from_slice(&[b, g, r, a]);
+        }
+    }
+    for y in (0..height).rev() {
+        let mut row = vec![0u8; mask_stride as usize];
+        for x in 0..width {
+            if image.get_pixel(x, y)[3] == 0 {
+                row[(x / 8) as usize] |= 0x80 >> (x % 8);
+            }
+        }
+        bmp.extend(row);
+    }
+    bmp
+}
+
+fn malformed(message: &str) -> ImageError {
+    ImageError::Decoding(DecodingError::new(ImageFormatHint::Exact(ImageFormat::Ico), message.to_string()))
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use image::Rgba;
+
+    use super::*;
+
+    #[test]
+    fn decodes_png_entry() {
+        let png = encode_png(&RgbaImage::from_pixel(24, 24, Rgba([1, 2, 3, 255]))).unwrap();
+
+        let image = decode_entry(&png, 24, 24).unwrap();
+        assert_eq!(image.dimensions(), (24, 24));
+        assert_eq!(image.get_pixel(5, 5).0, [1, 2, 3, 255]);
+    }
+
+    #[test]
+    fn written_sizes_read_back() {
+        let icon = |size, scale| {
+            let mut image = RgbaImage::from_pixel(size, size, Rgba([200, 100, 50, 255]));
+            image.put_pixel(0, 0, Rgba([0, 0, 0, 0]));
+            image.put_pixel(1, 0, Rgba([10, 20, 30, 128]));
+            IconImage::new_full_content(image, scale)
+        };
+        let set = IconSet::from_images(vec![
+            icon(256, 1.0),
+            icon(32, 2.0),
+            icon(16, 1.0),
+            icon(48, 1.0),
+            icon(32, 1.0),
+            icon(512, 1.0),
+        ]);
+
+        let file = write_ico(&set, TransparencyPolicy::Preserve).unwrap();
+        // The 256px entry is PNG-compressed
+        let last_offset = u32::from_le_bytes(file[6 + 3 * 16 + 12..6 + 3 * 16 + 16].try_into().unwrap()) as usize;
+        assert!(file[last_offset..].starts_with(b"\x89PNG"));
+
+        let icons = read_ico(&file, RenderLimits::default()).unwrap();
+        let sizes: Vec<_> = icons.iter().map(|img| img.dimensions().width).collect();
+        assert_eq!(sizes, [16, 32, 48, 256]);
+        for icon in icons.iter() {
+            assert_eq!(icon.data().get_pixel(0, 0)[3], 0);
+            assert_eq!(icon.data().get_pixel(1, 0).0, [10, 20, 30, 128]);
+            assert_eq!(icon.data().get_pixel(5, 9).0, [200, 100, 50, 255]);
+        }
+    }
+
+    #[test]
+    fn rejects_malformed_input() {
+        assert!(read_ico(b"\x89PNG", RenderLimits::default()).is_err());
+        assert!(read_ico(&[0, 0, 1, 0, 1, 0], RenderLimits::default()).is_err());
+        assert!(write_ico(&IconSet::new(), TransparencyPolicy::Preserve).is_err());
+    }
+
+    #[test]
+    fn oversized_payloads_are_rejected_before_decoding() {
+        let png = encode_png(&RgbaImage::new(64, 64)).unwrap();
+        assert_eq!(payload_dimensions(&png), Some((64, 64)));
+        assert_eq!(payload_dimensions(&encode_bmp(&RgbaImage::new(16, 24))), Some((16, 24)));
+
+        // A 16px directory entry whose header claims a huge image
+        let mut huge = png.clone();
+        huge[16..24].copy_from_slice(&[0, 1, 0, 0, 0, 1, 0, 0]);
+        let mut file = vec![0, 0, 1, 0, 1, 0, 16, 16, 0, 0, 1, 0, 32, 0];
+        file.extend_from_slice(&(huge.len() as u32).to_le_bytes());
+        file.extend_from_slice(&22u32.to_le_bytes());
+        file.extend(huge);
+        assert!(matches!(read_ico(&file, RenderLimits::default()), Err(ImageError::Limits(_))));
+        assert!(matches!(read_ico(&file, RenderLimits::new(8, u64::MAX)), Err(ImageError::Limits(_))));
+    }
+
+    #[test]
+    fn writes_with_the_transparency_policy() {
+        let mut image = RgbaImage::from_pixel(16, 16, Rgba([200, 100, 50, 255]));
+        image.put_pixel(0, 0, Rgba([10, 20, 30, 100]));
+        let set = IconSet::from_images(vec![IconImage::new_full_content(image, 1.0)]);
+
+        let file = write_ico(&set, TransparencyPolicy::Threshold { cutoff: 128 }).unwrap();
+        let icons = read_ico(&file, RenderLimits::default()).unwrap();
+        assert_eq!(icons.get(0).unwrap().data().get_pixel(0, 0)[3], 0);
+        assert_eq!(icons.get(0).unwrap().data().get_pixel(1, 0).0, [200, 100, 50, 255]);
+    }
+}
@@ -3,16 +3,31 @@
 //! This module provides types for representing system icons as a collection
 //! of images at various sizes and scales.
 
+use std::collections::btree_map::Entry;
+use std::collections::BTreeMap;
 use std::io::Cursor;
+use std::path::Path;
+use std::sync::{Arc, OnceLock};
 
-use image::{ImageFormat, RgbaImage};
-use serde::{Deserialize, Serialize};
+use image::error::{LimitError, LimitErrorKind, ParameterError, ParameterErrorKind};
+use image::{ImageError, ImageFormat, ImageReader, Limits, RgbaImage};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use thiserror::Error;
+
+use crate::icns;
+use crate::ico;
+use crate::error::RenderError;
+use crate::layer::RenderLimits;
+use crate::scale;
 
 /// A rectangle defined in pixel coordinates.
 ///
 /// Used to specify regions within an image, such as content bounds
 /// that indicate where the actual icon content exists (excluding padding/margins).
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "jsonschema", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "tsify", derive(tsify_next::Tsify))]
+#[cfg_attr(feature = "tsify", tsify(into_wasm_abi, from_wasm_abi))]
 pub struct RectPx {
     /// X offset from the left edge of the image
     pub x: u32,
@@ -64,15 +79,48 @@ impl SizePx {
     }
 }
 
+/// The state an icon image depicts.
+///
+/// Finder folder icons, for example, come with open-state artwork next to
+/// the normal one. Variants at the same size render separately, with the
+/// same customization. ICNS and ICO hold one variant, so export them one
+/// at a time (see [`IconSet::variant`]).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "tsify", derive(tsify_next::Tsify))]
+#[cfg_attr(feature = "tsify", tsify(into_wasm_abi, from_wasm_abi))]
+#[serde(rename_all = "kebab-case")]
+pub enum IconVariant {
+    /// The icon at rest.
+    #[default]
+    Normal,
+    /// An open folder.
+    Open,
+    /// The icon while selected.
+    Selected,
+    /// The icon while dragged, or hovered by a drag.
+    Dragged,
+}
+
+impl IconVariant {
+    /// Returns true for [`IconVariant::Normal`].
+    pub fn is_normal(&self) -> bool {
+        *self == Self::Normal
+    }
+}
+
 /// A single icon image with its associated metadata.
 ///
 /// Icon sets typically contain multiple images at different sizes and scales.
 /// For example, macOS uses @1x and @2x variants, Windows uses multiple sizes
 /// (16x16, 32x32, 48x48, 256x256), and Linux icon themes have similar patterns.
+///
+/// Serializes as a PNG-encoded [`SerializableIconImage`]; use
+/// [`raw_pixels`] for raw RGBA.
 #[derive(Debug, Clone, PartialEq)]
 pub struct IconImage {
-    /// The image data in RGBA format.
-    pub data: RgbaImage,
+    /// The image data in RGBA format. Private, so every edit goes through
+    /// [`data_mut`](Self::data_mut) and drops the memoized statistics.
+    data: RgbaImage,
 
     /// The display scale factor.
     ///
@@ -89,6 +137,12 @@ pub struct IconImage {
     /// If the icon fills the entire image, this will equal
     /// `RectPx::from_size(width, height)`.
     pub content_bounds: RectPx,
+
+    /// The state the image depicts. Rendering keeps it.
+    variant: IconVariant,
+
+    /// Statistics of `data`, computed on first use.
+    stats: StatsCell,
 }
 
 impl IconImage {
@@ -98,15 +152,72 @@ impl IconImage {
             data,
             scale,
             content_bounds,
+            variant: IconVariant::Normal,
+            stats: StatsCell::default(),
         }
     }
 
+    /// Tags the image as depicting `variant`.
+    pub fn with_variant(mut self, variant: IconVariant) -> Self {
+        self.variant = variant;
+        self
+    }
+
+    /// Returns the state the image depicts.
+    pub fn variant(&self) -> IconVariant {
+        self.variant
+    }
+
     /// Creates a new icon image assuming content fills the entire image.
     pub fn new_full_content(data: RgbaImage, scale: f32) -> Self {
         let content_bounds = RectPx::from_size(data.width(), data.height());
         Self::new(data, scale, content_bounds)
     }
 
+    /// Creates a full-content icon image from an RGBA8 buffer whose rows
+    /// start every `stride` bytes, as platform capture APIs often return
+    /// them with padding after each row.
+    ///
+    /// The padding, and anything after the last row's pixels, is dropped.
+    ///
+    /// ```
+    /// use folco_renderer::IconImage;
+    ///
+    /// // 2x2 pixels in rows padded to 12 bytes
+    /// let mut buffer = vec![0u8; 12 + 8];
+    /// buffer[12..16].copy_from_slice(&[255, 0, 0, 255]);
+    /// let icon = IconImage::from_raw(2, 2, 12, &buffer, 1.0).unwrap();
+    /// assert_eq!(icon.data().get_pixel(0, 1).0, [255, 0, 0, 255]);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Fails with a dimension mismatch if `stride` is shorter than a row
+    /// of pixels or `data` is shorter than the image.
+    pub fn from_raw(width: u32, height: u32, stride: usize, data: &[u8], scale: f32) -> Result<Self, ImageError> {
+        let mismatch = || ImageError::Parameter(ParameterError::from_kind(ParameterErrorKind::DimensionMismatch));
+        let row = width as usize * 4;
+        let needed = match height {
+            0 => 0,
+            _ => (height as usize - 1).checked_mul(stride).and_then(|rows| rows.checked_add(row)).ok_or_else(mismatch)?,
+        };
+        if stride < row || data.len() < needed {
+            return Err(mismatch());
+        }
+
+        let pixels = if stride == row {
+            data[..needed].to_vec()
+        } else {
+            let mut pixels = Vec::with_capacity(row * height as usize);
+            for y in 0..height as usize {
+                pixels.extend_from_slice(&data[y * stride..y * stride + row]);
+            }
+            pixels
+        };
+        let image = RgbaImage::from_raw(width, height, pixels).ok_or_else(mismatch)?;
+        Ok(Self::new_full_content(image, scale))
+    }
+
     /// Returns the pixel dimensions of the image.
     pub fn dimensions(&self) -> SizePx {
         SizePx::new(self.data.width(), self.data.height())
@@ -121,13 +232,348 @@ impl IconImage {
             self.data.height() as f32 / self.scale,
         )
     }
+
+    /// Encodes the image as a PNG.
+    ///
+    /// The scale is recorded as the PNG's resolution (144 DPI at @2x), so
+    /// [`IconSet::from_image_bytes`] reads it back; content bounds are not
+    /// kept.
+    ///
+    /// # Errors
+    ///
+    /// Fails if PNG encoding fails.
+    pub fn to_png(&self) -> Result<Vec<u8>, ImageError> {
+        let mut png = Vec::new();
+        self.data.write_to(&mut Cursor::new(&mut png), ImageFormat::Png)?;
+        Ok(scale::with_png_resolution(png, self.scale))
+    }
+
+    /// Returns the tight bounds of the image's visible content, or `None`
+    /// if nothing reaches the cutoff.
+    ///
+    /// Pixels fainter than [`SHADOW_ALPHA_CUTOFF`] are left out, so the
+    /// soft drop shadow system folder icons carry (and stray antialiasing
+    /// in their padding) doesn't widen the bounds.
+    ///
+    /// ```
+    /// use folco_renderer::{IconImage, RectPx};
+    /// use image::{Rgba, RgbaImage};
+    ///
+    /// let mut data = RgbaImage::new(32, 32);
+    /// for (x, y, pixel) in data.enumerate_pixels_mut() {
+    ///     if (2..30).contains(&x) && (5..27).contains(&y) {
+    ///         *pixel = Rgba([255, 217, 112, 255]);
+    ///     } else if y == 27 {
+    ///         *pixel = Rgba([0, 0, 0, 20]); // shadow
+    ///     }
+    /// }
+    /// let icon = IconImage::new_full_content(data, 1.0);
+    /// assert_eq!(icon.detect_content_bounds(), Some(RectPx::new(2, 5, 28, 22)));
+    /// ```
+    pub fn detect_content_bounds(&self) -> Option<RectPx> {
+        self.stats().content_bounds
+    }
+
+    /// Like [`detect_content_bounds`](Self::detect_content_bounds), counting
+    /// pixels with alpha of at least `min_alpha` (treated as 1 if 0).
+    pub fn detect_content_bounds_with_cutoff(&self, min_alpha: u8) -> Option<RectPx> {
+        let min_alpha = min_alpha.max(1);
+        match min_alpha {
+            1 => return self.stats().visible_bounds,
+            SHADOW_ALPHA_CUTOFF => return self.stats().content_bounds,
+            _ => {}
+        }
+        let (mut left, mut top, mut right, mut bottom) = (u32::MAX, u32::MAX, 0, 0);
+        for (x, y, pixel) in self.data.enumerate_pixels() {
+            if pixel[3] >= min_alpha {
+                (left, top) = (left.min(x), top.min(y));
+                (right, bottom) = (right.max(x + 1), bottom.max(y + 1));
+            }
+        }
+        (left < right).then(|| RectPx::new(left, top, right - left, bottom - top))
+    }
+
+    /// Replaces the content bounds with the detected ones (see
+    /// [`detect_content_bounds`](Self::detect_content_bounds)), keeping
+    /// them if the image has no visible content.
+    pub fn with_detected_content_bounds(mut self) -> Self {
+        if let Some(bounds) = self.detect_content_bounds() {
+            self.content_bounds = bounds;
+        }
+        self
+    }
+
+    /// Returns statistics of the pixels, scanning them on the first call
+    /// and reusing the result until they change through
+    /// [`data_mut`](Self::data_mut).
+    ///
+    /// Content bound detection and [`sample_dominant_color`](crate::analysis::sample_dominant_color)
+    /// read from here, so analyzing one image repeatedly scans it once.
+    ///
+    /// ```
+    /// use folco_renderer::{IconImage, RectPx};
+    /// use image::{Rgba, RgbaImage};
+    ///
+    /// let mut icon = IconImage::new_full_content(RgbaImage::new(8, 8), 1.0);
+    /// assert_eq!(icon.stats().alpha_histogram[0], 64);
+    ///
+    /// icon.data_mut().put_pixel(2, 3, Rgba([255, 0, 0, 255]));
+    /// assert_eq!(icon.stats().content_bounds, Some(RectPx::new(2, 3, 1, 1)));
+    /// assert_eq!(icon.stats().mean_color, Some((255, 0, 0)));
+    /// ```
+    pub fn stats(&self) -> &ImageStats {
+        self.stats.0.get_or_init(|| Arc::new(ImageStats::compute(&self.data)))
+    }
+
+    /// Returns the image data in RGBA format.
+    pub fn data(&self) -> &RgbaImage {
+        &self.data
+    }
+
+    /// Returns the pixels for editing, dropping the memoized
+    /// [`stats`](Self::stats).
+    pub fn data_mut(&mut self) -> &mut RgbaImage {
+        self.stats = StatsCell::default();
+        &mut self.data
+    }
+
+    /// Returns the image data, consuming the icon.
+    pub fn into_data(self) -> RgbaImage {
+        self.data
+    }
+
+    /// Returns the slot the image fills in an [`IconSet`]: its logical size
+    /// and scale, both rounded to whole numbers.
+    pub(crate) fn slot(&self) -> (u32, u32) {
+        (self.logical_size().0.round() as u32, self.scale.round().max(1.0) as u32)
+    }
+
+    /// Snaps every pixel to fully opaque or fully transparent.
+    ///
+    /// Pixels with alpha below `cutoff` are cleared; all others become
+    /// fully opaque. Useful for formats or sizes where partial alpha
+    /// renders as a dirty fringe.
+    pub fn threshold_alpha(&mut self, cutoff: u8) {
+        for pixel in self.data_mut().pixels_mut() {
+            pixel.0 = if pixel[3] < cutoff {
+                [0, 0, 0, 0]
+            } else {
+                [pixel[0], pixel[1], pixel[2], 255]
+            };
+        }
+    }
+
+    /// Reduces partial transparency according to `policy`.
+    ///
+    /// Intended for export targets that only support a 1-bit mask (e.g.
+    /// legacy ICO entries), so the result looks intentional rather than
+    /// whatever the encoder does with partial alpha.
+    pub fn apply_transparency_policy(&mut self, policy: TransparencyPolicy) {
+        match policy {
+            TransparencyPolicy::Preserve => {}
+            TransparencyPolicy::Matte { r, g, b } => {
+                for pixel in self.data_mut().pixels_mut() {
+                    let alpha = pixel[3] as u32;
+                    if alpha == 0 {
+                        continue;
+                    }
+                    let blend = |c: u8, m: u8| ((c as u32 * alpha + m as u32 * (255 - alpha)) / 255) as u8;
+                    pixel.0 = [blend(pixel[0], r), blend(pixel[1], g), blend(pixel[2], b), 255];
+                }
+            }
+            TransparencyPolicy::Threshold { cutoff } => self.threshold_alpha(cutoff),
+            TransparencyPolicy::Dither => {
+                for (x, y, pixel) in self.data_mut().enumerate_pixels_mut() {
+                    // Bayer threshold in 1..=255, centered in each of the 16 buckets
+                    let threshold = BAYER_4X4[(y % 4) as usize][(x % 4) as usize] as u32 * 16 + 8;
+                    pixel.0 = if (pixel[3] as u32) < threshold {
+                        [0, 0, 0, 0]
+                    } else {
+                        [pixel[0], pixel[1], pixel[2], 255]
+                    };
+                }
+            }
+        }
+    }
+
+    /// Removes semi-transparent halo pixels along the silhouette edge.
+    ///
+    /// Compositing often leaves a ring of faint, off-color pixels around
+    /// the icon. For each partially transparent pixel touching a fully
+    /// transparent one (or the image border):
+    ///
+    /// - If its alpha is below `min_alpha`, it is cleared.
+    /// - Otherwise its color is replaced with the average of its fully
+    ///   opaque 8-neighbors (if any), keeping its alpha for antialiasing.
+    pub fn refine_edges(&mut self, min_alpha: u8) {
+        let source = self.data.clone();
+        let (width, height) = source.dimensions();
+
+        for (x, y, pixel) in self.data_mut().enumerate_pixels_mut() {
+            let alpha = pixel[3];
+            if alpha == 0 || alpha == 255 {
+                continue;
+            }
+
+            // Anything past the image border counts as background
+            let on_border = x == 0 || y == 0 || x + 1 == width || y + 1 == height;
+            if !on_border && !neighbors8(&source, x, y).any(|n| n[3] == 0) {
+                continue;
+            }
+
+            if alpha < min_alpha {
+                pixel.0 = [0, 0, 0, 0];
+                continue;
+            }
+
+            let (sum, count) = neighbors8(&source, x, y)
+                .filter(|n| n[3] == 255)
+                .fold(([0u32; 3], 0u32), |(sum, count), n| {
+                    ([sum[0] + n[0] as u32, sum[1] + n[1] as u32, sum[2] + n[2] as u32], count + 1)
+                });
+            let average = |c: u32| c.checked_div(count).map(|v| v as u8);
+            if let (Some(r), Some(g), Some(b)) = (average(sum[0]), average(sum[1]), average(sum[2])) {
+                pixel.0 = [r, g, b, alpha];
+            }
+        }
+    }
+}
+
+// ============================================================================
+// Image Statistics
+// ============================================================================
+
+/// Whole-image statistics, gathered in one pass over the pixels and
+/// memoized by [`IconImage::stats`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImageStats {
+    /// The number of pixels at each alpha value.
+    pub alpha_histogram: [u32; 256],
+    /// Bounds of the pixels with any opacity, or `None` if the image is
+    /// fully transparent.
+    pub visible_bounds: Option<RectPx>,
+    /// Bounds of the pixels at or above [`SHADOW_ALPHA_CUTOFF`], as
+    /// [`IconImage::detect_content_bounds`] returns them.
+    pub content_bounds: Option<RectPx>,
+    /// The alpha-weighted mean color of the visible pixels, or `None` if
+    /// the image is fully transparent.
+    pub mean_color: Option<(u8, u8, u8)>,
+}
+
+impl ImageStats {
+    fn compute(data: &RgbaImage) -> Self {
+        let mut alpha_histogram = [0u32; 256];
+        // Left, top, right and bottom of the visible and content pixels
+        let mut extents = [(u32::MAX, u32::MAX, 0, 0); 2];
+        let mut sum = [0.0f64; 3];
+        let mut total = 0.0f64;
+        for (x, y, pixel) in data.enumerate_pixels() {
+            let alpha = pixel[3];
+            alpha_histogram[alpha as usize] += 1;
+            if alpha == 0 {
+                continue;
+            }
+            for (cutoff, (left, top, right, bottom)) in [1, SHADOW_ALPHA_CUTOFF].into_iter().zip(&mut extents) {
+                if alpha >= cutoff {
+                    (*left, *top) = ((*left).min(x), (*top).min(y));
+                    (*right, *bottom) = ((*right).max(x + 1), (*bottom).max(y + 1));
+                }
+            }
+            // Weighted as sample_dominant_color weighs a uniform sample
+            let weight = (alpha as f32 / 255.0) as f64;
+            for (acc, channel) in sum.iter_mut().zip(&pixel.0[..3]) {
+                *acc += *channel as f64 * weight;
+            }
+            total += weight;
+        }
+
+        let rect = |(left, top, right, bottom): (u32, u32, u32, u32)| {
+            (left < right).then(|| RectPx::new(left, top, right - left, bottom - top))
+        };
+        let mean = |c: f64| (c / total).round() as u8;
+        Self {
+            alpha_histogram,
+            visible_bounds: rect(extents[0]),
+            content_bounds: rect(extents[1]),
+            mean_color: (total > 0.0).then(|| (mean(sum[0]), mean(sum[1]), mean(sum[2]))),
+        }
+    }
+}
+
+/// Memoized [`ImageStats`] of an [`IconImage`].
+///
+/// Clones share the computed statistics, and it compares equal to any
+/// other, so it never makes otherwise equal images differ.
+#[derive(Debug, Clone, Default)]
+struct StatsCell(OnceLock<Arc<ImageStats>>);
+
+impl PartialEq for StatsCell {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
+
+/// Alpha below which [`IconImage::detect_content_bounds`] treats pixels as
+/// shadow rather than content.
+pub const SHADOW_ALPHA_CUTOFF: u8 = 48;
+
+/// 4×4 ordered dithering matrix.
+const BAYER_4X4: [[u8; 4]; 4] = [[0, 8, 2, 10], [12, 4, 14, 6], [3, 11, 1, 9], [15, 7, 13, 5]];
+
+/// How partial transparency is reduced to fully opaque / fully transparent.
+///
+/// See [`IconImage::apply_transparency_policy`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "jsonschema", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "tsify", derive(tsify_next::Tsify))]
+#[cfg_attr(feature = "tsify", tsify(into_wasm_abi, from_wasm_abi))]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+pub enum TransparencyPolicy {
+    /// Keep the alpha channel as-is.
+    #[default]
+    Preserve,
+    /// Blend partially transparent pixels over a solid color and make them
+    /// opaque. Fully transparent pixels stay transparent. Best when the
+    /// target background color is known.
+    Matte {
+        /// Matte red channel (0–255).
+        r: u8,
+        /// Matte green channel (0–255).
+        g: u8,
+        /// Matte blue channel (0–255).
+        b: u8,
+    },
+    /// Clear pixels below `cutoff` and make the rest opaque.
+    Threshold {
+        /// Alpha cutoff (0–255).
+        cutoff: u8,
+    },
+    /// Ordered (Bayer 4×4) dithering of the alpha channel, approximating
+    /// soft edges with a pattern of opaque and transparent pixels.
+    Dither,
+}
+
+/// Returns the in-bounds 8-neighbors of a pixel.
+fn neighbors8(image: &RgbaImage, x: u32, y: u32) -> impl Iterator<Item = &image::Rgba<u8>> {
+    let (width, height) = image.dimensions();
+    (-1i32..=1)
+        .flat_map(|dy| (-1i32..=1).map(move |dx| (dx, dy)))
+        .filter(|&offset| offset != (0, 0))
+        .filter_map(move |(dx, dy)| {
+            let nx = x.checked_add_signed(dx)?;
+            let ny = y.checked_add_signed(dy)?;
+            (nx < width && ny < height).then(|| image.get_pixel(nx, ny))
+        })
 }
 
 /// A collection of icon images representing a single icon at various sizes and scales.
 ///
 /// System icons typically come as a set of images at different resolutions.
 /// This struct groups them together as a cohesive unit.
-#[derive(Debug, Clone, PartialEq, Default)]
+///
+/// Serializes as `{ "images": [...] }`, each image PNG-encoded (see
+/// [`SerializableIconImage`]); use [`raw_pixels`] for raw RGBA.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
 pub struct IconSet {
     /// The individual icon images, typically at various sizes/scales.
     pub images: Vec<IconImage>,
@@ -162,18 +608,360 @@ impl IconSet {
     /// Finds an image by its logical size (closest match).
     ///
     /// This is useful when you need a specific size for display
-    /// and want to find the best available variant.
+    /// and want to find the best available variant. Images of the
+    /// [`Normal`](IconVariant::Normal) variant are preferred when there
+    /// are any.
     pub fn find_by_logical_size(&self, target_size: u32) -> Option<&IconImage> {
-        self.images.iter().min_by_key(|img| {
-            let (logical_w, _) = img.logical_size();
-            (logical_w - target_size as f32).abs() as u32
-        })
+        self.find_variant_by_logical_size(IconVariant::Normal, target_size)
+            .or_else(|| closest_logical_size(self.images.iter(), target_size))
+    }
+
+    /// Like [`find_by_logical_size`](Self::find_by_logical_size), among
+    /// the images of `variant` only.
+    pub fn find_variant_by_logical_size(&self, variant: IconVariant, target_size: u32) -> Option<&IconImage> {
+        closest_logical_size(self.images.iter().filter(|img| img.variant == variant), target_size)
+    }
+
+    /// Returns the images of `variant`, in order.
+    pub fn variant(&self, variant: IconVariant) -> IconSet {
+        Self::from_images(self.images.iter().filter(|img| img.variant == variant).cloned().collect())
+    }
+
+    /// Returns the variants present, in [`IconVariant`] order.
+    pub fn variants(&self) -> Vec<IconVariant> {
+        let mut variants: Vec<_> = self.images.iter().map(|img| img.variant).collect();
+        variants.sort();
+        variants.dedup();
+        variants
     }
 
     /// Returns an iterator over the icon images.
     pub fn iter(&self) -> impl Iterator<Item = &IconImage> {
         self.images.iter()
     }
+
+    /// Returns the image at `index`, if any.
+    pub fn get(&self, index: usize) -> Option<&IconImage> {
+        self.images.get(index)
+    }
+
+    /// Returns a mutable reference to the image at `index`, if any.
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut IconImage> {
+        self.images.get_mut(index)
+    }
+
+    /// Sorts images into canonical order: ascending logical size, then
+    /// ascending scale, then variant.
+    ///
+    /// Exporters that require deterministic ordering (ICNS, ICO) should
+    /// call this rather than rely on insertion order. The sort is stable,
+    /// so images that compare equal keep their relative order.
+    pub fn sort_canonical(&mut self) {
+        self.images.sort_by(|a, b| {
+            a.logical_size()
+                .0
+                .total_cmp(&b.logical_size().0)
+                .then(a.scale.total_cmp(&b.scale))
+                .then(a.variant.cmp(&b.variant))
+        });
+    }
+    /// Decodes an encoded image into an icon set.
+    ///
+    /// Accepts any format the `image` crate was built with (PNG, JPEG,
+    /// single-page TIFF, ...) and yields one image, at the scale implied by a
+    /// PNG's resolution (144 DPI is @2x) or else 1.0. With the
+    /// `tiff` feature enabled, multi-page TIFFs — the form macOS uses for
+    /// combined @1x/@2x icon resources — yield one image per page, with the
+    /// scale taken from each page's resolution relative to 72 DPI. Images
+    /// larger than the default [`RenderLimits`] are rejected before decoding.
+    ///
+    /// ICNS and ICO containers are detected and read as by
+    /// [`from_icns`](Self::from_icns) and [`from_ico`](Self::from_ico).
+    ///
+    /// HEIC is not supported; convert it to PNG or TIFF first.
+    pub fn from_image_bytes(bytes: &[u8]) -> Result<Self, ImageError> {
+        Self::from_image_bytes_with_limits(bytes, RenderLimits::default())
+    }
+
+    /// Like [`from_image_bytes`](Self::from_image_bytes), with explicit
+    /// limits on the decoded image dimensions.
+    pub fn from_image_bytes_with_limits(bytes: &[u8], limits: RenderLimits) -> Result<Self, ImageError> {
+        if bytes.starts_with(icns::MAGIC) {
+            return icns::read_icns(bytes, limits);
+        }
+        if image::guess_format(bytes).ok() == Some(ImageFormat::Ico) {
+            return ico::read_ico(bytes, limits);
+        }
+        #[cfg(feature = "tiff")]
+        if image::guess_format(bytes).ok() == Some(ImageFormat::Tiff) {
+            return tiff_pages::decode(bytes, limits);
+        }
+
+        let rgba = decode_with_limits(bytes, limits)?;
+        let scale = scale::from_png_metadata(bytes).unwrap_or(1.0);
+        Ok(Self::from_images(vec![IconImage::new_full_content(rgba, scale)]))
+    }
+
+    /// Reads an image file into an icon set.
+    ///
+    /// Decodes as [`from_image_bytes`](Self::from_image_bytes). A plain
+    /// image takes its scale from an `@2x`-style file name suffix
+    /// (`folder@2x.png`) when present, which wins over the file's
+    /// resolution metadata; containers keep the scales they declare.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the file can't be read or decoded, or exceeds the default
+    /// [`RenderLimits`].
+    pub fn from_path(path: impl AsRef<Path>) -> Result<Self, ImageError> {
+        Self::from_path_with_limits(path, RenderLimits::default())
+    }
+
+    /// Like [`from_path`](Self::from_path), with explicit limits on the
+    /// decoded image dimensions.
+    pub fn from_path_with_limits(path: impl AsRef<Path>, limits: RenderLimits) -> Result<Self, ImageError> {
+        let path = path.as_ref();
+        let bytes = std::fs::read(path).map_err(ImageError::IoError)?;
+        let mut icons = Self::from_image_bytes_with_limits(&bytes, limits)?;
+        let named = path.file_name().and_then(|name| name.to_str()).and_then(scale::from_file_name);
+        if let (Some(scale), [image]) = (named, &mut icons.images[..])
+            && !is_container(&bytes)
+        {
+            image.scale = scale;
+        }
+        Ok(icons)
+    }
+
+    /// Reads several image files, e.g. `folder.png` and `folder@2x.png`,
+    /// into one icon set in canonical order.
+    ///
+    /// Each file is read as by [`from_path`](Self::from_path).
+    ///
+    /// # Errors
+    ///
+    /// Fails on the first file that can't be read or decoded.
+    pub fn from_paths<P: AsRef<Path>>(paths: impl IntoIterator<Item = P>) -> Result<Self, ImageError> {
+        let mut icons = Self::new();
+        for path in paths {
+            icons.images.extend(Self::from_path(path)?);
+        }
+        icons.sort_canonical();
+        Ok(icons)
+    }
+
+    /// Reads a macOS `.icns` container into an icon set.
+    ///
+    /// Decodes PNG representations (including the @2x `ic10`–`ic14`
+    /// entries, which get scale 2.0) as well as legacy RLE RGB entries
+    /// with their 8-bit masks and RLE ARGB entries. JPEG 2000 and 1-bit
+    /// entries are skipped. Images are returned in canonical order.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the container is malformed, holds no supported
+    /// representations, or an image exceeds the default [`RenderLimits`].
+    pub fn from_icns(bytes: &[u8]) -> Result<Self, ImageError> {
+        Self::from_icns_with_limits(bytes, RenderLimits::default())
+    }
+
+    /// Like [`from_icns`](Self::from_icns), with explicit limits on the
+    /// decoded image dimensions.
+    pub fn from_icns_with_limits(bytes: &[u8], limits: RenderLimits) -> Result<Self, ImageError> {
+        icns::read_icns(bytes, limits)
+    }
+
+    /// Encodes the set as a macOS `.icns` container.
+    ///
+    /// Every square image whose pixel size and scale match an ICNS slot
+    /// (16–512px at @1x; 32, 64, 256, 512 and 1024px at @2x) is written as
+    /// a PNG entry, in canonical order. Other images are skipped, as are
+    /// duplicates of a slot after the first.
+    ///
+    /// # Errors
+    ///
+    /// Fails if PNG encoding fails or no image matches an ICNS slot.
+    pub fn to_icns(&self) -> Result<Vec<u8>, ImageError> {
+        self.to_icns_with_transparency(TransparencyPolicy::Preserve)
+    }
+
+    /// Like [`to_icns`](Self::to_icns), reducing every image's partial
+    /// transparency with `policy` first (see
+    /// [`IconImage::apply_transparency_policy`]).
+    pub fn to_icns_with_transparency(&self, policy: TransparencyPolicy) -> Result<Vec<u8>, ImageError> {
+        icns::write_icns(self, policy)
+    }
+
+    /// Reads a Windows `.ico` container into an icon set.
+    ///
+    /// Every entry, PNG or BMP, is decoded at scale 1.0. Images are
+    /// returned in canonical order.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the container is malformed or empty, or an image exceeds
+    /// the default [`RenderLimits`].
+    pub fn from_ico(bytes: &[u8]) -> Result<Self, ImageError> {
+        Self::from_ico_with_limits(bytes, RenderLimits::default())
+    }
+
+    /// Like [`from_ico`](Self::from_ico), with explicit limits on the
+    /// decoded image dimensions.
+    pub fn from_ico_with_limits(bytes: &[u8], limits: RenderLimits) -> Result<Self, ImageError> {
+        ico::read_ico(bytes, limits)
+    }
+
+    /// Encodes the set as a Windows `.ico` container.
+    ///
+    /// Writes one entry per square pixel size up to 256px (typically 16,
+    /// 32, 48 and 256), preferring @1x images when several share a size.
+    /// The 256px entry is PNG-compressed; smaller ones are 32-bit BMPs.
+    ///
+    /// # Errors
+    ///
+    /// Fails if PNG encoding fails or no image is square and at most 256px.
+    pub fn to_ico(&self) -> Result<Vec<u8>, ImageError> {
+        self.to_ico_with_transparency(TransparencyPolicy::Preserve)
+    }
+
+    /// Like [`to_ico`](Self::to_ico), reducing every entry's partial
+    /// transparency with `policy` first (see
+    /// [`IconImage::apply_transparency_policy`]), so the BMP entries' AND
+    /// masks match what legacy readers should show.
+    pub fn to_ico_with_transparency(&self, policy: TransparencyPolicy) -> Result<Vec<u8>, ImageError> {
+        ico::write_ico(self, policy)
+    }
+
+    /// Encodes every image as a PNG (see [`IconImage::to_png`]), in set
+    /// order.
+    ///
+    /// # Errors
+    ///
+    /// Fails if PNG encoding fails.
+    pub fn to_png_bytes(&self) -> Result<Vec<Vec<u8>>, ImageError> {
+        self.images.iter().map(IconImage::to_png).collect()
+    }
+
+    /// Encodes the set as PNGs keyed by rounded logical size and scale,
+    /// the slots [`merge`](Self::merge) uses. When several images share a
+    /// slot, the first in the set wins.
+    ///
+    /// ```
+    /// use folco_renderer::{IconImage, IconSet};
+    /// use image::RgbaImage;
+    ///
+    /// let icon = |size, scale| IconImage::new_full_content(RgbaImage::new(size, size), scale);
+    /// let set = IconSet::from_images(vec![icon(32, 1.0), icon(64, 2.0), icon(16, 1.0)]);
+    ///
+    /// let pngs = set.to_pngs_by_size().unwrap();
+    /// let slots: Vec<(u32, u32)> = pngs.keys().copied().collect();
+    /// assert_eq!(slots, [(16, 1), (32, 1), (32, 2)]);
+    /// // Written as e.g. `folder_32@2x.png`, the files load back with their scale
+    /// let reloaded = IconSet::from_image_bytes(&pngs[&(32, 2)]).unwrap();
+    /// assert_eq!(reloaded[0].scale, 2.0);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Fails if PNG encoding fails.
+    pub fn to_pngs_by_size(&self) -> Result<BTreeMap<(u32, u32), Vec<u8>>, ImageError> {
+        let mut pngs = BTreeMap::new();
+        for image in &self.images {
+            if let Entry::Vacant(entry) = pngs.entry(image.slot()) {
+                entry.insert(image.to_png()?);
+            }
+        }
+        Ok(pngs)
+    }
+
+    /// Replaces every image's content bounds with the detected ones (see
+    /// [`IconImage::detect_content_bounds`]), instead of hardcoding them per
+    /// platform.
+    pub fn with_detected_content_bounds(self) -> Self {
+        Self::from_images(self.images.into_iter().map(IconImage::with_detected_content_bounds).collect())
+    }
+
+    /// Merges the images of `other` into this set.
+    ///
+    /// Images occupy a slot given by their logical size and scale, both
+    /// rounded to whole numbers (so a 600px @2.34x image competes with a
+    /// 512px @2x one), per [`IconVariant`]. Images from `other` in slots
+    /// this set lacks are added; `policy` decides slots both sets fill. The result is in
+    /// canonical order (see [`sort_canonical`](Self::sort_canonical)).
+    ///
+    /// ```
+    /// use folco_renderer::{IconImage, IconSet, MergePolicy};
+    /// use image::RgbaImage;
+    ///
+    /// let icon = |size, scale| IconImage::new_full_content(RgbaImage::new(size, size), scale);
+    /// let mut system = IconSet::from_images(vec![icon(32, 1.0), icon(64, 2.0)]);
+    /// let artwork = IconSet::from_images(vec![icon(68, 2.1), icon(1024, 2.0)]);
+    ///
+    /// system.merge(artwork, MergePolicy::PreferHigherRes).unwrap();
+    /// let sizes: Vec<u32> = system.iter().map(|img| img.data().width()).collect();
+    /// assert_eq!(sizes, [32, 68, 1024]);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// With [`MergePolicy::ErrorOnConflict`], returns the first slot both
+    /// sets fill, leaving this set unchanged.
+    pub fn merge(&mut self, other: IconSet, policy: MergePolicy) -> Result<(), MergeConflict> {
+        let slot = |image: &IconImage| (image.slot(), image.variant);
+        let own_slots: Vec<_> = self.images.iter().map(slot).collect();
+
+        if policy == MergePolicy::ErrorOnConflict
+            && let Some(((logical_size, scale), _)) = other.iter().map(slot).find(|s| own_slots.contains(s))
+        {
+            return Err(MergeConflict { logical_size, scale });
+        }
+
+        for image in other {
+            let Some(index) = self.images.iter().position(|own| slot(own) == slot(&image)) else {
+                self.images.push(image);
+                continue;
+            };
+            let pixels = |image: &IconImage| image.data().width() as u64 * image.data().height() as u64;
+            if policy == MergePolicy::PreferHigherRes && pixels(&image) > pixels(&self.images[index]) {
+                self.images[index] = image;
+            }
+        }
+        self.sort_canonical();
+        Ok(())
+    }
+}
+
+/// How [`IconSet::merge`] resolves a slot both sets fill.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "jsonschema", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "tsify", derive(tsify_next::Tsify))]
+#[cfg_attr(feature = "tsify", tsify(into_wasm_abi, from_wasm_abi))]
+#[serde(rename_all = "kebab-case")]
+pub enum MergePolicy {
+    /// Keep whichever image has more pixels; ties keep the existing one.
+    #[default]
+    PreferHigherRes,
+    /// Keep the existing image.
+    PreferSelf,
+    /// Fail without merging.
+    ErrorOnConflict,
+}
+
+/// Both sets passed to [`IconSet::merge`] have an image for the same slot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Error)]
+#[error("both icon sets have a {logical_size}px @{scale}x image")]
+pub struct MergeConflict {
+    /// Rounded logical size of the slot.
+    pub logical_size: u32,
+    /// Rounded scale of the slot.
+    pub scale: u32,
+}
+
+impl std::ops::Index<usize> for IconSet {
+    type Output = IconImage;
+
+    fn index(&self, index: usize) -> &IconImage {
+        &self.images[index]
+    }
 }
 
 impl IntoIterator for IconSet {
@@ -194,6 +982,114 @@ impl<'a> IntoIterator for &'a IconSet {
     }
 }
 
+// ============================================================================
+// Image decoding
+// ============================================================================
+
+/// Returns true for multi-image containers, whose entries declare their
+/// own scales.
+fn is_container(bytes: &[u8]) -> bool {
+    bytes.starts_with(icns::MAGIC) || matches!(image::guess_format(bytes), Ok(ImageFormat::Ico | ImageFormat::Tiff))
+}
+
+/// Decodes `bytes` to RGBA, rejecting images larger than `limits`.
+///
+/// Limits are checked against the image header, so an oversized image
+/// fails before any pixel buffer is allocated.
+pub(crate) fn decode_with_limits(bytes: &[u8], limits: RenderLimits) -> Result<RgbaImage, ImageError> {
+    let (width, height) = header_dimensions(bytes)?;
+    if limits.check(width, height).is_err() {
+        return Err(limit_error());
+    }
+
+    let mut decoder_limits = Limits::default();
+    decoder_limits.max_image_width = Some(limits.max_dimension);
+    decoder_limits.max_image_height = Some(limits.max_dimension);
+    let mut reader = ImageReader::new(Cursor::new(bytes)).with_guessed_format()?;
+    reader.limits(decoder_limits);
+    Ok(reader.decode()?.to_rgba8())
+}
+
+/// Reads an encoded image's dimensions from its header, without
+/// allocating the pixel buffer.
+fn header_dimensions(bytes: &[u8]) -> Result<(u32, u32), ImageError> {
+    ImageReader::new(Cursor::new(bytes))
+        .with_guessed_format()?
+        .into_dimensions()
+}
+
+/// The error returned when an image exceeds [`RenderLimits`].
+pub(crate) fn limit_error() -> ImageError {
+    ImageError::Limits(LimitError::from_kind(LimitErrorKind::DimensionError))
+}
+
+/// Multi-page TIFF decoding for [`IconSet::from_image_bytes`].
+#[cfg(feature = "tiff")]
+mod tiff_pages {
+    use std::io::Cursor;
+
+    use image::error::{DecodingError, ImageFormatHint, UnsupportedError, UnsupportedErrorKind};
+    use image::{ImageError, ImageFormat, RgbaImage};
+    use tiff::decoder::{ifd::Value, Decoder, DecodingResult};
+    use tiff::tags::Tag;
+    use tiff::ColorType;
+
+    use super::{limit_error, IconImage, IconSet};
+    use crate::layer::RenderLimits;
+
+    /// Resolution of a 1x macOS icon page.
+    const BASE_DPI: f32 = 72.0;
+
+    pub(super) fn decode(bytes: &[u8], limits: RenderLimits) -> Result<IconSet, ImageError> {
+        let mut decoder = Decoder::new(Cursor::new(bytes)).map_err(decoding_error)?;
+        let mut icons = IconSet::new();
+        loop {
+            let (width, height) = decoder.dimensions().map_err(decoding_error)?;
+            if limits.check(width, height).is_err() {
+                return Err(limit_error());
+            }
+            let color = decoder.colortype().map_err(decoding_error)?;
+            let scale = match decoder.find_tag(Tag::XResolution).map_err(decoding_error)? {
+                Some(Value::Rational(n, d)) if n > 0 && d > 0 => n as f32 / d as f32 / BASE_DPI,
+                _ => 1.0,
+            };
+            let DecodingResult::U8(samples) = decoder.read_image().map_err(decoding_error)? else {
+                return Err(unsupported(color));
+            };
+            let rgba = to_rgba(width, height, color, samples).ok_or_else(|| unsupported(color))?;
+            icons.add_image(IconImage::new_full_content(rgba, scale));
+
+            if !decoder.more_images() {
+                return Ok(icons);
+            }
+            decoder.next_image().map_err(decoding_error)?;
+        }
+    }
+
+    /// Expands 8-bit gray, gray-alpha, RGB or RGBA samples to RGBA.
+    fn to_rgba(width: u32, height: u32, color: ColorType, samples: Vec<u8>) -> Option<RgbaImage> {
+        let rgba = match color {
+            ColorType::RGBA(8) => samples,
+            ColorType::RGB(8) => samples.chunks_exact(3).flat_map(|p| [p[0], p[1], p[2], 255]).collect(),
+            ColorType::GrayA(8) => samples.chunks_exact(2).flat_map(|p| [p[0], p[0], p[0], p[1]]).collect(),
+            ColorType::Gray(8) => samples.iter().flat_map(|&v| [v, v, v, 255]).collect(),
+            _ => return None,
+        };
+        RgbaImage::from_raw(width, height, rgba)
+    }
+
+    fn decoding_error(err: tiff::TiffError) -> ImageError {
+        ImageError::Decoding(DecodingError::new(ImageFormat::Tiff.into(), err))
+    }
+
+    fn unsupported(color: ColorType) -> ImageError {
+        ImageError::Unsupported(UnsupportedError::from_format_and_kind(
+            ImageFormatHint::Exact(ImageFormat::Tiff),
+            UnsupportedErrorKind::GenericFeature(format!("TIFF color type {color:?}")),
+        ))
+    }
+}
+
 /// The RGB color of an icon's primary content surface.
 ///
 /// Used as the reference point when computing color target deltas.
@@ -229,6 +1125,10 @@ pub struct FolderIconBase {
     pub icons: IconSet,
     /// The HSL color of the icon's primary content surface.
     pub surface_color: SurfaceColor,
+    /// The icon as an SVG document, when a vector source is available.
+    ///
+    /// Enables [`FolderIconCustomizer::render_svg_composite`](crate::FolderIconCustomizer::render_svg_composite).
+    pub svg: Option<String>,
 }
 
 impl FolderIconBase {
@@ -237,34 +1137,63 @@ impl FolderIconBase {
         Self {
             icons,
             surface_color,
+            svg: None,
         }
     }
+
+    /// Sets the SVG source of the icon.
+    pub fn with_svg(mut self, svg: impl Into<String>) -> Self {
+        self.svg = Some(svg.into());
+        self
+    }
+}
+
+/// Returns the image whose logical width is closest to `target_size`.
+fn closest_logical_size<'a>(images: impl Iterator<Item = &'a IconImage>, target_size: u32) -> Option<&'a IconImage> {
+    images.min_by_key(|img| {
+        let (logical_w, _) = img.logical_size();
+        (logical_w - target_size as f32).abs() as u32
+    })
 }
 
 // ============================================================================
 // Serializable transfer types
 // ============================================================================
 
-/// PNG-encoded representation of an [`IconImage`] for serialization and IPC.
+/// PNG-encoded or raw representation of an [`IconImage`] for
+/// serialization and IPC.
 ///
 /// Unlike [`IconImage`] (which holds `RgbaImage` in memory), this type stores
 /// the image as PNG bytes, making it suitable for JSON serialization,
-/// Tauri IPC, and wasm-bindgen transfer.
+/// Tauri IPC, and wasm-bindgen transfer. [`raw`](Self::raw) stores the
+/// RGBA bytes instead, trading size for skipping the encode and decode.
 ///
 /// Use `TryFrom<&IconImage>` to convert, or [`SerializableFolderIconBase::try_from`]
-/// to convert an entire [`FolderIconBase`].
+/// to convert an entire [`FolderIconBase`]. [`IconImage`] and [`IconSet`]
+/// also implement serde's traits directly, through this type.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[cfg_attr(feature = "tsify", derive(tsify_next::Tsify))]
 #[cfg_attr(feature = "tsify", tsify(into_wasm_abi, from_wasm_abi))]
 pub struct SerializableIconImage {
-    /// PNG-encoded image bytes.
+    /// PNG-encoded image bytes. Empty if `rgba_data` holds the pixels.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub png_data: Vec<u8>,
+    /// Raw RGBA bytes, row by row with no padding. Empty if `png_data`
+    /// holds the pixels.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rgba_data: Vec<u8>,
     /// Display scale factor (1.0 for @1x, 2.0 for @2x, etc.)
     pub scale: f32,
     /// Pixel width of the image.
     pub width: u32,
     /// Pixel height of the image.
     pub height: u32,
+    /// Content bounds, when they don't cover the whole image.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_bounds: Option<RectPx>,
+    /// The state the image depicts.
+    #[serde(default, skip_serializing_if = "IconVariant::is_normal")]
+    pub variant: IconVariant,
 }
 
 /// Serializable representation of an [`FolderIconBase`] for IPC transfer.
@@ -281,24 +1210,148 @@ pub struct SerializableFolderIconBase {
     pub images: Vec<SerializableIconImage>,
     /// The surface color of the base icon.
     pub surface_color: SurfaceColor,
+    /// The SVG source of the base icon, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub svg: Option<String>,
 }
 
 impl TryFrom<&IconImage> for SerializableIconImage {
     type Error = image::ImageError;
 
     fn try_from(img: &IconImage) -> std::result::Result<Self, Self::Error> {
-        let mut png_bytes = Cursor::new(Vec::new());
-        img.data.write_to(&mut png_bytes, ImageFormat::Png)?;
-
         Ok(Self {
-            png_data: png_bytes.into_inner(),
-            scale: img.scale,
-            width: img.data.width(),
-            height: img.data.height(),
+            png_data: img.to_png()?,
+            ..Self::raw_parts(img, Vec::new())
         })
     }
 }
 
+impl SerializableIconImage {
+    /// Stores `img` as raw RGBA bytes rather than a PNG.
+    pub fn raw(img: &IconImage) -> Self {
+        Self::raw_parts(img, img.data().as_raw().clone())
+    }
+
+    /// Fills in everything but the PNG bytes.
+    fn raw_parts(img: &IconImage, rgba_data: Vec<u8>) -> Self {
+        let (width, height) = img.data().dimensions();
+        Self {
+            png_data: Vec::new(),
+            rgba_data,
+            scale: img.scale,
+            width,
+            height,
+            content_bounds: (img.content_bounds != RectPx::from_size(width, height)).then_some(img.content_bounds),
+            variant: img.variant,
+        }
+    }
+
+    /// Decodes the image. This is the inverse of `TryFrom<&IconImage>` and
+    /// [`raw`](Self::raw).
+    ///
+    /// # Errors
+    ///
+    /// Returns [`RenderError::ImageTooLarge`] if the image exceeds
+    /// `limits`, or [`RenderError::ImageDecode`] if the PNG doesn't decode
+    /// or the raw bytes don't match the dimensions.
+    pub fn into_icon_image_with_limits(self, limits: RenderLimits) -> Result<IconImage, RenderError> {
+        let data = if self.png_data.is_empty() {
+            limits.check(self.width, self.height)?;
+            RgbaImage::from_raw(self.width, self.height, self.rgba_data).ok_or_else(|| {
+                ImageError::Parameter(ParameterError::from_kind(ParameterErrorKind::DimensionMismatch))
+            })?
+        } else {
+            let (width, height) = header_dimensions(&self.png_data)?;
+            limits.check(width, height)?;
+            decode_with_limits(&self.png_data, limits)?
+        };
+        let bounds = self.content_bounds.unwrap_or(RectPx::from_size(data.width(), data.height()));
+        Ok(IconImage::new(data, self.scale, bounds).with_variant(self.variant))
+    }
+}
+
+/// Serializes as a PNG-encoded [`SerializableIconImage`].
+impl Serialize for IconImage {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        SerializableIconImage::try_from(self)
+            .map_err(serde::ser::Error::custom)?
+            .serialize(serializer)
+    }
+}
+
+/// Deserializes from a PNG-encoded or raw [`SerializableIconImage`],
+/// within the default [`RenderLimits`].
+impl<'de> Deserialize<'de> for IconImage {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        SerializableIconImage::deserialize(deserializer)?
+            .into_icon_image_with_limits(RenderLimits::default())
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+/// Serde helpers storing images as raw RGBA instead of PNG, for
+/// `#[serde(with = "folco_renderer::icon::raw_pixels")]` on [`IconImage`] and
+/// [`IconSet`] fields.
+///
+/// Raw pixels are larger but skip PNG encoding, which suits IPC within one
+/// machine. Deserializing accepts either form.
+///
+/// ```
+/// use folco_renderer::{IconImage, IconSet};
+/// use image::RgbaImage;
+///
+/// #[derive(serde::Serialize, serde::Deserialize)]
+/// struct Rendered {
+///     #[serde(with = "folco_renderer::icon::raw_pixels")]
+///     icons: IconSet,
+/// }
+///
+/// let icons = IconSet::from_images(vec![IconImage::new_full_content(RgbaImage::new(2, 2), 1.0)]);
+/// let json = serde_json::to_string(&Rendered { icons: icons.clone() }).unwrap();
+/// assert!(json.contains("rgba_data"));
+/// assert_eq!(serde_json::from_str::<Rendered>(&json).unwrap().icons, icons);
+/// ```
+pub mod raw_pixels {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    use super::{IconImage, IconSet, SerializableIconImage};
+
+    /// Types [`raw_pixels`](self) can serialize.
+    pub trait RawPixels: Sized {
+        /// Serializes with raw RGBA pixels.
+        fn serialize_raw<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error>;
+    }
+
+    impl RawPixels for IconImage {
+        fn serialize_raw<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            SerializableIconImage::raw(self).serialize(serializer)
+        }
+    }
+
+    impl RawPixels for IconSet {
+        fn serialize_raw<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            #[derive(Serialize)]
+            struct RawSet {
+                images: Vec<SerializableIconImage>,
+            }
+            RawSet {
+                images: self.images.iter().map(SerializableIconImage::raw).collect(),
+            }
+            .serialize(serializer)
+        }
+    }
+
+    /// Serializes `value` with raw RGBA pixels.
+    pub fn serialize<T: RawPixels, S: Serializer>(value: &T, serializer: S) -> Result<S::Ok, S::Error> {
+        value.serialize_raw(serializer)
+    }
+
+    /// Deserializes a PNG-encoded or raw value.
+    pub fn deserialize<'de, T: Deserialize<'de>, D: Deserializer<'de>>(deserializer: D) -> Result<T, D::Error> {
+        T::deserialize(deserializer)
+    }
+}
+
 impl TryFrom<&FolderIconBase> for SerializableFolderIconBase {
     type Error = image::ImageError;
 
@@ -312,6 +1365,7 @@ impl TryFrom<&FolderIconBase> for SerializableFolderIconBase {
         Ok(Self {
             images,
             surface_color: base.surface_color,
+            svg: base.svg.clone(),
         })
     }
 }
@@ -319,18 +1373,37 @@ impl TryFrom<&FolderIconBase> for SerializableFolderIconBase {
 impl SerializableFolderIconBase {
     /// Decodes the PNG images and reconstructs an [`FolderIconBase`].
     ///
-    /// This is the inverse of `TryFrom<&FolderIconBase>`.
-    pub fn into_folder_icon_base(self) -> Result<FolderIconBase, image::ImageError> {
+    /// This is the inverse of `TryFrom<&FolderIconBase>`. Images larger
+    /// than the default [`RenderLimits`] are rejected before decoding.
+    ///
+    /// # Errors
+    ///
+    /// Same as [`into_folder_icon_base_with_limits`](Self::into_folder_icon_base_with_limits).
+    pub fn into_folder_icon_base(self) -> Result<FolderIconBase, RenderError> {
+        self.into_folder_icon_base_with_limits(RenderLimits::default())
+    }
+
+    /// Like [`into_folder_icon_base`](Self::into_folder_icon_base), with
+    /// explicit limits on the decoded image dimensions.
+    ///
+    /// Limits are checked against the PNG header, so an oversized image
+    /// fails before any pixel buffer is allocated.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`RenderError::ImageTooLarge`] if an image exceeds
+    /// `limits`, or [`RenderError::ImageDecode`] if one doesn't decode.
+    pub fn into_folder_icon_base_with_limits(self, limits: RenderLimits) -> Result<FolderIconBase, RenderError> {
         let mut icon_set = IconSet::new();
 
-        for img in &self.images {
-            let rgba = image::load_from_memory(&img.png_data)?.to_rgba8();
-            let width = rgba.width();
-            let height = rgba.height();
-            icon_set.add_image(IconImage::new(rgba, img.scale, RectPx::from_size(width, height)));
+        for img in self.images {
+            icon_set.add_image(img.into_icon_image_with_limits(limits)?);
         }
 
-        Ok(FolderIconBase::new(icon_set, self.surface_color))
+        Ok(FolderIconBase {
+            svg: self.svg,
+            ..FolderIconBase::new(icon_set, self.surface_color)
+        })
     }
 }
 
@@ -366,6 +1439,214 @@ mod tests {
         assert_eq!(h, 32.0);
     }
 
+    #[test]
+    fn icon_set_canonical_order() {
+        let image = |size: u32, scale: f32| IconImage::new_full_content(RgbaImage::new(size, size), scale);
+        let mut set = IconSet::from_images(vec![image(64, 2.0), image(16, 1.0), image(32, 1.0), image(32, 2.0)]);
+        set.sort_canonical();
+
+        let order: Vec<_> = set.iter().map(|img| (img.logical_size().0, img.scale)).collect();
+        assert_eq!(order, [(16.0, 1.0), (16.0, 2.0), (32.0, 1.0), (32.0, 2.0)]);
+        assert_eq!(set[1].dimensions(), SizePx::new(32, 32));
+        assert!(set.get(4).is_none());
+    }
+
+    #[test]
+    fn from_image_bytes_decodes_single_image() {
+        let mut png = Vec::new();
+        RgbaImage::from_pixel(8, 4, image::Rgba([1, 2, 3, 255]))
+            .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+            .unwrap();
+
+        let set = IconSet::from_image_bytes(&png).unwrap();
+        assert_eq!(set.len(), 1);
+        assert_eq!((set[0].dimensions(), set[0].scale), (SizePx::new(8, 4), 1.0));
+        assert!(IconSet::from_image_bytes_with_limits(&png, RenderLimits::new(4, 64)).is_err());
+    }
+
+    #[test]
+    fn from_paths_infers_scale_from_file_names() {
+        let dir = std::env::temp_dir().join(format!("folco-scale-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let (one, two) = (dir.join("folder.png"), dir.join("folder@2x.png"));
+        RgbaImage::new(32, 32).save(&two).unwrap();
+        RgbaImage::new(16, 16).save(&one).unwrap();
+
+        let set = IconSet::from_paths([&two, &one]).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        let order: Vec<_> = set.iter().map(|img| (img.dimensions().width, img.scale)).collect();
+        assert_eq!(order, [(16, 1.0), (32, 2.0)]);
+    }
+
+    #[cfg(feature = "tiff")]
+    #[test]
+    fn from_image_bytes_reads_tiff_pages_with_scale() {
+        use tiff::encoder::{colortype::RGBA8, Rational, TiffEncoder};
+        use tiff::tags::ResolutionUnit;
+
+        let mut bytes = Vec::new();
+        let mut encoder = TiffEncoder::new(Cursor::new(&mut bytes)).unwrap();
+        for (size, dpi) in [(16, 72), (32, 144)] {
+            let mut page = encoder.new_image::<RGBA8>(size, size).unwrap();
+            page.resolution(ResolutionUnit::Inch, Rational { n: dpi, d: 1 });
+            page.write_data(&vec![200; (size * size * 4) as usize]).unwrap();
+        }
+
+        let set = IconSet::from_image_bytes(&bytes).unwrap();
+        let pages: Vec<_> = set.iter().map(|img| (img.dimensions().width, img.scale)).collect();
+        assert_eq!(pages, [(16, 1.0), (32, 2.0)]);
+        assert_eq!(set[1].logical_size(), (16.0, 16.0));
+    }
+
+    #[test]
+    fn threshold_alpha_snaps_pixels() {
+        let mut img = IconImage::new_full_content(RgbaImage::new(2, 1), 1.0);
+        img.data_mut().put_pixel(0, 0, image::Rgba([10, 20, 30, 100]));
+        img.data_mut().put_pixel(1, 0, image::Rgba([10, 20, 30, 200]));
+
+        img.threshold_alpha(128);
+        assert_eq!(img.data().get_pixel(0, 0).0, [0, 0, 0, 0]);
+        assert_eq!(img.data().get_pixel(1, 0).0, [10, 20, 30, 255]);
+    }
+
+    #[test]
+    fn transparency_policies() {
+        let half = IconImage::new_full_content(
+            RgbaImage::from_pixel(4, 4, image::Rgba([200, 0, 0, 128])),
+            1.0,
+        );
+
+        let mut preserved = half.clone();
+        preserved.apply_transparency_policy(TransparencyPolicy::Preserve);
+        assert_eq!(preserved, half);
+
+        let mut matte = half.clone();
+        matte.apply_transparency_policy(TransparencyPolicy::Matte { r: 0, g: 0, b: 255 });
+        assert_eq!(matte.data().get_pixel(0, 0).0, [100, 0, 127, 255]);
+
+        let mut threshold = half.clone();
+        threshold.apply_transparency_policy(TransparencyPolicy::Threshold { cutoff: 200 });
+        assert!(threshold.data().pixels().all(|p| p[3] == 0));
+
+        // 50% alpha dithers to roughly half the pixels
+        let mut dithered = half.clone();
+        dithered.apply_transparency_policy(TransparencyPolicy::Dither);
+        let opaque = dithered.data().pixels().filter(|p| p[3] == 255).count();
+        assert!(dithered.data().pixels().all(|p| p[3] == 0 || p[3] == 255));
+        assert_eq!(opaque, 8);
+    }
+
+    #[test]
+    fn refine_edges_removes_halo() {
+        // Opaque red core with a faint grey halo and a stronger dark fringe
+        let mut data = RgbaImage::new(5, 1);
+        data.put_pixel(0, 0, image::Rgba([128, 128, 128, 20]));
+        data.put_pixel(1, 0, image::Rgba([255, 0, 0, 255]));
+        data.put_pixel(2, 0, image::Rgba([255, 0, 0, 255]));
+        data.put_pixel(3, 0, image::Rgba([40, 40, 40, 160]));
+        let mut img = IconImage::new_full_content(data, 1.0);
+
+        img.refine_edges(64);
+        assert_eq!(img.data().get_pixel(0, 0).0, [0, 0, 0, 0], "Faint halo is cleared");
+        assert_eq!(img.data().get_pixel(3, 0).0, [255, 0, 0, 160], "Fringe takes the core color");
+        assert_eq!(img.data().get_pixel(1, 0).0, [255, 0, 0, 255], "Opaque pixels untouched");
+    }
+
+    #[test]
+    fn detected_bounds_ignore_faint_pixels() {
+        let mut data = RgbaImage::new(16, 16);
+        data.put_pixel(3, 4, image::Rgba([0, 0, 0, 255]));
+        data.put_pixel(10, 12, image::Rgba([0, 0, 0, 60]));
+        data.put_pixel(15, 15, image::Rgba([0, 0, 0, 10]));
+        let icon = IconImage::new_full_content(data, 1.0);
+
+        assert_eq!(icon.detect_content_bounds(), Some(RectPx::new(3, 4, 8, 9)));
+        assert_eq!(icon.detect_content_bounds_with_cutoff(0), Some(RectPx::new(3, 4, 13, 12)));
+        assert_eq!(icon.clone().with_detected_content_bounds().content_bounds, RectPx::new(3, 4, 8, 9));
+
+        // Blank images keep their bounds
+        let blank = IconSet::from_images(vec![IconImage::new_full_content(RgbaImage::new(8, 8), 1.0)]);
+        assert_eq!(blank.with_detected_content_bounds()[0].content_bounds, RectPx::from_size(8, 8));
+    }
+
+    #[test]
+    fn stats_are_shared_until_pixels_change() {
+        use crate::analysis::{sample_dominant_color, DominantColorSampling};
+
+        let mut icon = IconImage::new_full_content(RgbaImage::from_pixel(8, 8, image::Rgba([200, 40, 40, 128])), 1.0);
+        icon.data_mut().put_pixel(0, 0, image::Rgba([0, 0, 255, 255]));
+        let stats: *const ImageStats = icon.stats();
+        let copy = icon.clone();
+        assert!(std::ptr::eq(copy.stats(), stats));
+
+        // The memoized mean matches a full sample
+        let full_scan = DominantColorSampling::new().with_excluded_region(RectPx::default());
+        assert_eq!(sample_dominant_color(&icon, &DominantColorSampling::new()), sample_dominant_color(&icon, &full_scan));
+
+        icon.data_mut().put_pixel(7, 7, image::Rgba([0, 0, 0, 0]));
+        assert_eq!(icon.stats().alpha_histogram[0], 1);
+        assert_eq!(copy.stats().alpha_histogram[0], 0);
+        assert_eq!(icon, {
+            let mut edited = copy.clone();
+            edited.data_mut().put_pixel(7, 7, image::Rgba([0, 0, 0, 0]));
+            edited
+        });
+    }
+
+    #[test]
+    fn oversized_serialized_image_is_rejected() {
+        let base = FolderIconBase::new(
+            IconSet::from_images(vec![IconImage::new_full_content(RgbaImage::new(64, 32), 1.0)]),
+            SurfaceColor::new(255, 217, 112),
+        );
+        let serialized = SerializableFolderIconBase::try_from(&base).unwrap();
+
+        let too_wide = serialized.clone().into_folder_icon_base_with_limits(RenderLimits::new(32, u64::MAX));
+        assert!(matches!(too_wide, Err(RenderError::ImageTooLarge { width: 64, height: 32, .. })));
+        assert!(serialized.clone().into_folder_icon_base_with_limits(RenderLimits::new(64, 1024)).is_err());
+
+        let mut corrupt = serialized.clone();
+        corrupt.images[0].png_data.truncate(20);
+        assert!(matches!(corrupt.into_folder_icon_base(), Err(RenderError::ImageDecode { .. })));
+        let restored = serialized.into_folder_icon_base_with_limits(RenderLimits::new(64, 2048)).unwrap();
+        assert_eq!(restored.icons.len(), 1);
+    }
+
+    #[test]
+    fn raw_buffers_with_stride() {
+        // 3x2 pixels, rows padded to 16 bytes, the last row unpadded
+        let mut buffer = vec![0u8; 16 + 12];
+        for (i, byte) in buffer.iter_mut().enumerate() {
+            *byte = i as u8;
+        }
+        let icon = IconImage::from_raw(3, 2, 16, &buffer, 2.0).unwrap();
+        assert_eq!(icon.data().get_pixel(2, 0).0, [8, 9, 10, 11]);
+        assert_eq!(icon.data().get_pixel(0, 1).0, [16, 17, 18, 19]);
+        assert_eq!((icon.scale, icon.content_bounds), (2.0, RectPx::from_size(3, 2)));
+
+        assert!(IconImage::from_raw(3, 2, 8, &buffer, 1.0).is_err());
+        assert!(IconImage::from_raw(3, 2, 16, &buffer[..27], 1.0).is_err());
+        assert!(IconImage::from_raw(3, 2, usize::MAX, &buffer, 1.0).is_err());
+    }
+
+    #[test]
+    fn icon_images_round_trip_through_serde() {
+        let mut data = RgbaImage::new(8, 4);
+        data.put_pixel(3, 1, image::Rgba([10, 20, 30, 255]));
+        let icon = IconImage::new(data, 2.0, RectPx::new(1, 1, 6, 2));
+        let set = IconSet::from_images(vec![icon.clone(), IconImage::new_full_content(RgbaImage::new(4, 4), 1.0)]);
+
+        let json = serde_json::to_string(&set).unwrap();
+        assert!(json.contains("png_data") && !json.contains("rgba_data"));
+        assert_eq!(serde_json::from_str::<IconSet>(&json).unwrap(), set);
+
+        let raw = serde_json::to_value(SerializableIconImage::raw(&icon)).unwrap();
+        assert_eq!(serde_json::from_value::<IconImage>(raw.clone()).unwrap(), icon);
+        let mut truncated = raw;
+        truncated["rgba_data"].as_array_mut().unwrap().pop();
+        assert!(serde_json::from_value::<IconImage>(truncated).is_err());
+    }
+
     #[test]
     fn icon_set_operations() {
         let mut set = IconSet::new();
@@ -388,4 +1669,27 @@ mod tests {
         // Should find the 16x16 since |16-20| < |32-20|
         assert_eq!(found.dimensions().width, 16);
     }
+
+    #[test]
+    fn merge_policies_resolve_shared_slots() {
+        let icon = |size, scale, shade| IconImage::new_full_content(RgbaImage::from_pixel(size, size, image::Rgba([shade, 0, 0, 255])), scale);
+        let base = IconSet::from_images(vec![icon(16, 1.0, 1), icon(32, 2.0, 1)]);
+        let extra = || IconSet::from_images(vec![icon(16, 1.0, 2), icon(33, 2.05, 2), icon(256, 1.0, 2)]);
+        let shades = |set: &IconSet| set.iter().map(|img| img.data().get_pixel(0, 0)[0]).collect::<Vec<_>>();
+
+        let mut merged = base.clone();
+        merged.merge(extra(), MergePolicy::PreferHigherRes).unwrap();
+        // Equal 16px images keep the existing one; the sharper @2x wins
+        assert_eq!(shades(&merged), [1, 2, 2]);
+        assert_eq!(merged[1].data().width(), 33);
+
+        let mut merged = base.clone();
+        merged.merge(extra(), MergePolicy::PreferSelf).unwrap();
+        assert_eq!(shades(&merged), [1, 1, 2]);
+
+        let mut merged = base.clone();
+        let conflict = merged.merge(extra(), MergePolicy::ErrorOnConflict).unwrap_err();
+        assert_eq!(conflict, MergeConflict { logical_size: 16, scale: 1 });
+        assert_eq!(merged, base);
+    }
 }
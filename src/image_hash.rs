@@ -0,0 +1,267 @@
+//! Perceptual hashes of rendered images.
+//!
+//! A [`RenderId`](crate::RenderId) changes whenever any input does, even
+//! if the output looks the same. Re-applying an OS folder icon flickers on
+//! macOS, so apps want to skip it when a config tweak (a hue nudged by one
+//! degree, a decal moved a pixel) left the image visually unchanged.
+//! [`IconImage::phash`] and [`IconImage::dhash`] reduce an image to 64 bits
+//! for each of its luma, chroma and alpha planes, which survive such small
+//! changes and rescaling; compare two hashes with [`ImageHash::distance`].
+
+use std::fmt;
+use std::str::FromStr;
+
+use image::imageops::{self, FilterType};
+use image::{ImageBuffer, Luma};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::icon::IconImage;
+
+/// Side of the downscaled image the DCT of [`IconImage::phash`] runs on.
+const PHASH_SIZE: usize = 32;
+
+/// Side of the low-frequency block of DCT coefficients that is hashed.
+const PHASH_BLOCK: usize = 8;
+
+/// Differences smaller than this, in 0–255 channel levels, don't set a
+/// bit, so flat planes hash the same whatever their rounding noise.
+const NOISE_FLOOR: f32 = 1.0;
+
+/// A single channel of an image, at full precision.
+type Plane = ImageBuffer<Luma<f32>, Vec<f32>>;
+
+/// A 256-bit perceptual hash of an image: 64 bits for each of its luma,
+/// blue-difference chroma, red-difference chroma and alpha planes.
+///
+/// Hashing chroma and alpha separately means a hue change at equal
+/// brightness, or a shape that only differs in transparency, still moves
+/// the hash. Only hashes from the same function are comparable. Formats
+/// and serializes as 64 lowercase hex digits, one plane after another.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ImageHash([u64; 4]);
+
+impl ImageHash {
+    /// Returns the number of differing bits, from 0 (visually identical)
+    /// to 256. Distances up to about 5 usually mean the same picture.
+    pub fn distance(&self, other: &ImageHash) -> u32 {
+        self.0.iter().zip(other.0).map(|(a, b)| (a ^ b).count_ones()).sum()
+    }
+
+    /// Returns the raw hashes of the luma, blue-difference chroma,
+    /// red-difference chroma and alpha planes, in that order.
+    pub fn planes(&self) -> [u64; 4] {
+        self.0
+    }
+}
+
+impl fmt::Display for ImageHash {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.iter().try_for_each(|plane| write!(f, "{plane:016x}"))
+    }
+}
+
+impl FromStr for ImageHash {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.len() != 64 {
+            return Err(format!("expected 64 hex digits, got {}", s.len()));
+        }
+        let mut planes = [0; 4];
+        for (i, plane) in planes.iter_mut().enumerate() {
+            let digits = s.get(i * 16..(i + 1) * 16).ok_or("hash must be ASCII hex digits")?;
+            *plane = u64::from_str_radix(digits, 16).map_err(|e| e.to_string())?;
+        }
+        Ok(Self(planes))
+    }
+}
+
+impl Serialize for ImageHash {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for ImageHash {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+impl IconImage {
+    /// Returns the DCT-based perceptual hash of the image.
+    ///
+    /// Each plane is reduced to 32×32, and each of its lowest 8×8
+    /// frequencies sets a bit if it is above their median. It tolerates
+    /// small color shifts, blur and rescaling better than
+    /// [`dhash`](Self::dhash), at a few times the cost.
+    ///
+    /// ```
+    /// use folco_renderer::IconImage;
+    /// use image::{Rgba, RgbaImage};
+    ///
+    /// let folder = |size: u32, tint| {
+    ///     let body = |x: u32, y: u32| y > size / 5 && y < size * 9 / 10 && x > size / 16 && x < size * 15 / 16;
+    ///     RgbaImage::from_fn(size, size, |x, y| if body(x, y) { Rgba([tint, 150, 243, 255]) } else { Rgba([0; 4]) })
+    /// };
+    /// let small = IconImage::new_full_content(folder(64, 33), 1.0);
+    /// let large = IconImage::new_full_content(folder(256, 36), 4.0);
+    /// assert!(small.phash().distance(&large.phash()) <= 5);
+    /// ```
+    pub fn phash(&self) -> ImageHash {
+        // Separable DCT-II, keeping only the low frequencies
+        let cos: Vec<f32> = (0..PHASH_BLOCK * PHASH_SIZE)
+            .map(|i| {
+                let (k, n) = (i / PHASH_SIZE, i % PHASH_SIZE);
+                (std::f32::consts::PI / PHASH_SIZE as f32 * (n as f32 + 0.5) * k as f32).cos()
+            })
+            .collect();
+        let basis = |k: usize| &cos[k * PHASH_SIZE..(k + 1) * PHASH_SIZE];
+
+        ImageHash(self.hash_planes(PHASH_SIZE as u32, PHASH_SIZE as u32).map(|plane| {
+            let pixels = plane.as_raw();
+            let rows: Vec<f32> = (0..PHASH_SIZE * PHASH_BLOCK)
+                .map(|i| {
+                    let (y, u) = (i / PHASH_BLOCK, i % PHASH_BLOCK);
+                    let row = &pixels[y * PHASH_SIZE..(y + 1) * PHASH_SIZE];
+                    row.iter().zip(basis(u)).map(|(p, c)| p * c).sum()
+                })
+                .collect();
+            // Scaled back to channel levels, so the noise floor applies
+            let coefficients: Vec<f32> = (0..PHASH_BLOCK * PHASH_BLOCK)
+                .map(|i| {
+                    let (v, u) = (i / PHASH_BLOCK, i % PHASH_BLOCK);
+                    let sum: f32 = (0..PHASH_SIZE).map(|y| rows[y * PHASH_BLOCK + u] * basis(v)[y]).sum();
+                    sum / (PHASH_SIZE * PHASH_SIZE) as f32
+                })
+                .collect();
+
+            // The DC term is the overall level, which would skew the median
+            let mut ac = coefficients[1..].to_vec();
+            ac.sort_by(f32::total_cmp);
+            let median = ac[ac.len() / 2];
+            bits(coefficients.iter().map(|&c| c - median > NOISE_FLOOR))
+        }))
+    }
+
+    /// Returns the gradient-based difference hash of the image.
+    ///
+    /// Each plane is reduced to 9×8; each bit says whether a pixel is
+    /// brighter than its right neighbor. Cheap, and enough to tell whether
+    /// a re-render changed anything visible.
+    pub fn dhash(&self) -> ImageHash {
+        ImageHash(self.hash_planes(9, 8).map(|plane| {
+            bits((0..8).flat_map(|y| {
+                let plane = &plane;
+                (0..8).map(move |x| plane.get_pixel(x, y)[0] - plane.get_pixel(x + 1, y)[0] > NOISE_FLOOR)
+            }))
+        }))
+    }
+
+    /// Splits the image into premultiplied luma, blue-difference chroma,
+    /// red-difference chroma and alpha planes, each resized to `width` ×
+    /// `height`.
+    ///
+    /// Premultiplying leaves transparent pixels at zero in the color
+    /// planes; the alpha plane tells them apart from black.
+    fn hash_planes(&self, width: u32, height: u32) -> [Plane; 4] {
+        fn luma(r: f32, g: f32, b: f32) -> f32 {
+            0.299 * r + 0.587 * g + 0.114 * b
+        }
+
+        let (w, h) = self.data().dimensions();
+        let channel = |f: fn([f32; 4]) -> f32, offset: f32| {
+            let plane = Plane::from_fn(w, h, |x, y| {
+                Luma([f(self.data().get_pixel(x, y).0.map(|c| c as f32 / 255.0)) + offset])
+            });
+            // Resizing clamps float pixels to 0.0–1.0, so chroma is offset
+            let mut plane = imageops::resize(&plane, width, height, FilterType::Triangle);
+            plane.iter_mut().for_each(|v| *v = (*v - offset) * 255.0);
+            plane
+        };
+        [
+            channel(|[r, g, b, a]| luma(r, g, b) * a, 0.0),
+            channel(|[r, g, b, a]| 0.564 * (b - luma(r, g, b)) * a, 0.5),
+            channel(|[r, g, b, a]| 0.713 * (r - luma(r, g, b)) * a, 0.5),
+            channel(|[.., a]| a, 0.0),
+        ]
+    }
+}
+
+/// Packs up to 64 flags into a hash, first flag in the highest bit.
+fn bits(flags: impl Iterator<Item = bool>) -> u64 {
+    flags.fold(0, |hash, set| (hash << 1) | set as u64)
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgba, RgbaImage};
+
+    fn folder(size: u32, color: [u8; 3], badge: bool) -> IconImage {
+        let data = RgbaImage::from_fn(size, size, |x, y| {
+            let (u, v) = (x as f32 / size as f32, y as f32 / size as f32);
+            if badge && (u - 0.7).hypot(v - 0.7) < 0.15 {
+                Rgba([255, 255, 255, 255])
+            } else if v > 0.2 && v < 0.9 && u > 0.05 && u < 0.95 {
+                let shade = 1.0 - 0.3 * v;
+                let [r, g, b] = color.map(|c| (c as f32 * shade).round() as u8);
+                Rgba([r, g, b, 255])
+            } else {
+                Rgba([0; 4])
+            }
+        });
+        IconImage::new_full_content(data, 1.0)
+    }
+
+    #[test]
+    fn hashes_ignore_small_changes_but_not_new_content() {
+        let original = folder(128, [33, 150, 243], false);
+        let nudged = folder(128, [36, 148, 240], false);
+        let rescaled = folder(48, [33, 150, 243], false);
+        let badged = folder(128, [33, 150, 243], true);
+
+        for hash in [IconImage::phash, IconImage::dhash] {
+            assert_eq!(hash(&original).distance(&hash(&nudged)), 0);
+            assert!(hash(&original).distance(&hash(&rescaled)) <= 4);
+            assert!(hash(&original).distance(&hash(&badged)) > 5);
+        }
+    }
+
+    #[test]
+    fn hash_round_trips_as_hex_string() {
+        let hash = folder(32, [33, 150, 243], true).phash();
+        let json = serde_json::to_string(&hash).unwrap();
+        assert_eq!(json.len(), 66);
+        assert_eq!(serde_json::from_str::<ImageHash>(&json).unwrap(), hash);
+        assert!(json[1..65].parse::<ImageHash>().is_ok());
+        assert!(json[1..64].parse::<ImageHash>().is_err());
+    }
+
+    #[test]
+    fn hue_and_alpha_changes_move_the_hash() {
+        // Blue and gray of nearly equal luma
+        let blue = folder(128, [33, 150, 243], false);
+        let gray = folder(128, [126, 126, 126], false);
+        // A hole punched through the folder, and a badge of the gray that
+        // used to stand in for transparency
+        let badge = |fill: [u8; 4]| {
+            let mut icon = folder(128, [33, 150, 243], true);
+            for pixel in icon.data_mut().pixels_mut().filter(|p| p.0 == [255; 4]) {
+                pixel.0 = fill;
+            }
+            icon
+        };
+        let (hole, gray_badge) = (badge([0; 4]), badge([128, 128, 128, 255]));
+
+        for hash in [IconImage::phash, IconImage::dhash] {
+            assert!(hash(&blue).distance(&hash(&gray)) > 5);
+            assert!(hash(&hole).distance(&hash(&gray_badge)) > 5);
+        }
+    }
+}
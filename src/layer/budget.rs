@@ -0,0 +1,275 @@
+//! Per-render time budgets.
+//!
+//! An interactive preview would rather show a slightly rough icon on time
+//! than a perfect one a frame late. With a budget set through
+//! [`LayerPipeline::set_time_budget`], the shadow and SVG layers (decal,
+//! overlay and text) switch to a cheaper rendering once the budget is
+//! spent:
+//!
+//! - SVGs are rasterized without anti-aliasing.
+//! - Expanded alpha clips skip their dilation pass and clip to the plain
+//!   icon alpha.
+//! - Drop shadows, standalone or attached to the decal or overlay, skip
+//!   their blur.
+//!
+//! Degraded output is never cached, so the next render with time to spare
+//! (or without a budget, e.g. for export) produces full quality. Which
+//! layers degraded is reported in a [`RenderReport`].
+
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use super::cost::CostEstimate;
+use crate::icon::IconImage;
+use super::warnings::RenderWarning;
+use super::{LayerKind, LayerPipeline, RenderContext};
+
+/// What happened during one [`LayerPipeline::render_with_report`] call.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "tsify", derive(tsify_next::Tsify))]
+#[cfg_attr(feature = "tsify", tsify(into_wasm_abi, from_wasm_abi))]
+#[serde(rename_all = "camelCase")]
+pub struct RenderReport {
+    /// The image was served from the composite cache.
+    pub cached: bool,
+    /// Layers rendered at reduced quality because the time budget ran out,
+    /// in pipeline order. Empty for a full-quality render.
+    pub degraded_layers: Vec<LayerKind>,
+    /// Conditions that made layers draw less than configured, in the
+    /// order raised. Empty for a cached result.
+    #[serde(default)]
+    pub warnings: Vec<RenderWarning>,
+    /// The device pixels the layers rasterized at. `None` only in reports
+    /// not produced by a render.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub effective_size: Option<EffectiveSize>,
+}
+
+impl RenderReport {
+    /// Returns true if any layer rendered at reduced quality.
+    pub fn is_degraded(&self) -> bool {
+        !self.degraded_layers.is_empty()
+    }
+}
+
+/// The pixel grid a render drew on.
+///
+/// Layers rasterize straight onto the base image's pixels, never at the
+/// logical size and then upscaled, so an @2x base reports twice the
+/// pixels of its logical size. Layer and stamp caches are keyed by these
+/// device pixels, so @1x and @2x outputs of the same logical size never
+/// share an entry.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "tsify", derive(tsify_next::Tsify))]
+#[cfg_attr(feature = "tsify", tsify(into_wasm_abi, from_wasm_abi))]
+#[serde(rename_all = "camelCase")]
+pub struct EffectiveSize {
+    /// Width in device pixels.
+    pub width: u32,
+    /// Height in device pixels.
+    pub height: u32,
+    /// Device pixels per logical pixel.
+    pub scale: f32,
+}
+
+impl EffectiveSize {
+    /// Returns the size `image` renders at.
+    pub fn of(image: &IconImage) -> Self {
+        Self {
+            width: image.data().width(),
+            height: image.data().height(),
+            scale: image.scale,
+        }
+    }
+
+    /// Returns the size in logical pixels.
+    pub fn logical_size(&self) -> (f32, f32) {
+        (self.width as f32 / self.scale, self.height as f32 / self.scale)
+    }
+}
+
+/// Marks a render as over its time budget.
+///
+/// Set by the pipeline before each SVG layer once the budget is spent.
+/// Layers that see it render cheaply, skip their cache store and record
+/// themselves in [`DegradedLayers`].
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct OverBudget;
+
+/// Layers that rendered at reduced quality, in pipeline order.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct DegradedLayers(pub(crate) Vec<LayerKind>);
+
+impl DegradedLayers {
+    /// Appends `layer` to the property in `ctx`, creating it if needed.
+    pub(crate) fn push(ctx: &mut RenderContext, layer: LayerKind) {
+        let mut degraded = ctx.get::<Self>().cloned().unwrap_or_default();
+        degraded.0.push(layer);
+        ctx.set(degraded);
+    }
+}
+
+/// Tracks one render against the pipeline's time budget.
+pub(crate) struct Deadline {
+    budget: Option<Duration>,
+    /// Recent timings predict the render can't finish in time.
+    predicted_over: bool,
+    #[cfg(not(target_arch = "wasm32"))]
+    start: std::time::Instant,
+}
+
+impl Deadline {
+    /// Starts the clock for a render expected to cost `estimate`.
+    ///
+    /// If even the fastest recent rate misses the budget, every SVG layer
+    /// degrades from the start instead of after the first one runs over.
+    pub(crate) fn start(budget: Option<Duration>, estimate: Option<&CostEstimate>) -> Self {
+        let predicted_over = budget
+            .zip(estimate.and_then(|e| e.expected_ms))
+            .is_some_and(|(budget, (min, _))| min as f64 > budget.as_secs_f64() * 1000.0);
+        Self {
+            budget,
+            predicted_over,
+            #[cfg(not(target_arch = "wasm32"))]
+            start: std::time::Instant::now(),
+        }
+    }
+
+    /// Returns true once the budget is spent.
+    ///
+    /// On `wasm32`, where `std::time::Instant` is unavailable, only the
+    /// up-front prediction applies.
+    pub(crate) fn exceeded(&self) -> bool {
+        let Some(_budget) = self.budget else {
+            return false;
+        };
+        #[cfg(not(target_arch = "wasm32"))]
+        if self.start.elapsed() > _budget {
+            return true;
+        }
+        self.predicted_over
+    }
+
+    /// Marks `ctx` as over budget if the budget is spent.
+    pub(crate) fn check(&self, ctx: &mut RenderContext) {
+        if self.exceeded() {
+            ctx.set(OverBudget);
+        }
+    }
+}
+
+impl LayerPipeline {
+    /// Returns the per-render time budget, if any.
+    pub fn time_budget(&self) -> Option<Duration> {
+        self.time_budget
+    }
+
+    /// Sets a per-render time budget, or `None` to always render at full
+    /// quality.
+    ///
+    /// Once a render exceeds the budget, remaining SVG layers degrade (see
+    /// the [module docs](self)). Clear the budget before exporting.
+    pub fn set_time_budget(&mut self, budget: Option<Duration>) {
+        self.time_budget = budget;
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use image::RgbaImage;
+
+    use super::*;
+    use crate::icon::{IconImage, SurfaceColor};
+    use crate::layer::{DecalConfig, OverlayPosition, ShadowConfig, SvgOverlayConfig};
+
+    const CIRCLE: &str = r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 10 10"><circle cx="5" cy="5" r="4"/></svg>"#;
+
+    fn has_partial_alpha(image: &RgbaImage) -> bool {
+        image.pixels().any(|p| p[3] > 0 && p[3] < 255)
+    }
+
+    #[test]
+    fn exhausted_budget_degrades_without_caching() {
+        let surface = SurfaceColor::new(255, 217, 112);
+        // A transparent base leaves the layers' edge coverage visible
+        let base = IconImage::new_full_content(RgbaImage::new(64, 64), 1.0);
+        let mut pipeline = LayerPipeline::default();
+        pipeline.overlay.set_config(Some(SvgOverlayConfig::new(CIRCLE, OverlayPosition::Center, 0.8)));
+        pipeline.decal.set_config(Some(DecalConfig::new(CIRCLE, 0.3)));
+
+        pipeline.set_time_budget(Some(Duration::ZERO));
+        let (draft, report) = pipeline.render_with_report(&base, &surface).unwrap();
+        assert_eq!(report.degraded_layers, [LayerKind::Decal, LayerKind::Overlay]);
+        assert!(!has_partial_alpha(draft.data()));
+
+        pipeline.set_time_budget(None);
+        let (full, report) = pipeline.render_with_report(&base, &surface).unwrap();
+        assert!(!report.cached && !report.is_degraded());
+        assert!(has_partial_alpha(full.data()));
+
+        let (_, report) = pipeline.render_with_report(&base, &surface).unwrap();
+        assert!(report.cached);
+    }
+
+    #[test]
+    fn standalone_shadow_degrades_without_caching() {
+        let surface = SurfaceColor::new(255, 217, 112);
+        let mut base = RgbaImage::new(32, 32);
+        for y in 8..24 {
+            for x in 8..24 {
+                base.put_pixel(x, y, image::Rgba([0, 0, 0, 255]));
+            }
+        }
+        let base = IconImage::new_full_content(base, 1.0);
+        let mut pipeline = LayerPipeline::default();
+        pipeline.shadow.set_config(Some(ShadowConfig::new(0.1, 0.1, 0.2)));
+
+        pipeline.set_time_budget(Some(Duration::ZERO));
+        let (_, report) = pipeline.render_with_report(&base, &surface).unwrap();
+        assert_eq!(report.degraded_layers, [LayerKind::Shadow]);
+        assert_eq!(pipeline.shadow.cache_footprint().count(), 0);
+
+        pipeline.set_time_budget(None);
+        let (_, report) = pipeline.render_with_report(&base, &surface).unwrap();
+        assert!(!report.is_degraded());
+        assert_eq!(pipeline.shadow.cache_footprint().count(), 1);
+    }
+
+    #[test]
+    fn deadline_follows_budget_and_prediction() {
+        let estimate = CostEstimate {
+            cached: false,
+            pixels_touched: 1,
+            svg_parse: true,
+            expected_ms: Some((50.0, 80.0)),
+        };
+        assert!(!Deadline::start(None, Some(&estimate)).exceeded());
+        assert!(Deadline::start(Some(Duration::from_secs(1) / 60), Some(&estimate)).exceeded());
+        assert!(!Deadline::start(Some(Duration::from_secs(1)), Some(&estimate)).exceeded());
+    }
+
+    #[test]
+    fn reports_the_device_pixels_rendered() {
+        let surface = SurfaceColor::new(255, 217, 112);
+        let mut pipeline = LayerPipeline::default();
+        pipeline.decal.set_config(Some(DecalConfig::new(CIRCLE, 0.5)));
+
+        let retina = IconImage::new_full_content(RgbaImage::new(64, 64), 2.0);
+        let (_, report) = pipeline.render_with_report(&retina, &surface).unwrap();
+        let size = report.effective_size.unwrap();
+        assert_eq!((size.width, size.height, size.logical_size()), (64, 64, (32.0, 32.0)));
+
+        // Same logical size at @1x: a separate cache entry
+        let standard = IconImage::new_full_content(RgbaImage::new(32, 32), 1.0);
+        let (_, report) = pipeline.render_with_report(&standard, &surface).unwrap();
+        assert!(!report.cached);
+        assert_eq!(report.effective_size, Some(EffectiveSize { width: 32, height: 32, scale: 1.0 }));
+        let (_, report) = pipeline.render_with_report(&retina, &surface).unwrap();
+        assert!(report.cached && report.effective_size.unwrap().width == 64);
+    }
+}
@@ -0,0 +1,75 @@
+//! Abandoning renders that are no longer wanted.
+//!
+//! While the user drags a slider, every render but the latest is stale by
+//! the time it finishes. A pipeline with a [`CancellationToken`] checks it
+//! before each layer and stops with [`RenderError::Cancelled`] once it is
+//! cancelled, from any thread. Layers that already finished keep their
+//! caches; the cancelled composite is not cached.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use super::LayerPipeline;
+use crate::error::RenderError;
+
+/// A flag that stops renders checking it. Clones share the flag.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    /// Creates a token that is not cancelled.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cancels every render checking this token or one of its clones.
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    /// Returns true once [`cancel`](Self::cancel) was called.
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+impl LayerPipeline {
+    /// Checks `token` before each layer of later renders, stopping with
+    /// [`RenderError::Cancelled`] once it is cancelled. `None` removes it.
+    ///
+    /// Results already in the composite cache are returned even after
+    /// cancellation, as they cost nothing.
+    ///
+    /// ```
+    /// use folco_renderer::layer::cancel::CancellationToken;
+    /// use folco_renderer::{FolderColorTargetConfig, IconImage, LayerPipeline, RenderError, SurfaceColor};
+    /// use image::RgbaImage;
+    ///
+    /// let mut pipeline = LayerPipeline::default();
+    /// pipeline.folder_color_target.set_config(Some(FolderColorTargetConfig::new(33, 150, 243)));
+    /// let token = CancellationToken::new();
+    /// pipeline.set_cancellation_token(Some(token.clone()));
+    ///
+    /// token.cancel();
+    /// let base = IconImage::new_full_content(RgbaImage::new(32, 32), 1.0);
+    /// let result = pipeline.render(&base, &SurfaceColor::new(255, 217, 112));
+    /// assert!(matches!(result, Err(RenderError::Cancelled)));
+    /// ```
+    pub fn set_cancellation_token(&mut self, token: Option<CancellationToken>) {
+        self.cancellation = token;
+    }
+
+    /// Returns the token set with
+    /// [`set_cancellation_token`](Self::set_cancellation_token), if any.
+    pub fn cancellation_token(&self) -> Option<&CancellationToken> {
+        self.cancellation.as_ref()
+    }
+
+    /// Fails with [`RenderError::Cancelled`] if the token is cancelled.
+    pub(crate) fn check_cancelled(&self) -> Result<(), RenderError> {
+        match &self.cancellation {
+            Some(token) if token.is_cancelled() => Err(RenderError::Cancelled),
+            _ => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,193 @@
+//! Dry-run render cost estimation.
+//!
+//! [`LayerPipeline::estimate_cost`] inspects layer caches without rendering
+//! to predict how much work the next [`LayerPipeline::render`] call will do.
+//! Hosts can use the estimate to choose between a synchronous render, an
+//! async render, or a draft-quality preview before committing.
+
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::icon::IconImage;
+
+use super::{CacheKey, LayerKind, LayerPipeline};
+
+/// Number of recent renders kept per workload class.
+const METRICS_WINDOW: usize = 16;
+
+/// Predicted cost of rendering one icon through the pipeline.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "tsify", derive(tsify_next::Tsify))]
+#[cfg_attr(feature = "tsify", tsify(into_wasm_abi, from_wasm_abi))]
+#[serde(rename_all = "camelCase")]
+pub struct CostEstimate {
+    /// The final image is already in the composite cache.
+    pub cached: bool,
+    /// Approximate number of pixels the render will read or write,
+    /// summed across layers that must re-render and tile composites.
+    pub pixels_touched: u64,
+    /// At least one SVG layer must re-render (parse and rasterize).
+    pub svg_parse: bool,
+    /// Expected wall time as `(min, max)` milliseconds, based on the
+    /// per-pixel rates of recent renders with the same `svg_parse` value.
+    /// `None` until such a render has been measured.
+    pub expected_ms: Option<(f32, f32)>,
+}
+
+/// Rolling per-pixel render rates, split by whether SVG work was involved.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct RenderMetrics {
+    raster: VecDeque<f64>,
+    svg: VecDeque<f64>,
+}
+
+impl RenderMetrics {
+    fn window(&self, svg_parse: bool) -> &VecDeque<f64> {
+        if svg_parse { &self.svg } else { &self.raster }
+    }
+
+    /// Records a measured render as nanoseconds per touched pixel.
+    fn record(&mut self, estimate: &CostEstimate, elapsed: Duration) {
+        if estimate.cached || estimate.pixels_touched == 0 {
+            return;
+        }
+        let window = if estimate.svg_parse { &mut self.svg } else { &mut self.raster };
+        if window.len() == METRICS_WINDOW {
+            window.pop_front();
+        }
+        window.push_back(elapsed.as_nanos() as f64 / estimate.pixels_touched as f64);
+    }
+
+    /// Scales the recorded rate range to the given pixel count.
+    fn expected_ms(&self, svg_parse: bool, pixels: u64) -> Option<(f32, f32)> {
+        let window = self.window(svg_parse);
+        let min = window.iter().copied().reduce(f64::min)?;
+        let max = window.iter().copied().reduce(f64::max)?;
+        let to_ms = |rate: f64| (rate * pixels as f64 / 1_000_000.0) as f32;
+        Some((to_ms(min), to_ms(max)))
+    }
+}
+
+impl LayerPipeline {
+    /// Estimates the cost of rendering `base` without rendering it.
+    ///
+    /// Layers whose cached output is still valid for this size cost nothing;
+    /// every layer that must re-render is charged a full pass over the
+    /// image, and each active tile layer is charged its composite.
+    pub fn estimate_cost(&self, base: &IconImage) -> CostEstimate {
+        let key = CacheKey::from_icon(base);
+        if self.composite.get_cached(key, self.composite_dependencies()).is_some() {
+            return CostEstimate {
+                cached: true,
+                pixels_touched: 0,
+                svg_parse: false,
+                expected_ms: Some((0.0, 0.0)),
+            };
+        }
+
+        let pixels = base.data().width() as u64 * base.data().height() as u64;
+        let versions = self.layer_versions();
+        let mut pixels_touched = 0;
+        let mut svg_parse = false;
+
+        let target = &self.folder_color_target;
+        if self.renders(LayerKind::FolderColorTarget) && target.get_cached(key, target.dependencies(&versions)).is_none() {
+            pixels_touched += pixels;
+        }
+        let tint = &self.gradient_tint;
+        if self.renders(LayerKind::GradientTint) && tint.get_cached(key, tint.dependencies(&versions)).is_none() {
+            pixels_touched += pixels;
+        }
+        let shadow = &self.shadow;
+        if self.renders(LayerKind::Shadow) && shadow.get_cached(key, shadow.dependencies(&versions)).is_none() {
+            pixels_touched += pixels;
+        }
+        for (active, cached) in [
+            (self.renders(LayerKind::Decal), self.decal.get_cached(key, self.decal.dependencies(&versions)).is_some()),
+            (self.renders(LayerKind::Overlay), self.overlay.get_cached(key, self.overlay.dependencies(&versions)).is_some()),
+            (self.renders(LayerKind::Text), self.text.get_cached(key, self.text.dependencies(&versions)).is_some()),
+        ] {
+            if active {
+                // Compositing the tile touches every pixel either way
+                pixels_touched += if cached { pixels } else { pixels * 2 };
+                svg_parse |= !cached;
+            }
+        }
+
+        CostEstimate {
+            cached: false,
+            pixels_touched,
+            svg_parse,
+            expected_ms: self.metrics.expected_ms(svg_parse, pixels_touched),
+        }
+    }
+
+    /// Feeds a measured render time back into future estimates.
+    ///
+    /// [`render`](Self::render) records its own timings on native targets.
+    /// On `wasm32`, where `std::time::Instant` is unavailable, hosts can
+    /// measure with `performance.now()` and report here instead.
+    pub fn record_render_time(&mut self, estimate: &CostEstimate, elapsed: Duration) {
+        self.metrics.record(estimate, elapsed);
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::icon::SurfaceColor;
+    use crate::layer::{FolderColorTargetConfig, OverlayPosition, SvgOverlayConfig};
+
+    const TEST_SVG: &str = r##"<svg xmlns="http://www.w3.org/2000/svg" width="10" height="10"><rect width="10" height="10" fill="red"/></svg>"##;
+
+    #[test]
+    fn estimate_reflects_cache_state() {
+        let base = IconImage::new_full_content(image::RgbaImage::new(16, 16), 1.0);
+        let surface = SurfaceColor::new(255, 217, 112);
+        let mut pipeline = LayerPipeline::default();
+        pipeline
+            .folder_color_target
+            .set_config(Some(FolderColorTargetConfig::new(33, 150, 243)));
+        pipeline
+            .overlay
+            .set_config(Some(SvgOverlayConfig::new(TEST_SVG, OverlayPosition::Center, 0.5)));
+
+        let cold = pipeline.estimate_cost(&base);
+        assert!(!cold.cached);
+        assert!(cold.svg_parse);
+        assert_eq!(cold.pixels_touched, 256 * 3);
+
+        pipeline.render(&base, &surface).unwrap();
+        assert!(pipeline.estimate_cost(&base).cached);
+
+        // Only the color target re-renders; the overlay tile is reused
+        pipeline
+            .folder_color_target
+            .set_config(Some(FolderColorTargetConfig::new(76, 175, 80)));
+        let warm = pipeline.estimate_cost(&base);
+        assert!(!warm.svg_parse);
+        assert_eq!(warm.pixels_touched, 256 * 2);
+    }
+
+    #[test]
+    fn expected_time_scales_recorded_rates() {
+        let base = IconImage::new_full_content(image::RgbaImage::new(10, 10), 1.0);
+        let mut pipeline = LayerPipeline::default();
+        pipeline
+            .folder_color_target
+            .set_config(Some(FolderColorTargetConfig::new(33, 150, 243)));
+
+        let estimate = pipeline.estimate_cost(&base);
+        assert_eq!(estimate.expected_ms, None);
+
+        pipeline.record_render_time(&estimate, Duration::from_millis(1));
+        pipeline.record_render_time(&estimate, Duration::from_millis(3));
+        assert_eq!(pipeline.estimate_cost(&base).expected_ms, Some((1.0, 3.0)));
+    }
+}
@@ -1,9 +1,16 @@
 //! Decal imprint layer — configuration and rendering.
 
-use super::svg::{composite_over, render_source_with_color, SvgSource};
-use super::{CacheKey, CachedOutput, DependencyVersion, DominantColor, Layer, LayerConfig, LayerVersions, RenderContext};
+use super::budget::DegradedLayers;
+use super::overlay::{clip_to_alpha, is_zero};
+use super::shadow::{drop_shadow, ShadowConfig};
+use super::placement::PlacementRounding;
+use super::stamps::{composite_keyed_stamp, StampKey};
+use super::warnings::RenderWarning;
+use super::svg::{fade, full_opacity, is_full_opacity, stamp_svg_with_color_at, BlendMode, stamp_svg_with_current_color_at, uses_current_color, RasterQuality, SvgSource};
+use super::{finite_or, AccentColor, CacheKey, CachedOutput, DecalPlacement, DependencyVersion, DominantColor, OccupiedRegions, Layer, LayerConfig, LayerKind, LayerVersions, RenderContext};
+use crate::analysis::{relative_luminance, sample_dominant_color, DominantColorSampling};
 use crate::error::RenderError;
-use crate::icon::SurfaceColor;
+use crate::icon::{RectPx, SurfaceColor, SHADOW_ALPHA_CUTOFF};
 use image::RgbaImage;
 use palette::{Hsl, IntoColor, Srgb};
 
@@ -25,10 +32,62 @@ const DECAL_DARKEN_AMOUNT: f32 = 0.25;
 #[serde(rename_all = "camelCase")]
 pub struct DecalConfig {
     /// The SVG source (should be a monochrome/single-color SVG).
+    ///
+    /// SVGs that paint with `currentColor` have only those paints colored;
+    /// any other SVG has all fills and strokes replaced.
     pub source: SvgSource,
 
     /// Scale factor relative to the icon's content bounds (0.0-1.0).
     pub scale: f32,
+
+    /// Optional size-dependent scale, overriding `scale` when non-empty.
+    ///
+    /// Breakpoints are sorted by logical size, and sorted again when
+    /// deserialized and when passed to [`Layer::set_config`]. Between
+    /// breakpoints the scale is interpolated on a log2 size axis (so 16→32
+    /// weighs the same as 256→512); outside the range the nearest
+    /// breakpoint applies.
+    #[serde(default, deserialize_with = "deserialize_scale_curve", skip_serializing_if = "Vec::is_empty")]
+    pub scale_curve: Vec<ScaleBreakpoint>,
+
+    /// Optional drop shadow beneath the decal glyph.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub shadow: Option<ShadowConfig>,
+
+    /// How the decal (with its shadow) blends with the icon beneath.
+    #[serde(default, skip_serializing_if = "BlendMode::is_normal")]
+    pub blend_mode: BlendMode,
+
+    /// Opacity of the decal and its shadow (0.0-1.0), e.g. for a faint
+    /// watermark. Defaults to opaque.
+    #[serde(default = "full_opacity", skip_serializing_if = "is_full_opacity")]
+    pub opacity: f32,
+
+    /// Relative luminance (0.0-1.0) below which the sampled folder color is
+    /// lightened instead of darkened, so the decal stays visible on dark
+    /// folders. Defaults to always darkening.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lighten_below: Option<f32>,
+
+    /// Whether the decal (with its shadow) is masked by the icon's alpha,
+    /// so it never spills outside the folder shape, as
+    /// [`OverlayClip::BaseAlpha`](super::OverlayClip::BaseAlpha) does for
+    /// overlays. Defaults to false.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub clip_to_alpha: bool,
+
+    /// Radius in pixels over which the edge of the alpha clip is
+    /// antialiased (see [`feather_edges`](super::feather_edges)). Only
+    /// used with `clip_to_alpha`. Defaults to 0, a plain cut.
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub clip_feather: f32,
+
+    /// Whether the decal's color is sampled from the icon behind the glyph,
+    /// as it looks after the color target, gradient tint and shadow, rather
+    /// than taken from the [`DominantColor`]. Keeps the decal matched to
+    /// the part of a multicolor base it sits on. Defaults to false.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub sample_behind: bool,
 }
 
 impl DecalConfig {
@@ -38,14 +97,150 @@ impl DecalConfig {
     pub fn new(svg: impl Into<String>, scale: f32) -> Self {
         Self {
             source: SvgSource::Raw(svg.into()),
-            scale: scale.clamp(0.0, 1.0),
+            scale: finite_or(scale, 0.0).clamp(0.0, 1.0),
+            scale_curve: Vec::new(),
+            shadow: None,
+            blend_mode: BlendMode::Normal,
+            opacity: 1.0,
+            lighten_below: None,
+            clip_to_alpha: false,
+            clip_feather: 0.0,
+            sample_behind: false,
         }
     }
+
+    /// Sets a size-dependent scale curve. Breakpoints are sorted by size.
+    pub fn with_scale_curve(mut self, mut curve: Vec<ScaleBreakpoint>) -> Self {
+        curve.sort_by_key(|b| b.size);
+        self.scale_curve = curve;
+        self
+    }
+
+    /// Sets a drop shadow beneath the decal glyph.
+    pub fn with_shadow(mut self, shadow: ShadowConfig) -> Self {
+        self.shadow = Some(shadow);
+        self
+    }
+
+    /// Sets how the decal blends with the icon beneath.
+    pub fn with_blend_mode(mut self, blend_mode: BlendMode) -> Self {
+        self.blend_mode = blend_mode;
+        self
+    }
+
+    /// Sets the opacity of the decal and its shadow. Clamped to 0.0-1.0.
+    pub fn with_opacity(mut self, opacity: f32) -> Self {
+        self.opacity = finite_or(opacity, 1.0).clamp(0.0, 1.0);
+        self
+    }
+
+    /// Lightens the decal instead of darkening it when the folder color's
+    /// relative luminance is below `luminance`. Clamped to 0.0-1.0.
+    pub fn with_lighten_below(mut self, luminance: f32) -> Self {
+        self.lighten_below = Some(finite_or(luminance, 0.0).clamp(0.0, 1.0));
+        self
+    }
+
+    /// Sets whether the decal is masked by the icon's alpha.
+    pub fn with_clip_to_alpha(mut self, clip_to_alpha: bool) -> Self {
+        self.clip_to_alpha = clip_to_alpha;
+        self
+    }
+
+    /// Sets the radius over which the edge of the alpha clip is
+    /// antialiased. Negative values are treated as 0.
+    pub fn with_clip_feather(mut self, radius: f32) -> Self {
+        self.clip_feather = finite_or(radius, 0.0).max(0.0);
+        self
+    }
+
+    /// Sets whether the decal's color is sampled from the icon behind it.
+    pub fn with_sample_behind(mut self, sample_behind: bool) -> Self {
+        self.sample_behind = sample_behind;
+        self
+    }
+
+    /// Returns the scale to use for an icon of the given logical size.
+    ///
+    /// Expects the curve sorted, as the constructors, deserialization and
+    /// [`Layer::set_config`] leave it.
+    pub fn scale_for_size(&self, logical_size: f32) -> f32 {
+        let curve = &self.scale_curve;
+        let (Some(first), Some(last)) = (curve.first(), curve.last()) else {
+            return self.scale;
+        };
+
+        let size = logical_size.max(1.0);
+        if size <= first.size as f32 {
+            return first.scale;
+        }
+        if size >= last.size as f32 {
+            return last.scale;
+        }
+
+        let upper = curve.partition_point(|b| (b.size as f32) < size);
+        let (lo, hi) = (&curve[upper - 1], &curve[upper]);
+        let (lo_log, hi_log) = ((lo.size.max(1) as f32).log2(), (hi.size as f32).log2());
+        let t = if hi_log > lo_log {
+            (size.log2() - lo_log) / (hi_log - lo_log)
+        } else {
+            0.0
+        };
+        lo.scale + (hi.scale - lo.scale) * t
+    }
 }
 
 impl LayerConfig for DecalConfig {
     fn differs_from(&self, other: &Self) -> bool {
-        self.source != other.source || (self.scale - other.scale).abs() > 0.0001
+        self.source != other.source
+            || (self.scale - other.scale).abs() > 0.0001
+            || self.scale_curve.len() != other.scale_curve.len()
+            || self.scale_curve.iter().zip(&other.scale_curve).any(|(a, b)| {
+                a.size != b.size || (a.scale - b.scale).abs() > 0.0001
+            })
+            || self.shadow != other.shadow
+            || self.blend_mode != other.blend_mode
+            || (self.opacity - other.opacity).abs() > 0.0001
+            || self.lighten_below != other.lighten_below
+            || self.clip_to_alpha != other.clip_to_alpha
+            || (self.clip_feather - other.clip_feather).abs() > 0.0001
+            || self.sample_behind != other.sample_behind
+    }
+
+    fn external_version(&self) -> u64 {
+        self.source.content_version()
+    }
+
+    fn normalize(&mut self) {
+        self.scale_curve.sort_by_key(|b| b.size);
+    }
+}
+
+/// Deserializes [`DecalConfig::scale_curve`], sorted by size.
+fn deserialize_scale_curve<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<Vec<ScaleBreakpoint>, D::Error> {
+    let mut curve = <Vec<ScaleBreakpoint> as serde::Deserialize>::deserialize(deserializer)?;
+    curve.sort_by_key(|b| b.size);
+    Ok(curve)
+}
+
+/// A point on a decal's size-dependent scale curve.
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "jsonschema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct ScaleBreakpoint {
+    /// Logical icon size in pixels (e.g. 16, 32, 256).
+    pub size: u32,
+    /// Scale factor at this size (0.0-1.0).
+    pub scale: f32,
+}
+
+impl ScaleBreakpoint {
+    /// Creates a breakpoint. The scale is clamped to 0.0-1.0.
+    pub fn new(size: u32, scale: f32) -> Self {
+        Self {
+            size,
+            scale: finite_or(scale, 0.0).clamp(0.0, 1.0),
+        }
     }
 }
 
@@ -54,31 +249,76 @@ impl LayerConfig for DecalConfig {
 // ============================================================================
 
 impl Layer<DecalConfig> {
+    /// Returns how the tile blends with the icon beneath.
+    pub(crate) fn blend_mode(&self) -> BlendMode {
+        self.config().map_or(BlendMode::Normal, |config| config.blend_mode)
+    }
+
+    /// Returns the upstream versions this layer's cache depends on.
+    ///
+    /// Clipping to the icon's alpha also follows the shadow, which widens
+    /// the icon's silhouette, and sampling the color behind the glyph
+    /// follows every layer that changes the icon's pixels.
+    pub(crate) fn dependencies(&self, versions: &LayerVersions) -> DependencyVersion {
+        if self.config().is_some_and(|config| config.sample_behind) {
+            DependencyVersion::combine(&[versions.folder_color_target, versions.gradient_tint, versions.accent, versions.shadow])
+        } else if self.config().is_some_and(|config| config.clip_to_alpha) {
+            DependencyVersion::combine(&[versions.folder_color_target, versions.accent, versions.shadow])
+        } else {
+            DependencyVersion::combine(&[versions.folder_color_target, versions.accent])
+        }
+    }
+
     /// Render this decal layer, returning a tile for compositing.
     ///
     /// Returns `None` if inactive. The tile is a transparent canvas with
     /// the decal rendered at the center using a darkened version of the
-    /// upstream [`DominantColor`] (or the [`SurfaceColor`] fallback).
+    /// upstream [`DominantColor`] (or the [`AccentColor`] / [`SurfaceColor`] fallback).
+    /// Emits [`DecalPlacement`] and adds to [`OccupiedRegions`] when the
+    /// decal is visible.
     pub fn render_tile(
         &mut self,
         ctx: &mut RenderContext,
         key: CacheKey,
         versions: &LayerVersions,
+    ) -> Result<Option<RgbaImage>, RenderError> {
+        let deps = self.dependencies(versions);
+        self.render_tile_with_deps(ctx, key, deps)
+    }
+
+    /// Like [`render_tile`](Self::render_tile), with the cache validated
+    /// against a caller-computed dependency version.
+    pub(crate) fn render_tile_with_deps(
+        &mut self,
+        ctx: &mut RenderContext,
+        key: CacheKey,
+        deps: DependencyVersion,
     ) -> Result<Option<RgbaImage>, RenderError> {
         if !self.is_active() {
             return Ok(None);
         }
 
-        let deps = DependencyVersion::from_version(versions.folder_color_target);
 
-        if let Some(CachedOutput::Tile(tile)) = self.get_cached(key, deps) {
+        if let Some(CachedOutput::Tile(tile, placement)) = self.get_cached(key, deps) {
+            if let Some(bounds) = *placement {
+                ctx.set(DecalPlacement { bounds });
+                OccupiedRegions::push(ctx, bounds);
+            }
             return Ok(Some(tile.clone()));
         }
 
         let config = self.config().unwrap();
-        let tile = render_decal(config, ctx)?;
+        let (tile, placement) = render_decal(config, ctx)?;
+        if let Some(bounds) = placement {
+            ctx.set(DecalPlacement { bounds });
+            OccupiedRegions::push(ctx, bounds);
+        }
 
-        self.store(key, CachedOutput::Tile(tile.clone()), deps);
+        if RasterQuality::of(ctx).is_draft() {
+            DegradedLayers::push(ctx, LayerKind::Decal);
+        } else {
+            self.store(key, CachedOutput::Tile(tile.clone(), placement), deps);
+        }
         Ok(Some(tile))
     }
 }
@@ -86,43 +326,120 @@ impl Layer<DecalConfig> {
 /// Renders a decal onto a transparent tile matching the icon dimensions.
 ///
 /// Uses [`DominantColor`] from the context if available, otherwise falls
-/// back to the [`SurfaceColor`]. The color is darkened before rendering.
+/// back to the [`AccentColor`] and then the [`SurfaceColor`], unless
+/// [`DecalConfig::sample_behind`] samples the icon behind the glyph. The
+/// color is darkened before rendering. If the SVG uses `currentColor`, only those
+/// paints receive the color; otherwise every fill and stroke is replaced.
+///
+/// Also returns the rectangle the decal was drawn into, or `None` if it
+/// rendered at zero size, which raises a [`RenderWarning`].
 pub(crate) fn render_decal(
     config: &DecalConfig,
-    ctx: &RenderContext,
-) -> Result<RgbaImage, RenderError> {
-    let dominant_color = ctx
-        .get::<DominantColor>()
-        .map(|c| c.as_tuple())
-        .unwrap_or_else(|| {
-            let sc = ctx
-                .get::<SurfaceColor>()
-                .expect("SurfaceColor must be set in RenderContext");
-            (sc.r, sc.g, sc.b, 255)
-        });
-
-    let darkened = darken_color(dominant_color, DECAL_DARKEN_AMOUNT);
-
+    ctx: &mut RenderContext,
+) -> Result<(RgbaImage, Option<RectPx>), RenderError> {
     let bounds = ctx.image.content_bounds;
     let min_dim = bounds.width.min(bounds.height) as f32;
-    let decal_size = (min_dim * config.scale) as u32;
+    let (logical_size, _) = ctx.image.logical_size();
+    // Deserialized configs bypass the constructor clamp
+    let scale = config.scale_for_size(logical_size).clamp(0.0, 1.0);
+    let decal_size = (min_dim * scale) as u32;
 
-    let width = ctx.image.data.width();
-    let height = ctx.image.data.height();
+    let width = ctx.image.data().width();
+    let height = ctx.image.data().height();
     let mut tile = RgbaImage::new(width, height);
 
     if decal_size == 0 {
-        return Ok(tile);
+        ctx.warn(RenderWarning::ZeroSize { layer: LayerKind::Decal });
+        return Ok((tile, None));
     }
 
-    let decal_img = render_source_with_color(&config.source, decal_size, Some(darkened))?;
+    let behind = || {
+        let glyph_area = RectPx::new(
+            bounds.x + bounds.width.saturating_sub(decal_size) / 2,
+            bounds.y + bounds.height.saturating_sub(decal_size) / 2,
+            decal_size,
+            decal_size,
+        );
+        color_behind(ctx, glyph_area)
+    };
+    let base_color = config.sample_behind.then(behind).flatten().unwrap_or_else(|| base_color(ctx));
+    let darkened = shade(base_color, config.lighten_below);
 
-    let center_x = bounds.x as i32 + (bounds.width as i32 - decal_img.width() as i32) / 2;
-    let center_y = bounds.y as i32 + (bounds.height as i32 - decal_img.height() as i32) / 2;
+    // SVGs authored with `currentColor` keep their structure; others are
+    // flattened to the single decal color
+    let svg = config.source.resolve_shared()?;
+    let quality = RasterQuality::of(ctx);
+    let current_color = uses_current_color(&svg);
+    let key = StampKey {
+        svg: svg.clone(),
+        size: decal_size,
+        color: Some((darkened, current_color)),
+        quality,
+    };
+    let build = || match current_color {
+        true => stamp_svg_with_current_color_at(&svg, decal_size, darkened, quality),
+        false => stamp_svg_with_color_at(&svg, decal_size, Some(darkened), quality),
+    };
+    let center = |width: u32, height: u32| {
+        (
+            bounds.x as f32 + (bounds.width as f32 - width as f32) / 2.0,
+            bounds.y as f32 + (bounds.height as f32 - height as f32) / 2.0,
+        )
+    };
+    let (stamp, (x, y, w, h)) = composite_keyed_stamp(&mut tile, ctx, key, build, center, PlacementRounding::of(ctx))?;
+    if stamp.is_empty() {
+        ctx.warn(RenderWarning::EmptySvg { layer: LayerKind::Decal });
+    }
+    if let Some(shadow) = &config.shadow {
+        drop_shadow(&mut tile, shadow, &bounds, quality);
+    }
+    if config.clip_to_alpha {
+        clip_to_alpha(&mut tile, ctx, 0, config.clip_feather);
+    }
+    fade(&mut tile, config.opacity);
+
+    let placement = RectPx::new(x.max(0) as u32, y.max(0) as u32, w, h);
+    Ok((tile, Some(placement)))
+}
+
+/// Returns the color a decal is drawn in.
+///
+/// Darkens [`DominantColor`] from the context if available, otherwise the
+/// [`AccentColor`] and then the [`SurfaceColor`]. Lightens it instead when
+/// its relative luminance is below `lighten_below`.
+pub(crate) fn decal_color(ctx: &RenderContext, lighten_below: Option<f32>) -> (u8, u8, u8, u8) {
+    shade(base_color(ctx), lighten_below)
+}
+
+/// Returns the color [`decal_color`] shades: the [`DominantColor`], else
+/// the [`AccentColor`], else the [`SurfaceColor`].
+fn base_color(ctx: &RenderContext) -> (u8, u8, u8, u8) {
+    ctx.get::<DominantColor>()
+        .map(|c| c.as_tuple())
+        .or_else(|| ctx.get::<AccentColor>().map(|c| (c.r, c.g, c.b, 255)))
+        .unwrap_or_else(|| {
+            let sc = ctx
+                .get::<SurfaceColor>()
+                .expect("SurfaceColor must be set in RenderContext");
+            (sc.r, sc.g, sc.b, 255)
+        })
+}
 
-    composite_over(&mut tile, &decal_img, center_x, center_y);
+/// Samples the icon's color within `area`, leaving out the drop shadow, or
+/// `None` if nothing opaque is there.
+fn color_behind(ctx: &RenderContext, area: RectPx) -> Option<(u8, u8, u8, u8)> {
+    let options = DominantColorSampling::new().with_region(area).with_min_alpha(SHADOW_ALPHA_CUTOFF);
+    sample_dominant_color(&ctx.image, &options).map(|(r, g, b)| (r, g, b, 255))
+}
 
-    Ok(tile)
+/// Darkens `color`, or lightens it when its relative luminance is below
+/// `lighten_below`.
+fn shade(color: (u8, u8, u8, u8), lighten_below: Option<f32>) -> (u8, u8, u8, u8) {
+    let (r, g, b, _) = color;
+    match lighten_below {
+        Some(threshold) if relative_luminance(r, g, b) < threshold => lighten_color(color, DECAL_DARKEN_AMOUNT),
+        _ => darken_color(color, DECAL_DARKEN_AMOUNT),
+    }
 }
 
 // ============================================================================
@@ -131,15 +448,125 @@ pub(crate) fn render_decal(
 
 /// Darkens an RGBA color by reducing its lightness.
 pub fn darken_color(color: (u8, u8, u8, u8), amount: f32) -> (u8, u8, u8, u8) {
+    shift_lightness(color, -amount)
+}
+
+/// Lightens an RGBA color by increasing its lightness.
+pub fn lighten_color(color: (u8, u8, u8, u8), amount: f32) -> (u8, u8, u8, u8) {
+    shift_lightness(color, amount)
+}
+
+/// Adds `delta` to the HSL lightness of a color, within 0.0-1.0.
+fn shift_lightness(color: (u8, u8, u8, u8), delta: f32) -> (u8, u8, u8, u8) {
     let (r, g, b, a) = color;
     let rgb = Srgb::new(r as f32 / 255.0, g as f32 / 255.0, b as f32 / 255.0);
     let mut hsl: Hsl = rgb.into_color();
-    hsl.lightness = (hsl.lightness - amount).max(0.0);
-    let darkened: Srgb = hsl.into_color();
+    hsl.lightness = (hsl.lightness + delta).clamp(0.0, 1.0);
+    let shifted: Srgb = hsl.into_color();
     (
-        (darkened.red * 255.0).round() as u8,
-        (darkened.green * 255.0).round() as u8,
-        (darkened.blue * 255.0).round() as u8,
+        (shifted.red * 255.0).round() as u8,
+        (shifted.green * 255.0).round() as u8,
+        (shifted.blue * 255.0).round() as u8,
         a,
     )
 }
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::icon::IconImage;
+    use crate::layer::{GradientStop, GradientTintConfig, LayerPipeline};
+    use image::Rgba;
+
+    const SQUARE: &str = r##"<svg xmlns="http://www.w3.org/2000/svg" width="10" height="10"><rect width="10" height="10" fill="#000"/></svg>"##;
+
+    #[test]
+    fn unsorted_scale_curves_are_sorted_when_loaded() {
+        let json = r#"{ "source": { "raw": "<svg/>" }, "scale": 0.5, "scaleCurve": [{ "size": 256, "scale": 0.4 }, { "size": 16, "scale": 0.8 }] }"#;
+        let loaded: DecalConfig = serde_json::from_str(json).unwrap();
+        assert_eq!(loaded.scale_for_size(16.0), 0.8);
+        assert_eq!(loaded.scale_for_size(512.0), 0.4);
+
+        let mut edited = DecalConfig::new(SQUARE, 0.5);
+        edited.scale_curve = vec![ScaleBreakpoint::new(256, 0.4), ScaleBreakpoint::new(16, 0.8)];
+        let mut layer = Layer::default();
+        layer.set_config(Some(edited));
+        assert_eq!(layer.config().unwrap().scale_for_size(16.0), 0.8);
+    }
+
+    #[test]
+    fn dark_folders_get_lightened_decals() {
+        let decal_pixel = |config: DecalConfig, (r, g, b)| {
+            let base = IconImage::new_full_content(RgbaImage::from_pixel(16, 16, Rgba([r, g, b, 255])), 1.0);
+            let mut pipeline = LayerPipeline::default();
+            pipeline.decal.set_config(Some(config));
+            pipeline.render(&base, &SurfaceColor::new(r, g, b)).unwrap().data().get_pixel(8, 8).0
+        };
+        let dark = (40, 44, 52);
+        let light = (255, 217, 112);
+
+        assert!(decal_pixel(DecalConfig::new(SQUARE, 0.5), dark)[..3].iter().all(|&c| c < 40));
+        let adaptive = DecalConfig::new(SQUARE, 0.5).with_lighten_below(0.2);
+        assert!(decal_pixel(adaptive.clone(), dark)[..3].iter().all(|&c| c > 52));
+        // Light folders still get a darker decal
+        assert_eq!(decal_pixel(adaptive, light), decal_pixel(DecalConfig::new(SQUARE, 0.5), light));
+    }
+
+    #[test]
+    fn clipped_decals_stay_inside_the_folder() {
+        // Opaque on the left half only
+        let mut folder = RgbaImage::new(16, 16);
+        for (x, _, pixel) in folder.enumerate_pixels_mut() {
+            if x < 8 {
+                *pixel = Rgba([255, 217, 112, 255]);
+            }
+        }
+        let base = IconImage::new_full_content(folder, 1.0);
+        let surface = SurfaceColor::new(255, 217, 112);
+        let mut pipeline = LayerPipeline::default();
+
+        pipeline.decal.set_config(Some(DecalConfig::new(SQUARE, 1.0)));
+        assert_eq!(pipeline.render(&base, &surface).unwrap().data().get_pixel(12, 8)[3], 255);
+        pipeline.decal.set_config(Some(DecalConfig::new(SQUARE, 1.0).with_clip_to_alpha(true)));
+        let clipped = pipeline.render(&base, &surface).unwrap();
+        assert_eq!(clipped.data().get_pixel(12, 8)[3], 0);
+        assert_eq!(clipped.data().get_pixel(4, 8)[3], 255);
+
+        // The shadow widens the silhouette, so the clip follows it
+        let before = pipeline.layer_versions();
+        pipeline.shadow.set_config(Some(ShadowConfig::new(1.0, 1.0, 2.0)));
+        assert_ne!(pipeline.decal.dependencies(&before), pipeline.decal.dependencies(&pipeline.layer_versions()));
+    }
+
+    #[test]
+    fn decals_can_match_the_color_behind_them() {
+        // A yellow folder with a blue label in the middle
+        let folder = RgbaImage::from_fn(32, 32, |x, y| match (8..24).contains(&x) && (8..24).contains(&y) {
+            true => Rgba([33, 150, 243, 255]),
+            false => Rgba([255, 217, 112, 255]),
+        });
+        let base = IconImage::new_full_content(folder, 1.0);
+        let surface = SurfaceColor::new(255, 217, 112);
+        let mut pipeline = LayerPipeline::default();
+
+        let mut decal_pixel = |config| {
+            pipeline.decal.set_config(Some(config));
+            let (r, g, b, a) = pipeline.render(&base, &surface).unwrap().data().get_pixel(16, 16).0.into();
+            (r, g, b, a)
+        };
+        assert_eq!(decal_pixel(DecalConfig::new(SQUARE, 0.25)), darken_color((255, 217, 112, 255), DECAL_DARKEN_AMOUNT));
+        assert_eq!(
+            decal_pixel(DecalConfig::new(SQUARE, 0.25).with_sample_behind(true)),
+            darken_color((33, 150, 243, 255), DECAL_DARKEN_AMOUNT)
+        );
+
+        let before = pipeline.layer_versions();
+        let stops = vec![GradientStop::new(0.0, 0, 0, 0), GradientStop::new(1.0, 255, 255, 255)];
+        pipeline.gradient_tint.set_config(Some(GradientTintConfig::linear(90.0, stops)));
+        assert_ne!(pipeline.decal.dependencies(&before), pipeline.decal.dependencies(&pipeline.layer_versions()));
+    }
+}
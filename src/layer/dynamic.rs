@@ -0,0 +1,605 @@
+//! Layer pipeline with a runtime-defined layer order.
+//!
+//! [`LayerPipeline`] always runs color target → gradient tint → shadow →
+//! decal → overlay → text.
+//! [`DynamicPipeline`] instead holds boxed [`PipelineLayer`]s in a
+//! user-controlled order, so an overlay can run before a decal, or the same
+//! layer type can appear twice.
+//!
+//! Because any layer can sit anywhere, each layer's cache is keyed on
+//! everything upstream of it: the accent color and the identity and
+//! version of every earlier layer, in order. Editing, toggling, inserting,
+//! removing or moving a layer therefore re-renders it and every layer
+//! after it, while the layers before it keep serving from cache.
+//!
+//! # Example
+//!
+//! ```
+//! use folco_renderer::{DecalConfig, DynamicPipeline, Layer, OverlayPosition, SvgOverlayConfig};
+//!
+//! let svg = r#"<svg xmlns="http://www.w3.org/2000/svg" width="8" height="8"><rect width="8" height="8"/></svg>"#;
+//!
+//! let mut pipeline = DynamicPipeline::new();
+//! let decal = pipeline.push(Layer::with_config(DecalConfig::new(svg, 0.5)));
+//! let overlay = pipeline.push(Layer::with_config(SvgOverlayConfig::new(svg, OverlayPosition::Center, 0.25)));
+//!
+//! // Draw the overlay first, under the decal
+//! pipeline.move_to(overlay, 0);
+//! assert_eq!(pipeline.ids(), vec![overlay, decal]);
+//!
+//! pipeline.layer_mut::<Layer<DecalConfig>>(decal).unwrap().set_enabled(false);
+//! ```
+
+use std::any::Any;
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+use super::overlay::ArrangedCorners;
+use super::{
+    svg, AccentColor, CacheKey, CompositeLayer, DecalConfig, DependencyVersion, DerivedPalette, DominantColor, FolderColorTargetConfig, GradientTintConfig, Layer, ShadowConfig,
+    LayerConfig, LayerPipeline, OverlayArrangement, PlacementRounding, RenderContext, RenderLimits, SvgOverlayConfig, TextLayerConfig, Thumbnail,
+};
+use crate::analysis::DominantColorSampler;
+use crate::error::RenderError;
+use crate::icon::{IconImage, SurfaceColor};
+
+// ============================================================================
+// PipelineLayer
+// ============================================================================
+
+/// A layer that can be placed anywhere in a [`DynamicPipeline`].
+///
+/// Implemented by every built-in [`Layer`] type. Custom layers can
+/// implement it too; they should treat `deps` as part of their cache key.
+pub trait PipelineLayer: Any + Send + Sync {
+    /// Renders the layer into `ctx.image`, using the layer's cache when it
+    /// was stored for the same `key` and `deps`.
+    ///
+    /// `deps` changes whenever anything upstream of the layer does.
+    /// Inactive layers leave the context unchanged.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the layer fails to render.
+    fn render(&mut self, ctx: &mut RenderContext, key: CacheKey, deps: DependencyVersion) -> Result<(), RenderError>;
+
+    /// Returns a number that changes whenever the layer's output may change.
+    fn version(&self) -> u64;
+
+    /// Clears the layer's cache.
+    fn invalidate(&mut self);
+
+    /// Clones the layer into a new box.
+    fn clone_box(&self) -> Box<dyn PipelineLayer>;
+
+    /// Returns the layer as [`Any`], for downcasting to its concrete type.
+    fn as_any(&self) -> &dyn Any;
+
+    /// Returns the layer as mutable [`Any`], for downcasting to its
+    /// concrete type.
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+impl Clone for Box<dyn PipelineLayer> {
+    fn clone(&self) -> Self {
+        self.clone_box()
+    }
+}
+
+impl<C: LayerConfig> Layer<C> {
+    /// Creates an enabled layer with the given configuration.
+    pub fn with_config(config: C) -> Self {
+        let mut layer = Self::default();
+        layer.set_config(Some(config));
+        layer
+    }
+}
+
+/// Implements the bookkeeping methods of [`PipelineLayer`] for a `Layer<C>`.
+macro_rules! pipeline_layer_common {
+    () => {
+        fn version(&self) -> u64 {
+            Layer::version(self)
+        }
+
+        fn invalidate(&mut self) {
+            Layer::invalidate(self);
+        }
+
+        fn clone_box(&self) -> Box<dyn PipelineLayer> {
+            Box::new(self.clone())
+        }
+
+        fn as_any(&self) -> &dyn Any {
+            self
+        }
+
+        fn as_any_mut(&mut self) -> &mut dyn Any {
+            self
+        }
+    };
+}
+
+impl PipelineLayer for Layer<FolderColorTargetConfig> {
+    fn render(&mut self, ctx: &mut RenderContext, key: CacheKey, deps: DependencyVersion) -> Result<(), RenderError> {
+        self.apply_with_deps(ctx, key, deps)
+    }
+
+    pipeline_layer_common!();
+}
+
+impl PipelineLayer for Layer<GradientTintConfig> {
+    fn render(&mut self, ctx: &mut RenderContext, key: CacheKey, deps: DependencyVersion) -> Result<(), RenderError> {
+        self.apply_with_deps(ctx, key, deps)
+    }
+
+    pipeline_layer_common!();
+}
+
+impl PipelineLayer for Layer<ShadowConfig> {
+    fn render(&mut self, ctx: &mut RenderContext, key: CacheKey, deps: DependencyVersion) -> Result<(), RenderError> {
+        self.apply_with_deps(ctx, key, deps)
+    }
+
+    pipeline_layer_common!();
+}
+
+impl PipelineLayer for Layer<DecalConfig> {
+    fn render(&mut self, ctx: &mut RenderContext, key: CacheKey, deps: DependencyVersion) -> Result<(), RenderError> {
+        if let Some(tile) = self.render_tile_with_deps(ctx, key, deps)? {
+            svg::composite_blend(ctx.image.data_mut(), &tile, 0, 0, self.blend_mode());
+        }
+        Ok(())
+    }
+
+    pipeline_layer_common!();
+}
+
+impl PipelineLayer for Layer<SvgOverlayConfig> {
+    fn render(&mut self, ctx: &mut RenderContext, key: CacheKey, deps: DependencyVersion) -> Result<(), RenderError> {
+        if let Some(tile) = self.render_tile_with_deps(ctx, key, deps)? {
+            svg::composite_blend(ctx.image.data_mut(), &tile, 0, 0, self.blend_mode());
+        }
+        Ok(())
+    }
+
+    pipeline_layer_common!();
+}
+
+impl PipelineLayer for Layer<TextLayerConfig> {
+    fn render(&mut self, ctx: &mut RenderContext, key: CacheKey, deps: DependencyVersion) -> Result<(), RenderError> {
+        if let Some(tile) = self.render_tile_with_deps(ctx, key, deps)? {
+            svg::composite_over(ctx.image.data_mut(), &tile, 0, 0);
+        }
+        Ok(())
+    }
+
+    pipeline_layer_common!();
+}
+
+// ============================================================================
+// DynamicPipeline
+// ============================================================================
+
+/// Identifies a layer in a [`DynamicPipeline`].
+///
+/// Ids are unique within a pipeline and stay valid while the layer is
+/// moved; they are never reused after removal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct LayerId(u64);
+
+/// A layer pipeline whose layers and their order are chosen at runtime.
+///
+/// Layers run in order, each seeing the image and [`RenderContext`]
+/// properties left by the ones before it. Cloning the pipeline clones every
+/// layer with its cache, as with [`LayerPipeline`].
+#[derive(Clone, Default)]
+pub struct DynamicPipeline {
+    layers: Vec<(LayerId, Box<dyn PipelineLayer>)>,
+    next_id: u64,
+    composite: CompositeLayer,
+    accent: Option<AccentColor>,
+    dominant_sampler: Option<DominantColorSampler>,
+    accent_version: u64,
+    placement_rounding: PlacementRounding,
+    overlay_arrangement: OverlayArrangement,
+    limits: RenderLimits,
+}
+
+impl DynamicPipeline {
+    /// Creates an empty pipeline.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends a layer, running after all existing ones.
+    pub fn push(&mut self, layer: impl PipelineLayer) -> LayerId {
+        self.insert(self.layers.len(), layer)
+    }
+
+    /// Inserts a layer at `index` (clamped to the number of layers).
+    pub fn insert(&mut self, index: usize, layer: impl PipelineLayer) -> LayerId {
+        self.insert_boxed(index, Box::new(layer))
+    }
+
+    /// Inserts an already boxed layer at `index` (clamped to the number of
+    /// layers).
+    pub fn insert_boxed(&mut self, index: usize, layer: Box<dyn PipelineLayer>) -> LayerId {
+        let id = LayerId(self.next_id);
+        self.next_id += 1;
+        self.layers.insert(index.min(self.layers.len()), (id, layer));
+        id
+    }
+
+    /// Removes a layer, returning it.
+    pub fn remove(&mut self, id: LayerId) -> Option<Box<dyn PipelineLayer>> {
+        let index = self.position(id)?;
+        Some(self.layers.remove(index).1)
+    }
+
+    /// Moves a layer to `index` (clamped to the last position). Returns
+    /// false if the id is unknown.
+    pub fn move_to(&mut self, id: LayerId, index: usize) -> bool {
+        let Some(from) = self.position(id) else {
+            return false;
+        };
+        let entry = self.layers.remove(from);
+        self.layers.insert(index.min(self.layers.len()), entry);
+        true
+    }
+
+    /// Returns a layer's current position.
+    pub fn position(&self, id: LayerId) -> Option<usize> {
+        self.layers.iter().position(|(layer_id, _)| *layer_id == id)
+    }
+
+    /// Returns the layer ids in render order.
+    pub fn ids(&self) -> Vec<LayerId> {
+        self.layers.iter().map(|(id, _)| *id).collect()
+    }
+
+    /// Returns the number of layers.
+    pub fn len(&self) -> usize {
+        self.layers.len()
+    }
+
+    /// Returns true if the pipeline has no layers.
+    pub fn is_empty(&self) -> bool {
+        self.layers.is_empty()
+    }
+
+    /// Returns a layer as its concrete type, or `None` if the id is unknown
+    /// or the layer is of another type.
+    pub fn layer<L: PipelineLayer>(&self, id: LayerId) -> Option<&L> {
+        let index = self.position(id)?;
+        self.layers[index].1.as_any().downcast_ref()
+    }
+
+    /// Returns a layer as its concrete type for editing, or `None` if the
+    /// id is unknown or the layer is of another type.
+    pub fn layer_mut<L: PipelineLayer>(&mut self, id: LayerId) -> Option<&mut L> {
+        let index = self.position(id)?;
+        self.layers[index].1.as_any_mut().downcast_mut()
+    }
+
+    /// Returns the render limits.
+    pub fn limits(&self) -> RenderLimits {
+        self.limits
+    }
+
+    /// Sets the render limits checked before each render.
+    pub fn set_limits(&mut self, limits: RenderLimits) {
+        self.limits = limits;
+    }
+
+    /// Returns the pipeline accent color, if any.
+    pub fn accent_color(&self) -> Option<AccentColor> {
+        self.accent
+    }
+
+    /// Sets the pipeline accent color. Returns true if it changed.
+    ///
+    /// Every layer re-renders on its next use.
+    pub fn set_accent_color(&mut self, accent: Option<AccentColor>) -> bool {
+        if self.accent != accent {
+            self.accent = accent;
+            self.accent_version = self.accent_version.wrapping_add(1);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Returns the dominant color sampler, if any.
+    pub fn dominant_color_sampler(&self) -> Option<DominantColorSampler> {
+        self.dominant_sampler
+    }
+
+    /// Samples each base image with `sampler`, emitting the result as a
+    /// [`DominantColor`] (and its [`DerivedPalette`]) before any layer
+    /// runs, as [`LayerPipeline::set_dominant_color_sampler`] does. Returns
+    /// true if it changed.
+    ///
+    /// Every layer re-renders on its next use.
+    pub fn set_dominant_color_sampler(&mut self, sampler: Option<DominantColorSampler>) -> bool {
+        if self.dominant_sampler != sampler {
+            self.dominant_sampler = sampler;
+            self.accent_version = self.accent_version.wrapping_add(1);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Returns how layer positions snap to pixels.
+    pub fn placement_rounding(&self) -> PlacementRounding {
+        self.placement_rounding
+    }
+
+    /// Sets how layer positions snap to pixels, emitted as a
+    /// [`PlacementRounding`] property. Returns true if it changed.
+    ///
+    /// Every layer re-renders on its next use.
+    pub fn set_placement_rounding(&mut self, rounding: PlacementRounding) -> bool {
+        let changed = self.placement_rounding != rounding;
+        self.placement_rounding = rounding;
+        changed
+    }
+
+    /// Returns how overlays sharing a corner are laid out.
+    pub fn overlay_arrangement(&self) -> OverlayArrangement {
+        self.overlay_arrangement
+    }
+
+    /// Sets how overlay layers sharing a corner are laid out. Returns true
+    /// if it changed.
+    ///
+    /// Every layer re-renders on its next use.
+    pub fn set_overlay_arrangement(&mut self, arrangement: OverlayArrangement) -> bool {
+        if self.overlay_arrangement != arrangement {
+            self.overlay_arrangement = arrangement;
+            self.accent_version = self.accent_version.wrapping_add(1);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Invalidates all caches.
+    pub fn invalidate_all(&mut self) {
+        for (_, layer) in &mut self.layers {
+            layer.invalidate();
+        }
+        self.composite.invalidate();
+    }
+
+    /// Returns the dependency version of each layer, followed by that of
+    /// the composite.
+    ///
+    /// Each entry hashes the accent version, the placement rounding and the
+    /// id and version of every earlier layer in order, so reordering
+    /// changes it even though the set of versions stays the same.
+    fn dependency_chain(&self) -> Vec<DependencyVersion> {
+        let mut hasher = DefaultHasher::new();
+        self.accent_version.hash(&mut hasher);
+        self.placement_rounding.hash(&mut hasher);
+        let mut chain = Vec::with_capacity(self.layers.len() + 1);
+        for (id, layer) in &self.layers {
+            chain.push(DependencyVersion::from_version(hasher.finish()));
+            (id, layer.version()).hash(&mut hasher);
+        }
+        chain.push(DependencyVersion::from_version(hasher.finish()));
+        chain
+    }
+
+    /// Renders an icon through the layers in order.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`RenderError::ImageTooLarge`] if the base image exceeds the
+    /// pipeline's [`RenderLimits`], or an error if any layer fails to render.
+    pub fn render(&mut self, base: &IconImage, surface_color: &SurfaceColor) -> Result<IconImage, RenderError> {
+        self.limits.check(base.data().width(), base.data().height())?;
+
+        let key = CacheKey::from_icon(base);
+        let chain = self.dependency_chain();
+        let composite_deps = chain[self.layers.len()];
+        if let Some(cached) = self.composite.get_cached(key, composite_deps) {
+            return Ok(cached.clone());
+        }
+
+        let mut ctx = RenderContext::new(base.clone());
+        ctx.set(*surface_color);
+        if let Some(accent) = self.accent {
+            ctx.set(accent);
+        }
+        if let Some((r, g, b)) = self.dominant_sampler.and_then(|sampler| sampler.sample(base)) {
+            ctx.set(DominantColor::new(r, g, b, 255));
+            ctx.set(DerivedPalette::from_rgb(r, g, b));
+        }
+        ctx.set(self.placement_rounding);
+        ctx.set(ArrangedCorners::new(self.overlay_arrangement));
+
+        let mut thumbnail_due = true;
+        for ((_, layer), deps) in self.layers.iter_mut().zip(chain) {
+            if thumbnail_due && !is_pixel_layer(layer.as_ref()) {
+                Thumbnail::emit(&mut ctx);
+                thumbnail_due = false;
+            }
+            layer.render(&mut ctx, key, deps)?;
+        }
+
+        self.composite.store(key, ctx.image.clone(), composite_deps);
+        Ok(ctx.image)
+    }
+}
+
+/// Returns true for the layers that transform the whole image, which a
+/// [`Thumbnail`] shows.
+fn is_pixel_layer(layer: &dyn PipelineLayer) -> bool {
+    let layer = layer.as_any();
+    layer.is::<Layer<FolderColorTargetConfig>>() || layer.is::<Layer<GradientTintConfig>>() || layer.is::<Layer<ShadowConfig>>()
+}
+
+impl From<LayerPipeline> for DynamicPipeline {
+    /// Converts a fixed pipeline into color target, gradient tint, shadow,
+    /// decal, overlay and text layers in that order, keeping their configs,
+    /// accent, dominant color sampler, placement rounding, overlay
+    /// arrangement and limits.
+    fn from(pipeline: LayerPipeline) -> Self {
+        let mut dynamic = Self::new();
+        dynamic.push(pipeline.folder_color_target);
+        dynamic.push(pipeline.gradient_tint);
+        dynamic.push(pipeline.shadow);
+        dynamic.push(pipeline.decal);
+        dynamic.push(pipeline.overlay);
+        dynamic.push(pipeline.text);
+        dynamic.accent = pipeline.accent;
+        dynamic.dominant_sampler = pipeline.dominant_sampler;
+        dynamic.placement_rounding = pipeline.placement_rounding;
+        dynamic.overlay_arrangement = pipeline.overlay_arrangement;
+        dynamic.limits = pipeline.limits;
+        dynamic
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use image::{Rgba, RgbaImage};
+
+    use super::*;
+    use crate::layer::OverlayPosition;
+
+    const SQUARE: &str = r##"<svg xmlns="http://www.w3.org/2000/svg" width="8" height="8"><rect width="8" height="8" fill="#00ff00"/></svg>"##;
+
+    fn base() -> IconImage {
+        IconImage::new_full_content(RgbaImage::from_pixel(32, 32, Rgba([255, 217, 112, 255])), 1.0)
+    }
+
+    /// Counts renders that missed its single-entry cache.
+    #[derive(Clone, Default)]
+    struct Counting {
+        renders: usize,
+        cached: Option<(CacheKey, DependencyVersion)>,
+    }
+
+    impl PipelineLayer for Counting {
+        fn render(&mut self, _: &mut RenderContext, key: CacheKey, deps: DependencyVersion) -> Result<(), RenderError> {
+            if self.cached != Some((key, deps)) {
+                self.renders += 1;
+                self.cached = Some((key, deps));
+            }
+            Ok(())
+        }
+
+        fn version(&self) -> u64 {
+            0
+        }
+
+        fn invalidate(&mut self) {
+            self.cached = None;
+        }
+
+        fn clone_box(&self) -> Box<dyn PipelineLayer> {
+            Box::new(self.clone())
+        }
+
+        fn as_any(&self) -> &dyn Any {
+            self
+        }
+
+        fn as_any_mut(&mut self) -> &mut dyn Any {
+            self
+        }
+    }
+
+    #[test]
+    fn order_decides_which_layer_is_on_top() {
+        let surface = SurfaceColor::new(255, 217, 112);
+        let mut pipeline = DynamicPipeline::new();
+        let decal = pipeline.push(Layer::with_config(DecalConfig::new(SQUARE, 0.5)));
+        let overlay = pipeline.push(Layer::with_config(SvgOverlayConfig::new(SQUARE, OverlayPosition::Center, 0.5)));
+
+        // Decal and overlay cover the same center square
+        let overlay_on_top = pipeline.render(&base(), &surface).unwrap();
+        assert_eq!(overlay_on_top.data().get_pixel(16, 16).0, [0, 255, 0, 255]);
+
+        assert!(pipeline.move_to(overlay, 0));
+        assert_eq!(pipeline.ids(), [overlay, decal]);
+        let decal_on_top = pipeline.render(&base(), &surface).unwrap();
+        assert_ne!(decal_on_top.data().get_pixel(16, 16).0, [0, 255, 0, 255]);
+
+        // The same layer type twice
+        pipeline.push(Layer::with_config(SvgOverlayConfig::new(SQUARE, OverlayPosition::TopLeft, 0.25)));
+        let twice = pipeline.render(&base(), &surface).unwrap();
+        assert_eq!(twice.data().get_pixel(1, 1).0, [0, 255, 0, 255]);
+        assert!(pipeline.layer::<Layer<DecalConfig>>(overlay).is_none());
+    }
+
+    #[test]
+    fn overlays_in_the_same_corner_are_arranged() {
+        let surface = SurfaceColor::new(255, 217, 112);
+        let mut pipeline = DynamicPipeline::new();
+        pipeline.push(Layer::with_config(SvgOverlayConfig::new(SQUARE, OverlayPosition::BottomLeft, 0.25)));
+        pipeline.push(Layer::with_config(SvgOverlayConfig::new(SQUARE, OverlayPosition::BottomLeft, 0.25)));
+
+        // Overlapping, only the first square is drawn
+        let overlapping = pipeline.render(&base(), &surface).unwrap();
+        assert_eq!(overlapping.data().get_pixel(12, 28).0, [255, 217, 112, 255]);
+
+        assert!(pipeline.set_overlay_arrangement(OverlayArrangement::Spread { spacing: 2.0 }));
+        let spread = pipeline.render(&base(), &surface).unwrap();
+        assert_eq!(spread.data().get_pixel(4, 28).0, [0, 255, 0, 255]);
+        assert_eq!(spread.data().get_pixel(12, 28).0, [0, 255, 0, 255]);
+    }
+
+    #[test]
+    fn only_downstream_layers_rerender() {
+        let surface = SurfaceColor::new(255, 217, 112);
+        let mut pipeline = DynamicPipeline::new();
+        let first = pipeline.push(Counting::default());
+        let decal = pipeline.push(Layer::with_config(DecalConfig::new(SQUARE, 0.5)));
+        let last = pipeline.push(Counting::default());
+        let renders = |pipeline: &DynamicPipeline| {
+            (pipeline.layer::<Counting>(first).unwrap().renders, pipeline.layer::<Counting>(last).unwrap().renders)
+        };
+
+        pipeline.render(&base(), &surface).unwrap();
+        pipeline.render(&base(), &surface).unwrap();
+        assert_eq!(renders(&pipeline), (1, 1));
+
+        pipeline.layer_mut::<Layer<DecalConfig>>(decal).unwrap().set_config(Some(DecalConfig::new(SQUARE, 0.25)));
+        pipeline.render(&base(), &surface).unwrap();
+        assert_eq!(renders(&pipeline), (1, 2));
+
+        // Moving the decal below the first layer changes what both see
+        pipeline.move_to(decal, 0);
+        pipeline.render(&base(), &surface).unwrap();
+        assert_eq!(renders(&pipeline), (2, 3));
+
+        assert!(pipeline.remove(decal).is_some());
+        assert!(!pipeline.move_to(decal, 0));
+        pipeline.render(&base(), &surface).unwrap();
+        assert_eq!(renders(&pipeline), (3, 4));
+    }
+
+    #[test]
+    fn conversion_keeps_the_dominant_color_sampler() {
+        use crate::analysis::SamplingStrategy;
+
+        let sampler = DominantColorSampler::new(SamplingStrategy::Median);
+        let mut fixed = LayerPipeline::default();
+        fixed.set_dominant_color_sampler(Some(sampler));
+        let mut pipeline = DynamicPipeline::from(fixed);
+        assert_eq!(pipeline.dominant_color_sampler(), Some(sampler));
+
+        let surface = SurfaceColor::new(255, 217, 112);
+        let before = pipeline.dependency_chain();
+        pipeline.render(&base(), &surface).unwrap();
+        assert!(pipeline.set_dominant_color_sampler(None));
+        assert_ne!(pipeline.dependency_chain(), before);
+    }
+}
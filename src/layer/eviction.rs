@@ -0,0 +1,245 @@
+//! Bounding the memory held by layer caches.
+//!
+//! Every layer caches one output per image size, and nothing is dropped
+//! until its configuration changes. An app rendering many sizes over a
+//! long session (previews at arbitrary zoom levels, say) would keep them
+//! all. With a [cache limit](LayerPipeline::set_cache_limit), the pipeline
+//! drops the sizes used least recently, across every layer at once, until
+//! its caches fit the budget again, and then its stamp rasterizations.
+//!
+//! Only the pipeline's own caches count: the per-size layer outputs, the
+//! composite and underlay, and the stamps it shares or keeps for
+//! [`composite_only`](LayerPipeline::composite_only). The process-wide
+//! caches of resolved SVG sources, parsed trees and fonts are shared by
+//! every pipeline and bounded on their own, so they aren't included.
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use super::{CacheKey, LayerPipeline};
+
+/// How much the caches of a [`LayerPipeline`] hold.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "tsify", derive(tsify_next::Tsify))]
+#[cfg_attr(feature = "tsify", tsify(into_wasm_abi, from_wasm_abi))]
+#[serde(rename_all = "camelCase")]
+pub struct CacheStats {
+    /// Cached outputs, summed across layers, the composite and stamp
+    /// rasterizations.
+    pub entries: usize,
+    /// Distinct image sizes with at least one cached output.
+    pub sizes: usize,
+    /// Approximate memory held by the cached pixels, in bytes, stamps
+    /// included.
+    pub bytes: usize,
+    /// The byte budget, if one is set.
+    pub limit: Option<usize>,
+}
+
+/// When each size was last rendered, as ticks of a per-pipeline clock.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct CacheUsage {
+    clock: u64,
+    last_used: HashMap<CacheKey, u64>,
+}
+
+impl CacheUsage {
+    /// Marks `key` as the most recently used size.
+    pub(crate) fn touch(&mut self, key: CacheKey) {
+        self.clock += 1;
+        self.last_used.insert(key, self.clock);
+    }
+
+    /// Takes in the uses recorded by a copy of this clock.
+    #[cfg(feature = "rayon")]
+    pub(crate) fn merge(&mut self, other: Self) {
+        self.clock = self.clock.max(other.clock);
+        for (key, tick) in other.last_used {
+            let last = self.last_used.entry(key).or_default();
+            *last = (*last).max(tick);
+        }
+    }
+}
+
+impl LayerPipeline {
+    /// Limits the memory held by all caches to about `bytes`.
+    ///
+    /// After each render, the least recently rendered sizes are dropped
+    /// from every layer until the caches fit, then the stamp
+    /// rasterizations if they still don't. A budget smaller than one
+    /// size's outputs leaves nothing cached.
+    ///
+    /// ```
+    /// use folco_renderer::{DecalConfig, IconImage, LayerPipeline, SurfaceColor};
+    /// use image::RgbaImage;
+    ///
+    /// let mut pipeline = LayerPipeline::default();
+    /// let square = r#"<svg xmlns="http://www.w3.org/2000/svg" width="10" height="10"><rect width="10" height="10"/></svg>"#;
+    /// pipeline.decal.set_config(Some(DecalConfig::new(square, 0.5)));
+    /// pipeline.set_cache_limit(1 << 20);
+    ///
+    /// let surface = SurfaceColor::new(255, 217, 112);
+    /// for size in [16, 32, 64, 128, 256, 512] {
+    ///     let base = IconImage::new_full_content(RgbaImage::new(size, size), 1.0);
+    ///     pipeline.render(&base, &surface).unwrap();
+    /// }
+    /// assert!(pipeline.cache_stats().bytes <= 1 << 20);
+    /// ```
+    pub fn set_cache_limit(&mut self, bytes: usize) {
+        self.cache_limit = Some(bytes);
+        self.enforce_cache_limit();
+    }
+
+    /// Removes the limit set with [`set_cache_limit`](Self::set_cache_limit).
+    pub fn clear_cache_limit(&mut self) {
+        self.cache_limit = None;
+    }
+
+    /// Returns the cache byte budget, if any.
+    pub fn cache_limit(&self) -> Option<usize> {
+        self.cache_limit
+    }
+
+    /// Returns the number of cached outputs and the memory they hold.
+    pub fn cache_stats(&self) -> CacheStats {
+        let sizes = self.cache_footprint();
+        let (stamps, stamp_bytes) = self.stamp_footprint();
+        CacheStats {
+            entries: sizes.values().map(|(entries, _)| entries).sum::<usize>() + stamps,
+            sizes: sizes.len(),
+            bytes: sizes.values().map(|(_, bytes)| bytes).sum::<usize>() + stamp_bytes,
+            limit: self.cache_limit,
+        }
+    }
+
+    /// Returns the number of stamp rasterizations held and their bytes,
+    /// shared ones included.
+    fn stamp_footprint(&self) -> (usize, usize) {
+        let (mut entries, mut bytes) = self.composite_stamps.0.footprint();
+        if let Some(shared) = &self.shared_stamps {
+            let (shared_entries, shared_bytes) = shared.0.footprint();
+            entries += shared_entries;
+            bytes += shared_bytes;
+        }
+        (entries, bytes)
+    }
+
+    /// Returns the number of cached outputs and their bytes, by size.
+    fn cache_footprint(&self) -> HashMap<CacheKey, (usize, usize)> {
+        let mut sizes = HashMap::<CacheKey, (usize, usize)>::new();
+        let mut add = |(key, bytes)| {
+            let size = sizes.entry(key).or_default();
+            size.0 += 1;
+            size.1 += bytes;
+        };
+        self.folder_color_target.cache_footprint().for_each(&mut add);
+        self.gradient_tint.cache_footprint().for_each(&mut add);
+        self.shadow.cache_footprint().for_each(&mut add);
+        self.decal.cache_footprint().for_each(&mut add);
+        self.overlay.cache_footprint().for_each(&mut add);
+        self.text.cache_footprint().for_each(&mut add);
+        self.composite.cache_footprint().for_each(&mut add);
+        self.underlay.cache_footprint().for_each(&mut add);
+        sizes
+    }
+
+    /// Drops the least recently used sizes until the caches fit the limit.
+    pub(crate) fn enforce_cache_limit(&mut self) {
+        let Some(limit) = self.cache_limit else {
+            return;
+        };
+        let sizes = self.cache_footprint();
+        let mut total: usize = sizes.values().map(|(_, bytes)| bytes).sum::<usize>() + self.stamp_footprint().1;
+        if total <= limit {
+            return;
+        }
+
+        // Sizes cached without a recorded use go first
+        let mut by_age: Vec<_> = sizes.into_iter().collect();
+        by_age.sort_by_key(|(key, _)| self.cache_usage.last_used.get(key).copied().unwrap_or(0));
+        for (key, (_, bytes)) in by_age {
+            if total <= limit {
+                break;
+            }
+            self.evict(key);
+            total -= bytes;
+        }
+        if total > limit {
+            self.composite_stamps.0.clear();
+            if let Some(shared) = &self.shared_stamps {
+                shared.0.clear();
+            }
+        }
+    }
+
+    /// Drops every cached output for `key`.
+    fn evict(&mut self, key: CacheKey) {
+        self.folder_color_target.evict(key);
+        self.gradient_tint.evict(key);
+        self.shadow.evict(key);
+        self.decal.evict(key);
+        self.overlay.evict(key);
+        self.text.evict(key);
+        self.composite.evict(key);
+        self.underlay.evict(key);
+        self.cache_usage.last_used.remove(&key);
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::icon::{IconImage, SurfaceColor};
+    use crate::layer::DecalConfig;
+    use image::RgbaImage;
+
+    const SQUARE: &str = r##"<svg xmlns="http://www.w3.org/2000/svg" width="10" height="10"><rect width="10" height="10" fill="#000"/></svg>"##;
+
+    #[test]
+    fn least_recently_used_sizes_are_evicted() {
+        let surface = SurfaceColor::new(255, 217, 112);
+        let base = |size| IconImage::new_full_content(RgbaImage::new(size, size), 1.0);
+        let mut pipeline = LayerPipeline::default();
+        pipeline.decal.set_config(Some(DecalConfig::new(SQUARE, 0.5)));
+        let cached = |pipeline: &mut LayerPipeline, size| pipeline.render_with_report(&base(size), &surface).unwrap().1.cached;
+
+        // A decal tile and a composite per size, 8 bytes per pixel
+        for size in [16, 32, 64] {
+            assert!(!cached(&mut pipeline, size));
+        }
+        let stats = pipeline.cache_stats();
+        assert_eq!((stats.entries, stats.sizes, stats.bytes), (6, 3, 8 * (256 + 1024 + 4096)));
+
+        pipeline.set_cache_limit(8 * (1024 + 4096));
+        assert_eq!(pipeline.cache_stats().sizes, 2);
+        assert!(cached(&mut pipeline, 32));
+        // 64 is now the least recently used
+        assert!(!cached(&mut pipeline, 16));
+        assert!(cached(&mut pipeline, 32));
+        assert!(!cached(&mut pipeline, 64));
+
+        pipeline.clear_cache_limit();
+        assert!(!cached(&mut pipeline, 16));
+        assert_eq!(pipeline.cache_stats(), CacheStats { entries: 6, sizes: 3, bytes: 8 * 5376, limit: None });
+    }
+
+    #[test]
+    fn stamp_rasterizations_count_toward_the_limit() {
+        use crate::layer::{OverlayPosition, SvgOverlayConfig};
+
+        let mut pipeline = LayerPipeline::default();
+        let base = IconImage::new_full_content(RgbaImage::new(32, 32), 1.0);
+        pipeline.composite_only(&base, &[SvgOverlayConfig::new(SQUARE, OverlayPosition::Center, 0.5)]).unwrap();
+        // One 16×16 rasterization
+        assert_eq!(pipeline.cache_stats().entries, 1);
+        assert_eq!(pipeline.cache_stats().bytes, 4 * 256);
+
+        pipeline.set_cache_limit(0);
+        assert_eq!(pipeline.cache_stats().bytes, 0);
+    }
+}
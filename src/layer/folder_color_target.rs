@@ -20,10 +20,11 @@
 //! A delta of 0.0 leaves the channel unchanged, +1.0 doubles it,
 //! and -1.0 drives it to zero.
 
-use super::{CacheKey, CachedOutput, DependencyVersion, DominantColor, Layer, LayerConfig, LayerVersions, RenderContext};
+use super::{finite_or, AccentColor, CacheKey, CachedOutput, DependencyVersion, DerivedPalette, DominantColor, Layer, LayerConfig, LayerVersions, RenderContext};
+use crate::analysis::relative_luminance;
 use crate::error::RenderError;
 use crate::icon::{IconImage, SurfaceColor};
-use palette::{Hsl, IntoColor, Srgb};
+use palette::{Hsl, IntoColor, LinSrgb, Srgb};
 
 // ============================================================================
 // FolderColorTargetConfig
@@ -47,6 +48,27 @@ pub struct FolderColorTargetConfig {
     pub target_g: u8,
     /// Target blue channel (0–255).
     pub target_b: u8,
+    /// Retarget to the pipeline [`AccentColor`] when one is set.
+    ///
+    /// The `target_r/g/b` values are used as a fallback when the
+    /// pipeline has no accent color.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub follow_accent: bool,
+    /// Colors left untouched by the per-pixel pass.
+    ///
+    /// Useful for logos or neutral highlights embedded in the base icon.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub protected_colors: Vec<ProtectedColor>,
+    /// How the per-pixel pass shifts colors.
+    #[serde(default, skip_serializing_if = "HueApproximation::is_exact")]
+    pub hue_approximation: HueApproximation,
+    /// How far to move from the surface color toward the target (0.0-1.0).
+    ///
+    /// Below 1.0 the layer retargets to a color part way between the two in
+    /// HSL, taking the shorter way around the hue circle, so 0.0 leaves the
+    /// icon unchanged. Defaults to the full target.
+    #[serde(default = "full_strength", skip_serializing_if = "is_full_strength")]
+    pub strength: f32,
 }
 
 impl FolderColorTargetConfig {
@@ -56,7 +78,96 @@ impl FolderColorTargetConfig {
             target_r,
             target_g,
             target_b,
+            follow_accent: false,
+            protected_colors: Vec::new(),
+            hue_approximation: HueApproximation::Exact,
+            strength: 1.0,
+        }
+    }
+
+    /// Sets how the per-pixel pass shifts colors.
+    pub fn with_hue_approximation(mut self, hue_approximation: HueApproximation) -> Self {
+        self.hue_approximation = hue_approximation;
+        self
+    }
+
+    /// Adds a color that the per-pixel pass should leave untouched.
+    pub fn with_protected_color(mut self, color: ProtectedColor) -> Self {
+        self.protected_colors.push(color);
+        self
+    }
+
+    /// Sets how far the layer moves toward the target (clamped to 0.0-1.0).
+    pub fn with_strength(mut self, strength: f32) -> Self {
+        self.strength = finite_or(strength, 1.0).clamp(0.0, 1.0);
+        self
+    }
+
+    /// Sets whether the layer retargets to the pipeline accent color.
+    pub fn with_follow_accent(mut self, follow_accent: bool) -> Self {
+        self.follow_accent = follow_accent;
+        self
+    }
+
+    /// Returns the target color to use given the current render context.
+    ///
+    /// Resolves to the [`AccentColor`] when `follow_accent` is set and one
+    /// is present, otherwise to the configured target, then moves it back
+    /// toward the context's [`SurfaceColor`] by the `strength`.
+    pub fn effective_target(&self, ctx: &RenderContext) -> (u8, u8, u8) {
+        let target = match ctx.get::<AccentColor>() {
+            Some(accent) if self.follow_accent => (accent.r, accent.g, accent.b),
+            _ => (self.target_r, self.target_g, self.target_b),
+        };
+        match ctx.get::<SurfaceColor>() {
+            Some(surface) => self.weaken(surface, target),
+            None => target,
+        }
+    }
+
+    /// Returns the color `strength` of the way from `surface` to `target`.
+    fn weaken(&self, surface: &SurfaceColor, target: (u8, u8, u8)) -> (u8, u8, u8) {
+        if is_full_strength(&self.strength) {
+            return target;
         }
+        let to_hsl = |(r, g, b): (u8, u8, u8)| -> Hsl { Srgb::new(r as f32 / 255.0, g as f32 / 255.0, b as f32 / 255.0).into_color() };
+        let (from, to) = (to_hsl((surface.r, surface.g, surface.b)), to_hsl(target));
+        let t = self.strength.clamp(0.0, 1.0);
+        let hue_delta = (to.hue.into_positive_degrees() - from.hue.into_positive_degrees() + 180.0).rem_euclid(360.0) - 180.0;
+        let mixed = Hsl::new(
+            from.hue.into_positive_degrees() + hue_delta * t,
+            from.saturation + (to.saturation - from.saturation) * t,
+            from.lightness + (to.lightness - from.lightness) * t,
+        );
+        let rgb: Srgb = mixed.into_color();
+        let channel = |c: f32| (c.clamp(0.0, 1.0) * 255.0).round() as u8;
+        (channel(rgb.red), channel(rgb.green), channel(rgb.blue))
+    }
+}
+
+impl FolderColorTargetConfig {
+    /// Returns a CSS `filter` value approximating this layer over `surface`.
+    ///
+    /// Intended for instant web previews while the accurate render catches
+    /// up: the hue shift maps to `hue-rotate()`, and the saturation and
+    /// lightness factors to `saturate()` and `brightness()`. CSS rotates
+    /// hue in a luminance-preserving RGB space rather than HSL, and ignores
+    /// `protected_colors`, so results drift for strongly saturated or
+    /// protected regions. Uses the configured target, weakened by the
+    /// `strength`; when following an accent, build the config from the
+    /// resolved accent color first.
+    ///
+    /// Render with [`HueApproximation::Matrix`] to make the final output
+    /// match this filter.
+    ///
+    /// ```
+    /// use folco_renderer::{FolderColorTargetConfig, SurfaceColor};
+    ///
+    /// let filter = FolderColorTargetConfig::new(33, 150, 243).to_css_filter(&SurfaceColor::new(255, 217, 112));
+    /// assert!(filter.starts_with("hue-rotate("));
+    /// ```
+    pub fn to_css_filter(&self, surface: &SurfaceColor) -> String {
+        HslShift::new(surface, self.weaken(surface, (self.target_r, self.target_g, self.target_b))).to_css_filter()
     }
 }
 
@@ -65,6 +176,132 @@ impl LayerConfig for FolderColorTargetConfig {
         self.target_r != other.target_r
             || self.target_g != other.target_g
             || self.target_b != other.target_b
+            || self.follow_accent != other.follow_accent
+            || self.protected_colors != other.protected_colors
+            || self.hue_approximation != other.hue_approximation
+            || self.strength != other.strength
+    }
+}
+
+/// Serde default for [`FolderColorTargetConfig::strength`].
+fn full_strength() -> f32 {
+    1.0
+}
+
+/// Returns true for a strength that reaches the target.
+fn is_full_strength(strength: &f32) -> bool {
+    *strength >= 1.0
+}
+
+// ============================================================================
+// HueApproximation
+// ============================================================================
+
+/// How the color target layer shifts each pixel.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "jsonschema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "kebab-case")]
+pub enum HueApproximation {
+    /// GIMP-style HSL adjustment (see the module docs).
+    #[default]
+    Exact,
+    /// The color matrices browsers use for the `hue-rotate()`, `saturate()`
+    /// and `brightness()` filters, applied in sRGB.
+    ///
+    /// Matches the output of [`FolderColorTargetConfig::to_css_filter`],
+    /// so a CSS preview and the final render agree. Less faithful to the
+    /// target color than `Exact`: the matrices rotate hue around the
+    /// luminance axis and clip saturated colors.
+    Matrix,
+    /// Paints every pixel in the target color, scaled in linear light so
+    /// its luminance keeps its ratio to the surface's.
+    ///
+    /// The surface color becomes exactly the target, whatever the base
+    /// icon's own hue, so one target gives the same folder on every
+    /// platform; shading is kept, pixel hues are not. Pixels brighter than
+    /// the target's gamut allows fade toward white.
+    PreserveLuminance,
+    /// The hue rotation and saturation of `Exact`, as color matrices
+    /// applied in linear light around the luminance axis, and the
+    /// lightness change as a luminance scale.
+    ///
+    /// Unlike the HSL adjustment, rotating hue leaves each pixel's
+    /// relative luminance alone, so bright highlights don't darken or
+    /// bloom as their hue moves. Only colors pushed out of gamut clip.
+    Linear,
+}
+
+impl HueApproximation {
+    /// Returns true for [`HueApproximation::Exact`].
+    pub fn is_exact(&self) -> bool {
+        *self == Self::Exact
+    }
+}
+
+// ============================================================================
+// ProtectedColor
+// ============================================================================
+
+/// A color range excluded from color targeting.
+///
+/// Tolerances are in the range 0.0–1.0 and are clamped on construction.
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "jsonschema", derive(schemars::JsonSchema))]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+pub enum ProtectedColor {
+    /// A specific color. Pixels within `tolerance` (normalized RGB
+    /// distance, where 1.0 spans black to white) are protected.
+    Rgb {
+        /// Red channel (0–255).
+        r: u8,
+        /// Green channel (0–255).
+        g: u8,
+        /// Blue channel (0–255).
+        b: u8,
+        /// Maximum normalized distance from the color.
+        tolerance: f32,
+    },
+    /// Whites, greys and blacks. Pixels whose chroma (max − min channel,
+    /// normalized) is at most `tolerance` are protected.
+    Neutral {
+        /// Maximum normalized chroma.
+        tolerance: f32,
+    },
+}
+
+impl ProtectedColor {
+    /// Protects a specific color and its near neighbors.
+    pub fn rgb(r: u8, g: u8, b: u8, tolerance: f32) -> Self {
+        Self::Rgb {
+            r,
+            g,
+            b,
+            tolerance: finite_or(tolerance, 0.0).clamp(0.0, 1.0),
+        }
+    }
+
+    /// Protects whites, greys and blacks.
+    pub fn neutral(tolerance: f32) -> Self {
+        Self::Neutral {
+            tolerance: finite_or(tolerance, 0.0).clamp(0.0, 1.0),
+        }
+    }
+
+    /// Returns true if the given pixel color falls within this range.
+    pub fn matches(&self, pr: u8, pg: u8, pb: u8) -> bool {
+        match *self {
+            Self::Rgb { r, g, b, tolerance } => {
+                let dr = pr as f32 - r as f32;
+                let dg = pg as f32 - g as f32;
+                let db = pb as f32 - b as f32;
+                let distance = (dr * dr + dg * dg + db * db).sqrt() / (255.0 * 3f32.sqrt());
+                distance <= tolerance
+            }
+            Self::Neutral { tolerance } => {
+                let chroma = pr.max(pg).max(pb) - pr.min(pg).min(pb);
+                chroma as f32 / 255.0 <= tolerance
+            }
+        }
     }
 }
 
@@ -73,11 +310,22 @@ impl LayerConfig for FolderColorTargetConfig {
 // ============================================================================
 
 impl Layer<FolderColorTargetConfig> {
+    /// Returns the upstream versions this layer's cache depends on.
+    ///
+    /// Root layer — only depends on the accent when following it.
+    pub(crate) fn dependencies(&self, versions: &LayerVersions) -> DependencyVersion {
+        if self.config().is_some_and(|c| c.follow_accent) {
+            DependencyVersion::from_version(versions.accent)
+        } else {
+            DependencyVersion::NONE
+        }
+    }
+
     /// Apply the color target layer to the render context, using cache if valid.
     ///
     /// Transforms `ctx.image` using GIMP-style HSL adjustment and emits
-    /// [`DominantColor`] for downstream layers. If inactive, the context
-    /// passes through unchanged.
+    /// [`DominantColor`] and [`DerivedPalette`] for downstream layers. If
+    /// inactive, the context passes through unchanged.
     ///
     /// The [`SurfaceColor`] must be present in the render context.
     ///
@@ -88,29 +336,48 @@ impl Layer<FolderColorTargetConfig> {
         &mut self,
         ctx: &mut RenderContext,
         key: CacheKey,
-        _versions: &LayerVersions,
+        versions: &LayerVersions,
+    ) -> Result<(), RenderError> {
+        let deps = self.dependencies(versions);
+        self.apply_with_deps(ctx, key, deps)
+    }
+
+    /// Like [`apply`](Self::apply), with the cache validated against a
+    /// caller-computed dependency version.
+    pub(crate) fn apply_with_deps(
+        &mut self,
+        ctx: &mut RenderContext,
+        key: CacheKey,
+        deps: DependencyVersion,
     ) -> Result<(), RenderError> {
         if !self.is_active() {
             return Ok(());
         }
 
-        let deps = DependencyVersion::NONE; // Root layer — no upstream dependencies
+        let config = self.config().unwrap();
+        let (r, g, b) = config.effective_target(ctx);
 
         // Check cache first
         if let Some(CachedOutput::Image(img)) = self.get_cached(key, deps) {
             ctx.image = img.clone();
-            let config = self.config().unwrap();
-            ctx.set(DominantColor::new(config.target_r, config.target_g, config.target_b, 255));
+            ctx.set(DominantColor::new(r, g, b, 255));
+            ctx.set(DerivedPalette::from_rgb(r, g, b));
             return Ok(());
         }
 
-        let config = self.config().unwrap();
         let surface = ctx
             .get::<SurfaceColor>()
             .expect("SurfaceColor must be set in RenderContext");
 
-        ctx.image = apply_folder_color_target(&ctx.image, surface, config);
-        ctx.set(DominantColor::new(config.target_r, config.target_g, config.target_b, 255));
+        let target = FolderColorTargetConfig {
+            target_r: r,
+            target_g: g,
+            target_b: b,
+            ..config.clone()
+        };
+        ctx.image = apply_folder_color_target(&ctx.image, surface, &target);
+        ctx.set(DominantColor::new(r, g, b, 255));
+        ctx.set(DerivedPalette::from_rgb(r, g, b));
 
         // Cache the transformed image
         self.store(key, CachedOutput::Image(ctx.image.clone()), deps);
@@ -125,7 +392,8 @@ impl Layer<FolderColorTargetConfig> {
 /// Applies GIMP-style HSL color targeting to an icon image.
 ///
 /// Computes hue/saturation/lightness deltas from `surface_color` and
-/// `config.target_r/g/b`, then for each opaque pixel:
+/// `config.target_r/g/b`, then for each opaque pixel that does not match
+/// one of `config.protected_colors`:
 ///
 /// 1. Converts sRGB → HSL
 /// 2. Shifts hue by `target_hue − surface_hue`
@@ -133,68 +401,314 @@ impl Layer<FolderColorTargetConfig> {
 /// 4. Scales lightness by `target_light / surface_light`
 /// 5. Clamps S and L to \[0.0, 1.0\]
 /// 6. Converts back to sRGB
+///
+/// With [`HueApproximation::Matrix`], steps 1–6 are replaced by the
+/// equivalent CSS filter matrices; with
+/// [`HueApproximation::PreserveLuminance`], by the target color scaled to
+/// the pixel's relative luminance; with [`HueApproximation::Linear`], by
+/// luminance-preserving rotation and saturation matrices in linear light.
 pub(crate) fn apply_folder_color_target(
     icon: &IconImage,
     surface: &SurfaceColor,
     config: &FolderColorTargetConfig,
 ) -> IconImage {
-    // Compute HSL deltas from surface → target
-    let surface_rgb = Srgb::new(
-        surface.r as f32 / 255.0,
-        surface.g as f32 / 255.0,
-        surface.b as f32 / 255.0,
-    );
-    let surface_hsl: Hsl = surface_rgb.into_color();
-    let surface_hue = surface_hsl.hue.into_positive_degrees();
-    let surface_saturation = surface_hsl.saturation;
-    let surface_lightness = surface_hsl.lightness;
-
-    let target_rgb = Srgb::new(
-        config.target_r as f32 / 255.0,
-        config.target_g as f32 / 255.0,
-        config.target_b as f32 / 255.0,
-    );
-    let target_hsl: Hsl = target_rgb.into_color();
-    let target_hue = target_hsl.hue.into_positive_degrees();
-    let target_saturation = target_hsl.saturation;
-    let target_lightness = target_hsl.lightness;
-
-    let hue_shift = (target_hue - surface_hue).rem_euclid(360.0);
-    let sat_factor = if surface_saturation > 0.0 {
-        (target_saturation / surface_saturation).clamp(0.0, 2.0)
-    } else {
-        1.0
-    };
-    let light_factor = if surface_lightness > 0.0 {
-        (target_lightness / surface_lightness).clamp(0.0, 2.0)
-    } else {
-        1.0
-    };
+    let shift = ColorShift::new(surface, (config.target_r, config.target_g, config.target_b), config.hue_approximation);
 
     // Apply per-pixel
-    let mut result = icon.data.clone();
+    let mut result = icon.data().clone();
     for pixel in result.pixels_mut() {
         let [r, g, b, a] = pixel.0;
-        if a == 0 {
+        if a == 0 || config.protected_colors.iter().any(|p| p.matches(r, g, b)) {
             continue;
         }
+        let [r, g, b] = shift.apply(r, g, b);
+        pixel.0 = [r, g, b, a];
+    }
+
+    IconImage::new(result, icon.scale, icon.content_bounds).with_variant(icon.variant())
+}
+
+/// A per-color shift from a surface color to a target color, in the
+/// configured [`HueApproximation`].
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum ColorShift {
+    Exact(HslShift),
+    Matrix(CssFilterMatrix),
+    Luminance(LuminanceMap),
+    Linear(LinearRotation),
+}
+
+impl ColorShift {
+    pub(crate) fn new(surface: &SurfaceColor, target: (u8, u8, u8), approximation: HueApproximation) -> Self {
+        let shift = HslShift::new(surface, target);
+        match approximation {
+            HueApproximation::Exact => Self::Exact(shift),
+            HueApproximation::Matrix => Self::Matrix(CssFilterMatrix::new(shift)),
+            HueApproximation::PreserveLuminance => Self::Luminance(LuminanceMap::new(surface, target)),
+            HueApproximation::Linear => Self::Linear(LinearRotation::new(surface, target, shift)),
+        }
+    }
+
+    /// Applies the shift to one sRGB color.
+    pub(crate) fn apply(&self, r: u8, g: u8, b: u8) -> [u8; 3] {
+        match self {
+            Self::Exact(shift) => shift.apply(r, g, b),
+            Self::Matrix(matrix) => matrix.apply(r, g, b),
+            Self::Luminance(map) => map.apply(r, g, b),
+            Self::Linear(rotation) => rotation.apply(r, g, b),
+        }
+    }
+}
+
+/// The hue shift and saturation/lightness factors taking a surface color
+/// to a target color.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct HslShift {
+    hue_shift: f32,
+    sat_factor: f32,
+    light_factor: f32,
+}
+
+impl HslShift {
+    /// Computes the HSL deltas from `surface` to `target`.
+    pub(crate) fn new(surface: &SurfaceColor, target: (u8, u8, u8)) -> Self {
+        let surface_rgb = Srgb::new(
+            surface.r as f32 / 255.0,
+            surface.g as f32 / 255.0,
+            surface.b as f32 / 255.0,
+        );
+        let surface_hsl: Hsl = surface_rgb.into_color();
+        let surface_hue = surface_hsl.hue.into_positive_degrees();
+        let surface_saturation = surface_hsl.saturation;
+        let surface_lightness = surface_hsl.lightness;
+
+        let target_rgb = Srgb::new(target.0 as f32 / 255.0, target.1 as f32 / 255.0, target.2 as f32 / 255.0);
+        let target_hsl: Hsl = target_rgb.into_color();
+        let target_hue = target_hsl.hue.into_positive_degrees();
+        let target_saturation = target_hsl.saturation;
+        let target_lightness = target_hsl.lightness;
+
+        let hue_shift = (target_hue - surface_hue).rem_euclid(360.0);
+        let sat_factor = if surface_saturation > 0.0 {
+            (target_saturation / surface_saturation).clamp(0.0, 2.0)
+        } else {
+            1.0
+        };
+        let light_factor = if surface_lightness > 0.0 {
+            (target_lightness / surface_lightness).clamp(0.0, 2.0)
+        } else {
+            1.0
+        };
+
+        Self {
+            hue_shift,
+            sat_factor,
+            light_factor,
+        }
+    }
 
+    /// A plain hue rotation (in degrees) and saturation factor, leaving
+    /// lightness alone.
+    pub(crate) fn rotation(hue_shift: f32, sat_factor: f32) -> Self {
+        Self {
+            hue_shift: hue_shift.rem_euclid(360.0),
+            sat_factor: sat_factor.clamp(0.0, 2.0),
+            light_factor: 1.0,
+        }
+    }
+
+    /// Returns the hue shift in degrees and the saturation factor.
+    pub(crate) fn hue_and_saturation(&self) -> (f32, f32) {
+        (self.hue_shift, self.sat_factor)
+    }
+
+    /// Formats the shift as CSS filter functions, omitting identities.
+    pub(crate) fn to_css_filter(self) -> String {
+        let (hue, saturate, brightness) = self.css_arguments();
+        let mut functions = Vec::new();
+        if hue != 0.0 {
+            functions.push(format!("hue-rotate({hue:.1}deg)"));
+        }
+        if saturate != 1.0 {
+            functions.push(format!("saturate({saturate:.3})"));
+        }
+        if brightness != 1.0 {
+            functions.push(format!("brightness({brightness:.3})"));
+        }
+        if functions.is_empty() {
+            "none".to_string()
+        } else {
+            functions.join(" ")
+        }
+    }
+
+    /// Returns the `hue-rotate()`, `saturate()` and `brightness()`
+    /// arguments, rounded to the precision written into CSS.
+    fn css_arguments(self) -> (f32, f32, f32) {
+        let round = |value: f32, scale: f32| (value * scale).round() / scale;
+        (
+            round(self.hue_shift, 10.0),
+            round(self.sat_factor, 1000.0),
+            round(self.light_factor, 1000.0),
+        )
+    }
+
+    /// Applies the shift to one sRGB color.
+    pub(crate) fn apply(&self, r: u8, g: u8, b: u8) -> [u8; 3] {
         let rgb = Srgb::new(r as f32 / 255.0, g as f32 / 255.0, b as f32 / 255.0);
         let mut hsl: Hsl = rgb.into_color();
 
-        hsl.hue += hue_shift;
-        hsl.saturation = (hsl.saturation * sat_factor).clamp(0.0, 1.0);
-        hsl.lightness = (hsl.lightness * light_factor).clamp(0.0, 1.0);
+        hsl.hue += self.hue_shift;
+        hsl.saturation = (hsl.saturation * self.sat_factor).clamp(0.0, 1.0);
+        hsl.lightness = (hsl.lightness * self.light_factor).clamp(0.0, 1.0);
 
         let mutated: Srgb = hsl.into_color();
-        pixel.0 = [
+        [
             (mutated.red * 255.0).round() as u8,
             (mutated.green * 255.0).round() as u8,
             (mutated.blue * 255.0).round() as u8,
-            a,
-        ];
+        ]
     }
+}
 
-    IconImage::new(result, icon.scale, icon.content_bounds)
+/// The filter chain produced by [`HslShift::to_css_filter`], as the color
+/// matrices defined in the Filter Effects spec.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct CssFilterMatrix {
+    hue_rotate: [[f32; 3]; 3],
+    saturate: [[f32; 3]; 3],
+    brightness: f32,
 }
 
+impl CssFilterMatrix {
+    fn new(shift: HslShift) -> Self {
+        let (hue, s, brightness) = shift.css_arguments();
+        let (sin, cos) = hue.to_radians().sin_cos();
+        Self {
+            hue_rotate: [
+                [
+                    0.213 + cos * 0.787 - sin * 0.213,
+                    0.715 - cos * 0.715 - sin * 0.715,
+                    0.072 - cos * 0.072 + sin * 0.928,
+                ],
+                [
+                    0.213 - cos * 0.213 + sin * 0.143,
+                    0.715 + cos * 0.285 + sin * 0.140,
+                    0.072 - cos * 0.072 - sin * 0.283,
+                ],
+                [
+                    0.213 - cos * 0.213 - sin * 0.787,
+                    0.715 - cos * 0.715 + sin * 0.715,
+                    0.072 + cos * 0.928 + sin * 0.072,
+                ],
+            ],
+            saturate: [
+                [0.213 + 0.787 * s, 0.715 - 0.715 * s, 0.072 - 0.072 * s],
+                [0.213 - 0.213 * s, 0.715 + 0.285 * s, 0.072 - 0.072 * s],
+                [0.213 - 0.213 * s, 0.715 - 0.715 * s, 0.072 + 0.928 * s],
+            ],
+            brightness,
+        }
+    }
+
+    /// Applies each filter in order, clamping between steps as browsers do.
+    fn apply(&self, r: u8, g: u8, b: u8) -> [u8; 3] {
+        let multiply = |m: &[[f32; 3]; 3], c: [f32; 3]| {
+            m.map(|row| (row[0] * c[0] + row[1] * c[1] + row[2] * c[2]).clamp(0.0, 1.0))
+        };
+        let color = [r, g, b].map(|c| c as f32 / 255.0);
+        let color = multiply(&self.saturate, multiply(&self.hue_rotate, color));
+        color.map(|c| ((c * self.brightness).clamp(0.0, 1.0) * 255.0).round() as u8)
+    }
+}
+
+/// The target color at each pixel's luminance relative to the surface, in
+/// linear light.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct LuminanceMap {
+    target: LinSrgb,
+    target_luminance: f32,
+    surface_luminance: f32,
+}
+
+impl LuminanceMap {
+    fn new(surface: &SurfaceColor, target: (u8, u8, u8)) -> Self {
+        Self {
+            target: Srgb::new(target.0, target.1, target.2).into_format::<f32>().into_linear(),
+            target_luminance: relative_luminance(target.0, target.1, target.2),
+            surface_luminance: relative_luminance(surface.r, surface.g, surface.b),
+        }
+    }
+
+    fn apply(&self, r: u8, g: u8, b: u8) -> [u8; 3] {
+        let luminance = match self.surface_luminance {
+            0.0 => self.target_luminance,
+            surface => self.target_luminance * relative_luminance(r, g, b) / surface,
+        };
+        let white = LinSrgb::new(1.0, 1.0, 1.0);
+        // A black target has no hue to keep, so it scales as grey
+        let color = match self.target_luminance {
+            0.0 => white * luminance,
+            target => self.target * (luminance / target),
+        };
+
+        // Past the gamut, keep the hue at full brightness and make up the
+        // missing luminance with white
+        let peak = color.red.max(color.green).max(color.blue);
+        let color = if peak > 1.0 {
+            let bright_luminance = luminance / peak;
+            let t = ((luminance.min(1.0) - bright_luminance) / (1.0 - bright_luminance).max(f32::EPSILON)).clamp(0.0, 1.0);
+            color / peak + (white - color / peak) * t
+        } else {
+            color
+        };
+        let color: Srgb<u8> = Srgb::from_linear(color);
+        [color.red, color.green, color.blue]
+    }
+}
+
+/// Rec. 709 luminance weights of linear red, green and blue.
+const LUMA: [f32; 3] = [0.2126, 0.7152, 0.0722];
+
+/// An [`HslShift`]'s hue rotation and saturation as one matrix on linear
+/// RGB, scaled by the ratio of target to surface luminance.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct LinearRotation {
+    matrix: [[f32; 3]; 3],
+}
+
+impl LinearRotation {
+    fn new(surface: &SurfaceColor, target: (u8, u8, u8), shift: HslShift) -> Self {
+        let (hue, saturation) = shift.hue_and_saturation();
+        let (sin, cos) = hue.to_radians().sin_cos();
+        let [lr, lg, lb] = LUMA;
+        // Each row keeps grey as grey (rows sum to 1) and every column's
+        // luminance-weighted sum is its weight, so luminance is unchanged
+        let skew = [
+            [-lr, -lg, 1.0 - lb],
+            [(lr * lr + lb * (1.0 - lr)) / lg, lr - lb, -(lr * (1.0 - lb) + lb * lb) / lg],
+            [-(1.0 - lr), lg, lb],
+        ];
+        let identity = |i, j| if i == j { 1.0 } else { 0.0 };
+        let rotate: [[f32; 3]; 3] = std::array::from_fn(|i| std::array::from_fn(|j| LUMA[j] + cos * (identity(i, j) - LUMA[j]) + sin * skew[i][j]));
+        let saturate: [[f32; 3]; 3] = std::array::from_fn(|i| std::array::from_fn(|j| LUMA[j] + saturation * (identity(i, j) - LUMA[j])));
+
+        let surface_luminance = relative_luminance(surface.r, surface.g, surface.b);
+        let scale = match surface_luminance {
+            0.0 => 1.0,
+            surface => relative_luminance(target.0, target.1, target.2) / surface,
+        };
+        let matrix = std::array::from_fn(|i| {
+            std::array::from_fn(|j| scale * (0..3).map(|k| saturate[i][k] * rotate[k][j]).sum::<f32>())
+        });
+        Self { matrix }
+    }
+
+    fn apply(&self, r: u8, g: u8, b: u8) -> [u8; 3] {
+        let color = Srgb::new(r, g, b).into_format::<f32>().into_linear();
+        let color = [color.red, color.green, color.blue];
+        let [red, green, blue] = self.matrix.map(|row| (row[0] * color[0] + row[1] * color[1] + row[2] * color[2]).clamp(0.0, 1.0));
+        let color: Srgb<u8> = Srgb::from_linear(LinSrgb::new(red, green, blue));
+        [color.red, color.green, color.blue]
+    }
+}
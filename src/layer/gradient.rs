@@ -0,0 +1,341 @@
+//! Gradient tint layer — configuration and rendering.
+//!
+//! Tints the icon with a linear or radial gradient spanning its content
+//! bounds. The tint only touches painted pixels: alpha is left unchanged,
+//! so transparent margins stay transparent.
+
+use super::svg::BlendMode;
+use super::{finite_or, CacheKey, CachedOutput, DependencyVersion, Layer, LayerConfig, LayerVersions, RenderContext};
+use crate::error::RenderError;
+use crate::icon::{IconImage, RectPx};
+
+// ============================================================================
+// GradientTintConfig
+// ============================================================================
+
+/// The shape of a gradient.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "jsonschema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "kebab-case")]
+pub enum GradientKind {
+    /// Colors change along a line at the configured angle.
+    #[default]
+    Linear,
+    /// Colors change outward from the center of the content bounds,
+    /// reaching the last stop at the corners.
+    Radial,
+}
+
+/// A color at a position along a gradient.
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "jsonschema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct GradientStop {
+    /// Position along the gradient (0.0-1.0).
+    pub offset: f32,
+    /// Color as RGB.
+    pub color: (u8, u8, u8),
+}
+
+impl GradientStop {
+    /// Creates a stop. The offset is clamped to 0.0-1.0.
+    pub fn new(offset: f32, r: u8, g: u8, b: u8) -> Self {
+        Self {
+            offset: finite_or(offset, 0.0).clamp(0.0, 1.0),
+            color: (r, g, b),
+        }
+    }
+}
+
+/// Configuration for a gradient tint — pure data.
+///
+/// Stops are sorted by offset; before the first and after the last stop
+/// the nearest stop's color applies, as in CSS. A single stop tints with a
+/// flat color, and no stops leave the icon unchanged.
+///
+/// # Example
+///
+/// ```
+/// use folco_renderer::{BlendMode, GradientStop, GradientTintConfig};
+///
+/// // Light at the top, dark at the bottom
+/// let tint = GradientTintConfig::linear(180.0, vec![GradientStop::new(0.0, 255, 255, 255), GradientStop::new(1.0, 0, 0, 0)])
+///     .with_blend_mode(BlendMode::SoftLight)
+///     .with_opacity(0.5);
+/// assert_eq!(tint.stops.len(), 2);
+/// ```
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "jsonschema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct GradientTintConfig {
+    /// Linear or radial.
+    #[serde(default)]
+    pub kind: GradientKind,
+
+    /// Color stops, sorted by offset. Sorted again when deserialized and
+    /// when passed to [`Layer::set_config`].
+    #[serde(deserialize_with = "deserialize_stops")]
+    pub stops: Vec<GradientStop>,
+
+    /// Direction of a linear gradient in degrees, clockwise from "to top"
+    /// as in CSS (90 runs left to right). Ignored for radial gradients.
+    #[serde(default)]
+    pub angle: f32,
+
+    /// How the gradient combines with the icon's colors.
+    #[serde(default, skip_serializing_if = "BlendMode::is_normal")]
+    pub blend_mode: BlendMode,
+
+    /// Strength of the tint (0.0-1.0).
+    pub opacity: f32,
+}
+
+impl GradientTintConfig {
+    /// Creates a linear gradient at `angle` degrees, at full opacity.
+    pub fn linear(angle: f32, stops: Vec<GradientStop>) -> Self {
+        Self::new(GradientKind::Linear, angle, stops)
+    }
+
+    /// Creates a radial gradient, at full opacity.
+    pub fn radial(stops: Vec<GradientStop>) -> Self {
+        Self::new(GradientKind::Radial, 0.0, stops)
+    }
+
+    fn new(kind: GradientKind, angle: f32, mut stops: Vec<GradientStop>) -> Self {
+        sort_stops(&mut stops);
+        Self {
+            kind,
+            stops,
+            angle: finite_or(angle, 0.0),
+            blend_mode: BlendMode::default(),
+            opacity: 1.0,
+        }
+    }
+
+    /// Sets the blend mode.
+    pub fn with_blend_mode(mut self, blend_mode: BlendMode) -> Self {
+        self.blend_mode = blend_mode;
+        self
+    }
+
+    /// Sets the opacity. Clamped to 0.0-1.0.
+    pub fn with_opacity(mut self, opacity: f32) -> Self {
+        self.opacity = finite_or(opacity, 1.0).clamp(0.0, 1.0);
+        self
+    }
+
+    /// Returns the gradient color at position `t`, in 0.0–1.0 per channel.
+    ///
+    /// Expects the stops sorted.
+    fn color_at(&self, t: f32) -> [f32; 3] {
+        let to_f32 = |(r, g, b): (u8, u8, u8)| [r as f32 / 255.0, g as f32 / 255.0, b as f32 / 255.0];
+        let stops = &self.stops;
+        let upper = stops.partition_point(|s| s.offset <= t);
+        if upper == 0 {
+            return to_f32(stops[0].color);
+        }
+        if upper == stops.len() {
+            return to_f32(stops[upper - 1].color);
+        }
+        let (lo, hi) = (&stops[upper - 1], &stops[upper]);
+        let span = hi.offset - lo.offset;
+        let f = if span > 0.0 { (t - lo.offset) / span } else { 1.0 };
+        let (a, b) = (to_f32(lo.color), to_f32(hi.color));
+        [0, 1, 2].map(|i| a[i] + (b[i] - a[i]) * f)
+    }
+
+    /// Returns the gradient position (unclamped) of pixel center `(x, y)`.
+    fn position(&self, bounds: &RectPx, x: u32, y: u32) -> f32 {
+        let (w, h) = (bounds.width as f32, bounds.height as f32);
+        let dx = x as f32 + 0.5 - (bounds.x as f32 + w / 2.0);
+        let dy = y as f32 + 0.5 - (bounds.y as f32 + h / 2.0);
+        match self.kind {
+            GradientKind::Linear => {
+                // CSS gradient line: through the center, long enough that
+                // the corners land on offsets 0 and 1
+                let (sin, cos) = self.angle.to_radians().sin_cos();
+                let length = (w * sin).abs() + (h * cos).abs();
+                if length > 0.0 { (dx * sin - dy * cos) / length + 0.5 } else { 0.5 }
+            }
+            GradientKind::Radial => {
+                let radius = (w * w + h * h).sqrt() / 2.0;
+                if radius > 0.0 { (dx * dx + dy * dy).sqrt() / radius } else { 0.0 }
+            }
+        }
+    }
+}
+
+impl LayerConfig for GradientTintConfig {
+    fn differs_from(&self, other: &Self) -> bool {
+        self.kind != other.kind
+            || self.stops != other.stops
+            || (self.angle - other.angle).abs() > 0.0001
+            || self.blend_mode != other.blend_mode
+            || (self.opacity - other.opacity).abs() > 0.0001
+    }
+
+    fn normalize(&mut self) {
+        sort_stops(&mut self.stops);
+    }
+}
+
+/// Sorts stops by offset, keeping the order of stops at the same offset.
+fn sort_stops(stops: &mut [GradientStop]) {
+    stops.sort_by(|a, b| a.offset.total_cmp(&b.offset));
+}
+
+/// Deserializes [`GradientTintConfig::stops`], sorted.
+fn deserialize_stops<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<Vec<GradientStop>, D::Error> {
+    let mut stops = <Vec<GradientStop> as serde::Deserialize>::deserialize(deserializer)?;
+    sort_stops(&mut stops);
+    Ok(stops)
+}
+
+// ============================================================================
+// Layer Rendering
+// ============================================================================
+
+impl Layer<GradientTintConfig> {
+    /// Returns the upstream versions this layer's cache depends on.
+    ///
+    /// Tints the color target's output, so follows it (and the accent it
+    /// may follow).
+    pub(crate) fn dependencies(&self, versions: &LayerVersions) -> DependencyVersion {
+        DependencyVersion::combine(&[versions.folder_color_target, versions.accent])
+    }
+
+    /// Apply the gradient tint to the render context, using cache if valid.
+    ///
+    /// Transforms `ctx.image` directly. If inactive, the context passes
+    /// through unchanged.
+    pub fn apply(&mut self, ctx: &mut RenderContext, key: CacheKey, versions: &LayerVersions) -> Result<(), RenderError> {
+        let deps = self.dependencies(versions);
+        self.apply_with_deps(ctx, key, deps)
+    }
+
+    /// Like [`apply`](Self::apply), with the cache validated against a
+    /// caller-computed dependency version.
+    pub(crate) fn apply_with_deps(
+        &mut self,
+        ctx: &mut RenderContext,
+        key: CacheKey,
+        deps: DependencyVersion,
+    ) -> Result<(), RenderError> {
+        if !self.is_active() {
+            return Ok(());
+        }
+
+        if let Some(CachedOutput::Image(img)) = self.get_cached(key, deps) {
+            ctx.image = img.clone();
+            return Ok(());
+        }
+
+        apply_gradient_tint(&mut ctx.image, self.config().unwrap());
+        self.store(key, CachedOutput::Image(ctx.image.clone()), deps);
+        Ok(())
+    }
+}
+
+/// Tints every painted pixel of `icon` with the gradient.
+pub(crate) fn apply_gradient_tint(icon: &mut IconImage, config: &GradientTintConfig) {
+    // Deserialized configs bypass the builder clamp
+    let opacity = config.opacity.clamp(0.0, 1.0);
+    if config.stops.is_empty() || opacity == 0.0 {
+        return;
+    }
+
+    let bounds = icon.content_bounds;
+    for (x, y, pixel) in icon.data_mut().enumerate_pixels_mut() {
+        if pixel[3] == 0 {
+            continue;
+        }
+        let source = config.color_at(config.position(&bounds, x, y));
+        for (channel, source) in pixel.0[..3].iter_mut().zip(source) {
+            let backdrop = *channel as f32 / 255.0;
+            let blended = config.blend_mode.blend(backdrop, source).clamp(0.0, 1.0);
+            *channel = ((backdrop + (blended - backdrop) * opacity) * 255.0).round() as u8;
+        }
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use image::{Rgba, RgbaImage};
+
+    use super::*;
+
+    fn icon() -> IconImage {
+        let mut data = RgbaImage::from_pixel(20, 20, Rgba([128, 128, 128, 255]));
+        data.put_pixel(0, 0, Rgba([0, 0, 0, 0]));
+        IconImage::new(data, 1.0, RectPx::new(0, 0, 20, 20))
+    }
+
+    #[test]
+    fn linear_gradient_follows_css_angles() {
+        let stops = vec![GradientStop::new(1.0, 0, 0, 255), GradientStop::new(0.0, 255, 0, 0)];
+        let mut left_to_right = icon();
+        apply_gradient_tint(&mut left_to_right, &GradientTintConfig::linear(90.0, stops.clone()));
+
+        // Transparent pixels stay untouched
+        assert_eq!(left_to_right.data().get_pixel(0, 0).0, [0, 0, 0, 0]);
+        let [r, _, b, a] = left_to_right.data().get_pixel(1, 10).0;
+        assert!(r > 230 && b < 25 && a == 255);
+        let [r, _, b, _] = left_to_right.data().get_pixel(19, 10).0;
+        assert!(r < 25 && b > 230);
+
+        // 180° runs top to bottom
+        let mut top_to_bottom = icon();
+        apply_gradient_tint(&mut top_to_bottom, &GradientTintConfig::linear(180.0, stops));
+        assert!(top_to_bottom.data().get_pixel(10, 1)[0] > 230);
+        assert!(top_to_bottom.data().get_pixel(10, 19)[2] > 230);
+    }
+
+    #[test]
+    fn radial_blend_and_opacity() {
+        let stops = vec![GradientStop::new(0.0, 255, 255, 255), GradientStop::new(1.0, 0, 0, 0)];
+        let mut radial = icon();
+        apply_gradient_tint(&mut radial, &GradientTintConfig::radial(stops.clone()).with_blend_mode(BlendMode::Multiply));
+        // Multiplying by white keeps the center, by near-black darkens the corner
+        assert!(radial.data().get_pixel(10, 10)[0].abs_diff(128) <= 8);
+        assert!(radial.data().get_pixel(19, 19)[0] < 20);
+
+        let mut faint = icon();
+        apply_gradient_tint(&mut faint, &GradientTintConfig::radial(vec![GradientStop::new(0.0, 255, 255, 255)]).with_opacity(0.5));
+        assert_eq!(faint.data().get_pixel(5, 5).0, [192, 192, 192, 255]);
+
+        let mut untouched = icon();
+        apply_gradient_tint(&mut untouched, &GradientTintConfig::radial(Vec::new()));
+        assert_eq!(untouched.data(), icon().data());
+    }
+
+    #[test]
+    fn unsorted_stops_are_sorted_when_loaded() {
+        let json = r#"{ "stops": [{ "offset": 1.0, "color": [0, 0, 255] }, { "offset": 0.0, "color": [255, 0, 0] }], "angle": 90.0, "opacity": 1.0 }"#;
+        let loaded: GradientTintConfig = serde_json::from_str(json).unwrap();
+        assert_eq!(loaded.stops, GradientTintConfig::linear(90.0, loaded.stops.clone()).stops);
+        assert_eq!(loaded.stops[0].color, (255, 0, 0));
+
+        let mut edited = GradientTintConfig::linear(90.0, Vec::new());
+        edited.stops = vec![GradientStop::new(1.0, 0, 0, 255), GradientStop::new(0.0, 255, 0, 0)];
+        let mut layer = Layer::default();
+        layer.set_config(Some(edited));
+        let mut tinted = icon();
+        apply_gradient_tint(&mut tinted, layer.config().unwrap());
+        assert!(tinted.data().get_pixel(1, 10)[0] > 230);
+    }
+
+    #[test]
+    fn blend_modes_match_reference_values() {
+        assert_eq!(BlendMode::Multiply.blend(0.5, 0.5), 0.25);
+        assert_eq!(BlendMode::Screen.blend(0.5, 0.5), 0.75);
+        assert_eq!(BlendMode::Overlay.blend(0.25, 1.0), 0.5);
+        assert_eq!(BlendMode::SoftLight.blend(0.5, 0.5), 0.5);
+        for mode in [BlendMode::Normal, BlendMode::Multiply, BlendMode::Screen, BlendMode::Overlay, BlendMode::SoftLight] {
+            assert!((0.0..=1.0).contains(&mode.blend(0.3, 0.8)), "{mode:?}");
+        }
+    }
+}
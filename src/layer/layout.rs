@@ -0,0 +1,117 @@
+//! Where a render placed things.
+//!
+//! [`LayerPipeline::render_with_layout`] returns, alongside the image, the
+//! rectangles the pipeline's placement math produced: the content bounds,
+//! the safe area that decal, overlay and text sizes are fractions of, and
+//! the rectangles the tile layers drew into. Editors can outline them with
+//! [`preview::with_guides`](crate::preview::with_guides) instead of
+//! repeating the geometry.
+
+use super::{budget, CacheKey, LayerPipeline};
+use crate::error::RenderError;
+use crate::icon::{IconImage, RectPx, SurfaceColor};
+
+/// Geometry of one render, in image pixels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LayerLayout {
+    /// The icon's content bounds.
+    pub content_bounds: RectPx,
+    /// The largest square centered in the content bounds. Decal, overlay,
+    /// text and shadow lengths are fractions of its side.
+    pub safe_area: RectPx,
+    /// Where the decal drew, clipped to the image.
+    pub decal: Option<RectPx>,
+    /// Where the overlay drew, clipped to the image.
+    pub overlay: Option<RectPx>,
+    /// Where the text label drew, clipped to the image.
+    pub text: Option<RectPx>,
+}
+
+impl LayerLayout {
+    /// Creates the layout of `icon` before any tile layer draws.
+    pub fn new(icon: &IconImage) -> Self {
+        let bounds = icon.content_bounds;
+        let side = bounds.width.min(bounds.height);
+        Self {
+            content_bounds: bounds,
+            safe_area: RectPx::new(
+                bounds.x + (bounds.width - side) / 2,
+                bounds.y + (bounds.height - side) / 2,
+                side,
+                side,
+            ),
+            decal: None,
+            overlay: None,
+            text: None,
+        }
+    }
+
+    /// Returns the tile layers' rectangles in compositing order.
+    pub fn placements(&self) -> impl Iterator<Item = RectPx> {
+        [self.decal, self.overlay, self.text].into_iter().flatten()
+    }
+}
+
+impl LayerPipeline {
+    /// Like [`render`](Self::render), also returning where the layers
+    /// placed things.
+    ///
+    /// Always runs the layers, serving them from their own caches where
+    /// possible, since the composite cache keeps no geometry. Renders at
+    /// full quality regardless of the time budget, and leaves the composite
+    /// cache and cost metrics untouched.
+    ///
+    /// # Errors
+    ///
+    /// Same as [`render`](Self::render).
+    pub fn render_with_layout(
+        &mut self,
+        base: &IconImage,
+        surface_color: &SurfaceColor,
+    ) -> Result<(IconImage, LayerLayout), RenderError> {
+        self.limits.check(base.data().width(), base.data().height())?;
+        if self.is_identity() {
+            return Ok((base.clone(), LayerLayout::new(base)));
+        }
+
+        let key = CacheKey::from_icon(base);
+        self.cache_usage.touch(key);
+        let mut ctx = self.context(base, surface_color);
+        let layout = self.apply_layers(&mut ctx, key, &budget::Deadline::start(None, None))?;
+        self.enforce_cache_limit();
+        Ok((ctx.image, layout))
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use image::RgbaImage;
+
+    use super::*;
+    use crate::layer::{DecalConfig, OverlayPosition, SvgOverlayConfig};
+
+    const SQUARE: &str = r##"<svg xmlns="http://www.w3.org/2000/svg" width="10" height="10"><rect width="10" height="10" fill="#000"/></svg>"##;
+
+    #[test]
+    fn layout_records_where_tile_layers_drew() {
+        let base = IconImage::new(RgbaImage::new(64, 64), 1.0, RectPx::new(0, 8, 64, 48));
+        let surface = SurfaceColor::new(255, 217, 112);
+        let mut pipeline = LayerPipeline::default();
+        pipeline.decal.set_config(Some(DecalConfig::new(SQUARE, 0.5)));
+        pipeline.overlay.set_config(Some(SvgOverlayConfig::new(SQUARE, OverlayPosition::TopLeft, 0.25)));
+
+        let (icon, layout) = pipeline.render_with_layout(&base, &surface).unwrap();
+        assert_eq!(layout.safe_area, RectPx::new(8, 8, 48, 48));
+        assert_eq!(layout.decal, Some(RectPx::new(20, 20, 24, 24)));
+        assert_eq!(layout.overlay, Some(RectPx::new(0, 8, 12, 12)));
+        assert_eq!(layout.text, None);
+        assert_eq!(icon, pipeline.render(&base, &surface).unwrap());
+
+        // Served from the layer caches, the geometry is the same
+        assert_eq!(pipeline.render_with_layout(&base, &surface).unwrap().1, layout);
+    }
+}
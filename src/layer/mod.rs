@@ -9,29 +9,67 @@
 //! Each layer config implements [`LayerConfig`] (pure data with change
 //! detection). Rendering logic lives on the concrete `Layer<Config>` types.
 //!
-//! - **Base layers** (e.g., color target) transform the icon image directly
+//! - **Base layers** (e.g., color target, gradient tint, shadow) transform the icon image directly
 //!   and cache the full result.
-//! - **Stackable layers** (e.g., decal, overlay) render to a transparent tile
+//! - **Stackable layers** (e.g., decal, overlay, text) render to a transparent tile
 //!   of the same dimensions, which the pipeline composites on top.
 //!
 //! Properties flow through the pipeline via [`RenderContext`], enabling
 //! layers to communicate without tight coupling.
+//!
+//! Custom layers implement [`PipelineLayer`] and run in a
+//! [`DynamicPipeline`]; [`prelude`] gathers what they need.
 
+pub mod budget;
+pub mod cancel;
+pub mod cost;
 pub mod folder_color_target;
 pub mod decal;
+pub mod dynamic;
+mod edges;
+pub mod eviction;
+pub mod gradient;
+pub mod layout;
 pub mod overlay;
+pub mod placement;
+pub mod prelude;
+pub mod quality;
+pub mod shadow;
+pub(crate) mod stamps;
 pub mod svg;
-
-pub use folder_color_target::FolderColorTargetConfig;
-pub use decal::DecalConfig;
-pub use overlay::{OverlayPosition, SvgOverlayConfig};
-pub use svg::SvgSource;
-
+pub mod text;
+#[cfg(feature = "trace-export")]
+pub mod trace;
+mod underlay;
+mod vector;
+pub mod warnings;
+
+pub use budget::{EffectiveSize, RenderReport};
+pub use cost::CostEstimate;
+pub use folder_color_target::{FolderColorTargetConfig, HueApproximation, ProtectedColor};
+pub use decal::{DecalConfig, ScaleBreakpoint};
+pub use dynamic::{DynamicPipeline, LayerId, PipelineLayer};
+pub use edges::feather_edges;
+pub use eviction::CacheStats;
+pub use gradient::{GradientKind, GradientStop, GradientTintConfig};
+pub use layout::LayerLayout;
+pub use overlay::{ColorAdjustment, MinSizePolicy, OverlayAnchor, OverlayArrangement, OverlayClip, OverlayPosition, SvgOverlayConfig};
+pub use placement::PlacementRounding;
+pub use quality::RenderQuality;
+pub use shadow::ShadowConfig;
+pub use svg::{BlendMode, ParsedSvg, ResolvedSvg, SvgSource};
+pub use text::{TextFont, TextLayerConfig};
+pub use warnings::RenderWarning;
+
+use crate::analysis::DominantColorSampler;
 use crate::error::RenderError;
-use crate::icon::{IconImage, SurfaceColor};
+use crate::icon::{IconImage, IconVariant, RectPx, SizePx, SurfaceColor};
 use image::RgbaImage;
+use serde::{Deserialize, Serialize};
 use std::any::{Any, TypeId};
+use std::borrow::Cow;
 use std::collections::HashMap;
+use std::hash::{DefaultHasher, Hash, Hasher};
 
 // ============================================================================
 // Render Context
@@ -41,17 +79,25 @@ use std::collections::HashMap;
 ///
 /// Layers can read properties set by upstream layers and emit new properties
 /// for downstream layers to consume. This enables loose coupling between layers.
+/// Properties are keyed by type, so setting a property replaces any earlier
+/// value of the same type. See [`prelude`] for writing custom layers.
 ///
 /// # Example
 ///
-/// ```ignore
+/// ```
+/// use folco_renderer::layer::prelude::*;
+///
+/// let image = IconImage::new_full_content(image::RgbaImage::new(16, 16), 1.0);
+/// let mut ctx = RenderContext::new(image);
+///
 /// // Upstream layer emits a property
-/// ctx.set(DominantColor(r, g, b, a));
+/// ctx.set(DominantColor::new(33, 150, 243, 255));
 ///
 /// // Downstream layer reads the property
 /// if let Some(color) = ctx.get::<DominantColor>() {
-///     // Use the color...
+///     assert_eq!(color.as_tuple(), (33, 150, 243, 255));
 /// }
+/// assert!(!ctx.has::<DecalPlacement>());
 /// ```
 pub struct RenderContext {
     /// The current image being processed through the pipeline.
@@ -94,7 +140,9 @@ impl RenderContext {
 
 /// The dominant color sampled from the image.
 ///
-/// Emitted by layers that modify the image appearance (like color target).
+/// Emitted by layers that modify the image appearance (like color target),
+/// and by the pipeline when it has a
+/// [dominant color sampler](LayerPipeline::set_dominant_color_sampler).
 /// Consumed by layers that need to derive colors from the image (like decal).
 #[derive(Debug, Clone, Copy)]
 pub struct DominantColor {
@@ -114,6 +162,190 @@ impl DominantColor {
     }
 }
 
+/// Where the decal glyph was drawn, in image pixels.
+///
+/// Emitted by the decal layer whenever it draws something. Consumed by
+/// overlays anchored with [`OverlayAnchor::Decal`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DecalPlacement {
+    /// The rectangle the rendered decal occupies.
+    pub bounds: RectPx,
+}
+
+/// Regions already painted by compositing layers, in image pixels.
+///
+/// Each tile layer appends the rectangle it drew into, so later layers can
+/// avoid covering earlier ones (see [`OverlayPosition::Auto`]).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct OccupiedRegions {
+    /// Painted rectangles, in compositing order.
+    pub regions: Vec<RectPx>,
+}
+
+impl OccupiedRegions {
+    /// Appends `region` to the property in `ctx`, creating it if needed.
+    pub fn push(ctx: &mut RenderContext, region: RectPx) {
+        let mut occupied = ctx.get::<Self>().cloned().unwrap_or_default();
+        occupied.regions.push(region);
+        ctx.set(occupied);
+    }
+
+    /// Returns the total area of `rect` covered by occupied regions.
+    ///
+    /// Overlapping regions are counted once per region.
+    pub fn overlap(&self, rect: &RectPx) -> u64 {
+        self.regions
+            .iter()
+            .map(|r| {
+                let w = rect.right().min(r.right()).saturating_sub(rect.x.max(r.x));
+                let h = rect.bottom().min(r.bottom()).saturating_sub(rect.y.max(r.y));
+                w as u64 * h as u64
+            })
+            .sum()
+    }
+}
+
+/// A small copy of the image as the color target, gradient tint and
+/// shadow left it.
+///
+/// Emitted once per render by the pipeline after those layers ran, even
+/// if none is active, so later layers can analyze the folder (for
+/// contrast, placement or picking colors) without scanning the
+/// full-resolution buffer every time. A [`DynamicPipeline`] emits it
+/// before the first other layer. Decals, overlays and text drawn
+/// afterwards are not in it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Thumbnail {
+    /// The image scaled to fit within [`Thumbnail::SIZE`] pixels a side,
+    /// keeping its aspect ratio. Smaller images are copied as they are.
+    pub image: RgbaImage,
+    /// Dimensions of the full-resolution image.
+    pub source_size: SizePx,
+}
+
+impl Thumbnail {
+    /// The longest side of a thumbnail, in pixels.
+    pub const SIZE: u32 = 32;
+
+    /// Makes the thumbnail of `icon`.
+    pub fn of(icon: &IconImage) -> Self {
+        let (width, height) = icon.data().dimensions();
+        let image = if width.max(height) <= Self::SIZE {
+            icon.data().clone()
+        } else {
+            let fit = |side: u32| ((side as u64 * Self::SIZE as u64) / width.max(height) as u64).max(1) as u32;
+            image::imageops::thumbnail(icon.data(), fit(width), fit(height))
+        };
+        Self {
+            image,
+            source_size: SizePx::new(width, height),
+        }
+    }
+
+    /// Sets the thumbnail of `ctx.image` in `ctx`.
+    pub fn emit(ctx: &mut RenderContext) {
+        let thumbnail = Self::of(&ctx.image);
+        ctx.set(thumbnail);
+    }
+
+    /// Returns the thumbnail pixels covering `rect`, given in pixels of the
+    /// full-resolution image.
+    ///
+    /// ```
+    /// use folco_renderer::layer::prelude::*;
+    ///
+    /// let mut ctx = RenderContext::new(IconImage::new_full_content(image::RgbaImage::new(256, 128), 2.0));
+    /// Thumbnail::emit(&mut ctx);
+    /// let thumbnail = ctx.get::<Thumbnail>().unwrap();
+    /// assert_eq!(thumbnail.image.dimensions(), (32, 16));
+    /// assert_eq!(thumbnail.map_rect(&RectPx::new(100, 0, 20, 9)), RectPx::new(12, 0, 3, 2));
+    /// ```
+    pub fn map_rect(&self, rect: &RectPx) -> RectPx {
+        let (width, height) = self.image.dimensions();
+        let map = |start: u32, end: u32, thumb: u32, source: u32| {
+            let scale = thumb as f32 / source.max(1) as f32;
+            let first = ((start as f32 * scale).floor() as u32).min(thumb.saturating_sub(1));
+            let last = ((end as f32 * scale).ceil() as u32).clamp(first + 1, thumb.max(first + 1));
+            (first, last - first)
+        };
+        let (x, w) = map(rect.x, rect.right(), width, self.source_size.width);
+        let (y, h) = map(rect.y, rect.bottom(), height, self.source_size.height);
+        RectPx::new(x, y, w, h)
+    }
+}
+
+/// Harmonious colors derived from the [`DominantColor`].
+///
+/// Emitted alongside [`DominantColor`] so downstream layers (badges, text,
+/// outlines) can pick colors that work with the folder without doing their
+/// own color math. All variants keep the dominant color's saturation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DerivedPalette {
+    /// Hue rotated 180°.
+    pub complementary: (u8, u8, u8),
+    /// Hue rotated −30° and +30°.
+    pub analogous: [(u8, u8, u8); 2],
+    /// Lightness reduced by [`DerivedPalette::LIGHTNESS_STEP`].
+    pub darker: (u8, u8, u8),
+    /// Lightness increased by [`DerivedPalette::LIGHTNESS_STEP`].
+    pub lighter: (u8, u8, u8),
+}
+
+impl DerivedPalette {
+    /// Lightness offset (0.0–1.0) used for the darker and lighter variants.
+    pub const LIGHTNESS_STEP: f32 = 0.2;
+
+    /// Derives the palette from an sRGB color.
+    pub fn from_rgb(r: u8, g: u8, b: u8) -> Self {
+        use palette::{Hsl, IntoColor, Srgb};
+
+        let base: Hsl = Srgb::new(r, g, b).into_format::<f32>().into_color();
+        let to_rgb = |hsl: Hsl| {
+            let rgb: Srgb = hsl.into_color();
+            let rgb = rgb.into_format::<u8>();
+            (rgb.red, rgb.green, rgb.blue)
+        };
+        let rotated = |degrees: f32| to_rgb(Hsl::new(base.hue + degrees, base.saturation, base.lightness));
+        let lightened = |delta: f32| {
+            to_rgb(Hsl::new(base.hue, base.saturation, (base.lightness + delta).clamp(0.0, 1.0)))
+        };
+
+        Self {
+            complementary: rotated(180.0),
+            analogous: [rotated(-30.0), rotated(30.0)],
+            darker: lightened(-Self::LIGHTNESS_STEP),
+            lighter: lightened(Self::LIGHTNESS_STEP),
+        }
+    }
+}
+
+/// A pipeline-wide seed color that themes all layers coherently.
+///
+/// Set via [`LayerPipeline::set_accent_color`] and emitted into the
+/// [`RenderContext`] before any layer runs. Consumers:
+/// - Color target retargets to it when [`FolderColorTargetConfig::follow_accent`] is set.
+/// - Decal uses it as its base color when no upstream [`DominantColor`] exists.
+/// - Overlay draws its SVG in it when [`SvgOverlayConfig::follow_accent`] is set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "jsonschema", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "tsify", derive(tsify_next::Tsify))]
+#[cfg_attr(feature = "tsify", tsify(into_wasm_abi, from_wasm_abi))]
+pub struct AccentColor {
+    /// Red channel (0–255).
+    pub r: u8,
+    /// Green channel (0–255).
+    pub g: u8,
+    /// Blue channel (0–255).
+    pub b: u8,
+}
+
+impl AccentColor {
+    /// Creates a new accent color from RGB values.
+    pub const fn new(r: u8, g: u8, b: u8) -> Self {
+        Self { r, g, b }
+    }
+}
+
 // ============================================================================
 // Layer Traits
 // ============================================================================
@@ -127,8 +359,28 @@ pub trait LayerConfig: Clone {
     /// Returns true if this config differs from another in a way that
     /// would produce different rendering output.
     fn differs_from(&self, other: &Self) -> bool;
+
+    /// Returns a fingerprint of content the config references but doesn't
+    /// hold, such as a registered asset, so caches notice when that
+    /// content changes under an unchanged config. Defaults to 0.
+    fn external_version(&self) -> u64 {
+        0
+    }
+
+    /// Restores invariants the constructors keep but public fields can
+    /// break, such as sorted gradient stops. Called by
+    /// [`Layer::set_config`]. Defaults to doing nothing.
+    fn normalize(&mut self) {}
 }
 
+/// Returns `value`, or `fallback` if it is NaN or infinite.
+///
+/// Values from JavaScript hosts may be NaN, which `f32::clamp` passes
+/// through into layer sizes and cache keys, so config constructors check
+/// with this before clamping.
+pub(crate) fn finite_or(value: f32, fallback: f32) -> f32 {
+    if value.is_finite() { value } else { fallback }
+}
 
 
 // ============================================================================
@@ -157,8 +409,14 @@ impl DependencyVersion {
     }
 
     /// Combines multiple upstream layer versions into one.
+    ///
+    /// Order-sensitive, so a change in one version is never cancelled out
+    /// by an opposite change in another (muted layers count down; see
+    /// [`LayerPipeline::layer_versions`]).
     pub fn combine(versions: &[u64]) -> Self {
-        Self(versions.iter().fold(0u64, |acc, v| acc.wrapping_add(*v)))
+        let mut hasher = DefaultHasher::new();
+        versions.hash(&mut hasher);
+        Self(hasher.finish())
     }
 }
 
@@ -174,10 +432,55 @@ impl DependencyVersion {
 pub struct LayerVersions {
     /// Version of the color target layer.
     pub folder_color_target: u64,
+    /// Version of the gradient tint layer.
+    pub gradient_tint: u64,
+    /// Version of the shadow layer.
+    pub shadow: u64,
     /// Version of the decal layer.
     pub decal: u64,
     /// Version of the overlay layer.
     pub overlay: u64,
+    /// Version of the text layer.
+    pub text: u64,
+    /// Version of the pipeline accent color and dominant color sampler.
+    pub accent: u64,
+}
+
+/// Identifies one of the configurable layers of a [`LayerPipeline`].
+///
+/// Serialized with the same names as the [`CustomizationProfile`](crate::CustomizationProfile)
+/// fields, e.g. `"folderColorTarget"`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "jsonschema", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "tsify", derive(tsify_next::Tsify))]
+#[cfg_attr(feature = "tsify", tsify(into_wasm_abi, from_wasm_abi))]
+#[serde(rename_all = "camelCase")]
+pub enum LayerKind {
+    /// The color target layer.
+    FolderColorTarget,
+    /// The gradient tint layer.
+    GradientTint,
+    /// The standalone shadow layer.
+    Shadow,
+    /// The decal imprint layer.
+    Decal,
+    /// The SVG overlay layer.
+    Overlay,
+    /// The text label layer.
+    Text,
+}
+
+impl std::fmt::Display for LayerKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::FolderColorTarget => "color target",
+            Self::GradientTint => "gradient tint",
+            Self::Shadow => "shadow",
+            Self::Decal => "decal",
+            Self::Overlay => "overlay",
+            Self::Text => "text",
+        })
+    }
 }
 
 // ============================================================================
@@ -187,11 +490,21 @@ pub struct LayerVersions {
 /// Key for cached rendered images.
 ///
 /// Uses width, height, and scale (as integer bits) to identify unique image sizes.
+/// The dimensions are device pixels, so an @2x image never shares an entry
+/// with an @1x image of the same logical size. Images of different
+/// [`IconVariant`]s at the same size get separate entries.
+///
+/// [`RenderQuality::Preview`] renders are keyed by the size of the base
+/// they were shrunk from, plus the quality, so previews of different bases
+/// that shrink to the same size don't share entries. Outputs degraded to
+/// meet a time budget are never cached, so they need no part in the key.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct CacheKey {
     width: u32,
     height: u32,
     scale_bits: u32,
+    variant: IconVariant,
+    quality: RenderQuality,
 }
 
 impl CacheKey {
@@ -201,12 +514,22 @@ impl CacheKey {
             width,
             height,
             scale_bits: scale.to_bits(),
+            variant: IconVariant::Normal,
+            quality: RenderQuality::Final,
         }
     }
 
-    /// Creates a cache key from an icon image.
+    /// Creates a cache key from an icon image, including its variant.
     pub fn from_icon(icon: &IconImage) -> Self {
-        Self::new(icon.data.width(), icon.data.height(), icon.scale)
+        Self {
+            variant: icon.variant(),
+            ..Self::new(icon.data().width(), icon.data().height(), icon.scale)
+        }
+    }
+
+    /// Returns the key for renders of the same base at `quality`.
+    pub fn with_quality(self, quality: RenderQuality) -> Self {
+        Self { quality, ..self }
     }
 }
 
@@ -225,11 +548,23 @@ impl CacheKey {
 ///   directly and cache the full transformed result.
 /// - **Tile layers** (e.g., decal, overlay) render to a transparent canvas
 ///   of the same dimensions, which the pipeline composites on top.
+#[derive(Clone)]
 enum CachedOutput {
     /// Full transformed image (e.g., color_target mutates the base icon).
     Image(IconImage),
-    /// Transparent tile for compositing (e.g., decal, overlay).
-    Tile(RgbaImage),
+    /// Transparent tile for compositing (e.g., decal, overlay), with the
+    /// rectangle actually drawn into (`None` if nothing was drawn).
+    Tile(RgbaImage, Option<RectPx>),
+}
+
+impl CachedOutput {
+    /// Returns the approximate memory held, in bytes.
+    fn byte_size(&self) -> usize {
+        match self {
+            Self::Image(image) => image.data().as_raw().len(),
+            Self::Tile(tile, _) => tile.as_raw().len(),
+        }
+    }
 }
 
 // ============================================================================
@@ -248,6 +583,10 @@ enum CachedOutput {
 /// A layer is considered **active** when it has a configuration set
 /// AND is enabled. The `enabled` flag is for live editing (UI toggles)
 /// and is not serialized into profiles.
+///
+/// Cloning a layer copies its config, version and cache; the clone evolves
+/// independently of the original.
+#[derive(Clone)]
 pub struct Layer<C: LayerConfig> {
     config: Option<C>,
     enabled: bool,
@@ -308,21 +647,36 @@ impl<C: LayerConfig> Layer<C> {
     }
 
     /// Returns the current version number.
+    ///
+    /// Also changes when content the config references changes (see
+    /// [`LayerConfig::external_version`]).
     pub fn version(&self) -> u64 {
-        self.version
+        self.version.wrapping_add(self.external_version())
     }
 
-    /// Sets the configuration. Returns true if it changed.
-    ///
-    /// Clears the cache and increments version if the config differs.
-    pub fn set_config(&mut self, config: Option<C>) -> bool {
-        let differs = match (&self.config, &config) {
+    fn external_version(&self) -> u64 {
+        self.config.as_ref().map_or(0, C::external_version)
+    }
+
+    /// Returns true if [`set_config`](Self::set_config) with `config`
+    /// would change the layer.
+    pub fn would_change(&self, config: Option<&C>) -> bool {
+        match (&self.config, config) {
             (None, None) => false,
             (Some(_), None) | (None, Some(_)) => true,
             (Some(old), Some(new)) => old.differs_from(new),
-        };
+        }
+    }
 
-        if differs {
+    /// Sets the configuration, normalized by [`LayerConfig::normalize`].
+    /// Returns true if it changed.
+    ///
+    /// Clears the cache and increments version if the config differs.
+    pub fn set_config(&mut self, mut config: Option<C>) -> bool {
+        if let Some(config) = &mut config {
+            config.normalize();
+        }
+        if self.would_change(config.as_ref()) {
             self.config = config;
             self.version = self.version.wrapping_add(1);
             self.cache.clear();
@@ -341,9 +695,12 @@ impl<C: LayerConfig> Layer<C> {
     }
 
     /// Gets a cached output if valid for the given key and dependency version.
+    ///
+    /// Entries also go stale when referenced content changes.
     fn get_cached(&self, key: CacheKey, deps: DependencyVersion) -> Option<&CachedOutput> {
+        let deps = deps.0.wrapping_add(self.external_version());
         self.cache.get(&key).and_then(|(output, stored_dep)| {
-            if *stored_dep == deps.0 {
+            if *stored_dep == deps {
                 Some(output)
             } else {
                 None
@@ -353,7 +710,47 @@ impl<C: LayerConfig> Layer<C> {
 
     /// Stores a layer output in the cache with the current dependency version.
     fn store(&mut self, key: CacheKey, output: CachedOutput, deps: DependencyVersion) {
-        self.cache.insert(key, (output, deps.0));
+        self.cache.insert(key, (output, deps.0.wrapping_add(self.external_version())));
+    }
+
+    /// Returns the size of every cached output, by key.
+    pub(crate) fn cache_footprint(&self) -> impl Iterator<Item = (CacheKey, usize)> + '_ {
+        self.cache.iter().map(|(key, (output, _))| (*key, output.byte_size()))
+    }
+
+    /// Drops the cached output for `key`, if any.
+    pub(crate) fn evict(&mut self, key: CacheKey) {
+        self.cache.remove(&key);
+    }
+
+    /// Moves the cache entry for `key` into a copy of this layer, for
+    /// rendering that size on another thread.
+    #[cfg(feature = "rayon")]
+    pub(crate) fn take_shard(&mut self, key: CacheKey) -> Self {
+        Self {
+            config: self.config.clone(),
+            enabled: self.enabled,
+            version: self.version,
+            cache: self.cache.remove_entry(&key).into_iter().collect(),
+        }
+    }
+
+    /// Takes back the cache entries of a shard from
+    /// [`take_shard`](Self::take_shard).
+    #[cfg(feature = "rayon")]
+    pub(crate) fn merge_shard(&mut self, shard: Self) {
+        self.cache.extend(shard.cache);
+    }
+
+    /// Like [`take_shard`](Self::take_shard), but copies the entry,
+    /// leaving this layer's cache as it is.
+    pub(crate) fn copy_shard(&self, key: CacheKey) -> Self {
+        Self {
+            config: self.config.clone(),
+            enabled: self.enabled,
+            version: self.version,
+            cache: self.cache.get_key_value(&key).map(|(k, v)| (*k, v.clone())).into_iter().collect(),
+        }
     }
 }
 
@@ -369,11 +766,13 @@ impl<C: LayerConfig> Layer<C> {
 /// Unlike [`Layer<C>`], this has no configuration or enabled state.
 /// It purely caches the final rendered output and tracks a version
 /// for invalidation when any upstream layer changes.
+#[derive(Clone)]
 pub struct CompositeLayer {
     version: u64,
     cache: HashMap<CacheKey, (IconImage, u64)>,
 }
 
+#[allow(clippy::derivable_impls)]
 impl Default for CompositeLayer {
     fn default() -> Self {
         Self {
@@ -410,6 +809,99 @@ impl CompositeLayer {
     pub fn store(&mut self, key: CacheKey, image: IconImage, deps: DependencyVersion) {
         self.cache.insert(key, (image, deps.0));
     }
+
+    /// Like [`Layer::cache_footprint`].
+    pub(crate) fn cache_footprint(&self) -> impl Iterator<Item = (CacheKey, usize)> + '_ {
+        self.cache.iter().map(|(key, (image, _))| (*key, image.data().as_raw().len()))
+    }
+
+    /// Like [`Layer::evict`].
+    pub(crate) fn evict(&mut self, key: CacheKey) {
+        self.cache.remove(&key);
+    }
+
+    /// Like [`Layer::take_shard`].
+    #[cfg(feature = "rayon")]
+    pub(crate) fn take_shard(&mut self, key: CacheKey) -> Self {
+        Self {
+            version: self.version,
+            cache: self.cache.remove_entry(&key).into_iter().collect(),
+        }
+    }
+
+    /// Like [`Layer::merge_shard`].
+    #[cfg(feature = "rayon")]
+    pub(crate) fn merge_shard(&mut self, shard: Self) {
+        self.cache.extend(shard.cache);
+    }
+
+    /// Like [`Layer::copy_shard`].
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) fn copy_shard(&self, key: CacheKey) -> Self {
+        Self {
+            version: self.version,
+            cache: self.cache.get_key_value(&key).map(|(k, v)| (*k, v.clone())).into_iter().collect(),
+        }
+    }
+}
+
+// ============================================================================
+// Render Limits
+// ============================================================================
+
+/// Upper bounds on the images the pipeline will process.
+///
+/// Guards against absurd inputs (e.g. a 20000×20000 PNG from an untrusted
+/// frontend) that would otherwise trigger multi-gigabyte allocations.
+/// Exceeding either bound yields [`RenderError::ImageTooLarge`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RenderLimits {
+    /// Maximum width or height in pixels.
+    pub max_dimension: u32,
+    /// Maximum total pixel count (width × height).
+    pub max_pixels: u64,
+}
+
+impl RenderLimits {
+    /// Default maximum width or height: comfortably above the largest
+    /// platform icon size (1024px @2x).
+    pub const DEFAULT_MAX_DIMENSION: u32 = 4096;
+
+    /// Creates limits with the given bounds.
+    pub fn new(max_dimension: u32, max_pixels: u64) -> Self {
+        Self {
+            max_dimension,
+            max_pixels,
+        }
+    }
+
+    /// Limits that accept any image size.
+    pub fn unlimited() -> Self {
+        Self::new(u32::MAX, u64::MAX)
+    }
+
+    /// Returns an error if an image of the given size exceeds these limits.
+    pub fn check(&self, width: u32, height: u32) -> Result<(), RenderError> {
+        if width > self.max_dimension
+            || height > self.max_dimension
+            || width as u64 * height as u64 > self.max_pixels
+        {
+            return Err(RenderError::ImageTooLarge {
+                width,
+                height,
+                max_dimension: self.max_dimension,
+                max_pixels: self.max_pixels,
+            });
+        }
+        Ok(())
+    }
+}
+
+impl Default for RenderLimits {
+    fn default() -> Self {
+        let max = Self::DEFAULT_MAX_DIMENSION;
+        Self::new(max, max as u64 * max as u64)
+    }
 }
 
 // ============================================================================
@@ -428,45 +920,201 @@ impl CompositeLayer {
 ///     │
 ///     ▼
 /// ┌──────────────┐
-/// │ Color Target │ ◄── No dependencies (root layer)
+/// │ Color Target │ ◄── Depends on: Accent (only when following it)
 /// └──────┬───────┘
 ///      │
 ///      ▼
+/// ┌───────────────┐
+/// │ Gradient Tint │ ◄── Depends on: Color Target + Accent
+/// └──────┬────────┘
+///      │
+///      ▼
 /// ┌─────────┐
-/// │  Decal  │ ◄── Depends on: Color Target
+/// │ Shadow  │ ◄── Depends on: Color Target + Gradient Tint + Accent
 /// └────┬────┘
 ///      │
 ///      ▼
 /// ┌─────────┐
-/// │ Overlay │ ◄── No direct dependencies (applied last)
+/// │  Decal  │ ◄── Depends on: Color Target + Accent
 /// └────┬────┘
 ///      │
 ///      ▼
+/// ┌─────────┐
+/// │ Overlay │ ◄── Depends on: Decal (only when anchored to it),
+/// └────┬────┘     Shadow (only when clipped to the icon's alpha)
+///      │
+///      ▼
+/// ┌─────────┐
+/// │  Text   │ ◄── Depends on: Color Target + Accent (derived color),
+/// └────┬────┘     Decal + Overlay (auto position)
+///      │
+///      ▼
 /// ┌─────────────┐
-/// │  Composite  │ ◄── Depends on: Color Target + Decal + Overlay
+/// │  Composite  │ ◄── Depends on: all layers + Accent
 /// └─────────────┘
 /// ```
+///
+/// The accent color and dominant color sampler are pipeline-level inputs
+/// rather than layers; see [`set_accent_color`](Self::set_accent_color) and
+/// [`set_dominant_color_sampler`](Self::set_dominant_color_sampler). For a user-defined layer
+/// order, use [`DynamicPipeline`].
+///
+/// # Speculative Rendering
+///
+/// Cloning the pipeline copies every layer's config, version and cache.
+/// A UI can clone it, tweak the clone (e.g. preview a preset on hover),
+/// render, and drop it without disturbing the live pipeline. Unchanged
+/// layers in the clone still serve from the copied caches.
+///
+/// The clone starts with its own stamp cache and without the live
+/// pipeline's cancellation token or trace, so clearing, cancelling or
+/// tracing one never reaches the other.
 pub struct LayerPipeline {
     /// Color target layer (root - no dependencies).
     pub folder_color_target: Layer<FolderColorTargetConfig>,
 
+    /// Gradient tint layer (depends on color target).
+    pub gradient_tint: Layer<GradientTintConfig>,
+
+    /// Standalone shadow layer (depends on color target and gradient tint).
+    pub shadow: Layer<ShadowConfig>,
+
     /// Decal imprint layer (depends on color target).
     pub decal: Layer<DecalConfig>,
 
-    /// SVG overlay layer (no dependencies, applied last).
+    /// SVG overlay layer (depends on decal only when anchored to it).
     pub overlay: Layer<SvgOverlayConfig>,
 
+    /// Text label layer (applied last).
+    pub text: Layer<TextLayerConfig>,
+
     /// Composite cache (depends on all layers).
     pub composite: CompositeLayer,
+
+    /// Composites beneath the overlay, reused while only the overlay or
+    /// text changes.
+    underlay: underlay::UnderlayCache,
+
+    /// Pipeline-wide accent color, emitted as an [`AccentColor`] property.
+    accent: Option<AccentColor>,
+
+    /// Samples the base image's dominant color, emitted as a
+    /// [`DominantColor`] property.
+    dominant_sampler: Option<DominantColorSampler>,
+
+    /// Incremented whenever `accent` or `dominant_sampler` changes.
+    accent_version: u64,
+
+    /// Bounds checked against every base image before rendering.
+    limits: RenderLimits,
+
+    /// Recent render timings used by [`estimate_cost`](Self::estimate_cost).
+    metrics: cost::RenderMetrics,
+
+    /// Per-render time budget; see [`set_time_budget`](Self::set_time_budget).
+    time_budget: Option<std::time::Duration>,
+
+    /// Checked before each layer; see
+    /// [`set_cancellation_token`](Self::set_cancellation_token).
+    cancellation: Option<cancel::CancellationToken>,
+
+    /// The only layer rendered, if any; see [`solo`](Self::solo).
+    solo: Option<LayerKind>,
+
+    /// How tile layer positions snap to pixels, emitted as a
+    /// [`PlacementRounding`] property.
+    placement_rounding: PlacementRounding,
+
+    /// How [`composite_only`](Self::composite_only) lays out overlays
+    /// sharing a corner.
+    overlay_arrangement: OverlayArrangement,
+
+    /// Called with each warning a render raises; see
+    /// [`set_warning_handler`](Self::set_warning_handler).
+    warning_handler: Option<warnings::WarningHandler>,
+
+    /// Stamps shared with other pipelines rendering the same layers, set
+    /// by [`IconBatchRenderer`](crate::IconBatchRenderer).
+    pub(crate) shared_stamps: Option<stamps::SharedStamps>,
+
+    /// Stamps kept by [`composite_only`](Self::composite_only) when none
+    /// are shared.
+    pub(crate) composite_stamps: stamps::SharedStamps,
+
+    /// Byte budget for all caches; see
+    /// [`set_cache_limit`](Self::set_cache_limit).
+    cache_limit: Option<usize>,
+
+    /// When each size was last rendered, for evicting the least recently
+    /// used.
+    cache_usage: eviction::CacheUsage,
+
+    /// The trace being recorded; see [`start_trace`](Self::start_trace).
+    #[cfg(feature = "trace-export")]
+    trace: Option<trace::SharedTraceLog>,
 }
 
+#[allow(clippy::derivable_impls)]
 impl Default for LayerPipeline {
     fn default() -> Self {
         Self {
             folder_color_target: Layer::default(),
+            gradient_tint: Layer::default(),
+            shadow: Layer::default(),
             decal: Layer::default(),
             overlay: Layer::default(),
+            text: Layer::default(),
             composite: CompositeLayer::default(),
+            underlay: underlay::UnderlayCache::default(),
+            accent: None,
+            dominant_sampler: None,
+            accent_version: 0,
+            limits: RenderLimits::default(),
+            metrics: cost::RenderMetrics::default(),
+            time_budget: None,
+            cancellation: None,
+            solo: None,
+            placement_rounding: PlacementRounding::default(),
+            overlay_arrangement: OverlayArrangement::default(),
+            warning_handler: None,
+            shared_stamps: None,
+            composite_stamps: stamps::SharedStamps::default(),
+            cache_limit: None,
+            cache_usage: eviction::CacheUsage::default(),
+            #[cfg(feature = "trace-export")]
+            trace: None,
+        }
+    }
+}
+
+impl Clone for LayerPipeline {
+    fn clone(&self) -> Self {
+        Self {
+            folder_color_target: self.folder_color_target.clone(),
+            gradient_tint: self.gradient_tint.clone(),
+            shadow: self.shadow.clone(),
+            decal: self.decal.clone(),
+            overlay: self.overlay.clone(),
+            text: self.text.clone(),
+            composite: self.composite.clone(),
+            underlay: self.underlay.clone(),
+            accent: self.accent,
+            dominant_sampler: self.dominant_sampler,
+            accent_version: self.accent_version,
+            limits: self.limits,
+            metrics: self.metrics.clone(),
+            time_budget: self.time_budget,
+            cancellation: None,
+            solo: self.solo,
+            placement_rounding: self.placement_rounding,
+            overlay_arrangement: self.overlay_arrangement,
+            warning_handler: self.warning_handler.clone(),
+            shared_stamps: self.shared_stamps.clone(),
+            composite_stamps: stamps::SharedStamps::default(),
+            cache_limit: self.cache_limit,
+            cache_usage: self.cache_usage.clone(),
+            #[cfg(feature = "trace-export")]
+            trace: None,
         }
     }
 }
@@ -475,73 +1123,564 @@ impl LayerPipeline {
     /// Returns a snapshot of all layer versions.
     ///
     /// Used by layer `dependencies()` methods to compute cache invalidation.
+    ///
+    /// Layers muted by [`solo`](Self::solo) report their version inverted,
+    /// so nothing cached downstream of them while soloing is served after
+    /// [`unsolo`](Self::unsolo), and vice versa.
     pub fn layer_versions(&self) -> LayerVersions {
+        let version = |kind, version: u64| if self.is_muted(kind) { !version } else { version };
         LayerVersions {
-            folder_color_target: self.folder_color_target.version(),
-            decal: self.decal.version(),
-            overlay: self.overlay.version(),
+            folder_color_target: version(LayerKind::FolderColorTarget, self.folder_color_target.version()),
+            gradient_tint: version(LayerKind::GradientTint, self.gradient_tint.version()),
+            shadow: version(LayerKind::Shadow, self.shadow.version()),
+            decal: version(LayerKind::Decal, self.decal.version()),
+            overlay: version(LayerKind::Overlay, self.overlay.version()),
+            text: version(LayerKind::Text, self.text.version()),
+            accent: self.accent_version,
+        }
+    }
+
+    /// Renders only `layer` (over the base) until [`unsolo`](Self::unsolo),
+    /// for tweaking one effect in isolation.
+    ///
+    /// Enabled flags and configs are left alone, so unsoloing restores the
+    /// full render exactly. Soloing a disabled or unconfigured layer
+    /// renders the bare base.
+    pub fn solo(&mut self, layer: LayerKind) {
+        self.solo = Some(layer);
+    }
+
+    /// Ends [`solo`](Self::solo), rendering every active layer again.
+    pub fn unsolo(&mut self) {
+        self.solo = None;
+    }
+
+    /// Returns the soloed layer, if any.
+    pub fn soloed(&self) -> Option<LayerKind> {
+        self.solo
+    }
+
+    /// Returns true if `layer` is active and not muted by
+    /// [`solo`](Self::solo), i.e. it takes part in the next render.
+    pub fn renders(&self, layer: LayerKind) -> bool {
+        let active = match layer {
+            LayerKind::FolderColorTarget => self.folder_color_target.is_active(),
+            LayerKind::GradientTint => self.gradient_tint.is_active(),
+            LayerKind::Shadow => self.shadow.is_active(),
+            LayerKind::Decal => self.decal.is_active(),
+            LayerKind::Overlay => self.overlay.is_active(),
+            LayerKind::Text => self.text.is_active(),
+        };
+        active && !self.is_muted(layer)
+    }
+
+    /// Returns true if another layer is soloed.
+    fn is_muted(&self, layer: LayerKind) -> bool {
+        self.solo.is_some_and(|solo| solo != layer)
+    }
+
+    /// Returns the render limits.
+    pub fn limits(&self) -> RenderLimits {
+        self.limits
+    }
+
+    /// Sets the render limits checked before each render.
+    pub fn set_limits(&mut self, limits: RenderLimits) {
+        self.limits = limits;
+    }
+
+    /// Returns the pipeline accent color, if any.
+    pub fn accent_color(&self) -> Option<AccentColor> {
+        self.accent
+    }
+
+    /// Sets the pipeline accent color. Returns true if it changed.
+    ///
+    /// Layers that consume [`AccentColor`] re-render on their next use;
+    /// layers that ignore it keep their caches.
+    pub fn set_accent_color(&mut self, accent: Option<AccentColor>) -> bool {
+        if self.accent != accent {
+            self.accent = accent;
+            self.accent_version = self.accent_version.wrapping_add(1);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Returns the dominant color sampler, if any.
+    pub fn dominant_color_sampler(&self) -> Option<DominantColorSampler> {
+        self.dominant_sampler
+    }
+
+    /// Samples each base image with `sampler`, emitting the result as a
+    /// [`DominantColor`] (and its [`DerivedPalette`]) before any layer
+    /// runs. Returns true if it changed.
+    ///
+    /// Layers that derive colors from the image then follow the folder's
+    /// own color when no color target is set, instead of falling back to
+    /// the accent or surface color. They re-render on their next use.
+    ///
+    /// ```
+    /// use folco_renderer::analysis::{DominantColorSampler, SamplingStrategy};
+    /// use folco_renderer::{DecalConfig, IconImage, LayerPipeline, SurfaceColor};
+    /// use image::{Rgba, RgbaImage};
+    ///
+    /// let mut pipeline = LayerPipeline::default();
+    /// let square = r#"<svg xmlns="http://www.w3.org/2000/svg" width="10" height="10"><rect width="10" height="10"/></svg>"#;
+    /// pipeline.decal.set_config(Some(DecalConfig::new(square, 0.5)));
+    /// assert!(pipeline.set_dominant_color_sampler(Some(DominantColorSampler::new(SamplingStrategy::Median))));
+    ///
+    /// let base = IconImage::new_full_content(RgbaImage::from_pixel(32, 32, Rgba([66, 165, 245, 255])), 1.0);
+    /// pipeline.render(&base, &SurfaceColor::new(255, 217, 112)).unwrap();
+    /// ```
+    pub fn set_dominant_color_sampler(&mut self, sampler: Option<DominantColorSampler>) -> bool {
+        if self.dominant_sampler != sampler {
+            self.dominant_sampler = sampler;
+            self.accent_version = self.accent_version.wrapping_add(1);
+            true
+        } else {
+            false
         }
     }
 
     /// Invalidates all caches.
     pub fn invalidate_all(&mut self) {
         self.folder_color_target.invalidate();
+        self.gradient_tint.invalidate();
+        self.shadow.invalidate();
         self.decal.invalidate();
         self.overlay.invalidate();
+        self.text.invalidate();
         self.composite.invalidate();
+        self.underlay.clear();
+        self.composite_stamps.0.clear();
     }
 
     /// Returns the combined dependency version for the composite layer.
     fn composite_dependencies(&self) -> DependencyVersion {
+        let versions = self.layer_versions();
         DependencyVersion::combine(&[
-            self.folder_color_target.version(),
-            self.decal.version(),
-            self.overlay.version(),
+            versions.folder_color_target,
+            versions.gradient_tint,
+            versions.shadow,
+            versions.decal,
+            versions.overlay,
+            versions.text,
+            versions.accent,
         ])
     }
 
+    /// Splits off a pipeline that renders images of size `key` with this
+    /// one's settings, moving that size's cache entries into it.
+    ///
+    /// Shards of different sizes can render in parallel without sharing
+    /// any cache; [`merge_shard`](Self::merge_shard) brings the entries
+    /// back. Shards must not be reconfigured.
+    #[cfg(feature = "rayon")]
+    pub(crate) fn take_shard(&mut self, key: CacheKey) -> Self {
+        Self {
+            folder_color_target: self.folder_color_target.take_shard(key),
+            gradient_tint: self.gradient_tint.take_shard(key),
+            shadow: self.shadow.take_shard(key),
+            decal: self.decal.take_shard(key),
+            overlay: self.overlay.take_shard(key),
+            text: self.text.take_shard(key),
+            composite: self.composite.take_shard(key),
+            underlay: self.underlay.take_shard(key),
+            accent: self.accent,
+            dominant_sampler: self.dominant_sampler,
+            accent_version: self.accent_version,
+            limits: self.limits,
+            metrics: self.metrics.clone(),
+            time_budget: self.time_budget,
+            cancellation: self.cancellation.clone(),
+            solo: self.solo,
+            placement_rounding: self.placement_rounding,
+            overlay_arrangement: self.overlay_arrangement,
+            warning_handler: self.warning_handler.clone(),
+            shared_stamps: self.shared_stamps.clone(),
+            composite_stamps: self.composite_stamps.clone(),
+            cache_limit: self.cache_limit,
+            cache_usage: self.cache_usage.clone(),
+            #[cfg(feature = "trace-export")]
+            trace: self.trace.clone(),
+        }
+    }
+
+    /// Takes back the cache entries of a shard from
+    /// [`take_shard`](Self::take_shard).
+    ///
+    /// The shard's render timings are dropped: measured while competing
+    /// for cores, they would skew the cost estimates.
+    #[cfg(feature = "rayon")]
+    pub(crate) fn merge_shard(&mut self, shard: Self) {
+        self.folder_color_target.merge_shard(shard.folder_color_target);
+        self.gradient_tint.merge_shard(shard.gradient_tint);
+        self.shadow.merge_shard(shard.shadow);
+        self.decal.merge_shard(shard.decal);
+        self.overlay.merge_shard(shard.overlay);
+        self.text.merge_shard(shard.text);
+        self.composite.merge_shard(shard.composite);
+        self.underlay.merge_shard(shard.underlay);
+        self.cache_usage.merge(shard.cache_usage);
+        self.enforce_cache_limit();
+    }
+
+    /// Like [`take_shard`](Self::take_shard), but copies that size's
+    /// cache entries, leaving this pipeline as it is.
+    ///
+    /// The copy renders on another thread without the cost of cloning
+    /// every size's caches.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) fn copy_shard(&self, key: CacheKey) -> Self {
+        Self {
+            folder_color_target: self.folder_color_target.copy_shard(key),
+            gradient_tint: self.gradient_tint.copy_shard(key),
+            shadow: self.shadow.copy_shard(key),
+            decal: self.decal.copy_shard(key),
+            overlay: self.overlay.copy_shard(key),
+            text: self.text.copy_shard(key),
+            composite: self.composite.copy_shard(key),
+            underlay: self.underlay.copy_shard(key),
+            accent: self.accent,
+            dominant_sampler: self.dominant_sampler,
+            accent_version: self.accent_version,
+            limits: self.limits,
+            metrics: self.metrics.clone(),
+            time_budget: self.time_budget,
+            cancellation: self.cancellation.clone(),
+            solo: self.solo,
+            placement_rounding: self.placement_rounding,
+            overlay_arrangement: self.overlay_arrangement,
+            warning_handler: self.warning_handler.clone(),
+            shared_stamps: self.shared_stamps.clone(),
+            composite_stamps: self.composite_stamps.clone(),
+            cache_limit: self.cache_limit,
+            cache_usage: self.cache_usage.clone(),
+            #[cfg(feature = "trace-export")]
+            trace: self.trace.clone(),
+        }
+    }
+
+    /// Returns true if no layer [renders](Self::renders), so rendering
+    /// would return the base image unchanged.
+    ///
+    /// Hosts can check this to skip rendering (and copying) altogether.
+    pub fn is_identity(&self) -> bool {
+        ![
+            LayerKind::FolderColorTarget,
+            LayerKind::GradientTint,
+            LayerKind::Shadow,
+            LayerKind::Decal,
+            LayerKind::Overlay,
+            LayerKind::Text,
+        ]
+        .into_iter()
+        .any(|layer| self.renders(layer))
+    }
+
     /// Renders an icon through the full layer pipeline.
     ///
-    /// This is the main entry point for rendering. It:
+    /// When the pipeline [is the identity](Self::is_identity), the base is
+    /// returned as is and nothing is cached; see
+    /// [`render_cow`](Self::render_cow) to avoid the copy too.
+    ///
+    /// Otherwise this is the main entry point for rendering. It:
     /// 1. Checks the composite cache first
-    /// 2. Creates a render context with the base image and surface color
-    /// 3. Applies the color target (mutates the base image directly)
-    /// 4. Applies tile layers (decal, overlay) and composites their tiles
+    /// 2. Creates a render context with the base image, surface and accent colors
+    /// 3. Applies the color target, gradient tint and shadow (mutate the image directly)
+    /// 4. Applies tile layers (decal, overlay, text) and composites their tiles
     /// 5. Caches and returns the final result
     ///
+    /// While only the overlay or text changed since the last render of a
+    /// size (e.g. dragging a badge), steps 3 and 4 start from the stored
+    /// composite beneath the overlay, and only the pixels the overlay and
+    /// text cover are re-composited.
+    ///
     /// # Errors
     ///
-    /// Returns an error if any layer fails to render.
+    /// Returns [`RenderError::ImageTooLarge`] if the base image exceeds the
+    /// pipeline's [`RenderLimits`], or an error if any layer fails to render.
     pub fn render(&mut self, base: &IconImage, surface_color: &SurfaceColor) -> Result<IconImage, RenderError> {
-        let key = CacheKey::from_icon(base);
+        self.render_with_report(base, surface_color).map(|(image, _)| image)
+    }
+
+    /// Like [`render`](Self::render), borrowing the base instead of copying
+    /// it when no layer is active.
+    ///
+    /// # Errors
+    ///
+    /// Same as [`render`](Self::render).
+    pub fn render_cow<'a>(
+        &mut self,
+        base: &'a IconImage,
+        surface_color: &SurfaceColor,
+    ) -> Result<Cow<'a, IconImage>, RenderError> {
+        if self.is_identity() {
+            self.limits.check(base.data().width(), base.data().height())?;
+            return Ok(Cow::Borrowed(base));
+        }
+        self.render(base, surface_color).map(Cow::Owned)
+    }
+
+    /// Like [`render`](Self::render), also reporting whether the result
+    /// came from the cache, which layers degraded to meet the
+    /// [time budget](Self::set_time_budget) and any [`RenderWarning`]s.
+    ///
+    /// A degraded result is not cached.
+    ///
+    /// # Errors
+    ///
+    /// Same as [`render`](Self::render).
+    pub fn render_with_report(
+        &mut self,
+        base: &IconImage,
+        surface_color: &SurfaceColor,
+    ) -> Result<(IconImage, RenderReport), RenderError> {
+        self.render_keyed(base, surface_color, CacheKey::from_icon(base))
+    }
+
+    /// Like [`render_with_report`](Self::render_with_report), caching under
+    /// `key` instead of the base's own key.
+    pub(crate) fn render_keyed(
+        &mut self,
+        base: &IconImage,
+        surface_color: &SurfaceColor,
+        key: CacheKey,
+    ) -> Result<(IconImage, RenderReport), RenderError> {
+        self.limits.check(base.data().width(), base.data().height())?;
+        if self.is_identity() {
+            // Caching a copy of the base would only cost memory
+            let report = RenderReport {
+                effective_size: Some(EffectiveSize::of(base)),
+                ..RenderReport::default()
+            };
+            return Ok((base.clone(), report));
+        }
+
         let composite_deps = self.composite_dependencies();
+        self.cache_usage.touch(key);
 
         // Check composite cache first
         if let Some(cached) = self.composite.get_cached(key, composite_deps) {
-            return Ok(cached.clone());
+            let report = RenderReport {
+                cached: true,
+                effective_size: Some(EffectiveSize::of(base)),
+                ..RenderReport::default()
+            };
+            return Ok((cached.clone(), report));
         }
 
-        // Create render context with surface color available for layers
+        #[cfg(not(target_arch = "wasm32"))]
+        let timing = (std::time::Instant::now(), self.estimate_cost(base));
+        #[cfg(not(target_arch = "wasm32"))]
+        let deadline = budget::Deadline::start(self.time_budget, Some(&timing.1));
+        #[cfg(target_arch = "wasm32")]
+        let deadline = budget::Deadline::start(
+            self.time_budget,
+            self.time_budget.map(|_| self.estimate_cost(base)).as_ref(),
+        );
+
+        let mut ctx = self.context(base, surface_color);
+        self.traced("render", &"render", key, |this| this.apply_layers(&mut ctx, key, &deadline))?;
+
+        self.report_warnings(&ctx);
+        let report = RenderReport {
+            cached: false,
+            degraded_layers: ctx.get::<budget::DegradedLayers>().map(|d| d.0.clone()).unwrap_or_default(),
+            warnings: ctx.warnings().to_vec(),
+            effective_size: Some(EffectiveSize::of(&ctx.image)),
+        };
+        if report.is_degraded() {
+            // Degraded timings would skew the cost estimates
+            self.enforce_cache_limit();
+            return Ok((ctx.image, report));
+        }
+
+        // Cache the final result
+        self.composite.store(key, ctx.image.clone(), composite_deps);
+        self.enforce_cache_limit();
+
+        #[cfg(not(target_arch = "wasm32"))]
+        self.record_render_time(&timing.1, timing.0.elapsed());
+
+        Ok((ctx.image, report))
+    }
+
+    /// Runs `f`. With the `trace-export` feature, it is also timed while a
+    /// trace is recording.
+    #[cfg(not(feature = "trace-export"))]
+    fn traced<T>(&mut self, _: &'static str, _: &dyn std::fmt::Display, _: CacheKey, f: impl FnOnce(&mut Self) -> T) -> T {
+        f(self)
+    }
+
+    /// Creates a render context with surface, accent and sampled dominant
+    /// colors and the placement rounding available for layers.
+    fn context(&self, base: &IconImage, surface_color: &SurfaceColor) -> RenderContext {
         let mut ctx = RenderContext::new(base.clone());
         ctx.set(*surface_color);
+        if let Some(accent) = self.accent {
+            ctx.set(accent);
+        }
+        if let Some((r, g, b)) = self.dominant_sampler.and_then(|sampler| sampler.sample(base)) {
+            ctx.set(DominantColor::new(r, g, b, 255));
+            ctx.set(DerivedPalette::from_rgb(r, g, b));
+        }
+        ctx.set(self.placement_rounding);
+        if let Some(stamps) = &self.shared_stamps {
+            ctx.set(stamps.clone());
+        }
+        ctx
+    }
 
-        // Apply layers in order (each layer computes its own dependencies)
+    /// Renders the unmuted layers beneath the decal and the decal tile for
+    /// `base`, returning the backdrop and the tile.
+    ///
+    /// Used to measure the decal against what it sits on. Layers serve
+    /// from cache but nothing is stored, so the caches stay as they are.
+    /// Returns `None` if the decal does not render.
+    pub(crate) fn decal_backdrop(
+        &self,
+        base: &IconImage,
+        surface_color: &SurfaceColor,
+    ) -> Result<Option<(RgbaImage, RgbaImage)>, RenderError> {
+        if !self.renders(LayerKind::Decal) {
+            return Ok(None);
+        }
+        let key = CacheKey::from_icon(base);
         let versions = self.layer_versions();
+        let mut ctx = self.context(base, surface_color);
+        if self.renders(LayerKind::FolderColorTarget) {
+            self.folder_color_target.copy_shard(key).apply(&mut ctx, key, &versions)?;
+        }
+        if self.renders(LayerKind::GradientTint) {
+            self.gradient_tint.copy_shard(key).apply(&mut ctx, key, &versions)?;
+        }
+        if self.renders(LayerKind::Shadow) {
+            self.shadow.copy_shard(key).apply(&mut ctx, key, &versions)?;
+        }
+        Thumbnail::emit(&mut ctx);
+        let tile = self.decal.copy_shard(key).render_tile(&mut ctx, key, &versions)?;
+        Ok(tile.map(|tile| (ctx.image.data().clone(), tile)))
+    }
 
-        // Color target transforms ctx.image directly (returns None)
-        self.folder_color_target.apply(&mut ctx, key, &versions)?;
+    /// Applies every unmuted layer to `ctx` in order, returning where the
+    /// tile layers drew.
+    fn apply_layers(
+        &mut self,
+        ctx: &mut RenderContext,
+        key: CacheKey,
+        deadline: &budget::Deadline,
+    ) -> Result<LayerLayout, RenderError> {
+        // Each layer computes its own dependencies
+        let versions = self.layer_versions();
+        let mut layout = LayerLayout::new(&ctx.image);
+        // Tile layers push at most one occupied region each, when they draw
+        let regions = |ctx: &RenderContext| ctx.get::<OccupiedRegions>().map_or(0, |o| o.regions.len());
+        let drawn = |ctx: &RenderContext, before: usize| {
+            ctx.get::<OccupiedRegions>().and_then(|o| o.regions.get(before).copied())
+        };
 
-        // Tile layers produce transparent canvases — composite them over ctx.image
-        if let Some(tile) = self.decal.render_tile(&mut ctx, key, &versions)? {
-            svg::composite_over(&mut ctx.image.data, &tile, 0, 0);
+        // While the layers beneath the overlay are unchanged, start from
+        // their stored composite
+        let restored = self.restore_underlay(ctx, key, &versions);
+        if restored {
+            layout.decal = ctx.get::<DecalPlacement>().map(|placement| placement.bounds);
+        } else {
+            // Color target transforms ctx.image directly (returns None)
+            self.check_cancelled()?;
+            if self.renders(LayerKind::FolderColorTarget) {
+                self.traced("layer", &LayerKind::FolderColorTarget, key, |this| this.folder_color_target.apply(ctx, key, &versions))?;
+            }
+            self.check_cancelled()?;
+            if self.renders(LayerKind::GradientTint) {
+                self.traced("layer", &LayerKind::GradientTint, key, |this| this.gradient_tint.apply(ctx, key, &versions))?;
+            }
+            self.check_cancelled()?;
+            deadline.check(ctx);
+            if self.renders(LayerKind::Shadow) {
+                self.traced("layer", &LayerKind::Shadow, key, |this| this.shadow.apply(ctx, key, &versions))?;
+            }
+            Thumbnail::emit(ctx);
+
+            // Tile layers produce transparent canvases — composite them over ctx.image
+            self.check_cancelled()?;
+            deadline.check(ctx);
+            let before = regions(ctx);
+            if self.renders(LayerKind::Decal)
+                && let Some(tile) = self.traced("layer", &LayerKind::Decal, key, |this| this.decal.render_tile(ctx, key, &versions))?
+            {
+                svg::composite_blend(ctx.image.data_mut(), &tile, 0, 0, self.decal.blend_mode());
+                layout.decal = drawn(ctx, before);
+            }
+            self.store_underlay(ctx, key, &versions);
         }
-        if let Some(tile) = self.overlay.render_tile(&mut ctx, key, &versions)? {
-            svg::composite_over(&mut ctx.image.data, &tile, 0, 0);
+
+        // Over a restored underlay, only the pixels a tile covers change
+        let composite = |image: &mut IconImage, tile: &RgbaImage, mode| match restored {
+            true => underlay::composite_visible(image.data_mut(), tile, mode),
+            false => svg::composite_blend(image.data_mut(), tile, 0, 0, mode),
+        };
+        self.check_cancelled()?;
+        deadline.check(ctx);
+        let before = regions(ctx);
+        if self.renders(LayerKind::Overlay)
+            && let Some(tile) = self.traced("layer", &LayerKind::Overlay, key, |this| this.overlay.render_tile(ctx, key, &versions))?
+        {
+            composite(&mut ctx.image, &tile, self.overlay.blend_mode());
+            layout.overlay = drawn(ctx, before);
+        }
+        self.check_cancelled()?;
+        deadline.check(ctx);
+        let before = regions(ctx);
+        if self.renders(LayerKind::Text)
+            && let Some(tile) = self.traced("layer", &LayerKind::Text, key, |this| this.text.render_tile(ctx, key, &versions))?
+        {
+            composite(&mut ctx.image, &tile, BlendMode::Normal);
+            layout.text = drawn(ctx, before);
         }
 
-        // Cache the final result
-        self.composite.store(key, ctx.image.clone(), composite_deps);
+        Ok(layout)
+    }
+}
 
-        Ok(ctx.image)
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::icon::SizePx;
+    use image::Rgba;
+
+    #[test]
+    fn pipeline_emits_one_thumbnail_beneath_the_tiles() {
+        let surface = SurfaceColor::new(255, 217, 112);
+        let base = IconImage::new_full_content(RgbaImage::from_pixel(128, 64, Rgba([255, 217, 112, 255])), 2.0);
+        let key = CacheKey::from_icon(&base);
+        let square = r#"<svg xmlns="http://www.w3.org/2000/svg" width="10" height="10"><rect width="10" height="10"/></svg>"#;
+        let mut pipeline = LayerPipeline::default();
+        let thumbnail = |pipeline: &mut LayerPipeline| {
+            let mut ctx = pipeline.context(&base, &surface);
+            pipeline.apply_layers(&mut ctx, key, &budget::Deadline::start(None, None)).unwrap();
+            ctx.get::<Thumbnail>().expect("thumbnail should be emitted").clone()
+        };
+
+        // Even with no pixel layer active
+        let plain = thumbnail(&mut pipeline);
+        assert_eq!(plain, Thumbnail::of(&base));
+        assert_eq!((plain.image.dimensions(), plain.source_size), ((32, 16), SizePx::new(128, 64)));
+
+        pipeline.folder_color_target.set_config(Some(FolderColorTargetConfig::new(200, 40, 40)));
+        pipeline.decal.set_config(Some(DecalConfig::new(square, 0.5)));
+        // Both the fresh and the cached path show the recolored folder
+        // without the decal drawn over it
+        for _ in 0..2 {
+            let recolored = thumbnail(&mut pipeline);
+            assert_ne!(recolored, plain);
+            assert_eq!(recolored.image.get_pixel(16, 8), recolored.image.get_pixel(1, 1));
+        }
     }
 }
@@ -1,22 +1,48 @@
 //! SVG overlay layer — configuration and rendering.
 
-use super::svg::{composite_over, render_source, SvgSource};
-use super::{CacheKey, CachedOutput, DependencyVersion, Layer, LayerConfig, LayerVersions, RenderContext};
+use super::budget::DegradedLayers;
+use super::edges::feather_edges;
+use super::folder_color_target::HslShift;
+use super::shadow::{drop_shadow, ShadowConfig};
+use super::placement::PlacementRounding;
+use super::stamps::{composite_keyed_stamp, StampKey};
+use super::warnings::RenderWarning;
+use super::svg::{
+    composite_blend, fade, full_opacity, is_full_opacity, stamp_svg_with_color_at, stamp_svg_with_current_color_at, uses_current_color, BlendMode,
+    RasterQuality, SvgSource,
+};
+use super::{finite_or, AccentColor, CacheKey, CachedOutput, DecalPlacement, DependencyVersion, Layer, LayerConfig, LayerKind, LayerPipeline, LayerVersions, OccupiedRegions, RenderContext};
 use crate::error::RenderError;
-use image::RgbaImage;
+use crate::icon::{IconImage, RectPx, SurfaceColor};
+use image::{Rgba, RgbaImage};
 
 // ============================================================================
 // OverlayPosition
 // ============================================================================
 
 /// Position for SVG overlay placement.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+///
+/// Defaults to the bottom-right corner, the conventional badge spot.
+///
+/// # Example
+///
+/// ```
+/// use folco_renderer::OverlayPosition;
+///
+/// // A third of the way across, at the bottom, nudged 2 logical pixels up
+/// let position = OverlayPosition::Custom { x: 1.0 / 3.0, y: 1.0, dx: 0.0, dy: -2.0 };
+/// let json = serde_json::to_string(&position).unwrap();
+/// assert_eq!(json, r#"{"custom":{"x":0.33333334,"y":1.0,"dy":-2.0}}"#);
+/// assert_eq!(OverlayPosition::custom(0.5, 0.5), serde_json::from_str(r#"{"custom":{"x":0.5,"y":0.5}}"#).unwrap());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Default, serde::Serialize, serde::Deserialize)]
 #[cfg_attr(feature = "jsonschema", derive(schemars::JsonSchema))]
 #[serde(rename_all = "kebab-case")]
 pub enum OverlayPosition {
     /// Bottom-left corner of content bounds.
     BottomLeft,
     /// Bottom-right corner of content bounds.
+    #[default]
     BottomRight,
     /// Top-left corner of content bounds.
     TopLeft,
@@ -24,6 +50,300 @@ pub enum OverlayPosition {
     TopRight,
     /// Centered within content bounds.
     Center,
+    /// Whichever corner overlaps least with regions already painted by
+    /// earlier layers (see [`OccupiedRegions`]). Ties prefer bottom-right,
+    /// then bottom-left, top-right and top-left.
+    Auto,
+    /// A point given as fractions (0.0-1.0) of the anchor rectangle: 0.0
+    /// aligns the overlay's left (top) edge with the anchor's, 1.0 its right
+    /// (bottom) edge, so `(0.5, 0.5)` is [`Center`](Self::Center) and
+    /// `(1.0, 1.0)` is [`BottomRight`](Self::BottomRight). `dx` and `dy`
+    /// then shift the overlay by logical pixels.
+    Custom {
+        /// Horizontal fraction of the anchor.
+        x: f32,
+        /// Vertical fraction of the anchor.
+        y: f32,
+        /// Horizontal offset in logical pixels.
+        #[serde(default, skip_serializing_if = "is_zero")]
+        dx: f32,
+        /// Vertical offset in logical pixels.
+        #[serde(default, skip_serializing_if = "is_zero")]
+        dy: f32,
+    },
+}
+
+pub(super) fn is_zero(value: &f32) -> bool {
+    *value == 0.0
+}
+
+impl OverlayPosition {
+    /// Creates a [`Custom`](Self::Custom) position without pixel offsets.
+    /// The fractions are clamped to 0.0-1.0.
+    pub fn custom(x: f32, y: f32) -> Self {
+        Self::Custom {
+            x: finite_or(x, 1.0).clamp(0.0, 1.0),
+            y: finite_or(y, 1.0).clamp(0.0, 1.0),
+            dx: 0.0,
+            dy: 0.0,
+        }
+    }
+}
+
+/// The rectangle an [`OverlayPosition`] is resolved against.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "jsonschema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "kebab-case")]
+pub enum OverlayAnchor {
+    /// The icon's content bounds.
+    #[default]
+    Content,
+    /// The rectangle the decal was drawn into, e.g. to badge the
+    /// bottom-right of the glyph. Falls back to the content bounds when
+    /// no decal is visible.
+    Decal,
+}
+
+/// Where overlay pixels may land.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "jsonschema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "kebab-case")]
+pub enum OverlayClip {
+    /// Anywhere on the canvas, including fully transparent margins.
+    #[default]
+    None,
+    /// Only inside the icon's content bounds.
+    ContentBounds,
+    /// Only where the icon painted so far (base plus decal) is opaque; the
+    /// overlay's alpha is multiplied by the icon's.
+    BaseAlpha,
+    /// Like `BaseAlpha`, with the icon's silhouette first grown by the
+    /// given number of pixels in every direction, so a badge can overhang
+    /// the edge slightly.
+    BaseAlphaExpanded(u32),
+}
+
+/// What an overlay does when its scale gives fewer pixels than its
+/// [`min_pixel_size`](SvgOverlayConfig::min_pixel_size).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "jsonschema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "kebab-case")]
+pub enum MinSizePolicy {
+    /// Draw the overlay at the minimum size, or the content size if
+    /// smaller.
+    #[default]
+    Clamp,
+    /// Leave the overlay out.
+    Omit,
+}
+
+/// How overlays that share a corner are laid out, in a
+/// [`DynamicPipeline`](super::DynamicPipeline) with several overlay layers
+/// or by [`LayerPipeline::composite_only`].
+///
+/// Overlays share a corner when they have the same corner
+/// [`OverlayPosition`] and [`OverlayAnchor`]. The first is drawn in the
+/// corner and each later one moves along an edge, past the ones before
+/// it. Other positions are left alone; [`OverlayPosition::Auto`] already
+/// avoids earlier overlays.
+///
+/// ```
+/// use folco_renderer::OverlayArrangement;
+///
+/// let json = serde_json::to_string(&OverlayArrangement::Spread { spacing: 2.0 }).unwrap();
+/// assert_eq!(json, r#"{"spread":{"spacing":2.0}}"#);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Default, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "jsonschema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "kebab-case")]
+pub enum OverlayArrangement {
+    /// Every overlay is drawn at its own position, on top of earlier ones.
+    #[default]
+    Overlap,
+    /// Overlays stack vertically, away from the corner along the left or
+    /// right edge.
+    Stack {
+        /// Gap between overlays in logical pixels.
+        #[serde(default, skip_serializing_if = "is_zero")]
+        spacing: f32,
+    },
+    /// Overlays spread horizontally, away from the corner along the top or
+    /// bottom edge.
+    Spread {
+        /// Gap between overlays in logical pixels.
+        #[serde(default, skip_serializing_if = "is_zero")]
+        spacing: f32,
+    },
+}
+
+impl OverlayArrangement {
+    /// Returns `position` moved `offset` logical pixels along the edge,
+    /// away from its corner, or `None` if it doesn't move.
+    fn shift(self, position: OverlayPosition, offset: f32) -> Option<OverlayPosition> {
+        if offset == 0.0 {
+            return None;
+        }
+        let (x, y) = match position {
+            OverlayPosition::TopLeft => (0.0, 0.0),
+            OverlayPosition::TopRight => (1.0, 0.0),
+            OverlayPosition::BottomLeft => (0.0, 1.0),
+            OverlayPosition::BottomRight => (1.0, 1.0),
+            _ => return None,
+        };
+        // Toward the opposite corner
+        let inward = |fraction: f32| if fraction == 0.0 { offset } else { -offset };
+        match self {
+            Self::Overlap => None,
+            Self::Stack { .. } => Some(OverlayPosition::Custom { x, y, dx: 0.0, dy: inward(y) }),
+            Self::Spread { .. } => Some(OverlayPosition::Custom { x, y, dx: inward(x), dy: 0.0 }),
+        }
+    }
+
+    /// Returns how far, in logical pixels, an overlay drawn into `placement`
+    /// moves the next one along, or `None` for [`Overlap`](Self::Overlap).
+    fn advance(self, placement: &RectPx, scale: f32) -> Option<f32> {
+        let (extent, spacing) = match self {
+            Self::Overlap => return None,
+            Self::Stack { spacing } => (placement.height, spacing),
+            Self::Spread { spacing } => (placement.width, spacing),
+        };
+        // Deserialized spacings bypass any clamp
+        Some(extent as f32 / scale + finite_or(spacing, 0.0).max(0.0))
+    }
+}
+
+/// Context property laying out the overlays of one render according to an
+/// [`OverlayArrangement`].
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ArrangedCorners {
+    arrangement: OverlayArrangement,
+    /// Logical pixels taken along the edge so far, by corner and anchor.
+    taken: Vec<(OverlayPosition, OverlayAnchor, f32)>,
+}
+
+impl ArrangedCorners {
+    pub(crate) fn new(arrangement: OverlayArrangement) -> Self {
+        Self {
+            arrangement,
+            taken: Vec::new(),
+        }
+    }
+
+    fn taken(&self, config: &SvgOverlayConfig) -> Option<usize> {
+        self.taken
+            .iter()
+            .position(|(position, anchor, _)| *position == config.position && *anchor == config.anchor)
+    }
+
+    /// Returns where `config` is drawn in `ctx`: its position, moved past
+    /// the earlier overlays in its corner.
+    fn position(ctx: &RenderContext, config: &SvgOverlayConfig) -> OverlayPosition {
+        ctx.get::<Self>()
+            .and_then(|corners| {
+                let offset = corners.taken(config).map_or(0.0, |i| corners.taken[i].2);
+                corners.arrangement.shift(config.position, offset)
+            })
+            .unwrap_or(config.position)
+    }
+
+    /// Records that `config` was drawn into `bounds`, moving the next
+    /// overlay in its corner along.
+    fn record(ctx: &mut RenderContext, config: &SvgOverlayConfig, bounds: &RectPx) {
+        let scale = ctx.image.scale;
+        let Some(mut corners) = ctx.get::<Self>().cloned() else {
+            return;
+        };
+        let Some(advance) = corners.arrangement.advance(bounds, scale) else {
+            return;
+        };
+        match corners.taken(config) {
+            Some(i) => corners.taken[i].2 += advance,
+            None => corners.taken.push((config.position, config.anchor, advance)),
+        }
+        ctx.set(corners);
+    }
+}
+
+impl OverlayClip {
+    /// Returns true if clipping reads the alpha of the icon painted so far.
+    fn uses_base_alpha(self) -> bool {
+        matches!(self, Self::BaseAlpha | Self::BaseAlphaExpanded(_))
+    }
+}
+
+// ============================================================================
+// ColorAdjustment
+// ============================================================================
+
+/// A hue rotation and saturation change for an overlay's pixels.
+///
+/// Applied to the rasterized overlay in HSL, the same way the color target
+/// layer shifts the folder, so a badge can follow the folder's new color.
+///
+/// ```
+/// use folco_renderer::ColorAdjustment;
+///
+/// // The shift that takes the default folder yellow to blue
+/// let adjustment = ColorAdjustment::between((255, 217, 112), (33, 150, 243));
+/// assert!((adjustment.hue - 163.0).abs() < 1.0);
+/// assert!(ColorAdjustment::new(360.0, 1.0).is_identity());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "jsonschema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct ColorAdjustment {
+    /// Degrees to rotate the hue by (0-360).
+    #[serde(default)]
+    pub hue: f32,
+    /// Factor the saturation is multiplied by (0.0-2.0; 1.0 keeps it).
+    #[serde(default = "unit_factor")]
+    pub saturation: f32,
+}
+
+fn unit_factor() -> f32 {
+    1.0
+}
+
+impl ColorAdjustment {
+    /// Creates an adjustment. The hue wraps to 0-360 and the saturation
+    /// factor is clamped to 0.0-2.0.
+    pub fn new(hue: f32, saturation: f32) -> Self {
+        let (hue, saturation) = HslShift::rotation(finite_or(hue, 0.0), finite_or(saturation, 1.0)).hue_and_saturation();
+        Self { hue, saturation }
+    }
+
+    /// Rotates the hue only.
+    pub fn hue(hue: f32) -> Self {
+        Self::new(hue, 1.0)
+    }
+
+    /// The hue rotation and saturation change taking `from` to `to`, such
+    /// as a folder's surface color to its color target.
+    pub fn between(from: (u8, u8, u8), to: (u8, u8, u8)) -> Self {
+        let (hue, saturation) = HslShift::new(&SurfaceColor::new(from.0, from.1, from.2), to).hue_and_saturation();
+        Self { hue, saturation }
+    }
+
+    /// Returns true if the adjustment leaves colors unchanged.
+    pub fn is_identity(&self) -> bool {
+        let hue = self.hue.rem_euclid(360.0);
+        hue.min(360.0 - hue) < 0.001 && (self.saturation - 1.0).abs() < 0.0001
+    }
+
+    /// Shifts every visible pixel of `tile`.
+    fn apply(&self, tile: &mut RgbaImage) {
+        if self.is_identity() {
+            return;
+        }
+        let shift = HslShift::rotation(self.hue, self.saturation);
+        for pixel in tile.pixels_mut() {
+            let [r, g, b, a] = pixel.0;
+            if a > 0 {
+                let [r, g, b] = shift.apply(r, g, b);
+                pixel.0 = [r, g, b, a];
+            }
+        }
+    }
 }
 
 // ============================================================================
@@ -40,6 +360,7 @@ pub enum OverlayPosition {
 /// Accepts any [`SvgSource`]:
 /// - Raw SVG markup via [`SvgSource::from_svg()`]
 /// - An emoji character via [`SvgSource::from_emoji()`] (requires `twemoji` feature)
+/// - A PNG or JPEG logo via [`SvgSource::from_image_bytes()`]
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 #[cfg_attr(feature = "jsonschema", derive(schemars::JsonSchema))]
 #[serde(rename_all = "camelCase")]
@@ -47,11 +368,71 @@ pub struct SvgOverlayConfig {
     /// The SVG source.
     pub source: SvgSource,
 
-    /// Position within the icon's content bounds.
+    /// Position within the anchor rectangle.
     pub position: OverlayPosition,
 
     /// Scale factor relative to the icon's content bounds (0.0-1.0).
     pub scale: f32,
+
+    /// What `position` is relative to. Defaults to the content bounds.
+    #[serde(default, skip_serializing_if = "is_default_anchor")]
+    pub anchor: OverlayAnchor,
+
+    /// Where overlay pixels may land. Defaults to anywhere.
+    #[serde(default, skip_serializing_if = "is_default_clip")]
+    pub clip: OverlayClip,
+
+    /// Radius in pixels over which the edge of an alpha `clip` is
+    /// antialiased (see [`feather_edges`](super::feather_edges)), smoothing
+    /// the staircase an expanded clip leaves. Defaults to 0, a plain cut.
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub clip_feather: f32,
+
+    /// Optional drop shadow beneath the overlay, clipped along with it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub shadow: Option<ShadowConfig>,
+
+    /// How the overlay (with its shadow) blends with the icon beneath.
+    #[serde(default, skip_serializing_if = "BlendMode::is_normal")]
+    pub blend_mode: BlendMode,
+
+    /// Smallest legible overlay, in output pixels. When `scale` gives
+    /// fewer, `below_min` decides what happens. Defaults to no minimum.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_pixel_size: Option<u32>,
+
+    /// What happens below `min_pixel_size`. Defaults to clamping.
+    #[serde(default, skip_serializing_if = "is_default_min_size_policy")]
+    pub below_min: MinSizePolicy,
+
+    /// Opacity of the overlay and its shadow (0.0-1.0). Defaults to
+    /// opaque.
+    #[serde(default = "full_opacity", skip_serializing_if = "is_full_opacity")]
+    pub opacity: f32,
+
+    /// Hue and saturation change applied to the overlay's pixels, e.g. to
+    /// tint an emoji toward the folder color. Defaults to none.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color_adjustment: Option<ColorAdjustment>,
+
+    /// Draw the overlay in the pipeline [`AccentColor`] when one is set,
+    /// as the decal does with its color: SVGs using `currentColor` pick it
+    /// up there, others are flattened to it. Raster sources are left as
+    /// they are. Defaults to off.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub follow_accent: bool,
+}
+
+fn is_default_anchor(anchor: &OverlayAnchor) -> bool {
+    *anchor == OverlayAnchor::default()
+}
+
+fn is_default_clip(clip: &OverlayClip) -> bool {
+    *clip == OverlayClip::default()
+}
+
+fn is_default_min_size_policy(policy: &MinSizePolicy) -> bool {
+    *policy == MinSizePolicy::default()
 }
 
 impl SvgOverlayConfig {
@@ -62,10 +443,78 @@ impl SvgOverlayConfig {
         Self {
             source: source.into(),
             position,
-            scale: scale.clamp(0.0, 1.0),
+            scale: finite_or(scale, 0.0).clamp(0.0, 1.0),
+            anchor: OverlayAnchor::default(),
+            clip: OverlayClip::default(),
+            clip_feather: 0.0,
+            shadow: None,
+            blend_mode: BlendMode::Normal,
+            min_pixel_size: None,
+            below_min: MinSizePolicy::default(),
+            opacity: 1.0,
+            color_adjustment: None,
+            follow_accent: false,
         }
     }
 
+    /// Sets what the position is relative to.
+    pub fn with_anchor(mut self, anchor: OverlayAnchor) -> Self {
+        self.anchor = anchor;
+        self
+    }
+
+    /// Sets where overlay pixels may land.
+    pub fn with_clip(mut self, clip: OverlayClip) -> Self {
+        self.clip = clip;
+        self
+    }
+
+    /// Sets the radius over which the edge of an alpha clip is
+    /// antialiased. Negative values are treated as 0.
+    pub fn with_clip_feather(mut self, radius: f32) -> Self {
+        self.clip_feather = finite_or(radius, 0.0).max(0.0);
+        self
+    }
+
+    /// Sets a drop shadow beneath the overlay.
+    pub fn with_shadow(mut self, shadow: ShadowConfig) -> Self {
+        self.shadow = Some(shadow);
+        self
+    }
+
+    /// Sets how the overlay blends with the icon beneath.
+    pub fn with_blend_mode(mut self, blend_mode: BlendMode) -> Self {
+        self.blend_mode = blend_mode;
+        self
+    }
+
+    /// Sets the opacity of the overlay and its shadow. Clamped to 0.0-1.0.
+    pub fn with_opacity(mut self, opacity: f32) -> Self {
+        self.opacity = finite_or(opacity, 1.0).clamp(0.0, 1.0);
+        self
+    }
+
+    /// Shifts the hue and saturation of the overlay's pixels.
+    pub fn with_color_adjustment(mut self, adjustment: ColorAdjustment) -> Self {
+        self.color_adjustment = Some(adjustment);
+        self
+    }
+
+    /// Sets whether the overlay is drawn in the pipeline accent color.
+    pub fn with_follow_accent(mut self, follow_accent: bool) -> Self {
+        self.follow_accent = follow_accent;
+        self
+    }
+
+    /// Sets the smallest legible overlay size in pixels, and whether a
+    /// smaller overlay is drawn at that size or left out, so an emoji
+    /// badge doesn't shrink to a few unreadable pixels on a 16px icon.
+    pub fn with_min_pixel_size(mut self, pixels: u32, policy: MinSizePolicy) -> Self {
+        self.min_pixel_size = Some(pixels);
+        self.below_min = policy;
+        self
+    }
+
     /// Creates a new overlay config from an emoji.
     ///
     /// Returns an error if the emoji is not supported by twemoji_assets.
@@ -74,7 +523,16 @@ impl SvgOverlayConfig {
         Ok(Self {
             source: SvgSource::from_emoji(emoji)?,
             position,
-            scale: scale.clamp(0.0, 1.0),
+            scale: finite_or(scale, 0.0).clamp(0.0, 1.0),
+            anchor: OverlayAnchor::default(),
+            clip: OverlayClip::default(),
+            clip_feather: 0.0,
+            shadow: None,
+            blend_mode: BlendMode::Normal,
+            min_pixel_size: None,
+            below_min: MinSizePolicy::default(),
+            opacity: 1.0,
+            color_adjustment: None,
         })
     }
 
@@ -86,7 +544,16 @@ impl SvgOverlayConfig {
         Ok(Self {
             source: SvgSource::from_emoji_name(name)?,
             position,
-            scale: scale.clamp(0.0, 1.0),
+            scale: finite_or(scale, 0.0).clamp(0.0, 1.0),
+            anchor: OverlayAnchor::default(),
+            clip: OverlayClip::default(),
+            clip_feather: 0.0,
+            shadow: None,
+            blend_mode: BlendMode::Normal,
+            min_pixel_size: None,
+            below_min: MinSizePolicy::default(),
+            opacity: 1.0,
+            color_adjustment: None,
         })
     }
 }
@@ -96,6 +563,20 @@ impl LayerConfig for SvgOverlayConfig {
         self.source != other.source
             || self.position != other.position
             || (self.scale - other.scale).abs() > 0.0001
+            || self.anchor != other.anchor
+            || self.clip != other.clip
+            || (self.clip_feather - other.clip_feather).abs() > 0.0001
+            || self.shadow != other.shadow
+            || self.blend_mode != other.blend_mode
+            || self.min_pixel_size != other.min_pixel_size
+            || self.below_min != other.below_min
+            || (self.opacity - other.opacity).abs() > 0.0001
+            || self.color_adjustment != other.color_adjustment
+            || self.follow_accent != other.follow_accent
+    }
+
+    fn external_version(&self) -> u64 {
+        self.source.content_version()
     }
 }
 
@@ -104,79 +585,607 @@ impl LayerConfig for SvgOverlayConfig {
 // ============================================================================
 
 impl Layer<SvgOverlayConfig> {
+    /// Returns how the tile blends with the icon beneath.
+    pub(crate) fn blend_mode(&self) -> BlendMode {
+        self.config().map_or(BlendMode::Normal, |config| config.blend_mode)
+    }
+
+    /// Returns the upstream versions this layer's cache depends on.
+    ///
+    /// Only overlays anchored to the decal, auto-positioned around it or
+    /// clipped to the icon's alpha depend on it (for its placement or
+    /// pixels). Alpha clips also follow the shadow, which widens the
+    /// icon's silhouette, and accent-following overlays the accent.
+    pub(crate) fn dependencies(&self, versions: &LayerVersions) -> DependencyVersion {
+        let Some(config) = self.config() else {
+            return DependencyVersion::NONE;
+        };
+        let mut upstream = Vec::new();
+        if config.clip.uses_base_alpha() {
+            upstream.extend([versions.decal, versions.shadow]);
+        } else if config.anchor == OverlayAnchor::Decal || config.position == OverlayPosition::Auto {
+            upstream.push(versions.decal);
+        }
+        if config.follow_accent {
+            upstream.push(versions.accent);
+        }
+        match upstream[..] {
+            [] => DependencyVersion::NONE,
+            [version] => DependencyVersion::from_version(version),
+            _ => DependencyVersion::combine(&upstream),
+        }
+    }
+
     /// Render this overlay layer, returning a tile for compositing.
     ///
     /// Returns `None` if inactive. The tile is a transparent canvas with
-    /// the SVG rendered at the configured position.
+    /// the SVG rendered at the configured position. Adds the drawn
+    /// rectangle to [`OccupiedRegions`].
     pub fn render_tile(
         &mut self,
         ctx: &mut RenderContext,
         key: CacheKey,
-        _versions: &LayerVersions,
+        versions: &LayerVersions,
+    ) -> Result<Option<RgbaImage>, RenderError> {
+        let deps = self.dependencies(versions);
+        self.render_tile_with_deps(ctx, key, deps)
+    }
+
+    /// Like [`render_tile`](Self::render_tile), with the cache validated
+    /// against a caller-computed dependency version.
+    pub(crate) fn render_tile_with_deps(
+        &mut self,
+        ctx: &mut RenderContext,
+        key: CacheKey,
+        deps: DependencyVersion,
     ) -> Result<Option<RgbaImage>, RenderError> {
         if !self.is_active() {
             return Ok(None);
         }
 
-        let deps = DependencyVersion::NONE; // No upstream dependencies
 
-        if let Some(CachedOutput::Tile(tile)) = self.get_cached(key, deps) {
+        if let Some(CachedOutput::Tile(tile, placement)) = self.get_cached(key, deps) {
+            if let Some(bounds) = *placement {
+                OccupiedRegions::push(ctx, bounds);
+                ArrangedCorners::record(ctx, self.config().unwrap(), &bounds);
+            }
             return Ok(Some(tile.clone()));
         }
 
         let config = self.config().unwrap();
-        let tile = render_overlay(config, ctx)?;
+        let (tile, placement) = render_overlay(config, ctx)?;
+        if let Some(bounds) = placement {
+            OccupiedRegions::push(ctx, bounds);
+            ArrangedCorners::record(ctx, config, &bounds);
+        }
 
-        self.store(key, CachedOutput::Tile(tile.clone()), deps);
+        if RasterQuality::of(ctx).is_draft() {
+            DegradedLayers::push(ctx, LayerKind::Overlay);
+        } else {
+            self.store(key, CachedOutput::Tile(tile.clone(), placement), deps);
+        }
         Ok(Some(tile))
     }
 }
 
 /// Renders an overlay SVG onto a transparent tile at the configured position.
+///
+/// The overlay is sized from the content bounds and positioned within its
+/// anchor rectangle, moved along the edge by any [`ArrangedCorners`] in
+/// `ctx`. Also returns the rectangle drawn into, clipped to the image, or
+/// `None` if nothing was drawn.
 fn render_overlay(
     config: &SvgOverlayConfig,
-    ctx: &RenderContext,
-) -> Result<RgbaImage, RenderError> {
+    ctx: &mut RenderContext,
+) -> Result<(RgbaImage, Option<RectPx>), RenderError> {
+    let position = ArrangedCorners::position(ctx, config);
     let bounds = ctx.image.content_bounds;
     let min_dim = bounds.width.min(bounds.height) as f32;
-    let overlay_size = (min_dim * config.scale) as u32;
+    // Deserialized configs bypass the constructor clamp
+    let mut overlay_size = (min_dim * config.scale.clamp(0.0, 1.0)) as u32;
 
-    let width = ctx.image.data.width();
-    let height = ctx.image.data.height();
+    let width = ctx.image.data().width();
+    let height = ctx.image.data().height();
     let mut tile = RgbaImage::new(width, height);
 
+    if let Some(min) = config.min_pixel_size
+        && overlay_size < min
+    {
+        match config.below_min {
+            MinSizePolicy::Clamp => overlay_size = min.min(min_dim as u32),
+            // Omitted on purpose, so not a warning
+            MinSizePolicy::Omit => return Ok((tile, None)),
+        }
+    }
     if overlay_size == 0 {
-        return Ok(tile);
+        ctx.warn(RenderWarning::ZeroSize { layer: LayerKind::Overlay });
+        return Ok((tile, None));
     }
 
-    let overlay_img = render_source(&config.source, overlay_size)?;
+    let quality = RasterQuality::of(ctx);
+    let svg = config.source.resolve_shared()?;
+    let accent = ctx
+        .get::<AccentColor>()
+        .filter(|_| config.follow_accent && !config.source.is_raster())
+        .map(|c| (c.r, c.g, c.b, 255));
+    let current_color = accent.is_some() && uses_current_color(&svg);
+    let key = StampKey {
+        svg: svg.clone(),
+        size: overlay_size,
+        color: accent.map(|color| (color, current_color)),
+        quality,
+    };
+    let build = || match accent {
+        Some(color) if current_color => stamp_svg_with_current_color_at(&svg, overlay_size, color, quality),
+        _ => stamp_svg_with_color_at(&svg, overlay_size, accent, quality),
+    };
+
+    let anchor = match config.anchor {
+        OverlayAnchor::Content => bounds,
+        OverlayAnchor::Decal => ctx.get::<DecalPlacement>().map_or(bounds, |p| p.bounds),
+    };
+    let occupied = ctx.get::<OccupiedRegions>().cloned().unwrap_or_default();
+    let place = |overlay_width, overlay_height| {
+        calculate_position(position, &anchor, &occupied, overlay_width, overlay_height, ctx.image.scale)
+    };
+
+    let (stamp, (x, y, w, h)) = composite_keyed_stamp(&mut tile, ctx, key, build, place, PlacementRounding::of(ctx))?;
+    if stamp.is_empty() {
+        ctx.warn(RenderWarning::EmptySvg { layer: LayerKind::Overlay });
+    }
+    if let Some(adjustment) = &config.color_adjustment {
+        adjustment.apply(&mut tile);
+    }
+    if let Some(shadow) = &config.shadow {
+        drop_shadow(&mut tile, shadow, &bounds, quality);
+    }
+    apply_clip(&mut tile, config.clip, config.clip_feather, ctx);
+    fade(&mut tile, config.opacity);
 
-    let (x, y) = calculate_position(config.position, &bounds, overlay_img.width(), overlay_img.height());
+    let placement = clip_to_image(x, y, w, h, width, height);
+    Ok((tile, placement))
+}
 
-    composite_over(&mut tile, &overlay_img, x, y);
+/// Masks an overlay tile according to its [`OverlayClip`], feathering
+/// alpha clips by `feather` pixels.
+fn apply_clip(tile: &mut RgbaImage, clip: OverlayClip, feather: f32, ctx: &RenderContext) {
+    let expand = match clip {
+        OverlayClip::None => return,
+        OverlayClip::ContentBounds => {
+            let bounds = ctx.image.content_bounds;
+            for (x, y, pixel) in tile.enumerate_pixels_mut() {
+                let inside = (bounds.x..bounds.x + bounds.width).contains(&x)
+                    && (bounds.y..bounds.y + bounds.height).contains(&y);
+                if !inside {
+                    pixel.0 = [0; 4];
+                }
+            }
+            return;
+        }
+        OverlayClip::BaseAlpha => 0,
+        // Dilation is the costliest step, so drafts clip to the plain alpha
+        OverlayClip::BaseAlphaExpanded(_) if RasterQuality::of(ctx).is_draft() => 0,
+        OverlayClip::BaseAlphaExpanded(px) => px,
+    };
 
-    Ok(tile)
+    clip_to_alpha(tile, ctx, expand, feather);
+}
+
+/// Masks a tile by the alpha of the icon drawn so far, grown by `expand`
+/// pixels, so it stays within the folder's silhouette.
+///
+/// A positive `feather` antialiases the mask's edge over that many pixels
+/// with [`feather_edges`]. Drafts skip it and cut along the plain mask.
+pub(crate) fn clip_to_alpha(tile: &mut RgbaImage, ctx: &RenderContext, expand: u32, feather: f32) {
+    let (width, height) = tile.dimensions();
+    let mut alpha: Vec<u8> = ctx.image.data().pixels().map(|p| p[3]).collect();
+    if expand > 0 {
+        alpha = dilate(&alpha, width as usize, height as usize, expand as usize);
+    }
+    if feather > 0.0 && !RasterQuality::of(ctx).is_draft() {
+        let mut mask = RgbaImage::from_fn(width, height, |x, y| Rgba([0, 0, 0, alpha[(y * width + x) as usize]]));
+        feather_edges(&mut mask, feather);
+        alpha = mask.pixels().map(|p| p[3]).collect();
+    }
+    for (pixel, mask) in tile.pixels_mut().zip(alpha) {
+        pixel[3] = ((pixel[3] as u16 * mask as u16 + 127) / 255) as u8;
+    }
+}
+
+/// Grows an alpha mask: each value becomes the maximum within `radius`
+/// pixels horizontally and vertically.
+fn dilate(alpha: &[u8], width: usize, height: usize, radius: usize) -> Vec<u8> {
+    let pass = |input: &[u8], horizontal: bool| {
+        let mut out = vec![0; input.len()];
+        for y in 0..height {
+            for x in 0..width {
+                let (pos, len) = if horizontal { (x, width) } else { (y, height) };
+                let range = pos.saturating_sub(radius)..(pos + radius + 1).min(len);
+                out[y * width + x] = range
+                    .map(|i| if horizontal { input[y * width + i] } else { input[i * width + x] })
+                    .max()
+                    .unwrap_or(0);
+            }
+        }
+        out
+    };
+    pass(&pass(alpha, true), false)
+}
+
+/// Clips a signed rectangle to the image, returning `None` if nothing remains.
+pub(super) fn clip_to_image(x: i32, y: i32, w: u32, h: u32, image_width: u32, image_height: u32) -> Option<RectPx> {
+    let (left, top) = (x.max(0) as u32, y.max(0) as u32);
+    let right = (x + w as i32).clamp(0, image_width as i32) as u32;
+    let bottom = (y + h as i32).clamp(0, image_height as i32) as u32;
+    (right > left && bottom > top).then(|| RectPx::new(left, top, right - left, bottom - top))
 }
 
 /// Calculates the (x, y) position for the overlay based on position setting and bounds.
-fn calculate_position(
+///
+/// Positions are exact, for the pipeline's [`PlacementRounding`] to snap:
+/// centering an odd-sized overlay in an even box lands on a half pixel.
+/// [`OverlayPosition::Auto`] resolves to the corner whose placement
+/// overlaps `occupied` the least. `scale` converts the logical pixel
+/// offsets of [`OverlayPosition::Custom`] to image pixels.
+pub(super) fn calculate_position(
     position: OverlayPosition,
-    bounds: &crate::icon::RectPx,
+    bounds: &RectPx,
+    occupied: &OccupiedRegions,
     overlay_width: u32,
     overlay_height: u32,
-) -> (i32, i32) {
-    let bx = bounds.x as i32;
-    let by = bounds.y as i32;
-    let bw = bounds.width as i32;
-    let bh = bounds.height as i32;
-    let ow = overlay_width as i32;
-    let oh = overlay_height as i32;
+    scale: f32,
+) -> (f32, f32) {
+    let bx = bounds.x as f32;
+    let by = bounds.y as f32;
+    let bw = bounds.width as f32;
+    let bh = bounds.height as f32;
+    let ow = overlay_width as f32;
+    let oh = overlay_height as f32;
 
     match position {
         OverlayPosition::TopLeft => (bx, by),
         OverlayPosition::TopRight => (bx + bw - ow, by),
         OverlayPosition::BottomLeft => (bx, by + bh - oh),
         OverlayPosition::BottomRight => (bx + bw - ow, by + bh - oh),
-        OverlayPosition::Center => (bx + (bw - ow) / 2, by + (bh - oh) / 2),
+        OverlayPosition::Center => (bx + (bw - ow) / 2.0, by + (bh - oh) / 2.0),
+        OverlayPosition::Custom { x, y, dx, dy } => {
+            // Deserialized positions bypass the constructor clamp
+            let along = |start: f32, free: f32, fraction: f32, offset: f32| {
+                let offset = if offset.is_finite() { offset * scale } else { 0.0 };
+                start + free * fraction.clamp(0.0, 1.0) + offset
+            };
+            (along(bx, bw - ow, x, dx), along(by, bh - oh, y, dy))
+        }
+        OverlayPosition::Auto => [
+            OverlayPosition::BottomRight,
+            OverlayPosition::BottomLeft,
+            OverlayPosition::TopRight,
+            OverlayPosition::TopLeft,
+        ]
+        .into_iter()
+        .map(|corner| calculate_position(corner, bounds, occupied, overlay_width, overlay_height, scale))
+        // min_by_key keeps the first of equal candidates, honoring the preference order
+        .min_by_key(|&(x, y)| {
+            // Corners of whole-pixel bounds are whole pixels
+            let rect = RectPx::new(x.max(0.0) as u32, y.max(0.0) as u32, overlay_width, overlay_height);
+            occupied.overlap(&rect)
+        })
+        .expect("corner list is non-empty"),
+    }
+}
+
+// ============================================================================
+// Composite-only rendering
+// ============================================================================
+
+impl LayerPipeline {
+    /// Returns how overlays sharing a corner are laid out.
+    pub fn overlay_arrangement(&self) -> OverlayArrangement {
+        self.overlay_arrangement
+    }
+
+    /// Sets how [`composite_only`](Self::composite_only) lays out overlays
+    /// sharing a corner, as kept in a
+    /// [`CustomizationProfile`](crate::CustomizationProfile). Returns true
+    /// if it changed.
+    ///
+    /// The pipeline has a single overlay layer, so its own renders are
+    /// unaffected.
+    pub fn set_overlay_arrangement(&mut self, arrangement: OverlayArrangement) -> bool {
+        let changed = self.overlay_arrangement != arrangement;
+        self.overlay_arrangement = arrangement;
+        changed
+    }
+
+    /// Composites `overlays` over `base`, in order, skipping every layer of
+    /// the pipeline.
+    ///
+    /// For hosts that recolor and imprint icons elsewhere but still want
+    /// this crate's overlay placement, blending and stamp caching. Each
+    /// overlay is rasterized once per size and kept for later calls (or
+    /// shared, under an [`IconBatchRenderer`](crate::IconBatchRenderer)).
+    /// Auto-positioned overlays avoid the ones drawn before them, and
+    /// overlays in the same corner are laid out by the
+    /// [`overlay_arrangement`](Self::overlay_arrangement); overlays
+    /// anchored to the decal use the content bounds, as there is none.
+    ///
+    /// ```
+    /// use folco_renderer::{IconImage, LayerPipeline, OverlayPosition, SvgOverlayConfig};
+    /// use image::{Rgba, RgbaImage};
+    ///
+    /// let badge = r##"<svg xmlns="http://www.w3.org/2000/svg" width="10" height="10"><circle cx="5" cy="5" r="5" fill="#e53935"/></svg>"##;
+    /// let base = IconImage::new_full_content(RgbaImage::from_pixel(32, 32, Rgba([255, 217, 112, 255])), 1.0);
+    /// let overlays = [SvgOverlayConfig::new(badge, OverlayPosition::BottomRight, 0.5)];
+    ///
+    /// let icon = LayerPipeline::default().composite_only(&base, &overlays).unwrap();
+    /// assert_eq!(icon.data().get_pixel(24, 24).0, [229, 57, 53, 255]);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `base` exceeds the render limits or an overlay
+    /// fails to render.
+    pub fn composite_only(&self, base: &IconImage, overlays: &[SvgOverlayConfig]) -> Result<IconImage, RenderError> {
+        self.composite_only_arranged(base, overlays, self.overlay_arrangement)
+    }
+
+    /// Like [`composite_only`](Self::composite_only), laying out overlays
+    /// that share a corner according to `arrangement` so they don't cover
+    /// each other.
+    ///
+    /// ```
+    /// use folco_renderer::{IconImage, LayerPipeline, OverlayArrangement, OverlayPosition, SvgOverlayConfig};
+    /// use image::{Rgba, RgbaImage};
+    ///
+    /// let square = r##"<svg xmlns="http://www.w3.org/2000/svg" width="10" height="10"><rect width="10" height="10" fill="#e53935"/></svg>"##;
+    /// let base = IconImage::new_full_content(RgbaImage::from_pixel(32, 32, Rgba([255, 217, 112, 255])), 1.0);
+    /// let badge = SvgOverlayConfig::new(square, OverlayPosition::BottomRight, 0.25);
+    /// let overlays = [badge.clone(), badge];
+    ///
+    /// // The second badge sits 2 pixels left of the first, at x 14-22
+    /// let icon = LayerPipeline::default()
+    ///     .composite_only_arranged(&base, &overlays, OverlayArrangement::Spread { spacing: 2.0 })
+    ///     .unwrap();
+    /// assert_eq!(icon.data().get_pixel(14, 28).0, [229, 57, 53, 255]);
+    /// assert_eq!(icon.data().get_pixel(23, 28).0, [255, 217, 112, 255]);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Same as [`composite_only`](Self::composite_only).
+    pub fn composite_only_arranged(
+        &self,
+        base: &IconImage,
+        overlays: &[SvgOverlayConfig],
+        arrangement: OverlayArrangement,
+    ) -> Result<IconImage, RenderError> {
+        self.limits.check(base.data().width(), base.data().height())?;
+        let mut ctx = RenderContext::new(base.clone());
+        ctx.set(self.placement_rounding);
+        ctx.set(self.shared_stamps.clone().unwrap_or_else(|| self.composite_stamps.clone()));
+        ctx.set(ArrangedCorners::new(arrangement));
+
+        for config in overlays {
+            let (tile, placement) = render_overlay(config, &mut ctx)?;
+            if let Some(bounds) = placement {
+                OccupiedRegions::push(&mut ctx, bounds);
+                ArrangedCorners::record(&mut ctx, config, &bounds);
+            }
+            composite_blend(ctx.image.data_mut(), &tile, 0, 0, config.blend_mode);
+        }
+        self.report_warnings(&ctx);
+        Ok(ctx.image)
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn auto_position_avoids_occupied_corners() {
+        let bounds = RectPx::new(0, 0, 32, 32);
+        let place = |occupied: &OccupiedRegions| calculate_position(OverlayPosition::Auto, &bounds, occupied, 8, 8, 1.0);
+
+        // Nothing occupied: prefer bottom-right
+        assert_eq!(place(&OccupiedRegions::default()), (24.0, 24.0));
+
+        // Something in the bottom-right: move to bottom-left
+        let mut occupied = OccupiedRegions::default();
+        occupied.regions.push(RectPx::new(16, 16, 16, 16));
+        assert_eq!(place(&occupied), (0.0, 24.0));
+
+        // Whole bottom half taken: top-right
+        occupied.regions.push(RectPx::new(0, 20, 16, 12));
+        assert_eq!(place(&occupied), (24.0, 0.0));
+    }
+
+    #[test]
+    fn custom_position_interpolates_and_offsets() {
+        let bounds = RectPx::new(4, 4, 32, 32);
+        let place = |position, scale| calculate_position(position, &bounds, &OccupiedRegions::default(), 8, 8, scale);
+
+        assert_eq!(place(OverlayPosition::custom(1.0, 1.0), 1.0), place(OverlayPosition::BottomRight, 1.0));
+        assert_eq!(place(OverlayPosition::custom(0.5, 0.5), 1.0), place(OverlayPosition::Center, 1.0));
+        assert_eq!(place(OverlayPosition::custom(0.25, 2.0), 1.0), (10.0, 28.0));
+        // Offsets are logical pixels
+        let nudged = OverlayPosition::Custom { x: 0.0, y: 0.0, dx: 3.0, dy: -1.0 };
+        assert_eq!(place(nudged, 2.0), (10.0, 2.0));
+    }
+
+    #[test]
+    fn clip_policies_limit_overlay_pixels() {
+        use crate::icon::IconImage;
+
+        // 16×16 canvas; content bounds and opaque silhouette are the 8×8 middle
+        let mut base = RgbaImage::new(16, 16);
+        for y in 4..12 {
+            for x in 4..12 {
+                base.put_pixel(x, y, image::Rgba([0, 0, 0, 255]));
+            }
+        }
+        let ctx = RenderContext::new(IconImage::new(base, 1.0, RectPx::new(2, 2, 12, 12)));
+        let clipped = |clip| {
+            let mut tile = RgbaImage::from_pixel(16, 16, image::Rgba([255, 0, 0, 255]));
+            apply_clip(&mut tile, clip, 0.0, &ctx);
+            tile.pixels().filter(|p| p[3] > 0).count()
+        };
+        let partial = |feather| {
+            let mut tile = RgbaImage::from_pixel(16, 16, image::Rgba([255, 0, 0, 255]));
+            apply_clip(&mut tile, OverlayClip::BaseAlphaExpanded(1), feather, &ctx);
+            tile.pixels().filter(|p| p[3] > 0 && p[3] < 255).count()
+        };
+
+        assert_eq!(clipped(OverlayClip::None), 256);
+        assert_eq!(clipped(OverlayClip::ContentBounds), 144);
+        assert_eq!(clipped(OverlayClip::BaseAlpha), 64);
+        assert_eq!(clipped(OverlayClip::BaseAlphaExpanded(1)), 100);
+        // Feathering ramps the cut edge instead of leaving it hard
+        assert_eq!(partial(0.0), 0);
+        assert!(partial(1.0) > 0);
+
+        let clipped = SvgOverlayConfig::new("<svg/>", OverlayPosition::Center, 0.5).with_clip(OverlayClip::BaseAlphaExpanded(2));
+        let json = serde_json::to_string(&clipped).unwrap();
+        assert!(json.contains(r#""clip":{"base-alpha-expanded":2}"#), "{json}");
+    }
+
+    #[test]
+    fn blend_mode_applies_when_compositing() {
+        use crate::icon::{IconImage, SurfaceColor};
+        use crate::layer::LayerPipeline;
+
+        let square = r##"<svg xmlns="http://www.w3.org/2000/svg" width="10" height="10"><rect width="10" height="10" fill="#808080"/></svg>"##;
+        let base = IconImage::new_full_content(RgbaImage::from_pixel(16, 16, image::Rgba([200, 100, 0, 255])), 1.0);
+        let surface = SurfaceColor::new(200, 100, 0);
+        let mut pipeline = LayerPipeline::default();
+        let config = SvgOverlayConfig::new(square, OverlayPosition::Center, 1.0);
+
+        pipeline.overlay.set_config(Some(config.clone()));
+        assert_eq!(pipeline.render(&base, &surface).unwrap().data().get_pixel(8, 8).0, [128, 128, 128, 255]);
+        pipeline.overlay.set_config(Some(config.with_blend_mode(BlendMode::Multiply)));
+        assert_eq!(pipeline.render(&base, &surface).unwrap().data().get_pixel(8, 8).0, [100, 50, 0, 255]);
+    }
+
+    #[test]
+    fn small_overlays_clamp_or_are_omitted() {
+        use crate::icon::IconImage;
+
+        let square = r##"<svg xmlns="http://www.w3.org/2000/svg" width="10" height="10"><rect width="10" height="10" fill="#000"/></svg>"##;
+        let drawn = |config: &SvgOverlayConfig, size| {
+            let mut ctx = RenderContext::new(IconImage::new_full_content(RgbaImage::new(size, size), 1.0));
+            let (tile, placement) = render_overlay(config, &mut ctx).unwrap();
+            assert!(ctx.warnings().is_empty());
+            (placement.map(|p| p.width), tile.pixels().filter(|p| p[3] > 0).count())
+        };
+
+        // A 20% badge is 3px at 16px, 12px at 64px
+        let config = SvgOverlayConfig::new(square, OverlayPosition::TopLeft, 0.2);
+        assert_eq!(drawn(&config, 16), (Some(3), 9));
+        let clamped = config.clone().with_min_pixel_size(6, MinSizePolicy::Clamp);
+        assert_eq!(drawn(&clamped, 16), (Some(6), 36));
+        assert_eq!(drawn(&clamped, 64), (Some(12), 144));
+        let omitted = config.with_min_pixel_size(6, MinSizePolicy::Omit);
+        assert_eq!(drawn(&omitted, 16), (None, 0));
+        assert_eq!(drawn(&omitted, 64), (Some(12), 144));
+
+        let json = serde_json::to_string(&omitted).unwrap();
+        assert!(json.contains(r#""minPixelSize":6,"belowMin":"omit""#), "{json}");
+    }
+
+    #[test]
+    fn opacity_fades_decals_and_overlays() {
+        use crate::icon::{IconImage, SurfaceColor};
+        use crate::layer::{DecalConfig, LayerPipeline};
+
+        let square = r##"<svg xmlns="http://www.w3.org/2000/svg" width="10" height="10"><rect width="10" height="10" fill="#000"/></svg>"##;
+        let base = IconImage::new_full_content(RgbaImage::new(16, 16), 1.0);
+        let surface = SurfaceColor::new(255, 217, 112);
+        let mut pipeline = LayerPipeline::default();
+
+        pipeline.decal.set_config(Some(DecalConfig::new(square, 1.0).with_opacity(0.5)));
+        assert_eq!(pipeline.render(&base, &surface).unwrap().data().get_pixel(8, 8)[3], 128);
+        pipeline.decal.set_config(None);
+        let overlay = SvgOverlayConfig::new(square, OverlayPosition::Center, 1.0).with_opacity(0.25);
+        pipeline.overlay.set_config(Some(overlay.clone()));
+        assert_eq!(pipeline.render(&base, &surface).unwrap().data().get_pixel(8, 8)[3], 64);
+
+        let json = serde_json::to_string(&overlay).unwrap();
+        assert!(json.contains(r#""opacity":0.25"#), "{json}");
+        let opaque = serde_json::to_string(&overlay.with_opacity(1.0)).unwrap();
+        assert!(!opaque.contains("opacity"), "{opaque}");
+        let parsed: SvgOverlayConfig = serde_json::from_str(&opaque).unwrap();
+        assert_eq!(parsed.opacity, 1.0);
+    }
+
+    #[test]
+    fn composite_only_draws_overlays_in_order() {
+        use crate::icon::SurfaceColor;
+
+        let square = r##"<svg xmlns="http://www.w3.org/2000/svg" width="10" height="10"><rect width="10" height="10" fill="#808080"/></svg>"##;
+        let badge = r##"<svg xmlns="http://www.w3.org/2000/svg" width="10" height="10"><rect width="10" height="10" fill="#e53935"/></svg>"##;
+        let base = IconImage::new_full_content(RgbaImage::from_pixel(32, 32, image::Rgba([200, 100, 0, 255])), 1.0);
+        let first = SvgOverlayConfig::new(square, OverlayPosition::Auto, 0.5).with_blend_mode(BlendMode::Multiply);
+        let second = SvgOverlayConfig::new(badge, OverlayPosition::Auto, 0.25);
+
+        // The first matches the overlay layer
+        let mut pipeline = LayerPipeline::default();
+        let overlays = [first.clone(), second];
+        let icon = pipeline.composite_only(&base, &overlays).unwrap();
+        pipeline.overlay.set_config(Some(first));
+        let layered = pipeline.render(&base, &SurfaceColor::new(200, 100, 0)).unwrap();
+        assert_eq!(icon.data().get_pixel(24, 24), layered.data().get_pixel(24, 24));
+        // The second badge steps around the first, to the bottom left
+        assert_eq!(icon.data().get_pixel(4, 28).0, [229, 57, 53, 255]);
+        // Repeat calls reuse the rasterized stamps
+        assert_eq!(pipeline.composite_only(&base, &overlays).unwrap(), icon);
+        assert_eq!(pipeline.composite_stamps.0.len(), 2);
+    }
+
+    #[test]
+    fn arranged_overlays_move_along_the_edge() {
+        let badge = r##"<svg xmlns="http://www.w3.org/2000/svg" width="10" height="10"><rect width="10" height="10" fill="#e53935"/></svg>"##;
+        let base = IconImage::new_full_content(RgbaImage::from_pixel(32, 32, image::Rgba([200, 100, 0, 255])), 2.0);
+        let corner = SvgOverlayConfig::new(badge, OverlayPosition::TopLeft, 0.25);
+        let center = SvgOverlayConfig::new(badge, OverlayPosition::Center, 0.25);
+        let overlays = [corner.clone(), center, corner.clone(), corner];
+        let red = |arrangement, x, y| {
+            let icon = LayerPipeline::default().composite_only_arranged(&base, &overlays, arrangement).unwrap();
+            icon.data().get_pixel(x, y).0 == [229, 57, 53, 255]
+        };
+
+        // 8px badges, 1 logical (2px) apart; the centered one stays put
+        let stack = OverlayArrangement::Stack { spacing: 1.0 };
+        assert!(red(stack, 0, 10) && red(stack, 0, 20) && !red(stack, 0, 9) && !red(stack, 10, 10));
+        assert!(red(stack, 12, 12) && !red(stack, 0, 28));
+        let spread = OverlayArrangement::Spread { spacing: 0.0 };
+        assert!(red(spread, 8, 0) && red(spread, 23, 7) && !red(spread, 24, 0));
+        assert!(!red(OverlayArrangement::Overlap, 8, 0));
+    }
+
+    #[test]
+    fn color_adjustment_shifts_overlay_hue() {
+        let red = r##"<svg xmlns="http://www.w3.org/2000/svg" width="10" height="10"><rect width="10" height="10" fill="#ff0000"/></svg>"##;
+        let base = IconImage::new_full_content(RgbaImage::new(16, 16), 1.0);
+        let overlay = |adjustment| SvgOverlayConfig::new(red, OverlayPosition::Center, 1.0).with_color_adjustment(adjustment);
+        let center = |config| LayerPipeline::default().composite_only(&base, &[config]).unwrap().data().get_pixel(8, 8).0;
+
+        assert_eq!(center(overlay(ColorAdjustment::hue(120.0))), [0, 255, 0, 255]);
+        assert_eq!(center(overlay(ColorAdjustment::new(-120.0, 1.0))), [0, 0, 255, 255]);
+        assert_eq!(center(overlay(ColorAdjustment::new(0.0, 0.0))), [128, 128, 128, 255]);
+
+        let json = serde_json::to_string(&overlay(ColorAdjustment::hue(90.0))).unwrap();
+        assert!(json.contains(r#""colorAdjustment":{"hue":90.0,"saturation":1.0}"#), "{json}");
+        let parsed: ColorAdjustment = serde_json::from_str(r#"{"hue":30}"#).unwrap();
+        assert_eq!(parsed, ColorAdjustment::hue(30.0));
+    }
+
+    #[test]
+    fn placement_is_clipped_to_image() {
+        assert_eq!(clip_to_image(-4, 28, 8, 8, 32, 32), Some(RectPx::new(0, 28, 4, 4)));
+        assert_eq!(clip_to_image(40, 0, 8, 8, 32, 32), None);
     }
 }
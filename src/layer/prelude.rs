@@ -0,0 +1,124 @@
+//! Supported surface for writing custom layers.
+//!
+//! Third-party layers plug into a [`DynamicPipeline`] by implementing
+//! [`PipelineLayer`]. Everything such a layer needs is re-exported here,
+//! so layer crates can depend on a single import:
+//!
+//! ```
+//! use folco_renderer::layer::prelude::*;
+//! ```
+//!
+//! Items in this module follow the crate's semver guarantees; other paths
+//! under [`layer`](crate::layer) may move between minor versions.
+//!
+//! # Writing a layer
+//!
+//! A layer's [`render`](PipelineLayer::render) receives the
+//! [`RenderContext`], whose `image` holds everything painted by the layers
+//! before it, and draws into it:
+//!
+//! - **Reading properties.** Upstream layers publish typed properties in
+//!   the context: the [`SurfaceColor`] and [`AccentColor`] inputs, the
+//!   [`DominantColor`] and [`DerivedPalette`] of a recolored folder, the
+//!   [`DecalPlacement`], the [`OccupiedRegions`] painted so far, and the
+//!   [`PlacementRounding`] to snap your own positions with. Read
+//!   them with [`RenderContext::get`]; any of them may be absent.
+//! - **Emitting properties.** Call [`RenderContext::set`] with any
+//!   `Send + Sync` type, including your own, for downstream layers to
+//!   read. Push the rectangle you drew into with [`OccupiedRegions::push`]
+//!   so auto-positioned overlays avoid it.
+//! - **Warnings.** When your layer draws less than configured without
+//!   failing, record why with [`RenderContext::warn`] so hosts can see it.
+//! - **Compositing.** Render to a transparent tile the size of
+//!   `ctx.image.data()` and blend it with [`composite_over`], or
+//!   [`composite_blend`] for a [`BlendMode`] other than normal;
+//!   [`composite_at`] places a stamp at an exact position following the
+//!   [`PlacementRounding`]. The `render_svg*` helpers rasterize SVG
+//!   markup, and [`feather_edges`] smooths hard-cut alpha.
+//! - **Caching.** `deps` changes whenever anything upstream does. Treat
+//!   `(key, deps)` plus your own settings as the cache key, and re-emit
+//!   your properties on a cache hit so downstream layers see the same
+//!   context either way. Bump the value returned by
+//!   [`version`](PipelineLayer::version) whenever your settings change, so
+//!   downstream layers and the composite re-render.
+//!
+//! # Example
+//!
+//! A layer that stamps a square in the folder's complementary color into
+//! the bottom-right corner:
+//!
+//! ```
+//! use std::any::Any;
+//!
+//! use folco_renderer::layer::prelude::*;
+//! use image::{Rgba, RgbaImage};
+//!
+//! #[derive(Clone, Default)]
+//! struct CornerStamp {
+//!     version: u64,
+//!     cache: Option<(CacheKey, DependencyVersion, RgbaImage, RectPx)>,
+//! }
+//!
+//! impl PipelineLayer for CornerStamp {
+//!     fn render(&mut self, ctx: &mut RenderContext, key: CacheKey, deps: DependencyVersion) -> Result<(), RenderError> {
+//!         let (tile, placement) = match &self.cache {
+//!             Some((k, d, tile, placement)) if (*k, *d) == (key, deps) => (tile.clone(), *placement),
+//!             _ => {
+//!                 let (r, g, b) = ctx.get::<DerivedPalette>().map_or((0, 0, 0), |p| p.complementary);
+//!                 let bounds = ctx.image.content_bounds;
+//!                 let size = (bounds.width / 4).max(1);
+//!                 let placement = RectPx::new(bounds.right() - size, bounds.bottom() - size, size, size);
+//!
+//!                 let mut tile = RgbaImage::new(ctx.image.data().width(), ctx.image.data().height());
+//!                 let stamp = RgbaImage::from_pixel(size, size, Rgba([r, g, b, 255]));
+//!                 composite_over(&mut tile, &stamp, placement.x as i32, placement.y as i32);
+//!                 self.cache = Some((key, deps, tile.clone(), placement));
+//!                 (tile, placement)
+//!             }
+//!         };
+//!         composite_over(ctx.image.data_mut(), &tile, 0, 0);
+//!         OccupiedRegions::push(ctx, placement);
+//!         Ok(())
+//!     }
+//!
+//!     fn version(&self) -> u64 {
+//!         self.version
+//!     }
+//!
+//!     fn invalidate(&mut self) {
+//!         self.version += 1;
+//!         self.cache = None;
+//!     }
+//!
+//!     fn clone_box(&self) -> Box<dyn PipelineLayer> {
+//!         Box::new(self.clone())
+//!     }
+//!
+//!     fn as_any(&self) -> &dyn Any {
+//!         self
+//!     }
+//!
+//!     fn as_any_mut(&mut self) -> &mut dyn Any {
+//!         self
+//!     }
+//! }
+//!
+//! let mut pipeline = DynamicPipeline::new();
+//! pipeline.push(CornerStamp::default());
+//!
+//! let base = IconImage::new_full_content(RgbaImage::from_pixel(16, 16, Rgba([255, 217, 112, 255])), 1.0);
+//! let icon = pipeline.render(&base, &SurfaceColor::new(255, 217, 112)).unwrap();
+//! assert_eq!(icon.data().get_pixel(15, 15).0, [0, 0, 0, 255]);
+//! assert_eq!(icon.data().get_pixel(0, 0).0, [255, 217, 112, 255]);
+//! ```
+
+pub use super::dynamic::{DynamicPipeline, LayerId, PipelineLayer};
+pub use super::edges::feather_edges;
+pub use super::placement::composite_at;
+pub use super::svg::{composite_blend, composite_over, render_source, render_svg, render_svg_with_color, render_svg_with_current_color};
+pub use super::{
+    AccentColor, BlendMode, CacheKey, DecalPlacement, DependencyVersion, DerivedPalette, DominantColor, OccupiedRegions,
+    PlacementRounding, RenderContext, RenderWarning, Thumbnail,
+};
+pub use crate::error::RenderError;
+pub use crate::icon::{IconImage, RectPx, SurfaceColor};
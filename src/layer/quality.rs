@@ -0,0 +1,153 @@
+//! Fast, low-resolution renders for live previews.
+//!
+//! Running every layer over a 1024px base on each slider tick is too slow
+//! for an interactive canvas. [`RenderQuality::Preview`] renders a copy of
+//! the base shrunk to at most [`RenderQuality::PREVIEW_MAX_DIMENSION`]
+//! pixels a side, then scales the result back up with nearest-neighbor
+//! sampling. Exports should use [`RenderQuality::Final`].
+//!
+//! Previews keep their own cache entries, keyed by the base they were made
+//! from, so switching between the two qualities doesn't evict either.
+
+use image::imageops::{self, FilterType};
+
+use super::{CacheKey, LayerPipeline};
+use crate::error::RenderError;
+use crate::icon::{IconImage, RectPx, SurfaceColor};
+
+/// How much detail [`LayerPipeline::render_with_quality`] renders.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "jsonschema", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "tsify", derive(tsify_next::Tsify))]
+#[cfg_attr(feature = "tsify", tsify(into_wasm_abi, from_wasm_abi))]
+#[serde(rename_all = "kebab-case")]
+pub enum RenderQuality {
+    /// Renders at the base image's resolution.
+    #[default]
+    Final,
+    /// Renders at no more than
+    /// [`PREVIEW_MAX_DIMENSION`](Self::PREVIEW_MAX_DIMENSION) pixels a
+    /// side, scaled back up to the base's dimensions with nearest-neighbor
+    /// sampling.
+    Preview,
+}
+
+impl RenderQuality {
+    /// The longest side, in pixels, that previews render at.
+    pub const PREVIEW_MAX_DIMENSION: u32 = 256;
+}
+
+impl LayerPipeline {
+    /// Like [`render`](Self::render), at the given `quality`.
+    ///
+    /// A preview has the base's dimensions, scale and content bounds, but
+    /// only the detail of a base at most
+    /// [`RenderQuality::PREVIEW_MAX_DIMENSION`] pixels a side. Bases that
+    /// small already render as with [`RenderQuality::Final`].
+    ///
+    /// ```
+    /// use folco_renderer::{FolderColorTargetConfig, IconImage, LayerPipeline, RenderQuality, SurfaceColor};
+    /// use image::{Rgba, RgbaImage};
+    ///
+    /// let mut pipeline = LayerPipeline::default();
+    /// pipeline.folder_color_target.set_config(Some(FolderColorTargetConfig::new(33, 150, 243)));
+    /// let base = IconImage::new_full_content(RgbaImage::from_pixel(1024, 1024, Rgba([255, 217, 112, 255])), 2.0);
+    ///
+    /// let preview = pipeline.render_with_quality(&base, &SurfaceColor::new(255, 217, 112), RenderQuality::Preview).unwrap();
+    /// assert_eq!(preview.data().dimensions(), (1024, 1024));
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Same as [`render`](Self::render); the base's own dimensions are
+    /// checked against the [`RenderLimits`](super::RenderLimits).
+    pub fn render_with_quality(
+        &mut self,
+        base: &IconImage,
+        surface_color: &SurfaceColor,
+        quality: RenderQuality,
+    ) -> Result<IconImage, RenderError> {
+        let (width, height) = base.data().dimensions();
+        let longest = width.max(height);
+        if quality == RenderQuality::Final || longest <= RenderQuality::PREVIEW_MAX_DIMENSION {
+            return self.render(base, surface_color);
+        }
+        self.limits.check(width, height)?;
+
+        let small = shrink(base, RenderQuality::PREVIEW_MAX_DIMENSION as f32 / longest as f32);
+        let key = CacheKey::from_icon(base).with_quality(quality);
+        let (rendered, _) = self.render_keyed(&small, surface_color, key)?;
+        let data = imageops::resize(rendered.data(), width, height, FilterType::Nearest);
+        Ok(IconImage::new(data, base.scale, base.content_bounds).with_variant(rendered.variant()))
+    }
+}
+
+/// Returns `base` scaled by `factor` (below 1) with nearest-neighbor
+/// sampling, keeping its logical size.
+fn shrink(base: &IconImage, factor: f32) -> IconImage {
+    let (width, height) = base.data().dimensions();
+    let size = |side: u32| ((side as f32 * factor).round() as u32).max(1);
+    let (small_width, small_height) = (size(width), size(height));
+    let data = imageops::resize(base.data(), small_width, small_height, FilterType::Nearest);
+
+    // Content bounds grow to whole pixels, so no content is cut off
+    let bounds = base.content_bounds;
+    let (left, top) = ((bounds.x as f32 * factor) as u32, (bounds.y as f32 * factor) as u32);
+    let right = ((bounds.right() as f32 * factor).ceil() as u32).clamp(left + 1, small_width.max(left + 1));
+    let bottom = ((bounds.bottom() as f32 * factor).ceil() as u32).clamp(top + 1, small_height.max(top + 1));
+    let content_bounds = RectPx::new(left, top, right - left, bottom - top);
+
+    IconImage::new(data, base.scale * factor, content_bounds).with_variant(base.variant())
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layer::DecalConfig;
+    use image::{Rgba, RgbaImage};
+
+    const SQUARE: &str = r##"<svg xmlns="http://www.w3.org/2000/svg" width="10" height="10"><rect width="10" height="10" fill="#000"/></svg>"##;
+
+    #[test]
+    fn previews_render_small_and_keep_the_layout() {
+        let data = RgbaImage::from_pixel(1024, 512, Rgba([255, 217, 112, 255]));
+        let base = IconImage::new(data, 2.0, RectPx::new(100, 50, 824, 412));
+        let surface = SurfaceColor::new(255, 217, 112);
+        let mut pipeline = LayerPipeline::default();
+        pipeline.decal.set_config(Some(DecalConfig::new(SQUARE, 0.5)));
+
+        let small = shrink(&base, 0.25);
+        assert_eq!((small.data().dimensions(), small.logical_size()), ((256, 128), base.logical_size()));
+        assert_eq!(small.content_bounds, RectPx::new(25, 12, 206, 104));
+
+        let preview = pipeline.render_with_quality(&base, &surface, RenderQuality::Preview).unwrap();
+        let full = pipeline.render_with_quality(&base, &surface, RenderQuality::Final).unwrap();
+        assert_eq!((preview.data().dimensions(), preview.content_bounds), (full.data().dimensions(), full.content_bounds));
+        // The decal lands in the same place
+        for (x, y) in [(512, 256), (400, 256), (300, 256)] {
+            assert_eq!(preview.data().get_pixel(x, y), full.data().get_pixel(x, y), "at ({x}, {y})");
+        }
+        assert_eq!(pipeline.cache_stats().sizes, 2);
+    }
+
+    #[test]
+    fn previews_of_bases_shrinking_alike_keep_separate_entries() {
+        let surface = SurfaceColor::new(255, 217, 112);
+        let mut pipeline = LayerPipeline::default();
+        pipeline.decal.set_config(Some(DecalConfig::new(SQUARE, 0.5)));
+        // Both shrink to 256px at 0.5x
+        let large = IconImage::new_full_content(RgbaImage::from_pixel(1024, 1024, Rgba([255, 217, 112, 255])), 2.0);
+        let small = IconImage::new_full_content(RgbaImage::new(512, 512), 1.0);
+
+        pipeline.render_with_quality(&large, &surface, RenderQuality::Preview).unwrap();
+        let preview = pipeline.render_with_quality(&small, &surface, RenderQuality::Preview).unwrap();
+        let mut fresh = pipeline.clone();
+        fresh.invalidate_all();
+        assert_eq!(preview.data(), fresh.render_with_quality(&small, &surface, RenderQuality::Preview).unwrap().data());
+        assert_eq!(pipeline.cache_stats().sizes, 2);
+    }
+}
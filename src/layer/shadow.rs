@@ -0,0 +1,223 @@
+//! Drop shadows — configuration and rendering.
+//!
+//! A [`ShadowConfig`] can be attached to the decal or overlay, shadowing
+//! just that layer's glyph, or used as a standalone layer that shadows the
+//! icon painted so far. Either way the shadow is the alpha of what it
+//! shadows, offset, blurred and filled with a single color, drawn beneath
+//! the original pixels.
+
+use image::RgbaImage;
+
+use super::budget::DegradedLayers;
+use super::edges::gaussian_blur;
+use super::svg::{composite_over, RasterQuality};
+use super::{finite_or, CacheKey, CachedOutput, DependencyVersion, Layer, LayerConfig, LayerKind, LayerVersions, RenderContext};
+use crate::error::RenderError;
+use crate::icon::RectPx;
+
+// ============================================================================
+// ShadowConfig
+// ============================================================================
+
+/// Configuration for a drop shadow — pure data.
+///
+/// Lengths are fractions of the shorter side of the content bounds, so a
+/// shadow keeps its proportions at every icon size.
+///
+/// # Example
+///
+/// ```
+/// use folco_renderer::{DecalConfig, ShadowConfig};
+///
+/// let svg = r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 10 10"><circle cx="5" cy="5" r="4"/></svg>"#;
+/// let decal = DecalConfig::new(svg, 0.5).with_shadow(ShadowConfig::new(0.0, 0.02, 0.04).with_opacity(0.3));
+/// assert!(decal.shadow.is_some());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "jsonschema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct ShadowConfig {
+    /// Horizontal and vertical offset; positive values move the shadow
+    /// right and down.
+    pub offset: (f32, f32),
+
+    /// Blur radius. As in CSS, the Gaussian's standard deviation is half
+    /// the radius; 0 gives a hard-edged shadow.
+    pub blur_radius: f32,
+
+    /// Shadow color as RGB.
+    pub color: (u8, u8, u8),
+
+    /// Shadow opacity (0.0-1.0).
+    pub opacity: f32,
+}
+
+impl ShadowConfig {
+    /// Creates a black shadow at 50% opacity. The blur radius is clamped
+    /// to be non-negative.
+    pub fn new(offset_x: f32, offset_y: f32, blur_radius: f32) -> Self {
+        Self {
+            offset: (finite_or(offset_x, 0.0), finite_or(offset_y, 0.0)),
+            blur_radius: finite_or(blur_radius, 0.0).max(0.0),
+            color: (0, 0, 0),
+            opacity: 0.5,
+        }
+    }
+
+    /// Sets the shadow color.
+    pub fn with_color(mut self, r: u8, g: u8, b: u8) -> Self {
+        self.color = (r, g, b);
+        self
+    }
+
+    /// Sets the opacity. Clamped to 0.0-1.0.
+    pub fn with_opacity(mut self, opacity: f32) -> Self {
+        self.opacity = finite_or(opacity, 1.0).clamp(0.0, 1.0);
+        self
+    }
+}
+
+impl LayerConfig for ShadowConfig {
+    fn differs_from(&self, other: &Self) -> bool {
+        (self.offset.0 - other.offset.0).abs() > 0.0001
+            || (self.offset.1 - other.offset.1).abs() > 0.0001
+            || (self.blur_radius - other.blur_radius).abs() > 0.0001
+            || self.color != other.color
+            || (self.opacity - other.opacity).abs() > 0.0001
+    }
+}
+
+// ============================================================================
+// Layer Rendering
+// ============================================================================
+
+impl Layer<ShadowConfig> {
+    /// Returns the upstream versions this layer's cache depends on.
+    ///
+    /// Shadows the image produced by the color target and gradient tint
+    /// (and the accent either may follow).
+    pub(crate) fn dependencies(&self, versions: &LayerVersions) -> DependencyVersion {
+        DependencyVersion::combine(&[versions.folder_color_target, versions.gradient_tint, versions.accent])
+    }
+
+    /// Draw the shadow beneath the icon, using cache if valid.
+    ///
+    /// Transforms `ctx.image` directly. If inactive, the context passes
+    /// through unchanged. Over the time budget, the blur is skipped and
+    /// the result isn't cached.
+    pub fn apply(&mut self, ctx: &mut RenderContext, key: CacheKey, versions: &LayerVersions) -> Result<(), RenderError> {
+        let deps = self.dependencies(versions);
+        self.apply_with_deps(ctx, key, deps)
+    }
+
+    /// Like [`apply`](Self::apply), with the cache validated against a
+    /// caller-computed dependency version.
+    pub(crate) fn apply_with_deps(
+        &mut self,
+        ctx: &mut RenderContext,
+        key: CacheKey,
+        deps: DependencyVersion,
+    ) -> Result<(), RenderError> {
+        if !self.is_active() {
+            return Ok(());
+        }
+
+        if let Some(CachedOutput::Image(img)) = self.get_cached(key, deps) {
+            ctx.image = img.clone();
+            return Ok(());
+        }
+
+        let bounds = ctx.image.content_bounds;
+        let quality = RasterQuality::of(ctx);
+        drop_shadow(ctx.image.data_mut(), self.config().unwrap(), &bounds, quality);
+        if quality.is_draft() {
+            DegradedLayers::push(ctx, LayerKind::Shadow);
+        } else {
+            self.store(key, CachedOutput::Image(ctx.image.clone()), deps);
+        }
+        Ok(())
+    }
+}
+
+/// Draws `config`'s shadow of `image` beneath it.
+///
+/// `bounds` are the content bounds the shadow's lengths are relative to.
+/// Drafts skip the blur.
+pub(crate) fn drop_shadow(image: &mut RgbaImage, config: &ShadowConfig, bounds: &RectPx, quality: RasterQuality) {
+    // Deserialized configs bypass the builder clamps
+    let opacity = config.opacity.clamp(0.0, 1.0);
+    if opacity == 0.0 {
+        return;
+    }
+
+    let (width, height) = (image.width() as usize, image.height() as usize);
+    let unit = bounds.width.min(bounds.height) as f32;
+    let dx = (config.offset.0 * unit).round() as i64;
+    let dy = (config.offset.1 * unit).round() as i64;
+
+    let mut field = vec![0.0; width * height];
+    for (x, y, pixel) in image.enumerate_pixels() {
+        let (sx, sy) = (x as i64 + dx, y as i64 + dy);
+        if (0..width as i64).contains(&sx) && (0..height as i64).contains(&sy) {
+            field[sy as usize * width + sx as usize] = pixel[3] as f32 / 255.0;
+        }
+    }
+
+    let sigma = config.blur_radius.max(0.0) * unit / 2.0;
+    // Below a third of a pixel the kernel is a single tap
+    if sigma >= 1.0 / 3.0 && !quality.is_draft() {
+        gaussian_blur(&mut field, width, height, sigma);
+    }
+
+    let (r, g, b) = config.color;
+    let mut shadow = RgbaImage::new(image.width(), image.height());
+    for (pixel, coverage) in shadow.pixels_mut().zip(field) {
+        pixel.0 = [r, g, b, (coverage * opacity * 255.0).round() as u8];
+    }
+    composite_over(&mut shadow, image, 0, 0);
+    *image = shadow;
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use image::Rgba;
+
+    use super::*;
+
+    #[test]
+    fn shadow_is_offset_blurred_and_beneath() {
+        let mut image = RgbaImage::new(40, 40);
+        for y in 10..20 {
+            for x in 10..20 {
+                image.put_pixel(x, y, Rgba([255, 0, 0, 255]));
+            }
+        }
+        let bounds = RectPx::new(0, 0, 40, 40);
+        let config = ShadowConfig::new(0.25, 0.25, 0.0).with_color(0, 0, 255).with_opacity(1.0);
+
+        let mut hard = image.clone();
+        drop_shadow(&mut hard, &config, &bounds, RasterQuality::Full);
+        // The original stays on top; the shadow shows where it doesn't cover
+        assert_eq!(hard.get_pixel(15, 15).0, [255, 0, 0, 255]);
+        assert_eq!(hard.get_pixel(25, 25).0, [0, 0, 255, 255]);
+        assert_eq!(hard.get_pixel(30, 30)[3], 0);
+
+        let blurred = config.with_opacity(0.5);
+        let blurred = ShadowConfig { blur_radius: 0.1, ..blurred };
+        let mut soft = image.clone();
+        drop_shadow(&mut soft, &blurred, &bounds, RasterQuality::Full);
+        let edge = soft.get_pixel(30, 25)[3];
+        assert!(edge > 0 && edge < 64, "edge alpha {edge}");
+        assert!(soft.get_pixel(25, 25)[3] <= 128);
+
+        // Drafts skip the blur
+        let mut draft = image;
+        drop_shadow(&mut draft, &blurred, &bounds, RasterQuality::Draft);
+        assert_eq!(draft.get_pixel(30, 25)[3], 0);
+        assert_eq!(draft.get_pixel(25, 25).0, [0, 0, 255, 128]);
+    }
+}
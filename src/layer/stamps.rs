@@ -0,0 +1,192 @@
+//! Sharing parsed and rasterized stamps between renders.
+//!
+//! A pipeline's layer caches hold one output per image size, which only
+//! suits one base icon. When many base icons get the same decal or badge,
+//! the stamps themselves are still identical at each size: the
+//! [`StampCache`] keeps them, keyed by everything that determines their
+//! pixels, so each is parsed and rasterized once per batch.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use image::RgbaImage;
+
+use super::placement::{composite_stamp_at, PlacementRounding};
+use super::svg::{composite_over, RasterQuality, ResolvedSvg, SvgStamp};
+use super::RenderContext;
+use crate::error::RenderError;
+
+/// Everything that determines a stamp's pixels, apart from its offset.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct StampKey {
+    pub(crate) svg: ResolvedSvg,
+    /// The size in device pixels, so @2x stamps are rasterized at 2×.
+    pub(crate) size: u32,
+    /// The color the SVG is drawn in, if any, and whether it only replaces
+    /// `currentColor`.
+    pub(crate) color: Option<((u8, u8, u8, u8), bool)>,
+    pub(crate) quality: RasterQuality,
+}
+
+/// Maximum number of parsed stamps a [`StampCache`] holds.
+const STAMP_CAPACITY: usize = 256;
+
+/// Default byte budget for a [`StampCache`]'s rasterizations.
+const RASTER_CAPACITY: usize = 64 << 20;
+
+/// Parsed stamps and their rasterizations, shared across renders and
+/// threads.
+///
+/// Each map is emptied when it would outgrow its capacity, as the parsed
+/// tree cache is, so a long session drawing many stamps stays bounded.
+pub(crate) struct StampCache {
+    stamps: Mutex<HashMap<StampKey, Arc<SvgStamp>>>,
+    rasters: Mutex<Rasters>,
+    /// Byte budget for the rasterizations.
+    raster_capacity: usize,
+}
+
+/// Rasterizations by stamp and fractional offset bits, with their total
+/// size.
+#[derive(Default)]
+struct Rasters {
+    images: HashMap<(StampKey, u32, u32), Arc<RgbaImage>>,
+    bytes: usize,
+}
+
+impl Default for StampCache {
+    fn default() -> Self {
+        Self::with_raster_capacity(RASTER_CAPACITY)
+    }
+}
+
+impl StampCache {
+    /// Creates a cache holding up to `bytes` of rasterizations.
+    pub(crate) fn with_raster_capacity(bytes: usize) -> Self {
+        Self {
+            stamps: Mutex::default(),
+            rasters: Mutex::default(),
+            raster_capacity: bytes,
+        }
+    }
+
+    /// Returns the number of distinct rasterizations held.
+    pub(crate) fn len(&self) -> usize {
+        self.rasters.lock().unwrap_or_else(|e| e.into_inner()).images.len()
+    }
+
+    /// Returns the number of rasterizations held and their bytes.
+    pub(crate) fn footprint(&self) -> (usize, usize) {
+        let rasters = self.rasters.lock().unwrap_or_else(|e| e.into_inner());
+        (rasters.images.len(), rasters.bytes)
+    }
+
+    /// Drops every stamp and rasterization.
+    pub(crate) fn clear(&self) {
+        self.stamps.lock().unwrap_or_else(|e| e.into_inner()).clear();
+        *self.rasters.lock().unwrap_or_else(|e| e.into_inner()) = Rasters::default();
+    }
+
+    fn stamp(
+        &self,
+        key: &StampKey,
+        build: impl FnOnce() -> Result<SvgStamp, RenderError>,
+    ) -> Result<Arc<SvgStamp>, RenderError> {
+        if let Some(stamp) = self.stamps.lock().unwrap_or_else(|e| e.into_inner()).get(key) {
+            return Ok(Arc::clone(stamp));
+        }
+        // Built unlocked, so threads parsing different SVGs don't wait on
+        // each other; a racing duplicate is dropped
+        let stamp = Arc::new(build()?);
+        let mut stamps = self.stamps.lock().unwrap_or_else(|e| e.into_inner());
+        if stamps.len() >= STAMP_CAPACITY && !stamps.contains_key(key) {
+            stamps.clear();
+        }
+        Ok(Arc::clone(stamps.entry(key.clone()).or_insert(stamp)))
+    }
+
+    fn raster(&self, key: &StampKey, stamp: &SvgStamp, fx: f32, fy: f32) -> Result<Arc<RgbaImage>, RenderError> {
+        let raster_key = (key.clone(), fx.to_bits(), fy.to_bits());
+        if let Some(image) = self.rasters.lock().unwrap_or_else(|e| e.into_inner()).images.get(&raster_key) {
+            return Ok(Arc::clone(image));
+        }
+        let image = Arc::new(stamp.rasterize(fx, fy)?);
+        let mut rasters = self.rasters.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(image) = rasters.images.get(&raster_key) {
+            return Ok(Arc::clone(image));
+        }
+        let len = image.as_raw().len();
+        if rasters.bytes + len > self.raster_capacity {
+            *rasters = Rasters::default();
+        }
+        rasters.bytes += len;
+        Ok(Arc::clone(rasters.images.entry(raster_key).or_insert(image)))
+    }
+}
+
+/// Origin and size of the area a stamp was drawn into.
+type DrawnArea = (i32, i32, u32, u32);
+
+/// Context property pointing stamp layers at a [`StampCache`].
+#[derive(Clone, Default)]
+pub(crate) struct SharedStamps(pub(crate) Arc<StampCache>);
+
+/// Composites the stamp `build` makes onto `tile` at the position `place`
+/// computes from its dimensions, snapped by `rounding`.
+///
+/// With [`SharedStamps`] in `ctx`, the parsed stamp and its rasterization
+/// come from the shared cache when another render already made them.
+/// Returns the stamp and the area drawn into, as
+/// [`composite_stamp_at`] does.
+pub(crate) fn composite_keyed_stamp(
+    tile: &mut RgbaImage,
+    ctx: &RenderContext,
+    key: StampKey,
+    build: impl FnOnce() -> Result<SvgStamp, RenderError>,
+    place: impl FnOnce(u32, u32) -> (f32, f32),
+    rounding: PlacementRounding,
+) -> Result<(Arc<SvgStamp>, DrawnArea), RenderError> {
+    let Some(SharedStamps(cache)) = ctx.get::<SharedStamps>() else {
+        let stamp = build()?;
+        let (width, height) = stamp.dimensions();
+        let drawn = composite_stamp_at(tile, &stamp, place(width, height), rounding)?;
+        return Ok((Arc::new(stamp), drawn));
+    };
+
+    let stamp = cache.stamp(&key, build)?;
+    let (width, height) = stamp.dimensions();
+    let (x, y) = place(width, height);
+    let ((x, fx), (y, fy)) = (rounding.snap(x), rounding.snap(y));
+    let image = cache.raster(&key, &stamp, fx, fy)?;
+    composite_over(tile, &image, x, y);
+    Ok((stamp, (x, y, image.width(), image.height())))
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layer::svg::stamp_svg_with_color_at;
+    use crate::layer::SvgSource;
+
+    #[test]
+    fn rasterizations_stay_within_the_capacity() {
+        let square = r##"<svg xmlns="http://www.w3.org/2000/svg" width="10" height="10"><rect width="10" height="10"/></svg>"##;
+        let svg = SvgSource::from_svg(square).resolve_shared().unwrap();
+        let key = StampKey { svg, size: 8, color: None, quality: RasterQuality::Full };
+        let stamp = stamp_svg_with_color_at(&key.svg, 8, None, key.quality).unwrap();
+
+        // Room for two 9×9 shifted rasterizations
+        let cache = StampCache::with_raster_capacity(2 * 4 * 81);
+        for fx in [0.25, 0.5, 0.25] {
+            cache.raster(&key, &stamp, fx, 0.5).unwrap();
+        }
+        assert_eq!(cache.footprint(), (2, 2 * 4 * 81));
+        // A third clears the cache first
+        cache.raster(&key, &stamp, 0.75, 0.5).unwrap();
+        assert_eq!(cache.footprint(), (1, 4 * 81));
+    }
+}
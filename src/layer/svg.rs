@@ -3,12 +3,33 @@
 //! This module provides shared SVG parsing and rendering functionality
 //! used by both the decal and overlay layers.
 
-use image::{Rgba, RgbaImage};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fmt;
+#[cfg(not(target_arch = "wasm32"))]
+use std::fs;
+#[cfg(not(target_arch = "wasm32"))]
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::io::Cursor;
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::{Path, PathBuf};
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::SystemTime;
+use std::ops::Deref;
+use std::sync::{Arc, Mutex, OnceLock, PoisonError};
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use image::{ImageFormat, ImageReader, Rgba, RgbaImage};
 use resvg::tiny_skia::{Pixmap, Transform};
-use resvg::usvg::{Options, Tree};
+use resvg::usvg::{roxmltree, Options, ShapeRendering, TextRendering, Tree, WriteOptions};
 
+use crate::asset::{AssetData, AssetRegistry};
 use crate::error::RenderError;
 
+use super::budget::OverBudget;
+use super::{RenderContext, RenderLimits};
+
 // ============================================================================
 // SvgSource
 // ============================================================================
@@ -18,6 +39,9 @@ use crate::error::RenderError;
 /// This enum allows layers to accept SVG content from multiple sources:
 /// - Raw SVG markup strings
 /// - Emoji characters (when the `twemoji` feature is enabled)
+/// - Assets shared through the [`AssetRegistry`]
+/// - PNG or JPEG images, for logos without a vector version
+/// - SVG files on disk
 ///
 /// # Example
 ///
@@ -31,7 +55,7 @@ use crate::error::RenderError;
 /// #[cfg(feature = "twemoji")]
 /// let emoji = SvgSource::from_emoji("🦆").unwrap();
 /// ```
-#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
 #[cfg_attr(feature = "jsonschema", derive(schemars::JsonSchema))]
 #[serde(rename_all = "camelCase")]
 pub enum SvgSource {
@@ -49,6 +73,34 @@ pub enum SvgSource {
     /// Only available when the `twemoji` feature is enabled with the `names` feature.
     /// At render time, this is resolved to the corresponding Twemoji SVG.
     EmojiName(String),
+
+    /// The id of an SVG asset in the global [`AssetRegistry`].
+    ///
+    /// Resolved at render time, so the asset must stay registered while
+    /// layers using it render.
+    AssetId(String),
+
+    /// A base64-encoded PNG or JPEG image, for logos that only exist as
+    /// pixels.
+    ///
+    /// The image is wrapped in an SVG of its pixel size, so it scales and
+    /// positions like any other source; resvg resamples it smoothly.
+    /// Decal recoloring leaves its pixels alone.
+    Raster(String),
+
+    /// An SVG file on disk, read at render time.
+    ///
+    /// The markup is cached until the file's modification time changes,
+    /// so profiles can reference large assets without embedding them.
+    /// Layers only pick up an edited file when they next re-render. Not
+    /// available on WASM, which has no file system.
+    ///
+    /// [`CustomizationProfile`](crate::CustomizationProfile) loaders reject
+    /// file sources unless [`ParseOptions::allow_files`](crate::ParseOptions::allow_files)
+    /// is set, so a profile from an untrusted origin can't read arbitrary
+    /// paths.
+    #[cfg(not(target_arch = "wasm32"))]
+    File(PathBuf),
 }
 
 /// Looks up an emoji in `twemoji_assets`, falling back to a version
@@ -78,6 +130,43 @@ impl SvgSource {
         Self::Raw(svg.into())
     }
 
+    /// Creates a source referencing an asset in the global [`AssetRegistry`].
+    pub fn asset(id: impl Into<String>) -> Self {
+        Self::AssetId(id.into())
+    }
+
+    /// Creates a source reading the SVG file at `path` at render time.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn file(path: impl Into<PathBuf>) -> Self {
+        Self::File(path.into())
+    }
+
+    /// Creates a source from the contents of a PNG or JPEG file.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`RenderError::InvalidRaster`] if the data is not a PNG or
+    /// JPEG image, or [`RenderError::ImageTooLarge`] if its header claims a
+    /// size beyond the default [`RenderLimits`].
+    pub fn from_image_bytes(data: &[u8]) -> Result<Self, RenderError> {
+        raster_header(data)?;
+        Ok(Self::Raster(STANDARD.encode(data)))
+    }
+
+    /// Creates a source from decoded pixels, stored as PNG.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`RenderError::InvalidRaster`] if the image cannot be
+    /// encoded, e.g. because it is empty.
+    pub fn from_raster(image: &RgbaImage) -> Result<Self, RenderError> {
+        let mut png = Vec::new();
+        image
+            .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+            .map_err(|e| RenderError::InvalidRaster { reason: e.to_string() })?;
+        Ok(Self::Raster(STANDARD.encode(png)))
+    }
+
     /// Creates a source from an emoji character.
     ///
     /// Returns an error if the emoji is not supported by twemoji_assets.
@@ -113,15 +202,35 @@ impl SvgSource {
     ///
     /// For `Raw` sources, returns the SVG string directly.
     /// For `Emoji` sources, looks up the emoji in twemoji_assets.
+    /// For `AssetId` sources, copies the markup out of the registry; use
+    /// [`resolve_shared`](Self::resolve_shared) to share it instead. PNG
+    /// assets resolve as `Raster` sources do.
+    /// For `Raster` sources, wraps the image in an SVG of its pixel size.
     ///
     /// # Errors
     ///
     /// Returns an error if:
     /// - An emoji character or name cannot be resolved.
     /// - An emoji source is used without the `twemoji` feature enabled.
-    pub fn resolve(&self) -> Result<&str, RenderError> {
+    /// - An asset id is not registered.
+    /// - Raster data is not a base64-encoded PNG or JPEG image, or exceeds
+    ///   the default [`RenderLimits`].
+    /// - A file cannot be read or is not UTF-8.
+    pub fn resolve(&self) -> Result<Cow<'_, str>, RenderError> {
         match self {
-            Self::Raw(svg) => Ok(svg.as_str()),
+            Self::Raw(svg) => Ok(Cow::Borrowed(svg.as_str())),
+            Self::AssetId(_) => Ok(Cow::Owned(self.resolve_shared()?.to_string())),
+            #[cfg(not(target_arch = "wasm32"))]
+            Self::File(_) => Ok(Cow::Owned(self.resolve_shared()?.to_string())),
+            Self::Raster(data) => {
+                let bytes = STANDARD
+                    .decode(data)
+                    .map_err(|e| RenderError::InvalidRaster { reason: e.to_string() })?;
+                let (mime, width, height) = raster_header(&bytes)?;
+                Ok(Cow::Owned(format!(
+                    r#"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="{height}"><image width="{width}" height="{height}" href="data:{mime};base64,{data}"/></svg>"#
+                )))
+            }
             #[cfg(feature = "twemoji")]
             Self::Emoji(emoji) => {
                 let asset = resolve_twemoji(emoji).ok_or_else(|| {
@@ -129,7 +238,7 @@ impl SvgSource {
                         emoji: emoji.clone(),
                     }
                 })?;
-                Ok(asset.as_ref())
+                Ok(Cow::Borrowed(asset.as_ref()))
             }
             #[cfg(not(feature = "twemoji"))]
             Self::Emoji(_) => Err(RenderError::TwemojiNotAvailable),
@@ -141,13 +250,89 @@ impl SvgSource {
                         name: name.clone(),
                     }
                 })?;
-                Ok(asset.as_ref())
+                Ok(Cow::Borrowed(asset.as_ref()))
             }
             #[cfg(not(feature = "twemoji"))]
             Self::EmojiName(_) => Err(RenderError::TwemojiNotAvailable),
         }
     }
 
+    /// Resolves this source to shared, owned SVG markup.
+    ///
+    /// Results are memoized in a process-wide cache shared by all layers
+    /// and threads, so repeated renders of the same source (at each icon
+    /// size, or from cloned pipelines) skip the emoji lookup and copy.
+    /// The cache holds at most 256 sources and is cleared when full.
+    /// `AssetId` sources bypass it and share the registry's copy directly;
+    /// `File` sources keep their own cache, refreshed when the file changes.
+    ///
+    /// # Errors
+    ///
+    /// Same as [`resolve`](Self::resolve).
+    pub fn resolve_shared(&self) -> Result<ResolvedSvg, RenderError> {
+        match self {
+            Self::AssetId(id) => {
+                return match AssetRegistry::global().get(id) {
+                    Some(AssetData::Svg(svg)) => Ok(svg),
+                    Some(AssetData::Png(png)) => Self::Raster(STANDARD.encode(png)).resolve_shared(),
+                    None => Err(RenderError::UnknownAsset { id: id.clone() }),
+                };
+            }
+            #[cfg(not(target_arch = "wasm32"))]
+            Self::File(path) => return read_svg_file(path),
+            _ => {}
+        }
+
+        static CACHE: OnceLock<Mutex<HashMap<SvgSource, ResolvedSvg>>> = OnceLock::new();
+
+        // A panic while holding the lock cannot leave the map inconsistent
+        let mut cache = CACHE
+            .get_or_init(Default::default)
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        if let Some(resolved) = cache.get(self) {
+            return Ok(resolved.clone());
+        }
+
+        let resolved = ResolvedSvg(Arc::from(self.resolve()?));
+        if cache.len() >= RESOLVED_CACHE_CAPACITY {
+            cache.clear();
+        }
+        cache.insert(self.clone(), resolved.clone());
+        Ok(resolved)
+    }
+
+    /// Returns a fingerprint of the content behind a source that doesn't
+    /// hold its content, for [`LayerConfig::external_version`](super::LayerConfig::external_version).
+    ///
+    /// `AssetId` sources hash the registered asset, or return 0 while it
+    /// is unregistered. `File` sources hash the file's modification time
+    /// and length, or return 0 while it can't be read. Other sources
+    /// return 0.
+    pub(crate) fn content_version(&self) -> u64 {
+        match self {
+            Self::AssetId(id) => AssetRegistry::global().content_hash(id).unwrap_or(0),
+            #[cfg(not(target_arch = "wasm32"))]
+            Self::File(path) => fs::metadata(path).and_then(|m| Ok((m.modified()?, m.len()))).map_or(0, |stamp| {
+                let mut hasher = DefaultHasher::new();
+                stamp.hash(&mut hasher);
+                hasher.finish()
+            }),
+            _ => 0,
+        }
+    }
+
+    /// Returns `true` if the source references content held elsewhere,
+    /// which can change while the source stays the same.
+    pub(crate) fn is_external(&self) -> bool {
+        match self {
+            Self::AssetId(_) => true,
+            #[cfg(not(target_arch = "wasm32"))]
+            Self::File(_) => true,
+            _ => false,
+        }
+    }
+
     /// Returns `true` if this is an emoji source.
     pub fn is_emoji(&self) -> bool {
         matches!(self, Self::Emoji(_))
@@ -162,6 +347,67 @@ impl SvgSource {
     pub fn is_raw(&self) -> bool {
         matches!(self, Self::Raw(_))
     }
+
+    /// Returns `true` if this is an asset registry source.
+    pub fn is_asset(&self) -> bool {
+        matches!(self, Self::AssetId(_))
+    }
+
+    /// Returns `true` if this is a raster image source.
+    pub fn is_raster(&self) -> bool {
+        matches!(self, Self::Raster(_))
+    }
+
+    /// Returns `true` if this is a file source.
+    pub fn is_file(&self) -> bool {
+        #[cfg(not(target_arch = "wasm32"))]
+        return matches!(self, Self::File(_));
+        #[cfg(target_arch = "wasm32")]
+        false
+    }
+}
+
+/// Reads an SVG file, reusing the markup read last time if the file's
+/// modification time hasn't changed since.
+#[cfg(not(target_arch = "wasm32"))]
+fn read_svg_file(path: &Path) -> Result<ResolvedSvg, RenderError> {
+    static CACHE: OnceLock<Mutex<HashMap<PathBuf, (SystemTime, ResolvedSvg)>>> = OnceLock::new();
+
+    let failed = |source| RenderError::SvgFile { path: path.to_path_buf(), source };
+    let modified = fs::metadata(path).and_then(|m| m.modified()).map_err(failed)?;
+    let cache = CACHE.get_or_init(Default::default);
+    if let Some((cached_at, svg)) = cache.lock().unwrap_or_else(PoisonError::into_inner).get(path)
+        && *cached_at == modified
+    {
+        return Ok(svg.clone());
+    }
+
+    let svg = ResolvedSvg::from(fs::read_to_string(path).map_err(failed)?.as_str());
+    let mut cache = cache.lock().unwrap_or_else(PoisonError::into_inner);
+    if cache.len() >= RESOLVED_CACHE_CAPACITY {
+        cache.clear();
+    }
+    cache.insert(path.to_path_buf(), (modified, svg.clone()));
+    Ok(svg)
+}
+
+/// Returns the MIME type and pixel size of PNG or JPEG data.
+///
+/// The size is read from the header and checked against the default
+/// [`RenderLimits`] so oversized images are rejected before usvg decodes them.
+fn raster_header(data: &[u8]) -> Result<(&'static str, u32, u32), RenderError> {
+    let invalid = |reason: String| RenderError::InvalidRaster { reason };
+    let format = image::guess_format(data).map_err(|e| invalid(e.to_string()))?;
+    let mime = match format {
+        ImageFormat::Png => "image/png",
+        ImageFormat::Jpeg => "image/jpeg",
+        other => return Err(invalid(format!("unsupported format {other:?}, expected PNG or JPEG"))),
+    };
+    let (width, height) = ImageReader::with_format(Cursor::new(data), format)
+        .into_dimensions()
+        .map_err(|e| invalid(e.to_string()))?;
+    RenderLimits::default().check(width, height)?;
+    Ok((mime, width, height))
 }
 
 impl<S: Into<String>> From<S> for SvgSource {
@@ -170,10 +416,168 @@ impl<S: Into<String>> From<S> for SvgSource {
     }
 }
 
+// ============================================================================
+// ResolvedSvg
+// ============================================================================
+
+/// Maximum number of sources memoized by [`SvgSource::resolve_shared`].
+const RESOLVED_CACHE_CAPACITY: usize = 256;
+
+/// SVG markup resolved from an [`SvgSource`], cheap to clone and share
+/// across threads.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ResolvedSvg(Arc<str>);
+
+impl Deref for ResolvedSvg {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl AsRef<str> for ResolvedSvg {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<&str> for ResolvedSvg {
+    fn from(svg: &str) -> Self {
+        Self(Arc::from(svg))
+    }
+}
+
+impl fmt::Display for ResolvedSvg {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+// ============================================================================
+// ParsedSvg
+// ============================================================================
+
+/// Summary of a parsed SVG document, for inspecting user-supplied SVGs.
+///
+/// This is a stable, minimal view over the crate's SVG engine: hosts can
+/// validate and inspect SVGs without depending on (and version-matching)
+/// `resvg` themselves.
+///
+/// # Example
+///
+/// ```
+/// use folco_renderer::ParsedSvg;
+///
+/// let svg = ParsedSvg::parse(r#"<svg xmlns="http://www.w3.org/2000/svg" width="24" height="12"><text>A</text></svg>"#).unwrap();
+/// assert_eq!(svg.size(), (24.0, 12.0));
+/// assert!(svg.has_text());
+/// assert!(!svg.has_images());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ParsedSvg {
+    width: f32,
+    height: f32,
+    node_count: usize,
+    has_text: bool,
+    has_images: bool,
+}
+
+impl ParsedSvg {
+    /// Parses SVG markup with the same engine and options used for rendering.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`RenderError::SvgParse`] if the markup would fail to render.
+    pub fn parse(svg_data: &str) -> Result<Self, RenderError> {
+        let xml_opt = roxmltree::ParsingOptions {
+            allow_dtd: true,
+            ..Default::default()
+        };
+        let doc = roxmltree::Document::parse_with_options(svg_data, xml_opt)
+            .map_err(resvg::usvg::Error::ParsingFailed)?;
+        let tree = Tree::from_xmltree(&doc, &Options::default())?;
+
+        // Inspect the source document: the render tree drops text without
+        // fonts and images it cannot resolve, which is what hosts want to know
+        let elements = || doc.descendants().filter(|n| n.is_element());
+        let size = tree.size();
+        Ok(Self {
+            width: size.width(),
+            height: size.height(),
+            node_count: elements().count(),
+            has_text: elements().any(|n| matches!(n.tag_name().name(), "text" | "tspan" | "textPath")),
+            has_images: elements().any(|n| matches!(n.tag_name().name(), "image" | "feImage")),
+        })
+    }
+
+    /// Resolves and parses an [`SvgSource`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the source cannot be resolved or parsed.
+    pub fn from_source(source: &SvgSource) -> Result<Self, RenderError> {
+        Self::parse(&source.resolve_shared()?)
+    }
+
+    /// Returns the document's intrinsic `(width, height)` in user units.
+    pub fn size(&self) -> (f32, f32) {
+        (self.width, self.height)
+    }
+
+    /// Returns the number of elements in the document, including the root.
+    pub fn node_count(&self) -> usize {
+        self.node_count
+    }
+
+    /// Returns true if the document contains text elements.
+    ///
+    /// Text renders only with fonts available to the renderer, so icons
+    /// containing text may render incompletely.
+    pub fn has_text(&self) -> bool {
+        self.has_text
+    }
+
+    /// Returns true if the document contains raster or nested image elements.
+    pub fn has_images(&self) -> bool {
+        self.has_images
+    }
+}
+
 // ============================================================================
 // SVG Rendering
 // ============================================================================
 
+/// How carefully SVG layers rasterize.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub(crate) enum RasterQuality {
+    /// Anti-aliased output.
+    #[default]
+    Full,
+    /// Aliased output for renders over their time budget.
+    Draft,
+}
+
+impl RasterQuality {
+    /// Returns the quality a layer rendering into `ctx` should use.
+    pub(crate) fn of(ctx: &RenderContext) -> Self {
+        if ctx.has::<OverBudget>() { Self::Draft } else { Self::Full }
+    }
+
+    /// Returns true for [`Draft`](Self::Draft).
+    pub(crate) fn is_draft(self) -> bool {
+        self == Self::Draft
+    }
+
+    /// Adjusts usvg options for this quality.
+    pub(crate) fn configure(self, opts: &mut Options) {
+        if self.is_draft() {
+            opts.shape_rendering = ShapeRendering::OptimizeSpeed;
+            opts.text_rendering = TextRendering::OptimizeSpeed;
+        }
+    }
+}
+
 /// Renders an SVG string to an RGBA image at the specified size.
 ///
 /// The SVG is scaled to fit within `size x size` pixels while preserving
@@ -190,7 +594,8 @@ pub fn render_svg(svg_data: &str, size: u32) -> Result<RgbaImage, RenderError> {
 /// Renders an SVG string to an RGBA image, optionally replacing all colors.
 ///
 /// If `fill_color` is provided, all fills and strokes in the SVG are replaced
-/// with this color. This is useful for monochrome icon decals.
+/// with this color, as [`recolor_tree`] does. This is useful for monochrome
+/// icon decals.
 ///
 /// # Errors
 ///
@@ -201,30 +606,154 @@ pub fn render_svg_with_color(
     size: u32,
     fill_color: Option<(u8, u8, u8, u8)>,
 ) -> Result<RgbaImage, RenderError> {
-    // Apply color replacement if needed
-    let svg_data = if let Some((r, g, b, _a)) = fill_color {
-        replace_svg_colors(svg_data, r, g, b)
-    } else {
-        svg_data.to_string()
+    stamp_svg_with_color_at(svg_data, size, fill_color, RasterQuality::Full)?.rasterize(0.0, 0.0)
+}
+
+/// Like [`render_svg_with_color`], at the given quality, returning the
+/// stamp to rasterize.
+pub(crate) fn stamp_svg_with_color_at(
+    svg_data: &str,
+    size: u32,
+    fill_color: Option<(u8, u8, u8, u8)>,
+    quality: RasterQuality,
+) -> Result<SvgStamp, RenderError> {
+    let Some((r, g, b, _a)) = fill_color else {
+        return SvgStamp::fit(svg_data, size, None, quality);
     };
+    // Recolored through the parsed tree, then memoized like any markup
+    let tree = parse_tree_cached(svg_data, None, quality)?;
+    SvgStamp::fit(&recolored_markup(&tree, r, g, b)?, size, None, quality)
+}
+
+/// Renders an SVG string with `currentColor` resolving to the given color.
+///
+/// Unlike [`render_svg_with_color`], the markup is left untouched: only
+/// paints declared as `currentColor` pick up the color, so `none` fills,
+/// stroke structure and any deliberately fixed colors are preserved. This
+/// is the CSS convention followed by well-authored monochrome icon sets.
+///
+/// # Errors
+///
+/// Returns an error if the SVG cannot be parsed or the pixel buffer
+/// cannot be allocated.
+pub fn render_svg_with_current_color(
+    svg_data: &str,
+    size: u32,
+    color: (u8, u8, u8, u8),
+) -> Result<RgbaImage, RenderError> {
+    stamp_svg_with_current_color_at(svg_data, size, color, RasterQuality::Full)?.rasterize(0.0, 0.0)
+}
+
+/// Like [`render_svg_with_current_color`], at the given quality, returning
+/// the stamp to rasterize.
+pub(crate) fn stamp_svg_with_current_color_at(
+    svg_data: &str,
+    size: u32,
+    color: (u8, u8, u8, u8),
+    quality: RasterQuality,
+) -> Result<SvgStamp, RenderError> {
+    let (r, g, b, _a) = color;
+    // User style sheets override presentation attributes, and `color`
+    // inherits from the root to every `currentColor` reference
+    let style_sheet = format!("svg {{ color: #{r:02x}{g:02x}{b:02x} }}");
+    SvgStamp::fit(svg_data, size, Some(style_sheet), quality)
+}
 
-    // Parse the SVG
-    let opts = Options::default();
-    let tree = Tree::from_str(&svg_data, &opts)?;
+/// Returns true if the SVG paints anything with `currentColor`.
+pub fn uses_current_color(svg_data: &str) -> bool {
+    svg_data.contains("currentColor")
+}
+
+/// A parsed SVG scaled to a pixel size, not yet rasterized.
+///
+/// Its [`dimensions`](Self::dimensions) are known up front, so layers can
+/// work out where it goes and then rasterize it with the fractional part
+/// of the position baked into the transform: sharper than resampling the
+/// rasterized image, and free of jitter when the position animates.
+pub(crate) struct SvgStamp {
+    tree: Arc<Tree>,
+    scale: f32,
+    width: u32,
+    height: u32,
+}
+
+impl SvgStamp {
+    /// Parses an SVG scaled to fit within `size x size` pixels, reusing the
+    /// tree of an earlier parse of the same markup.
+    fn fit(svg_data: &str, size: u32, style_sheet: Option<String>, quality: RasterQuality) -> Result<Self, RenderError> {
+        let tree = parse_tree_cached(svg_data, style_sheet, quality)?;
+        let svg_size = tree.size();
+        let scale = (size as f32) / svg_size.width().max(svg_size.height());
+        Ok(Self::new(tree, scale))
+    }
+
+    /// Wraps a tree drawn at `scale`.
+    pub(crate) fn new(tree: impl Into<Arc<Tree>>, scale: f32) -> Self {
+        let tree = tree.into();
+        let svg_size = tree.size();
+        let width = (svg_size.width() * scale).ceil() as u32;
+        let height = (svg_size.height() * scale).ceil() as u32;
+        Self { tree, scale, width, height }
+    }
 
-    // Calculate scale to fit within size x size
-    let svg_size = tree.size();
-    let scale = (size as f32) / svg_size.width().max(svg_size.height());
-    let width = (svg_size.width() * scale).ceil() as u32;
-    let height = (svg_size.height() * scale).ceil() as u32;
+    /// Returns true if the tree has nothing to draw.
+    pub(crate) fn is_empty(&self) -> bool {
+        !self.tree.root().has_children()
+    }
 
-    // Create pixmap and render
-    let mut pixmap = Pixmap::new(width, height).ok_or(RenderError::PixmapCreation { width, height })?;
-    let transform = Transform::from_scale(scale, scale);
-    resvg::render(&tree, transform, &mut pixmap.as_mut());
+    /// Returns the pixel size the stamp rasterizes to without an offset.
+    pub(crate) fn dimensions(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
 
-    // Convert to RgbaImage
-    Ok(pixmap_to_rgba_image(&pixmap))
+    /// Rasterizes the stamp moved right by `fx` and down by `fy` pixels
+    /// (each 0.0-1.0). The image grows by a pixel along each axis with a
+    /// nonzero offset.
+    pub(crate) fn rasterize(&self, fx: f32, fy: f32) -> Result<RgbaImage, RenderError> {
+        let width = self.width + (fx > 0.0) as u32;
+        let height = self.height + (fy > 0.0) as u32;
+        let mut pixmap = Pixmap::new(width, height).ok_or(RenderError::PixmapCreation { width, height })?;
+        let transform = Transform::from_scale(self.scale, self.scale).post_translate(fx, fy);
+        resvg::render(&self.tree, transform, &mut pixmap.as_mut());
+        Ok(pixmap_to_rgba_image(&pixmap))
+    }
+}
+
+/// Maximum number of trees memoized by [`parse_tree_cached`].
+const TREE_CACHE_CAPACITY: usize = 128;
+
+/// What a parsed tree depends on: the markup, the user style sheet and the
+/// quality options.
+type TreeKey = (Arc<str>, Option<String>, RasterQuality);
+
+/// Parses `svg_data`, memoizing the tree by content.
+///
+/// A tree doesn't depend on the size it's drawn at, so every size of an
+/// icon, and every render after a change that leaves the markup alone
+/// (a hue shift under an uncolored overlay, say), reuses one parse.
+fn parse_tree_cached(svg_data: &str, style_sheet: Option<String>, quality: RasterQuality) -> Result<Arc<Tree>, RenderError> {
+    static CACHE: OnceLock<Mutex<HashMap<TreeKey, Arc<Tree>>>> = OnceLock::new();
+
+    let key: TreeKey = (Arc::from(svg_data), style_sheet, quality);
+    let cache = CACHE.get_or_init(Default::default);
+    if let Some(tree) = cache.lock().unwrap_or_else(PoisonError::into_inner).get(&key) {
+        return Ok(Arc::clone(tree));
+    }
+
+    // Parsed unlocked, so threads parsing different SVGs don't wait on
+    // each other
+    let mut opts = Options {
+        style_sheet: key.1.clone(),
+        ..Options::default()
+    };
+    quality.configure(&mut opts);
+    let tree = Arc::new(Tree::from_str(svg_data, &opts)?);
+
+    let mut cache = cache.lock().unwrap_or_else(PoisonError::into_inner);
+    if cache.len() >= TREE_CACHE_CAPACITY {
+        cache.clear();
+    }
+    Ok(Arc::clone(cache.entry(key).or_insert(tree)))
 }
 
 /// Renders an [`SvgSource`] to an RGBA image at the specified size.
@@ -235,80 +764,82 @@ pub fn render_svg_with_color(
 ///
 /// Returns an error if the source cannot be resolved or the SVG cannot be parsed.
 pub fn render_source(source: &SvgSource, size: u32) -> Result<RgbaImage, RenderError> {
-    let svg_data = source.resolve()?;
-    render_svg(svg_data, size)
+    stamp_source_at(source, size, RasterQuality::Full)?.rasterize(0.0, 0.0)
+}
+
+/// Like [`render_source`], at the given quality, returning the stamp to
+/// rasterize.
+pub(crate) fn stamp_source_at(source: &SvgSource, size: u32, quality: RasterQuality) -> Result<SvgStamp, RenderError> {
+    let svg_data = source.resolve_shared()?;
+    stamp_svg_with_color_at(&svg_data, size, None, quality)
 }
 
-/// Renders an [`SvgSource`] to an RGBA image, optionally replacing all colors.
+/// Returns a copy of `tree` with every fill and stroke painted in the given
+/// RGB color, for drawing monochrome icons in one color.
+///
+/// The paints are taken from the parsed tree, so colors set through
+/// `style` attributes, CSS classes or single-quoted attributes are all
+/// replaced. `none` paints stay unpainted and mask contents keep their
+/// colors, so cutouts and masks are preserved. Gradients and patterns are
+/// replaced by the flat color.
 ///
-/// This is a convenience wrapper around [`render_svg_with_color`] that handles source resolution.
+/// ```
+/// use folco_renderer::layer::svg::recolor_tree;
+/// use resvg::usvg::{Options, Paint, Tree};
+///
+/// let svg = r#"<svg xmlns="http://www.w3.org/2000/svg" width="10" height="10"><rect width="10" height="10" style="fill:#000"/></svg>"#;
+/// let tree = recolor_tree(&Tree::from_str(svg, &Options::default()).unwrap(), 255, 0, 0).unwrap();
+/// let resvg::usvg::Node::Path(rect) = &tree.root().children()[0] else { unreachable!() };
+/// assert!(matches!(rect.fill().unwrap().paint(), Paint::Color(c) if (c.red, c.green, c.blue) == (255, 0, 0)));
+/// ```
 ///
 /// # Errors
 ///
-/// Returns an error if the source cannot be resolved or the SVG cannot be parsed.
-pub fn render_source_with_color(
-    source: &SvgSource,
-    size: u32,
-    fill_color: Option<(u8, u8, u8, u8)>,
-) -> Result<RgbaImage, RenderError> {
-    let svg_data = source.resolve()?;
-    render_svg_with_color(svg_data, size, fill_color)
+/// Returns [`RenderError::SvgParse`] if the recolored tree fails to parse
+/// back, which would be a bug in the SVG writer.
+pub fn recolor_tree(tree: &Tree, r: u8, g: u8, b: u8) -> Result<Tree, RenderError> {
+    Ok(Tree::from_str(&recolored_markup(tree, r, g, b)?, &Options::default())?)
 }
 
-/// Replaces common color attributes in SVG with the specified RGB color.
-///
-/// This is a simple text-based replacement that handles common cases:
-/// - `fill="..."` attributes
-/// - `stroke="..."` attributes
-/// - `style="..."` attributes containing fill/stroke
+/// Replaces the fill and stroke colors in SVG markup with the specified RGB
+/// color, leaving `none` paints alone.
 ///
-/// For complex SVGs, consider using a proper SVG manipulation library.
-fn replace_svg_colors(svg_data: &str, r: u8, g: u8, b: u8) -> String {
-    let hex_color = format!("#{:02x}{:02x}{:02x}", r, g, b);
-
-    // Replace fill and stroke attributes
-    // This is a simple approach; for production, consider using an XML parser
-    let mut result = svg_data.to_string();
-
-    // Replace fill="..." (but not fill="none")
-    result = replace_color_attr(&result, "fill", &hex_color);
-    // Replace stroke="..." (but not stroke="none")
-    result = replace_color_attr(&result, "stroke", &hex_color);
-
-    result
+/// Markup that fails to parse is returned unchanged.
+#[deprecated(note = "use `recolor_tree`, which recolors the parsed tree including styles and classes")]
+pub fn replace_svg_colors(svg_data: &str, r: u8, g: u8, b: u8) -> String {
+    Tree::from_str(svg_data, &Options::default())
+        .map_err(RenderError::from)
+        .and_then(|tree| recolored_markup(&tree, r, g, b))
+        .unwrap_or_else(|_| svg_data.to_string())
 }
 
-/// Replaces a color attribute value, preserving "none" values.
-fn replace_color_attr(svg: &str, attr: &str, new_color: &str) -> String {
-    let mut result = String::with_capacity(svg.len());
-    let pattern = format!("{}=\"", attr);
-    let mut remaining = svg;
-
-    while let Some(start) = remaining.find(&pattern) {
-        // Copy everything up to and including the attribute name and ="
-        result.push_str(&remaining[..start + pattern.len()]);
-        remaining = &remaining[start + pattern.len()..];
-
-        // Find the closing quote
-        if let Some(end) = remaining.find('"') {
-            let value = &remaining[..end];
-            // Preserve "none" and "transparent", replace everything else
-            if value == "none" || value == "transparent" {
-                result.push_str(value);
-            } else {
-                result.push_str(new_color);
-            }
-            remaining = &remaining[end..];
-        }
+/// Writes `tree` back to SVG with its fills and strokes in one color, as
+/// [`recolor_tree`] does.
+fn recolored_markup(tree: &Tree, r: u8, g: u8, b: u8) -> Result<String, RenderError> {
+    // The writer resolves styles and classes into plain attributes and
+    // spells out every paint, `none` included
+    let mut svg = tree.to_string(&WriteOptions::default());
+    let doc = roxmltree::Document::parse(&svg).map_err(resvg::usvg::Error::ParsingFailed)?;
+    let in_mask = |node: roxmltree::Node| node.ancestors().any(|n| n.has_tag_name("mask"));
+    let mut paints: Vec<_> = doc
+        .descendants()
+        .filter(|node| node.is_element() && !in_mask(*node))
+        .flat_map(|node| node.attributes())
+        .filter(|attr| matches!(attr.name(), "fill" | "stroke") && attr.value() != "none")
+        .map(|attr| attr.range_value())
+        .collect();
+
+    // From the end, so earlier ranges stay valid
+    paints.sort_by_key(|range| std::cmp::Reverse(range.start));
+    let hex_color = format!("#{r:02x}{g:02x}{b:02x}");
+    for range in paints {
+        svg.replace_range(range, &hex_color);
     }
-
-    // Append any remaining content
-    result.push_str(remaining);
-    result
+    Ok(svg)
 }
 
 /// Converts a tiny_skia Pixmap to an image::RgbaImage.
-fn pixmap_to_rgba_image(pixmap: &Pixmap) -> RgbaImage {
+pub(crate) fn pixmap_to_rgba_image(pixmap: &Pixmap) -> RgbaImage {
     let width = pixmap.width();
     let height = pixmap.height();
     let mut img = RgbaImage::new(width, height);
@@ -344,10 +875,106 @@ fn unpremultiply(r: u8, g: u8, b: u8, a: u8) -> (u8, u8, u8, u8) {
 // Compositing
 // ============================================================================
 
+/// How a layer's colors combine with the colors beneath it.
+///
+/// Formulas follow the W3C Compositing and Blending spec, applied per
+/// channel to non-premultiplied sRGB values.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "jsonschema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "kebab-case")]
+pub enum BlendMode {
+    /// The layer's color replaces the backdrop.
+    #[default]
+    Normal,
+    /// Darkens: backdrop × source.
+    Multiply,
+    /// Lightens: the inverse of multiplying the inverses.
+    Screen,
+    /// Multiplies dark backdrops and screens light ones, keeping contrast.
+    Overlay,
+    /// A gentler overlay that never reaches pure black or white.
+    SoftLight,
+}
+
+impl BlendMode {
+    /// Returns true for [`BlendMode::Normal`].
+    pub fn is_normal(&self) -> bool {
+        *self == Self::Normal
+    }
+
+    /// Returns the CSS `mix-blend-mode` keyword for this mode.
+    pub(crate) fn css_name(self) -> &'static str {
+        match self {
+            Self::Normal => "normal",
+            Self::Multiply => "multiply",
+            Self::Screen => "screen",
+            Self::Overlay => "overlay",
+            Self::SoftLight => "soft-light",
+        }
+    }
+
+    /// Blends one channel, with `backdrop` and `source` in 0.0–1.0.
+    pub fn blend(self, backdrop: f32, source: f32) -> f32 {
+        let (cb, cs) = (backdrop, source);
+        match self {
+            Self::Normal => cs,
+            Self::Multiply => cb * cs,
+            Self::Screen => cb + cs - cb * cs,
+            // Hard light with the layers swapped
+            Self::Overlay => {
+                if cb <= 0.5 {
+                    2.0 * cb * cs
+                } else {
+                    Self::Screen.blend(cs, 2.0 * cb - 1.0)
+                }
+            }
+            Self::SoftLight => {
+                if cs <= 0.5 {
+                    cb - (1.0 - 2.0 * cs) * cb * (1.0 - cb)
+                } else {
+                    let d = if cb <= 0.25 { ((16.0 * cb - 12.0) * cb + 4.0) * cb } else { cb.sqrt() };
+                    cb + (2.0 * cs - 1.0) * (d - cb)
+                }
+            }
+        }
+    }
+}
+
+/// Serde default for layer opacities.
+pub(crate) fn full_opacity() -> f32 {
+    1.0
+}
+
+/// Returns true for an opacity that leaves a layer unchanged.
+pub(crate) fn is_full_opacity(opacity: &f32) -> bool {
+    *opacity >= 1.0
+}
+
+/// Multiplies the alpha of every pixel by `opacity` (clamped to 0.0-1.0).
+pub(crate) fn fade(image: &mut RgbaImage, opacity: f32) {
+    let opacity = opacity.clamp(0.0, 1.0);
+    if opacity >= 1.0 {
+        return;
+    }
+    for pixel in image.pixels_mut() {
+        pixel[3] = (pixel[3] as f32 * opacity).round() as u8;
+    }
+}
+
 /// Composites a source image onto a destination image at the specified position.
 ///
 /// Uses standard alpha blending (source over destination).
 pub fn composite_over(dest: &mut RgbaImage, src: &RgbaImage, x: i32, y: i32) {
+    composite_blend(dest, src, x, y, BlendMode::Normal);
+}
+
+/// Composites a source image onto a destination image at the specified
+/// position, mixing colors with `mode`.
+///
+/// Where the destination is opaque the source color is replaced by the
+/// blend result; over transparent pixels the source is drawn as is, as in
+/// the W3C Compositing spec.
+pub fn composite_blend(dest: &mut RgbaImage, src: &RgbaImage, x: i32, y: i32, mode: BlendMode) {
     let dest_width = dest.width() as i32;
     let dest_height = dest.height() as i32;
 
@@ -365,12 +992,63 @@ pub fn composite_over(dest: &mut RgbaImage, src: &RgbaImage, x: i32, y: i32) {
             let dst_pixel = dest.get_pixel(dx as u32, dy as u32);
 
             // Alpha blending (source over)
-            let blended = alpha_blend(*src_pixel, *dst_pixel);
+            let blended = alpha_blend(mix(*src_pixel, *dst_pixel, mode), *dst_pixel);
             dest.put_pixel(dx as u32, dy as u32, blended);
         }
     }
 }
 
+/// Returns `src` moved right by `fx` and down by `fy` pixels (each
+/// 0.0-1.0), on an image one pixel larger along each axis.
+///
+/// Pixels are resampled bilinearly with premultiplied colors, so
+/// transparent neighbors don't darken the edges.
+pub(crate) fn shift_subpixel(src: &RgbaImage, fx: f32, fy: f32) -> RgbaImage {
+    let (width, height) = src.dimensions();
+    let premultiplied = |x: i64, y: i64| -> [f32; 4] {
+        if x < 0 || y < 0 || x >= width as i64 || y >= height as i64 {
+            return [0.0; 4];
+        }
+        let p = src.get_pixel(x as u32, y as u32);
+        let a = p[3] as f32 / 255.0;
+        [p[0] as f32 * a, p[1] as f32 * a, p[2] as f32 * a, p[3] as f32]
+    };
+    // Each output pixel takes (1 - f) of its own source and f of the one
+    // up or to the left
+    let weights = [((0, 0), (1.0 - fx) * (1.0 - fy)), ((1, 0), fx * (1.0 - fy)), ((0, 1), (1.0 - fx) * fy), ((1, 1), fx * fy)];
+
+    RgbaImage::from_fn(width + 1, height + 1, |x, y| {
+        let mut sum = [0.0f32; 4];
+        for ((ox, oy), weight) in weights {
+            let p = premultiplied(x as i64 - ox, y as i64 - oy);
+            for (total, value) in sum.iter_mut().zip(p) {
+                *total += value * weight;
+            }
+        }
+        let alpha = sum[3].round().min(255.0);
+        if alpha == 0.0 {
+            return Rgba([0, 0, 0, 0]);
+        }
+        let color = |c: f32| (c * 255.0 / sum[3]).round().min(255.0) as u8;
+        Rgba([color(sum[0]), color(sum[1]), color(sum[2]), alpha as u8])
+    })
+}
+
+/// Replaces the source color by its blend with the backdrop, weighted by
+/// the backdrop's alpha.
+fn mix(src: Rgba<u8>, dst: Rgba<u8>, mode: BlendMode) -> Rgba<u8> {
+    if mode.is_normal() || src[3] == 0 || dst[3] == 0 {
+        return src;
+    }
+    let da = dst[3] as f32 / 255.0;
+    let channel = |s: u8, d: u8| {
+        let (cs, cb) = (s as f32 / 255.0, d as f32 / 255.0);
+        let mixed = (1.0 - da) * cs + da * mode.blend(cb, cs).clamp(0.0, 1.0);
+        (mixed * 255.0).round() as u8
+    };
+    Rgba([channel(src[0], dst[0]), channel(src[1], dst[1]), channel(src[2], dst[2]), src[3]])
+}
+
 /// Alpha blends two RGBA pixels (source over destination).
 fn alpha_blend(src: Rgba<u8>, dst: Rgba<u8>) -> Rgba<u8> {
     let sa = src[3] as f32 / 255.0;
@@ -421,6 +1099,67 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn parsed_trees_are_shared_across_sizes() {
+        let stamp = |size, color| stamp_svg_with_color_at(SIMPLE_SVG, size, color, RasterQuality::Full).unwrap();
+        let (small, large) = (stamp(16, None), stamp(64, None));
+        assert!(Arc::ptr_eq(&small.tree, &large.tree));
+        assert_eq!((small.dimensions(), large.dimensions()), ((16, 16), (64, 64)));
+
+        // Recoloring changes the markup, so it parses anew
+        let blue = stamp(16, Some((0, 0, 255, 255)));
+        assert!(!Arc::ptr_eq(&small.tree, &blue.tree));
+        assert!(Arc::ptr_eq(&blue.tree, &stamp(32, Some((0, 0, 255, 255))).tree));
+        let draft = stamp_svg_with_color_at(SIMPLE_SVG, 16, None, RasterQuality::Draft).unwrap();
+        assert!(!Arc::ptr_eq(&small.tree, &draft.tree));
+    }
+
+    #[test]
+    fn parsed_svg_summary() {
+        let parsed = ParsedSvg::parse(SIMPLE_SVG).unwrap();
+        assert_eq!(parsed.size(), (100.0, 100.0));
+        assert_eq!(parsed.node_count(), 2);
+        assert!(!parsed.has_text());
+        assert!(!parsed.has_images());
+
+        let with_image = r#"<svg xmlns="http://www.w3.org/2000/svg" width="10" height="10"><image href="photo.png" width="10" height="10"/></svg>"#;
+        assert!(ParsedSvg::parse(with_image).unwrap().has_images());
+
+        assert!(matches!(
+            ParsedSvg::parse("not valid svg at all"),
+            Err(RenderError::SvgParse { .. })
+        ));
+    }
+
+    #[test]
+    fn resolve_shared_is_memoized_across_threads() {
+        let source = SvgSource::from_svg(SIMPLE_SVG);
+        let first = source.resolve_shared().unwrap();
+        assert_eq!(&*first, SIMPLE_SVG);
+
+        let from_thread = std::thread::spawn({
+            let source = source.clone();
+            move || source.resolve_shared().unwrap()
+        })
+        .join()
+        .unwrap();
+        assert!(Arc::ptr_eq(&first.0, &from_thread.0));
+    }
+
+    #[test]
+    fn current_color_preserves_structure() {
+        // A ring: the inner circle is cut out with a `none` fill and must stay
+        // transparent, while the `currentColor` stroke takes the injected color
+        let ring = r##"<svg xmlns="http://www.w3.org/2000/svg" width="20" height="20"><circle cx="10" cy="10" r="7" fill="none" stroke="currentColor" stroke-width="4"/><rect width="2" height="2" fill="#ffffff"/></svg>"##;
+        assert!(uses_current_color(ring));
+
+        let img = render_svg_with_current_color(ring, 20, (0, 128, 255, 255)).unwrap();
+        assert_eq!(img.get_pixel(10, 10)[3], 0);
+        assert_eq!(img.get_pixel(10, 3).0, [0, 128, 255, 255]);
+        // Fixed colors are not touched
+        assert_eq!(img.get_pixel(0, 0).0, [255, 255, 255, 255]);
+    }
+
     #[test]
     fn render_svg_with_color_replacement() {
         let img = render_svg_with_color(SIMPLE_SVG, 50, Some((0, 255, 0, 255))).unwrap();
@@ -465,11 +1204,47 @@ mod tests {
     }
 
     #[test]
-    fn replace_color_preserves_none() {
-        let svg = r##"<circle fill="none" stroke="#000000"/>"##;
-        let result = replace_svg_colors(svg, 255, 0, 0);
-        assert!(result.contains(r#"fill="none""#));
-        assert!(result.contains(r##"stroke="#ff0000""##));
+    fn composite_blend_mixes_with_opaque_backdrop() {
+        let mut dest = RgbaImage::from_pixel(2, 1, Rgba([255, 128, 0, 255]));
+        dest.put_pixel(1, 0, Rgba([0, 0, 0, 0]));
+        let src = RgbaImage::from_pixel(2, 1, Rgba([128, 128, 255, 255]));
+
+        composite_blend(&mut dest, &src, 0, 0, BlendMode::Multiply);
+        assert_eq!(dest.get_pixel(0, 0).0, [128, 64, 0, 255]);
+        // Nothing to blend with: the source shows as is
+        assert_eq!(dest.get_pixel(1, 0).0, [128, 128, 255, 255]);
+    }
+
+    #[test]
+    fn recolor_reaches_styles_classes_and_single_quotes() {
+        let svg = r#"<svg xmlns="http://www.w3.org/2000/svg" width="40" height="10">
+            <style>.dark { fill: #000 }</style>
+            <rect width="10" height="10" style="fill:#000"/>
+            <rect x="10" width="10" height="10" class="dark"/>
+            <rect x="20" width="10" height="10" fill='#000'/>
+            <rect x="30" width="10" height="10" fill="none" stroke='#000'/>
+        </svg>"#;
+        let tree = Tree::from_str(svg, &Options::default()).unwrap();
+        let image = SvgStamp::new(recolor_tree(&tree, 255, 0, 0).unwrap(), 1.0).rasterize(0.0, 0.0).unwrap();
+
+        for x in [5, 15, 25] {
+            assert_eq!(image.get_pixel(x, 5).0, [255, 0, 0, 255], "x = {x}");
+        }
+        // The stroke is recolored, the `none` fill inside it left empty
+        assert_eq!(image.get_pixel(30, 5).0[..3], [255, 0, 0]);
+        assert_eq!(image.get_pixel(35, 5).0[3], 0);
+        // The colored render path recolors the same way
+        let colored = render_svg_with_color(svg, 40, Some((255, 0, 0, 255))).unwrap();
+        assert_eq!(colored.get_pixel(15, 5).0, [255, 0, 0, 255]);
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn replace_svg_colors_wraps_recolor_tree() {
+        let svg = r#"<svg xmlns="http://www.w3.org/2000/svg" width="10" height="10"><rect width="10" height="10" style="fill:#000"/></svg>"#;
+        let recolored = replace_svg_colors(svg, 255, 0, 0);
+        assert!(recolored.contains("#ff0000"), "{recolored}");
+        assert_eq!(replace_svg_colors("not svg", 255, 0, 0), "not svg");
     }
 
     #[test]
@@ -480,6 +1255,63 @@ mod tests {
         assert_eq!(source.resolve().unwrap(), "<svg></svg>");
     }
 
+    #[test]
+    fn raster_sources_render_as_images() {
+        let logo = RgbaImage::from_pixel(4, 2, Rgba([200, 30, 40, 255]));
+        let source = SvgSource::from_raster(&logo).unwrap();
+        assert!(source.is_raster());
+
+        // Scaled to fit, keeping the 2:1 aspect ratio
+        let rendered = render_source(&source, 16).unwrap();
+        assert_eq!(rendered.dimensions(), (16, 8));
+        assert_eq!(rendered.get_pixel(8, 4).0, [200, 30, 40, 255]);
+
+        let json = serde_json::to_string(&source).unwrap();
+        assert!(json.starts_with(r#"{"raster":"iVBORw0KGgo"#), "{json}");
+        assert_eq!(serde_json::from_str::<SvgSource>(&json).unwrap(), source);
+        assert!(matches!(SvgSource::from_image_bytes(b"GIF89a"), Err(RenderError::InvalidRaster { .. })));
+    }
+
+    #[test]
+    fn oversized_raster_sources_are_rejected_before_decoding() {
+        let wide = RgbaImage::new(RenderLimits::DEFAULT_MAX_DIMENSION + 1, 1);
+        let source = SvgSource::from_raster(&wide).unwrap();
+        assert!(matches!(source.resolve(), Err(RenderError::ImageTooLarge { width: 4097, height: 1, .. })));
+
+        let SvgSource::Raster(data) = &source else { unreachable!() };
+        let bytes = STANDARD.decode(data).unwrap();
+        assert!(matches!(SvgSource::from_image_bytes(&bytes), Err(RenderError::ImageTooLarge { .. })));
+    }
+
+    #[test]
+    fn file_sources_reload_when_modified() {
+        use std::time::Duration;
+
+        let path = std::env::temp_dir().join(format!("folco-svg-source-{}.svg", std::process::id()));
+        let write = |svg: &str, modified: SystemTime| {
+            fs::write(&path, svg).unwrap();
+            fs::File::options().write(true).open(&path).unwrap().set_modified(modified).unwrap();
+        };
+        let source = SvgSource::file(&path);
+        let then = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+
+        write(SIMPLE_SVG, then);
+        assert_eq!(&*source.resolve_shared().unwrap(), SIMPLE_SVG);
+        let version = source.content_version();
+        write("<svg/>", then + Duration::from_secs(1));
+        assert_eq!(source.resolve().unwrap(), "<svg/>");
+        // Edits reach layer caches and render ids
+        assert!(source.is_external());
+        assert_ne!(source.content_version(), version);
+
+        fs::remove_file(&path).unwrap();
+        assert!(matches!(source.resolve_shared(), Err(RenderError::SvgFile { .. })));
+        let json = serde_json::to_value(&source).unwrap();
+        assert_eq!(json["file"], path.to_str().unwrap());
+
+        assert_eq!(serde_json::from_value::<SvgSource>(json).unwrap(), source);
+    }
+
     #[test]
     fn svg_source_into_from_string() {
         let source: SvgSource = "<svg></svg>".into();
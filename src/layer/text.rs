@@ -0,0 +1,410 @@
+//! Text label layer — configuration and rendering.
+//!
+//! Draws a short caption such as "2024" or "WIP" onto the icon. The text
+//! is laid out and rasterized by resvg from a generated `<text>` element,
+//! using the bundled font, an installed font family, or font data supplied
+//! by the caller.
+
+use std::sync::{Arc, OnceLock};
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use image::RgbaImage;
+use resvg::usvg::{fontdb, Options, Tree};
+
+use super::budget::DegradedLayers;
+use super::decal::decal_color;
+use super::overlay::{calculate_position, clip_to_image};
+use super::placement::{composite_stamp_at, PlacementRounding};
+use super::warnings::RenderWarning;
+use super::svg::{RasterQuality, SvgStamp};
+use super::{finite_or, CacheKey, CachedOutput, DependencyVersion, Layer, LayerConfig, LayerKind, LayerVersions, OccupiedRegions, OverlayPosition, RenderContext};
+use crate::error::RenderError;
+use crate::icon::RectPx;
+
+/// The public-domain Tuffy typeface.
+#[cfg(feature = "bundled-font")]
+const BUNDLED_FONT: &[u8] = include_bytes!("../../assets/fonts/Tuffy.ttf");
+
+// ============================================================================
+// TextFont
+// ============================================================================
+
+/// The font a [`TextLayerConfig`] is drawn with.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "jsonschema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
+pub enum TextFont {
+    /// The font bundled with the crate (Tuffy, public domain).
+    ///
+    /// Needs the `bundled-font` feature, which is on by default; builds
+    /// that turn it off must set another font on every text layer.
+    #[default]
+    Bundled,
+
+    /// An installed font family, e.g. `"DejaVu Sans"`.
+    ///
+    /// Resolved against the system fonts at render time, so profiles using
+    /// it render differently, or fail, on machines without the family.
+    Family(String),
+
+    /// Base64-encoded TrueType or OpenType font data.
+    Data(String),
+}
+
+impl TextFont {
+    /// Creates a font from TrueType or OpenType data.
+    pub fn from_data(data: &[u8]) -> Self {
+        Self::Data(STANDARD.encode(data))
+    }
+
+    /// Returns true if this is the bundled font.
+    pub fn is_bundled(&self) -> bool {
+        matches!(self, Self::Bundled)
+    }
+
+    /// Returns a font database holding the font, and the family name to
+    /// request from it.
+    fn load(&self) -> Result<(Arc<fontdb::Database>, String), RenderError> {
+        let unavailable = |font: &str| RenderError::FontNotAvailable { font: font.to_string() };
+        let from_data = |data: Vec<u8>, name: &str| {
+            let mut db = fontdb::Database::new();
+            db.load_font_data(data);
+            let family = db
+                .faces()
+                .next()
+                .and_then(|face| face.families.first())
+                .map(|(family, _)| family.clone())
+                .ok_or_else(|| unavailable(name))?;
+            Ok((Arc::new(db), family))
+        };
+
+        match self {
+            #[cfg(feature = "bundled-font")]
+            Self::Bundled => {
+                static BUNDLED: OnceLock<(Arc<fontdb::Database>, String)> = OnceLock::new();
+                if let Some(loaded) = BUNDLED.get() {
+                    return Ok(loaded.clone());
+                }
+                let loaded = from_data(BUNDLED_FONT.to_vec(), "bundled")?;
+                Ok(BUNDLED.get_or_init(|| loaded).clone())
+            }
+            #[cfg(not(feature = "bundled-font"))]
+            Self::Bundled => Err(RenderError::BundledFontNotAvailable),
+            Self::Family(family) => {
+                // Scanning the system fonts is slow, so it's done once
+                static SYSTEM: OnceLock<Arc<fontdb::Database>> = OnceLock::new();
+                let db = SYSTEM.get_or_init(|| {
+                    let mut db = fontdb::Database::new();
+                    db.load_system_fonts();
+                    Arc::new(db)
+                });
+                let installed = db
+                    .faces()
+                    .any(|face| face.families.iter().any(|(name, _)| name.eq_ignore_ascii_case(family)));
+                if !installed {
+                    return Err(unavailable(family));
+                }
+                Ok((db.clone(), family.clone()))
+            }
+            Self::Data(data) => {
+                let bytes = STANDARD.decode(data).map_err(|_| unavailable("font data"))?;
+                from_data(bytes, "font data")
+            }
+        }
+    }
+}
+
+// ============================================================================
+// TextLayerConfig
+// ============================================================================
+
+/// Configuration for a text label — pure data.
+///
+/// The text is drawn on a single line, sized relative to the icon's content
+/// bounds and placed like an overlay.
+///
+/// # Example
+///
+/// ```
+/// use folco_renderer::{OverlayPosition, TextLayerConfig};
+///
+/// let label = TextLayerConfig::new("WIP", OverlayPosition::BottomRight, 0.3).with_color(255, 255, 255);
+/// assert_eq!(label.color, Some((255, 255, 255)));
+/// ```
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "jsonschema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct TextLayerConfig {
+    /// The text to draw. Line breaks are not supported.
+    pub text: String,
+
+    /// The font to draw with. Defaults to the bundled font.
+    #[serde(default, skip_serializing_if = "TextFont::is_bundled")]
+    pub font: TextFont,
+
+    /// Font size relative to the smaller side of the content bounds
+    /// (0.0-1.0).
+    pub size: f32,
+
+    /// Text color as RGB. `None` derives it from the folder color the same
+    /// way the decal does.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<(u8, u8, u8)>,
+
+    /// Where to place the text within the content bounds.
+    pub position: OverlayPosition,
+}
+
+impl TextLayerConfig {
+    /// Creates a text label drawn with the bundled font in the derived
+    /// color.
+    ///
+    /// The size is clamped to 0.0-1.0.
+    pub fn new(text: impl Into<String>, position: OverlayPosition, size: f32) -> Self {
+        Self {
+            text: text.into(),
+            font: TextFont::default(),
+            size: finite_or(size, 0.0).clamp(0.0, 1.0),
+            color: None,
+            position,
+        }
+    }
+
+    /// Sets the font.
+    pub fn with_font(mut self, font: TextFont) -> Self {
+        self.font = font;
+        self
+    }
+
+    /// Sets a fixed text color.
+    pub fn with_color(mut self, r: u8, g: u8, b: u8) -> Self {
+        self.color = Some((r, g, b));
+        self
+    }
+}
+
+impl LayerConfig for TextLayerConfig {
+    fn differs_from(&self, other: &Self) -> bool {
+        self.text != other.text
+            || self.font != other.font
+            || (self.size - other.size).abs() > 0.0001
+            || self.color != other.color
+            || self.position != other.position
+    }
+}
+
+// ============================================================================
+// Layer Rendering
+// ============================================================================
+
+impl Layer<TextLayerConfig> {
+    /// Returns the upstream versions this layer's cache depends on.
+    ///
+    /// Derived colors follow the color target and accent; auto-placed text
+    /// avoids the decal and overlay.
+    pub(crate) fn dependencies(&self, versions: &LayerVersions) -> DependencyVersion {
+        let Some(config) = self.config() else {
+            return DependencyVersion::NONE;
+        };
+        let mut upstream = Vec::new();
+        if config.color.is_none() {
+            upstream.extend([versions.folder_color_target, versions.accent]);
+        }
+        if config.position == OverlayPosition::Auto {
+            upstream.extend([versions.decal, versions.overlay]);
+        }
+        DependencyVersion::combine(&upstream)
+    }
+
+    /// Render this text layer, returning a tile for compositing.
+    ///
+    /// Returns `None` if inactive. The tile is a transparent canvas with
+    /// the text drawn at the configured position. Adds the drawn rectangle
+    /// to [`OccupiedRegions`].
+    pub fn render_tile(
+        &mut self,
+        ctx: &mut RenderContext,
+        key: CacheKey,
+        versions: &LayerVersions,
+    ) -> Result<Option<RgbaImage>, RenderError> {
+        let deps = self.dependencies(versions);
+        self.render_tile_with_deps(ctx, key, deps)
+    }
+
+    /// Like [`render_tile`](Self::render_tile), with the cache validated
+    /// against a caller-computed dependency version.
+    pub(crate) fn render_tile_with_deps(
+        &mut self,
+        ctx: &mut RenderContext,
+        key: CacheKey,
+        deps: DependencyVersion,
+    ) -> Result<Option<RgbaImage>, RenderError> {
+        if !self.is_active() {
+            return Ok(None);
+        }
+
+        if let Some(CachedOutput::Tile(tile, placement)) = self.get_cached(key, deps) {
+            if let Some(bounds) = *placement {
+                OccupiedRegions::push(ctx, bounds);
+            }
+            return Ok(Some(tile.clone()));
+        }
+
+        let config = self.config().unwrap();
+        let (tile, placement) = render_text(config, ctx)?;
+        if let Some(bounds) = placement {
+            OccupiedRegions::push(ctx, bounds);
+        }
+
+        if RasterQuality::of(ctx).is_draft() {
+            DegradedLayers::push(ctx, LayerKind::Text);
+        } else {
+            self.store(key, CachedOutput::Tile(tile.clone(), placement), deps);
+        }
+        Ok(Some(tile))
+    }
+}
+
+/// Renders the label onto a transparent tile matching the icon dimensions.
+///
+/// Also returns the rectangle drawn into, clipped to the image, or `None`
+/// if nothing was drawn.
+fn render_text(config: &TextLayerConfig, ctx: &mut RenderContext) -> Result<(RgbaImage, Option<RectPx>), RenderError> {
+    let width = ctx.image.data().width();
+    let height = ctx.image.data().height();
+    let mut tile = RgbaImage::new(width, height);
+
+    let bounds = ctx.image.content_bounds;
+    // Deserialized configs bypass the constructor clamp
+    let font_size = bounds.width.min(bounds.height) as f32 * config.size.clamp(0.0, 1.0);
+    if config.text.trim().is_empty() {
+        return Ok((tile, None));
+    }
+    if font_size < 1.0 {
+        ctx.warn(RenderWarning::ZeroSize { layer: LayerKind::Text });
+        return Ok((tile, None));
+    }
+
+    let Some(tree) = text_tree(config, ctx, font_size)? else {
+        ctx.warn(RenderWarning::NoVisibleText { text: config.text.clone() });
+        return Ok((tile, None));
+    };
+    let stamp = SvgStamp::new(tree, 1.0);
+    let (w, h) = stamp.dimensions();
+
+    let occupied = ctx.get::<OccupiedRegions>().cloned().unwrap_or_default();
+    let position = calculate_position(config.position, &bounds, &occupied, w, h, ctx.image.scale);
+    let (x, y, w, h) = composite_stamp_at(&mut tile, &stamp, position, PlacementRounding::of(ctx))?;
+
+    let placement = clip_to_image(x, y, w, h, width, height);
+    Ok((tile, placement))
+}
+
+/// Lays out the label at `font_size` as a tree whose view box is cropped
+/// to the ink, or `None` if no glyph has any ink.
+pub(crate) fn text_tree(config: &TextLayerConfig, ctx: &RenderContext, font_size: f32) -> Result<Option<Tree>, RenderError> {
+    let (r, g, b) = config.color.unwrap_or_else(|| {
+        let (r, g, b, _) = decal_color(ctx, None);
+        (r, g, b)
+    });
+    let (fontdb, family) = config.font.load()?;
+    let mut opts = Options {
+        fontdb,
+        ..Options::default()
+    };
+    RasterQuality::of(ctx).configure(&mut opts);
+    let text = format!(
+        r##"<text x="0" y="0" font-family="{}" font-size="{font_size}" fill="#{r:02x}{g:02x}{b:02x}">{}</text>"##,
+        escape_xml(&family),
+        escape_xml(&config.text),
+    );
+
+    // Lay out once to measure the ink, then again inside a view box around it
+    let measured = Tree::from_str(&format!(r#"<svg xmlns="http://www.w3.org/2000/svg" width="1" height="1">{text}</svg>"#), &opts)?;
+    let ink = measured.root().abs_bounding_box();
+    if !measured.root().has_children() || ink.width() <= 0.0 || ink.height() <= 0.0 {
+        return Ok(None);
+    }
+    let svg = format!(
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{h}" viewBox="{x} {y} {w} {h}">{text}</svg>"#,
+        x = ink.x(),
+        y = ink.y(),
+        w = ink.width(),
+        h = ink.height(),
+    );
+    Ok(Some(Tree::from_str(&svg, &opts)?))
+}
+
+/// Escapes text for use in XML content and attribute values.
+pub(crate) fn escape_xml(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use image::Rgba;
+
+    use super::*;
+    use crate::icon::{IconImage, SurfaceColor};
+
+    const TEST_FONT: &[u8] = include_bytes!("../../assets/fonts/Tuffy.ttf");
+
+    fn context() -> RenderContext {
+        let base = RgbaImage::from_pixel(64, 64, Rgba([255, 217, 112, 255]));
+        let mut ctx = RenderContext::new(IconImage::new_full_content(base, 1.0));
+        ctx.set(SurfaceColor::new(255, 217, 112));
+        ctx
+    }
+
+    #[test]
+    fn label_is_drawn_in_its_corner() {
+        let config = TextLayerConfig::new("W&<", OverlayPosition::BottomRight, 0.25)
+            .with_font(TextFont::from_data(TEST_FONT))
+            .with_color(0, 0, 255);
+
+        let (tile, placement) = render_text(&config, &mut context()).unwrap();
+        let placement = placement.unwrap();
+        assert_eq!((placement.right(), placement.bottom()), (64, 64));
+        // Ink of 16px type, escaped markup characters included
+        assert!(placement.height <= 18 && placement.width > placement.height && placement.x > 0, "{placement:?}");
+        assert!(tile.pixels().any(|p| p.0 == [0, 0, 255, 255]));
+        assert!(tile.enumerate_pixels().all(|(x, y, p)| p[3] == 0 || (x >= placement.x && y >= placement.y)));
+    }
+
+    #[test]
+    fn blank_labels_and_missing_fonts() {
+        let blank = TextLayerConfig::new("  ", OverlayPosition::Center, 0.5);
+        assert_eq!(render_text(&blank, &mut context()).unwrap().1, None);
+
+        let missing = TextLayerConfig::new("2024", OverlayPosition::Center, 0.5)
+            .with_font(TextFont::Family("No Such Family 1234".into()));
+        assert!(matches!(render_text(&missing, &mut context()), Err(RenderError::FontNotAvailable { .. })));
+        let corrupt = missing.with_font(TextFont::Data("not base64!".into()));
+        assert!(matches!(render_text(&corrupt, &mut context()), Err(RenderError::FontNotAvailable { .. })));
+
+        let bundled = render_text(&TextLayerConfig::new("2024", OverlayPosition::Center, 0.5), &mut context());
+        #[cfg(feature = "bundled-font")]
+        assert!(bundled.unwrap().1.is_some());
+        #[cfg(not(feature = "bundled-font"))]
+        assert!(matches!(bundled, Err(RenderError::BundledFontNotAvailable)));
+
+        let json = serde_json::to_string(&TextLayerConfig::new("2024", OverlayPosition::TopLeft, 0.2)).unwrap();
+        assert_eq!(json, r#"{"text":"2024","size":0.2,"position":"top-left"}"#);
+    }
+}
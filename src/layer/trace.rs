@@ -0,0 +1,203 @@
+//! Recording render timelines for `chrome://tracing`.
+//!
+//! [`RenderReport`](super::RenderReport)s say what a render did, not where
+//! its time went. While a [trace is recording](LayerPipeline::start_trace),
+//! the pipeline times every render and each layer within it. The
+//! [`RenderTrace`] it returns writes the Trace Event Format that
+//! `chrome://tracing`, Perfetto and `speedscope` open, so timelines from
+//! two crate versions can be compared side by side.
+//!
+//! Sizes rendered in parallel (with the `rayon` feature) record into the
+//! same trace, one track per thread.
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::thread::ThreadId;
+use std::time::{Duration, Instant};
+
+use serde_json::json;
+
+use super::{CacheKey, LayerPipeline};
+
+/// One timed span of a render.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TraceEvent {
+    /// What ran: `"render"`, or the layer's name (e.g. `"decal"`).
+    pub name: String,
+    /// `"render"` for whole renders, `"layer"` for a layer within one.
+    pub category: &'static str,
+    /// When the span started, from the start of the trace.
+    pub start: Duration,
+    /// How long the span took.
+    pub duration: Duration,
+    /// The track the span ran on, numbered by first use from 1.
+    pub thread: u32,
+    /// The rendered image's device pixel dimensions.
+    pub size: (u32, u32),
+    /// The rendered image's scale factor.
+    pub scale: f32,
+}
+
+/// The spans recorded between [`start_trace`](LayerPipeline::start_trace)
+/// and [`take_trace`](LayerPipeline::take_trace), in the order they ended.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RenderTrace {
+    /// The recorded spans.
+    pub events: Vec<TraceEvent>,
+}
+
+impl RenderTrace {
+    /// Formats the trace as Trace Event Format JSON, with each span as a
+    /// complete (`"X"`) event in microseconds.
+    pub fn to_chrome_json(&self) -> String {
+        let micros = |d: Duration| d.as_nanos() as f64 / 1000.0;
+        let events: Vec<_> = self
+            .events
+            .iter()
+            .map(|event| {
+                json!({
+                    "name": event.name,
+                    "cat": event.category,
+                    "ph": "X",
+                    "ts": micros(event.start),
+                    "dur": micros(event.duration),
+                    "pid": 1,
+                    "tid": event.thread,
+                    "args": {
+                        "width": event.size.0,
+                        "height": event.size.1,
+                        "scale": event.scale,
+                    },
+                })
+            })
+            .collect();
+        json!({ "traceEvents": events, "displayTimeUnit": "ms" }).to_string()
+    }
+
+    /// Writes [`to_chrome_json`](Self::to_chrome_json) to `path`.
+    ///
+    /// # Errors
+    ///
+    /// Returns any error writing the file.
+    pub fn write_chrome_trace(&self, path: impl AsRef<Path>) -> std::io::Result<()> {
+        std::fs::write(path, self.to_chrome_json())
+    }
+}
+
+/// A trace being recorded, shared by a pipeline and its shards.
+#[derive(Debug)]
+pub(crate) struct TraceLog {
+    start: Instant,
+    threads: HashMap<ThreadId, u32>,
+    trace: RenderTrace,
+}
+
+/// A [`TraceLog`] shared between a pipeline, its clones and its shards.
+pub(crate) type SharedTraceLog = Arc<Mutex<TraceLog>>;
+
+impl LayerPipeline {
+    /// Starts recording a [`RenderTrace`] of every render and layer,
+    /// discarding any trace in progress.
+    ///
+    /// ```
+    /// use folco_renderer::{FolderColorTargetConfig, IconImage, LayerPipeline, SurfaceColor};
+    /// use image::RgbaImage;
+    ///
+    /// let mut pipeline = LayerPipeline::default();
+    /// pipeline.folder_color_target.set_config(Some(FolderColorTargetConfig::new(33, 150, 243)));
+    /// pipeline.start_trace();
+    /// pipeline.render(&IconImage::new_full_content(RgbaImage::new(32, 32), 1.0), &SurfaceColor::new(255, 217, 112)).unwrap();
+    ///
+    /// let trace = pipeline.take_trace().unwrap();
+    /// assert_eq!(trace.events.len(), 2);
+    /// assert!(trace.to_chrome_json().starts_with(r#"{"displayTimeUnit":"ms","traceEvents":["#));
+    /// ```
+    pub fn start_trace(&mut self) {
+        self.trace = Some(Arc::new(Mutex::new(TraceLog {
+            start: Instant::now(),
+            threads: HashMap::new(),
+            trace: RenderTrace::default(),
+        })));
+    }
+
+    /// Stops recording and returns the trace, or `None` if none was
+    /// started.
+    pub fn take_trace(&mut self) -> Option<RenderTrace> {
+        let log = self.trace.take()?;
+        let log = log.lock().unwrap_or_else(|e| e.into_inner());
+        Some(log.trace.clone())
+    }
+
+    /// Runs `f`, recording it as a span named `name` if a trace is
+    /// recording.
+    pub(crate) fn traced<T>(
+        &mut self,
+        category: &'static str,
+        name: &dyn std::fmt::Display,
+        key: CacheKey,
+        f: impl FnOnce(&mut Self) -> T,
+    ) -> T {
+        let Some(log) = self.trace.clone() else {
+            return f(self);
+        };
+        let started = Instant::now();
+        let result = f(self);
+        let duration = started.elapsed();
+
+        let mut log = log.lock().unwrap_or_else(|e| e.into_inner());
+        let next = log.threads.len() as u32 + 1;
+        let thread = *log.threads.entry(std::thread::current().id()).or_insert(next);
+        let event = TraceEvent {
+            name: name.to_string(),
+            category,
+            start: started.saturating_duration_since(log.start),
+            duration,
+            thread,
+            size: (key.width, key.height),
+            scale: f32::from_bits(key.scale_bits),
+        };
+        log.trace.events.push(event);
+        result
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::icon::{IconImage, SurfaceColor};
+    use crate::layer::{DecalConfig, FolderColorTargetConfig};
+    use image::RgbaImage;
+
+    const SQUARE: &str = r##"<svg xmlns="http://www.w3.org/2000/svg" width="10" height="10"><rect width="10" height="10" fill="#000"/></svg>"##;
+
+    #[test]
+    fn trace_nests_layers_inside_renders() {
+        let surface = SurfaceColor::new(255, 217, 112);
+        let mut pipeline = LayerPipeline::default();
+        pipeline.folder_color_target.set_config(Some(FolderColorTargetConfig::new(33, 150, 243)));
+        pipeline.decal.set_config(Some(DecalConfig::new(SQUARE, 0.5)));
+        pipeline.start_trace();
+        pipeline.render(&IconImage::new_full_content(RgbaImage::new(32, 32), 2.0), &surface).unwrap();
+        // Served from the composite cache, so not traced
+        pipeline.render(&IconImage::new_full_content(RgbaImage::new(32, 32), 2.0), &surface).unwrap();
+
+        let trace = pipeline.take_trace().unwrap();
+        let names: Vec<_> = trace.events.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, ["color target", "decal", "render"]);
+        let render = &trace.events[2];
+        assert_eq!((render.category, render.size, render.scale), ("render", (32, 32), 2.0));
+        for layer in &trace.events[..2] {
+            assert!(layer.start >= render.start && layer.start + layer.duration <= render.start + render.duration);
+        }
+
+        let json: serde_json::Value = serde_json::from_str(&trace.to_chrome_json()).unwrap();
+        assert_eq!(json["traceEvents"][1]["ph"], "X");
+        assert_eq!(json["traceEvents"][1]["cat"], "layer");
+        assert!(pipeline.take_trace().is_none());
+    }
+}
@@ -0,0 +1,235 @@
+//! Re-rendering only what moved above the decal.
+//!
+//! Dragging an overlay badge changes nothing beneath it, yet every frame
+//! would still restore the color target's output, redraw the shadow and
+//! composite the decal over the whole icon. The pipeline therefore keeps
+//! the composite of the layers beneath the overlay (color target, gradient
+//! tint, shadow and decal), with the properties they emitted. While those
+//! layers are unchanged, renders start from it, and the overlay and text
+//! are composited over only the pixels they cover.
+//!
+//! The underlay is stored with fully transparent pixels cleared, as
+//! compositing over the whole icon would leave them, so both paths produce
+//! identical pixels.
+
+use std::collections::HashMap;
+
+use image::{GenericImageView, Rgba, RgbaImage};
+
+use super::svg::{composite_blend, BlendMode};
+use super::warnings::RenderWarning;
+use super::{CacheKey, DecalPlacement, DependencyVersion, DerivedPalette, DominantColor, LayerKind, LayerPipeline, LayerVersions, OccupiedRegions, RenderContext, Thumbnail};
+use crate::icon::IconImage;
+
+/// The composite beneath the overlay and what its layers emitted.
+#[derive(Clone)]
+pub(crate) struct Underlay {
+    image: IconImage,
+    decal: Option<DecalPlacement>,
+    occupied: Option<OccupiedRegions>,
+    dominant: Option<DominantColor>,
+    palette: Option<DerivedPalette>,
+    thumbnail: Option<Thumbnail>,
+    warnings: Vec<RenderWarning>,
+}
+
+impl Underlay {
+    /// Captures `ctx` after the layers beneath the overlay ran.
+    fn capture(ctx: &RenderContext) -> Self {
+        let mut image = ctx.image.clone();
+        if image.data().pixels().any(|p| p[3] == 0 && p.0 != [0; 4]) {
+            for pixel in image.data_mut().pixels_mut().filter(|p| p[3] == 0) {
+                *pixel = Rgba([0; 4]);
+            }
+        }
+        Self {
+            image,
+            decal: ctx.get::<DecalPlacement>().copied(),
+            occupied: ctx.get::<OccupiedRegions>().cloned(),
+            dominant: ctx.get::<DominantColor>().copied(),
+            palette: ctx.get::<DerivedPalette>().copied(),
+            thumbnail: ctx.get::<Thumbnail>().cloned(),
+            warnings: ctx.warnings().to_vec(),
+        }
+    }
+
+    /// Puts the captured image and properties into a fresh `ctx`.
+    fn restore(&self, ctx: &mut RenderContext) {
+        ctx.image = self.image.clone();
+        if let Some(decal) = self.decal {
+            ctx.set(decal);
+        }
+        if let Some(occupied) = &self.occupied {
+            ctx.set(occupied.clone());
+        }
+        if let Some(dominant) = self.dominant {
+            ctx.set(dominant);
+        }
+        if let Some(palette) = self.palette {
+            ctx.set(palette);
+        }
+        if let Some(thumbnail) = &self.thumbnail {
+            ctx.set(thumbnail.clone());
+        }
+        for warning in &self.warnings {
+            ctx.warn(warning.clone());
+        }
+    }
+}
+
+/// Underlays by size, each with the dependency version it was stored at.
+#[derive(Clone, Default)]
+pub(crate) struct UnderlayCache {
+    cache: HashMap<CacheKey, (Underlay, u64)>,
+}
+
+impl UnderlayCache {
+    /// Like [`Layer::cache_footprint`](super::Layer::cache_footprint).
+    pub(crate) fn cache_footprint(&self) -> impl Iterator<Item = (CacheKey, usize)> + '_ {
+        self.cache.iter().map(|(key, (underlay, _))| (*key, underlay.image.data().as_raw().len()))
+    }
+
+    /// Like [`Layer::evict`](super::Layer::evict).
+    pub(crate) fn evict(&mut self, key: CacheKey) {
+        self.cache.remove(&key);
+    }
+
+    /// Drops every underlay.
+    pub(crate) fn clear(&mut self) {
+        self.cache.clear();
+    }
+
+    /// Like [`Layer::take_shard`](super::Layer::take_shard).
+    #[cfg(feature = "rayon")]
+    pub(crate) fn take_shard(&mut self, key: CacheKey) -> Self {
+        Self {
+            cache: self.cache.remove_entry(&key).into_iter().collect(),
+        }
+    }
+
+    /// Like [`Layer::merge_shard`](super::Layer::merge_shard).
+    #[cfg(feature = "rayon")]
+    pub(crate) fn merge_shard(&mut self, shard: Self) {
+        self.cache.extend(shard.cache);
+    }
+
+    /// Like [`Layer::copy_shard`](super::Layer::copy_shard).
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) fn copy_shard(&self, key: CacheKey) -> Self {
+        Self {
+            cache: self.cache.get_key_value(&key).map(|(k, v)| (*k, v.clone())).into_iter().collect(),
+        }
+    }
+}
+
+impl LayerPipeline {
+    /// Returns true if the overlay or text will draw, so an underlay can
+    /// be reused.
+    fn draws_above_decal(&self) -> bool {
+        self.renders(LayerKind::Overlay) || self.renders(LayerKind::Text)
+    }
+
+    /// Returns the versions the underlay depends on: every layer beneath
+    /// the overlay, and the accent.
+    fn underlay_dependencies(versions: &LayerVersions) -> DependencyVersion {
+        DependencyVersion::combine(&[
+            versions.folder_color_target,
+            versions.gradient_tint,
+            versions.shadow,
+            versions.decal,
+            versions.accent,
+        ])
+    }
+
+    /// Restores the underlay of size `key` into `ctx`, returning true, if
+    /// one is stored for the current layers and something draws above it.
+    pub(crate) fn restore_underlay(&self, ctx: &mut RenderContext, key: CacheKey, versions: &LayerVersions) -> bool {
+        if !self.draws_above_decal() {
+            return false;
+        }
+        let deps = Self::underlay_dependencies(versions);
+        match self.underlay.cache.get(&key) {
+            Some((underlay, stored)) if *stored == deps.0 => {
+                underlay.restore(ctx);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Stores `ctx` as the underlay of size `key`, unless nothing draws
+    /// above it or the render is over its time budget.
+    pub(crate) fn store_underlay(&mut self, ctx: &RenderContext, key: CacheKey, versions: &LayerVersions) {
+        if self.draws_above_decal() && !ctx.has::<super::budget::OverBudget>() && !ctx.has::<super::budget::DegradedLayers>() {
+            let deps = Self::underlay_dependencies(versions);
+            self.underlay.cache.insert(key, (Underlay::capture(ctx), deps.0));
+        }
+    }
+}
+
+/// Composites the visible part of `tile` over `dest` with `mode`.
+///
+/// Over an image whose transparent pixels are cleared, this matches
+/// compositing the whole tile.
+pub(crate) fn composite_visible(dest: &mut RgbaImage, tile: &RgbaImage, mode: BlendMode) {
+    let (mut left, mut top, mut right, mut bottom) = (u32::MAX, u32::MAX, 0, 0);
+    for (x, y, pixel) in tile.enumerate_pixels() {
+        if pixel[3] != 0 {
+            (left, top) = (left.min(x), top.min(y));
+            (right, bottom) = (right.max(x + 1), bottom.max(y + 1));
+        }
+    }
+    if left < right {
+        let visible = tile.view(left, top, right - left, bottom - top).to_image();
+        composite_blend(dest, &visible, left as i32, top as i32, mode);
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use crate::icon::{IconImage, SurfaceColor};
+    use crate::layer::{DecalConfig, FolderColorTargetConfig, LayerPipeline, OverlayPosition, ShadowConfig, SvgOverlayConfig};
+    use image::{Rgba, RgbaImage};
+
+    const SQUARE: &str = r##"<svg xmlns="http://www.w3.org/2000/svg" width="10" height="10"><rect width="10" height="10" fill="#fff"/></svg>"##;
+
+    #[test]
+    fn moving_an_overlay_reuses_the_underlay_with_identical_pixels() {
+        // Transparent pixels with color, which full compositing clears
+        let folder = RgbaImage::from_fn(64, 64, |x, y| match (4..60).contains(&x) && (12..56).contains(&y) {
+            true => Rgba([255, 217, 112, 255]),
+            false => Rgba([90, 20, 20, 0]),
+        });
+        let base = IconImage::new_full_content(folder, 1.0);
+        let surface = SurfaceColor::new(255, 217, 112);
+        let mut pipeline = LayerPipeline::default();
+        pipeline.folder_color_target.set_config(Some(FolderColorTargetConfig::new(33, 150, 243)));
+        pipeline.shadow.set_config(Some(ShadowConfig::new(0.0, 2.0, 3.0)));
+        pipeline.decal.set_config(Some(DecalConfig::new(SQUARE, 0.4)));
+
+        let positions = [OverlayPosition::TopLeft, OverlayPosition::BottomRight, OverlayPosition::Auto];
+        for (i, position) in positions.into_iter().enumerate() {
+            pipeline.folder_color_target.cache.clear();
+            pipeline.overlay.set_config(Some(SvgOverlayConfig::new(SQUARE, position, 0.25)));
+            let fast = pipeline.render(&base, &surface).unwrap();
+            // Only the first render runs the layers beneath the overlay
+            assert_eq!(pipeline.folder_color_target.cache.is_empty(), i > 0);
+            assert_eq!(pipeline.underlay.cache.len(), 1);
+
+            let mut fresh = pipeline.clone();
+            fresh.underlay.clear();
+            fresh.composite.invalidate();
+            assert_eq!(fresh.render(&base, &surface).unwrap().data(), fast.data());
+        }
+
+        // Changing a layer beneath the overlay stores a new underlay
+        let (_, stored) = pipeline.underlay.cache.values().next().unwrap().clone();
+        pipeline.decal.set_config(Some(DecalConfig::new(SQUARE, 0.3)));
+        pipeline.render(&base, &surface).unwrap();
+        assert_ne!(pipeline.underlay.cache.values().next().unwrap().1, stored);
+    }
+}
@@ -0,0 +1,396 @@
+//! Vector (SVG) output of the layer pipeline.
+//!
+//! [`LayerPipeline::render_svg_composite`] produces a single SVG document
+//! instead of pixels, for resolution-independent icon themes. Every input
+//! is first normalized through usvg — styles resolved, `currentColor`
+//! substituted, shapes converted to paths, every paint written as an
+//! explicit `#rrggbb` value — and then:
+//!
+//! - **Color target** rewrites each base color with the same HSL shift the
+//!   raster path applies per pixel, so flat fills and gradient stops match
+//!   exactly.
+//! - **Decal**, **overlay** and **text** are embedded as transformed
+//!   groups, sized and placed like their raster tiles with the full canvas
+//!   as the content bounds. Text is converted to glyph outlines, so the
+//!   document doesn't depend on the font being installed.
+//! - **Shadows**, standalone or attached to the decal or overlay, become
+//!   `feDropShadow` filters on the groups they shadow.
+//! - **Blend modes** of the decal and overlay become CSS `mix-blend-mode`
+//!   styles.
+
+use resvg::usvg::{Options, Rect, Tree, WriteOptions};
+
+use super::decal::decal_color;
+use super::folder_color_target::ColorShift;
+use super::svg::{recolor_tree, uses_current_color, BlendMode, ParsedSvg};
+use super::text::text_tree;
+use super::{AccentColor, DominantColor, LayerKind, LayerPipeline, OverlayAnchor, OverlayClip, OverlayPosition, RenderContext, ShadowConfig};
+use crate::error::RenderError;
+use crate::icon::{IconImage, SurfaceColor};
+
+impl LayerPipeline {
+    /// Renders the active layers over an SVG base as a single SVG document.
+    ///
+    /// The base SVG's nominal width is used as the logical size for the
+    /// decal's scale curve.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`RenderError::NotVectorRepresentable`] if the color target
+    /// is active and the base embeds raster images (which can't be
+    /// recolored as vectors), a gradient tint is active, the decal is
+    /// clipped to the icon's alpha or samples its color from behind, or the overlay is clipped to it or
+    /// color-adjusted, or an error if any SVG fails to parse.
+    pub fn render_svg_composite(&self, base_svg: &str, surface_color: &SurfaceColor) -> Result<String, RenderError> {
+        let base = Tree::from_str(base_svg, &Options::default())?;
+        let (width, height) = (base.size().width(), base.size().height());
+
+        // Properties the layers read, as in `render`
+        let mut ctx = RenderContext::new(IconImage::new_full_content(image::RgbaImage::new(0, 0), 1.0));
+        ctx.set(*surface_color);
+        if let Some(accent) = self.accent_color() {
+            ctx.set(accent);
+        }
+
+        if self.renders(LayerKind::GradientTint) {
+            return Err(RenderError::NotVectorRepresentable {
+                reason: "the gradient tint blends with rendered pixels",
+            });
+        }
+
+        let mut base_body = inner_markup(&base, "folco-base-");
+        if let Some(config) = self.folder_color_target.config().filter(|_| self.renders(LayerKind::FolderColorTarget)) {
+            if ParsedSvg::parse(base_svg)?.has_images() {
+                return Err(RenderError::NotVectorRepresentable {
+                    reason: "the color target cannot recolor raster images embedded in the base SVG",
+                });
+            }
+            let (r, g, b) = config.effective_target(&ctx);
+            let shift = ColorShift::new(surface_color, (r, g, b), config.hue_approximation);
+            base_body = map_hex_colors(&base_body, |rgb| {
+                if config.protected_colors.iter().any(|p| p.matches(rgb[0], rgb[1], rgb[2])) {
+                    rgb
+                } else {
+                    shift.apply(rgb[0], rgb[1], rgb[2])
+                }
+            });
+            ctx.set(DominantColor::new(r, g, b, 255));
+        }
+
+        let mut document = format!(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" xmlns:xlink="http://www.w3.org/1999/xlink" width="{width}" height="{height}" viewBox="0 0 {width} {height}">"#
+        );
+        let canvas = Rect::from_xywh(0.0, 0.0, width, height).expect("usvg sizes are positive");
+        let min_dim = width.min(height);
+
+        let base_group = format!("\n<g id=\"folco-base\">\n{base_body}</g>");
+        match self.shadow.config().filter(|_| self.renders(LayerKind::Shadow)) {
+            Some(shadow) => document.push_str(&shadowed(&base_group, "folco-base", shadow, canvas)),
+            None => document.push_str(&base_group),
+        }
+
+        let mut decal_rect = None;
+        if let Some(config) = self.decal.config().filter(|_| self.renders(LayerKind::Decal)) {
+            if config.clip_to_alpha {
+                return Err(RenderError::NotVectorRepresentable {
+                    reason: "the decal cannot be clipped to the icon's alpha",
+                });
+            }
+            if config.sample_behind {
+                return Err(RenderError::NotVectorRepresentable {
+                    reason: "the decal samples its color from rendered pixels",
+                });
+            }
+            let scale = config.scale_for_size(width).clamp(0.0, 1.0);
+            let (r, g, b, _) = decal_color(&ctx, config.lighten_below);
+            let svg = config.source.resolve_shared()?;
+            let decal = if uses_current_color(&svg) {
+                let opts = Options {
+                    style_sheet: Some(format!("svg {{ color: #{r:02x}{g:02x}{b:02x} }}")),
+                    ..Options::default()
+                };
+                Tree::from_str(&svg, &opts)?
+            } else {
+                recolor_tree(&Tree::from_str(&svg, &Options::default())?, r, g, b)?
+            };
+            if let Some((group, rect)) = embed(&decal, "folco-decal", min_dim * scale, |w, h| {
+                (canvas.x() + (canvas.width() - w) / 2.0, canvas.y() + (canvas.height() - h) / 2.0)
+            }) {
+                let group = match &config.shadow {
+                    Some(shadow) => shadowed(&group, "folco-decal", shadow, canvas),
+                    None => group,
+                };
+                document.push_str(&blended(group, config.blend_mode, config.opacity));
+                decal_rect = Some(rect);
+            }
+        }
+
+        if let Some(config) = self.overlay.config().filter(|_| self.renders(LayerKind::Overlay)) {
+            // The canvas is the content bounds here, so only alpha clips differ
+            if matches!(config.clip, OverlayClip::BaseAlpha | OverlayClip::BaseAlphaExpanded(_)) {
+                return Err(RenderError::NotVectorRepresentable {
+                    reason: "the overlay cannot be clipped to the icon's alpha",
+                });
+            }
+            if config.color_adjustment.is_some_and(|adjustment| !adjustment.is_identity()) {
+                return Err(RenderError::NotVectorRepresentable {
+                    reason: "the overlay's color adjustment applies to its pixels",
+                });
+            }
+            let svg = config.source.resolve_shared()?;
+            let overlay = match ctx.get::<AccentColor>().copied().filter(|_| config.follow_accent && !config.source.is_raster()) {
+                Some(AccentColor { r, g, b }) if uses_current_color(&svg) => {
+                    let opts = Options {
+                        style_sheet: Some(format!("svg {{ color: #{r:02x}{g:02x}{b:02x} }}")),
+                        ..Options::default()
+                    };
+                    Tree::from_str(&svg, &opts)?
+                }
+                Some(AccentColor { r, g, b }) => recolor_tree(&Tree::from_str(&svg, &Options::default())?, r, g, b)?,
+                None => Tree::from_str(&svg, &Options::default())?,
+            };
+            let anchor = match config.anchor {
+                OverlayAnchor::Content => canvas,
+                OverlayAnchor::Decal => decal_rect.unwrap_or(canvas),
+            };
+            let size = min_dim * config.scale.clamp(0.0, 1.0);
+            if let Some((group, _)) = embed(&overlay, "folco-overlay", size, |w, h| {
+                position(config.position, anchor, decal_rect, w, h)
+            }) {
+                let group = match &config.shadow {
+                    Some(shadow) => shadowed(&group, "folco-overlay", shadow, canvas),
+                    None => group,
+                };
+                document.push_str(&blended(group, config.blend_mode, config.opacity));
+            }
+        }
+
+        if let Some(config) = self.text.config().filter(|_| self.renders(LayerKind::Text)) {
+            let font_size = min_dim * config.size.clamp(0.0, 1.0);
+            if let Some(text) = text_tree(config, &ctx, font_size)? {
+                let size = text.size().width().max(text.size().height());
+                if let Some((group, _)) = embed(&text, "folco-text", size, |w, h| {
+                    position(config.position, canvas, decal_rect, w, h)
+                }) {
+                    document.push_str(&group);
+                }
+            }
+        }
+
+        document.push_str("\n</svg>\n");
+        Ok(document)
+    }
+}
+
+/// Serializes a tree and returns the markup inside its root `<svg>`.
+///
+/// `id_prefix` keeps ids unique once several documents are merged.
+fn inner_markup(tree: &Tree, id_prefix: &str) -> String {
+    let opts = WriteOptions {
+        id_prefix: Some(id_prefix.to_string()),
+        ..WriteOptions::default()
+    };
+    let svg = tree.to_string(&opts);
+    let start = svg.find('>').map_or(svg.len(), |i| i + 1);
+    let end = svg.rfind("</svg>").unwrap_or(svg.len()).max(start);
+    svg[start..end].trim_start_matches('\n').to_string()
+}
+
+/// Wraps `tree` in a group scaled to fit a `size`-square box, placed by
+/// `place(width, height)`. Returns the group and the rectangle it covers,
+/// or `None` if it would be empty.
+fn embed(tree: &Tree, id: &str, size: f32, place: impl FnOnce(f32, f32) -> (f32, f32)) -> Option<(String, Rect)> {
+    let (tree_w, tree_h) = (tree.size().width(), tree.size().height());
+    let scale = size / tree_w.max(tree_h);
+    let rect_size = (tree_w * scale, tree_h * scale);
+    if !(rect_size.0 > 0.0 && rect_size.1 > 0.0) {
+        return None;
+    }
+    let (x, y) = place(rect_size.0, rect_size.1);
+    let rect = Rect::from_xywh(x, y, rect_size.0, rect_size.1)?;
+    let body = inner_markup(tree, &format!("{id}-"));
+    let group = format!("\n<g id=\"{id}\" transform=\"matrix({scale} 0 0 {scale} {x} {y})\">\n{body}</g>");
+    Some((group, rect))
+}
+
+/// Wraps `group` in a drop shadow filter covering `canvas`, which is also
+/// the content bounds the shadow's lengths are relative to.
+fn shadowed(group: &str, id: &str, shadow: &ShadowConfig, canvas: Rect) -> String {
+    let unit = canvas.width().min(canvas.height());
+    let (dx, dy) = (shadow.offset.0 * unit, shadow.offset.1 * unit);
+    let deviation = shadow.blur_radius.max(0.0) * unit / 2.0;
+    let (r, g, b) = shadow.color;
+    let opacity = shadow.opacity.clamp(0.0, 1.0);
+    format!(
+        r##"
+<filter id="{id}-shadow" filterUnits="userSpaceOnUse" x="{x}" y="{y}" width="{w}" height="{h}"><feDropShadow dx="{dx}" dy="{dy}" stdDeviation="{deviation}" flood-color="#{r:02x}{g:02x}{b:02x}" flood-opacity="{opacity}"/></filter>
+<g filter="url(#{id}-shadow)">{group}
+</g>"##,
+        x = canvas.x(),
+        y = canvas.y(),
+        w = canvas.width(),
+        h = canvas.height(),
+    )
+}
+
+/// Wraps `group` in a group with a CSS `mix-blend-mode` and an `opacity`,
+/// unless `mode` is normal and the group opaque.
+fn blended(group: String, mode: BlendMode, opacity: f32) -> String {
+    let opacity = opacity.clamp(0.0, 1.0);
+    let opacity = if opacity < 1.0 { format!(" opacity=\"{opacity}\"") } else { String::new() };
+    if mode.is_normal() && opacity.is_empty() {
+        return group;
+    }
+    format!("\n<g style=\"mix-blend-mode:{}\"{opacity}>{group}\n</g>", mode.css_name())
+}
+
+/// Positions a `width × height` box within `anchor`.
+///
+/// Mirrors the raster overlay placement; [`OverlayPosition::Auto`] picks
+/// the corner overlapping `occupied` the least.
+fn position(position: OverlayPosition, anchor: Rect, occupied: Option<Rect>, width: f32, height: f32) -> (f32, f32) {
+    let (left, top) = (anchor.x(), anchor.y());
+    let right = anchor.right() - width;
+    let bottom = anchor.bottom() - height;
+
+    match position {
+        OverlayPosition::TopLeft => (left, top),
+        OverlayPosition::TopRight => (right, top),
+        OverlayPosition::BottomLeft => (left, bottom),
+        OverlayPosition::BottomRight => (right, bottom),
+        OverlayPosition::Center => ((left + right) / 2.0, (top + bottom) / 2.0),
+        // User units stand in for logical pixels
+        OverlayPosition::Custom { x, y, dx, dy } => {
+            let offset = |d: f32| if d.is_finite() { d } else { 0.0 };
+            (
+                left + (right - left) * x.clamp(0.0, 1.0) + offset(dx),
+                top + (bottom - top) * y.clamp(0.0, 1.0) + offset(dy),
+            )
+        }
+        OverlayPosition::Auto => {
+            let overlap = |&(x, y): &(f32, f32)| {
+                let rect = Rect::from_xywh(x, y, width, height);
+                match (rect, occupied) {
+                    (Some(rect), Some(occupied)) => rect
+                        .intersect(&occupied)
+                        .map_or(0.0, |i| i.width() * i.height()),
+                    _ => 0.0,
+                }
+            };
+            [(right, bottom), (left, bottom), (right, top), (left, top)]
+                .into_iter()
+                // Strict comparison keeps the first of equal candidates
+                .reduce(|best, corner| if overlap(&corner) < overlap(&best) { corner } else { best })
+                .expect("corner list is non-empty")
+        }
+    }
+}
+
+/// Rewrites every `"#rrggbb"` attribute value with `map`.
+///
+/// usvg writes all paints, stop colors and filter colors in this form.
+fn map_hex_colors(markup: &str, map: impl Fn([u8; 3]) -> [u8; 3]) -> String {
+    let mut result = String::with_capacity(markup.len());
+    let mut rest = markup;
+    while let Some(start) = rest.find("=\"#") {
+        let value_start = start + 3;
+        result.push_str(&rest[..value_start]);
+        rest = &rest[value_start..];
+
+        let hex = rest.get(..7).filter(|v| v.ends_with('"')).map(|v| &v[..6]);
+        if let Some(rgb) = hex.and_then(|h| u32::from_str_radix(h, 16).ok().filter(|_| h.len() == 6)) {
+            let [r, g, b] = map([(rgb >> 16) as u8, (rgb >> 8) as u8, rgb as u8]);
+            result.push_str(&format!("{r:02x}{g:02x}{b:02x}"));
+            rest = &rest[6..];
+        }
+    }
+    result.push_str(rest);
+    result
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layer::folder_color_target::apply_folder_color_target;
+    use crate::layer::{DecalConfig, FolderColorTargetConfig, SvgOverlayConfig};
+
+    const BASE: &str = r##"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 32 32" width="64" height="64"><rect width="32" height="32" style="fill:#ffd970"/></svg>"##;
+    const GLYPH: &str = r##"<svg xmlns="http://www.w3.org/2000/svg" width="10" height="10"><rect width="10" height="10" fill="red"/></svg>"##;
+    const SURFACE: SurfaceColor = SurfaceColor::new(255, 217, 112);
+
+    #[test]
+    fn color_target_matches_raster_shift() {
+        let mut pipeline = LayerPipeline::default();
+        let config = FolderColorTargetConfig::new(33, 150, 243);
+        pipeline.folder_color_target.set_config(Some(config.clone()));
+
+        let svg = pipeline.render_svg_composite(BASE, &SURFACE).unwrap();
+        let pixel = IconImage::new_full_content(image::RgbaImage::from_pixel(1, 1, image::Rgba([255, 217, 112, 255])), 1.0);
+        let [r, g, b, _] = apply_folder_color_target(&pixel, &SURFACE, &config).data().get_pixel(0, 0).0;
+
+        assert!(svg.contains(&format!("fill=\"#{r:02x}{g:02x}{b:02x}\"")), "{svg}");
+        assert!(!svg.contains("#ffd970"));
+    }
+
+    #[test]
+    fn tile_layers_become_groups() {
+        let mut pipeline = LayerPipeline::default();
+        pipeline.decal.set_config(Some(DecalConfig::new(GLYPH, 0.5)));
+        pipeline
+            .overlay
+            .set_config(Some(SvgOverlayConfig::new(GLYPH, OverlayPosition::Auto, 0.25).with_opacity(0.5)));
+
+        let svg = pipeline.render_svg_composite(BASE, &SURFACE).unwrap();
+        assert!(svg.contains(r#"<g style="mix-blend-mode:normal" opacity="0.5">"#), "{svg}");
+        // Decal: 32 units centered on the 64-unit canvas
+        assert!(svg.contains(r#"<g id="folco-decal" transform="matrix(3.2 0 0 3.2 16 16)">"#), "{svg}");
+        // Auto overlay: no corner touches the decal, so the preferred bottom-right wins
+        assert!(svg.contains(r#"<g id="folco-overlay" transform="matrix(1.6 0 0 1.6 48 48)">"#), "{svg}");
+
+        let tree = Tree::from_str(&svg, &Options::default()).unwrap();
+        assert_eq!(tree.size().width(), 64.0);
+    }
+
+    #[test]
+    fn shadows_become_filters() {
+        let mut pipeline = LayerPipeline::default();
+        let shadow = ShadowConfig::new(0.1, 0.1, 0.0).with_color(0, 0, 255).with_opacity(1.0);
+        pipeline.decal.set_config(Some(DecalConfig::new(GLYPH, 0.5).with_shadow(shadow)));
+
+        let svg = pipeline.render_svg_composite(BASE, &SURFACE).unwrap();
+        assert!(svg.contains(r#"<g filter="url(#folco-decal-shadow)">"#), "{svg}");
+
+        // The decal spans 16..48; its shadow is offset by 6.4 units
+        let tree = Tree::from_str(&svg, &Options::default()).unwrap();
+        let mut pixmap = resvg::tiny_skia::Pixmap::new(64, 64).unwrap();
+        resvg::render(&tree, resvg::tiny_skia::Transform::default(), &mut pixmap.as_mut());
+        let pixel = pixmap.pixel(52, 52).unwrap();
+        assert_eq!((pixel.red(), pixel.green(), pixel.blue()), (0, 0, 255));
+    }
+
+    #[test]
+    fn embedded_images_block_recoloring() {
+        let base = r#"<svg xmlns="http://www.w3.org/2000/svg" width="8" height="8"><image width="8" height="8" href="data:image/png;base64,"/></svg>"#;
+        let mut pipeline = LayerPipeline::default();
+        assert!(pipeline.render_svg_composite(base, &SURFACE).is_ok());
+
+        pipeline
+            .folder_color_target
+            .set_config(Some(FolderColorTargetConfig::new(33, 150, 243)));
+        assert!(matches!(
+            pipeline.render_svg_composite(base, &SURFACE),
+            Err(RenderError::NotVectorRepresentable { .. })
+        ));
+    }
+
+    #[test]
+    fn hex_colors_are_mapped() {
+        let markup = r##"<path fill="#102030" stroke="none" href="#id"/><stop stop-color="#abcdef"/>"##;
+        let mapped = map_hex_colors(markup, |[r, g, b]| [b, g, r]);
+        assert_eq!(mapped, r##"<path fill="#302010" stroke="none" href="#id"/><stop stop-color="#efcdab"/>"##);
+    }
+}
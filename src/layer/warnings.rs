@@ -0,0 +1,159 @@
+//! Non-fatal problems noticed while rendering.
+//!
+//! Some inputs render without error yet not as intended: a decal scaled
+//! below a pixel at small sizes, an SVG with nothing to draw, a label the
+//! font has no glyphs for. Layers record these as [`RenderWarning`]s on
+//! the [`RenderContext`] instead of failing or silently drawing nothing.
+//! The pipeline returns them in the [`RenderReport`](super::RenderReport)
+//! and passes each to its [warning handler](LayerPipeline::set_warning_handler),
+//! if one is set.
+//!
+//! Warnings are raised when a layer actually renders, so a result served
+//! from a cache doesn't repeat the warnings of the render that filled it.
+
+use std::fmt;
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+
+use super::{LayerKind, LayerPipeline, RenderContext};
+
+/// A condition that made a layer draw less than configured.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "tsify", derive(tsify_next::Tsify))]
+#[cfg_attr(feature = "tsify", tsify(into_wasm_abi, from_wasm_abi))]
+#[serde(tag = "kind", rename_all = "kebab-case", rename_all_fields = "camelCase")]
+pub enum RenderWarning {
+    /// The layer scaled to less than a pixel at this size, so it was
+    /// skipped.
+    ZeroSize {
+        /// The skipped layer.
+        layer: LayerKind,
+    },
+    /// The layer's SVG parsed but has nothing to draw.
+    EmptySvg {
+        /// The layer holding the SVG.
+        layer: LayerKind,
+    },
+    /// The font has no visible glyphs for the label.
+    NoVisibleText {
+        /// The label text.
+        text: String,
+    },
+    /// A warning raised by a custom layer.
+    Other {
+        /// What went wrong.
+        message: String,
+    },
+}
+
+impl fmt::Display for RenderWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ZeroSize { layer } => write!(f, "{layer:?} layer is smaller than a pixel at this size"),
+            Self::EmptySvg { layer } => write!(f, "{layer:?} layer's SVG has nothing to draw"),
+            Self::NoVisibleText { text } => write!(f, "no visible glyphs for label {text:?}"),
+            Self::Other { message } => f.write_str(message),
+        }
+    }
+}
+
+/// Warnings raised so far in a render, in the order raised.
+#[derive(Debug, Clone, Default)]
+struct Warnings(Vec<RenderWarning>);
+
+/// Receives each warning a [`LayerPipeline`] render raises.
+pub type WarningHandler = Arc<dyn Fn(&RenderWarning) + Send + Sync>;
+
+impl RenderContext {
+    /// Records a warning for the render in progress.
+    pub fn warn(&mut self, warning: RenderWarning) {
+        let mut warnings = self.get::<Warnings>().cloned().unwrap_or_default();
+        warnings.0.push(warning);
+        self.set(warnings);
+    }
+
+    /// Returns the warnings raised so far, in the order raised.
+    pub fn warnings(&self) -> &[RenderWarning] {
+        self.get::<Warnings>().map_or(&[], |w| &w.0)
+    }
+}
+
+impl LayerPipeline {
+    /// Calls `handler` with every warning raised by later renders, for
+    /// logging them in production.
+    ///
+    /// ```
+    /// use std::sync::{Arc, Mutex};
+    /// use folco_renderer::layer::warnings::RenderWarning;
+    /// use folco_renderer::{DecalConfig, IconImage, LayerPipeline, SurfaceColor};
+    /// use image::RgbaImage;
+    ///
+    /// let seen = Arc::new(Mutex::new(Vec::new()));
+    /// let log = Arc::clone(&seen);
+    /// let mut pipeline = LayerPipeline::default();
+    /// pipeline.set_warning_handler(move |warning: &RenderWarning| log.lock().unwrap().push(warning.to_string()));
+    ///
+    /// // A 5% decal is under a pixel on a 16px icon
+    /// pipeline.decal.set_config(Some(DecalConfig::new("<svg/>", 0.05)));
+    /// let base = IconImage::new_full_content(RgbaImage::new(16, 16), 1.0);
+    /// pipeline.render(&base, &SurfaceColor::new(255, 217, 112)).unwrap();
+    /// assert_eq!(seen.lock().unwrap().len(), 1);
+    /// ```
+    pub fn set_warning_handler(&mut self, handler: impl Fn(&RenderWarning) + Send + Sync + 'static) {
+        self.warning_handler = Some(Arc::new(handler));
+    }
+
+    /// Removes the handler set with
+    /// [`set_warning_handler`](Self::set_warning_handler).
+    pub fn clear_warning_handler(&mut self) {
+        self.warning_handler = None;
+    }
+
+    /// Passes the warnings raised in `ctx` to the handler, if any.
+    pub(crate) fn report_warnings(&self, ctx: &RenderContext) {
+        if let Some(handler) = &self.warning_handler {
+            ctx.warnings().iter().for_each(|warning| handler(warning));
+        }
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::icon::{IconImage, SurfaceColor};
+    use crate::layer::{DecalConfig, OverlayPosition, SvgOverlayConfig};
+    use image::RgbaImage;
+
+    const EMPTY: &str = r#"<svg xmlns="http://www.w3.org/2000/svg" width="10" height="10"/>"#;
+    const SQUARE: &str = r##"<svg xmlns="http://www.w3.org/2000/svg" width="10" height="10"><rect width="10" height="10" fill="#000"/></svg>"##;
+
+    #[test]
+    fn silent_fallbacks_are_reported() {
+        let surface = SurfaceColor::new(255, 217, 112);
+        let mut pipeline = LayerPipeline::default();
+        pipeline.decal.set_config(Some(DecalConfig::new(SQUARE, 0.05)));
+        pipeline.overlay.set_config(Some(SvgOverlayConfig::new(EMPTY, OverlayPosition::Center, 0.5)));
+
+        let small = IconImage::new_full_content(RgbaImage::new(16, 16), 1.0);
+        let (_, report) = pipeline.render_with_report(&small, &surface).unwrap();
+        assert_eq!(
+            report.warnings,
+            [
+                RenderWarning::ZeroSize { layer: LayerKind::Decal },
+                RenderWarning::EmptySvg { layer: LayerKind::Overlay },
+            ]
+        );
+
+        // The decal is visible at 64px
+        let large = IconImage::new_full_content(RgbaImage::new(64, 64), 1.0);
+        let (_, report) = pipeline.render_with_report(&large, &surface).unwrap();
+        assert_eq!(report.warnings, [RenderWarning::EmptySvg { layer: LayerKind::Overlay }]);
+        let (_, cached) = pipeline.render_with_report(&large, &surface).unwrap();
+        assert!(cached.cached && cached.warnings.is_empty());
+    }
+}
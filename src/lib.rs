@@ -47,24 +47,71 @@
 //! let json = exported.to_json().unwrap();
 //! ```
 
+pub mod analysis;
+pub mod animation;
+mod asset;
+pub mod batch;
+#[cfg(feature = "import-compat")]
+pub mod compat;
 pub mod folder_color;
+mod icns;
+mod ico;
 mod customizer;
 mod error;
-mod icon;
-mod layer;
+pub mod export;
+#[cfg(feature = "extract")]
+pub mod extract;
+pub mod icon;
+mod image_hash;
+pub mod layer;
+pub mod presets;
+pub mod preview;
+#[cfg(feature = "system")]
+pub mod platform;
 mod profile;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod queue;
+mod render_id;
+mod scale;
+#[cfg(feature = "system")]
+pub mod system;
 
-pub use customizer::{Configurable, FolderIconCustomizer};
+pub use analysis::{
+    DominantColorSampler, LegibilityReport, LegibilityWarning, SamplingStrategy, SizeLegibility,
+};
+pub use animation::{AnimatedProfile, FramePlan, FrameScheduler, Keyframes};
+pub use asset::{AssetData, AssetError, AssetHandle, AssetRegistry};
+pub use batch::IconBatchRenderer;
+pub use customizer::{ApplyReport, ApplyWarning, Configurable, FolderIconCustomizer, RenderedIcon};
 pub use error::RenderError;
+pub use folder_color::{FolderColor, FolderColorMetadata};
 pub use icon::{
-    FolderIconBase, IconImage, IconSet, RectPx, SerializableFolderIconBase, SerializableIconImage, SizePx,
-    SurfaceColor,
+    FolderIconBase, IconImage, IconSet, IconVariant, ImageStats, MergeConflict, MergePolicy, RectPx,
+    SHADOW_ALPHA_CUTOFF, SerializableFolderIconBase, SerializableIconImage, SizePx, SurfaceColor,
+    TransparencyPolicy,
 };
+pub use image_hash::ImageHash;
 pub use layer::{
-    CacheKey, DecalConfig, DominantColor, FolderColorTargetConfig, Layer, LayerConfig,
-    LayerPipeline, LayerVersions, OverlayPosition, RenderContext,
-    SvgOverlayConfig, SvgSource,
+    AccentColor, BlendMode, CacheKey, CacheStats, ColorAdjustment, CostEstimate, DecalConfig,
+    DecalPlacement, DerivedPalette, DominantColor, DynamicPipeline, EffectiveSize,
+    FolderColorTargetConfig, GradientKind, GradientStop, GradientTintConfig, HueApproximation,
+    Layer, LayerConfig, LayerId, LayerKind, LayerLayout, LayerPipeline, LayerVersions,
+    MinSizePolicy, OccupiedRegions, OverlayAnchor, OverlayArrangement, OverlayClip, OverlayPosition,
+    ParsedSvg, PipelineLayer, PlacementRounding, ProtectedColor, RenderContext, RenderLimits,
+    RenderQuality, RenderReport, RenderWarning, ResolvedSvg, ScaleBreakpoint, ShadowConfig,
+    SvgOverlayConfig, SvgSource, TextFont, TextLayerConfig, Thumbnail, feather_edges,
 };
-pub use profile::CustomizationProfile;
-pub use folder_color::{FolderColor, FolderColorMetadata};
+pub use profile::{
+    CustomizationProfile, LayerPreset, ParseOptions, PresetRefs, ProfileFragmentError,
+    ProfileParseReport, ProfileParseWarning, ProfilePatchError, ProfilePresetError,
+    ProfileValueError,
+};
+pub use render_id::{PIXEL_PIPELINE_VERSION, RenderId};
+
+/// Internal entry points exposed for the `fuzz/` harness. Not a stable API.
+#[cfg(feature = "fuzzing")]
+#[doc(hidden)]
+pub mod fuzzing {
+    pub use crate::layer::svg::{render_svg, render_svg_with_color};
+}
 
@@ -0,0 +1,67 @@
+//! macOS icons through AppKit.
+//!
+//! ```no_run
+//! if let Some(icons) = folco_renderer::platform::macos::load_system_folder_icon() {
+//!     println!("{} folder icon sizes", icons.len());
+//! }
+//! ```
+
+use objc2::AnyThread;
+use objc2_app_kit::{NSImage, NSImageRep, NSWorkspace};
+use objc2_foundation::{NSSize, NSString};
+
+use crate::icon::{IconImage, IconSet};
+
+/// Loads the generic folder icon from `NSWorkspace`, at every size it
+/// provides.
+///
+/// The icon is the one the workspace hands out for a plain directory (the
+/// temporary directory, which never carries a custom icon), so it follows
+/// the running macOS version and its appearance. Each representation is
+/// rendered at its own pixel size, with the scale it implies (2.0 for the
+/// @2x sizes). Returns `None` if no representation renders.
+///
+/// Images keep full-image content bounds; see
+/// [`system::folder_icon_base`](crate::system::folder_icon_base) for a
+/// base ready to customize.
+pub fn load_system_folder_icon() -> Option<IconSet> {
+    let directory = std::env::temp_dir();
+    let workspace = NSWorkspace::sharedWorkspace();
+    let folder = workspace.iconForFile(&NSString::from_str(directory.to_str()?));
+
+    let mut icons = IconSet::new();
+    for rep in folder.representations().iter() {
+        let Some(icon) = render_rep(&rep) else {
+            continue;
+        };
+        let duplicate = icons
+            .iter()
+            .any(|other| other.data().dimensions() == icon.data().dimensions() && other.scale == icon.scale);
+        if !duplicate {
+            icons.add_image(icon);
+        }
+    }
+    icons.sort_canonical();
+    (!icons.is_empty()).then_some(icons)
+}
+
+/// Renders one image representation at its pixel size.
+///
+/// Icon representations are not necessarily bitmaps, so the rep is drawn
+/// into an image of its pixel size and read back as TIFF.
+fn render_rep(rep: &NSImageRep) -> Option<IconImage> {
+    let (width, height) = (rep.pixelsWide(), rep.pixelsHigh());
+    let logical = rep.size();
+    if width <= 0 || height <= 0 || logical.width <= 0.0 {
+        return None;
+    }
+
+    let image = NSImage::initWithSize(NSImage::alloc(), NSSize::new(width as f64, height as f64));
+    image.addRepresentation(rep);
+    let tiff = image.TIFFRepresentation()?.to_vec();
+    let data = image::load_from_memory_with_format(&tiff, image::ImageFormat::Tiff)
+        .ok()?
+        .to_rgba8();
+    let scale = (data.width() as f64 / logical.width) as f32;
+    Some(IconImage::new_full_content(data, scale))
+}
@@ -0,0 +1,14 @@
+//! Native OS APIs for loading system icons.
+//!
+//! Where [`system`](crate::system) shells out to settings tools, these
+//! modules link the platform's own icon APIs, so the artwork matches what
+//! the file manager draws. Each module is only compiled on its platform,
+//! and only when the `system` feature is enabled.
+//!
+//! - [`macos`]: the generic folder icon from `NSWorkspace`.
+//! - [`windows`]: the folder icon from the shell's stock icons.
+
+#[cfg(target_os = "macos")]
+pub mod macos;
+#[cfg(target_os = "windows")]
+pub mod windows;
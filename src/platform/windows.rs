@@ -0,0 +1,129 @@
+//! Windows icons through the shell.
+//!
+//! ```no_run
+//! if let Some(icons) = folco_renderer::platform::windows::load_folder_icon() {
+//!     println!("{} folder icon sizes", icons.len());
+//! }
+//! ```
+
+use std::ptr::null_mut;
+
+use image::RgbaImage;
+use windows_sys::Win32::Graphics::Gdi::{
+    BITMAPINFO, BITMAPINFOHEADER, BI_RGB, CreateCompatibleDC, DIB_RGB_COLORS, DeleteDC, DeleteObject, GetDIBits, HBITMAP, HDC,
+};
+use windows_sys::Win32::UI::Shell::{SHDefExtractIconW, SHGetStockIconInfo, SHGSI_ICONLOCATION, SHSTOCKICONINFO, SIID_FOLDER};
+use windows_sys::Win32::UI::WindowsAndMessaging::{DestroyIcon, GetIconInfo, HICON, ICONINFO};
+
+use crate::icon::{IconImage, IconSet};
+
+/// Pixel sizes the shell draws icons at, from the small list view up to
+/// extra large icons.
+const SHELL_ICON_SIZES: [u32; 10] = [16, 20, 24, 32, 40, 48, 64, 96, 128, 256];
+
+/// Loads the folder icon from the shell's stock icons, at every standard
+/// shell size.
+///
+/// `SHGetStockIconInfo(SIID_FOLDER)` names the icon resource the shell
+/// uses for folders, and each size is extracted from it with
+/// `SHDefExtractIconW`, so the artwork follows the running Windows
+/// version. All images are at scale 1.0. Returns `None` if the stock icon
+/// can't be located or no size loads.
+///
+/// Images keep full-image content bounds; see
+/// [`system::folder_icon_base`](crate::system::folder_icon_base) for a
+/// base ready to customize.
+pub fn load_folder_icon() -> Option<IconSet> {
+    // SAFETY: the struct is zeroed plain data with its size set, as the
+    // API requires.
+    let mut info: SHSTOCKICONINFO = unsafe { std::mem::zeroed() };
+    info.cbSize = size_of::<SHSTOCKICONINFO>() as u32;
+    // SAFETY: `info` is a valid, writable SHSTOCKICONINFO.
+    if unsafe { SHGetStockIconInfo(SIID_FOLDER, SHGSI_ICONLOCATION, &mut info) } != 0 {
+        return None;
+    }
+
+    let mut icons = IconSet::new();
+    for size in SHELL_ICON_SIZES {
+        let mut icon: HICON = null_mut();
+        // SAFETY: `szPath` is NUL-terminated by the shell, and only the
+        // large icon is requested, at `size` in the low word.
+        let result = unsafe { SHDefExtractIconW(info.szPath.as_ptr(), info.iIcon, 0, &mut icon, null_mut(), size) };
+        if result != 0 || icon.is_null() {
+            continue;
+        }
+        let data = icon_pixels(icon);
+        // SAFETY: the icon was created by SHDefExtractIconW and is not
+        // used afterwards.
+        unsafe { DestroyIcon(icon) };
+        if let Some(data) = data {
+            icons.add_image(IconImage::new_full_content(data, 1.0));
+        }
+    }
+    icons.sort_canonical();
+    (!icons.is_empty()).then_some(icons)
+}
+
+/// Reads an icon's pixels as RGBA.
+///
+/// Icons without an alpha channel take their transparency from the AND
+/// mask instead.
+fn icon_pixels(icon: HICON) -> Option<RgbaImage> {
+    // SAFETY: ICONINFO is plain data, filled in by GetIconInfo.
+    let mut info: ICONINFO = unsafe { std::mem::zeroed() };
+    // SAFETY: `icon` is a valid icon handle and `info` is writable.
+    if unsafe { GetIconInfo(icon, &mut info) } == 0 {
+        return None;
+    }
+    // SAFETY: a memory DC compatible with the screen, deleted below.
+    let dc = unsafe { CreateCompatibleDC(null_mut()) };
+    let pixels = (!info.hbmColor.is_null())
+        .then(|| bitmap_bgra(dc, info.hbmColor))
+        .flatten()
+        .map(|(width, height, mut bgra)| {
+            if bgra.chunks_exact(4).all(|pixel| pixel[3] == 0) {
+                let mask = bitmap_bgra(dc, info.hbmMask).map(|(_, _, mask)| mask);
+                for (i, pixel) in bgra.chunks_exact_mut(4).enumerate() {
+                    // A set mask bit (white) is transparent
+                    let transparent = mask.as_ref().is_some_and(|mask| mask.get(i * 4) == Some(&0xff));
+                    pixel[3] = if transparent { 0 } else { 0xff };
+                }
+            }
+            for pixel in bgra.chunks_exact_mut(4) {
+                pixel.swap(0, 2);
+            }
+            (width, height, bgra)
+        });
+    // SAFETY: the DC and both bitmaps were created for this call and
+    // belong to it; deleting a null bitmap is a no-op.
+    unsafe {
+        DeleteDC(dc);
+        DeleteObject(info.hbmColor);
+        DeleteObject(info.hbmMask);
+    }
+    let (width, height, rgba) = pixels?;
+    RgbaImage::from_raw(width, height, rgba)
+}
+
+/// Reads a bitmap as top-down 32-bit BGRA, with its width and height.
+fn bitmap_bgra(dc: HDC, bitmap: HBITMAP) -> Option<(u32, u32, Vec<u8>)> {
+    // SAFETY: zeroed BITMAPINFO is valid input for a size query.
+    let mut header: BITMAPINFO = unsafe { std::mem::zeroed() };
+    header.bmiHeader.biSize = size_of::<BITMAPINFOHEADER>() as u32;
+    // SAFETY: with no bits buffer, GetDIBits only fills in the header.
+    if unsafe { GetDIBits(dc, bitmap, 0, 0, null_mut(), &mut header, DIB_RGB_COLORS) } == 0 {
+        return None;
+    }
+    let width = u32::try_from(header.bmiHeader.biWidth).ok()?;
+    let height = header.bmiHeader.biHeight.unsigned_abs();
+
+    header.bmiHeader.biHeight = -(height as i32);
+    header.bmiHeader.biBitCount = 32;
+    header.bmiHeader.biCompression = BI_RGB;
+    header.bmiHeader.biSizeImage = 0;
+    let mut bgra = vec![0u8; width as usize * height as usize * 4];
+    // SAFETY: `bgra` holds `height` rows of `width` 32-bit pixels, the
+    // format the header requests.
+    let lines = unsafe { GetDIBits(dc, bitmap, 0, height, bgra.as_mut_ptr().cast(), &mut header, DIB_RGB_COLORS) };
+    (lines == height as i32).then_some((width, height, bgra))
+}
@@ -0,0 +1,95 @@
+//! Curated profiles bundled with the crate.
+//!
+//! Each preset recolors the folder and imprints a decal suited to a common
+//! kind of folder, as a one-click starting point that users can tweak.
+//! Look one up with [`CustomizationProfile::preset`] or [`get`], and list
+//! them with [`NAMES`] or [`all`].
+//!
+//! ```
+//! use folco_renderer::presets;
+//! use folco_renderer::CustomizationProfile;
+//!
+//! assert!(presets::NAMES.contains(&"Archive"));
+//! let archive = CustomizationProfile::preset("archive").unwrap();
+//! assert!(archive.decal.is_some());
+//! ```
+
+use crate::layer::{BlendMode, DecalConfig, FolderColorTargetConfig, GradientStop, GradientTintConfig};
+use crate::profile::CustomizationProfile;
+
+/// The preset names, in display order.
+pub const NAMES: [&str; 5] = ["Work", "Archive", "Media", "Code", "Downloads"];
+
+const BRIEFCASE: &str = r##"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 24 24"><path fill="#000" fill-rule="evenodd" d="M9 3h6a2 2 0 0 1 2 2v2h3a2 2 0 0 1 2 2v10a2 2 0 0 1-2 2H4a2 2 0 0 1-2-2V9a2 2 0 0 1 2-2h3V5a2 2 0 0 1 2-2zm0 2v2h6V5H9z"/></svg>"##;
+
+const ARCHIVE_BOX: &str = r##"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 24 24"><path fill="#000" fill-rule="evenodd" d="M3 3h18v5H3V3zm1 6h16v10a2 2 0 0 1-2 2H6a2 2 0 0 1-2-2V9zm5 3v2h6v-2H9z"/></svg>"##;
+
+const PLAY: &str = r##"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 24 24"><path fill="#000" fill-rule="evenodd" d="M12 2a10 10 0 1 1 0 20 10 10 0 0 1 0-20zm-2 5.5v9l7-4.5-7-4.5z"/></svg>"##;
+
+const BRACKETS: &str = r##"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 24 24"><path fill="none" stroke="#000" stroke-width="2.5" stroke-linecap="round" stroke-linejoin="round" d="M8 6l-6 6 6 6M16 6l6 6-6 6M14 4l-4 16"/></svg>"##;
+
+const ARROW_DOWN: &str = r##"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 24 24"><path fill="#000" d="M10 3h4v9h4l-6 7-6-7h4V3zM4 20h16v2H4z"/></svg>"##;
+
+/// Returns the preset named `name`, ignoring ASCII case.
+pub fn get(name: &str) -> Option<CustomizationProfile> {
+    let name = NAMES.iter().find(|n| n.eq_ignore_ascii_case(name))?;
+    Some(match *name {
+        "Work" => CustomizationProfile::new()
+            .with_folder_color_target(FolderColorTargetConfig::new(33, 150, 243))
+            .with_decal(DecalConfig::new(BRIEFCASE, 0.4)),
+        "Archive" => CustomizationProfile::new()
+            .with_folder_color_target(FolderColorTargetConfig::new(141, 110, 99))
+            .with_decal(DecalConfig::new(ARCHIVE_BOX, 0.4)),
+        "Media" => CustomizationProfile::new()
+            .with_folder_color_target(FolderColorTargetConfig::new(142, 68, 173))
+            .with_gradient_tint(
+                GradientTintConfig::linear(180.0, vec![GradientStop::new(0.0, 255, 255, 255), GradientStop::new(1.0, 0, 0, 0)])
+                    .with_blend_mode(BlendMode::SoftLight)
+                    .with_opacity(0.4),
+            )
+            .with_decal(DecalConfig::new(PLAY, 0.42)),
+        "Code" => CustomizationProfile::new()
+            .with_folder_color_target(FolderColorTargetConfig::new(67, 160, 71))
+            .with_decal(DecalConfig::new(BRACKETS, 0.45)),
+        "Downloads" => CustomizationProfile::new()
+            .with_folder_color_target(FolderColorTargetConfig::new(0, 150, 136))
+            .with_decal(DecalConfig::new(ARROW_DOWN, 0.4)),
+        _ => unreachable!("every name in NAMES has a profile"),
+    })
+}
+
+/// Returns every preset with its name, in display order.
+pub fn all() -> impl Iterator<Item = (&'static str, CustomizationProfile)> {
+    NAMES.into_iter().map(|name| (name, get(name).expect("every name in NAMES has a profile")))
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::customizer::{Configurable, FolderIconCustomizer};
+    use crate::icon::{FolderIconBase, IconImage, IconSet, SurfaceColor};
+    use image::{Rgba, RgbaImage};
+
+    #[test]
+    fn every_preset_renders() {
+        let mut icons = IconSet::new();
+        icons.add_image(IconImage::new_full_content(RgbaImage::from_pixel(32, 32, Rgba([255, 217, 112, 255])), 1.0));
+        let surface = SurfaceColor::new(255, 217, 112);
+        let mut customizer = FolderIconCustomizer::new(FolderIconBase::new(icons, surface));
+
+        for (name, profile) in all() {
+            customizer.apply_profile(&profile);
+            let base = customizer.base_icons()[0].clone();
+            let (_, report) = customizer.pipeline.render_with_report(&base, &surface).unwrap();
+            assert!(report.warnings.is_empty(), "{name}: {:?}", report.warnings);
+            let json = profile.to_json().unwrap();
+            assert_eq!(CustomizationProfile::from_json(&json).unwrap().to_json().unwrap(), json, "{name}");
+        }
+        assert!(get("WORK").is_some());
+        assert!(get("Vacation").is_none());
+    }
+}
@@ -0,0 +1,137 @@
+//! Layout guides drawn over rendered icons.
+
+use image::{Pixel, Rgba, RgbaImage};
+
+use crate::icon::{IconImage, RectPx};
+use crate::layer::LayerLayout;
+
+/// Colors and line width for [`with_guides`].
+///
+/// Colors are RGBA; `None` hides that guide. The defaults outline the
+/// content bounds in cyan, the safe area in yellow and layer placements in
+/// magenta, with no grid.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GuideStyle {
+    /// Line width in image pixels. Lines are drawn inside each rectangle.
+    pub line_width: u32,
+    /// Content bounds outline color.
+    pub content_bounds: Option<(u8, u8, u8, u8)>,
+    /// Safe area outline color.
+    pub safe_area: Option<(u8, u8, u8, u8)>,
+    /// Decal, overlay and text placement outline color.
+    pub placements: Option<(u8, u8, u8, u8)>,
+    /// Grid spacing in logical pixels, measured from the content bounds'
+    /// top-left corner. 0 hides the grid.
+    pub grid_spacing: u32,
+    /// Grid line color.
+    pub grid_color: (u8, u8, u8, u8),
+}
+
+impl Default for GuideStyle {
+    fn default() -> Self {
+        Self {
+            line_width: 1,
+            content_bounds: Some((0, 200, 255, 255)),
+            safe_area: Some((255, 200, 0, 255)),
+            placements: Some((255, 0, 200, 255)),
+            grid_spacing: 0,
+            grid_color: (128, 128, 128, 96),
+        }
+    }
+}
+
+impl GuideStyle {
+    /// Sets the line width. Clamped to at least 1 pixel.
+    pub fn with_line_width(mut self, line_width: u32) -> Self {
+        self.line_width = line_width.max(1);
+        self
+    }
+
+    /// Shows a grid every `spacing` logical pixels.
+    pub fn with_grid(mut self, spacing: u32, color: (u8, u8, u8, u8)) -> Self {
+        self.grid_spacing = spacing;
+        self.grid_color = color;
+        self
+    }
+}
+
+/// Returns a copy of `icon` with `layout` outlined over it.
+///
+/// Draws, bottom to top, the grid, safe area, content bounds and layer
+/// placements. The rectangles are the ones the pipeline used, so the
+/// outlines match the rendered pixels exactly.
+///
+/// # Example
+///
+/// ```
+/// use folco_renderer::preview::{with_guides, GuideStyle};
+/// use folco_renderer::{IconImage, LayerPipeline, SurfaceColor};
+/// use image::RgbaImage;
+///
+/// let base = IconImage::new_full_content(RgbaImage::new(32, 32), 1.0);
+/// let (icon, layout) = LayerPipeline::default().render_with_layout(&base, &SurfaceColor::new(255, 217, 112)).unwrap();
+/// let guided = with_guides(&icon, &layout, &GuideStyle::default().with_grid(8, (0, 0, 0, 64)));
+/// assert_eq!(guided.data().get_pixel(0, 0).0, [0, 200, 255, 255]);
+/// ```
+pub fn with_guides(icon: &IconImage, layout: &LayerLayout, style: &GuideStyle) -> IconImage {
+    let mut guided = icon.clone();
+    let image = guided.data_mut();
+    let width = style.line_width.max(1);
+
+    if style.grid_spacing > 0 {
+        draw_grid(image, &layout.content_bounds, style.grid_spacing as f32 * icon.scale, style.grid_color);
+    }
+    if let Some(color) = style.safe_area {
+        outline(image, &layout.safe_area, width, color);
+    }
+    if let Some(color) = style.content_bounds {
+        outline(image, &layout.content_bounds, width, color);
+    }
+    if let Some(color) = style.placements {
+        for rect in layout.placements() {
+            outline(image, &rect, width, color);
+        }
+    }
+    guided
+}
+
+/// Draws one-pixel grid lines every `spacing` image pixels inside `bounds`.
+fn draw_grid(image: &mut RgbaImage, bounds: &RectPx, spacing: f32, color: (u8, u8, u8, u8)) {
+    if spacing.is_nan() || spacing < 1.0 {
+        return;
+    }
+    let lines = |start: u32, len: u32| {
+        (1..)
+            .map(move |i| start + (i as f32 * spacing).round() as u32)
+            .take_while(move |&at| at < start + len)
+    };
+    for x in lines(bounds.x, bounds.width) {
+        fill(image, &RectPx::new(x, bounds.y, 1, bounds.height), color);
+    }
+    for y in lines(bounds.y, bounds.height) {
+        fill(image, &RectPx::new(bounds.x, y, bounds.width, 1), color);
+    }
+}
+
+/// Outlines `rect` with lines `width` pixels wide, drawn inside it.
+fn outline(image: &mut RgbaImage, rect: &RectPx, width: u32, color: (u8, u8, u8, u8)) {
+    let (w, h) = (width.min(rect.width), width.min(rect.height));
+    fill(image, &RectPx::new(rect.x, rect.y, rect.width, h), color);
+    fill(image, &RectPx::new(rect.x, rect.bottom() - h, rect.width, h), color);
+    // The sides skip the corners the top and bottom already covered
+    let side = rect.height.saturating_sub(2 * h);
+    fill(image, &RectPx::new(rect.x, rect.y + h, w, side), color);
+    fill(image, &RectPx::new(rect.right() - w, rect.y + h, w, side), color);
+}
+
+/// Blends `color` over every pixel of `rect` inside the image.
+fn fill(image: &mut RgbaImage, rect: &RectPx, color: (u8, u8, u8, u8)) {
+    let (r, g, b, a) = color;
+    let right = rect.right().min(image.width());
+    let bottom = rect.bottom().min(image.height());
+    for y in rect.y..bottom {
+        for x in rect.x..right {
+            image.get_pixel_mut(x, y).blend(&Rgba([r, g, b, a]));
+        }
+    }
+}
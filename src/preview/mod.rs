@@ -0,0 +1,172 @@
+//! Preview-only tools for development and theme authoring.
+//!
+//! - [`simulate_cvd`] shows how an icon looks with a color vision
+//!   deficiency, so theme authors can check that folder colors stay
+//!   distinguishable.
+//! - [`with_guides`] outlines the content bounds, safe area and layer
+//!   placements of a [`LayerLayout`](crate::LayerLayout) over an icon, for
+//!   editors that show where things will land.
+//! - `PreviewWindow` opens a window showing every rendered size of a
+//!   [`FolderIconCustomizer`](crate::FolderIconCustomizer) side by side on
+//!   a checkerboard, optionally reloading a
+//!   [`CustomizationProfile`](crate::CustomizationProfile) JSON file
+//!   whenever it changes on disk. This makes it possible to iterate on a
+//!   layer without a full frontend. Only available when the
+//!   `preview-window` feature is enabled.
+//!
+//! # Example
+//!
+//! ```no_run
+//! # #[cfg(feature = "preview-window")]
+//! # {
+//! use folco_renderer::{FolderIconCustomizer, FolderIconBase, IconSet, SurfaceColor};
+//! use folco_renderer::preview::PreviewWindow;
+//!
+//! let surface = SurfaceColor::new(255, 217, 112);
+//! let mut customizer = FolderIconCustomizer::new(FolderIconBase::new(IconSet::new(), surface));
+//!
+//! PreviewWindow::new()
+//!     .watch_profile("profile.json")
+//!     .run(&mut customizer)
+//!     .unwrap();
+//! # }
+//! ```
+
+mod guides;
+#[cfg(feature = "preview-window")]
+mod window;
+
+pub use guides::{with_guides, GuideStyle};
+
+#[cfg(feature = "preview-window")]
+pub use window::{PreviewError, PreviewWindow};
+
+use serde::{Deserialize, Serialize};
+
+use crate::icon::IconImage;
+
+// ============================================================================
+// Color vision deficiency simulation
+// ============================================================================
+
+/// A type of color vision deficiency to simulate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "tsify", derive(tsify_next::Tsify))]
+#[cfg_attr(feature = "tsify", tsify(into_wasm_abi, from_wasm_abi))]
+#[serde(rename_all = "kebab-case")]
+pub enum CvdKind {
+    /// No functioning long-wavelength (red) cones.
+    Protanopia,
+    /// No functioning medium-wavelength (green) cones.
+    Deuteranopia,
+    /// No functioning short-wavelength (blue) cones.
+    Tritanopia,
+}
+
+impl CvdKind {
+    /// Every kind, for rendering comparison strips.
+    pub const ALL: [CvdKind; 3] = [Self::Protanopia, Self::Deuteranopia, Self::Tritanopia];
+
+    /// The Machado, Oliveira & Fernandes (2009) matrix at full severity,
+    /// applied to linear RGB.
+    fn matrix(self) -> [[f32; 3]; 3] {
+        match self {
+            Self::Protanopia => [
+                [0.152286, 1.052583, -0.204868],
+                [0.114503, 0.786281, 0.099216],
+                [-0.003882, -0.048116, 1.051998],
+            ],
+            Self::Deuteranopia => [
+                [0.367322, 0.860646, -0.227968],
+                [0.280085, 0.672501, 0.047413],
+                [-0.011820, 0.042940, 0.968881],
+            ],
+            Self::Tritanopia => [
+                [1.255528, -0.076749, -0.178779],
+                [-0.078411, 0.930809, 0.147602],
+                [0.004733, 0.691367, 0.303900],
+            ],
+        }
+    }
+}
+
+/// Returns a copy of `icon` as seen with the given color vision deficiency.
+///
+/// For previews only: the result is an approximation for checking that
+/// colors stay distinguishable, not something to export. Alpha, scale and
+/// content bounds are preserved.
+///
+/// ```
+/// use folco_renderer::IconImage;
+/// use folco_renderer::preview::{simulate_cvd, CvdKind};
+///
+/// let icon = IconImage::new_full_content(image::RgbaImage::from_pixel(4, 4, image::Rgba([255, 0, 0, 255])), 1.0);
+/// let seen = simulate_cvd(&icon, CvdKind::Protanopia);
+/// assert_ne!(seen.data().get_pixel(0, 0), icon.data().get_pixel(0, 0));
+/// ```
+pub fn simulate_cvd(icon: &IconImage, kind: CvdKind) -> IconImage {
+    let matrix = kind.matrix();
+    let mut result = icon.clone();
+    for pixel in result.data_mut().pixels_mut() {
+        let [r, g, b, a] = pixel.0;
+        if a == 0 {
+            continue;
+        }
+        let linear = [r, g, b].map(srgb_to_linear);
+        let simulated = matrix.map(|row| row[0] * linear[0] + row[1] * linear[1] + row[2] * linear[2]);
+        let [r, g, b] = simulated.map(linear_to_srgb);
+        pixel.0 = [r, g, b, a];
+    }
+    result
+}
+
+fn srgb_to_linear(c: u8) -> f32 {
+    let c = c as f32 / 255.0;
+    if c <= 0.04045 { c / 12.92 } else { ((c + 0.055) / 1.055).powf(2.4) }
+}
+
+fn linear_to_srgb(c: f32) -> u8 {
+    let c = c.clamp(0.0, 1.0);
+    let c = if c <= 0.0031308 { c * 12.92 } else { 1.055 * c.powf(1.0 / 2.4) - 0.055 };
+    (c * 255.0).round() as u8
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use image::{Rgba, RgbaImage};
+
+    use super::*;
+
+    fn solid(rgba: [u8; 4]) -> IconImage {
+        IconImage::new_full_content(RgbaImage::from_pixel(2, 2, Rgba(rgba)), 2.0)
+    }
+
+    #[test]
+    fn cvd_preserves_neutrals_and_alpha() {
+        for kind in CvdKind::ALL {
+            let seen = simulate_cvd(&solid([128, 128, 128, 77]), kind);
+            let [r, g, b, a] = seen.data().get_pixel(0, 0).0;
+            assert!(r.abs_diff(128) <= 1 && g.abs_diff(128) <= 1 && b.abs_diff(128) <= 1, "{kind:?}");
+            assert_eq!(a, 77);
+            assert_eq!(seen.scale, 2.0);
+        }
+    }
+
+    #[test]
+    fn cvd_collapses_confusable_colors() {
+        let distance = |a: [u8; 4], b: [u8; 4]| a.iter().zip(b).map(|(x, y)| x.abs_diff(y) as u32).sum::<u32>();
+        let seen = |rgba, kind| simulate_cvd(&solid(rgba), kind).data().get_pixel(0, 0).0;
+        let collapses = |a, b, kind| distance(seen(a, kind), seen(b, kind)) * 3 < distance(a, b);
+
+        // Red/olive pairs for protans and deutans, blue/green for tritans
+        let (red, olive) = ([230, 0, 0, 255], [110, 110, 0, 255]);
+        assert!(collapses(red, olive, CvdKind::Protanopia));
+        assert!(collapses(red, olive, CvdKind::Deuteranopia));
+        assert!(!collapses(red, olive, CvdKind::Tritanopia));
+        assert!(collapses([0, 150, 255, 255], [0, 200, 100, 255], CvdKind::Tritanopia));
+    }
+}
@@ -0,0 +1,189 @@
+//! Live native preview window.
+
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use image::RgbaImage;
+use minifb::{Key, Window, WindowOptions};
+use thiserror::Error;
+
+use crate::customizer::{Configurable, FolderIconCustomizer};
+use crate::error::RenderError;
+use crate::profile::{CustomizationProfile, ParseOptions};
+
+/// Gap between rendered sizes, in pixels.
+const PADDING: u32 = 16;
+
+/// Edge length of a checkerboard square, in pixels.
+const CHECKER_SIZE: u32 = 8;
+
+/// How often the watched profile's modification time is checked.
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Errors that can occur while running the preview window.
+#[derive(Debug, Error)]
+pub enum PreviewError {
+    /// The native window could not be created or updated.
+    #[error("preview window error: {0}")]
+    Window(#[from] minifb::Error),
+
+    /// Rendering the customizer output failed.
+    #[error(transparent)]
+    Render(#[from] RenderError),
+}
+
+/// Builder and event loop for the live preview window.
+#[derive(Debug, Clone)]
+pub struct PreviewWindow {
+    title: String,
+    profile_path: Option<PathBuf>,
+}
+
+impl Default for PreviewWindow {
+    fn default() -> Self {
+        Self {
+            title: "folco-renderer preview".to_string(),
+            profile_path: None,
+        }
+    }
+}
+
+impl PreviewWindow {
+    /// Creates a preview window with default settings.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the window title.
+    pub fn with_title(mut self, title: impl Into<String>) -> Self {
+        self.title = title.into();
+        self
+    }
+
+    /// Reloads the given profile JSON whenever its modification time changes.
+    ///
+    /// Parse errors are printed to stderr and the previous profile is kept,
+    /// so a half-saved file never closes the window.
+    pub fn watch_profile(mut self, path: impl AsRef<Path>) -> Self {
+        self.profile_path = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Opens the window and blocks until it is closed (or Escape is pressed).
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the window cannot be created, or if rendering
+    /// fails on the initial frame. Render errors after a profile reload are
+    /// printed to stderr instead, leaving the last good frame on screen.
+    pub fn run(&self, customizer: &mut FolderIconCustomizer) -> Result<(), PreviewError> {
+        let mut last_modified = None;
+        self.reload_profile(customizer, &mut last_modified);
+
+        let mut frame = compose_frame(&render_images(customizer)?);
+        let mut window = Window::new(
+            &self.title,
+            frame.width as usize,
+            frame.height as usize,
+            WindowOptions::default(),
+        )?;
+        window.set_target_fps(30);
+
+        let mut last_poll = SystemTime::now();
+        while window.is_open() && !window.is_key_down(Key::Escape) {
+            let now = SystemTime::now();
+            if now.duration_since(last_poll).unwrap_or_default() >= POLL_INTERVAL {
+                last_poll = now;
+                if self.reload_profile(customizer, &mut last_modified) {
+                    match render_images(customizer) {
+                        // Base icon sizes are fixed, so the frame size never changes
+                        Ok(images) => frame = compose_frame(&images),
+                        Err(e) => eprintln!("preview: render failed: {e}"),
+                    }
+                }
+            }
+
+            window.update_with_buffer(&frame.buffer, frame.width as usize, frame.height as usize)?;
+        }
+
+        Ok(())
+    }
+
+    /// Re-reads the watched profile if it changed. Returns true if applied.
+    fn reload_profile(
+        &self,
+        customizer: &mut FolderIconCustomizer,
+        last_modified: &mut Option<SystemTime>,
+    ) -> bool {
+        let Some(path) = &self.profile_path else {
+            return false;
+        };
+        let Ok(modified) = std::fs::metadata(path).and_then(|m| m.modified()) else {
+            return false;
+        };
+        if *last_modified == Some(modified) {
+            return false;
+        }
+        *last_modified = Some(modified);
+
+        let profile = std::fs::read_to_string(path)
+            .map_err(|e| e.to_string())
+            .and_then(|json| {
+                // The profile is the developer's own file, so it may name files too
+                CustomizationProfile::from_json_with(&json, ParseOptions { allow_files: true }).map_err(|e| e.to_string())
+            });
+        match profile {
+            Ok(profile) => {
+                customizer.apply_profile(&profile);
+                true
+            }
+            Err(e) => {
+                eprintln!("preview: failed to load {}: {e}", path.display());
+                false
+            }
+        }
+    }
+}
+
+/// A 0RGB framebuffer ready for minifb.
+struct Frame {
+    buffer: Vec<u32>,
+    width: u32,
+    height: u32,
+}
+
+/// Renders every size in the customizer's base icon set.
+fn render_images(customizer: &mut FolderIconCustomizer) -> Result<Vec<RgbaImage>, RenderError> {
+    Ok(customizer.render_all()?.into_iter().map(|img| img.into_data()).collect())
+}
+
+/// Lays images out left to right on a checkerboard, bottom-aligned.
+fn compose_frame(images: &[RgbaImage]) -> Frame {
+    let width = images.iter().map(|img| img.width() + PADDING).sum::<u32>() + PADDING;
+    let height = images.iter().map(|img| img.height()).max().unwrap_or(0) + PADDING * 2;
+
+    let mut buffer: Vec<u32> = (0..width * height)
+        .map(|i| {
+            let (x, y) = (i % width, i / width);
+            if (x / CHECKER_SIZE + y / CHECKER_SIZE).is_multiple_of(2) { 0xcccccc } else { 0xffffff }
+        })
+        .collect();
+
+    let mut offset_x = PADDING;
+    for img in images {
+        let offset_y = height - PADDING - img.height();
+        for (x, y, pixel) in img.enumerate_pixels() {
+            let index = ((offset_y + y) * width + offset_x + x) as usize;
+            let background = buffer[index];
+            let alpha = pixel[3] as u32;
+            let blend = |shift: u32, channel: u8| {
+                let bg = (background >> shift) & 0xff;
+                ((channel as u32 * alpha + bg * (255 - alpha)) / 255) << shift
+            };
+            buffer[index] = blend(16, pixel[0]) | blend(8, pixel[1]) | blend(0, pixel[2]);
+        }
+        offset_x += img.width() + PADDING;
+    }
+
+    Frame { buffer, width, height }
+}
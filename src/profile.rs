@@ -21,10 +21,49 @@
 //! // Deserialize in backend
 //! let restored = CustomizationProfile::from_json(&json).unwrap();
 //! ```
+//!
+//! # Presets
+//!
+//! A profile can define named layer configs in `presets` and point layers
+//! at them through `uses`, so a theme file can define a style once and
+//! reference it from several places. References are resolved when the
+//! profile is applied; an inline layer config takes precedence over a
+//! reference for the same layer.
+//!
+//! ```json
+//! {
+//!   "presets": {
+//!     "badge": { "overlay": { "source": { "raw": "<svg>...</svg>" }, "position": "bottom-right", "scale": 0.3 } }
+//!   },
+//!   "uses": { "overlay": "badge" }
+//! }
+//! ```
+//!
+//! # Intensity
+//!
+//! `intensity` scales every layer's strength when the profile is applied,
+//! so an app can offer one subtle-to-bold slider over any preset: the
+//! color target moves only part way from the folder's color toward its
+//! target, and the gradient, shadow, decal and overlay fade. Text is left
+//! as is, to stay legible.
+
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
 
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use serde::ser;
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use thiserror::Error;
 
-use crate::layer::{FolderColorTargetConfig, DecalConfig, SvgOverlayConfig};
+use crate::layer::{
+    finite_or, BlendMode, DecalConfig, FolderColorTargetConfig, GradientKind, GradientTintConfig, HueApproximation, MinSizePolicy,
+    OverlayAnchor, OverlayArrangement, OverlayClip, OverlayPosition, ShadowConfig, SvgOverlayConfig, SvgSource, TextFont,
+    TextLayerConfig,
+};
 
 // ============================================================================
 // CustomizationProfile
@@ -51,7 +90,7 @@ use crate::layer::{FolderColorTargetConfig, DecalConfig, SvgOverlayConfig};
 ///   }
 /// }
 /// ```
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[cfg_attr(feature = "jsonschema", derive(schemars::JsonSchema))]
 #[serde(rename_all = "camelCase")]
 pub struct CustomizationProfile {
@@ -59,6 +98,14 @@ pub struct CustomizationProfile {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub folder_color_target: Option<FolderColorTargetConfig>,
 
+    /// Gradient tint layer config. `None` means not configured.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gradient_tint: Option<GradientTintConfig>,
+
+    /// Standalone shadow layer config. `None` means not configured.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub shadow: Option<ShadowConfig>,
+
     /// Decal imprint layer config. `None` means not configured.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub decal: Option<DecalConfig>,
@@ -66,6 +113,46 @@ pub struct CustomizationProfile {
     /// SVG overlay layer config. `None` means not configured.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub overlay: Option<SvgOverlayConfig>,
+
+    /// Text label layer config. `None` means not configured.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub text: Option<TextLayerConfig>,
+
+    /// Named layer configs that layers can reference through
+    /// [`uses`](Self::uses).
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub presets: BTreeMap<String, LayerPreset>,
+
+    /// Preset references for layers without an inline config.
+    #[serde(default, skip_serializing_if = "PresetRefs::is_empty")]
+    pub uses: PresetRefs,
+
+    /// How strongly the layers apply (0.0-1.0); see the
+    /// [module docs](self#intensity). Defaults to full strength.
+    #[serde(default = "full_intensity", skip_serializing_if = "is_full_intensity")]
+    pub intensity: f32,
+
+    /// How overlays sharing a corner are laid out. Defaults to
+    /// [`OverlayArrangement::Overlap`].
+    #[serde(default, skip_serializing_if = "is_overlap")]
+    pub overlay_arrangement: OverlayArrangement,
+}
+
+impl Default for CustomizationProfile {
+    fn default() -> Self {
+        Self {
+            folder_color_target: None,
+            gradient_tint: None,
+            shadow: None,
+            decal: None,
+            overlay: None,
+            text: None,
+            presets: BTreeMap::new(),
+            uses: PresetRefs::default(),
+            intensity: 1.0,
+            overlay_arrangement: OverlayArrangement::Overlap,
+        }
+    }
 }
 
 impl CustomizationProfile {
@@ -74,12 +161,30 @@ impl CustomizationProfile {
         Self::default()
     }
 
+    /// Returns the bundled preset named `name`, ignoring ASCII case. See
+    /// [`presets`](crate::presets) for the available names.
+    pub fn preset(name: &str) -> Option<Self> {
+        crate::presets::get(name)
+    }
+
     /// Sets the color target configuration.
     pub fn with_folder_color_target(mut self, config: FolderColorTargetConfig) -> Self {
         self.folder_color_target = Some(config);
         self
     }
 
+    /// Sets the gradient tint configuration.
+    pub fn with_gradient_tint(mut self, config: GradientTintConfig) -> Self {
+        self.gradient_tint = Some(config);
+        self
+    }
+
+    /// Sets the standalone shadow configuration.
+    pub fn with_shadow(mut self, config: ShadowConfig) -> Self {
+        self.shadow = Some(config);
+        self
+    }
+
     /// Sets the decal configuration.
     pub fn with_decal(mut self, config: DecalConfig) -> Self {
         self.decal = Some(config);
@@ -92,6 +197,268 @@ impl CustomizationProfile {
         self
     }
 
+    /// Sets the text label configuration.
+    pub fn with_text(mut self, config: TextLayerConfig) -> Self {
+        self.text = Some(config);
+        self
+    }
+
+    /// Sets the intensity (clamped to 0.0-1.0).
+    pub fn with_intensity(mut self, intensity: f32) -> Self {
+        self.intensity = finite_or(intensity, 1.0).clamp(0.0, 1.0);
+        self
+    }
+
+    /// Sets how overlays sharing a corner are laid out.
+    pub fn with_overlay_arrangement(mut self, arrangement: OverlayArrangement) -> Self {
+        self.overlay_arrangement = arrangement;
+        self
+    }
+
+    /// Defines a named preset.
+    pub fn with_preset(mut self, name: impl Into<String>, preset: LayerPreset) -> Self {
+        self.presets.insert(name.into(), preset);
+        self
+    }
+
+    /// Points the color target layer at a preset.
+    pub fn with_folder_color_target_preset(mut self, name: impl Into<String>) -> Self {
+        self.uses.folder_color_target = Some(name.into());
+        self
+    }
+
+    /// Points the gradient tint layer at a preset.
+    pub fn with_gradient_tint_preset(mut self, name: impl Into<String>) -> Self {
+        self.uses.gradient_tint = Some(name.into());
+        self
+    }
+
+    /// Points the shadow layer at a preset.
+    pub fn with_shadow_preset(mut self, name: impl Into<String>) -> Self {
+        self.uses.shadow = Some(name.into());
+        self
+    }
+
+    /// Points the decal layer at a preset.
+    pub fn with_decal_preset(mut self, name: impl Into<String>) -> Self {
+        self.uses.decal = Some(name.into());
+        self
+    }
+
+    /// Points the overlay layer at a preset.
+    pub fn with_overlay_preset(mut self, name: impl Into<String>) -> Self {
+        self.uses.overlay = Some(name.into());
+        self
+    }
+
+    /// Points the text layer at a preset.
+    pub fn with_text_preset(mut self, name: impl Into<String>) -> Self {
+        self.uses.text = Some(name.into());
+        self
+    }
+
+    /// Returns a copy with every preset reference replaced by the preset's
+    /// config, and `presets`/`uses` cleared.
+    ///
+    /// ```
+    /// use folco_renderer::{CustomizationProfile, LayerPreset, OverlayPosition, SvgOverlayConfig};
+    ///
+    /// let badge = SvgOverlayConfig::new("<svg></svg>", OverlayPosition::BottomRight, 0.3);
+    /// let profile = CustomizationProfile::new()
+    ///     .with_preset("badge", LayerPreset::Overlay(badge))
+    ///     .with_overlay_preset("badge");
+    ///
+    /// let resolved = profile.resolve_presets().unwrap();
+    /// assert_eq!(resolved.overlay.unwrap().scale, 0.3);
+    /// assert!(resolved.presets.is_empty());
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a reference names a missing preset or a preset
+    /// for a different layer.
+    pub fn resolve_presets(&self) -> Result<Self, ProfilePresetError> {
+        Ok(Self {
+            folder_color_target: self.resolved_folder_color_target()?.cloned(),
+            gradient_tint: self.resolved_gradient_tint()?.cloned(),
+            shadow: self.resolved_shadow()?.cloned(),
+            decal: self.resolved_decal()?.cloned(),
+            overlay: self.resolved_overlay()?.cloned(),
+            text: self.resolved_text()?.cloned(),
+            presets: BTreeMap::new(),
+            uses: PresetRefs::default(),
+            intensity: self.intensity,
+            overlay_arrangement: self.overlay_arrangement,
+        })
+    }
+
+    /// Returns the profile with its [`intensity`](Self::intensity) baked
+    /// into the layer configs, presets included, and reset to 1.0.
+    ///
+    /// Applying a profile does this first, so the layers see the scaled
+    /// strengths.
+    ///
+    /// ```
+    /// use folco_renderer::{CustomizationProfile, DecalConfig, FolderColorTargetConfig};
+    ///
+    /// let profile = CustomizationProfile::new()
+    ///     .with_folder_color_target(FolderColorTargetConfig::new(33, 150, 243))
+    ///     .with_decal(DecalConfig::new("<svg/>", 0.5).with_opacity(0.8))
+    ///     .with_intensity(0.5);
+    ///
+    /// let scaled = profile.with_intensity_applied();
+    /// assert_eq!(scaled.decal.as_ref().unwrap().opacity, 0.4);
+    /// assert_eq!(scaled.folder_color_target.as_ref().unwrap().strength, 0.5);
+    /// assert_eq!(scaled.intensity, 1.0);
+    /// ```
+    pub fn with_intensity_applied(&self) -> Cow<'_, Self> {
+        if is_full_intensity(&self.intensity) {
+            return Cow::Borrowed(self);
+        }
+        let intensity = self.intensity.clamp(0.0, 1.0);
+        let mut scaled = self.clone();
+        scaled.intensity = 1.0;
+        if let Some(config) = &mut scaled.folder_color_target {
+            config.strength *= intensity;
+        }
+        if let Some(config) = &mut scaled.gradient_tint {
+            config.opacity *= intensity;
+        }
+        if let Some(config) = &mut scaled.shadow {
+            config.opacity *= intensity;
+        }
+        if let Some(config) = &mut scaled.decal {
+            config.opacity *= intensity;
+        }
+        if let Some(config) = &mut scaled.overlay {
+            config.opacity *= intensity;
+        }
+        for preset in scaled.presets.values_mut() {
+            match preset {
+                LayerPreset::FolderColorTarget(config) => config.strength *= intensity,
+                LayerPreset::GradientTint(config) => config.opacity *= intensity,
+                LayerPreset::Shadow(config) => config.opacity *= intensity,
+                LayerPreset::Decal(config) => config.opacity *= intensity,
+                LayerPreset::Overlay(config) => config.opacity *= intensity,
+                LayerPreset::Text(_) => {}
+            }
+        }
+        Cow::Owned(scaled)
+    }
+
+    /// Returns the color target config, following a preset reference if
+    /// there is no inline config.
+    pub(crate) fn resolved_folder_color_target(&self) -> Result<Option<&FolderColorTargetConfig>, ProfilePresetError> {
+        self.resolve_layer(
+            self.folder_color_target.as_ref(),
+            self.uses.folder_color_target.as_deref(),
+            "folderColorTarget",
+            |preset| match preset {
+                LayerPreset::FolderColorTarget(config) => Some(config),
+                _ => None,
+            },
+        )
+    }
+
+    /// Returns the gradient tint config, following a preset reference if
+    /// there is no inline config.
+    pub(crate) fn resolved_gradient_tint(&self) -> Result<Option<&GradientTintConfig>, ProfilePresetError> {
+        self.resolve_layer(
+            self.gradient_tint.as_ref(),
+            self.uses.gradient_tint.as_deref(),
+            "gradientTint",
+            |preset| match preset {
+                LayerPreset::GradientTint(config) => Some(config),
+                _ => None,
+            },
+        )
+    }
+
+    /// Returns the shadow config, following a preset reference if there is
+    /// no inline config.
+    pub(crate) fn resolved_shadow(&self) -> Result<Option<&ShadowConfig>, ProfilePresetError> {
+        self.resolve_layer(self.shadow.as_ref(), self.uses.shadow.as_deref(), "shadow", |preset| match preset {
+            LayerPreset::Shadow(config) => Some(config),
+            _ => None,
+        })
+    }
+
+    /// Returns the decal config, following a preset reference if there is
+    /// no inline config.
+    pub(crate) fn resolved_decal(&self) -> Result<Option<&DecalConfig>, ProfilePresetError> {
+        self.resolve_layer(self.decal.as_ref(), self.uses.decal.as_deref(), "decal", |preset| match preset {
+            LayerPreset::Decal(config) => Some(config),
+            _ => None,
+        })
+    }
+
+    /// Returns the overlay config, following a preset reference if there is
+    /// no inline config.
+    pub(crate) fn resolved_overlay(&self) -> Result<Option<&SvgOverlayConfig>, ProfilePresetError> {
+        self.resolve_layer(self.overlay.as_ref(), self.uses.overlay.as_deref(), "overlay", |preset| match preset {
+            LayerPreset::Overlay(config) => Some(config),
+            _ => None,
+        })
+    }
+
+    /// Returns the text config, following a preset reference if there is
+    /// no inline config.
+    pub(crate) fn resolved_text(&self) -> Result<Option<&TextLayerConfig>, ProfilePresetError> {
+        self.resolve_layer(self.text.as_ref(), self.uses.text.as_deref(), "text", |preset| match preset {
+            LayerPreset::Text(config) => Some(config),
+            _ => None,
+        })
+    }
+
+    fn resolve_layer<'a, C>(
+        &'a self,
+        inline: Option<&'a C>,
+        reference: Option<&str>,
+        layer: &'static str,
+        pick: fn(&LayerPreset) -> Option<&C>,
+    ) -> Result<Option<&'a C>, ProfilePresetError> {
+        let Some(name) = reference.filter(|_| inline.is_none()) else {
+            return Ok(inline);
+        };
+        let preset = self
+            .presets
+            .get(name)
+            .ok_or_else(|| ProfilePresetError::UnknownPreset { name: name.to_string() })?;
+        pick(preset)
+            .map(Some)
+            .ok_or_else(|| ProfilePresetError::WrongLayer { name: name.to_string(), layer })
+    }
+
+    /// Checks that every number in the profile is finite.
+    ///
+    /// JSON can't spell NaN or infinity, but profiles handed over from
+    /// JavaScript as objects can hold them, and they would otherwise reach
+    /// layer sizes and cache keys. Hosts should validate such profiles
+    /// before applying them.
+    ///
+    /// ```
+    /// use folco_renderer::{CustomizationProfile, DecalConfig, ProfileValueError};
+    ///
+    /// let mut decal = DecalConfig::new("<svg/>", 0.5);
+    /// decal.scale = f32::NAN;
+    /// let profile = CustomizationProfile::new().with_decal(decal);
+    /// assert!(matches!(profile.validate(), Err(ProfileValueError::NonFinite { path }) if path == "/decal/scale"));
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ProfileValueError::NonFinite`] with the JSON Pointer of
+    /// the first NaN or infinite value, presets included, or
+    /// [`ProfileValueError::Unserializable`] if the profile can't be
+    /// serialized at all.
+    pub fn validate(&self) -> Result<(), ProfileValueError> {
+        match self.serialize(&mut FiniteCheck::default()) {
+            Ok(()) => Ok(()),
+            Err(CheckStop::NonFinite(path)) => Err(ProfileValueError::NonFinite { path }),
+            Err(CheckStop::Failed(message)) => Err(ProfileValueError::Unserializable { message }),
+        }
+    }
+
     /// Serializes the profile to a JSON string.
     pub fn to_json(&self) -> Result<String, serde_json::Error> {
         serde_json::to_string(self)
@@ -103,8 +470,193 @@ impl CustomizationProfile {
     }
 
     /// Deserializes a profile from a JSON string.
+    ///
+    /// Fails on enum values this version doesn't know; see
+    /// [`from_json_tolerant`](Self::from_json_tolerant) for profiles that
+    /// may come from a newer version. Also fails on numbers too large for
+    /// their field, which would otherwise become infinite; see
+    /// [`validate`](Self::validate). Rejects file sources; see
+    /// [`from_json_with`](Self::from_json_with).
     pub fn from_json(json: &str) -> Result<Self, serde_json::Error> {
-        serde_json::from_str(json)
+        Self::from_json_with(json, ParseOptions::default())
+    }
+
+    /// Deserializes a profile from a JSON string as
+    /// [`from_json`](Self::from_json) does, with the given options.
+    ///
+    /// ```
+    /// use folco_renderer::{CustomizationProfile, ParseOptions};
+    ///
+    /// let json = r#"{"decal":{"source":{"file":"logo.svg"},"scale":0.5}}"#;
+    /// assert!(CustomizationProfile::from_json(json).is_err());
+    /// assert!(CustomizationProfile::from_json_with(json, ParseOptions { allow_files: true }).is_ok());
+    /// ```
+    pub fn from_json_with(json: &str, options: ParseOptions) -> Result<Self, serde_json::Error> {
+        let profile: Self = serde_json::from_str(json)?;
+        profile.validate().map_err(serde::de::Error::custom)?;
+        options.check(&profile)?;
+        Ok(profile)
+    }
+
+    /// Deserializes a profile that may have been written by a newer
+    /// version of this crate.
+    ///
+    /// Unknown fields are always ignored. Here, in addition, enum values
+    /// this version doesn't know (e.g. a new [`OverlayPosition`]) fall back
+    /// to the field's default, and presets for unknown layers are dropped.
+    /// Each substitution is recorded in the returned report.
+    ///
+    /// ```
+    /// use folco_renderer::{CustomizationProfile, OverlayPosition, ProfileParseWarning};
+    ///
+    /// let json = r#"{ "overlay": { "source": { "raw": "<svg/>" }, "position": { "offset": [0.1, 0.2] }, "scale": 0.3 } }"#;
+    /// assert!(CustomizationProfile::from_json(json).is_err());
+    ///
+    /// let (profile, report) = CustomizationProfile::from_json_tolerant(json).unwrap();
+    /// assert_eq!(profile.overlay.unwrap().position, OverlayPosition::BottomRight);
+    /// assert!(matches!(&report.warnings[..], [ProfileParseWarning::UnknownVariant { path, .. }] if path == "/overlay/position"));
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the input is not JSON or is malformed in a way
+    /// no fallback covers, such as a missing required field, a number
+    /// too large for its field or a file source; see
+    /// [`from_json_tolerant_with`](Self::from_json_tolerant_with).
+    pub fn from_json_tolerant(json: &str) -> Result<(Self, ProfileParseReport), serde_json::Error> {
+        Self::from_json_tolerant_with(json, ParseOptions::default())
+    }
+
+    /// Deserializes a profile as
+    /// [`from_json_tolerant`](Self::from_json_tolerant) does, with the
+    /// given options.
+    pub fn from_json_tolerant_with(json: &str, options: ParseOptions) -> Result<(Self, ProfileParseReport), serde_json::Error> {
+        let mut document: Value = serde_json::from_str(json)?;
+        let mut report = ProfileParseReport::default();
+        if let Some(profile) = document.as_object_mut() {
+            for (key, layer) in profile.iter_mut() {
+                tolerate_layer(key, layer, &format!("/{key}"), &mut report);
+            }
+            if let Some(value) = profile.get_mut("overlayArrangement")
+                && !parses::<OverlayArrangement>(value)
+            {
+                let replacement = default_json::<OverlayArrangement>();
+                report.warnings.push(ProfileParseWarning::UnknownVariant {
+                    path: "/overlayArrangement".to_string(),
+                    found: value.to_string(),
+                    fallback: replacement.to_string(),
+                });
+                *value = replacement;
+            }
+            if let Some(presets) = profile.get_mut("presets").and_then(Value::as_object_mut) {
+                presets.retain(|name, preset| {
+                    let Some((key, layer)) = preset.as_object_mut().and_then(|p| p.iter_mut().next()) else {
+                        return true;
+                    };
+                    if !LAYER_KEYS.contains(&key.as_str()) {
+                        report.warnings.push(ProfileParseWarning::UnknownPresetLayer {
+                            name: name.clone(),
+                            layer: key.clone(),
+                        });
+                        return false;
+                    }
+                    tolerate_layer(key, layer, &format!("/presets/{}/{key}", escape_pointer(name)), &mut report);
+                    true
+                });
+            }
+        }
+        let profile: Self = serde_json::from_value(document)?;
+        profile.validate().map_err(serde::de::Error::custom)?;
+        options.check(&profile)?;
+        Ok((profile, report))
+    }
+
+    /// Encodes the profile as a compact, URL-safe string.
+    ///
+    /// The JSON form is DEFLATE-compressed and base64url-encoded without
+    /// padding, so the result can be dropped straight into a URL fragment
+    /// (`https://example.com/#<fragment>`) to share a customization without
+    /// a backend.
+    pub fn to_url_fragment(&self) -> Result<String, serde_json::Error> {
+        let json = serde_json::to_vec(self)?;
+        let compressed = miniz_oxide::deflate::compress_to_vec(&json, FRAGMENT_COMPRESSION_LEVEL);
+        Ok(URL_SAFE_NO_PAD.encode(compressed))
+    }
+
+    /// Decodes a profile produced by [`to_url_fragment`](Self::to_url_fragment).
+    ///
+    /// A leading `#` is ignored, so `location.hash` can be passed as-is.
+    /// Decompressed output is capped at 1 MiB to bound memory use on
+    /// untrusted links, and the decoded profile must pass
+    /// [`validate`](Self::validate). File sources are rejected; see
+    /// [`from_url_fragment_with`](Self::from_url_fragment_with).
+    pub fn from_url_fragment(fragment: &str) -> Result<Self, ProfileFragmentError> {
+        Self::from_url_fragment_with(fragment, ParseOptions::default())
+    }
+
+    /// Decodes a profile as [`from_url_fragment`](Self::from_url_fragment)
+    /// does, with the given options.
+    pub fn from_url_fragment_with(fragment: &str, options: ParseOptions) -> Result<Self, ProfileFragmentError> {
+        let fragment = fragment.strip_prefix('#').unwrap_or(fragment);
+        let compressed = URL_SAFE_NO_PAD.decode(fragment)?;
+        let json = miniz_oxide::inflate::decompress_to_vec_with_limit(&compressed, MAX_FRAGMENT_JSON_LEN)
+            .map_err(|_| ProfileFragmentError::Decompress)?;
+        let profile: Self = serde_json::from_slice(&json)?;
+        profile.validate()?;
+        options.check(&profile)?;
+        Ok(profile)
+    }
+
+    /// Applies an [RFC 6902] JSON Patch to the profile.
+    ///
+    /// Paths address the profile's JSON form (e.g. `/decal/scale`,
+    /// `/folderColorTarget/protectedColors/-`). All six operations are
+    /// supported. The patch is atomic: if any operation fails, or the
+    /// result is not a valid profile or fails [`validate`](Self::validate),
+    /// the profile is left unchanged. File sources the profile already
+    /// holds are kept, but a patch can't add new ones.
+    ///
+    /// ```
+    /// use folco_renderer::{CustomizationProfile, FolderColorTargetConfig};
+    ///
+    /// let mut profile = CustomizationProfile::new().with_folder_color_target(FolderColorTargetConfig::new(33, 150, 243));
+    /// profile.apply_patch(r#"[{ "op": "replace", "path": "/folderColorTarget/targetR", "value": 200 }]"#).unwrap();
+    /// assert_eq!(profile.folder_color_target.unwrap().target_r, 200);
+    /// ```
+    ///
+    /// [RFC 6902]: https://www.rfc-editor.org/rfc/rfc6902
+    pub fn apply_patch(&mut self, patch: &str) -> Result<(), ProfilePatchError> {
+        let operations: Vec<PatchOperation> = serde_json::from_str(patch)?;
+        let mut document = serde_json::to_value(&*self)?;
+        for operation in operations {
+            operation.apply(&mut document)?;
+        }
+        let patched: Self = serde_json::from_value(document)?;
+        patched.validate()?;
+        let existing: Vec<_> = self.file_sources().collect();
+        if let Some(path) = patched.file_sources().find(|path| !existing.contains(path)) {
+            return Err(ProfilePatchError::FileSource { path: path.to_path_buf() });
+        }
+        *self = patched;
+        Ok(())
+    }
+
+    /// Returns the paths of the profile's file sources, presets included.
+    fn file_sources(&self) -> impl Iterator<Item = &Path> {
+        let presets = self.presets.values().filter_map(|preset| match preset {
+            LayerPreset::Decal(decal) => Some(&decal.source),
+            LayerPreset::Overlay(overlay) => Some(&overlay.source),
+            _ => None,
+        });
+        [self.decal.as_ref().map(|d| &d.source), self.overlay.as_ref().map(|o| &o.source)]
+            .into_iter()
+            .flatten()
+            .chain(presets)
+            .filter_map(|source| match source {
+                #[cfg(not(target_arch = "wasm32"))]
+                SvgSource::File(path) => Some(path.as_path()),
+                _ => None,
+            })
     }
 
     /// Returns the JSON Schema for `CustomizationProfile`.
@@ -120,6 +672,711 @@ impl CustomizationProfile {
     }
 }
 
+// ============================================================================
+// Presets
+// ============================================================================
+
+/// A named, reusable layer config stored in [`CustomizationProfile::presets`].
+///
+/// Serialized as an object keyed by layer, e.g. `{ "decal": { ... } }`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "jsonschema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
+pub enum LayerPreset {
+    /// A color target config.
+    FolderColorTarget(FolderColorTargetConfig),
+    /// A gradient tint config.
+    GradientTint(GradientTintConfig),
+    /// A standalone shadow config.
+    Shadow(ShadowConfig),
+    /// A decal config.
+    Decal(DecalConfig),
+    /// An overlay config.
+    Overlay(SvgOverlayConfig),
+    /// A text label config.
+    Text(TextLayerConfig),
+}
+
+/// Preset names referenced by each layer of a profile.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "jsonschema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct PresetRefs {
+    /// Preset for the color target layer.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub folder_color_target: Option<String>,
+
+    /// Preset for the gradient tint layer.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gradient_tint: Option<String>,
+
+    /// Preset for the shadow layer.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub shadow: Option<String>,
+
+    /// Preset for the decal layer.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub decal: Option<String>,
+
+    /// Preset for the overlay layer.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub overlay: Option<String>,
+
+    /// Preset for the text layer.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+}
+
+impl PresetRefs {
+    /// Returns `true` if no layer references a preset.
+    pub fn is_empty(&self) -> bool {
+        self.folder_color_target.is_none()
+            && self.gradient_tint.is_none()
+            && self.shadow.is_none()
+            && self.decal.is_none() && self.overlay.is_none() && self.text.is_none()
+    }
+}
+
+/// Errors that can occur while resolving preset references.
+#[derive(Debug, Error)]
+pub enum ProfilePresetError {
+    /// A layer references a preset that is not defined.
+    #[error("unknown preset {name:?}")]
+    UnknownPreset {
+        /// The referenced preset name.
+        name: String,
+    },
+
+    /// A layer references a preset defined for a different layer.
+    #[error("preset {name:?} is not a {layer} preset")]
+    WrongLayer {
+        /// The referenced preset name.
+        name: String,
+        /// The layer that referenced it.
+        layer: &'static str,
+    },
+}
+
+// ============================================================================
+// Validation
+// ============================================================================
+
+/// A value that makes a profile unusable, found by
+/// [`CustomizationProfile::validate`].
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum ProfileValueError {
+    /// A number is NaN or infinite.
+    #[error("{path}: not a finite number")]
+    NonFinite {
+        /// JSON Pointer to the value, e.g. `/decal/scale`.
+        path: String,
+    },
+    /// The profile can't be serialized, e.g. because a file source's path
+    /// is not valid UTF-8.
+    #[error("profile cannot be serialized: {message}")]
+    Unserializable {
+        /// Why serialization failed.
+        message: String,
+    },
+}
+
+/// Serde default for [`CustomizationProfile::intensity`].
+fn full_intensity() -> f32 {
+    1.0
+}
+
+/// Returns true for an intensity that leaves the layers unchanged.
+fn is_full_intensity(intensity: &f32) -> bool {
+    *intensity >= 1.0
+}
+
+/// Returns true for the default overlay arrangement.
+fn is_overlap(arrangement: &OverlayArrangement) -> bool {
+    *arrangement == OverlayArrangement::Overlap
+}
+
+/// A serializer that visits every number in a value, stopping at the first
+/// NaN or infinity with its JSON Pointer.
+///
+/// Field names, map keys and enum tags come from the value's `Serialize`
+/// impl, so the pointer matches the value's JSON form.
+#[derive(Default)]
+struct FiniteCheck {
+    /// Pointer segments down to the value being visited.
+    path: Vec<String>,
+    /// Next element index of each sequence being visited.
+    indices: Vec<usize>,
+}
+
+/// Why a [`FiniteCheck`] stopped.
+#[derive(Debug)]
+enum CheckStop {
+    /// A NaN or infinity, at this JSON Pointer.
+    NonFinite(String),
+    /// The value failed to serialize.
+    Failed(String),
+}
+
+impl std::fmt::Display for CheckStop {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NonFinite(path) => write!(f, "{path}: not a finite number"),
+            Self::Failed(message) => f.write_str(message),
+        }
+    }
+}
+
+impl std::error::Error for CheckStop {}
+
+impl ser::Error for CheckStop {
+    fn custom<T: std::fmt::Display>(message: T) -> Self {
+        Self::Failed(message.to_string())
+    }
+}
+
+impl FiniteCheck {
+    fn check(&self, value: f64) -> Result<(), CheckStop> {
+        if value.is_finite() {
+            return Ok(());
+        }
+        Err(CheckStop::NonFinite(
+            self.path.iter().map(|segment| format!("/{}", escape_pointer(segment))).collect(),
+        ))
+    }
+
+    /// Visits `value` under the extra path segment `segment`.
+    fn visit<T: Serialize + ?Sized>(&mut self, segment: String, value: &T) -> Result<(), CheckStop> {
+        self.path.push(segment);
+        value.serialize(&mut *self)?;
+        self.path.pop();
+        Ok(())
+    }
+
+    /// Visits the next element of the innermost sequence.
+    fn element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), CheckStop> {
+        let index = self.indices.last_mut().expect("inside a sequence");
+        let segment = index.to_string();
+        *index += 1;
+        self.visit(segment, value)
+    }
+}
+
+/// Implements the scalar methods of [`ser::Serializer`], none of which can
+/// hold a float.
+macro_rules! finite_scalars {
+    ($($method:ident($ty:ty)),*) => {
+        $(fn $method(self, _: $ty) -> Result<(), CheckStop> {
+            Ok(())
+        })*
+    };
+}
+
+impl ser::Serializer for &mut FiniteCheck {
+    type Ok = ();
+    type Error = CheckStop;
+    type SerializeSeq = Self;
+    type SerializeTuple = Self;
+    type SerializeTupleStruct = Self;
+    type SerializeTupleVariant = Self;
+    type SerializeMap = Self;
+    type SerializeStruct = Self;
+    type SerializeStructVariant = Self;
+
+    finite_scalars!(
+        serialize_bool(bool),
+        serialize_i8(i8),
+        serialize_i16(i16),
+        serialize_i32(i32),
+        serialize_i64(i64),
+        serialize_u8(u8),
+        serialize_u16(u16),
+        serialize_u32(u32),
+        serialize_u64(u64),
+        serialize_char(char),
+        serialize_str(&str),
+        serialize_bytes(&[u8]),
+        serialize_unit_struct(&'static str)
+    );
+
+    fn serialize_f32(self, value: f32) -> Result<(), CheckStop> {
+        self.check(value.into())
+    }
+
+    fn serialize_f64(self, value: f64) -> Result<(), CheckStop> {
+        self.check(value)
+    }
+
+    fn serialize_none(self) -> Result<(), CheckStop> {
+        Ok(())
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<(), CheckStop> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<(), CheckStop> {
+        Ok(())
+    }
+
+    fn serialize_unit_variant(self, _: &'static str, _: u32, _: &'static str) -> Result<(), CheckStop> {
+        Ok(())
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(self, _: &'static str, value: &T) -> Result<(), CheckStop> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _: &'static str,
+        _: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<(), CheckStop> {
+        self.visit(variant.to_string(), value)
+    }
+
+    fn serialize_seq(self, _: Option<usize>) -> Result<Self, CheckStop> {
+        self.indices.push(0);
+        Ok(self)
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<Self, CheckStop> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(self, _: &'static str, len: usize) -> Result<Self, CheckStop> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(self, _: &'static str, _: u32, variant: &'static str, len: usize) -> Result<Self, CheckStop> {
+        self.path.push(variant.to_string());
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_map(self, _: Option<usize>) -> Result<Self, CheckStop> {
+        Ok(self)
+    }
+
+    fn serialize_struct(self, _: &'static str, _: usize) -> Result<Self, CheckStop> {
+        Ok(self)
+    }
+
+    fn serialize_struct_variant(self, _: &'static str, _: u32, variant: &'static str, _: usize) -> Result<Self, CheckStop> {
+        self.path.push(variant.to_string());
+        Ok(self)
+    }
+}
+
+impl ser::SerializeSeq for &mut FiniteCheck {
+    type Ok = ();
+    type Error = CheckStop;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), CheckStop> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<(), CheckStop> {
+        self.indices.pop();
+        Ok(())
+    }
+}
+
+impl ser::SerializeTuple for &mut FiniteCheck {
+    type Ok = ();
+    type Error = CheckStop;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), CheckStop> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<(), CheckStop> {
+        self.indices.pop();
+        Ok(())
+    }
+}
+
+impl ser::SerializeTupleStruct for &mut FiniteCheck {
+    type Ok = ();
+    type Error = CheckStop;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), CheckStop> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<(), CheckStop> {
+        self.indices.pop();
+        Ok(())
+    }
+}
+
+impl ser::SerializeTupleVariant for &mut FiniteCheck {
+    type Ok = ();
+    type Error = CheckStop;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), CheckStop> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<(), CheckStop> {
+        self.indices.pop();
+        self.path.pop();
+        Ok(())
+    }
+}
+
+impl ser::SerializeMap for &mut FiniteCheck {
+    type Ok = ();
+    type Error = CheckStop;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), CheckStop> {
+        // Keys become the pointer segment of the value that follows
+        let key = match serde_json::to_value(key).map_err(|e| CheckStop::Failed(e.to_string()))? {
+            Value::String(key) => key,
+            other => other.to_string(),
+        };
+        self.path.push(key);
+        Ok(())
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), CheckStop> {
+        value.serialize(&mut **self)?;
+        self.path.pop();
+        Ok(())
+    }
+
+    fn end(self) -> Result<(), CheckStop> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeStruct for &mut FiniteCheck {
+    type Ok = ();
+    type Error = CheckStop;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, name: &'static str, value: &T) -> Result<(), CheckStop> {
+        self.visit(name.to_string(), value)
+    }
+
+    fn end(self) -> Result<(), CheckStop> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeStructVariant for &mut FiniteCheck {
+    type Ok = ();
+    type Error = CheckStop;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, name: &'static str, value: &T) -> Result<(), CheckStop> {
+        self.visit(name.to_string(), value)
+    }
+
+    fn end(self) -> Result<(), CheckStop> {
+        self.path.pop();
+        Ok(())
+    }
+}
+
+// ============================================================================
+// Parse Options
+// ============================================================================
+
+/// Options for the [`CustomizationProfile`] loaders, such as
+/// [`from_json_with`](CustomizationProfile::from_json_with).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ParseOptions {
+    /// Accept [`SvgSource::File`] sources.
+    ///
+    /// Off by default, since a profile naming a path reads that file when
+    /// it renders. Set it only for profiles from a trusted origin, such as
+    /// the host's own settings file.
+    pub allow_files: bool,
+}
+
+impl ParseOptions {
+    /// Returns an error naming the first file source in `profile`, unless
+    /// file sources are allowed.
+    fn check(self, profile: &CustomizationProfile) -> Result<(), serde_json::Error> {
+        match profile.file_sources().next() {
+            Some(path) if !self.allow_files => Err(serde::de::Error::custom(format!(
+                "file source {path:?} not allowed here; parse with ParseOptions {{ allow_files: true }} to opt in"
+            ))),
+            _ => Ok(()),
+        }
+    }
+}
+
+// ============================================================================
+// Tolerant Parsing
+// ============================================================================
+
+/// Substitutions made by [`CustomizationProfile::from_json_tolerant`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "tsify", derive(tsify_next::Tsify))]
+#[cfg_attr(feature = "tsify", tsify(into_wasm_abi, from_wasm_abi))]
+#[serde(rename_all = "camelCase")]
+pub struct ProfileParseReport {
+    /// Values that were replaced or dropped.
+    pub warnings: Vec<ProfileParseWarning>,
+}
+
+impl ProfileParseReport {
+    /// Returns true if the profile parsed without substitutions.
+    pub fn is_clean(&self) -> bool {
+        self.warnings.is_empty()
+    }
+}
+
+/// A value [`CustomizationProfile::from_json_tolerant`] couldn't read.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "tsify", derive(tsify_next::Tsify))]
+#[cfg_attr(feature = "tsify", tsify(into_wasm_abi, from_wasm_abi))]
+#[serde(tag = "kind", rename_all = "kebab-case", rename_all_fields = "camelCase")]
+pub enum ProfileParseWarning {
+    /// An enum value was not recognized and the field's default was used.
+    UnknownVariant {
+        /// JSON Pointer to the field, e.g. `/overlay/position`.
+        path: String,
+        /// The unrecognized value, as JSON.
+        found: String,
+        /// The default used instead, as JSON.
+        fallback: String,
+    },
+    /// A preset for an unknown layer was dropped.
+    UnknownPresetLayer {
+        /// The preset name.
+        name: String,
+        /// The unrecognized layer key.
+        layer: String,
+    },
+}
+
+impl std::fmt::Display for ProfileParseWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UnknownVariant { path, found, fallback } => {
+                write!(f, "{path}: unknown value {found}, using {fallback}")
+            }
+            Self::UnknownPresetLayer { name, layer } => write!(f, "preset {name:?} for unknown layer {layer:?} dropped"),
+        }
+    }
+}
+
+/// Layer keys a profile or preset may hold.
+const LAYER_KEYS: [&str; 6] = ["folderColorTarget", "gradientTint", "shadow", "decal", "overlay", "text"];
+
+/// Replaces unknown enum values in the layer config stored under `key`.
+fn tolerate_layer(key: &str, layer: &mut Value, path: &str, report: &mut ProfileParseReport) {
+    let Some(config) = layer.as_object_mut() else {
+        return;
+    };
+    let mut field = |name: &str, valid: fn(&Value) -> bool, fallback: fn() -> Value| {
+        if let Some(value) = config.get_mut(name)
+            && !valid(value)
+        {
+            let replacement = fallback();
+            report.warnings.push(ProfileParseWarning::UnknownVariant {
+                path: format!("{path}/{name}"),
+                found: value.to_string(),
+                fallback: replacement.to_string(),
+            });
+            *value = replacement;
+        }
+    };
+    match key {
+        "folderColorTarget" => field("hueApproximation", parses::<HueApproximation>, default_json::<HueApproximation>),
+        "gradientTint" => {
+            field("kind", parses::<GradientKind>, default_json::<GradientKind>);
+            field("blendMode", parses::<BlendMode>, default_json::<BlendMode>);
+        }
+        "decal" => field("blendMode", parses::<BlendMode>, default_json::<BlendMode>),
+        "overlay" => {
+            field("blendMode", parses::<BlendMode>, default_json::<BlendMode>);
+            field("position", parses::<OverlayPosition>, default_json::<OverlayPosition>);
+            field("anchor", parses::<OverlayAnchor>, default_json::<OverlayAnchor>);
+            field("clip", parses::<OverlayClip>, default_json::<OverlayClip>);
+            field("belowMin", parses::<MinSizePolicy>, default_json::<MinSizePolicy>);
+        }
+        "text" => {
+            field("position", parses::<OverlayPosition>, default_json::<OverlayPosition>);
+            field("font", parses::<TextFont>, default_json::<TextFont>);
+        }
+        _ => {}
+    }
+}
+
+fn parses<T: DeserializeOwned>(value: &Value) -> bool {
+    T::deserialize(value).is_ok()
+}
+
+fn default_json<T: Default + Serialize>() -> Value {
+    serde_json::to_value(T::default()).expect("enum defaults serialize")
+}
+
+/// Escapes a JSON Pointer reference token (RFC 6901).
+fn escape_pointer(token: &str) -> String {
+    token.replace('~', "~0").replace('/', "~1")
+}
+
+// ============================================================================
+// URL fragments
+// ============================================================================
+
+/// DEFLATE level used for URL fragments (maximum; profiles are tiny).
+const FRAGMENT_COMPRESSION_LEVEL: u8 = 10;
+
+/// Largest decompressed profile accepted by
+/// [`CustomizationProfile::from_url_fragment`], in bytes.
+const MAX_FRAGMENT_JSON_LEN: usize = 1024 * 1024;
+
+/// Errors that can occur while decoding a profile URL fragment.
+#[derive(Debug, Error)]
+pub enum ProfileFragmentError {
+    /// The fragment is not valid unpadded base64url.
+    #[error("invalid base64url in profile fragment: {0}")]
+    Base64(#[from] base64::DecodeError),
+
+    /// The payload is not valid DEFLATE data or exceeds 1 MiB when
+    /// decompressed.
+    #[error("profile fragment could not be decompressed")]
+    Decompress,
+
+    /// The decompressed payload is not a valid profile.
+    #[error("invalid profile JSON in fragment: {0}")]
+    Json(#[from] serde_json::Error),
+
+    /// The decoded profile fails [`CustomizationProfile::validate`].
+    #[error("invalid profile in fragment: {0}")]
+    Invalid(#[from] ProfileValueError),
+}
+
+// ============================================================================
+// JSON Patch
+// ============================================================================
+
+/// Errors that can occur while applying a JSON Patch to a profile.
+#[derive(Debug, Error)]
+pub enum ProfilePatchError {
+    /// The patch is not valid JSON Patch, or the patched document is not
+    /// a valid profile.
+    #[error("invalid profile patch: {0}")]
+    Json(#[from] serde_json::Error),
+
+    /// The patched profile fails [`CustomizationProfile::validate`].
+    #[error("patched profile is invalid: {0}")]
+    Invalid(#[from] ProfileValueError),
+
+    /// The patch adds a file source the profile didn't already hold.
+    #[error("patch adds file source {path:?}")]
+    FileSource {
+        /// The path of the added source.
+        path: PathBuf,
+    },
+
+    /// A path does not point to an existing location (or, for `add`, to
+    /// an existing parent).
+    #[error("patch path not found: {path:?}")]
+    PathNotFound {
+        /// The JSON Pointer that failed to resolve.
+        path: String,
+    },
+
+    /// A `test` operation did not match.
+    #[error("patch test failed at {path:?}")]
+    TestFailed {
+        /// The JSON Pointer that was tested.
+        path: String,
+    },
+}
+
+/// One RFC 6902 operation.
+#[derive(Debug, Deserialize)]
+#[serde(tag = "op", rename_all = "lowercase")]
+enum PatchOperation {
+    Add { path: String, value: Value },
+    Remove { path: String },
+    Replace { path: String, value: Value },
+    Move { from: String, path: String },
+    Copy { from: String, path: String },
+    Test { path: String, value: Value },
+}
+
+impl PatchOperation {
+    fn apply(self, document: &mut Value) -> Result<(), ProfilePatchError> {
+        match self {
+            Self::Add { path, value } => add(document, &path, value),
+            Self::Remove { path } => remove(document, &path).map(drop),
+            Self::Replace { path, value } => {
+                *document.pointer_mut(&path).ok_or(ProfilePatchError::PathNotFound { path })? = value;
+                Ok(())
+            }
+            Self::Move { from, path } => {
+                // A location can't be moved into its own child
+                if path.starts_with(&format!("{from}/")) {
+                    return Err(ProfilePatchError::PathNotFound { path });
+                }
+                let value = remove(document, &from)?;
+                add(document, &path, value)
+            }
+            Self::Copy { from, path } => {
+                let value = document
+                    .pointer(&from)
+                    .cloned()
+                    .ok_or(ProfilePatchError::PathNotFound { path: from })?;
+                add(document, &path, value)
+            }
+            Self::Test { path, value } => match document.pointer(&path) {
+                Some(actual) if *actual == value => Ok(()),
+                _ => Err(ProfilePatchError::TestFailed { path }),
+            },
+        }
+    }
+}
+
+/// Splits a JSON Pointer into its parent pointer and unescaped last token.
+fn split_pointer(path: &str) -> Option<(&str, String)> {
+    let (parent, token) = path.rsplit_once('/')?;
+    Some((parent, token.replace("~1", "/").replace("~0", "~")))
+}
+
+/// The RFC 6902 `add` operation: inserts into arrays, sets object members.
+fn add(document: &mut Value, path: &str, value: Value) -> Result<(), ProfilePatchError> {
+    if path.is_empty() {
+        *document = value;
+        return Ok(());
+    }
+    let not_found = || ProfilePatchError::PathNotFound { path: path.to_string() };
+    let (parent, token) = split_pointer(path).ok_or_else(not_found)?;
+    match document.pointer_mut(parent).ok_or_else(not_found)? {
+        Value::Object(map) => {
+            map.insert(token, value);
+        }
+        Value::Array(items) => {
+            let index = if token == "-" { items.len() } else { token.parse().map_err(|_| not_found())? };
+            if index > items.len() {
+                return Err(not_found());
+            }
+            items.insert(index, value);
+        }
+        _ => return Err(not_found()),
+    }
+    Ok(())
+}
+
+/// The RFC 6902 `remove` operation, returning the removed value.
+fn remove(document: &mut Value, path: &str) -> Result<Value, ProfilePatchError> {
+    let not_found = || ProfilePatchError::PathNotFound { path: path.to_string() };
+    let (parent, token) = split_pointer(path).ok_or_else(not_found)?;
+    match document.pointer_mut(parent).ok_or_else(not_found)? {
+        Value::Object(map) => map.remove(&token).ok_or_else(not_found),
+        Value::Array(items) => {
+            let index: usize = token.parse().map_err(|_| not_found())?;
+            (index < items.len()).then(|| items.remove(index)).ok_or_else(not_found)
+        }
+        _ => Err(not_found()),
+    }
+}
+
 // ============================================================================
 // Tests
 // ============================================================================
@@ -127,7 +1384,7 @@ impl CustomizationProfile {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::layer::{OverlayPosition, SvgSource};
+    use crate::layer::{ProtectedColor, SvgSource};
 
     #[test]
     fn profile_serialization_roundtrip() {
@@ -183,6 +1440,30 @@ mod tests {
         assert!(!customizer.pipeline.decal.is_active());
     }
 
+    #[test]
+    fn overlay_arrangement_round_trips_through_the_customizer() {
+        use crate::customizer::Configurable;
+        use crate::icon::{FolderIconBase, IconSet, SurfaceColor};
+        use crate::FolderIconCustomizer;
+
+        let arrangement = OverlayArrangement::Stack { spacing: 1.0 };
+        let profile = CustomizationProfile::new().with_overlay_arrangement(arrangement);
+        let json = profile.to_json().unwrap();
+        assert_eq!(json, r#"{"overlayArrangement":{"stack":{"spacing":1.0}}}"#);
+        assert_eq!(CustomizationProfile::from_json(&json).unwrap().overlay_arrangement, arrangement);
+
+        let mut customizer = FolderIconCustomizer::new(FolderIconBase::new(IconSet::new(), SurfaceColor::new(255, 217, 112)));
+        customizer.apply_profile(&profile);
+        assert_eq!(customizer.pipeline.overlay_arrangement(), arrangement);
+
+        customizer.pipeline.set_overlay_arrangement(OverlayArrangement::Overlap);
+        assert_eq!(customizer.export_profile().overlay_arrangement, OverlayArrangement::Overlap);
+
+        let (tolerated, report) = CustomizationProfile::from_json_tolerant(r#"{"overlayArrangement":"ring"}"#).unwrap();
+        assert_eq!(tolerated.overlay_arrangement, OverlayArrangement::Overlap);
+        assert_eq!(report.warnings.len(), 1);
+    }
+
     #[test]
     fn profile_export_from_customizer() {
         use crate::customizer::Configurable;
@@ -218,6 +1499,83 @@ mod tests {
         assert_eq!(restored.overlay.unwrap().position, OverlayPosition::TopLeft);
     }
 
+    #[test]
+    fn out_of_range_scales_are_clamped_at_render() {
+        use crate::customizer::Configurable;
+        use crate::icon::{FolderIconBase, IconImage, IconSet, SurfaceColor};
+        use crate::FolderIconCustomizer;
+
+        // Deserialization skips the constructor clamp
+        let json = r#"{
+            "decal": { "source": { "raw": "<svg xmlns='http://www.w3.org/2000/svg' width='10' height='10'/>" }, "scale": 500.0 },
+            "overlay": { "source": { "raw": "<svg xmlns='http://www.w3.org/2000/svg' width='10' height='10'/>" }, "position": "center", "scale": -3.0 }
+        }"#;
+        let profile = CustomizationProfile::from_json(json).unwrap();
+
+        let mut icons = IconSet::new();
+        icons.add_image(IconImage::new_full_content(image::RgbaImage::new(16, 16), 1.0));
+        let mut customizer = FolderIconCustomizer::new(FolderIconBase::new(icons, SurfaceColor::new(255, 217, 112)));
+        customizer.apply_profile(&profile);
+
+        let rendered = customizer.render(16).unwrap();
+        assert_eq!(rendered.dimensions().width, 16);
+    }
+
+    #[test]
+    fn url_fragment_roundtrip() {
+        let profile = CustomizationProfile::new()
+            .with_folder_color_target(FolderColorTargetConfig::new(33, 150, 243))
+            .with_overlay(SvgOverlayConfig::new("<svg></svg>", OverlayPosition::BottomRight, 0.3));
+
+        let fragment = profile.to_url_fragment().unwrap();
+        assert!(fragment.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'));
+
+        let restored = CustomizationProfile::from_url_fragment(&format!("#{fragment}")).unwrap();
+        assert_eq!(restored.to_json().unwrap(), profile.to_json().unwrap());
+    }
+
+    #[test]
+    fn file_sources_load_only_when_allowed() {
+        let allowed = ParseOptions { allow_files: true };
+        let mut decal = DecalConfig::new("<svg/>", 0.5);
+        decal.source = SvgSource::file("logo.svg");
+        let profile = CustomizationProfile::new().with_decal(decal.clone());
+
+        let json = profile.to_json().unwrap();
+        assert!(CustomizationProfile::from_json(&json).is_err());
+        assert!(CustomizationProfile::from_json_tolerant(&json).is_err());
+        assert!(CustomizationProfile::from_json_with(&json, allowed).is_ok());
+        assert!(CustomizationProfile::from_json_tolerant_with(&json, allowed).is_ok());
+        let fragment = profile.to_url_fragment().unwrap();
+        assert!(CustomizationProfile::from_url_fragment(&fragment).is_err());
+        assert_eq!(CustomizationProfile::from_url_fragment_with(&fragment, allowed).unwrap().to_json().unwrap(), json);
+        // Presets are checked too
+        let preset = CustomizationProfile::new().with_preset("logo", LayerPreset::Decal(decal));
+        assert!(CustomizationProfile::from_json(&preset.to_json().unwrap()).is_err());
+
+        // Patches keep the profile's file sources but can't add others
+        let mut patched = profile.clone();
+        patched.apply_patch(r#"[{ "op": "replace", "path": "/decal/scale", "value": 0.25 }]"#).unwrap();
+        assert_eq!(patched.decal.as_ref().unwrap().source, SvgSource::file("logo.svg"));
+        assert!(matches!(
+            patched.apply_patch(r#"[{ "op": "replace", "path": "/decal/source", "value": { "file": "/etc/passwd" } }]"#),
+            Err(ProfilePatchError::FileSource { .. })
+        ));
+        assert_eq!(patched.decal.unwrap().scale, 0.25);
+    }
+
+    #[test]
+    fn invalid_url_fragments_are_rejected() {
+        assert!(matches!(
+            CustomizationProfile::from_url_fragment("not base64!"),
+            Err(ProfileFragmentError::Base64(_))
+        ));
+        assert!(matches!(
+            CustomizationProfile::from_url_fragment("AAAA"),
+            Err(ProfileFragmentError::Decompress)
+        ));
+    }
+
     #[test]
     fn empty_profile_deserializes() {
         let json = "{}";
@@ -227,4 +1585,272 @@ mod tests {
         assert!(profile.decal.is_none());
         assert!(profile.overlay.is_none());
     }
+
+    #[test]
+    fn presets_resolve_for_layers_without_inline_config() {
+        let json = r#"{
+            "decal": { "source": { "raw": "<svg/>" }, "scale": 0.5 },
+            "presets": {
+                "blue": { "folderColorTarget": { "targetR": 33, "targetG": 150, "targetB": 243 } },
+                "stamp": { "decal": { "source": { "raw": "<svg></svg>" }, "scale": 0.2 } }
+            },
+            "uses": { "folderColorTarget": "blue", "decal": "stamp" }
+        }"#;
+        let profile = CustomizationProfile::from_json(json).unwrap();
+        let resolved = profile.resolve_presets().unwrap();
+
+        assert_eq!(resolved.folder_color_target.unwrap().target_b, 243);
+        // Inline config wins over the reference
+        assert_eq!(resolved.decal.unwrap().scale, 0.5);
+        assert!(resolved.presets.is_empty() && resolved.uses.is_empty());
+
+        // References survive a round trip unresolved
+        let restored = CustomizationProfile::from_json(&profile.to_json().unwrap()).unwrap();
+        assert_eq!(restored.uses, profile.uses);
+        assert!(restored.folder_color_target.is_none());
+    }
+
+    #[test]
+    fn bad_preset_references_are_rejected() {
+        let profile = CustomizationProfile::new()
+            .with_preset("blue", LayerPreset::FolderColorTarget(FolderColorTargetConfig::new(33, 150, 243)))
+            .with_overlay_preset("blue");
+        assert!(matches!(
+            profile.resolve_presets(),
+            Err(ProfilePresetError::WrongLayer { layer: "overlay", .. })
+        ));
+
+        let profile = CustomizationProfile::new().with_decal_preset("missing");
+        assert!(matches!(profile.resolve_presets(), Err(ProfilePresetError::UnknownPreset { .. })));
+    }
+
+    #[test]
+    fn apply_profile_follows_preset_references() {
+        use crate::customizer::Configurable;
+        use crate::icon::{FolderIconBase, IconSet, SurfaceColor};
+        use crate::FolderIconCustomizer;
+
+        let profile = CustomizationProfile::new()
+            .with_preset("badge", LayerPreset::Overlay(SvgOverlayConfig::new("<svg/>", OverlayPosition::BottomRight, 0.3)))
+            .with_overlay_preset("badge")
+            .with_decal_preset("missing");
+
+        let mut customizer = FolderIconCustomizer::new(FolderIconBase::new(IconSet::new(), SurfaceColor::new(255, 217, 112)));
+        customizer.apply_profile(&profile);
+
+        assert_eq!(customizer.pipeline.overlay.config().unwrap().scale, 0.3);
+        assert!(!customizer.pipeline.decal.has_config());
+        assert!(customizer.export_profile().presets.is_empty());
+    }
+
+    #[test]
+    fn json_patch_operations() {
+        let mut profile = CustomizationProfile::new()
+            .with_folder_color_target(FolderColorTargetConfig::new(33, 150, 243))
+            .with_decal(DecalConfig::new("<svg></svg>", 0.5));
+
+        profile
+            .apply_patch(
+                r#"[
+                    { "op": "test", "path": "/decal/scale", "value": 0.5 },
+                    { "op": "replace", "path": "/decal/scale", "value": 0.25 },
+                    { "op": "add", "path": "/folderColorTarget/protectedColors", "value": [] },
+                    { "op": "add", "path": "/folderColorTarget/protectedColors/-", "value": { "kind": "neutral", "tolerance": 0.1 } },
+                    { "op": "copy", "from": "/decal/source", "path": "/overlay" },
+                    { "op": "remove", "path": "/overlay" }
+                ]"#,
+            )
+            .unwrap();
+
+        assert_eq!(profile.decal.as_ref().unwrap().scale, 0.25);
+        let target = profile.folder_color_target.as_ref().unwrap();
+        assert_eq!(target.protected_colors, [ProtectedColor::neutral(0.1)]);
+        assert!(profile.overlay.is_none());
+    }
+
+    #[test]
+    fn failed_json_patch_leaves_profile_unchanged() {
+        let mut profile = CustomizationProfile::new().with_decal(DecalConfig::new("<svg></svg>", 0.5));
+        let before = profile.to_json().unwrap();
+
+        let result = profile.apply_patch(
+            r#"[{ "op": "replace", "path": "/decal/scale", "value": 0.9 }, { "op": "test", "path": "/decal/scale", "value": 0.5 }]"#,
+        );
+        assert!(matches!(result, Err(ProfilePatchError::TestFailed { .. })));
+        assert!(matches!(
+            profile.apply_patch(r#"[{ "op": "remove", "path": "/overlay" }]"#),
+            Err(ProfilePatchError::PathNotFound { .. })
+        ));
+        // Patched document is not a valid profile
+        assert!(matches!(
+            profile.apply_patch(r#"[{ "op": "replace", "path": "/decal/scale", "value": "big" }]"#),
+            Err(ProfilePatchError::Json(_))
+        ));
+        assert_eq!(profile.to_json().unwrap(), before);
+    }
+
+    #[test]
+    fn tolerant_parse_replaces_unknown_values() {
+        let json = r#"{
+            "gradientTint": { "kind": "conic", "stops": [], "opacity": 1.0 },
+            "text": { "text": "v2", "size": 0.3, "position": "center", "font": { "variable": "x" } },
+            "presets": {
+                "glow": { "outerGlow": { "radius": 2 } },
+                "a/b": { "overlay": { "source": { "raw": "<svg/>" }, "position": "top-left", "scale": 0.2, "clip": "hull", "belowMin": "shrink" } }
+            }
+        }"#;
+        let (profile, report) = CustomizationProfile::from_json_tolerant(json).unwrap();
+
+        assert_eq!(profile.gradient_tint.unwrap().kind, GradientKind::Linear);
+        let text = profile.text.unwrap();
+        assert_eq!(text.position, OverlayPosition::Center);
+        assert!(text.font.is_bundled());
+        assert!(!profile.presets.contains_key("glow"));
+        assert!(matches!(&profile.presets["a/b"], LayerPreset::Overlay(o) if o.clip == OverlayClip::None && o.below_min == MinSizePolicy::Clamp));
+
+        let paths: Vec<_> = report
+            .warnings
+            .iter()
+            .map(|w| match w {
+                ProfileParseWarning::UnknownVariant { path, .. } => path.as_str(),
+                ProfileParseWarning::UnknownPresetLayer { name, .. } => name.as_str(),
+            })
+            .collect();
+        assert_eq!(paths, ["/gradientTint/kind", "/text/font", "/presets/a~1b/overlay/clip", "/presets/a~1b/overlay/belowMin", "glow"]);
+
+        let current = CustomizationProfile::new()
+            .with_overlay(SvgOverlayConfig::new("<svg/>", OverlayPosition::Auto, 0.2).with_clip(OverlayClip::BaseAlphaExpanded(2)))
+            .with_text(TextLayerConfig::new("v2", OverlayPosition::TopLeft, 0.3).with_font(TextFont::Family("Inter".into())));
+        let (_, report) = CustomizationProfile::from_json_tolerant(&current.to_json().unwrap()).unwrap();
+        assert!(report.is_clean());
+    }
+
+    #[test]
+    fn non_finite_values_are_caught() {
+        use crate::layer::{ColorAdjustment, GradientStop, ScaleBreakpoint};
+
+        // Every optional field set: none may serialize as null
+        let full = CustomizationProfile::new()
+            .with_folder_color_target(FolderColorTargetConfig::new(33, 150, 243).with_protected_color(ProtectedColor::neutral(0.1)))
+            .with_gradient_tint(GradientTintConfig::linear(90.0, vec![GradientStop::new(0.0, 0, 0, 0), GradientStop::new(1.0, 255, 255, 255)]))
+            .with_shadow(ShadowConfig::new(1.0, 2.0, 3.0))
+            .with_decal(
+                DecalConfig::new("<svg/>", 0.5)
+                    .with_scale_curve(vec![ScaleBreakpoint::new(16, 0.6)])
+                    .with_shadow(ShadowConfig::new(0.0, 1.0, 1.0))
+                    .with_opacity(0.5)
+                    .with_lighten_below(0.2)
+                    .with_clip_to_alpha(true),
+            )
+            .with_overlay(
+                SvgOverlayConfig::new("<svg/>", OverlayPosition::custom(0.5, 0.5), 0.3)
+                    .with_min_pixel_size(6, MinSizePolicy::Omit)
+                    .with_color_adjustment(ColorAdjustment::hue(30.0)),
+            )
+            .with_text(TextLayerConfig::new("Hi", OverlayPosition::Auto, 0.2).with_color(0, 0, 0))
+            .with_preset("shadow", LayerPreset::Shadow(ShadowConfig::new(0.0, 0.0, 0.0)))
+            .with_shadow_preset("shadow");
+        assert_eq!(full.validate(), Ok(()));
+
+        let mut gradient = full.gradient_tint.clone().unwrap();
+        gradient.stops[1].offset = f32::INFINITY;
+        let invalid = full.clone().with_gradient_tint(gradient);
+        assert_eq!(invalid.validate(), Err(ProfileValueError::NonFinite { path: "/gradientTint/stops/1/offset".into() }));
+        let mut shadow = ShadowConfig::new(0.0, 0.0, 0.0);
+        shadow.blur_radius = f32::NAN;
+        let invalid = full.with_preset("shadow", LayerPreset::Shadow(shadow));
+        assert_eq!(invalid.validate(), Err(ProfileValueError::NonFinite { path: "/presets/shadow/shadow/blurRadius".into() }));
+
+        // Loading and applying check it too
+        let json = r#"{"decal":{"source":{"raw":"<svg/>"},"scale":1e300}}"#;
+        assert!(CustomizationProfile::from_json(json).is_err());
+        assert!(CustomizationProfile::from_json_tolerant(json).is_err());
+        let mut customizer = crate::FolderIconCustomizer::new(crate::FolderIconBase::new(
+            crate::IconSet::new(),
+            crate::SurfaceColor::new(255, 217, 112),
+        ));
+        let report = customizer.preview_apply(&invalid);
+        assert!(report.is_noop());
+        assert!(matches!(&report.warnings[..], [crate::ApplyWarning::NonFinite { path }] if path == "/presets/shadow/shadow/blurRadius"));
+        crate::Configurable::apply_profile(&mut customizer, &invalid);
+        assert!(!customizer.pipeline.shadow.has_config());
+
+        // So do the URL fragment and JSON Patch paths
+        let json = r#"{"decal":{"source":{"raw":"<svg/>"},"scale":1e39}}"#;
+        let fragment = URL_SAFE_NO_PAD.encode(miniz_oxide::deflate::compress_to_vec(json.as_bytes(), FRAGMENT_COMPRESSION_LEVEL));
+        assert!(matches!(CustomizationProfile::from_url_fragment(&fragment), Err(ProfileFragmentError::Invalid(_))));
+        let mut profile = CustomizationProfile::new().with_decal(DecalConfig::new("<svg/>", 0.5));
+        assert!(matches!(
+            profile.apply_patch(r#"[{ "op": "replace", "path": "/decal/scale", "value": 1e39 }]"#),
+            Err(ProfilePatchError::Invalid(ProfileValueError::NonFinite { .. }))
+        ));
+        assert_eq!(profile.decal.unwrap().scale, 0.5);
+
+        // Constructors fall back to defaults rather than keep NaN
+        assert_eq!(DecalConfig::new("<svg/>", f32::NAN).scale, 0.0);
+        assert_eq!(DecalConfig::new("<svg/>", 0.5).with_opacity(f32::NAN).opacity, 1.0);
+        assert_eq!(OverlayPosition::custom(f32::NAN, f32::NEG_INFINITY), OverlayPosition::custom(1.0, 1.0));
+        assert_eq!(ShadowConfig::new(f32::NAN, 1.0, f32::INFINITY), ShadowConfig::new(0.0, 1.0, 0.0));
+        assert!(ColorAdjustment::new(f32::NAN, f32::NAN).is_identity());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn unserializable_profiles_are_rejected() {
+        use std::os::unix::ffi::OsStrExt;
+
+        let mut decal = DecalConfig::new("<svg/>", 0.5);
+        decal.source = SvgSource::file(std::ffi::OsStr::from_bytes(b"\xff.svg"));
+        let profile = CustomizationProfile::new().with_decal(decal);
+        assert!(matches!(profile.validate(), Err(ProfileValueError::Unserializable { .. })));
+
+        let icons = crate::IconSet::from_images(vec![crate::IconImage::new_full_content(image::RgbaImage::new(16, 16), 1.0)]);
+        let mut customizer = crate::FolderIconCustomizer::new(crate::FolderIconBase::new(icons, crate::SurfaceColor::new(255, 217, 112)));
+        assert!(customizer.try_apply_profile(&profile).is_err());
+        crate::Configurable::apply_profile(&mut customizer, &profile);
+        assert!(!customizer.pipeline.decal.has_config());
+
+        // A customizer holding such a source can't compute render IDs
+        customizer.pipeline.decal.set_config(profile.decal);
+        assert_eq!(customizer.render_id(16), None);
+        assert!(matches!(customizer.render_identified(16), Err(crate::RenderError::Unidentifiable { .. })));
+    }
+
+    #[test]
+    fn intensity_scales_layers_and_presets() {
+        let profile = CustomizationProfile::new()
+            .with_folder_color_target(FolderColorTargetConfig::new(33, 150, 243))
+            .with_preset("badge", LayerPreset::Overlay(SvgOverlayConfig::new("<svg/>", OverlayPosition::BottomRight, 0.3)))
+            .with_preset("label", LayerPreset::Text(TextLayerConfig::new("Docs", OverlayPosition::Center, 0.2)))
+            .with_overlay_preset("badge")
+            .with_intensity(0.25);
+        assert!(profile.to_json().unwrap().contains("\"intensity\":0.25"));
+        assert!(!CustomizationProfile::new().to_json().unwrap().contains("intensity"));
+
+        let scaled = profile.with_intensity_applied();
+        assert_eq!(scaled.folder_color_target.as_ref().unwrap().strength, 0.25);
+        assert_eq!(scaled.resolved_overlay().unwrap().unwrap().opacity, 0.25);
+        // Text stays legible
+        let label = |profile: &CustomizationProfile| serde_json::to_value(&profile.presets["label"]).unwrap();
+        assert_eq!(label(&scaled), label(&profile));
+        assert!(matches!(CustomizationProfile::new().with_intensity_applied(), Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn zero_intensity_leaves_the_folder_color() {
+        use crate::icon::{IconImage, SurfaceColor};
+        use crate::layer::LayerPipeline;
+        use image::{Rgba, RgbaImage};
+
+        let base = IconImage::new_full_content(RgbaImage::from_pixel(16, 16, Rgba([255, 217, 112, 255])), 1.0);
+        let mut pipeline = LayerPipeline::default();
+        let profile = CustomizationProfile::new()
+            .with_folder_color_target(FolderColorTargetConfig::new(33, 150, 243))
+            .with_intensity(0.0);
+        crate::customizer::apply_profile_to(&mut pipeline, &profile, &mut Vec::new()).unwrap();
+
+        let rendered = pipeline.render(&base, &SurfaceColor::new(255, 217, 112)).unwrap();
+        let [r, g, b, _] = rendered.data().get_pixel(8, 8).0;
+        assert!(r.abs_diff(255) <= 1 && g.abs_diff(217) <= 1 && b.abs_diff(112) <= 1, "{:?}", (r, g, b));
+    }
 }
@@ -0,0 +1,474 @@
+//! Rendering off the UI thread.
+//!
+//! An editor re-renders on every slider tick, at several sizes, while the
+//! user keeps dragging. Rendering each request in turn on the UI thread
+//! stutters, and queueing them all renders stale states long after the
+//! slider stopped. A [`RenderQueue`] owns a [`FolderIconCustomizer`] on a
+//! worker thread and renders submitted jobs by priority. A job replaces any
+//! pending job for the same size, so only the latest state of a slider is
+//! rendered, and pending jobs can be cancelled. Finished renders arrive on
+//! a channel the UI polls.
+//!
+//! Async backends can instead await single renders from
+//! [`FolderIconCustomizer::render_async`], which returns a [`RenderFuture`].
+//! Those share one worker: rayon's pool with the `rayon` feature, otherwise
+//! a single thread started on first use.
+//!
+//! ```
+//! use folco_renderer::queue::{RenderJob, RenderPriority, RenderQueue};
+//! use folco_renderer::{CustomizationProfile, FolderColorTargetConfig, FolderIconBase, FolderIconCustomizer, IconImage, IconSet, SurfaceColor};
+//! use image::{Rgba, RgbaImage};
+//!
+//! let icon = IconImage::new_full_content(RgbaImage::from_pixel(32, 32, Rgba([255, 217, 112, 255])), 1.0);
+//! let base = FolderIconBase::new(IconSet::from_images(vec![icon]), SurfaceColor::new(255, 217, 112));
+//! let queue = RenderQueue::new(FolderIconCustomizer::new(base));
+//!
+//! let profile = CustomizationProfile::new().with_folder_color_target(FolderColorTargetConfig::new(33, 150, 243));
+//! let id = queue.submit(RenderJob::new(32).with_profile(profile).with_priority(RenderPriority::High));
+//!
+//! let done = queue.results().recv().unwrap();
+//! assert_eq!(done.id, id);
+//! assert_eq!(done.result.unwrap().data().get_pixel(16, 16).0, [33, 150, 243, 255]);
+//! ```
+
+use std::future::Future;
+use std::panic::{self, AssertUnwindSafe};
+use std::pin::Pin;
+use std::sync::mpsc::{self, Receiver, Sender};
+#[cfg(not(feature = "rayon"))]
+use std::sync::OnceLock;
+use std::sync::{Arc, Condvar, Mutex};
+use std::task::{Context, Poll, Waker};
+use std::thread::{self, JoinHandle};
+
+use crate::customizer::{Configurable, FolderIconCustomizer};
+use crate::error::RenderError;
+use crate::icon::IconImage;
+use crate::layer::cancel::CancellationToken;
+use crate::profile::CustomizationProfile;
+
+/// How urgently a [`RenderJob`] is wanted. Higher priorities render first;
+/// jobs of equal priority render in submission order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub enum RenderPriority {
+    /// Prefetching, e.g. sizes not on screen.
+    Low,
+    /// The default.
+    #[default]
+    Normal,
+    /// What the user is looking at, e.g. the preview under a slider.
+    High,
+}
+
+/// Identifies a submitted [`RenderJob`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct JobId(u64);
+
+/// A render of one size, optionally with a new profile applied first.
+#[derive(Debug, Clone)]
+pub struct RenderJob {
+    /// The logical size to render, as for [`FolderIconCustomizer::render`].
+    pub logical_size: u32,
+    /// A profile to apply before rendering. Profiles apply in submission
+    /// order, whatever order their jobs render in, and stay applied for
+    /// later jobs, even if this job is replaced or cancelled.
+    pub profile: Option<CustomizationProfile>,
+    /// How urgently the render is wanted.
+    pub priority: RenderPriority,
+}
+
+impl RenderJob {
+    /// Creates a job rendering `logical_size` with the current settings.
+    pub fn new(logical_size: u32) -> Self {
+        Self {
+            logical_size,
+            profile: None,
+            priority: RenderPriority::Normal,
+        }
+    }
+
+    /// Applies `profile` before rendering.
+    pub fn with_profile(mut self, profile: CustomizationProfile) -> Self {
+        self.profile = Some(profile);
+        self
+    }
+
+    /// Sets the priority.
+    pub fn with_priority(mut self, priority: RenderPriority) -> Self {
+        self.priority = priority;
+        self
+    }
+}
+
+/// A finished [`RenderJob`].
+#[derive(Debug)]
+pub struct CompletedRender {
+    /// The id [`RenderQueue::submit`] returned.
+    pub id: JobId,
+    /// The rendered logical size.
+    pub logical_size: u32,
+    /// The rendered image, or why rendering failed.
+    pub result: Result<IconImage, RenderError>,
+}
+
+/// Jobs waiting for the worker, the latest profile it hasn't applied yet,
+/// and whether it should stop.
+///
+/// Profiles are kept apart from the jobs that carried them, so a job
+/// rendering late can't roll the customizer back to an older profile.
+/// Applying a profile replaces every layer's settings, so only the latest
+/// one needs to be kept.
+#[derive(Default)]
+struct Pending {
+    jobs: Vec<(JobId, RenderJob)>,
+    profile: Option<CustomizationProfile>,
+    next_id: u64,
+    shutdown: bool,
+}
+
+impl Pending {
+    /// Queues `job` in place of any pending job for the same size.
+    fn push(&mut self, mut job: RenderJob) -> JobId {
+        let id = JobId(self.next_id);
+        self.next_id += 1;
+        if let Some(profile) = job.profile.take() {
+            self.profile = Some(profile);
+        }
+        self.jobs.retain(|(_, queued)| queued.logical_size != job.logical_size);
+        self.jobs.push((id, job));
+        id
+    }
+
+    /// Drops the job `id`. Returns true if it was pending.
+    fn remove(&mut self, id: JobId) -> bool {
+        let before = self.jobs.len();
+        self.jobs.retain(|(queued, _)| *queued != id);
+        self.jobs.len() != before
+    }
+
+    /// Removes the job to render next: the highest priority, oldest first.
+    /// It carries the latest unapplied profile, whichever job brought it.
+    fn pop(&mut self) -> Option<(JobId, RenderJob)> {
+        let next = (0..self.jobs.len()).max_by_key(|&i| (self.jobs[i].1.priority, std::cmp::Reverse(self.jobs[i].0)))?;
+        let (id, mut job) = self.jobs.remove(next);
+        job.profile = self.profile.take();
+        Some((id, job))
+    }
+}
+
+type Shared = Arc<(Mutex<Pending>, Condvar)>;
+
+/// Renders [`RenderJob`]s on a worker thread that owns a
+/// [`FolderIconCustomizer`], by priority, reporting each on
+/// [`results`](Self::results).
+///
+/// Dropping the queue discards pending jobs and waits for the render in
+/// progress, if any.
+pub struct RenderQueue {
+    shared: Shared,
+    results: Receiver<CompletedRender>,
+    worker: Option<JoinHandle<FolderIconCustomizer>>,
+}
+
+impl RenderQueue {
+    /// Moves `customizer` onto a new worker thread.
+    pub fn new(customizer: FolderIconCustomizer) -> Self {
+        let shared = Shared::default();
+        let (sender, results) = mpsc::channel();
+        let worker = {
+            let shared = Arc::clone(&shared);
+            thread::spawn(move || run(customizer, &shared, &sender))
+        };
+        Self {
+            shared,
+            results,
+            worker: Some(worker),
+        }
+    }
+
+    /// Queues `job`, replacing any pending job for the same size, so
+    /// only the latest of a burst of updates renders. A replaced job is
+    /// dropped without a result; its profile still applies.
+    pub fn submit(&self, job: RenderJob) -> JobId {
+        let id = self.lock().push(job);
+        self.shared.1.notify_one();
+        id
+    }
+
+    /// Drops the job `id` if it hasn't started. Returns true if it was
+    /// pending; a job already rendering still reports its result. The
+    /// job's profile still applies.
+    pub fn cancel(&self, id: JobId) -> bool {
+        self.lock().remove(id)
+    }
+
+    /// Drops every pending job. Returns how many were dropped.
+    pub fn cancel_all(&self) -> usize {
+        std::mem::take(&mut self.lock().jobs).len()
+    }
+
+    /// Returns the number of jobs waiting to render.
+    pub fn pending(&self) -> usize {
+        self.lock().jobs.len()
+    }
+
+    /// Returns the channel finished renders arrive on, in the order they
+    /// finish.
+    pub fn results(&self) -> &Receiver<CompletedRender> {
+        &self.results
+    }
+
+    /// Drops pending jobs, waits for the render in progress and returns
+    /// the customizer with every submitted profile applied.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a render panicked on the worker thread.
+    pub fn shutdown(mut self) -> FolderIconCustomizer {
+        self.stop().expect("the render worker panicked")
+    }
+
+    fn stop(&mut self) -> Option<FolderIconCustomizer> {
+        {
+            let mut pending = self.lock();
+            pending.jobs.clear();
+            pending.shutdown = true;
+        }
+        self.shared.1.notify_one();
+        // A panicking layer takes the customizer with it
+        self.worker.take()?.join().ok()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Pending> {
+        self.shared.0.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Drop for RenderQueue {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+/// The worker loop: renders jobs until shut down.
+fn run(mut customizer: FolderIconCustomizer, shared: &Shared, results: &Sender<CompletedRender>) -> FolderIconCustomizer {
+    let (lock, wakeup) = &**shared;
+    loop {
+        let (id, job) = {
+            let mut pending = lock.lock().unwrap_or_else(|e| e.into_inner());
+            loop {
+                if pending.shutdown {
+                    if let Some(profile) = pending.profile.take() {
+                        customizer.apply_profile(&profile);
+                    }
+                    return customizer;
+                }
+                if let Some(next) = pending.pop() {
+                    break next;
+                }
+                pending = wakeup.wait(pending).unwrap_or_else(|e| e.into_inner());
+            }
+        };
+
+        if let Some(profile) = &job.profile {
+            customizer.apply_profile(profile);
+        }
+        let result = customizer.render(job.logical_size);
+        // Nobody is listening once the receiver is gone, but the queue may
+        // still be shutting down
+        let _ = results.send(CompletedRender {
+            id,
+            logical_size: job.logical_size,
+            result,
+        });
+    }
+}
+
+// ============================================================================
+// RenderFuture
+// ============================================================================
+
+/// A job for the shared render worker.
+type WorkerJob = Box<dyn FnOnce() + Send>;
+
+/// Runs `job` on the shared render worker: rayon's pool with the `rayon`
+/// feature, otherwise one thread started on first use. A panicking job
+/// doesn't take the worker down with it.
+fn spawn_render(job: impl FnOnce() + Send + 'static) {
+    let job: WorkerJob = Box::new(move || {
+        let _ = panic::catch_unwind(AssertUnwindSafe(job));
+    });
+    #[cfg(feature = "rayon")]
+    rayon::spawn(job);
+    #[cfg(not(feature = "rayon"))]
+    {
+        static WORKER: OnceLock<Sender<WorkerJob>> = OnceLock::new();
+        let worker = WORKER.get_or_init(|| {
+            let (sender, jobs) = mpsc::channel::<WorkerJob>();
+            thread::spawn(move || jobs.into_iter().for_each(|job| job()));
+            sender
+        });
+        // The worker never exits, so its receiver is never dropped
+        let _ = worker.send(job);
+    }
+}
+
+/// The result of a render on the shared worker, once it arrives.
+#[derive(Default)]
+struct Slot {
+    result: Option<Result<IconImage, RenderError>>,
+    waker: Option<Waker>,
+}
+
+/// A render running on the shared render worker, from
+/// [`FolderIconCustomizer::render_async`].
+///
+/// Works with any executor. Dropping the future cancels the render.
+pub struct RenderFuture {
+    slot: Arc<Mutex<Slot>>,
+    token: CancellationToken,
+}
+
+impl RenderFuture {
+    /// Runs `render` on the shared render worker, completing with its
+    /// result, or with [`RenderError::Panicked`] if it panics.
+    pub(crate) fn spawn(token: CancellationToken, render: impl FnOnce() -> Result<IconImage, RenderError> + Send + 'static) -> Self {
+        let slot = Arc::new(Mutex::new(Slot::default()));
+        let done = Arc::clone(&slot);
+        spawn_render(move || {
+            let result = panic::catch_unwind(AssertUnwindSafe(render)).unwrap_or(Err(RenderError::Panicked));
+            let mut slot = done.lock().unwrap_or_else(|e| e.into_inner());
+            slot.result = Some(result);
+            if let Some(waker) = slot.waker.take() {
+                waker.wake();
+            }
+        });
+        Self { slot, token }
+    }
+
+    /// Returns the token cancelling this render, e.g. to cancel it from
+    /// elsewhere while it is awaited.
+    pub fn cancellation_token(&self) -> &CancellationToken {
+        &self.token
+    }
+}
+
+impl Future for RenderFuture {
+    type Output = Result<IconImage, RenderError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut slot = self.slot.lock().unwrap_or_else(|e| e.into_inner());
+        match slot.result.take() {
+            Some(result) => Poll::Ready(result),
+            None => {
+                slot.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+impl Drop for RenderFuture {
+    fn drop(&mut self) {
+        self.token.cancel();
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::icon::{FolderIconBase, IconSet, SurfaceColor};
+    use crate::layer::FolderColorTargetConfig;
+    use image::{Rgba, RgbaImage};
+
+    #[test]
+    fn jobs_run_by_priority_and_coalesce_per_size() {
+        let mut pending = Pending::default();
+        let low = pending.push(RenderJob::new(16).with_priority(RenderPriority::Low));
+        let first = pending.push(RenderJob::new(32));
+        let latest = pending.push(RenderJob::new(32).with_profile(CustomizationProfile::new()));
+        let high = pending.push(RenderJob::new(64).with_priority(RenderPriority::High));
+        let cancelled = pending.push(RenderJob::new(48));
+        assert!(pending.remove(cancelled));
+        assert!(!pending.remove(first));
+
+        let order: Vec<_> = std::iter::from_fn(|| pending.pop()).map(|(id, _)| id).collect();
+        assert_eq!(order, [high, latest, low]);
+    }
+
+    #[test]
+    fn profiles_apply_in_submission_order() {
+        let color = |g| CustomizationProfile::new().with_folder_color_target(FolderColorTargetConfig::new(0, g, 0));
+        let mut pending = Pending::default();
+        pending.push(RenderJob::new(16).with_profile(color(100)).with_priority(RenderPriority::Low));
+        pending.push(RenderJob::new(32).with_profile(color(200)));
+        // Replacing a job keeps its profile
+        pending.push(RenderJob::new(32).with_priority(RenderPriority::High));
+
+        let mut popped = || {
+            let (_, job) = pending.pop().unwrap();
+            (job.logical_size, job.profile.and_then(|p| p.folder_color_target).map(|c| c.target_g))
+        };
+        assert_eq!(popped(), (32, Some(200)));
+        // The older, lower-priority job renders later without rolling back
+        assert_eq!(popped(), (16, None));
+    }
+
+    #[test]
+    fn worker_applies_profiles_and_hands_back_the_customizer() {
+        let icon = |size| IconImage::new_full_content(RgbaImage::from_pixel(size, size, Rgba([255, 217, 112, 255])), 1.0);
+        let base = FolderIconBase::new(IconSet::from_images(vec![icon(16), icon(32)]), SurfaceColor::new(255, 217, 112));
+        let queue = RenderQueue::new(FolderIconCustomizer::new(base));
+
+        let profile = CustomizationProfile::new().with_folder_color_target(FolderColorTargetConfig::new(0, 200, 0));
+        let recolored = queue.submit(RenderJob::new(32).with_profile(profile));
+        let done = queue.results().recv().unwrap();
+        assert_eq!((done.id, done.logical_size), (recolored, 32));
+        // The profile stays applied for later jobs
+        queue.submit(RenderJob::new(16));
+        assert_eq!(queue.results().recv().unwrap().result.unwrap().data().get_pixel(0, 0).0, [0, 200, 0, 255]);
+
+        let customizer = queue.shutdown();
+        assert!(customizer.pipeline.folder_color_target.config().is_some());
+    }
+
+    #[test]
+    fn render_futures_resolve_and_cancel_on_drop() {
+        let poll = |mut future: RenderFuture| loop {
+            let mut cx = Context::from_waker(Waker::noop());
+            if let Poll::Ready(result) = Pin::new(&mut future).poll(&mut cx) {
+                return result;
+            }
+            thread::yield_now();
+        };
+        let icon = |size| IconImage::new_full_content(RgbaImage::from_pixel(size, size, Rgba([255, 217, 112, 255])), 1.0);
+        let base = FolderIconBase::new(IconSet::from_images(vec![icon(16), icon(32)]), SurfaceColor::new(255, 217, 112));
+        let mut customizer = FolderIconCustomizer::new(base);
+        customizer.pipeline.folder_color_target.set_config(Some(FolderColorTargetConfig::new(0, 200, 0)));
+
+        let rendered = poll(customizer.render_async(32, CancellationToken::new())).unwrap();
+        assert_eq!(rendered.data().get_pixel(0, 0).0, [0, 200, 0, 255]);
+
+        let stale = CancellationToken::new();
+        stale.cancel();
+        assert!(matches!(poll(customizer.render_async(32, stale)), Err(RenderError::Cancelled)));
+        let token = CancellationToken::new();
+        drop(customizer.render_async(32, token.clone()));
+        assert!(token.is_cancelled());
+
+        // A panicking render still completes its future
+        let panicked = RenderFuture::spawn(CancellationToken::new(), || panic!("layer bug"));
+        assert!(matches!(poll(panicked), Err(RenderError::Panicked)));
+
+        // A render's copy of the pipeline holds only its size's entries
+        customizer.render(16).unwrap();
+        customizer.render(32).unwrap();
+        let shard = customizer.pipeline.copy_shard(crate::layer::CacheKey::from_icon(&icon(32)));
+        assert_eq!(shard.composite.cache_footprint().count(), 1);
+        assert_eq!(customizer.pipeline.composite.cache_footprint().count(), 2);
+    }
+}
@@ -0,0 +1,227 @@
+//! Deterministic identifiers for rendered outputs.
+//!
+//! A [`RenderId`] fingerprints everything that determines a rendered
+//! image's pixels: the base image, the surface color, every active layer
+//! config, the accent color, the dominant color sampler, the placement
+//! rounding, and the crate's pixel-pipeline version. Two
+//! renders with the same ID are pixel-identical, so caching layers and sync
+//! servers can dedupe outputs and skip re-rendering until an input — or the
+//! crate's rendering itself — actually changes.
+
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::analysis::DominantColorSampler;
+use crate::icon::{IconImage, SurfaceColor};
+use crate::layer::{AccentColor, PlacementRounding};
+use crate::profile::CustomizationProfile;
+
+/// Version of the crate's pixel pipeline.
+///
+/// Bumped whenever a release can change rendered output for the same inputs
+/// — blend or color math changes, an SVG engine upgrade, resampling tweaks.
+/// Disk caches and golden-image tests should store this alongside their
+/// outputs and invalidate on mismatch. It is also mixed into every
+/// [`RenderId`], so IDs change across such releases automatically.
+pub const PIXEL_PIPELINE_VERSION: u32 = 2;
+
+/// FNV-1a 128-bit offset basis.
+const FNV_OFFSET: u128 = 0x6c62272e07bb014262b821756295c58d;
+
+/// FNV-1a 128-bit prime.
+const FNV_PRIME: u128 = 0x0000000001000000000000000000013b;
+
+/// A stable 128-bit fingerprint of a render's inputs.
+///
+/// The hash is platform- and process-independent (FNV-1a over a fixed byte
+/// layout), so IDs can be persisted and compared across machines. Formats
+/// and serializes as 32 lowercase hex digits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct RenderId(u128);
+
+impl RenderId {
+    /// Computes the ID for rendering `base` with the given inputs.
+    ///
+    /// `profile` should contain only the layers that are active, so that
+    /// toggling a layer off changes the ID. Fails if `profile` can't be
+    /// serialized, e.g. because a file source's path is not valid UTF-8.
+    pub(crate) fn compute(
+        base: &IconImage,
+        surface: &SurfaceColor,
+        profile: &CustomizationProfile,
+        accent: Option<AccentColor>,
+        sampler: Option<DominantColorSampler>,
+        rounding: PlacementRounding,
+    ) -> Result<Self, serde_json::Error> {
+        let mut hasher = Fnv1a::default();
+        hasher.write(&PIXEL_PIPELINE_VERSION.to_le_bytes());
+
+        let bounds = base.content_bounds;
+        for value in [base.data().width(), base.data().height(), base.scale.to_bits()] {
+            hasher.write(&value.to_le_bytes());
+        }
+        for value in [bounds.x, bounds.y, bounds.width, bounds.height] {
+            hasher.write(&value.to_le_bytes());
+        }
+        hasher.write(base.data().as_raw());
+
+        hasher.write(&[surface.r, surface.g, surface.b]);
+        match accent {
+            Some(a) => hasher.write(&[1, a.r, a.g, a.b]),
+            None => hasher.write(&[0]),
+        }
+
+        // Struct fields serialize in declaration order, so the JSON is stable
+        let json = serde_json::to_vec(profile)?;
+        hasher.write(&(json.len() as u64).to_le_bytes());
+        hasher.write(&json);
+
+        // Sources held elsewhere can change under the same config, so hash
+        // what they resolve to; other sources are covered by the JSON
+        let sources = [profile.decal.as_ref().map(|d| &d.source), profile.overlay.as_ref().map(|o| &o.source)];
+        for source in sources.into_iter().flatten().filter(|source| source.is_external()) {
+            match source.resolve_shared() {
+                Ok(svg) => {
+                    hasher.write(&[1]);
+                    hasher.write(&(svg.len() as u64).to_le_bytes());
+                    hasher.write(svg.as_bytes());
+                }
+                Err(_) => hasher.write(&[0]),
+            }
+        }
+
+        // Appended only when set, so IDs from before the option existed hold
+        if rounding != PlacementRounding::default() {
+            hasher.write(&[rounding as u8]);
+        }
+        if let Some(sampler) = sampler {
+            let json = serde_json::to_vec(&sampler).expect("sampler serialization is infallible");
+            hasher.write(&(json.len() as u64).to_le_bytes());
+            hasher.write(&json);
+        }
+
+        Ok(Self(hasher.0))
+    }
+
+    /// Returns the raw 128-bit value.
+    pub fn as_u128(&self) -> u128 {
+        self.0
+    }
+}
+
+impl fmt::Display for RenderId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:032x}", self.0)
+    }
+}
+
+impl FromStr for RenderId {
+    type Err = std::num::ParseIntError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        u128::from_str_radix(s, 16).map(Self)
+    }
+}
+
+impl Serialize for RenderId {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for RenderId {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// Minimal FNV-1a 128-bit hasher.
+struct Fnv1a(u128);
+
+impl Default for Fnv1a {
+    fn default() -> Self {
+        Self(FNV_OFFSET)
+    }
+}
+
+impl Fnv1a {
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 ^= byte as u128;
+            self.0 = self.0.wrapping_mul(FNV_PRIME);
+        }
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layer::FolderColorTargetConfig;
+
+    fn base() -> IconImage {
+        IconImage::new_full_content(image::RgbaImage::new(4, 4), 1.0)
+    }
+
+    #[test]
+    fn id_is_stable_and_input_sensitive() {
+        let surface = SurfaceColor::new(255, 217, 112);
+        let profile = CustomizationProfile::new();
+        let id = RenderId::compute(&base(), &surface, &profile, None, None, PlacementRounding::Floor).unwrap();
+
+        assert_eq!(id, RenderId::compute(&base(), &surface, &profile, None, None, PlacementRounding::Floor).unwrap());
+        assert_ne!(id, RenderId::compute(&base(), &surface, &profile, Some(AccentColor::new(0, 0, 0)), None, PlacementRounding::Floor).unwrap());
+
+        let recolored = profile.clone().with_folder_color_target(FolderColorTargetConfig::new(1, 2, 3));
+        assert_ne!(id, RenderId::compute(&base(), &surface, &recolored, None, None, PlacementRounding::Floor).unwrap());
+
+        let mut edited = base();
+        edited.data_mut().put_pixel(0, 0, image::Rgba([1, 0, 0, 0]));
+        assert_ne!(id, RenderId::compute(&edited, &surface, &profile, None, None, PlacementRounding::Floor).unwrap());
+        assert_ne!(id, RenderId::compute(&base(), &surface, &profile, None, None, PlacementRounding::Round).unwrap());
+        let sampler = DominantColorSampler::new(crate::analysis::SamplingStrategy::Median);
+        assert_ne!(id, RenderId::compute(&base(), &surface, &profile, None, Some(sampler), PlacementRounding::Floor).unwrap());
+    }
+
+    /// Fails when rendered pixels change. If the change is intentional,
+    /// bump [`PIXEL_PIPELINE_VERSION`] and update the expected hash.
+    #[test]
+    fn pixel_output_matches_pipeline_version() {
+        use crate::{FolderIconBase, FolderIconCustomizer, IconSet};
+
+        let mut data = image::RgbaImage::new(16, 16);
+        for (x, y, pixel) in data.enumerate_pixels_mut() {
+            *pixel = image::Rgba([255, 217, 112, if x > 0 && y > 1 { 255 } else { 0 }]);
+        }
+        let mut icons = IconSet::new();
+        icons.add_image(IconImage::new_full_content(data, 1.0));
+
+        let mut customizer = FolderIconCustomizer::new(FolderIconBase::new(icons, SurfaceColor::new(255, 217, 112)));
+        customizer
+            .pipeline
+            .folder_color_target
+            .set_config(Some(FolderColorTargetConfig::new(33, 150, 243)));
+
+        let mut hasher = Fnv1a::default();
+        hasher.write(customizer.render(16).unwrap().data().as_raw());
+        assert_eq!(
+            (PIXEL_PIPELINE_VERSION, format!("{:032x}", hasher.0)),
+            (2, "0b25eb55b18f311dae7435c56a35d665".to_string()),
+            "rendered output changed: bump PIXEL_PIPELINE_VERSION and update this hash"
+        );
+    }
+
+    #[test]
+    fn id_serializes_as_hex_string() {
+        let id = RenderId::compute(&base(), &SurfaceColor::new(0, 0, 0), &CustomizationProfile::new(), None, None, PlacementRounding::Floor).unwrap();
+        let json = serde_json::to_string(&id).unwrap();
+        assert_eq!(json.len(), 34);
+        assert_eq!(serde_json::from_str::<RenderId>(&json).unwrap(), id);
+    }
+}
@@ -0,0 +1,141 @@
+//! Scale inference from file names and image metadata.
+//!
+//! Icon artwork marks its scale in two common ways: an `@2x`-style suffix
+//! on the file name (the Apple convention) or a resolution stored in the
+//! image (macOS writes 144 DPI for @2x PNGs). Both are read here so
+//! loaders can fill in [`IconImage::scale`](crate::IconImage::scale), and
+//! the resolution is written back when exporting PNGs.
+
+/// Resolution of a 1x image.
+const BASE_DPI: f32 = 72.0;
+
+/// Metres per inch, for PNG resolutions stored per metre.
+const METRES_PER_INCH: f32 = 0.0254;
+
+/// How far a resolution may stray from a whole multiple of 72 DPI.
+const DPI_TOLERANCE: f32 = 0.05;
+
+/// Returns the scale named by an `@<n>x` suffix, e.g. `folder@2x.png`.
+///
+/// The suffix must end the file name, before its extension; directories
+/// are ignored. Fractional scales (`@1.5x`) are accepted.
+pub(crate) fn from_file_name(name: &str) -> Option<f32> {
+    let file = name.rsplit(['/', '\\']).next()?;
+    let stem = file.rsplit_once('.').map_or(file, |(stem, _)| stem);
+    let (_, suffix) = stem.rsplit_once('@')?;
+    let scale: f32 = suffix.strip_suffix(['x', 'X'])?.parse().ok()?;
+    (scale.is_finite() && scale > 0.0).then_some(scale)
+}
+
+/// PNG file signature.
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+/// Returns the scale implied by a PNG's `pHYs` chunk.
+///
+/// Only resolutions close to a whole multiple of 72 DPI count, so the
+/// 96 DPI many Windows tools write doesn't turn into a 1.33x scale.
+/// Returns `None` for other formats, missing or non-metric chunks.
+pub(crate) fn from_png_metadata(bytes: &[u8]) -> Option<f32> {
+    let mut rest = bytes.strip_prefix(PNG_SIGNATURE)?;
+    // Chunks are length, type, data, CRC; pHYs must precede IDAT
+    while rest.len() >= 8 {
+        let len = u32::from_be_bytes(rest[..4].try_into().unwrap()) as usize;
+        let (kind, data) = (&rest[4..8], rest.get(8..8usize.checked_add(len)?)?);
+        match kind {
+            b"pHYs" if data.len() == 9 && data[8] == 1 => {
+                let per_metre = u32::from_be_bytes(data[..4].try_into().unwrap());
+                let scale = per_metre as f32 * METRES_PER_INCH / BASE_DPI;
+                let whole = scale.round();
+                return (whole >= 1.0 && (scale - whole).abs() <= DPI_TOLERANCE).then_some(whole);
+            }
+            b"IDAT" | b"IEND" => return None,
+            _ => rest = rest.get(12 + len..)?,
+        }
+    }
+    None
+}
+
+/// Inserts a `pHYs` chunk recording `scale` × 72 DPI into an encoded PNG
+/// that has none, right after its header.
+///
+/// Leaves `png` unchanged if it isn't a PNG or the scale isn't positive.
+pub(crate) fn with_png_resolution(mut png: Vec<u8>, scale: f32) -> Vec<u8> {
+    // Signature, then the 13-byte IHDR chunk with its length, type and CRC
+    const IHDR_END: usize = PNG_SIGNATURE.len() + 12 + 13;
+
+    if !png.starts_with(PNG_SIGNATURE) || png.len() < IHDR_END || !(scale.is_finite() && scale > 0.0) {
+        return png;
+    }
+    let per_metre = (scale * BASE_DPI / METRES_PER_INCH).round() as u32;
+    let mut chunk = Vec::with_capacity(21);
+    chunk.extend_from_slice(&9u32.to_be_bytes());
+    chunk.extend_from_slice(b"pHYs");
+    chunk.extend_from_slice(&per_metre.to_be_bytes());
+    chunk.extend_from_slice(&per_metre.to_be_bytes());
+    chunk.push(1);
+    let crc = crc32fast::hash(&chunk[4..]);
+    chunk.extend_from_slice(&crc.to_be_bytes());
+
+    png.splice(IHDR_END..IHDR_END, chunk);
+    png
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Builds a PNG header followed by a pHYs chunk (CRCs are not checked).
+    fn png_with_phys(per_metre: u32, unit: u8) -> Vec<u8> {
+        let mut png = b"\x89PNG\r\n\x1a\n".to_vec();
+        png.extend_from_slice(&13u32.to_be_bytes());
+        png.extend_from_slice(b"IHDR");
+        png.extend_from_slice(&[0; 13 + 4]);
+        png.extend_from_slice(&9u32.to_be_bytes());
+        png.extend_from_slice(b"pHYs");
+        png.extend_from_slice(&per_metre.to_be_bytes());
+        png.extend_from_slice(&per_metre.to_be_bytes());
+        png.extend_from_slice(&[unit, 0, 0, 0, 0]);
+        png
+    }
+
+    #[test]
+    fn file_name_suffixes() {
+        assert_eq!(from_file_name("icons/folder@2x.png"), Some(2.0));
+        assert_eq!(from_file_name(r"C:\art\badge_32x32@3X.png"), Some(3.0));
+        assert_eq!(from_file_name("folder@1.5x.png"), Some(1.5));
+        assert_eq!(from_file_name("folder@2x"), Some(2.0));
+        assert_eq!(from_file_name("user@example/folder.png"), None);
+        assert_eq!(from_file_name("folder_32x32.png"), None);
+        assert_eq!(from_file_name("folder@0x.png"), None);
+    }
+
+    #[test]
+    fn png_resolution() {
+        // 144 DPI ≈ 5669 px/m
+        assert_eq!(from_png_metadata(&png_with_phys(5669, 1)), Some(2.0));
+        assert_eq!(from_png_metadata(&png_with_phys(2835, 1)), Some(1.0));
+        // 96 DPI isn't a whole multiple of 72
+        assert_eq!(from_png_metadata(&png_with_phys(3780, 1)), None);
+        // Aspect-ratio-only chunks carry no resolution
+        assert_eq!(from_png_metadata(&png_with_phys(5669, 0)), None);
+        assert_eq!(from_png_metadata(b"GIF89a"), None);
+    }
+
+    #[test]
+    fn written_resolution_reads_back() {
+        let mut png = Vec::new();
+        image::RgbaImage::new(4, 4)
+            .write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+            .unwrap();
+
+        let tagged = with_png_resolution(png.clone(), 2.0);
+        assert_eq!(from_png_metadata(&tagged), Some(2.0));
+        // The chunk's CRC must hold for strict decoders
+        assert_eq!(image::load_from_memory(&tagged).unwrap().width(), 4);
+        assert_eq!(with_png_resolution(b"GIF89a".to_vec(), 2.0), b"GIF89a");
+    }
+}
@@ -0,0 +1,718 @@
+//! Desktop environment integration.
+//!
+//! Queries the host OS for user appearance settings (accent color,
+//! wallpaper, icon theme) so apps can theme icons to match the desktop by
+//! default, and loads the OS's own folder icon to customize. Only available
+//! when the `system` feature is enabled.
+//!
+//! Settings lookups shell out to the platform's own tools (`defaults` on
+//! macOS, `reg` on Windows, `gsettings` on GNOME) rather than linking
+//! native APIs. The folder icon is the exception: it comes from the native
+//! icon APIs in [`platform`](crate::platform) where there is one, which is
+//! why the feature pulls in `objc2` on macOS and `windows-sys` on Windows.
+//! Every lookup returns `None` on failure instead of erroring — callers are
+//! expected to fall back to their own defaults.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use folco_renderer::{FolderIconCustomizer, FolderIconBase, IconSet, SurfaceColor};
+//!
+//! let surface = SurfaceColor::new(255, 217, 112);
+//! let mut customizer = FolderIconCustomizer::new(FolderIconBase::new(IconSet::new(), surface));
+//! customizer.pipeline.set_accent_color(folco_renderer::system::accent_color());
+//! ```
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use image::imageops::FilterType;
+
+use crate::analysis::{extract_palette, sample_dominant_color, DominantColorSampling};
+use crate::icon::{FolderIconBase, IconSet, SurfaceColor};
+use crate::layer::{AccentColor, FolderColorTargetConfig};
+
+/// Edge length the wallpaper is downsampled to before palette extraction.
+const WALLPAPER_SAMPLE_SIZE: u32 = 64;
+
+/// Theme every freedesktop icon lookup falls back to.
+const FALLBACK_ICON_THEME: &str = "hicolor";
+
+/// Icon file extensions, in the lookup spec's order of preference.
+const ICON_EXTENSIONS: [&str; 3] = ["png", "svg", "xpm"];
+
+/// Sizes of the theme's `folder` icon gathered on Linux.
+#[cfg(all(unix, not(target_os = "macos")))]
+const THEME_FOLDER_SIZES: [u32; 7] = [16, 24, 32, 48, 64, 128, 256];
+
+/// Returns the user's OS accent color, if one can be determined.
+///
+/// - **macOS**: `AppleAccentColor` from the global defaults domain.
+///   The "multicolor" setting (no key) resolves to the system blue.
+/// - **Windows**: `AccentColor` from the DWM registry key.
+/// - **Linux**: `accent-color` from GNOME's interface settings (GNOME 47+).
+pub fn accent_color() -> Option<AccentColor> {
+    #[cfg(target_os = "macos")]
+    {
+        // A missing key is not an error — it means "multicolor" (blue)
+        let output = Command::new("defaults")
+            .args(["read", "-g", "AppleAccentColor"])
+            .output()
+            .ok()?;
+        let value = String::from_utf8_lossy(&output.stdout);
+        parse_macos_accent(output.status.success().then_some(value.as_ref()))
+    }
+
+    #[cfg(target_os = "windows")]
+    {
+        let value = command_stdout("reg", &["query", r"HKCU\Software\Microsoft\Windows\DWM", "/v", "AccentColor"])?;
+        parse_windows_accent(&value)
+    }
+
+    #[cfg(all(unix, not(target_os = "macos")))]
+    {
+        let value = command_stdout("gsettings", &["get", "org.gnome.desktop.interface", "accent-color"])?;
+        parse_gnome_accent(&value)
+    }
+
+    #[cfg(not(any(unix, target_os = "windows")))]
+    {
+        None
+    }
+}
+
+/// Suggests up to `n` color targets drawn from the current desktop wallpaper.
+///
+/// The wallpaper is downsampled and summarized with
+/// [`extract_palette`], most prominent color first. Each suggestion is a
+/// ready-to-use [`FolderColorTargetConfig`]. Returns `None` if the wallpaper
+/// cannot be located or decoded (e.g. a solid-color or dynamic background).
+pub fn wallpaper_palette(n: usize) -> Option<Vec<FolderColorTargetConfig>> {
+    let path = wallpaper_path()?;
+    let image = image::open(path).ok()?;
+    let sample = image
+        .resize(WALLPAPER_SAMPLE_SIZE, WALLPAPER_SAMPLE_SIZE, FilterType::Triangle)
+        .to_rgba8();
+
+    Some(
+        extract_palette(&sample, n)
+            .into_iter()
+            .map(|(r, g, b)| FolderColorTargetConfig::new(r, g, b))
+            .collect(),
+    )
+}
+
+/// Returns the path of the current desktop wallpaper image, if any.
+///
+/// - **macOS**: the picture of the current desktop via System Events.
+/// - **Windows**: `WallPaper` from the `Control Panel\Desktop` registry key.
+/// - **Linux**: GNOME's `picture-uri` background setting.
+pub fn wallpaper_path() -> Option<PathBuf> {
+    #[cfg(target_os = "macos")]
+    {
+        let value = command_stdout(
+            "osascript",
+            &["-e", "tell application \"System Events\" to get picture of current desktop"],
+        )?;
+        non_empty_path(value.trim())
+    }
+
+    #[cfg(target_os = "windows")]
+    {
+        let value = command_stdout("reg", &["query", r"HKCU\Control Panel\Desktop", "/v", "WallPaper"])?;
+        parse_windows_wallpaper(&value)
+    }
+
+    #[cfg(all(unix, not(target_os = "macos")))]
+    {
+        let value = command_stdout("gsettings", &["get", "org.gnome.desktop.background", "picture-uri"])?;
+        parse_gnome_wallpaper(&value)
+    }
+
+    #[cfg(not(any(unix, target_os = "windows")))]
+    {
+        None
+    }
+}
+
+/// Loads the OS's generic folder icon at every size it ships.
+///
+/// - **macOS**: the folder icon `NSWorkspace` hands out, including the @2x
+///   sizes; see [`load_system_folder_icon`](crate::platform::macos::load_system_folder_icon).
+/// - **Linux**: the `folder` icon of the user's theme (see
+///   [`lookup_theme_icon`]); scalable-only themes yield nothing, since only
+///   PNG sizes are gathered.
+/// - **Windows**: the shell's stock folder icon at each standard shell
+///   size; see [`load_folder_icon`](crate::platform::windows::load_folder_icon).
+///
+/// Images keep full-image content bounds; [`folder_icon_base`] detects
+/// them.
+pub fn folder_icon() -> Option<IconSet> {
+    #[cfg(target_os = "macos")]
+    {
+        crate::platform::macos::load_system_folder_icon()
+    }
+
+    #[cfg(all(unix, not(target_os = "macos")))]
+    {
+        let mut seen = HashSet::new();
+        let mut icons = IconSet::new();
+        for size in THEME_FOLDER_SIZES {
+            let Some(path) = lookup_theme_icon("folder", size, 1) else {
+                continue;
+            };
+            let is_png = path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("png"));
+            if !is_png || !seen.insert(path.clone()) {
+                continue;
+            }
+            // A broken file for one size shouldn't lose the others
+            let Ok(found) = IconSet::from_path(&path) else {
+                continue;
+            };
+            icons.images.extend(found.images);
+        }
+        icons.sort_canonical();
+        (!icons.is_empty()).then_some(icons)
+    }
+
+    #[cfg(target_os = "windows")]
+    {
+        crate::platform::windows::load_folder_icon()
+    }
+
+    #[cfg(not(any(unix, target_os = "windows")))]
+    {
+        None
+    }
+}
+
+/// Loads the OS's generic folder icon (see [`folder_icon`]) as a base
+/// ready to customize.
+///
+/// Content bounds are detected from each image's alpha, and the surface
+/// color is sampled from the largest image, so nothing needs hardcoding
+/// per platform.
+///
+/// ```no_run
+/// use folco_renderer::FolderIconCustomizer;
+///
+/// if let Some(base) = folco_renderer::system::folder_icon_base() {
+///     let mut customizer = FolderIconCustomizer::new(base);
+/// }
+/// ```
+pub fn folder_icon_base() -> Option<FolderIconBase> {
+    folder_base_from(folder_icon()?)
+}
+
+/// Builds a folder base from OS artwork, detecting content bounds and
+/// sampling the surface color.
+fn folder_base_from(icons: IconSet) -> Option<FolderIconBase> {
+    let icons = icons.with_detected_content_bounds();
+    let largest = icons.iter().max_by_key(|icon| icon.data().width() as u64 * icon.data().height() as u64)?;
+    let (r, g, b) = sample_dominant_color(largest, &DominantColorSampling::new())?;
+    Some(FolderIconBase::new(icons, SurfaceColor::new(r, g, b)))
+}
+
+/// Finds the file for a named icon in the user's icon theme.
+///
+/// Implements the [freedesktop icon theme lookup]: the current theme's
+/// `index.theme` is parsed, its directories are searched for an exact
+/// size and scale match, then for the closest size, before falling back
+/// through inherited themes, `hicolor`, and unthemed icons in
+/// `/usr/share/pixmaps`. `scale` is the integer UI scale (2 for HiDPI).
+///
+/// The current theme comes from GNOME's `icon-theme` setting or KDE's
+/// `kdeglobals`, defaulting to `hicolor`. The result may be a PNG, SVG or
+/// XPM file; PNGs can be loaded with [`IconSet::from_image_bytes`](crate::IconSet::from_image_bytes).
+///
+/// ```no_run
+/// let folder = folco_renderer::system::lookup_theme_icon("folder", 64, 2);
+/// ```
+///
+/// [freedesktop icon theme lookup]: https://specifications.freedesktop.org/icon-theme-spec/latest/
+pub fn lookup_theme_icon(name: &str, size: u32, scale: u32) -> Option<PathBuf> {
+    let theme = icon_theme_name().unwrap_or_else(|| FALLBACK_ICON_THEME.to_string());
+    IconLookup::new(icon_base_dirs()).find(&theme, name, size, scale.max(1))
+}
+
+/// Returns the name of the user's icon theme, if one is configured.
+fn icon_theme_name() -> Option<String> {
+    #[cfg(all(unix, not(target_os = "macos")))]
+    {
+        if let Some(value) = command_stdout("gsettings", &["get", "org.gnome.desktop.interface", "icon-theme"]) {
+            let name = value.trim().trim_matches('\'');
+            if !name.is_empty() {
+                return Some(name.to_string());
+            }
+        }
+        let config = std::env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".config")))?;
+        let kdeglobals = std::fs::read_to_string(config.join("kdeglobals")).ok()?;
+        parse_ini(&kdeglobals).into_iter().find_map(|(section, key, value)| {
+            (section == "Icons" && key == "Theme").then_some(value)
+        })
+    }
+
+    #[cfg(not(all(unix, not(target_os = "macos"))))]
+    {
+        None
+    }
+}
+
+/// Returns the icon search directories in spec order: `$HOME/.icons`,
+/// `$XDG_DATA_HOME/icons`, each `$XDG_DATA_DIRS/icons`, then `/usr/share/pixmaps`.
+fn icon_base_dirs() -> Vec<PathBuf> {
+    let home = std::env::var_os("HOME").map(PathBuf::from);
+    let data_home = std::env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .or_else(|| home.as_ref().map(|home| home.join(".local/share")));
+    let data_dirs = std::env::var("XDG_DATA_DIRS")
+        .ok()
+        .filter(|dirs| !dirs.is_empty())
+        .unwrap_or_else(|| "/usr/local/share:/usr/share".to_string());
+
+    home.map(|home| home.join(".icons"))
+        .into_iter()
+        .chain(data_home.map(|dir| dir.join("icons")))
+        .chain(data_dirs.split(':').map(|dir| Path::new(dir).join("icons")))
+        .chain([PathBuf::from("/usr/share/pixmaps")])
+        .collect()
+}
+
+/// Runs a command and returns its stdout if it exited successfully.
+fn command_stdout(program: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(program).args(args).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    String::from_utf8(output.stdout).ok()
+}
+
+// ============================================================================
+// Parsers
+// ============================================================================
+
+/// Parses the output of `defaults read -g AppleAccentColor`.
+///
+/// `None` input means the key is absent, which macOS treats as multicolor
+/// (rendered with the blue accent).
+#[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+pub(crate) fn parse_macos_accent(value: Option<&str>) -> Option<AccentColor> {
+    let index = match value {
+        None => return Some(AccentColor::new(0, 122, 255)),
+        Some(v) => v.trim().parse::<i32>().ok()?,
+    };
+
+    // System accent colors from NSColor.controlAccentColor
+    match index {
+        -1 => Some(AccentColor::new(140, 140, 140)), // Graphite
+        0 => Some(AccentColor::new(255, 59, 48)),    // Red
+        1 => Some(AccentColor::new(255, 149, 0)),    // Orange
+        2 => Some(AccentColor::new(255, 204, 0)),    // Yellow
+        3 => Some(AccentColor::new(40, 205, 65)),    // Green
+        4 => Some(AccentColor::new(0, 122, 255)),    // Blue
+        5 => Some(AccentColor::new(175, 82, 222)),   // Purple
+        6 => Some(AccentColor::new(255, 45, 85)),    // Pink
+        _ => None,
+    }
+}
+
+/// Parses the output of `reg query ... /v AccentColor`.
+///
+/// The value is a `REG_DWORD` laid out as `0xAABBGGRR`.
+#[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+pub(crate) fn parse_windows_accent(output: &str) -> Option<AccentColor> {
+    let line = output.lines().find(|l| l.contains("AccentColor"))?;
+    let hex = line.split_whitespace().last()?.trim_start_matches("0x");
+    let dword = u32::from_str_radix(hex, 16).ok()?;
+    Some(AccentColor::new(
+        (dword & 0xff) as u8,
+        ((dword >> 8) & 0xff) as u8,
+        ((dword >> 16) & 0xff) as u8,
+    ))
+}
+
+/// Parses the output of `gsettings get org.gnome.desktop.interface accent-color`.
+///
+/// GNOME stores a named color (e.g. `'blue'`); values map to the
+/// libadwaita accent palette.
+#[cfg_attr(not(all(unix, not(target_os = "macos"))), allow(dead_code))]
+pub(crate) fn parse_gnome_accent(output: &str) -> Option<AccentColor> {
+    match output.trim().trim_matches('\'') {
+        "blue" => Some(AccentColor::new(53, 132, 228)),
+        "teal" => Some(AccentColor::new(33, 144, 164)),
+        "green" => Some(AccentColor::new(58, 148, 74)),
+        "yellow" => Some(AccentColor::new(200, 136, 0)),
+        "orange" => Some(AccentColor::new(237, 91, 0)),
+        "red" => Some(AccentColor::new(230, 45, 66)),
+        "pink" => Some(AccentColor::new(213, 97, 153)),
+        "purple" => Some(AccentColor::new(145, 65, 172)),
+        "slate" => Some(AccentColor::new(111, 131, 150)),
+        _ => None,
+    }
+}
+
+/// Parses the output of `reg query ... /v WallPaper`.
+#[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+pub(crate) fn parse_windows_wallpaper(output: &str) -> Option<PathBuf> {
+    let line = output.lines().find(|l| l.contains("REG_SZ"))?;
+    let (_, value) = line.split_once("REG_SZ")?;
+    non_empty_path(value.trim())
+}
+
+/// Parses the output of `gsettings get org.gnome.desktop.background picture-uri`.
+///
+/// The value is a quoted `file://` URI; percent-encoded bytes are decoded.
+#[cfg_attr(not(all(unix, not(target_os = "macos"))), allow(dead_code))]
+pub(crate) fn parse_gnome_wallpaper(output: &str) -> Option<PathBuf> {
+    let uri = output.trim().trim_matches('\'');
+    let path = uri.strip_prefix("file://")?;
+
+    let mut bytes = Vec::with_capacity(path.len());
+    let mut rest = path.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        if byte == b'%'
+            && let Some(hex) = tail.get(..2)
+            && let Ok(decoded) = u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16)
+        {
+            bytes.push(decoded);
+            rest = &tail[2..];
+            continue;
+        }
+        bytes.push(byte);
+        rest = tail;
+    }
+
+    non_empty_path(&String::from_utf8(bytes).ok()?)
+}
+
+/// Parses an INI-style desktop file into `(section, key, value)` triples.
+///
+/// Comments and localized keys (`Name[de]=...`) are skipped.
+fn parse_ini(text: &str) -> Vec<(String, String, String)> {
+    let mut section = String::new();
+    let mut entries = Vec::new();
+    for line in text.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            section = name.to_string();
+        } else if let Some((key, value)) = line.split_once('=')
+            && !key.contains('[')
+        {
+            entries.push((section.clone(), key.trim().to_string(), value.trim().to_string()));
+        }
+    }
+    entries
+}
+
+/// Converts a non-empty string to a path.
+fn non_empty_path(value: &str) -> Option<PathBuf> {
+    (!value.is_empty()).then(|| PathBuf::from(value))
+}
+
+// ============================================================================
+// Icon theme lookup
+// ============================================================================
+
+/// How a theme directory's icons match requested sizes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum DirectoryType {
+    Fixed,
+    Scalable,
+    Threshold,
+}
+
+/// One size directory declared in an `index.theme`.
+#[derive(Debug, Clone, PartialEq)]
+struct ThemeDirectory {
+    path: String,
+    size: u32,
+    scale: u32,
+    kind: DirectoryType,
+    min_size: u32,
+    max_size: u32,
+    threshold: u32,
+}
+
+impl ThemeDirectory {
+    /// The spec's `DirectoryMatchesSize`.
+    fn matches(&self, size: u32, scale: u32) -> bool {
+        if self.scale != scale {
+            return false;
+        }
+        match self.kind {
+            DirectoryType::Fixed => self.size == size,
+            DirectoryType::Scalable => (self.min_size..=self.max_size).contains(&size),
+            DirectoryType::Threshold => {
+                (self.size.saturating_sub(self.threshold)..=self.size + self.threshold).contains(&size)
+            }
+        }
+    }
+
+    /// The spec's `DirectorySizeDistance`, in device pixels.
+    fn distance(&self, size: u32, scale: u32) -> u32 {
+        let requested = size * scale;
+        let (min, max) = match self.kind {
+            DirectoryType::Fixed => (self.size, self.size),
+            DirectoryType::Scalable => (self.min_size, self.max_size),
+            DirectoryType::Threshold => (
+                self.size.saturating_sub(self.threshold),
+                self.size + self.threshold,
+            ),
+        };
+        if requested < min * self.scale {
+            min * self.scale - requested
+        } else {
+            requested.saturating_sub(max * self.scale)
+        }
+    }
+}
+
+/// The parts of an `index.theme` file used for lookup.
+#[derive(Debug, Clone, Default, PartialEq)]
+struct ThemeIndex {
+    inherits: Vec<String>,
+    directories: Vec<ThemeDirectory>,
+}
+
+impl ThemeIndex {
+    fn parse(text: &str) -> Self {
+        let entries = parse_ini(text);
+        let get = |section: &str, key: &str| {
+            entries
+                .iter()
+                .find(|(s, k, _)| s == section && k == key)
+                .map(|(_, _, v)| v.as_str())
+        };
+        let list = |key: &str| {
+            get("Icon Theme", key)
+                .into_iter()
+                .flat_map(|value| value.split(','))
+                .map(str::trim)
+                .filter(|item| !item.is_empty())
+                .map(str::to_string)
+        };
+        let number = |section: &str, key: &str| get(section, key).and_then(|v| v.parse::<u32>().ok());
+
+        let directories = list("Directories")
+            .chain(list("ScaledDirectories"))
+            .filter_map(|path| {
+                let size = number(&path, "Size")?;
+                let kind = match get(&path, "Type") {
+                    Some("Fixed") => DirectoryType::Fixed,
+                    Some("Scalable") => DirectoryType::Scalable,
+                    _ => DirectoryType::Threshold,
+                };
+                Some(ThemeDirectory {
+                    size,
+                    scale: number(&path, "Scale").unwrap_or(1),
+                    kind,
+                    min_size: number(&path, "MinSize").unwrap_or(size),
+                    max_size: number(&path, "MaxSize").unwrap_or(size),
+                    threshold: number(&path, "Threshold").unwrap_or(2),
+                    path,
+                })
+            })
+            .collect();
+
+        Self {
+            inherits: list("Inherits").collect(),
+            directories,
+        }
+    }
+}
+
+/// Freedesktop icon lookup over a fixed list of base directories.
+struct IconLookup {
+    base_dirs: Vec<PathBuf>,
+}
+
+impl IconLookup {
+    fn new(base_dirs: Vec<PathBuf>) -> Self {
+        Self { base_dirs }
+    }
+
+    /// The spec's `FindIcon`: the theme and its ancestors, then `hicolor`,
+    /// then unthemed icons directly in the base directories.
+    fn find(&self, theme: &str, name: &str, size: u32, scale: u32) -> Option<PathBuf> {
+        let mut visited = HashSet::new();
+        self.find_in_tree(theme, name, size, scale, &mut visited)
+            .or_else(|| self.find_in_tree(FALLBACK_ICON_THEME, name, size, scale, &mut visited))
+            .or_else(|| {
+                self.base_dirs
+                    .iter()
+                    .find_map(|dir| existing_icon(dir, name))
+            })
+    }
+
+    /// Searches `theme`, then its parents depth-first, skipping themes
+    /// already searched so inheritance cycles terminate.
+    fn find_in_tree(
+        &self,
+        theme: &str,
+        name: &str,
+        size: u32,
+        scale: u32,
+        visited: &mut HashSet<String>,
+    ) -> Option<PathBuf> {
+        if !visited.insert(theme.to_string()) {
+            return None;
+        }
+        let index = self.theme_index(theme)?;
+        if let Some(path) = self.lookup(theme, &index, name, size, scale) {
+            return Some(path);
+        }
+        index
+            .inherits
+            .iter()
+            .find_map(|parent| self.find_in_tree(parent, name, size, scale, visited))
+    }
+
+    /// Reads the first `index.theme` found for `theme`.
+    fn theme_index(&self, theme: &str) -> Option<ThemeIndex> {
+        self.base_dirs
+            .iter()
+            .find_map(|dir| std::fs::read_to_string(dir.join(theme).join("index.theme")).ok())
+            .map(|text| ThemeIndex::parse(&text))
+    }
+
+    /// The spec's `LookupIcon`: an exact match, else the closest size.
+    fn lookup(&self, theme: &str, index: &ThemeIndex, name: &str, size: u32, scale: u32) -> Option<PathBuf> {
+        let candidates = || {
+            index.directories.iter().flat_map(move |directory| {
+                self.base_dirs.iter().filter_map(move |dir| {
+                    existing_icon(&dir.join(theme).join(&directory.path), name).map(|path| (directory, path))
+                })
+            })
+        };
+
+        candidates()
+            .find(|(directory, _)| directory.matches(size, scale))
+            .or_else(|| candidates().min_by_key(|(directory, _)| directory.distance(size, scale)))
+            .map(|(_, path)| path)
+    }
+}
+
+/// Returns the first existing `dir/name.{png,svg,xpm}`.
+fn existing_icon(dir: &Path, name: &str) -> Option<PathBuf> {
+    ICON_EXTENSIONS
+        .iter()
+        .map(|ext| dir.join(format!("{name}.{ext}")))
+        .find(|path| path.is_file())
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn folder_base_detects_bounds_and_surface() {
+        let folder = |size: u32| {
+            let data = image::RgbaImage::from_fn(size, size, |_, y| {
+                if y < size / 4 { image::Rgba([0, 0, 0, 0]) } else { image::Rgba([84, 180, 240, 255]) }
+            });
+            crate::IconImage::new_full_content(data, 1.0)
+        };
+        let base = folder_base_from(IconSet::from_images(vec![folder(16), folder(64)])).unwrap();
+        assert_eq!(base.surface_color, SurfaceColor::new(84, 180, 240));
+        assert_eq!(base.icons[1].content_bounds, crate::RectPx::new(0, 16, 64, 48));
+
+        assert!(folder_base_from(IconSet::new()).is_none());
+    }
+
+    #[test]
+    fn macos_accent_values() {
+        assert_eq!(parse_macos_accent(None), Some(AccentColor::new(0, 122, 255)));
+        assert_eq!(parse_macos_accent(Some("0\n")), Some(AccentColor::new(255, 59, 48)));
+        assert_eq!(parse_macos_accent(Some("-1")), Some(AccentColor::new(140, 140, 140)));
+        assert_eq!(parse_macos_accent(Some("42")), None);
+    }
+
+    #[test]
+    fn windows_accent_is_abgr() {
+        let output = "\r\nHKEY_CURRENT_USER\\Software\\Microsoft\\Windows\\DWM\r\n    AccentColor    REG_DWORD    0xffd77800\r\n\r\n";
+        assert_eq!(parse_windows_accent(output), Some(AccentColor::new(0, 120, 215)));
+        assert_eq!(parse_windows_accent("ERROR: not found"), None);
+    }
+
+    #[test]
+    fn windows_wallpaper_path() {
+        let output = "\r\nHKEY_CURRENT_USER\\Control Panel\\Desktop\r\n    WallPaper    REG_SZ    C:\\Users\\me\\Pictures\\lake view.jpg\r\n";
+        assert_eq!(
+            parse_windows_wallpaper(output),
+            Some(PathBuf::from("C:\\Users\\me\\Pictures\\lake view.jpg"))
+        );
+
+        let empty = "    WallPaper    REG_SZ    \r\n";
+        assert_eq!(parse_windows_wallpaper(empty), None);
+    }
+
+    #[test]
+    fn gnome_wallpaper_uri_is_decoded() {
+        assert_eq!(
+            parse_gnome_wallpaper("'file:///home/me/Pictures/lake%20view.jpg'\n"),
+            Some(PathBuf::from("/home/me/Pictures/lake view.jpg"))
+        );
+        assert_eq!(parse_gnome_wallpaper("''"), None);
+    }
+
+    #[test]
+    fn gnome_accent_names() {
+        assert_eq!(parse_gnome_accent("'blue'\n"), Some(AccentColor::new(53, 132, 228)));
+        assert_eq!(parse_gnome_accent("'chartreuse'"), None);
+    }
+
+    #[test]
+    fn theme_index_directories() {
+        let index = ThemeIndex::parse(
+            "[Icon Theme]\nName=Test\nName[de]=Probe\nInherits=Adwaita, hicolor\nDirectories=16x16/places,scalable/places\n\
+             ScaledDirectories=16x16@2/places\n\n# comment\n[16x16/places]\nSize=16\nType=Fixed\n\n\
+             [16x16@2/places]\nSize=16\nScale=2\n\n[scalable/places]\nSize=64\nType=Scalable\nMinSize=8\nMaxSize=512\n",
+        );
+        assert_eq!(index.inherits, ["Adwaita", "hicolor"]);
+        assert_eq!(index.directories.len(), 3);
+
+        let [fixed, scalable, scaled] = &index.directories[..] else { panic!() };
+        assert!(fixed.matches(16, 1) && !fixed.matches(17, 1) && !fixed.matches(16, 2));
+        assert_eq!(fixed.distance(24, 1), 8);
+        assert!(scalable.matches(300, 1));
+        assert_eq!((scaled.kind, scaled.scale), (DirectoryType::Threshold, 2));
+        assert!(scaled.matches(18, 2) && !scaled.matches(19, 2));
+    }
+
+    #[test]
+    fn icon_lookup_follows_inheritance_and_sizes() {
+        let root = std::env::temp_dir().join(format!("folco-icons-{}", std::process::id()));
+        let write = |path: &str, contents: &str| {
+            let path = root.join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, contents).unwrap();
+        };
+        write("child/index.theme", "[Icon Theme]\nInherits=parent\nDirectories=\n");
+        write(
+            "parent/index.theme",
+            "[Icon Theme]\nInherits=child\nDirectories=16/places,48/places\n[16/places]\nSize=16\nType=Fixed\n[48/places]\nSize=48\nType=Fixed\n",
+        );
+        write("parent/16/places/folder.png", "");
+        write("parent/48/places/folder.svg", "");
+        write("pixmaps/legacy.xpm", "");
+
+        let lookup = IconLookup::new(vec![root.clone(), root.join("pixmaps")]);
+        let found = |name: &str, size: u32| lookup.find("child", name, size, 1);
+        let relative = |path: Option<PathBuf>| path.map(|p| p.strip_prefix(&root).unwrap().to_path_buf());
+
+        assert_eq!(relative(found("folder", 16)), Some(PathBuf::from("parent/16/places/folder.png")));
+        assert_eq!(relative(found("folder", 40)), Some(PathBuf::from("parent/48/places/folder.svg")));
+        assert_eq!(relative(found("legacy", 16)), Some(PathBuf::from("pixmaps/legacy.xpm")));
+        assert_eq!(found("missing", 16), None);
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}